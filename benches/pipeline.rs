@@ -0,0 +1,149 @@
+use arbitrage_engine::bench::{self, OpportunitySet};
+use arbitrage_engine::protocol::{self, wire};
+use arbitrage_engine::{shared_memory, ArbitrageResponse};
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+use serde_json::Value;
+use std::sync::Arc;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+// 請求解析：線路解碼加版本升級與消息分類
+fn parse(c: &mut Criterion) {
+    let json = bench::execute_payload(1_000.0);
+    let value: Value = serde_json::from_slice(&json).unwrap();
+    let msgpack = wire::encode(wire::Encoding::Msgpack, &value);
+    let mut group = c.benchmark_group("parse");
+    group.bench_function("json", |b| {
+        b.iter(|| protocol::parse(wire::decode::<Value>(wire::Encoding::Json, &json).unwrap()).unwrap())
+    });
+    group.bench_function("msgpack", |b| {
+        let payload = &msgpack[wire::HEADER_LEN..];
+        b.iter(|| protocol::parse(wire::decode::<Value>(wire::Encoding::Msgpack, payload).unwrap()).unwrap())
+    });
+    group.finish();
+}
+
+// 解析到分派：與客戶端連接相同的處理路徑，響應寫入複用的緩衝
+fn dispatch(c: &mut Criterion) {
+    let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap();
+    let _runtime = runtime.enter();
+    let (engine, path) = bench::simulated_engine(1).unwrap();
+    let execute = bench::execute_payload(1_000.0);
+    let command = br#"{"command":"get_latency"}"#;
+    let mut output = Vec::with_capacity(4096);
+    let mut group = c.benchmark_group("dispatch");
+    group.bench_function("command", |b| {
+        b.iter(|| runtime.block_on(bench::dispatch(&engine, wire::Encoding::Json, command, &mut output)))
+    });
+    group.bench_function("execute", |b| {
+        b.iter(|| runtime.block_on(bench::dispatch(&engine, wire::Encoding::Json, &execute, &mut output)))
+    });
+    group.finish();
+    let _ = std::fs::remove_file(&path);
+    let _ = std::fs::remove_file(path.with_extension("journal"));
+}
+
+// 同一指令經回環 TCP、Unix 域套接字與共享內存的往返延遲
+fn transport(c: &mut Criterion) {
+    let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap();
+    let _runtime = runtime.enter();
+    let (engine, path) = bench::simulated_engine(3).unwrap();
+    let engine = Arc::new(engine);
+    let command = br#"{"command":"get_latency"}"#;
+    let mut buffer = vec![0; 64 * 1024];
+    let mut group = c.benchmark_group("transport");
+
+    let listener = runtime.block_on(tokio::net::TcpListener::bind("127.0.0.1:0")).unwrap();
+    let address = listener.local_addr().unwrap();
+    let served = Arc::clone(&engine);
+    runtime.spawn(async move {
+        let (socket, _) = listener.accept().await.unwrap();
+        socket.set_nodelay(true).unwrap();
+        bench::serve_connection(&served, socket).await
+    });
+    let mut tcp = runtime.block_on(tokio::net::TcpStream::connect(address)).unwrap();
+    tcp.set_nodelay(true).unwrap();
+    group.bench_function("tcp", |b| b.iter(|| runtime.block_on(round_trip(&mut tcp, command, &mut buffer))));
+
+    #[cfg(unix)]
+    {
+        let socket_path = std::env::temp_dir().join(format!("arb-bench-{}.sock", std::process::id()));
+        let _ = std::fs::remove_file(&socket_path);
+        let listener = tokio::net::UnixListener::bind(&socket_path).unwrap();
+        let served = Arc::clone(&engine);
+        runtime.spawn(async move {
+            let (socket, _) = listener.accept().await.unwrap();
+            bench::serve_connection(&served, socket).await
+        });
+        let mut unix = runtime.block_on(tokio::net::UnixStream::connect(&socket_path)).unwrap();
+        group.bench_function("unix", |b| b.iter(|| runtime.block_on(round_trip(&mut unix, command, &mut buffer))));
+        let _ = std::fs::remove_file(&socket_path);
+    }
+
+    let ring_path = std::env::temp_dir().join(format!("arb-bench-{}.ring", std::process::id()));
+    let ring_path = ring_path.to_string_lossy().into_owned();
+    let server = bench::serve_shared_memory(Arc::clone(&engine), &ring_path).unwrap();
+    let mut client = shared_memory::Client::open(&ring_path).unwrap();
+    let message: Value = serde_json::from_slice(command).unwrap();
+    let mut id = 0;
+    group.bench_function("shared_memory", |b| {
+        b.iter(|| {
+            id += 1;
+            client.submit(id, &message).unwrap();
+            // 響應由當前線程運行時上的任務寫回，輪詢間讓出運行時
+            runtime.block_on(async {
+                loop {
+                    if let Some(response) = client.try_recv().unwrap() {
+                        return response;
+                    }
+                    tokio::task::yield_now().await;
+                }
+            })
+        })
+    });
+    drop(server);
+    group.finish();
+    let _ = std::fs::remove_file(&path);
+    let _ = std::fs::remove_file(path.with_extension("journal"));
+}
+
+// 響應小於緩衝，一次讀取即為完整響應
+async fn round_trip<S: AsyncRead + AsyncWrite + Unpin>(stream: &mut S, message: &[u8], buffer: &mut [u8]) -> usize {
+    stream.write_all(message).await.unwrap();
+    stream.read(buffer).await.unwrap()
+}
+
+// 機會評估吞吐：每輪對全部交易對的交易所組合兩兩比較
+fn opportunities(c: &mut Criterion) {
+    let mut group = c.benchmark_group("opportunities");
+    for symbols in [50, 500] {
+        let set = OpportunitySet::generate(symbols, 7);
+        group.throughput(Throughput::Elements(set.pairs() as u64));
+        group.bench_function(format!("rank/{}", symbols), |b| b.iter(|| set.rank()));
+    }
+    group.finish();
+}
+
+// 響應編碼：以一次模擬執行的真實響應為樣本
+fn serialization(c: &mut Criterion) {
+    let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap();
+    let _runtime = runtime.enter();
+    let (engine, path) = bench::simulated_engine(2).unwrap();
+    let mut output = Vec::new();
+    runtime.block_on(bench::dispatch(&engine, wire::Encoding::Json, &bench::execute_payload(1_000.0), &mut output));
+    let response: ArbitrageResponse = serde_json::from_slice(&output).unwrap();
+    let mut group = c.benchmark_group("serialization");
+    for encoding in [wire::Encoding::Json, wire::Encoding::Msgpack] {
+        group.bench_function(format!("encode_into/{:?}", encoding), |b| {
+            b.iter(|| wire::encode_into(encoding, &response, &mut output))
+        });
+        group.bench_function(format!("encode/{:?}", encoding), |b| {
+            b.iter(|| wire::encode(encoding, &response))
+        });
+    }
+    group.finish();
+    let _ = std::fs::remove_file(&path);
+    let _ = std::fs::remove_file(path.with_extension("journal"));
+}
+
+criterion_group!(benches, parse, dispatch, transport, opportunities, serialization);
+criterion_main!(benches);
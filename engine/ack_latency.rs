@@ -0,0 +1,93 @@
+use super::config::{self, AckLatencyConfig};
+use dashmap::DashMap;
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct VenueLatency {
+    pub samples: u64,
+    pub last_ms: f64,
+    pub ewma_ms: f64,
+    // 機會評估使用的確認延遲：樣本不足時為 default_ack_ms
+    pub expected_ms: f64,
+}
+
+// 按兩邊交易所的確認延遲估算的成交耗時與屆時剩餘的收益比例
+#[derive(Debug, Clone, Copy)]
+pub struct EdgeDecay {
+    pub expected_fill_ms: f64,
+    pub survival: f64,
+}
+
+/// 各交易所下單確認延遲模型：每筆吃單確認後更新指數加權平均，
+/// 掃描器據此估算機會在兩條腿依次成交時還剩多少收益，過濾掉捕獲不到的邊際機會。
+pub struct AckLatencyModel {
+    config: AckLatencyConfig,
+    // 交易所（子賬戶併入主賬戶）-> 延遲統計；各腿下單確認後並發寫入
+    venues: DashMap<String, VenueLatency>,
+}
+
+impl AckLatencyModel {
+    pub fn new(config: AckLatencyConfig) -> Self {
+        Self {
+            config,
+            venues: DashMap::new(),
+        }
+    }
+
+    pub fn record(&self, exchange: &str, micros: i64) {
+        let ms = micros.max(0) as f64 / 1_000.0;
+        let mut venue = self.venues.entry(config::venue(exchange).to_string()).or_default();
+        venue.ewma_ms = if venue.samples == 0 { ms } else { venue.ewma_ms + self.config.ewma_alpha * (ms - venue.ewma_ms) };
+        venue.samples += 1;
+        venue.last_ms = ms;
+        venue.expected_ms = if venue.samples >= self.config.min_samples { venue.ewma_ms } else { self.config.default_ack_ms };
+    }
+
+    pub fn expected_ms(&self, exchange: &str) -> f64 {
+        self.venues
+            .get(config::venue(exchange))
+            .map_or(self.config.default_ack_ms, |venue| venue.expected_ms)
+    }
+
+    // 兩條腿依次下單，成交耗時為兩邊確認延遲之和
+    pub fn decay(&self, short_exchange: &str, long_exchange: &str) -> EdgeDecay {
+        let expected_fill_ms = self.expected_ms(short_exchange) + self.expected_ms(long_exchange);
+        EdgeDecay {
+            expected_fill_ms,
+            survival: (-expected_fill_ms / self.config.edge_decay_ms).exp(),
+        }
+    }
+
+    pub fn snapshot(&self) -> BTreeMap<String, VenueLatency> {
+        self.venues.iter().map(|item| (item.key().clone(), item.value().clone())).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::deterministic_sim::{engine, run};
+
+    #[tokio::test]
+    async fn ack_latency_model_decays_edge_by_expected_fill_time() {
+        let simulation = config::SimulatedExchangeConfig {
+            order_latency_us: 50_000,
+            reject_rate: 0.0,
+            ..Default::default()
+        };
+        let (engine, path) = engine("ack_latency", 3, simulation);
+        // 樣本不足時使用 default_ack_ms
+        let decay = engine.ack_latency.decay("binance", "bybit");
+        assert_eq!(decay.expected_fill_ms, 40.0);
+
+        run(&engine, 5).await;
+        let venues = engine.ack_latency.snapshot();
+        assert_eq!(venues["binance"].samples, 5);
+        assert!((venues["binance"].expected_ms - 50.0).abs() < 1e-9);
+        let decay = engine.ack_latency.decay("binance", "bybit");
+        assert!((decay.expected_fill_ms - 100.0).abs() < 1e-9);
+        assert!((decay.survival - (-100.0f64 / 500.0).exp()).abs() < 1e-12);
+        let _ = std::fs::remove_file(path);
+    }
+}
@@ -0,0 +1,300 @@
+use super::config::AdminApiConfig;
+use super::session::Health;
+use super::{health, storage, CommandResponse, EngineCommand, ExecutionEngine};
+use axum::extract::{Query, Request, State};
+use axum::http::{header, StatusCode};
+use axum::middleware::{self, Next};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use serde::Deserialize;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use tracing::{error, info, warn};
+
+#[derive(Clone)]
+struct ApiState {
+    engine: Arc<ExecutionEngine>,
+    token: Arc<String>,
+    max_market_data_age_ms: i64,
+}
+
+#[derive(Debug, Deserialize)]
+struct PositionsQuery {
+    at_ms: Option<i64>,
+    at_sequence: Option<u64>,
+}
+
+#[derive(Debug, Deserialize)]
+struct KillSwitchBody {
+    engaged: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct ResumeBody {
+    strategy_id: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ReconcileBody {
+    auto_correct: Option<bool>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RebalanceBody {
+    #[serde(default)]
+    dry_run: bool,
+}
+
+pub fn spawn(engine: Arc<ExecutionEngine>, config: AdminApiConfig) {
+    if !config.enabled {
+        info!("HTTP 管理接口未啟用");
+        return;
+    }
+    // 未配置 token 時不啟動，避免無鑒權地暴露管理接口
+    let token = match engine.env.var(&config.token_env) {
+        Some(token) if !token.is_empty() => token,
+        _ => {
+            error!(token_env = %config.token_env, "未設置管理接口 token，HTTP 管理接口不啟動");
+            return;
+        }
+    };
+    let state = ApiState {
+        engine,
+        token: Arc::new(token),
+        max_market_data_age_ms: config.max_market_data_age_secs as i64 * 1_000,
+    };
+    let protected = Router::new()
+        .route("/config", get(config_view))
+        .route("/config/reload", post(reload_config))
+        .route("/positions", get(positions))
+        .route("/executions/open", get(open_executions))
+        .route("/history", get(history))
+        .route("/kill-switch", get(kill_switch).post(set_kill_switch))
+        .route("/halts", get(halts).post(resume_trading))
+        .route("/reconciliation", get(reconciliation).post(reconcile))
+        .route("/rebalance", get(rebalance_status).post(rebalance))
+        .route("/metrics", get(metrics))
+        .route_layer(middleware::from_fn_with_state(state.clone(), require_token));
+    let app = Router::new()
+        .route("/health", get(health))
+        .route("/healthz", get(healthz))
+        .route("/readyz", get(readyz))
+        .merge(protected)
+        .with_state(state);
+    tokio::spawn(async move {
+        let listener = match tokio::net::TcpListener::bind(&config.listen_address).await {
+            Ok(listener) => listener,
+            Err(e) => {
+                error!(error = %e, address = %config.listen_address, "HTTP 管理接口綁定失敗");
+                return;
+            }
+        };
+        info!(address = %config.listen_address, "HTTP 管理接口已啟動");
+        if let Err(e) = axum::serve(listener, app).await {
+            error!(error = %e, "HTTP 管理接口退出");
+        }
+    });
+}
+
+// 逐字節比較全部長度，不因提前返回洩露匹配前綴
+fn token_matches(expected: &str, provided: &str) -> bool {
+    expected.len() == provided.len()
+        && expected.bytes().zip(provided.bytes()).fold(0u8, |acc, (a, b)| acc | (a ^ b)) == 0
+}
+
+async fn require_token(State(state): State<ApiState>, request: Request, next: Next) -> Response {
+    let provided = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+    match provided {
+        Some(provided) if token_matches(&state.token, provided) => next.run(request).await,
+        _ => {
+            warn!(path = %request.uri().path(), "管理接口鑒權失敗");
+            (StatusCode::UNAUTHORIZED, Json(CommandResponse::error("未授權"))).into_response()
+        }
+    }
+}
+
+fn respond(response: CommandResponse) -> Response {
+    let status = if response.status == "success" { StatusCode::OK } else { StatusCode::BAD_REQUEST };
+    (status, Json(response)).into_response()
+}
+
+// 所有交易所連接就緒、未緊急停止、未觸發當日限額且未在關閉時為 ok，否則為 degraded
+async fn health(State(state): State<ApiState>) -> Response {
+    let engine = &state.engine;
+    let sessions = engine.sessions.snapshot();
+    let kill_switch = engine.kill_switch.load(Ordering::Relaxed);
+    let shutting_down = engine.shutting_down.load(Ordering::Relaxed);
+    let connected = sessions.values().all(|session| session.health == Health::Connected);
+    let halts = engine.risk.halts(engine.env.now_ms());
+    Json(serde_json::json!({
+        "status": if connected && !kill_switch && halts.is_empty() && !shutting_down { "ok" } else { "degraded" },
+        "kill_switch": kill_switch,
+        "halts": halts,
+        "shutting_down": shutting_down,
+        "open_executions": engine.open_executions.len(),
+        "sessions": sessions
+            .iter()
+            .map(|(exchange, session)| (exchange.clone(), serde_json::json!(session.health)))
+            .collect::<serde_json::Map<_, _>>(),
+    }))
+    .into_response()
+}
+
+// 存活探針：管理接口能響應即返回 200，附帶完整的就緒報告
+async fn healthz(State(state): State<ApiState>) -> Response {
+    Json(health::report(&state.engine, state.max_market_data_age_ms)).into_response()
+}
+
+// 就緒探針：未就緒時返回 503，負載均衡據此摘除流量
+async fn readyz(State(state): State<ApiState>) -> Response {
+    let report = health::report(&state.engine, state.max_market_data_age_ms);
+    let status = if report.ready { StatusCode::OK } else { StatusCode::SERVICE_UNAVAILABLE };
+    (status, Json(report)).into_response()
+}
+
+async fn config_view(State(state): State<ApiState>) -> Response {
+    respond(state.engine.handle_command(EngineCommand::GetConfig).await)
+}
+
+async fn reload_config(State(state): State<ApiState>) -> Response {
+    respond(state.engine.handle_command(EngineCommand::ReloadConfig).await)
+}
+
+async fn positions(State(state): State<ApiState>, Query(query): Query<PositionsQuery>) -> Response {
+    respond(
+        state
+            .engine
+            .handle_command(EngineCommand::GetPositions {
+                at_ms: query.at_ms,
+                at_sequence: query.at_sequence,
+            })
+            .await,
+    )
+}
+
+async fn open_executions(State(state): State<ApiState>) -> Response {
+    respond(state.engine.handle_command(EngineCommand::GetOpenExecutions).await)
+}
+
+async fn history(State(state): State<ApiState>, Query(filter): Query<storage::HistoryFilter>) -> Response {
+    respond(state.engine.handle_command(EngineCommand::GetHistory(filter)).await)
+}
+
+async fn kill_switch(State(state): State<ApiState>) -> Response {
+    Json(serde_json::json!({ "engaged": state.engine.kill_switch.load(Ordering::Relaxed) })).into_response()
+}
+
+async fn set_kill_switch(State(state): State<ApiState>, Json(body): Json<KillSwitchBody>) -> Response {
+    respond(state.engine.handle_command(EngineCommand::SetKillSwitch { engaged: body.engaged }).await)
+}
+
+async fn halts(State(state): State<ApiState>) -> Response {
+    respond(state.engine.handle_command(EngineCommand::GetTradingHalts).await)
+}
+
+async fn resume_trading(State(state): State<ApiState>, Json(body): Json<ResumeBody>) -> Response {
+    respond(state.engine.handle_command(EngineCommand::ResumeTrading { strategy_id: body.strategy_id }).await)
+}
+
+async fn reconciliation(State(state): State<ApiState>) -> Response {
+    respond(state.engine.handle_command(EngineCommand::GetReconciliation).await)
+}
+
+async fn reconcile(State(state): State<ApiState>, Json(body): Json<ReconcileBody>) -> Response {
+    respond(state.engine.handle_command(EngineCommand::Reconcile { auto_correct: body.auto_correct }).await)
+}
+
+async fn rebalance_status(State(state): State<ApiState>) -> Response {
+    respond(state.engine.handle_command(EngineCommand::GetRebalance).await)
+}
+
+async fn rebalance(State(state): State<ApiState>, Json(body): Json<RebalanceBody>) -> Response {
+    respond(state.engine.handle_command(EngineCommand::Rebalance { dry_run: body.dry_run }).await)
+}
+
+async fn metrics(State(state): State<ApiState>) -> Response {
+    (
+        [(header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+        state.engine.latency.prometheus() + &state.engine.maker.prometheus(),
+    )
+        .into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::deterministic_sim::request;
+    use crate::mock_exchange::Scenario;
+    use crate::mock_exchange_e2e::engine_with;
+    use crate::{config, Environment};
+    use std::time::Duration;
+
+    // 管理接口：健康檢查無需鑒權，其餘端點要求 Bearer token；緊急停止經 HTTP 切換後引擎立即生效
+    #[tokio::test]
+    async fn admin_api_requires_token_and_toggles_kill_switch() {
+        let env = Environment::system().with_vars([("ADMIN_E2E_TOKEN", "s3cret")]);
+        let (_mock, engine, path) = engine_with("admin", Scenario::default(), env).await;
+        let engine = Arc::new(engine);
+        let port = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+        spawn(
+            Arc::clone(&engine),
+            config::AdminApiConfig {
+                enabled: true,
+                listen_address: format!("127.0.0.1:{}", port),
+                token_env: "ADMIN_E2E_TOKEN".to_string(),
+                ..Default::default()
+            },
+        );
+        let base = format!("http://127.0.0.1:{}", port);
+        let client = reqwest::Client::new();
+        let mut health = None;
+        for _ in 0..250 {
+            if let Ok(response) = client.get(format!("{}/health", base)).send().await {
+                health = Some(response.json::<serde_json::Value>().await.unwrap());
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        let health = health.expect("管理接口未啟動");
+        assert_eq!(health["status"], "degraded", "會話尚未連接");
+        assert_eq!(health["kill_switch"], false);
+
+        let get = |path: &str, token: Option<&str>| {
+            let request = client.get(format!("{}{}", base, path));
+            match token {
+                Some(token) => request.bearer_auth(token),
+                None => request,
+            }
+            .send()
+        };
+        assert_eq!(get("/positions", None).await.unwrap().status(), 401);
+        assert_eq!(get("/positions", Some("s3cre")).await.unwrap().status(), 401);
+        let positions = get("/positions", Some("s3cret")).await.unwrap();
+        assert_eq!(positions.status(), 200);
+        assert_eq!(positions.json::<serde_json::Value>().await.unwrap()["status"], "success");
+        let config = get("/config", Some("s3cret")).await.unwrap().json::<serde_json::Value>().await.unwrap();
+        assert!(config["data"]["loaded"]["exchanges"]["binance"].is_object());
+        assert_eq!(get("/executions/open", Some("s3cret")).await.unwrap().status(), 200);
+        assert_eq!(get("/metrics", Some("s3cret")).await.unwrap().status(), 200);
+
+        let response = client
+            .post(format!("{}/kill-switch", base))
+            .bearer_auth("s3cret")
+            .json(&serde_json::json!({"engaged": true}))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 200);
+        assert!(engine.kill_switch.load(std::sync::atomic::Ordering::Relaxed));
+        let state = get("/kill-switch", Some("s3cret")).await.unwrap().json::<serde_json::Value>().await.unwrap();
+        assert_eq!(state["engaged"], true);
+        let response = engine.execute_funding_rate_arbitrage(request(10.0)).await;
+        assert_eq!(response.status, "error");
+        let _ = std::fs::remove_file(path);
+    }
+}
@@ -0,0 +1,220 @@
+use super::config::{AlertSinkConfig, AlertSinkKind, AlertsConfig, Severity};
+use super::environment::Vars;
+use serde::Serialize;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::warn;
+
+pub type AlertFuture<'a> = Pin<Box<dyn Future<Output = Result<(), String>> + Send + 'a>>;
+
+const TELEGRAM_API: &str = "https://api.telegram.org";
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Alert {
+    pub severity: Severity,
+    pub title: String,
+    pub message: String,
+    pub at_ms: i64,
+}
+
+impl Alert {
+    // 聊天類通道使用的純文本
+    fn text(&self) -> String {
+        let level = match self.severity {
+            Severity::Info => "INFO",
+            Severity::Warning => "WARNING",
+            Severity::Critical => "CRITICAL",
+        };
+        format!("[{}] {}\n{}", level, self.title, self.message)
+    }
+}
+
+pub trait AlertSink: Send + Sync {
+    fn name(&self) -> &'static str;
+    fn send<'a>(&'a self, alert: &'a Alert) -> AlertFuture<'a>;
+}
+
+// 以 JSON POST 到 url；Discord、Slack 與通用 webhook 只是消息體不同
+struct HttpSink {
+    kind: AlertSinkKind,
+    client: reqwest::Client,
+    url: String,
+    chat_id: Option<String>,
+}
+
+impl HttpSink {
+    fn new(config: &AlertSinkConfig, client: reqwest::Client, vars: &dyn Vars) -> Result<Self, String> {
+        let env = |name: &Option<String>| {
+            let name = name.as_deref().unwrap_or_default();
+            vars.var(name)
+                .filter(|value| !value.is_empty())
+                .ok_or_else(|| format!("未設置告警通道環境變量 {}", name))
+        };
+        let url = match config.kind {
+            AlertSinkKind::Telegram => format!(
+                "{}/bot{}/sendMessage",
+                config.api_url.as_deref().unwrap_or(TELEGRAM_API).trim_end_matches('/'),
+                env(&config.token_env)?
+            ),
+            _ => env(&config.url_env)?,
+        };
+        Ok(Self {
+            kind: config.kind,
+            client,
+            url,
+            chat_id: config.chat_id.clone(),
+        })
+    }
+
+    fn body(&self, alert: &Alert) -> serde_json::Value {
+        match self.kind {
+            AlertSinkKind::Telegram => serde_json::json!({"chat_id": self.chat_id, "text": alert.text()}),
+            AlertSinkKind::Discord => serde_json::json!({"content": alert.text()}),
+            AlertSinkKind::Slack => serde_json::json!({"text": alert.text()}),
+            AlertSinkKind::Webhook => serde_json::json!(alert),
+        }
+    }
+}
+
+impl AlertSink for HttpSink {
+    fn name(&self) -> &'static str {
+        match self.kind {
+            AlertSinkKind::Telegram => "telegram",
+            AlertSinkKind::Discord => "discord",
+            AlertSinkKind::Slack => "slack",
+            AlertSinkKind::Webhook => "webhook",
+        }
+    }
+
+    fn send<'a>(&'a self, alert: &'a Alert) -> AlertFuture<'a> {
+        Box::pin(async move {
+            let response = self
+                .client
+                .post(&self.url)
+                .json(&self.body(alert))
+                .send()
+                .await
+                .map_err(|e| e.without_url().to_string())?;
+            if !response.status().is_success() {
+                return Err(format!("HTTP {}", response.status()));
+            }
+            Ok(())
+        })
+    }
+}
+
+// 按嚴重級別把告警分發到各通道，後台發送，不阻塞調用方
+pub struct Alerter {
+    sinks: Vec<(Severity, Arc<dyn AlertSink>)>,
+}
+
+impl Alerter {
+    pub fn new(config: &AlertsConfig, vars: &dyn Vars) -> Result<Self, String> {
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_millis(config.timeout_ms))
+            .build()
+            .map_err(|e| e.to_string())?;
+        let sinks = config
+            .sinks
+            .iter()
+            .map(|sink| {
+                let http: Arc<dyn AlertSink> = Arc::new(HttpSink::new(sink, client.clone(), vars)?);
+                Ok((sink.min_severity, http))
+            })
+            .collect::<Result<_, String>>()?;
+        Ok(Self { sinks })
+    }
+
+    pub fn notify(&self, severity: Severity, title: &str, message: String, at_ms: i64) {
+        let routed: Vec<Arc<dyn AlertSink>> = self
+            .sinks
+            .iter()
+            .filter(|(min_severity, _)| severity >= *min_severity)
+            .map(|(_, sink)| Arc::clone(sink))
+            .collect();
+        if routed.is_empty() {
+            return;
+        }
+        let alert = Arc::new(Alert {
+            severity,
+            title: title.to_string(),
+            message,
+            at_ms,
+        });
+        for sink in routed {
+            let alert = Arc::clone(&alert);
+            tokio::spawn(async move {
+                if let Err(error) = sink.send(&alert).await {
+                    warn!(sink = sink.name(), %error, title = %alert.title, "告警推送失敗");
+                }
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::deterministic_sim::build;
+    use crate::{config, EngineCommand, Environment};
+
+    // 本地接收 Slack 與通用 webhook 推送，按嚴重級別路由
+    #[tokio::test]
+    async fn alerts_route_by_severity() {
+        use axum::routing::post;
+        let received = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let (slack, webhook) = (std::sync::Arc::clone(&received), std::sync::Arc::clone(&received));
+        let app = axum::Router::new()
+            .route(
+                "/slack",
+                post(move |axum::Json(body): axum::Json<serde_json::Value>| async move {
+                    slack.lock().unwrap().push(("slack", body));
+                }),
+            )
+            .route(
+                "/webhook",
+                post(move |axum::Json(body): axum::Json<serde_json::Value>| async move {
+                    webhook.lock().unwrap().push(("webhook", body));
+                }),
+            );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await });
+
+        let mut config = config::EngineConfig::default();
+        for (kind, min_severity, url_env) in [
+            (config::AlertSinkKind::Slack, config::Severity::Warning, "E2E_ALERT_SLACK"),
+            (config::AlertSinkKind::Webhook, config::Severity::Critical, "E2E_ALERT_WEBHOOK"),
+        ] {
+            config.alerts.sinks.push(config::AlertSinkConfig {
+                kind,
+                min_severity,
+                url_env: Some(url_env.to_string()),
+                ..Default::default()
+            });
+        }
+        let env = Environment::system().with_vars([
+            ("E2E_ALERT_SLACK", format!("http://{}/slack", address)),
+            ("E2E_ALERT_WEBHOOK", format!("http://{}/webhook", address)),
+        ]);
+        let (engine, path) = build("alerts", config, env);
+        for engaged in [true, false] {
+            engine.handle_command(EngineCommand::SetKillSwitch { engaged }).await;
+        }
+        let deadline = std::time::Instant::now() + std::time::Duration::from_secs(5);
+        while received.lock().unwrap().len() < 3 && std::time::Instant::now() < deadline {
+            tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+        }
+        let received = received.lock().unwrap().clone();
+        assert_eq!(received.len(), 3, "{:?}", received);
+        let slack: Vec<&str> = received.iter().filter(|(sink, _)| *sink == "slack").map(|(_, body)| body["text"].as_str().unwrap()).collect();
+        assert!(slack.iter().any(|text| text.starts_with("[CRITICAL] 緊急停止已啟用")));
+        assert!(slack.iter().any(|text| text.starts_with("[WARNING] 緊急停止已解除")));
+        // 通用 webhook 只接收 critical，消息體為完整告警
+        let webhook: Vec<_> = received.iter().filter(|(sink, _)| *sink == "webhook").collect();
+        assert_eq!(webhook.len(), 1);
+        assert_eq!(webhook[0].1["severity"], "critical");
+        let _ = std::fs::remove_file(path);
+    }
+}
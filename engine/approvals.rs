@@ -0,0 +1,446 @@
+use super::config::{ApprovalConfig, ApprovalMode};
+use super::dex;
+use super::flash_loan::{encode_call, load_abi};
+use super::gas::GasOptimizer;
+use super::wallet::{HotWallet, WalletManager};
+use super::ExecutionEngine;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tracing::{debug, info, warn};
+use web3::ethabi::{Contract, Token};
+use web3::types::{Address, Bytes, CallRequest, TransactionParameters, U256};
+
+const ERC20_APPROVAL_ABI: &str = r#"[
+    {"type":"function","name":"allowance","stateMutability":"view","inputs":[{"name":"owner","type":"address"},{"name":"spender","type":"address"}],"outputs":[{"name":"","type":"uint256"}]},
+    {"type":"function","name":"approve","stateMutability":"nonpayable","inputs":[{"name":"spender","type":"address"},{"name":"amount","type":"uint256"}],"outputs":[{"name":"","type":"bool"}]}
+]"#;
+
+// Permit2 AllowanceTransfer：按 (用戶, 代幣, 路由) 記錄帶有效期的 uint160 額度
+const PERMIT2_ABI: &str = r#"[
+    {"type":"function","name":"allowance","stateMutability":"view","inputs":[{"name":"user","type":"address"},{"name":"token","type":"address"},{"name":"spender","type":"address"}],"outputs":[{"name":"amount","type":"uint160"},{"name":"expiration","type":"uint48"},{"name":"nonce","type":"uint48"}]},
+    {"type":"function","name":"approve","stateMutability":"nonpayable","inputs":[{"name":"token","type":"address"},{"name":"spender","type":"address"},{"name":"amount","type":"uint160"},{"name":"expiration","type":"uint48"}],"outputs":[]}
+]"#;
+
+// 授權不是時間敏感的操作，按最低檔位出價
+const APPROVAL_PRIORITY: i32 = 0;
+
+fn max_uint160() -> U256 {
+    (U256::one() << 160) - 1
+}
+
+// 錢包把某代幣授權給某路由；經 Permit2 時額度為兩段授權中較小者
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Grant {
+    pub owner: Address,
+    pub token: Address,
+    pub spender: Address,
+    pub permit2: bool,
+}
+
+// 緩存的額度；Permit2 額度帶過期時間（秒），過期後視為未緩存
+#[derive(Debug, Clone, Copy)]
+struct Cached {
+    amount: U256,
+    expires_at: Option<u64>,
+}
+
+impl Cached {
+    fn live(&self, now: u64) -> bool {
+        self.expires_at.is_none_or(|expires_at| expires_at > now)
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ApprovalStatus {
+    pub owner: String,
+    pub token: String,
+    pub spender: String,
+    pub permit2: bool,
+    pub allowance: Option<String>,
+    pub expires_at: Option<u64>,
+    // 等待後台授權的最少額度
+    pub pending: Option<String>,
+}
+
+/// 路由合約的代幣授權：緩存各錢包、代幣與路由的當前額度，兌換前只做檢查；
+/// 額度不足時登記缺口並立即返回錯誤，由後台按配置一次性授權最大額度或補足到指定數量。
+pub struct ApprovalManager {
+    config: ApprovalConfig,
+    permit2: Address,
+    erc20: Contract,
+    permit2_abi: Contract,
+    allowances: Mutex<HashMap<Grant, Cached>>,
+    wanted: Mutex<BTreeMap<Grant, U256>>,
+}
+
+impl ApprovalManager {
+    pub fn new(config: &ApprovalConfig) -> Result<Self, String> {
+        Ok(Self {
+            config: config.clone(),
+            permit2: Address::from_str(&config.permit2_address)
+                .map_err(|e| format!("dex.approvals.permit2_address 不是有效地址: {}", e))?,
+            erc20: load_abi(ERC20_APPROVAL_ABI),
+            permit2_abi: load_abi(PERMIT2_ABI),
+            allowances: Mutex::new(HashMap::new()),
+            wanted: Mutex::new(BTreeMap::new()),
+        })
+    }
+
+    pub fn grant(&self, owner: Address, token: Address, spender: Address, venue: &str) -> Grant {
+        Grant {
+            owner,
+            token,
+            spender,
+            permit2: self.config.permit2_venues.iter().any(|permit2_venue| permit2_venue == venue),
+        }
+    }
+
+    pub fn approve_on_startup(&self) -> bool {
+        self.config.approve_on_startup
+    }
+
+    pub fn interval(&self) -> Duration {
+        Duration::from_millis(self.config.interval_ms)
+    }
+
+    async fn erc20_allowance(&self, wallets: &WalletManager, token: Address, owner: Address, spender: Address) -> Result<U256, String> {
+        let tokens = [Token::Address(owner), Token::Address(spender)];
+        dex::call_uint(wallets.web3(), &self.erc20, token, "allowance", &tokens, None).await
+    }
+
+    // 額度與過期時間；已過期的額度為 0
+    async fn permit2_allowance(&self, wallets: &WalletManager, grant: &Grant) -> Result<(U256, u64), String> {
+        let tokens = [Token::Address(grant.owner), Token::Address(grant.token), Token::Address(grant.spender)];
+        let output = dex::call(wallets.web3(), &self.permit2_abi, self.permit2, "allowance", &tokens, None).await?;
+        let (Some(Token::Uint(amount)), Some(Token::Uint(expiration))) = (output.first(), output.get(1)) else {
+            return Err("Permit2 allowance 返回值格式錯誤".to_string());
+        };
+        let expiration = expiration.low_u64();
+        Ok((if expiration > now_secs() { *amount } else { U256::zero() }, expiration))
+    }
+
+    // 從鏈上讀取並緩存當前額度
+    pub async fn refresh(&self, wallets: &WalletManager, grant: Grant) -> Result<U256, String> {
+        let cached = if grant.permit2 {
+            let to_permit2 = self.erc20_allowance(wallets, grant.token, grant.owner, self.permit2).await?;
+            let (amount, expiration) = self.permit2_allowance(wallets, &grant).await?;
+            Cached {
+                amount: to_permit2.min(amount),
+                expires_at: Some(expiration),
+            }
+        } else {
+            Cached {
+                amount: self.erc20_allowance(wallets, grant.token, grant.owner, grant.spender).await?,
+                expires_at: None,
+            }
+        };
+        self.allowances.lock().unwrap().insert(grant, cached);
+        Ok(cached.amount)
+    }
+
+    /// 兌換前檢查額度：未緩存或 Permit2 額度已過期時讀取一次鏈上額度，不足時登記缺口並返回錯誤，不等待授權上鏈。
+    pub async fn require(&self, wallets: &WalletManager, grant: Grant, amount: U256) -> Result<(), String> {
+        let now = now_secs();
+        let cached = self.allowances.lock().unwrap().get(&grant).filter(|cached| cached.live(now)).copied();
+        let allowance = match cached {
+            Some(cached) => cached.amount,
+            None => self.refresh(wallets, grant).await?,
+        };
+        if allowance >= amount {
+            return Ok(());
+        }
+        self.request(grant, amount);
+        Err(format!(
+            "錢包 {:?} 對路由 {:?} 的代幣 {:?} 授權不足，已排入後台授權，本次不兌換",
+            grant.owner, grant.spender, grant.token
+        ))
+    }
+
+    pub fn request(&self, grant: Grant, amount: U256) {
+        let mut wanted = self.wanted.lock().unwrap();
+        let entry = wanted.entry(grant).or_default();
+        *entry = (*entry).max(amount);
+    }
+
+    // 兌換成交後扣減緩存額度；最大額度授權不會被扣減
+    pub fn consume(&self, grant: Grant, amount: U256) {
+        let unlimited = if grant.permit2 { max_uint160() } else { U256::MAX };
+        if let Some(cached) = self.allowances.lock().unwrap().get_mut(&grant) {
+            if cached.amount < unlimited {
+                cached.amount = cached.amount.saturating_sub(amount);
+            }
+        }
+    }
+
+    // 兌換估算或上鏈失敗時丟棄緩存，下次兌換前重新讀取鏈上額度
+    pub fn invalidate(&self, grant: Grant) {
+        self.allowances.lock().unwrap().remove(&grant);
+    }
+
+    pub fn snapshot(&self) -> Vec<ApprovalStatus> {
+        let allowances = self.allowances.lock().unwrap();
+        let wanted = self.wanted.lock().unwrap();
+        let mut grants: Vec<Grant> = allowances.keys().chain(wanted.keys()).copied().collect();
+        grants.sort();
+        grants.dedup();
+        grants
+            .into_iter()
+            .map(|grant| ApprovalStatus {
+                owner: format!("{:?}", grant.owner),
+                token: format!("{:?}", grant.token),
+                spender: format!("{:?}", grant.spender),
+                permit2: grant.permit2,
+                allowance: allowances.get(&grant).map(|cached| cached.amount.to_string()),
+                expires_at: allowances.get(&grant).and_then(|cached| cached.expires_at),
+                pending: wanted.get(&grant).map(U256::to_string),
+            })
+            .collect()
+    }
+
+    // 授權的目標額度：max 模式為最大值，exact 模式為配置數量與缺口中較大者
+    fn target(&self, asset: Option<&(String, u32)>, needed: U256, permit2: bool) -> U256 {
+        let unlimited = if permit2 { max_uint160() } else { U256::MAX };
+        match (self.config.mode, asset) {
+            (ApprovalMode::Max, _) => unlimited,
+            (ApprovalMode::Exact, Some((asset, decimals))) => self
+                .config
+                .exact_amounts
+                .get(asset)
+                .map(|amount| dex::to_units(*amount, *decimals))
+                .unwrap_or_default()
+                .max(needed)
+                .min(unlimited),
+            (ApprovalMode::Exact, None) => needed.min(unlimited),
+        }
+    }
+
+    /// 依次處理登記的授權缺口；assets 為代幣地址 -> (資產名, 精度)，用於 exact 模式的配置數量。
+    /// 失敗的缺口保留到下一輪重試。
+    pub async fn process(
+        &self,
+        wallets: &WalletManager,
+        gas: &GasOptimizer,
+        gas_buffer: f64,
+        confirmations: usize,
+        assets: &HashMap<Address, (String, u32)>,
+    ) {
+        let pending: Vec<(Grant, U256)> = self.wanted.lock().unwrap().iter().map(|(grant, amount)| (*grant, *amount)).collect();
+        for (grant, needed) in pending {
+            let Some(wallet) = wallets.wallet(grant.owner) else {
+                self.wanted.lock().unwrap().remove(&grant);
+                continue;
+            };
+            let sender = Sender {
+                wallets,
+                wallet,
+                gas,
+                gas_buffer,
+                confirmations,
+            };
+            let asset = assets.get(&grant.token);
+            match self.approve(&sender, grant, needed, asset).await {
+                Ok(allowance) => {
+                    info!(owner = ?grant.owner, token = ?grant.token, spender = ?grant.spender, permit2 = grant.permit2, %allowance, "代幣授權已確認");
+                    self.wanted.lock().unwrap().remove(&grant);
+                }
+                Err(error) => warn!(owner = ?grant.owner, token = ?grant.token, spender = ?grant.spender, %error, "代幣授權失敗，稍後重試"),
+            }
+        }
+    }
+
+    async fn approve(&self, sender: &Sender<'_>, grant: Grant, needed: U256, asset: Option<&(String, u32)>) -> Result<U256, String> {
+        // 授權期間額度可能已被其他途徑補足
+        let current = self.refresh(sender.wallets, grant).await?;
+        if current >= needed {
+            return Ok(current);
+        }
+        let target = self.target(asset, needed, grant.permit2);
+        let reset = asset.is_some_and(|(asset, _)| self.config.reset_before_approve.contains(asset));
+        let erc20_spender = if grant.permit2 { self.permit2 } else { grant.spender };
+        let erc20_current = self.erc20_allowance(sender.wallets, grant.token, grant.owner, erc20_spender).await?;
+        if erc20_current < needed {
+            // 經 Permit2 時代幣對 Permit2 的授權總按最大額度，額度由 Permit2 一側控制
+            let erc20_target = if grant.permit2 { U256::MAX } else { target };
+            if reset && !erc20_current.is_zero() {
+                let data = encode_call(&self.erc20, "approve", &[Token::Address(erc20_spender), Token::Uint(U256::zero())])?;
+                sender.send(grant.token, data, "清零授權").await?;
+            }
+            let data = encode_call(&self.erc20, "approve", &[Token::Address(erc20_spender), Token::Uint(erc20_target)])?;
+            sender.send(grant.token, data, "代幣授權").await?;
+        }
+        if grant.permit2 {
+            let expiration = U256::from(now_secs() + self.config.permit2_expiration_secs);
+            let tokens = [Token::Address(grant.token), Token::Address(grant.spender), Token::Uint(target), Token::Uint(expiration)];
+            let data = encode_call(&self.permit2_abi, "approve", &tokens)?;
+            sender.send(self.permit2, data, "Permit2 授權").await?;
+        }
+        self.refresh(sender.wallets, grant).await
+    }
+}
+
+struct Sender<'a> {
+    wallets: &'a WalletManager,
+    wallet: &'a HotWallet,
+    gas: &'a GasOptimizer,
+    gas_buffer: f64,
+    confirmations: usize,
+}
+
+impl Sender<'_> {
+    async fn send(&self, to: Address, data: Vec<u8>, what: &str) -> Result<(), String> {
+        let estimated = self
+            .wallets
+            .verified_web3()
+            .eth()
+            .estimate_gas(
+                CallRequest {
+                    from: Some(self.wallet.address()),
+                    to: Some(to),
+                    data: Some(Bytes(data.clone())),
+                    ..CallRequest::default()
+                },
+                None,
+            )
+            .await
+            .map_err(|e| format!("{} gas 估算失敗: {}", what, e))?;
+        let estimated = u64::try_from(estimated).map_err(|_| format!("{} gas 估算超出範圍: {}", what, estimated))?;
+        let gas_limit = (estimated as f64 * self.gas_buffer) as u64;
+        let quote = self.gas.quote(APPROVAL_PRIORITY);
+        self.gas.check_budget(&quote, gas_limit).map_err(|e| format!("{}{}", what, e))?;
+        let hash = self
+            .wallets
+            .submit(
+                self.wallet,
+                TransactionParameters {
+                    to: Some(to),
+                    gas: gas_limit.into(),
+                    data: Bytes(data),
+                    transaction_type: Some(2.into()),
+                    max_fee_per_gas: Some(quote.max_fee_per_gas.into()),
+                    max_priority_fee_per_gas: Some(quote.max_priority_fee_per_gas.into()),
+                    ..TransactionParameters::default()
+                },
+            )
+            .await?;
+        let tx_hash = format!("{:?}", hash);
+        debug!(%tx_hash, wallet = self.wallet.name(), what, "授權交易已發送");
+        let receipt = self
+            .wallets
+            .confirm(hash, self.confirmations, what)
+            .await?;
+        if receipt.status != Some(1.into()) {
+            return Err(format!("{}回滾: {}", what, tx_hash));
+        }
+        Ok(())
+    }
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+// 每條配置了 DEX 的鏈：啟動時按配置預先授權，之後按間隔處理兌換時登記的缺口
+pub fn spawn(engine: Arc<ExecutionEngine>) {
+    for name in engine.chains.keys() {
+        if engine.chains[name].dex.is_none() {
+            continue;
+        }
+        let engine = Arc::clone(&engine);
+        let name = name.clone();
+        tokio::spawn(async move {
+            let chain = &engine.chains[&name];
+            let Some(dex) = chain.dex.as_ref() else { return };
+            if dex.approvals().approve_on_startup() {
+                dex.request_all_approvals().await;
+            }
+            let mut interval = tokio::time::interval(dex.approvals().interval());
+            loop {
+                interval.tick().await;
+                dex.process_approvals(&chain.gas_optimizer).await;
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock_exchange_e2e::{abi_hex, hot_wallets, mined_receipt, rpc_node};
+    use crate::{config, gas};
+    use std::time::Instant;
+
+    // 額度不足的兌換立即失敗並登記缺口，後台授權確認後放行；Permit2 額度過期或緩存被丟棄時重新讀取
+    #[tokio::test]
+    async fn approvals_defer_swaps_until_background_approval_confirms() {
+        use web3::ethabi::Token;
+        use web3::types::{Address, U256};
+        let now = || std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_secs();
+        let config = config::ApprovalConfig {
+            permit2_venues: vec!["uniswap_v3".to_string()],
+            ..Default::default()
+        };
+        let permit2: Address = config.permit2_address.parse().unwrap();
+        // (代幣對路由或 Permit2 的額度, Permit2 額度, Permit2 過期時間)
+        let state = Arc::new(std::sync::Mutex::new((U256::zero(), U256::zero(), 0u64)));
+        let node = Arc::clone(&state);
+        let url = rpc_node(Arc::new(move |method, params| {
+            let mut state = node.lock().unwrap();
+            Ok(match method {
+                "eth_call" if params[0]["to"].as_str().and_then(|to| to.parse::<Address>().ok()) == Some(permit2) => {
+                    abi_hex(&[Token::Uint(state.1), Token::Uint(state.2.into()), Token::Uint(U256::zero())])
+                }
+                "eth_call" => abi_hex(&[Token::Uint(state.0)]),
+                "eth_estimateGas" => serde_json::json!("0xea60"),
+                "eth_getTransactionCount" => serde_json::json!("0x0"),
+                "eth_blockNumber" => serde_json::json!("0x10"),
+                // 任一授權交易上鏈後兩段額度都補足
+                "eth_sendRawTransaction" => {
+                    *state = (U256::MAX, (U256::one() << 160) - 1, now() + 3600);
+                    serde_json::json!(format!("{:?}", web3::types::H256::from_low_u64_be(7)))
+                }
+                "eth_getTransactionReceipt" => mined_receipt(&params[0]),
+                other => return Err(format!("unexpected {}", other)),
+            })
+        }))
+        .await;
+        let wallets = hot_wallets(&url);
+        let owner = wallets.addresses()[0];
+        let gas = gas::GasOptimizer::connect(config::GasConfig::default(), None);
+        let approvals = ApprovalManager::new(&config).unwrap();
+        let (token, router) = (Address::from_low_u64_be(0x70), Address::from_low_u64_be(0x80));
+        let direct = approvals.grant(owner, token, router, "curve");
+        let amount = U256::from(1_000_000u64);
+
+        // 缺口登記後兌換直接返回錯誤，不等待授權
+        let started = Instant::now();
+        assert!(approvals.require(&wallets, direct, amount).await.unwrap_err().contains("授權不足"));
+        assert!(started.elapsed() < Duration::from_secs(1));
+        assert_eq!(approvals.snapshot()[0].pending.as_deref(), Some("1000000"));
+        approvals.process(&wallets, &gas, 1.2, 1, &HashMap::new()).await;
+        assert!(approvals.snapshot().iter().all(|status| status.pending.is_none()));
+        approvals.require(&wallets, direct, amount).await.unwrap();
+
+        // 授權被撤銷：緩存仍放行，兌換失敗丟棄緩存後重新讀取
+        state.lock().unwrap().0 = U256::zero();
+        approvals.require(&wallets, direct, amount).await.unwrap();
+        approvals.invalidate(direct);
+        assert!(approvals.require(&wallets, direct, amount).await.is_err());
+
+        // Permit2 額度即將過期：過期後緩存失效，重新讀取並排入授權
+        *state.lock().unwrap() = (U256::MAX, U256::MAX >> 96, now() + 1);
+        let via_permit2 = approvals.grant(owner, token, router, "uniswap_v3");
+        approvals.require(&wallets, via_permit2, amount).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(2_100)).await;
+        assert!(approvals.require(&wallets, via_permit2, amount).await.is_err());
+        approvals.process(&wallets, &gas, 1.2, 1, &HashMap::new()).await;
+        approvals.require(&wallets, via_permit2, amount).await.unwrap();
+        let status = approvals.snapshot().into_iter().find(|status| status.permit2).unwrap();
+        assert!(status.expires_at.unwrap() > now() + 3000);
+    }
+}
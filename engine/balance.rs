@@ -0,0 +1,221 @@
+use super::config::PreTradeConfig;
+use super::user_stream::UserStreams;
+use super::LeverageSetting;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MarginView {
+    // 保證金資產的可用餘額（穩定幣按 1:1 計價）
+    pub free_collateral: f64,
+    pub reserved: f64,
+    pub available: f64,
+}
+
+// 一次通過的檢查：各交易所按 notional 預留的保證金，執行結束後調用 release 歸還
+#[derive(Debug, Clone)]
+pub struct Reservation {
+    pub notional: f64,
+    pub margins: Vec<(String, f64)>,
+}
+
+pub struct BalanceService {
+    config: PreTradeConfig,
+    reserved: Mutex<BTreeMap<String, f64>>,
+}
+
+impl BalanceService {
+    pub fn new(config: PreTradeConfig) -> Self {
+        Self {
+            config,
+            reserved: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.config.enabled
+    }
+
+    pub fn default_setting(&self) -> LeverageSetting {
+        LeverageSetting {
+            leverage: self.config.default_leverage,
+            margin_mode: self.config.default_margin_mode,
+        }
+    }
+
+    // 開倉 notional 所需的初始保證金（含緩衝）與預估手續費
+    pub fn required(&self, notional: f64, leverage: f64, fee_rate: f64) -> f64 {
+        notional * ((1.0 + self.config.margin_buffer) / leverage + fee_rate)
+    }
+
+    fn free_collateral(&self, streams: &UserStreams, exchange: &str) -> Option<f64> {
+        let account = streams.account(exchange)?;
+        Some(
+            self.config
+                .collateral_assets
+                .iter()
+                .filter_map(|asset| account.balances.get(asset))
+                .map(|balance| balance.free)
+                .sum(),
+        )
+    }
+
+    // 各交易所（交易所, 手續費率）的可用保證金能支持的 notional；不足且不允許縮減時返回錯誤
+    fn size(
+        &self,
+        reserved: &BTreeMap<String, f64>,
+        streams: &UserStreams,
+        legs: &[(&str, f64)],
+        notional: f64,
+        leverage: f64,
+    ) -> Result<f64, String> {
+        let mut sized = notional;
+        for (exchange, fee_rate) in legs {
+            let free = self
+                .free_collateral(streams, exchange)
+                .ok_or_else(|| format!("交易所 {} 尚無餘額數據", exchange))?;
+            let available = free - reserved.get(*exchange).copied().unwrap_or_default();
+            let affordable = available.max(0.0) / self.required(1.0, leverage, *fee_rate);
+            if affordable < sized {
+                if !self.config.allow_downsize || affordable < notional * self.config.min_fill_fraction {
+                    return Err(format!(
+                        "交易所 {} 可用保證金不足: 可用 {:.2}，需要 {:.2}",
+                        exchange,
+                        available,
+                        self.required(notional, leverage, *fee_rate)
+                    ));
+                }
+                sized = affordable;
+            }
+        }
+        Ok(sized)
+    }
+
+    // 只檢查不預留，用於風險預覽
+    pub fn check(&self, streams: &UserStreams, legs: &[(&str, f64)], notional: f64, leverage: f64) -> Result<f64, String> {
+        self.size(&self.reserved.lock().unwrap(), streams, legs, notional, leverage)
+    }
+
+    pub fn reserve(
+        &self,
+        streams: &UserStreams,
+        legs: &[(&str, f64)],
+        notional: f64,
+        leverage: f64,
+    ) -> Result<Reservation, String> {
+        let mut reserved = self.reserved.lock().unwrap();
+        let sized = self.size(&reserved, streams, legs, notional, leverage)?;
+        let margins: Vec<(String, f64)> = legs
+            .iter()
+            .map(|(exchange, fee_rate)| (exchange.to_string(), self.required(sized, leverage, *fee_rate)))
+            .collect();
+        for (exchange, margin) in &margins {
+            *reserved.entry(exchange.clone()).or_default() += margin;
+        }
+        Ok(Reservation { notional: sized, margins })
+    }
+
+    pub fn release(&self, reservation: &Reservation) {
+        let mut reserved = self.reserved.lock().unwrap();
+        for (exchange, margin) in &reservation.margins {
+            if let Some(total) = reserved.get_mut(exchange) {
+                *total = (*total - margin).max(0.0);
+            }
+        }
+    }
+
+    pub fn snapshot(&self, streams: &UserStreams) -> BTreeMap<String, MarginView> {
+        let reserved = self.reserved.lock().unwrap();
+        streams
+            .snapshot()
+            .keys()
+            .filter_map(|exchange| {
+                let free_collateral = self.free_collateral(streams, exchange)?;
+                let reserved = reserved.get(exchange).copied().unwrap_or_default();
+                Some((
+                    exchange.clone(),
+                    MarginView {
+                        free_collateral,
+                        reserved,
+                        available: free_collateral - reserved,
+                    },
+                ))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bench::START_MS;
+    use crate::deterministic_sim::{build, connect_sessions, funded_config, request};
+    use crate::{config, session, Environment};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    // 默認配置下模擬賬戶帶有初始保證金，資金費率套利通過下單前檢查，結束後歸還預留
+    #[tokio::test]
+    async fn default_config_passes_the_pre_trade_check() {
+        let (engine, path) = build("default-margin", config::EngineConfig::default(), Environment::simulated(START_MS, 1));
+        let engine = Arc::new(engine);
+        session::spawn(Arc::clone(&engine));
+        for _ in 0..250 {
+            if ["binance", "bybit"].iter().all(|exchange| engine.sessions.is_available(exchange)) {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        let margins = engine.balances.snapshot(&engine.user_streams);
+        assert_eq!(margins["binance"].available, 100_000.0);
+        assert_eq!(margins["bybit"].available, 100_000.0);
+
+        engine.quotes.record_predicted_rate("binance", "BTCUSDT", 0.0060, START_MS);
+        engine.quotes.record_predicted_rate("bybit", "BTCUSDT", 0.0010, START_MS);
+        let response = engine.execute_funding_rate_arbitrage(request(1_000.0)).await;
+        assert_eq!(response.status, "success", "{:?}", response.error_message);
+        assert!(engine.balances.snapshot(&engine.user_streams).values().all(|margin| margin.reserved == 0.0));
+        let _ = std::fs::remove_file(path);
+    }
+
+    // 兩條永續腿按用戶數據流餘額預留初始保證金（含 10% 緩衝與手續費）：不足時縮減到可負擔金額，
+    // 低於請求金額的 25% 或不允許縮減時拒絕；預留在執行結束前佔用可用保證金
+    #[tokio::test]
+    async fn pre_trade_margin_check_downsizes_or_rejects_legs() {
+        let mut config = funded_config();
+        config.spot_arbitrage.inventory.insert("binance".to_string(), [("USDT".to_string(), 1_000.0)].into());
+        let (engine, path) = build("pre-trade", config, Environment::simulated(START_MS, 1));
+        let engine = Arc::new(engine);
+        connect_sessions(&engine).await;
+        let downsized = request(5_000.0);
+        let legs = engine.margin_legs(&downsized).unwrap();
+        assert_eq!(legs.iter().map(|(exchange, _)| *exchange).collect::<Vec<_>>(), ["binance", "bybit"]);
+        let affordable = 1_000.0 / engine.balances.required(1.0, 2.0, engine.taker_fee("binance"));
+        let sized = engine.balances.check(&engine.user_streams, &legs, 5_000.0, 2.0).unwrap();
+        assert!((sized - affordable).abs() < 1e-6 && sized < 5_000.0);
+        assert!(engine.balances.check(&engine.user_streams, &legs, 10_000.0, 2.0).is_err());
+        // 槓桿越高所需保證金越少
+        assert_eq!(engine.balances.check(&engine.user_streams, &legs, 5_000.0, 10.0), Ok(5_000.0));
+
+        let reservation = engine.reserve_margin(&downsized, engine.leverage_setting(&downsized)).unwrap().unwrap();
+        assert_eq!(reservation.notional, sized);
+        assert!(engine.balances.snapshot(&engine.user_streams)["binance"].available < 1e-6);
+        let error = engine.balances.check(&engine.user_streams, &legs, 1_000.0, 2.0).unwrap_err();
+        assert!(error.contains("binance 可用保證金不足"), "{}", error);
+        engine.balances.release(&reservation);
+        assert_eq!(engine.balances.snapshot(&engine.user_streams)["binance"].available, 1_000.0);
+
+        let response = engine.execute_funding_rate_arbitrage(request(10_000.0)).await;
+        assert!(response.error_message.unwrap().contains("binance 可用保證金不足"));
+        let _ = std::fs::remove_file(path);
+
+        let mut config = funded_config();
+        config.spot_arbitrage.inventory.insert("binance".to_string(), [("USDT".to_string(), 1_000.0)].into());
+        config.pre_trade.allow_downsize = false;
+        let (strict, path) = build("pre-trade-strict", config, Environment::simulated(START_MS, 1));
+        let strict = Arc::new(strict);
+        connect_sessions(&strict).await;
+        assert!(strict.balances.check(&strict.user_streams, &legs, 5_000.0, 2.0).is_err());
+        let _ = std::fs::remove_file(path);
+    }
+}
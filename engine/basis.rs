@@ -0,0 +1,224 @@
+use super::config::BasisConfig;
+use super::market_data::{self, Fill};
+use super::{ArbitrageRequest, ChildOrder, ExecutionOutcome, ExecutionEngine};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use tracing::info;
+
+const EWMA_ALPHA: f64 = 0.1;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BasisStats {
+    pub last_basis: f64,
+    pub mean_basis: f64,
+    pub samples: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CarryPosition {
+    pub spot_quantity: f64,
+    pub perp_quantity: f64,
+    pub notional: f64,
+    pub entry_basis: f64,
+    pub opened_at_ms: i64,
+}
+
+#[derive(Default)]
+struct State {
+    // 鍵："symbol:spot_exchange:perp_exchange"
+    stats: HashMap<String, BasisStats>,
+    positions: HashMap<String, CarryPosition>,
+}
+
+pub struct BasisTracker {
+    config: BasisConfig,
+    state: Mutex<State>,
+}
+
+impl BasisTracker {
+    pub fn new(config: BasisConfig) -> Self {
+        Self {
+            config,
+            state: Mutex::new(State::default()),
+        }
+    }
+
+    fn observe(&self, key: &str, basis: f64) {
+        let mut state = self.state.lock().unwrap();
+        let stats = state.stats.entry(key.to_string()).or_insert(BasisStats {
+            last_basis: basis,
+            mean_basis: basis,
+            samples: 0,
+        });
+        stats.last_basis = basis;
+        stats.mean_basis += EWMA_ALPHA * (basis - stats.mean_basis);
+        stats.samples += 1;
+    }
+
+    pub fn snapshot(&self) -> serde_json::Value {
+        let state = self.state.lock().unwrap();
+        serde_json::json!({ "basis": state.stats, "positions": state.positions })
+    }
+}
+
+// 若已有持倉且基差已收斂則平倉，否則在收益達標時開倉
+pub async fn execute(engine: &ExecutionEngine, request: &ArbitrageRequest) -> Result<ExecutionOutcome, String> {
+    let spot_exchange = &request.primary_exchange;
+    let perp_exchange = &request.secondary_exchange;
+    for exchange in [spot_exchange, perp_exchange] {
+        if !engine.exchanges.contains_key(exchange) {
+            return Err(format!("不支持的交易所: {}", exchange));
+        }
+    }
+    let (base, quote) =
+        market_data::split_symbol(&request.symbol).ok_or_else(|| format!("無法解析交易對: {}", request.symbol))?;
+    let spot_book = market_data::simulated_spot_book(engine.env.rng.as_ref(), spot_exchange, &base, &quote)?;
+    let perp_book = market_data::simulated_perp_book(engine.env.rng.as_ref(), perp_exchange, &base, &quote)?;
+    let (spot_mid, perp_mid) = spot_book.mid().zip(perp_book.mid()).ok_or("訂單簿為空")?;
+    let basis = (perp_mid - spot_mid) / spot_mid;
+
+    let key = format!("{}:{}:{}", request.symbol, spot_exchange, perp_exchange);
+    let tracker = &engine.basis;
+    tracker.observe(&key, basis);
+    let spot_fee = engine.taker_fee(spot_exchange);
+    let perp_fee = engine.taker_fee(perp_exchange);
+
+    let open_position = tracker.state.lock().unwrap().positions.get(&key).cloned();
+    if let Some(position) = open_position {
+        if basis > tracker.config.exit_basis {
+            return Err(format!("已有期現頭寸，基差 {:.6} 尚未收斂到平倉閾值", basis));
+        }
+        // 平倉：賣出現貨、買回永續
+        let spot_fill = spot_book.sell_base(position.spot_quantity);
+        let perp_fill = perp_book.buy_base(position.perp_quantity);
+        if !spot_fill.complete || !perp_fill.complete {
+            return Err("訂單簿深度不足，無法平倉".to_string());
+        }
+        engine.acquire_orders(&[spot_exchange, perp_exchange]).await?;
+        tracker.state.lock().unwrap().positions.remove(&key);
+        let fees = spot_fill.quote_quantity * spot_fee + perp_fill.quote_quantity * perp_fee;
+        let profit = (position.entry_basis - basis) * position.notional - fees;
+        info!(entry_basis = position.entry_basis, exit_basis = basis, profit, "期現頭寸已平倉");
+        return Ok(outcome(engine, request, "exit", &spot_fill, &perp_fill, spot_fee, perp_fee, profit, fees));
+    }
+
+    // 開倉收益：基差 + 持有期資金費（按預測的下一期費率）- 兩條腿往返手續費
+    let funding_rate = engine.get_predicted_funding_rate(perp_exchange, &request.symbol).await?;
+    let expected_edge =
+        basis + funding_rate * tracker.config.holding_periods as f64 - 2.0 * (spot_fee + perp_fee);
+    if expected_edge < tracker.config.min_entry_edge {
+        return Err(format!("期現預期收益 {:.6} 低於開倉閾值", expected_edge));
+    }
+
+    // 開倉：買入現貨，按相同數量做空永續
+    let spot_fill = spot_book.buy_with_quote(request.amount);
+    let perp_fill = perp_book.sell_base(spot_fill.base_quantity);
+    if !spot_fill.complete || !perp_fill.complete {
+        return Err("訂單簿深度不足，無法開倉".to_string());
+    }
+    engine.acquire_orders(&[spot_exchange, perp_exchange]).await?;
+    let position = CarryPosition {
+        spot_quantity: spot_fill.base_quantity,
+        perp_quantity: perp_fill.base_quantity,
+        notional: request.amount,
+        entry_basis: basis,
+        opened_at_ms: engine.env.now_ms(),
+    };
+    tracker.state.lock().unwrap().positions.insert(key, position);
+    let fees = spot_fill.quote_quantity * spot_fee + perp_fill.quote_quantity * perp_fee;
+    let profit = expected_edge * request.amount;
+    info!(basis, funding_rate, expected_edge, "期現頭寸已開倉");
+    Ok(outcome(engine, request, "entry", &spot_fill, &perp_fill, spot_fee, perp_fee, profit, fees))
+}
+
+#[allow(clippy::too_many_arguments)]
+fn outcome(
+    engine: &ExecutionEngine,
+    request: &ArbitrageRequest,
+    phase: &str,
+    spot_fill: &Fill,
+    perp_fill: &Fill,
+    spot_fee: f64,
+    perp_fee: f64,
+    profit: f64,
+    fees: f64,
+) -> ExecutionOutcome {
+    let (spot_side, perp_side) = if phase == "entry" { ("buy", "sell") } else { ("sell", "buy") };
+    let order = |leg: &str, exchange: &str, side: &str, fill: &Fill, fee_rate: f64| ChildOrder {
+        leg: format!("{}_{}", phase, leg),
+        exchange: exchange.to_string(),
+        symbol: request.symbol.clone(),
+        side: side.to_string(),
+        quantity: fill.base_quantity,
+        filled_quantity: fill.base_quantity,
+        fee: fill.quote_quantity * fee_rate,
+        status: "filled".to_string(),
+    };
+    ExecutionOutcome {
+        profit,
+        fees,
+        orders: vec![
+            order("spot", &request.primary_exchange, spot_side, spot_fill, spot_fee),
+            order("perp", &request.secondary_exchange, perp_side, perp_fill, perp_fee),
+        ],
+        expected_slippage_bps: engine.sizing.expected_slippage_bps(request.amount),
+        realized_slippage_bps: (spot_fill.slippage_bps() + perp_fill.slippage_bps()) / 2.0,
+        gas_used: None,
+        gas_cost_eth: None,
+        settlement_proof: None,
+        unwound: Vec::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bench::START_MS;
+    use crate::deterministic_sim::{build, funded_config, request};
+    use crate::{config, Environment, StrategyType};
+
+    // 開倉收益按基差加 21 期預測資金費再扣往返手續費；持倉後基差收斂到平倉閾值以下才平倉
+    #[tokio::test]
+    async fn cash_and_carry_enters_on_basis_plus_funding_and_exits_on_convergence() {
+        let mut config = funded_config();
+        config.basis = config::BasisConfig { min_entry_edge: 0.01, exit_basis: 0.01, holding_periods: 21 };
+        let (engine, path) = build("cash-and-carry", config, Environment::simulated(START_MS, 1));
+        let carry = ArbitrageRequest { strategy_type: StrategyType::CashAndCarry, ..request(1_000.0) };
+        let last_basis = |engine: &ExecutionEngine| engine.basis.snapshot()["basis"]["BTCUSDT:binance:bybit"]["last_basis"].as_f64().unwrap();
+        let open_positions = |engine: &ExecutionEngine| engine.basis.snapshot()["positions"].as_object().unwrap().len();
+        let round_trip_fees = 2.0 * (engine.taker_fee("binance") + engine.taker_fee("bybit"));
+
+        // 預期收益低於 1% 的開倉要求
+        let error = execute(&engine, &carry).await.err().unwrap();
+        assert!(error.contains("低於開倉閾值"), "{}", error);
+        assert_eq!(open_positions(&engine), 0);
+
+        let tracker = |exit_basis| BasisTracker::new(config::BasisConfig { min_entry_edge: 0.0, exit_basis, holding_periods: 21 });
+        let engine = ExecutionEngine { basis: tracker(0.01), ..engine };
+        let entry = execute(&engine, &carry).await.unwrap();
+        let entry_basis = last_basis(&engine);
+        // 開倉時查詢的預測費率寫入行情緩存
+        let funding_rate = engine.quotes.predicted_rate("bybit", "BTCUSDT", START_MS).unwrap();
+        assert!((entry.profit - (entry_basis + funding_rate * 21.0 - round_trip_fees) * 1_000.0).abs() < 1e-9);
+        let legs: Vec<_> = entry.orders.iter().map(|o| (o.leg.as_str(), o.exchange.as_str(), o.side.as_str())).collect();
+        assert_eq!(legs, [("entry_spot", "binance", "buy"), ("entry_perp", "bybit", "sell")]);
+        assert_eq!(entry.orders[0].quantity, entry.orders[1].quantity);
+        assert_eq!(engine.basis.snapshot()["positions"]["BTCUSDT:binance:bybit"]["entry_basis"].as_f64(), Some(entry_basis));
+
+        // 基差總低於 1%：同一持倉按開倉數量反向平倉，收益為基差收斂部分減手續費
+        let exit = execute(&engine, &carry).await.unwrap();
+        let legs: Vec<_> = exit.orders.iter().map(|o| (o.leg.as_str(), o.side.as_str(), o.quantity)).collect();
+        assert_eq!(legs, [("exit_spot", "sell", entry.orders[0].quantity), ("exit_perp", "buy", entry.orders[1].quantity)]);
+        assert!((exit.profit - ((entry_basis - last_basis(&engine)) * 1_000.0 - exit.fees)).abs() < 1e-9);
+        assert_eq!(open_positions(&engine), 0);
+
+        // 平倉閾值低於任何可能的基差時持倉保留
+        let engine = ExecutionEngine { basis: tracker(-1.0), ..engine };
+        execute(&engine, &carry).await.unwrap();
+        let error = execute(&engine, &carry).await.err().unwrap();
+        assert!(error.contains("尚未收斂到平倉閾值"), "{}", error);
+        assert_eq!(open_positions(&engine), 1);
+        let _ = std::fs::remove_file(path);
+    }
+}
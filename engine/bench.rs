@@ -0,0 +1,120 @@
+use super::config::{self, ScannerConfig};
+use super::funding_history::FundingSample;
+use super::protocol::wire;
+use super::scanner::Scanner;
+use super::{chain, client_auth, events, journal, routing, server, shared_memory, tls, Environment, ExecutionEngine};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+use tokio::io::{AsyncRead, AsyncWrite};
+
+// 模擬時鐘的起點，請求時間戳與之一致
+pub const START_MS: i64 = 1_700_000_000_000;
+const EXCHANGES: [&str; 3] = ["binance", "bybit", "okx"];
+const TAKER_FEE: f64 = 0.0004;
+
+/// 與 [`ExecutionEngine::start_with`] 相同的組裝，但不連接歷史存儲、不啟動後台任務；事件日誌寫到臨時目錄，
+/// 執行日誌為同名 `.journal` 文件。返回引擎與事件日誌路徑，調用方用完後刪除。需在 tokio 運行時中調用。
+pub fn assemble(name: &str, config: config::EngineConfig, env: Environment) -> Result<(ExecutionEngine, PathBuf), String> {
+    config.validate()?;
+    let path = std::env::temp_dir().join(format!("arb-sim-{}-{}.jsonl", name, std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let events = events::EventStore::open(&path.to_string_lossy(), Arc::clone(&env.clock)).map_err(|e| e.to_string())?;
+    let journal_path = path.with_extension("journal");
+    let _ = std::fs::remove_file(&journal_path);
+    let journal = journal::Journal::open(&journal_path.to_string_lossy()).map_err(|e| e.to_string())?;
+    let mut chains = BTreeMap::new();
+    for (name, chain_config) in config.chain_configs() {
+        chains.insert(name.clone(), chain::ChainStack::connect(&name, &chain_config, &config.bundle, env.vars.as_ref())?);
+    }
+    let routing = tls::peer_connector(&config.tls).and_then(|connector| routing::LegRouter::new(config.routing.clone(), connector, env.vars.as_ref()))?;
+    let auth = client_auth::Authenticator::new(config.client_auth.clone(), env.vars.as_ref())?;
+    let engine = ExecutionEngine::new(config, None, None, events, journal, chains, routing, auth, env)?;
+    Ok((engine, path))
+}
+
+/// 基準使用的引擎：三家模擬交易所、模擬時鐘與固定種子，關閉執行隊列，請求在調用方任務上直接執行。
+pub fn simulated_engine(seed: u64) -> Result<(ExecutionEngine, PathBuf), String> {
+    let mut config = config::EngineConfig::default();
+    config.execution_queue.enabled = false;
+    for exchange in EXCHANGES {
+        config.exchanges.entry(exchange.to_string()).or_default();
+    }
+    assemble(&format!("bench-{}", seed), config, Environment::simulated(START_MS, seed))
+}
+
+// 一筆資金費率套利請求的 JSON 編碼，時間戳與 START_MS 一致
+pub fn execute_payload(amount: f64) -> Vec<u8> {
+    serde_json::to_vec(&serde_json::json!({
+        "strategy_id": "bench",
+        "symbol": "BTCUSDT",
+        "primary_exchange": "binance",
+        "secondary_exchange": "bybit",
+        "amount": amount,
+        "priority": 5,
+        "timestamp": START_MS.to_string()
+    }))
+    .expect("請求可序列化")
+}
+
+/// 經與客戶端連接相同的路徑解析、鑑權並分派一條消息，響應按 `encoding` 寫入 `output`；
+/// 每次使用新的連接狀態（未認證）。
+pub async fn dispatch(engine: &ExecutionEngine, encoding: wire::Encoding, payload: &[u8], output: &mut Vec<u8>) {
+    server::dispatch(engine, encoding, payload, output).await
+}
+
+/// 在 `socket` 上按客戶端連接處理消息直到對端關閉，供傳輸層基準在回環 TCP 與 Unix 域套接字上對比往返延遲。
+pub async fn serve_connection<S: AsyncRead + AsyncWrite + Unpin>(engine: &ExecutionEngine, socket: S) {
+    server::handle_connection(socket, engine).await
+}
+
+/// 在 `path` 上以默認槽位配置啟動共享內存傳輸，返回值釋放時停止並刪除文件；需在 tokio 運行時中調用。
+pub fn serve_shared_memory(engine: Arc<ExecutionEngine>, path: &str) -> Result<shared_memory::Server, String> {
+    let config = config::SharedMemoryConfig {
+        enabled: true,
+        path: path.to_string(),
+        ..Default::default()
+    };
+    shared_memory::Server::start(engine, &config)
+}
+
+/// 機會評估的輸入：若干交易對在三家交易所的資金費率樣本，按固定種子生成。
+pub struct OpportunitySet {
+    scanner: Scanner,
+    samples: Vec<FundingSample>,
+}
+
+impl OpportunitySet {
+    pub fn generate(symbols: usize, seed: u64) -> Self {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut samples = Vec::with_capacity(symbols * EXCHANGES.len());
+        for n in 0..symbols {
+            for exchange in EXCHANGES {
+                let funding_rate = rng.gen_range(-0.0005..0.001);
+                samples.push(FundingSample {
+                    exchange: exchange.to_string(),
+                    symbol: format!("S{}USDT", n),
+                    sampled_at_ms: START_MS,
+                    funding_rate,
+                    predicted_rate: funding_rate + rng.gen_range(-0.0001..0.0001),
+                });
+            }
+        }
+        Self {
+            scanner: Scanner::new(ScannerConfig::default()),
+            samples,
+        }
+    }
+
+    // 每輪兩兩比較的交易所組合數
+    pub fn pairs(&self) -> usize {
+        self.samples.len() / EXCHANGES.len() * EXCHANGES.len() * (EXCHANGES.len() - 1) / 2
+    }
+
+    // 評估一輪，返回達標的機會數
+    pub fn rank(&self) -> usize {
+        self.scanner.rank(&self.samples, |_| TAKER_FEE).len()
+    }
+}
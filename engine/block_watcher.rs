@@ -0,0 +1,343 @@
+use super::config::{Severity, SubscriptionConfig};
+use super::events::EngineEvent;
+use super::wallet::WalletManager;
+use super::ExecutionEngine;
+use futures::StreamExt;
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::watch;
+use tracing::{error, info, warn};
+use web3::api::SubscriptionId;
+use web3::transports::WebSocket;
+use web3::types::{BlockHeader, BlockId, BlockNumber, Log, TransactionReceipt, H256};
+use web3::{DuplexTransport, Transport};
+
+// Transfer(address,address,uint256)
+const TRANSFER_TOPIC: &str = "0xddf252ad1be2c89b69c2b068fc378daa952ba7f163c4a11628f55a4df523b3ef";
+
+// 已確認、仍在重組監控窗口內的交易
+#[derive(Debug, Clone, Serialize)]
+pub struct SettledTx {
+    pub tx_hash: H256,
+    pub block_number: u64,
+    pub block_hash: H256,
+    // 所在區塊被重組且尚未重新打包
+    pub reorged: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct WatcherStatus {
+    pub connected: bool,
+    pub head: Option<u64>,
+    pub reorgs: u64,
+    pub settled: Vec<SettledTx>,
+}
+
+/// 新區塊與結算事件訂閱：在線時等待回執的任務隨新區塊喚醒，已確認的交易按區塊哈希核對重組。
+pub struct BlockWatcher {
+    config: SubscriptionConfig,
+    connected: AtomicBool,
+    // 最新區塊號，0 表示尚未收到
+    head: watch::Sender<u64>,
+    // 規範鏈上最近的區塊：區塊號 -> 哈希
+    canonical: Mutex<BTreeMap<u64, H256>>,
+    settled: Mutex<Vec<SettledTx>>,
+    reorgs: AtomicU64,
+}
+
+impl BlockWatcher {
+    pub fn new(config: SubscriptionConfig) -> Self {
+        Self {
+            config,
+            connected: AtomicBool::new(false),
+            head: watch::Sender::new(0),
+            canonical: Mutex::new(BTreeMap::new()),
+            settled: Mutex::new(Vec::new()),
+            reorgs: AtomicU64::new(0),
+        }
+    }
+
+    // 訂閱在線時的最新區塊號
+    pub fn head(&self) -> Option<u64> {
+        let head = *self.head.borrow();
+        (self.connected.load(Ordering::Relaxed) && head > 0).then_some(head)
+    }
+
+    // 在查詢回執前取得，避免錯過查詢期間到達的新區塊
+    pub fn subscribe(&self) -> watch::Receiver<u64> {
+        self.head.subscribe()
+    }
+
+    // 等待下一個新區塊或相關事件；訂閱不在線時按 poll 休眠
+    pub async fn wait(&self, heads: &mut watch::Receiver<u64>, poll: Duration) {
+        if self.connected.load(Ordering::Relaxed) {
+            let _ = tokio::time::timeout(Duration::from_millis(self.config.fallback_poll_ms), heads.changed()).await;
+        } else {
+            tokio::time::sleep(poll).await;
+        }
+    }
+
+    // 未配置訂閱時無法感知重組，不做跟踪
+    pub fn track(&self, receipt: &TransactionReceipt) {
+        let (Some(block_number), Some(block_hash)) = (receipt.block_number, receipt.block_hash) else {
+            return;
+        };
+        if self.config.ws_url.is_none() {
+            return;
+        }
+        let mut settled = self.settled.lock().unwrap();
+        settled.retain(|tx| tx.tx_hash != receipt.transaction_hash);
+        settled.push(SettledTx {
+            tx_hash: receipt.transaction_hash,
+            block_number: block_number.as_u64(),
+            block_hash,
+            reorged: false,
+        });
+    }
+
+    pub fn status(&self) -> WatcherStatus {
+        WatcherStatus {
+            connected: self.connected.load(Ordering::Relaxed),
+            head: self.head(),
+            reorgs: self.reorgs.load(Ordering::Relaxed),
+            settled: self.settled.lock().unwrap().clone(),
+        }
+    }
+
+    /// 記錄新區塊並喚醒等待回執的任務；父哈希或同高度哈希與本地記錄不符時返回 true（發生重組）。
+    /// 超出監控窗口的區塊與已確認交易一併清理。
+    pub fn advance(&self, number: u64, hash: H256, parent_hash: H256) -> bool {
+        let reorged = {
+            let mut canonical = self.canonical.lock().unwrap();
+            let reorged = canonical.get(&number.saturating_sub(1)).is_some_and(|known| *known != parent_hash)
+                || canonical.get(&number).is_some_and(|known| *known != hash);
+            // 新區塊之上的記錄已不在規範鏈上
+            canonical.retain(|height, _| *height < number);
+            canonical.insert(number, hash);
+            let oldest = number.saturating_sub(self.config.reorg_window_blocks);
+            canonical.retain(|height, _| *height >= oldest);
+            reorged
+        };
+        let finalized = number.saturating_sub(self.config.reorg_window_blocks);
+        self.settled.lock().unwrap().retain(|tx| tx.block_number > finalized);
+        self.head.send_replace(number);
+        reorged
+    }
+
+    // 分叉點：從新區塊往下逐個比對本地記錄與節點返回的區塊哈希，返回第一個被替換的高度
+    async fn fork_point(&self, wallets: &WalletManager, number: u64) -> u64 {
+        let heights: Vec<u64> = self.canonical.lock().unwrap().range(..number).rev().map(|(height, _)| *height).collect();
+        let mut fork = number;
+        for height in heights {
+            let block = wallets.web3().eth().block(BlockId::Number(BlockNumber::Number(height.into()))).await;
+            let Ok(Some(block)) = block else { break };
+            let known = self.canonical.lock().unwrap().get(&height).copied();
+            match block.hash {
+                Some(hash) if Some(hash) == known => break,
+                Some(hash) => {
+                    self.canonical.lock().unwrap().insert(height, hash);
+                    fork = height;
+                }
+                None => break,
+            }
+        }
+        fork
+    }
+
+    // 重新核驗分叉點之後確認的交易，以及之前被重組、尚未重新打包的交易
+    async fn reverify(&self, engine: &ExecutionEngine, chain: &str, wallets: &WalletManager, from_block: u64) {
+        let affected: Vec<SettledTx> = self
+            .settled
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|tx| tx.reorged || tx.block_number >= from_block)
+            .cloned()
+            .collect();
+        for tx in affected {
+            let receipt = match wallets.web3().eth().transaction_receipt(tx.tx_hash).await {
+                Ok(receipt) => receipt,
+                Err(error) => {
+                    warn!(chain, tx_hash = ?tx.tx_hash, %error, "重組後查詢回執失敗，下一區塊重試");
+                    continue;
+                }
+            };
+            let included = receipt.as_ref().and_then(|receipt| Some((receipt.block_number?.as_u64(), receipt.block_hash?, receipt.status)));
+            match included {
+                // 所在區塊未受影響
+                Some((_, block_hash, _)) if block_hash == tx.block_hash && !tx.reorged => {}
+                Some((block_number, block_hash, status)) => {
+                    self.update(tx.tx_hash, |settled| {
+                        settled.block_number = block_number;
+                        settled.block_hash = block_hash;
+                        settled.reorged = false;
+                    });
+                    if status == Some(1.into()) {
+                        info!(chain, tx_hash = ?tx.tx_hash, from = tx.block_number, to = block_number, "重組後交易已重新打包");
+                    } else {
+                        let message = format!("{:?} 重組後在區塊 {} 重新打包但已回滾，鏈上腿結算不成立", tx.tx_hash, block_number);
+                        error!(chain, %message, "鏈上結算被重組");
+                        engine.alert(Severity::Critical, "鏈上結算被重組", message);
+                    }
+                    self.record(engine, chain, &tx, Some(block_number));
+                }
+                None if tx.reorged => {}
+                None => {
+                    self.update(tx.tx_hash, |settled| settled.reorged = true);
+                    // 交易退回內存池或被丟棄，本地 nonce 可能已超前，下次發送前從鏈上恢復
+                    wallets.resync_nonces().await;
+                    let message = format!("{:?} 所在區塊 {} 已被重組出規範鏈，等待重新打包", tx.tx_hash, tx.block_number);
+                    error!(chain, %message, "鏈上結算被重組");
+                    engine.alert(Severity::Critical, "鏈上結算被重組", message);
+                    self.record(engine, chain, &tx, None);
+                }
+            }
+        }
+    }
+
+    fn update(&self, tx_hash: H256, apply: impl FnOnce(&mut SettledTx)) {
+        if let Some(settled) = self.settled.lock().unwrap().iter_mut().find(|settled| settled.tx_hash == tx_hash) {
+            apply(settled);
+        }
+    }
+
+    fn record(&self, engine: &ExecutionEngine, chain: &str, tx: &SettledTx, reincluded_block: Option<u64>) {
+        let tx_hash = format!("{:?}", tx.tx_hash);
+        engine.events.append(EngineEvent::SettlementReorged {
+            chain: chain.to_string(),
+            execution_id: engine.events.execution_for_tx(&tx_hash),
+            tx_hash,
+            block_number: tx.block_number,
+            reincluded_block,
+        });
+    }
+
+    async fn on_head(&self, engine: &ExecutionEngine, chain: &str, wallets: &WalletManager, header: BlockHeader) {
+        let (Some(number), Some(hash)) = (header.number, header.hash) else { return };
+        let number = number.as_u64();
+        if self.advance(number, hash, header.parent_hash) {
+            let fork = self.fork_point(wallets, number).await;
+            self.reorgs.fetch_add(1, Ordering::Relaxed);
+            warn!(chain, head = number, fork, "檢測到區塊重組，重新核驗已確認的交易");
+            self.reverify(engine, chain, wallets, fork).await;
+        } else if self.settled.lock().unwrap().iter().any(|tx| tx.reorged) {
+            self.reverify(engine, chain, wallets, u64::MAX).await;
+        }
+    }
+
+    // 節點在重組時以 removed 重發已推送的日誌
+    async fn on_log(&self, engine: &ExecutionEngine, chain: &str, wallets: &WalletManager, log: Log) {
+        let Some(tx_hash) = log.transaction_hash else { return };
+        let tracked = self.settled.lock().unwrap().iter().find(|tx| tx.tx_hash == tx_hash).map(|tx| tx.block_number);
+        match (log.removed, tracked) {
+            (Some(true), Some(block_number)) => {
+                warn!(chain, ?tx_hash, block_number, "已確認交易的日誌被移除，重新核驗");
+                self.reverify(engine, chain, wallets, block_number).await;
+            }
+            // 結算轉賬已到達，提前喚醒等待回執的任務
+            (Some(false) | None, None) => self.head.send_modify(|_| {}),
+            _ => {}
+        }
+    }
+}
+
+// 每條配置了 subscription.ws_url 且加載了熱錢包的鏈各自維持一個訂閱，斷線後按間隔重連
+pub fn spawn(engine: Arc<ExecutionEngine>) {
+    for (name, chain) in &engine.chains {
+        let Some(wallets) = chain.wallets.clone() else { continue };
+        let Some(url) = wallets.watcher().config.ws_url.clone() else { continue };
+        let mut addresses = wallets.addresses();
+        if let Some(flash_loan) = &chain.flash_loan {
+            addresses.push(flash_loan.profit_recipient());
+        }
+        let engine = Arc::clone(&engine);
+        let name = name.clone();
+        tokio::spawn(async move {
+            let watcher = wallets.watcher();
+            let reconnect = Duration::from_secs(watcher.config.reconnect_secs);
+            loop {
+                if let Err(error) = watch(&engine, &name, &wallets, &url, &addresses).await {
+                    warn!(chain = %name, %error, "區塊訂閱中斷，稍後重連");
+                }
+                watcher.connected.store(false, Ordering::Relaxed);
+                tokio::time::sleep(reconnect).await;
+            }
+        });
+    }
+}
+
+async fn subscribe(transport: &WebSocket, params: Vec<Value>) -> Result<SubscriptionId, String> {
+    let id = transport
+        .execute("eth_subscribe", params)
+        .await
+        .map_err(|e| format!("eth_subscribe 失敗: {}", e))?;
+    let id: String = serde_json::from_value(id).map_err(|e| format!("無效的訂閱 ID: {}", e))?;
+    Ok(SubscriptionId::from(id))
+}
+
+async fn watch(engine: &ExecutionEngine, chain: &str, wallets: &WalletManager, url: &str, addresses: &[web3::types::Address]) -> Result<(), String> {
+    let transport = WebSocket::new(url).await.map_err(|e| format!("連接 {} 失敗: {}", url, e))?;
+    let heads_id = subscribe(&transport, vec![json!("newHeads")]).await?;
+    // 轉入熱錢包或收益地址的 ERC20 轉賬：兌換產出、閃電貸收益與借貸贖回
+    let recipients: Vec<H256> = addresses.iter().map(|address| H256::from(*address)).collect();
+    let logs_id = subscribe(&transport, vec![json!("logs"), json!({"topics": [TRANSFER_TOPIC, null, recipients]})]).await?;
+    let mut heads = transport.subscribe(heads_id).map_err(|e| format!("註冊新區塊訂閱失敗: {}", e))?;
+    let mut logs = transport.subscribe(logs_id).map_err(|e| format!("註冊事件訂閱失敗: {}", e))?;
+    let watcher = wallets.watcher();
+    watcher.connected.store(true, Ordering::Relaxed);
+    info!(chain, %url, "已訂閱新區塊與結算事件");
+    loop {
+        tokio::select! {
+            head = heads.next() => {
+                let head = head.ok_or("新區塊訂閱已關閉")?;
+                match serde_json::from_value::<BlockHeader>(head) {
+                    Ok(header) => watcher.on_head(engine, chain, wallets, header).await,
+                    Err(error) => warn!(chain, %error, "無法解析新區塊"),
+                }
+            }
+            log = logs.next() => {
+                let log = log.ok_or("事件訂閱已關閉")?;
+                if let Ok(log) = serde_json::from_value::<Log>(log) {
+                    watcher.on_log(engine, chain, wallets, log).await;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config;
+
+    // 同高度出現另一區塊或父哈希與本地記錄不符時判定為重組；確認後超出監控窗口的交易不再跟踪
+    #[test]
+    fn block_watcher_detects_reorgs_and_finalizes_settled_transactions() {
+        use web3::types::{TransactionReceipt, H256};
+        let watcher = BlockWatcher::new(config::SubscriptionConfig {
+            ws_url: Some("ws://127.0.0.1:8546".to_string()),
+            reorg_window_blocks: 3,
+            ..Default::default()
+        });
+        let hash = H256::from_low_u64_be;
+        assert!(!watcher.advance(10, hash(10), hash(9)));
+        assert!(!watcher.advance(11, hash(11), hash(10)));
+        watcher.track(&TransactionReceipt {
+            transaction_hash: hash(99),
+            block_number: Some(11.into()),
+            block_hash: Some(hash(11)),
+            ..Default::default()
+        });
+        assert!(watcher.advance(11, hash(111), hash(10)));
+        assert!(watcher.advance(12, hash(12), hash(11)));
+        assert!(!watcher.advance(13, hash(13), hash(12)));
+        let status = watcher.status();
+        assert_eq!((status.head, status.settled.len()), (None, 1));
+        assert!(!watcher.advance(14, hash(14), hash(13)));
+        assert!(watcher.status().settled.is_empty());
+    }
+}
@@ -0,0 +1,137 @@
+use super::storage::HistoryStore;
+use super::{ArbitrageRequest, ArbitrageResponse, ChildOrder};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tracing::{error, info, warn};
+
+const DEFERRED_QUEUE_CAPACITY: usize = 1024;
+
+#[derive(Debug, Clone)]
+pub struct ExecutionRecord {
+    pub execution_id: String,
+    pub strategy_id: String,
+    pub symbol: String,
+    pub primary_exchange: String,
+    pub secondary_exchange: String,
+    pub amount: f64,
+    pub priority: i32,
+    pub requested_at: String,
+    pub completed_at_ms: i64,
+    pub status: String,
+    pub profit: Option<f64>,
+    pub fees: f64,
+    pub execution_time: String,
+    pub error_message: Option<String>,
+    pub fast_path: bool,
+    pub orders: Vec<ChildOrder>,
+}
+
+impl ExecutionRecord {
+    pub fn new(
+        execution_id: String,
+        request: &ArbitrageRequest,
+        response: &ArbitrageResponse,
+        fees: f64,
+        orders: Vec<ChildOrder>,
+        completed_at_ms: i64,
+    ) -> Self {
+        Self {
+            execution_id,
+            strategy_id: request.strategy_id.clone(),
+            symbol: request.symbol.clone(),
+            primary_exchange: request.primary_exchange.clone(),
+            secondary_exchange: request.secondary_exchange.clone(),
+            amount: request.amount,
+            priority: request.priority,
+            requested_at: request.timestamp.clone(),
+            completed_at_ms,
+            status: response.status.clone(),
+            profit: response.profit,
+            fees,
+            execution_time: response.execution_time.clone(),
+            error_message: response.error_message.clone(),
+            fast_path: request.fast_path,
+            orders,
+        }
+    }
+}
+
+pub struct Bookkeeper {
+    deferred: mpsc::Sender<ExecutionRecord>,
+    history: Option<Arc<HistoryStore>>,
+    // 已延後但尚未寫入的記錄數
+    pending: Arc<AtomicUsize>,
+}
+
+impl Bookkeeper {
+    // 啟動後台記帳任務，需在 tokio 運行時內調用
+    pub fn spawn(history: Option<Arc<HistoryStore>>) -> Self {
+        let (deferred, mut rx) = mpsc::channel::<ExecutionRecord>(DEFERRED_QUEUE_CAPACITY);
+        let background_history = history.clone();
+        let pending = Arc::new(AtomicUsize::new(0));
+        let background_pending = Arc::clone(&pending);
+        tokio::spawn(async move {
+            while let Some(record) = rx.recv().await {
+                write(background_history.as_deref(), &record).await;
+                background_pending.fetch_sub(1, Ordering::AcqRel);
+            }
+        });
+        Self { deferred, history, pending }
+    }
+
+    pub async fn record(&self, record: ExecutionRecord) {
+        write(self.history.as_deref(), &record).await;
+    }
+
+    // 隊列已滿時改由獨立任務寫入，不阻塞快速通道
+    pub fn defer(&self, record: ExecutionRecord) {
+        self.pending.fetch_add(1, Ordering::AcqRel);
+        if let Err(e) = self.deferred.try_send(record) {
+            warn!("記帳隊列已滿，改由獨立任務寫入");
+            let history = self.history.clone();
+            let pending = Arc::clone(&self.pending);
+            let record = e.into_inner();
+            tokio::spawn(async move {
+                write(history.as_deref(), &record).await;
+                pending.fetch_sub(1, Ordering::AcqRel);
+            });
+        }
+    }
+
+    // 等待所有延後的記錄寫入完成
+    pub async fn flush(&self) {
+        let pending = self.pending.load(Ordering::Acquire);
+        if pending > 0 {
+            info!(pending, "等待延後的記帳寫入");
+        }
+        while self.pending.load(Ordering::Acquire) > 0 {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    }
+}
+
+async fn write(history: Option<&HistoryStore>, record: &ExecutionRecord) {
+    if let Some(history) = history {
+        if let Err(e) = history.insert(record).await {
+            error!(error = %e, execution_id = %record.execution_id, "寫入歷史記錄失敗");
+        }
+    }
+    info!(
+        target: "bookkeeping",
+        execution_id = %record.execution_id,
+        strategy_id = %record.strategy_id,
+        symbol = %record.symbol,
+        primary_exchange = %record.primary_exchange,
+        secondary_exchange = %record.secondary_exchange,
+        amount = record.amount,
+        status = %record.status,
+        profit = ?record.profit,
+        fees = record.fees,
+        execution_time = %record.execution_time,
+        error = ?record.error_message,
+        fast_path = record.fast_path,
+        "執行記錄"
+    );
+}
@@ -0,0 +1,257 @@
+use super::config::BundleConfig;
+use super::environment::Vars;
+use super::rpc::RpcTransport;
+use serde_json::{json, Value};
+use std::str::FromStr;
+use std::time::Duration;
+use tracing::{debug, info, warn};
+use web3::signing::{hash_message, keccak256, Key, SecretKey, SecretKeyRef};
+use web3::types::{SignedTransaction, TransactionParameters, H256, U256};
+use web3::Web3;
+
+const INCLUSION_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+pub struct BundleSubmitter {
+    config: BundleConfig,
+    client: reqwest::Client,
+    // 中繼按該身份累積信譽，不持有資金
+    identity: SecretKey,
+}
+
+impl BundleSubmitter {
+    pub fn connect(config: &BundleConfig, vars: &dyn Vars) -> Result<Option<Self>, String> {
+        if !config.enabled {
+            return Ok(None);
+        }
+        let key = vars
+            .var(&config.signing_key_env)
+            .ok_or_else(|| format!("bundle 未設置環境變量 {}", config.signing_key_env))?;
+        let identity = SecretKey::from_str(key.trim().trim_start_matches("0x"))
+            .map_err(|e| format!("bundle 身份私鑰無效: {}", e))?;
+        info!(
+            relay = %config.relay,
+            url = %config.relay_url,
+            identity = ?SecretKeyRef::new(&identity).address(),
+            "已啟用私有 bundle 提交"
+        );
+        Ok(Some(Self {
+            config: config.clone(),
+            client: reqwest::Client::new(),
+            identity,
+        }))
+    }
+
+    pub fn cap_priority_fee(&self, tx: TransactionParameters) -> TransactionParameters {
+        let cap = U256::from((self.config.max_priority_fee_gwei * 1e9) as u64);
+        TransactionParameters {
+            max_priority_fee_per_gas: tx.max_priority_fee_per_gas.map(|fee| fee.min(cap)),
+            ..tx
+        }
+    }
+
+    // 返回交易哈希時交易已被打包（bundle）或已被節點接收（公開發送）
+    pub async fn submit(&self, web3: &Web3<RpcTransport>, signed: &SignedTransaction) -> Result<H256, String> {
+        let eth = web3.eth();
+        let hash = signed.transaction_hash;
+        let head = eth.block_number().await.map_err(|e| format!("查詢區塊高度失敗: {}", e))?.as_u64();
+        let (first, last) = (head + 1, head + self.config.max_blocks);
+        let raw = format!("0x{}", hex::encode(&signed.raw_transaction.0));
+        match self.send_bundle(&raw, first, last).await {
+            Ok(()) => {
+                debug!(tx_hash = ?hash, first, last, relay = %self.config.relay, "bundle 已提交");
+                loop {
+                    // 先讀區塊高度再查回執，保證最後一個目標區塊也被檢查到
+                    let current = eth.block_number().await.map_err(|e| e.to_string())?.as_u64();
+                    if eth.transaction_receipt(hash).await.map_err(|e| e.to_string())?.is_some() {
+                        info!(tx_hash = ?hash, block = current, "bundle 已被打包");
+                        return Ok(hash);
+                    }
+                    if current >= last {
+                        break;
+                    }
+                    tokio::time::sleep(INCLUSION_POLL_INTERVAL).await;
+                }
+                warn!(tx_hash = ?hash, first, last, "bundle 未在目標區塊內被打包，改為公開發送");
+            }
+            Err(error) => warn!(tx_hash = ?hash, %error, "bundle 提交失敗，改為公開發送"),
+        }
+        eth.send_raw_transaction(signed.raw_transaction.clone())
+            .await
+            .map_err(|e| e.to_string())
+    }
+
+    async fn send_bundle(&self, raw: &str, first: u64, last: u64) -> Result<(), String> {
+        let bodies: Vec<Value> = match self.config.relay.as_str() {
+            // eth_sendBundle 每次只針對一個區塊
+            "flashbots" => (first..=last)
+                .map(|block| {
+                    rpc_request("eth_sendBundle", json!({"txs": [raw], "blockNumber": format!("{:#x}", block)}))
+                })
+                .collect(),
+            _ => vec![rpc_request(
+                "mev_sendBundle",
+                json!({
+                    "version": "v0.1",
+                    "inclusion": {"block": format!("{:#x}", first), "maxBlock": format!("{:#x}", last)},
+                    "body": [{"tx": raw, "canRevert": false}],
+                    "privacy": {"hints": self.config.mev_share_hints},
+                }),
+            )],
+        };
+        for body in bodies {
+            self.post(body.to_string()).await?;
+        }
+        Ok(())
+    }
+
+    // 簽名內容為請求體 keccak256 的十六進制字符串，按 EIP-191 簽名
+    async fn post(&self, body: String) -> Result<Value, String> {
+        let identity = SecretKeyRef::new(&self.identity);
+        let digest = format!("0x{}", hex::encode(keccak256(body.as_bytes())));
+        let signature = identity
+            .sign_message(hash_message(digest).as_bytes())
+            .map_err(|e| format!("bundle 請求簽名失敗: {}", e))?;
+        let mut bytes = [signature.r.as_bytes(), signature.s.as_bytes()].concat();
+        bytes.push(signature.v as u8 + 27);
+        let response: Value = self
+            .client
+            .post(&self.config.relay_url)
+            .header("Content-Type", "application/json")
+            .header("X-Flashbots-Signature", format!("{:?}:0x{}", identity.address(), hex::encode(bytes)))
+            .body(body)
+            .send()
+            .await
+            .map_err(|e| format!("連接中繼失敗: {}", e))?
+            .json()
+            .await
+            .map_err(|e| format!("中繼響應無效: {}", e))?;
+        if let Some(error) = response.get("error") {
+            return Err(format!("中繼返回錯誤: {}", error));
+        }
+        Ok(response)
+    }
+}
+
+fn rpc_request(method: &str, params: Value) -> Value {
+    json!({"jsonrpc": "2.0", "id": 1, "method": method, "params": [params]})
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::environment::FixedVars;
+    use crate::mock_exchange_e2e::{mined_receipt, rpc_node};
+    use crate::{config, rpc};
+    use std::sync::Arc;
+    use std::time::Instant;
+
+    // 私有 bundle：flashbots 按目標區塊逐個發送 eth_sendBundle，mev_share 以一個 mev_sendBundle 覆蓋區塊範圍；
+    // 請求體由身份私鑰按 EIP-191 簽名。目標區塊內被打包時不公開發送，未被打包或中繼出錯時改為公開發送
+    #[tokio::test]
+    async fn bundles_target_blocks_and_fall_back_to_public_submission() {
+        use web3::types::{SignedTransaction, TransactionParameters, H256, U256};
+        let identity = "0x4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318";
+        let vars = FixedVars::new([("E2E_BUNDLE_IDENTITY", identity)]);
+        let identity_address = web3::signing::Key::address(&web3::signing::SecretKeyRef::new(&identity[2..].parse().unwrap()));
+
+        // 中繼記錄請求體與簽名頭；failing 為 true 時返回 JSON-RPC 錯誤
+        let received = Arc::new(std::sync::Mutex::new(Vec::<(String, serde_json::Value)>::new()));
+        let failing = Arc::new(std::sync::atomic::AtomicBool::new(false));
+        let (relay_received, relay_failing) = (Arc::clone(&received), Arc::clone(&failing));
+        let relay = axum::Router::new().route(
+            "/",
+            axum::routing::post(move |headers: axum::http::HeaderMap, body: String| async move {
+                let signature = headers["X-Flashbots-Signature"].to_str().unwrap().to_string();
+                relay_received.lock().unwrap().push((signature, serde_json::from_str(&body).unwrap()));
+                if relay_failing.load(std::sync::atomic::Ordering::SeqCst) {
+                    axum::Json(serde_json::json!({"jsonrpc": "2.0", "id": 1, "error": {"code": -32000, "message": "bundle rejected"}}))
+                } else {
+                    axum::Json(serde_json::json!({"jsonrpc": "2.0", "id": 1, "result": {"bundleHash": "0x01"}}))
+                }
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let relay_url = format!("http://{}/", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, relay).await });
+
+        // 節點：(區塊高度, 是否已打包, 公開發送次數)；每次查詢區塊高度後前進一個區塊
+        let chain = Arc::new(std::sync::Mutex::new((0x10u64, true, 0usize)));
+        let node = Arc::clone(&chain);
+        let url = rpc_node(Arc::new(move |method, params| {
+            let mut chain = node.lock().unwrap();
+            Ok(match method {
+                "eth_blockNumber" => {
+                    chain.0 += 1;
+                    serde_json::json!(format!("{:#x}", chain.0 - 1))
+                }
+                "eth_getTransactionReceipt" if chain.1 => mined_receipt(&params[0]),
+                "eth_getTransactionReceipt" => serde_json::Value::Null,
+                "eth_sendRawTransaction" => {
+                    chain.2 += 1;
+                    serde_json::json!(format!("{:?}", H256::from_low_u64_be(0xb0)))
+                }
+                other => return Err(format!("unexpected {}", other)),
+            })
+        }))
+        .await;
+        let web3 = web3::Web3::new(rpc::RpcTransport::connect("e2e", &url, &config::RpcConfig::default()).unwrap());
+        let raw = vec![0x02, 0xf8, 0x01];
+        let signed = SignedTransaction {
+            message_hash: H256::zero(),
+            v: 0,
+            r: H256::zero(),
+            s: H256::zero(),
+            raw_transaction: raw.clone().into(),
+            transaction_hash: H256::from(web3::signing::keccak256(&raw)),
+        };
+        let bundle = config::BundleConfig {
+            enabled: true,
+            relay_url,
+            signing_key_env: "E2E_BUNDLE_IDENTITY".to_string(),
+            max_blocks: 2,
+            ..Default::default()
+        };
+        assert!(BundleSubmitter::connect(&config::BundleConfig { enabled: false, ..bundle.clone() }, &vars).unwrap().is_none());
+        let submitter = BundleSubmitter::connect(&bundle, &vars).unwrap().unwrap();
+        let capped = submitter.cap_priority_fee(TransactionParameters {
+            max_priority_fee_per_gas: Some(U256::from(5_000_000_000u64)),
+            ..Default::default()
+        });
+        assert_eq!(capped.max_priority_fee_per_gas, Some(U256::from(2_000_000_000u64)));
+
+        // 下一區塊起的兩個目標區塊各一個 eth_sendBundle，被打包後直接返回
+        assert_eq!(submitter.submit(&web3, &signed).await.unwrap(), signed.transaction_hash);
+        let requests = std::mem::take(&mut *received.lock().unwrap());
+        let blocks: Vec<&str> = requests.iter().map(|(_, body)| body["params"][0]["blockNumber"].as_str().unwrap()).collect();
+        assert_eq!(blocks, ["0x11", "0x12"]);
+        assert!(requests.iter().all(|(_, body)| body["method"] == "eth_sendBundle" && body["params"][0]["txs"][0] == "0x02f801"));
+        assert_eq!(chain.lock().unwrap().2, 0);
+        let (header, body) = &requests[0];
+        let (address, signature) = header.split_once(':').unwrap();
+        assert_eq!(address, format!("{:?}", identity_address));
+        let signature = hex::decode(&signature[2..]).unwrap();
+        let digest = format!("0x{}", hex::encode(web3::signing::keccak256(body.to_string().as_bytes())));
+        let signer = web3::signing::recover(web3::signing::hash_message(digest).as_bytes(), &signature[..64], signature[64] as i32 - 27).unwrap();
+        assert_eq!(signer, identity_address);
+
+        // 超過最後一個目標區塊仍未打包：公開發送
+        *chain.lock().unwrap() = (0x20, false, 0);
+        let submitter = BundleSubmitter::connect(&config::BundleConfig { relay: "mev_share".to_string(), ..bundle.clone() }, &vars).unwrap().unwrap();
+        assert_eq!(submitter.submit(&web3, &signed).await.unwrap(), H256::from_low_u64_be(0xb0));
+        let requests = std::mem::take(&mut *received.lock().unwrap());
+        assert_eq!(requests.len(), 1);
+        let params = &requests[0].1["params"][0];
+        assert_eq!(requests[0].1["method"], "mev_sendBundle");
+        assert_eq!(params["inclusion"], serde_json::json!({"block": "0x21", "maxBlock": "0x22"}));
+        assert_eq!(params["privacy"]["hints"], serde_json::json!(["hash"]));
+        assert_eq!(chain.lock().unwrap().2, 1);
+
+        // 中繼拒絕：不等待目標區塊，立即公開發送
+        failing.store(true, std::sync::atomic::Ordering::SeqCst);
+        *chain.lock().unwrap() = (0x30, false, 0);
+        let started = Instant::now();
+        submitter.submit(&web3, &signed).await.unwrap();
+        assert!(started.elapsed() < Duration::from_millis(500));
+        assert_eq!(chain.lock().unwrap().2, 1);
+    }
+}
@@ -0,0 +1,177 @@
+use super::config::{BundleConfig, ChainConfig, RpcConfig};
+use super::dex::DexExecutor;
+use super::environment::Vars;
+use super::flash_loan::FlashLoanExecutor;
+use super::gas::{GasOptimizer, GasQuote};
+use super::market_data;
+use super::rpc::RpcTransport;
+use super::wallet::WalletManager;
+use std::sync::Arc;
+use tracing::info;
+
+pub struct ChainStack {
+    pub name: String,
+    pub chain_id: u64,
+    // 1 個原生代幣折合的 ETH
+    eth_per_native: f64,
+    pub flash_loan: Option<FlashLoanExecutor>,
+    pub dex: Option<DexExecutor>,
+    // 未配置 RPC 時為 None；借貸市場等直接發送交易的模塊使用
+    pub wallets: Option<Arc<WalletManager>>,
+    pub gas_optimizer: GasOptimizer,
+    // 未配置 rpc_url 時為 None
+    pub rpc: Option<RpcTransport>,
+}
+
+impl ChainStack {
+    pub fn connect(name: &str, config: &ChainConfig, bundle: &BundleConfig, vars: &dyn Vars) -> Result<Self, String> {
+        let flash_loan_config = config.resolved_flash_loan();
+        // Flashbots 中繼只服務以太坊主網，其他鏈直接公開發送
+        let bundle = BundleConfig {
+            enabled: bundle.enabled && config.chain_id == 1,
+            ..bundle.clone()
+        };
+        let rpc = config.rpc_url.as_deref().map(|url| RpcTransport::connect(name, url, &config.rpc)).transpose()?;
+        let wallets = WalletManager::connect(&flash_loan_config, rpc.as_ref(), &bundle, &config.tx_simulation, &config.subscription, vars)
+            .map_err(|e| format!("加載熱錢包失敗: {}", e))?;
+        let flash_loan = FlashLoanExecutor::connect(&flash_loan_config, wallets.clone())
+            .map_err(|e| format!("初始化鏈上閃電貸失敗: {}", e))?;
+        let dex = DexExecutor::connect(&config.dex, &flash_loan_config, wallets.clone())
+            .map_err(|e| format!("初始化 DEX 執行失敗: {}", e))?;
+        // 專用 gas 節點失敗時退回該鏈的 RPC 節點
+        let gas_rpc = match &config.gas.rpc_url {
+            Some(url) => {
+                let fallback = RpcConfig {
+                    fallback_urls: config.rpc_url.iter().chain(&config.rpc.fallback_urls).cloned().collect(),
+                    ..config.rpc.clone()
+                };
+                Some(RpcTransport::connect(name, url, &fallback).map_err(|e| format!("初始化 gas 預言機失敗: {}", e))?)
+            }
+            None => rpc.clone(),
+        };
+        let gas_optimizer = GasOptimizer::connect(config.gas.clone(), gas_rpc);
+        let eth_per_native = if config.native_token == "ETH" {
+            1.0
+        } else {
+            let eth_price = market_data::reference_price("ETH").ok_or("缺少 ETH 參考價格")?;
+            config.native_token_price_usd / eth_price
+        };
+        info!(
+            chain = name,
+            chain_id = config.chain_id,
+            native_token = %config.native_token,
+            flash_loan = flash_loan.is_some(),
+            dex = dex.is_some(),
+            "鏈上執行棧已初始化"
+        );
+        Ok(Self {
+            name: name.to_string(),
+            chain_id: config.chain_id,
+            eth_per_native,
+            flash_loan,
+            dex,
+            wallets,
+            gas_optimizer,
+            rpc,
+        })
+    }
+
+    // 1 個原生代幣按參考價折合的 asset 數量，用於把 gas 費與以 asset 計的收益比較
+    pub fn native_price(&self, asset: &str) -> Option<f64> {
+        let eth = market_data::reference_price("ETH")?;
+        Some(self.eth_per_native * eth / market_data::reference_price(asset)?)
+    }
+
+    // 按配置的預估 gas 單位估算一次鏈上操作的 gas 費（USDT）
+    pub fn estimated_gas_usdt(&self, kind: &str, quote: &GasQuote) -> Result<f64, String> {
+        self.gas_usdt(self.gas_optimizer.estimated_gas(kind), quote)
+    }
+
+    // 按當前基礎費加小費估算 gas 單位的花費，按 ETH 參考價折算為 USDT
+    pub fn gas_usdt(&self, gas: u64, quote: &GasQuote) -> Result<f64, String> {
+        let native = self.gas_optimizer.expected_cost(quote, gas);
+        self.native_price("USDT")
+            .map(|price| native * price)
+            .ok_or_else(|| format!("鏈 {} 缺少原生代幣參考價，無法估算 gas 費", self.name))
+    }
+
+    // 把原生代幣計的 gas 花費折算為 ETH，各鏈共用每日 gas 預算
+    pub fn to_eth(&self, native: f64) -> f64 {
+        native * self.eth_per_native
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bench::START_MS;
+    use crate::deterministic_sim::{build, connect_sessions, flash_loan_node, funded_config, request, wallet_env, WALLET_KEY_ENV};
+    use crate::{config, events, ArbitrageRequest};
+
+    // 每條鏈有各自的 RPC、錢包與閃電貸部署；請求按 chain 選擇執行棧，未配置 RPC 的鏈只走交易所腿。
+    // 非 ETH 原生代幣的 gas 花費按美元價格折算為 ETH 計入每日預算
+    #[tokio::test]
+    async fn chain_config_selects_the_on_chain_stack_per_request() {
+        use web3::types::Address;
+        let vault: Address = config::BalancerConfig::default().vault_address.parse().unwrap();
+        let url = flash_loan_node(Arc::new(std::sync::Mutex::new([(vault, u64::MAX)].into())), Address::from_low_u64_be(0xf1)).await;
+        let mut bsc = config::ChainConfig {
+            chain_id: 56,
+            rpc_url: Some(url),
+            native_token: "BNB".to_string(),
+            native_token_price_usd: 550.0,
+            ..Default::default()
+        };
+        bsc.flash_loan.receiver_address = Some(format!("{:?}", Address::from_low_u64_be(0xf1)));
+        bsc.flash_loan.wallets[0].private_key_env = Some(WALLET_KEY_ENV.to_string());
+        let mut config = funded_config();
+        config.chains.insert("bsc".to_string(), bsc.clone());
+        config.chains.insert("arbitrum".to_string(), config::ChainConfig { chain_id: 42161, ..Default::default() });
+        config.validate().unwrap();
+
+        let (engine, path) = build("chains", config.clone(), wallet_env());
+        let engine = Arc::new(engine);
+        let names: Vec<&String> = engine.chains.keys().collect();
+        assert_eq!(names, ["arbitrum", "bsc", "ethereum"]);
+        let chain = engine.chain(Some("bsc")).unwrap();
+        assert_eq!(chain.chain_id, 56);
+        assert!(chain.flash_loan.is_some() && engine.default_chain().flash_loan.is_none());
+        assert!(engine.chain(Some("arbitrum")).unwrap().flash_loan.is_none());
+        assert!((chain.native_price("USDT").unwrap() - 550.0).abs() < 1e-9);
+        assert!((chain.to_eth(3.0) - 0.55).abs() < 1e-12);
+
+        connect_sessions(&engine).await;
+        engine.quotes.record_predicted_rate("binance", "BTCUSDT", 0.0060, START_MS);
+        engine.quotes.record_predicted_rate("bybit", "BTCUSDT", 0.0010, START_MS);
+        let mut legs = Vec::new();
+        for chain in [Some("bsc"), None] {
+            let response = engine
+                .execute_funding_rate_arbitrage(ArbitrageRequest { chain: chain.map(str::to_string), ..request(10_000.0) })
+                .await;
+            assert_eq!(response.status, "success", "{:?}", response.error_message);
+            let execution_id = response.execution_id.unwrap();
+            legs.extend(engine.events.read(0, 1_000).into_iter().filter_map(|envelope| match envelope.event {
+                events::EngineEvent::OrderPlaced { execution_id: id, leg, .. } if id == execution_id => Some(leg),
+                _ => None,
+            }));
+        }
+        assert_eq!(legs, ["flash_loan", "short", "long"]);
+        // 200k gas × 1 gwei 的 BNB 按 550 / 3000 折算
+        let spent = engine.gas_budget.snapshot().into_iter().find(|status| status.strategy == "funding_rate").unwrap().spent_eth;
+        assert!((spent - 0.0002 * 550.0 / 3000.0).abs() < 1e-12, "{}", spent);
+        let response = engine.execute_funding_rate_arbitrage(ArbitrageRequest { chain: Some("polygon".to_string()), ..request(10_000.0) }).await;
+        assert!(response.error_message.unwrap().contains("未配置鏈: polygon"));
+
+        // chain_id 重複、非 ETH 原生代幣缺少價格或與默認鏈重名時拒絕配置
+        let mut invalid = config.clone();
+        invalid.chains.insert("base".to_string(), config::ChainConfig { chain_id: 56, ..Default::default() });
+        assert!(invalid.validate().unwrap_err().contains("chain_id 相同"));
+        let mut invalid = config.clone();
+        invalid.chains.insert("bsc".to_string(), config::ChainConfig { native_token_price_usd: 0.0, ..bsc });
+        assert!(invalid.validate().unwrap_err().contains("chains.bsc.native_token_price_usd"));
+        let mut invalid = config;
+        invalid.chains.insert(config::DEFAULT_CHAIN.to_string(), config::ChainConfig { chain_id: 10, ..Default::default() });
+        assert!(invalid.validate().unwrap_err().contains("不能包含默認鏈"));
+        let _ = std::fs::remove_file(path);
+    }
+}
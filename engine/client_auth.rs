@@ -0,0 +1,257 @@
+use super::config::{ClientAuthConfig, Scope};
+use super::environment::Vars;
+use super::{ClientMessage, EngineCommand};
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tracing::info;
+
+type HmacSha256 = Hmac<Sha256>;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuthRequest {
+    pub client: String,
+    pub timestamp_ms: i64,
+    pub nonce: String,
+    pub signature: String,
+}
+
+// 已認證的客戶端
+#[derive(Debug, Clone, Serialize)]
+pub struct Principal {
+    pub client: String,
+    pub scopes: Vec<Scope>,
+}
+
+impl Principal {
+    pub fn allows(&self, required: Scope) -> bool {
+        self.scopes.iter().any(|scope| scope.covers(required))
+    }
+}
+
+struct Credential {
+    secret: Vec<u8>,
+    scopes: Vec<Scope>,
+}
+
+pub struct Authenticator {
+    config: ClientAuthConfig,
+    credentials: HashMap<String, Credential>,
+    // 時鐘偏差窗口內已使用的 client:nonce -> 時間戳
+    seen: Mutex<HashMap<String, i64>>,
+}
+
+fn now_ms() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as i64)
+        .unwrap_or_default()
+}
+
+fn mac(secret: &[u8], client: &str, timestamp_ms: i64, nonce: &str) -> HmacSha256 {
+    let mut mac = HmacSha256::new_from_slice(secret).expect("HMAC 接受任意長度密鑰");
+    mac.update(format!("{}|{}|{}", client, timestamp_ms, nonce).as_bytes());
+    mac
+}
+
+// 客戶端生成握手簽名
+pub fn sign(secret: &[u8], client: &str, timestamp_ms: i64, nonce: &str) -> String {
+    hex::encode(mac(secret, client, timestamp_ms, nonce).finalize().into_bytes())
+}
+
+// 每條消息所需的權限範圍；None 表示無需認證
+pub fn required_scope(message: &ClientMessage) -> Option<Scope> {
+    let command = match message {
+        ClientMessage::Execute(_) | ClientMessage::Batch(_) => return Some(Scope::Execute),
+        ClientMessage::Command(command) => command,
+    };
+    match command {
+        EngineCommand::CancelScheduled { .. } => Some(Scope::Execute),
+        // 轉發的腿自帶實例間簽名；心跳在認證前也需要保持連接
+        EngineCommand::Authenticate(_)
+        | EngineCommand::SetEncoding { .. }
+        | EngineCommand::Ping
+        | EngineCommand::Pong { .. }
+        | EngineCommand::ExecuteLeg(_) => None,
+        EngineCommand::SetLogLevel { .. }
+        | EngineCommand::SetKillSwitch { .. }
+        | EngineCommand::ResumeTrading { .. }
+        | EngineCommand::Reconcile { .. }
+        | EngineCommand::SetStrategyEnabled { .. }
+        | EngineCommand::ReloadConfig
+        | EngineCommand::RecordTransfer { .. }
+        | EngineCommand::Rebalance { .. }
+        // 讀取服務器上的數據文件
+        | EngineCommand::RunBacktest(_) => Some(Scope::Admin),
+        EngineCommand::GetHistory(_)
+        | EngineCommand::GetSpreadStats { .. }
+        | EngineCommand::GetFundingCalendar
+        | EngineCommand::GetSizingLimits
+        | EngineCommand::GetPositions { .. }
+        | EngineCommand::GetEvents { .. }
+        | EngineCommand::GetOpportunities
+        | EngineCommand::SubscribeOpportunities
+        | EngineCommand::GetCrowding
+        | EngineCommand::GetBasis
+        | EngineCommand::GetRateLimits
+        | EngineCommand::GetSessions
+        | EngineCommand::GetAccounts { .. }
+        | EngineCommand::GetMarginAvailability
+        | EngineCommand::GetLiquidationRisk
+        | EngineCommand::GetFundingPairs
+        | EngineCommand::GetPredictedFunding
+        | EngineCommand::GetConfig
+        | EngineCommand::GetOpenExecutions
+        | EngineCommand::GetExecutionQueue
+        | EngineCommand::GetLatency
+        | EngineCommand::GetMakerStats
+        | EngineCommand::GetQuotes
+        | EngineCommand::GetVenueLatency
+        | EngineCommand::GetGateways
+        | EngineCommand::GetClockSync
+        | EngineCommand::GetInventory
+        | EngineCommand::GetMargin
+        | EngineCommand::GetSettlementProof { .. }
+        | EngineCommand::GetWallets { .. }
+        | EngineCommand::GetGasBudget
+        | EngineCommand::GetCapital
+        | EngineCommand::GetTradingHalts
+        | EngineCommand::GetEventBus
+        | EngineCommand::GetJournal
+        | EngineCommand::GetReconciliation
+        | EngineCommand::GetScheduled
+        | EngineCommand::GetRebalance
+        | EngineCommand::GetPriceCheck { .. }
+        | EngineCommand::GetOraclePrices
+        | EngineCommand::GetApprovals { .. }
+        | EngineCommand::GetRpcProviders { .. }
+        | EngineCommand::GetBlockSubscription { .. }
+        | EngineCommand::GetSymbol { .. }
+        | EngineCommand::GetMempool
+        | EngineCommand::PreviewRisk { .. }
+        | EngineCommand::GetAlgoExecutions
+        | EngineCommand::GetStrategies
+        | EngineCommand::GetStrategyPlugins
+        | EngineCommand::GetLending
+        | EngineCommand::PlanOrder { .. }
+        | EngineCommand::SimulateOpportunity { .. } => Some(Scope::ReadOnly),
+    }
+}
+
+impl Authenticator {
+    pub fn new(config: ClientAuthConfig, vars: &dyn Vars) -> Result<Self, String> {
+        let mut credentials = HashMap::new();
+        if config.enabled {
+            for client in &config.clients {
+                let secret = vars
+                    .var(&client.secret_env)
+                    .ok_or_else(|| format!("客戶端 {} 未設置環境變量 {}", client.name, client.secret_env))?;
+                info!(client = %client.name, scopes = ?client.scopes, "已配置客戶端憑證");
+                credentials.insert(
+                    client.name.clone(),
+                    Credential {
+                        secret: secret.into_bytes(),
+                        scopes: client.scopes.clone(),
+                    },
+                );
+            }
+        }
+        Ok(Self {
+            config,
+            credentials,
+            seen: Mutex::new(HashMap::new()),
+        })
+    }
+
+    // 未啟用認證時握手總是成功並擁有全部權限
+    pub fn authenticate(&self, request: &AuthRequest) -> Result<Principal, String> {
+        if !self.config.enabled {
+            return Ok(Principal {
+                client: request.client.clone(),
+                scopes: vec![Scope::Admin],
+            });
+        }
+        let now = now_ms();
+        let skew = self.config.max_clock_skew_ms;
+        // 客戶端可任意填寫時間戳，相減可能溢出
+        if now.abs_diff(request.timestamp_ms) > skew {
+            return Err(format!("握手已過期或時鐘偏差過大: {}ms", now.saturating_sub(request.timestamp_ms)));
+        }
+        // 未知客戶端與簽名錯誤返回相同錯誤，不洩露客戶端名是否存在
+        let credential = self.credentials.get(&request.client).ok_or("認證失敗")?;
+        let signature = hex::decode(&request.signature).map_err(|_| "認證失敗")?;
+        mac(&credential.secret, &request.client, request.timestamp_ms, &request.nonce)
+            .verify_slice(&signature)
+            .map_err(|_| "認證失敗")?;
+        let mut seen = self.seen.lock().unwrap();
+        seen.retain(|_, timestamp_ms| now.abs_diff(*timestamp_ms) <= skew);
+        if seen.insert(format!("{}:{}", request.client, request.nonce), request.timestamp_ms).is_some() {
+            return Err("重複的握手 nonce".to_string());
+        }
+        Ok(Principal {
+            client: request.client.clone(),
+            scopes: credential.scopes.clone(),
+        })
+    }
+
+    // 啟用認證時校驗連接上的客戶端是否有權處理該消息
+    pub fn authorize(&self, principal: Option<&Principal>, message: &ClientMessage) -> Result<(), String> {
+        if !self.config.enabled {
+            return Ok(());
+        }
+        let Some(required) = required_scope(message) else {
+            return Ok(());
+        };
+        match principal {
+            None => Err("未認證，請先發送 authenticate 握手".to_string()),
+            Some(principal) if principal.allows(required) => Ok(()),
+            Some(principal) => Err(format!("客戶端 {} 缺少 {:?} 權限", principal.client, required)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config;
+    use crate::environment::FixedVars;
+
+    // 握手時間戳過期或超前、簽名錯誤、nonce 重放時拒絕
+    #[test]
+    fn client_handshake_rejects_stale_forged_and_replayed_requests() {
+        let auth = Authenticator::new(
+            config::ClientAuthConfig {
+                enabled: true,
+                max_clock_skew_ms: 30_000,
+                clients: vec![config::ClientCredentialConfig {
+                    name: "desk".to_string(),
+                    secret_env: "E2E_CLIENT_SECRET".to_string(),
+                    scopes: vec![config::Scope::ReadOnly],
+                }],
+            },
+            &FixedVars::new([("E2E_CLIENT_SECRET", "e2e-secret")]),
+        )
+        .unwrap();
+        let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_millis() as i64;
+        let request = |timestamp_ms: i64, nonce: &str, secret: &[u8]| AuthRequest {
+            client: "desk".to_string(),
+            timestamp_ms,
+            nonce: nonce.to_string(),
+            signature: sign(secret, "desk", timestamp_ms, nonce),
+        };
+
+        // 過期與任意極端時間戳都按時鐘偏差拒絕，不溢出
+        for timestamp_ms in [now - 60_000, now + 60_000, i64::MIN, i64::MAX] {
+            let error = auth.authenticate(&request(timestamp_ms, "n0", b"e2e-secret")).unwrap_err();
+            assert!(error.contains("時鐘偏差"), "{}", error);
+        }
+        assert_eq!(auth.authenticate(&request(now, "n1", b"wrong-secret")).unwrap_err(), "認證失敗");
+
+        let principal = auth.authenticate(&request(now, "n2", b"e2e-secret")).unwrap();
+        assert!(principal.allows(config::Scope::ReadOnly) && !principal.allows(config::Scope::Admin));
+        assert_eq!(auth.authenticate(&request(now, "n2", b"e2e-secret")).unwrap_err(), "重複的握手 nonce");
+    }
+}
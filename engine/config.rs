@@ -0,0 +1,3034 @@
+use super::{MarginMode, StrategyType};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::Path;
+
+pub const DEFAULT_CONFIG_PATH: &str = "config/engine.json";
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EngineConfig {
+    pub sizing: SizingConfig,
+    pub exchanges: HashMap<String, ExchangeConfig>,
+    pub scanner: ScannerConfig,
+    pub basis: BasisConfig,
+    pub spot_arbitrage: SpotArbitrageConfig,
+    pub lending: LendingConfig,
+    pub order_router: OrderRouterConfig,
+    pub execution_algo: ExecutionAlgoConfig,
+    pub sessions: SessionConfig,
+    pub pre_trade: PreTradeConfig,
+    pub liquidation: LiquidationConfig,
+    pub funding_exit: FundingExitConfig,
+    pub flash_loan: FlashLoanConfig,
+    pub margin: MarginConfig,
+    pub gas: GasConfig,
+    pub dex: DexConfig,
+    // 默認鏈的上鏈前交易模擬
+    pub tx_simulation: TxSimulationConfig,
+    // 默認鏈的備用 RPC 節點與交叉驗證
+    pub rpc: RpcConfig,
+    // 默認鏈的新區塊與結算事件訂閱
+    pub subscription: SubscriptionConfig,
+    pub mempool: MempoolConfig,
+    pub risk: RiskConfig,
+    pub bundle: BundleConfig,
+    pub routing: RoutingConfig,
+    pub admin_api: AdminApiConfig,
+    pub tls: TlsConfig,
+    pub client_auth: ClientAuthConfig,
+    pub shutdown: ShutdownConfig,
+    pub connections: ConnectionConfig,
+    pub execution_queue: ExecutionQueueConfig,
+    pub stage_timeouts: StageTimeoutConfig,
+    pub time_sync: TimeSyncConfig,
+    // 默認鏈之外的其他鏈（如 arbitrum / bsc / base）
+    pub chains: BTreeMap<String, ChainConfig>,
+    // 啟動時停用的策略類型；熱加載時該列表有變更則覆蓋運行時的策略開關
+    pub disabled_strategies: Vec<StrategyType>,
+    pub config_reload: ConfigReloadConfig,
+    pub secrets: SecretsConfig,
+    pub alerts: AlertsConfig,
+    pub event_bus: EventBusConfig,
+    pub reconciliation: ReconciliationConfig,
+    pub scheduler: SchedulerConfig,
+    pub rebalance: RebalanceConfig,
+    pub transfer_costs: TransferCostConfig,
+    pub price_guard: PriceGuardConfig,
+    pub oracle: OracleConfig,
+    pub maker: MakerConfig,
+    pub quotes: QuoteConfig,
+    pub ack_latency: AckLatencyConfig,
+    pub runtime: RuntimeConfig,
+    pub unix_socket: UnixSocketConfig,
+    pub shared_memory: SharedMemoryConfig,
+}
+
+pub const DEFAULT_CHAIN: &str = "ethereum";
+
+// 交易所主賬戶的名稱；請求以 account 指定 main 時使用主賬戶
+pub const MAIN_ACCOUNT: &str = "main";
+
+// 子賬戶連接器的鍵，如 binance@sub1；主賬戶沿用交易所名
+pub fn account_key(exchange: &str, account: &str) -> String {
+    if account == MAIN_ACCOUNT {
+        exchange.to_string()
+    } else {
+        format!("{}@{}", exchange, account)
+    }
+}
+
+// 連接器鍵所屬的交易所
+pub fn venue(key: &str) -> &str {
+    key.split_once('@').map_or(key, |(venue, _)| venue)
+}
+
+// 一條鏈上的鏈上執行棧；頂層 flash_loan / gas / dex 構成默認鏈，其他鏈的合約地址按各自部署配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ChainConfig {
+    pub chain_id: u64,
+    // 未配置時該鏈不做鏈上執行；覆蓋 flash_loan 中的 rpc_url 與 chain_id
+    pub rpc_url: Option<String>,
+    pub rpc: RpcConfig,
+    // 原生代幣不是 ETH 時按美元價格把 gas 花費折算為 ETH，計入每日 gas 預算
+    pub native_token: String,
+    pub native_token_price_usd: f64,
+    pub flash_loan: FlashLoanConfig,
+    // 單次執行上限等金額以該鏈原生代幣計
+    pub gas: GasConfig,
+    // 默認不啟用 DEX
+    pub dex: DexConfig,
+    pub tx_simulation: TxSimulationConfig,
+    pub subscription: SubscriptionConfig,
+}
+
+impl Default for ChainConfig {
+    fn default() -> Self {
+        Self {
+            chain_id: 0,
+            rpc_url: None,
+            rpc: RpcConfig::default(),
+            native_token: "ETH".to_string(),
+            native_token_price_usd: 0.0,
+            flash_loan: FlashLoanConfig::default(),
+            gas: GasConfig::default(),
+            dex: DexConfig {
+                venues: Vec::new(),
+                ..DexConfig::default()
+            },
+            tx_simulation: TxSimulationConfig::default(),
+            subscription: SubscriptionConfig::default(),
+        }
+    }
+}
+
+impl ChainConfig {
+    // 錢包與各執行器按該配置連接
+    pub fn resolved_flash_loan(&self) -> FlashLoanConfig {
+        FlashLoanConfig {
+            rpc_url: self.rpc_url.clone(),
+            chain_id: self.chain_id,
+            ..self.flash_loan.clone()
+        }
+    }
+
+    fn validate(&self, name: &str) -> Result<(), String> {
+        // 默認鏈沿用頂層字段名，其他鏈加上 chains.<name>. 前綴
+        let prefix = if name == DEFAULT_CHAIN { String::new() } else { format!("chains.{}.", name) };
+        if self.native_token != "ETH" && self.native_token_price_usd <= 0.0 {
+            return Err(format!("{}native_token_price_usd 必須大於 0", prefix));
+        }
+        let flash_loan = &self.flash_loan;
+        if flash_loan.gas_buffer < 1.0 {
+            return Err(format!("{}flash_loan.gas_buffer 不能小於 1", prefix));
+        }
+        if flash_loan.receipt_timeout_secs == 0 {
+            return Err(format!("{}flash_loan.receipt_timeout_secs 必須大於 0", prefix));
+        }
+        if flash_loan.providers.is_empty() {
+            return Err(format!("{}flash_loan.providers 至少需要一個閃電貸來源", prefix));
+        }
+        if flash_loan.wallets.is_empty() {
+            return Err(format!("{}flash_loan.wallets 至少需要一個熱錢包", prefix));
+        }
+        let gas = &self.gas;
+        if gas.poll_interval_ms == 0 || gas.fee_history_blocks == 0 {
+            return Err(format!("{}gas.poll_interval_ms 與 gas.fee_history_blocks 必須大於 0", prefix));
+        }
+        if gas.urgency_tiers.is_empty() {
+            return Err(format!("{}gas.urgency_tiers 至少需要一個檔位", prefix));
+        }
+        if gas.urgency_tiers.windows(2).any(|pair| pair[0].min_priority >= pair[1].min_priority) {
+            return Err(format!("{}gas.urgency_tiers 必須按 min_priority 嚴格升序排列", prefix));
+        }
+        for tier in &gas.urgency_tiers {
+            if !(0.0..=100.0).contains(&tier.reward_percentile) || tier.base_fee_multiplier < 1.0 {
+                return Err(format!("{}gas 檔位 {} 的 reward_percentile 必須在 0..=100 且 base_fee_multiplier 不小於 1", prefix, tier.name));
+            }
+        }
+        if gas.fallback_priority_fee_gwei > gas.fallback_max_fee_gwei {
+            return Err(format!("{}gas.fallback_priority_fee_gwei 不能超過 fallback_max_fee_gwei", prefix));
+        }
+        if let Some((strategy, _)) = gas.daily_budget_eth.iter().find(|(_, budget)| **budget <= 0.0) {
+            return Err(format!("{}gas.daily_budget_eth.{} 必須大於 0", prefix, strategy));
+        }
+        if let Some(kind) = gas.estimated_gas.keys().find(|kind| !matches!(kind.as_str(), "flash_loan" | "swap" | "lend")) {
+            return Err(format!("{}gas.estimated_gas 含未知的鏈上操作 {}", prefix, kind));
+        }
+        if !(gas.min_net_profit_usdt.is_finite() && gas.min_net_profit_usdt >= 0.0) {
+            return Err(format!("{}gas.min_net_profit_usdt 不能為負數", prefix));
+        }
+        let dex = &self.dex;
+        if let Some(venue) = dex.venues.iter().find(|venue| !matches!(venue.as_str(), "uniswap_v3" | "curve")) {
+            return Err(format!("{}dex.venues 包含未知的 DEX: {}", prefix, venue));
+        }
+        if !(0.0 < dex.slippage_bps && dex.slippage_bps < 10_000.0) || dex.deadline_secs == 0 {
+            return Err(format!("{}dex.slippage_bps 必須介於 0 與 10000 之間且 dex.deadline_secs 大於 0", prefix));
+        }
+        if let Some(coin) = dex.curve.coins.iter().find(|coin| !dex.tokens.contains_key(*coin)) {
+            return Err(format!("{}dex.curve.coins 中的 {} 未在 dex.tokens 中配置", prefix, coin));
+        }
+        let approvals = &dex.approvals;
+        if approvals.interval_ms == 0 || approvals.permit2_expiration_secs == 0 {
+            return Err(format!("{}dex.approvals.interval_ms 與 permit2_expiration_secs 必須大於 0", prefix));
+        }
+        if let Some((asset, _)) = approvals.exact_amounts.iter().find(|(_, amount)| !(amount.is_finite() && **amount > 0.0)) {
+            return Err(format!("{}dex.approvals.exact_amounts.{} 必須大於 0", prefix, asset));
+        }
+        if let Some(venue) = approvals.permit2_venues.iter().find(|venue| !dex.venues.contains(venue)) {
+            return Err(format!("{}dex.approvals.permit2_venues 中的 {} 未在 dex.venues 中啟用", prefix, venue));
+        }
+        for wallet in &flash_loan.wallets {
+            match (&wallet.keystore_path, &wallet.password_env, &wallet.private_key_env) {
+                (Some(_), Some(_), _) | (None, _, Some(_)) => {}
+                (Some(_), None, _) => {
+                    return Err(format!("錢包 {} 使用 keystore 時需要配置 password_env", wallet.name));
+                }
+                (None, _, None) => {
+                    return Err(format!("錢包 {} 需要配置 keystore_path 或 private_key_env", wallet.name));
+                }
+            }
+        }
+        let simulation = &self.tx_simulation;
+        if simulation.timeout_ms == 0 {
+            return Err(format!("{}tx_simulation.timeout_ms 必須大於 0", prefix));
+        }
+        match simulation.backend {
+            SimulationBackend::Fork if simulation.fork_rpc_url.is_none() => {
+                return Err(format!("{}tx_simulation 使用 fork 時需要配置 fork_rpc_url", prefix));
+            }
+            SimulationBackend::Tenderly if simulation.tenderly.account.is_empty() || simulation.tenderly.project.is_empty() => {
+                return Err(format!("{}tx_simulation 使用 tenderly 時需要配置 tenderly.account 與 tenderly.project", prefix));
+            }
+            _ => {}
+        }
+        let rpc = &self.rpc;
+        if rpc.request_timeout_ms == 0 || rpc.max_consecutive_failures == 0 || rpc.cooldown_ms == 0 {
+            return Err(format!("{}rpc.request_timeout_ms、max_consecutive_failures 與 cooldown_ms 必須大於 0", prefix));
+        }
+        if rpc.quorum == 0 || rpc.quorum > 1 + rpc.fallback_urls.len() {
+            return Err(format!("{}rpc.quorum 必須介於 1 與 RPC 節點數（rpc_url 加 fallback_urls）之間", prefix));
+        }
+        if !(rpc.max_deviation_bps.is_finite() && rpc.max_deviation_bps >= 0.0) {
+            return Err(format!("{}rpc.max_deviation_bps 不能為負數", prefix));
+        }
+        let subscription = &self.subscription;
+        if subscription.reconnect_secs == 0 || subscription.reorg_window_blocks == 0 || subscription.fallback_poll_ms == 0 {
+            return Err(format!("{}subscription.reconnect_secs、reorg_window_blocks 與 fallback_poll_ms 必須大於 0", prefix));
+        }
+        Ok(())
+    }
+}
+
+// 經 WebSocket 訂閱新區塊與轉入熱錢包 / 收益地址的轉賬事件，新區塊到達時查詢回執以取代輪詢；
+// 已確認的交易在 reorg_window_blocks 內持續核對，所在區塊被重組時重新核驗並告警
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SubscriptionConfig {
+    // 支持 eth_subscribe 的 WebSocket 節點，未配置時輪詢回執且不檢測重組
+    pub ws_url: Option<String>,
+    pub reconnect_secs: u64,
+    // 確認後超過該區塊數視為最終確定，不再核對
+    pub reorg_window_blocks: u64,
+    // 訂閱在線但遲遲沒有新區塊時，仍按該間隔查詢回執
+    pub fallback_poll_ms: u64,
+}
+
+impl Default for SubscriptionConfig {
+    fn default() -> Self {
+        Self {
+            ws_url: None,
+            reconnect_secs: 5,
+            reorg_window_blocks: 64,
+            fallback_poll_ms: 12_000,
+        }
+    }
+}
+
+// 一條鏈的多個 RPC 節點：按健康評分選用，節點不可達或超時時切換到下一個；
+// quorum 大於 1 時，簽名前的關鍵讀取（餘額、gas 估算、nonce）需有 quorum 個節點結果一致
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RpcConfig {
+    // 排在 rpc_url 之後的備用節點
+    pub fallback_urls: Vec<String>,
+    pub request_timeout_ms: u64,
+    // 連續失敗達到次數後暫停使用該節點 cooldown_ms
+    pub max_consecutive_failures: u32,
+    pub cooldown_ms: u64,
+    pub quorum: usize,
+    // 數值結果（如 gas 估算、餘額）與中位數的偏差在此範圍內視為一致；nonce 要求完全相同
+    pub max_deviation_bps: f64,
+}
+
+impl Default for RpcConfig {
+    fn default() -> Self {
+        Self {
+            fallback_urls: Vec::new(),
+            request_timeout_ms: 5_000,
+            max_consecutive_failures: 3,
+            cooldown_ms: 30_000,
+            quorum: 1,
+            max_deviation_bps: 100.0,
+        }
+    }
+}
+
+// 鏈上交易簽名發送前先按當前狀態模擬，模擬回滾時不發送
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TxSimulationConfig {
+    pub enabled: bool,
+    pub backend: SimulationBackend,
+    // fork 後端的本地分叉節點（如 anvil --fork-url），需與主網保持同步
+    pub fork_rpc_url: Option<String>,
+    pub tenderly: TenderlyConfig,
+    pub timeout_ms: u64,
+}
+
+impl Default for TxSimulationConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            backend: SimulationBackend::EthCall,
+            fork_rpc_url: None,
+            tenderly: TenderlyConfig::default(),
+            timeout_ms: 3_000,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SimulationBackend {
+    // 節點的 eth_call，只判斷是否回滾
+    EthCall,
+    // 節點的 debug_traceCall（callTracer），另外給出實際消耗的 gas；節點需開啟 debug 命名空間
+    DebugTraceCall,
+    // 本地分叉節點上的 debug_traceCall
+    Fork,
+    // Tenderly 模擬 API
+    Tenderly,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TenderlyConfig {
+    pub api_url: String,
+    pub account: String,
+    pub project: String,
+    // 保存 access key 的環境變量名
+    pub access_key_env: String,
+}
+
+impl Default for TenderlyConfig {
+    fn default() -> Self {
+        Self {
+            api_url: "https://api.tenderly.co/api/v1".to_string(),
+            account: String::new(),
+            project: String::new(),
+            access_key_env: "TENDERLY_ACCESS_KEY".to_string(),
+        }
+    }
+}
+
+// 單次執行各階段的超時
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StageTimeoutConfig {
+    pub rate_fetch_ms: u64,
+    pub leg_submission_ms: u64,
+    // 等待轉發至其他區域實例的腿回報成交
+    pub fill_confirmation_ms: u64,
+}
+
+impl Default for StageTimeoutConfig {
+    fn default() -> Self {
+        Self {
+            rate_fetch_ms: 500,
+            leg_submission_ms: 2_000,
+            fill_confirmation_ms: 3_000,
+        }
+    }
+}
+
+// 交易所時鐘同步
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TimeSyncConfig {
+    pub enabled: bool,
+    pub interval_secs: u64,
+    // 每輪同步的服務器時間採樣次數
+    pub samples: u32,
+    // 偏差超過該值時告警
+    pub warn_offset_ms: u64,
+}
+
+impl Default for TimeSyncConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            interval_secs: 60,
+            samples: 4,
+            warn_offset_ms: 1_000,
+        }
+    }
+}
+
+// 執行隊列
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ExecutionQueueConfig {
+    pub enabled: bool,
+    pub max_pending: usize,
+    // 同一交易所同時執行的請求數上限
+    pub max_concurrent_per_exchange: usize,
+    // 排隊超過此時間仍未開始執行則以錯誤返回
+    pub max_wait_ms: u64,
+    pub preemption: PreemptionConfig,
+}
+
+impl Default for ExecutionQueueConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            max_pending: 256,
+            max_concurrent_per_exchange: 4,
+            max_wait_ms: 5_000,
+            preemption: PreemptionConfig::default(),
+        }
+    }
+}
+
+// 優先級搶佔：高優先級請求因交易所並發額度佔滿而等待時，中止優先級低至少 min_priority_gap 且尚未下單的執行
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PreemptionConfig {
+    pub enabled: bool,
+    pub mode: PreemptionMode,
+    pub min_priority_gap: i32,
+}
+
+impl Default for PreemptionConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            mode: PreemptionMode::Requeue,
+            min_priority_gap: 1,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PreemptionMode {
+    // 被搶佔的請求以 preempted 狀態返回
+    Abort,
+    // 被搶佔的請求推送 preempted 進度後重新排隊，等待額度空出再執行
+    Requeue,
+}
+
+// 配置熱加載：監視配置文件變更，風控限額、閾值、手續費與策略開關無需重啟即可生效
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ConfigReloadConfig {
+    pub watch: bool,
+    pub poll_interval_secs: u64,
+}
+
+impl Default for ConfigReloadConfig {
+    fn default() -> Self {
+        Self {
+            watch: false,
+            poll_interval_secs: 5,
+        }
+    }
+}
+
+// 交易所 API 憑證來源：環境變量，或運行時從 Vault / AWS Secrets Manager 讀取並定期刷新，只保存在內存中
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SecretsConfig {
+    pub provider: SecretsBackend,
+    pub vault: VaultConfig,
+    pub aws: AwsSecretsConfig,
+    // 重新讀取憑證以接收輪換後的版本；0 表示只在啟動時讀取
+    pub refresh_interval_secs: u64,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SecretsBackend {
+    // 按 ExchangeConfig::credential_envs 讀取環境變量
+    #[default]
+    Env,
+    Vault,
+    AwsSecretsManager,
+}
+
+// Vault KV v2 引擎；密鑰在 <mount>/data/<secret_path> 下，字段為 api_key、secret_key、passphrase
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct VaultConfig {
+    pub address: String,
+    pub mount: String,
+    // 保存 Vault token 的環境變量名
+    pub token_env: String,
+    pub namespace: Option<String>,
+}
+
+impl Default for VaultConfig {
+    fn default() -> Self {
+        Self {
+            address: "http://127.0.0.1:8200".to_string(),
+            mount: "secret".to_string(),
+            token_env: "VAULT_TOKEN".to_string(),
+            namespace: None,
+        }
+    }
+}
+
+// SecretString 為含 api_key、secret_key、passphrase 的 JSON；訪問憑證取自 AWS_ACCESS_KEY_ID、
+// AWS_SECRET_ACCESS_KEY 與可選的 AWS_SESSION_TOKEN
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AwsSecretsConfig {
+    pub region: String,
+    // 覆蓋默認端點，如 LocalStack
+    pub endpoint: Option<String>,
+}
+
+impl Default for AwsSecretsConfig {
+    fn default() -> Self {
+        Self {
+            region: "ap-northeast-1".to_string(),
+            endpoint: None,
+        }
+    }
+}
+
+impl Default for SecretsConfig {
+    fn default() -> Self {
+        Self {
+            provider: SecretsBackend::Env,
+            vault: VaultConfig::default(),
+            aws: AwsSecretsConfig::default(),
+            refresh_interval_secs: 300,
+        }
+    }
+}
+
+// 告警推送：每個通道按最低嚴重級別接收告警，未配置通道時只寫日誌
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AlertsConfig {
+    pub sinks: Vec<AlertSinkConfig>,
+    pub timeout_ms: u64,
+}
+
+impl Default for AlertsConfig {
+    fn default() -> Self {
+        Self {
+            sinks: Vec::new(),
+            timeout_ms: 5_000,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    #[default]
+    Info,
+    Warning,
+    Critical,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AlertSinkKind {
+    // Bot API sendMessage，token 從 token_env 讀取
+    Telegram,
+    Discord,
+    Slack,
+    // 以 JSON POST 完整告警內容
+    #[default]
+    Webhook,
+}
+
+// 地址與 token 含憑證，只配置所在的環境變量名
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AlertSinkConfig {
+    pub kind: AlertSinkKind,
+    pub min_severity: Severity,
+    // Discord / Slack / 通用 webhook 的地址
+    pub url_env: Option<String>,
+    // Telegram bot token 與目標會話
+    pub token_env: Option<String>,
+    pub chat_id: Option<String>,
+    // 覆蓋 Telegram API 地址，默認 https://api.telegram.org
+    pub api_url: Option<String>,
+}
+
+// 事件總線：從事件日誌按序號讀取執行相關事件發布到 Kafka / NATS / Redis Streams，
+// 已確認的序號寫入 cursor_path，代理不可用時事件留在日誌中，恢復後從斷點重發（至少一次）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EventBusConfig {
+    pub enabled: bool,
+    pub backend: EventBusBackend,
+    pub kafka: KafkaConfig,
+    pub nats: NatsConfig,
+    pub redis: RedisStreamConfig,
+    // 首次啟用（沒有 cursor 文件）時從當前日誌末尾開始
+    pub cursor_path: String,
+    pub batch_size: usize,
+    pub poll_interval_ms: u64,
+    pub retry_secs: u64,
+    pub timeout_ms: u64,
+}
+
+impl Default for EventBusConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            backend: EventBusBackend::Kafka,
+            kafka: KafkaConfig::default(),
+            nats: NatsConfig::default(),
+            redis: RedisStreamConfig::default(),
+            cursor_path: "event_bus.cursor".to_string(),
+            batch_size: 100,
+            poll_interval_ms: 200,
+            retry_secs: 5,
+            timeout_ms: 5_000,
+        }
+    }
+}
+
+// 狀態對賬：定時以交易所 REST 快照核對本地持倉（事件日誌投影）、餘額（用戶數據流）與未完結訂單，
+// auto_correct 時按交易所修正本地持倉與餘額，訂單差異只標記
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ReconciliationConfig {
+    pub enabled: bool,
+    pub interval_secs: u64,
+    pub auto_correct: bool,
+    // 忽略的數量差（持倉按合約數量，餘額按資產數量）
+    pub position_tolerance: f64,
+    pub balance_tolerance: f64,
+}
+
+impl Default for ReconciliationConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            interval_secs: 300,
+            auto_correct: false,
+            position_tolerance: 1e-6,
+            balance_tolerance: 1e-6,
+        }
+    }
+}
+
+// 定時執行：按 execute_at_ms / execute_before_funding_secs 登記的請求每 tick_ms 檢查一次是否到點；
+// 因引擎繁忙晚於觸發時間超過 max_delay_ms 的請求放棄執行
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SchedulerConfig {
+    pub enabled: bool,
+    pub tick_ms: u64,
+    pub max_pending: usize,
+    pub max_delay_ms: u64,
+    // 保留的已觸發記錄數
+    pub history: usize,
+}
+
+impl Default for SchedulerConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            tick_ms: 100,
+            max_pending: 256,
+            max_delay_ms: 5_000,
+            history: 100,
+        }
+    }
+}
+
+// 庫存再平衡：每 interval_secs 按各連接器（交易所或 交易所@子賬戶）的可用餘額檢查 targets 中的資產，
+// 某一方佔比偏離目標超過 tolerance 時從富餘方劃轉到不足方。同一交易所的賬戶之間走內部劃轉，
+// 跨交易所只提幣到 withdrawal_addresses 中的白名單地址；單筆與每個資產每日的劃轉量受限
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RebalanceConfig {
+    pub enabled: bool,
+    pub interval_secs: u64,
+    // 資產 -> 連接器鍵 -> 目標佔比，各資產的佔比之和為 1
+    pub targets: BTreeMap<String, BTreeMap<String, f64>>,
+    pub tolerance: f64,
+    // 低於 min_transfer 的劃轉不執行，超過 max_transfer 的分多輪完成
+    pub min_transfer: f64,
+    pub max_transfer: f64,
+    // 每個資產每個 UTC 日的劃轉總量上限
+    pub daily_limit: f64,
+    // 目標交易所 -> 資產 -> 充值地址
+    pub withdrawal_addresses: BTreeMap<String, BTreeMap<String, WithdrawalAddress>>,
+    // 保留的劃轉記錄數
+    pub history: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WithdrawalAddress {
+    pub address: String,
+    pub network: String,
+}
+
+impl Default for RebalanceConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            interval_secs: 300,
+            targets: BTreeMap::new(),
+            tolerance: 0.1,
+            min_transfer: 100.0,
+            max_transfer: 50_000.0,
+            daily_limit: 200_000.0,
+            withdrawal_addresses: BTreeMap::new(),
+            history: 100,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EventBusBackend {
+    #[default]
+    Kafka,
+    Nats,
+    Redis,
+}
+
+// 經 Kafka REST Proxy（v2 API）寫入，消息以執行 ID 為鍵保證同一執行的事件有序
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct KafkaConfig {
+    pub rest_url: String,
+    pub topic: String,
+}
+
+impl Default for KafkaConfig {
+    fn default() -> Self {
+        Self {
+            rest_url: "http://127.0.0.1:8082".to_string(),
+            topic: "arbitrage.executions".to_string(),
+        }
+    }
+}
+
+// 主題為 <subject_prefix>.<事件類型>；持久化與確認依賴 JetStream 流捕獲這些主題
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NatsConfig {
+    pub address: String,
+    pub subject_prefix: String,
+    pub token_env: Option<String>,
+}
+
+impl Default for NatsConfig {
+    fn default() -> Self {
+        Self {
+            address: "127.0.0.1:4222".to_string(),
+            subject_prefix: "arbitrage.executions".to_string(),
+            token_env: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RedisStreamConfig {
+    pub address: String,
+    pub stream: String,
+    pub password_env: Option<String>,
+    // XADD MAXLEN ~ 的近似上限
+    pub max_len: u64,
+}
+
+impl Default for RedisStreamConfig {
+    fn default() -> Self {
+        Self {
+            address: "127.0.0.1:6379".to_string(),
+            stream: "arbitrage:executions".to_string(),
+            password_env: None,
+            max_len: 1_000_000,
+        }
+    }
+}
+
+// 退出流程
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ShutdownConfig {
+    // 等待執行中的請求完成的最長時間
+    pub drain_timeout_secs: u64,
+}
+
+impl Default for ShutdownConfig {
+    fn default() -> Self {
+        Self { drain_timeout_secs: 30 }
+    }
+}
+
+// 客戶端 TCP 連接的心跳：連續 heartbeat_interval_ms 沒有收到客戶端消息時推送 ping，
+// 超過 idle_timeout_ms 仍無任何消息時關閉連接並取消其訂閱
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ConnectionConfig {
+    pub heartbeat_interval_ms: u64,
+    pub idle_timeout_ms: u64,
+}
+
+impl Default for ConnectionConfig {
+    fn default() -> Self {
+        Self {
+            heartbeat_interval_ms: 15_000,
+            idle_timeout_ms: 60_000,
+        }
+    }
+}
+
+// 客戶端監聽 TLS
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TlsConfig {
+    pub enabled: bool,
+    // PEM 格式的證書鏈與私鑰
+    pub cert_path: String,
+    pub key_path: String,
+    // 信任的客戶端 CA；配置後校驗客戶端證書，區域實例互連時也以此校驗對端
+    pub client_ca_path: Option<String>,
+    // 為 false 時客戶端可不出示證書，出示的證書仍須有效
+    pub require_client_cert: bool,
+}
+
+impl Default for TlsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            cert_path: "config/tls/server.pem".to_string(),
+            key_path: "config/tls/server.key".to_string(),
+            client_ca_path: None,
+            require_client_cert: true,
+        }
+    }
+}
+
+// 客戶端權限範圍：admin 包含 execute，execute 包含 read_only
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Scope {
+    ReadOnly,
+    Execute,
+    Admin,
+}
+
+impl Scope {
+    pub fn covers(self, required: Scope) -> bool {
+        match self {
+            Scope::Admin => true,
+            Scope::Execute => required != Scope::Admin,
+            Scope::ReadOnly => required == Scope::ReadOnly,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClientCredentialConfig {
+    pub name: String,
+    // 保存 HMAC 密鑰的環境變量名
+    pub secret_env: String,
+    pub scopes: Vec<Scope>,
+}
+
+// TCP 客戶端認證
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ClientAuthConfig {
+    pub enabled: bool,
+    pub max_clock_skew_ms: u64,
+    pub clients: Vec<ClientCredentialConfig>,
+}
+
+impl Default for ClientAuthConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            max_clock_skew_ms: 30_000,
+            clients: Vec::new(),
+        }
+    }
+}
+
+// HTTP 管理接口
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AdminApiConfig {
+    pub enabled: bool,
+    pub listen_address: String,
+    // 保存 Bearer token 的環境變量名
+    pub token_env: String,
+    // /readyz 判定行情過期的資金費率樣本年齡
+    pub max_market_data_age_secs: u64,
+}
+
+impl Default for AdminApiConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            listen_address: "127.0.0.1:8081".to_string(),
+            token_env: "ARB_ADMIN_TOKEN".to_string(),
+            max_market_data_age_secs: 180,
+        }
+    }
+}
+
+// 多區域部署：交易所由其他區域實例負責的腿轉交該實例執行，實例間指令以 HMAC-SHA256 簽名
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RoutingConfig {
+    // 本實例所在區域，寫入轉發指令供對端記錄
+    pub region: String,
+    pub peers: Vec<PeerConfig>,
+    // 實例間共享密鑰所在環境變量；配置了 peers 或需要接收轉發時必須設置
+    pub secret_env: String,
+    // 指令簽發時間與本地時鐘的最大偏差，超出即拒絕
+    pub max_clock_skew_ms: u64,
+    pub timeout_ms: u64,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PeerConfig {
+    pub name: String,
+    pub region: String,
+    pub address: String,
+    // 由該實例執行的交易所
+    pub venues: Vec<String>,
+}
+
+impl Default for RoutingConfig {
+    fn default() -> Self {
+        Self {
+            region: "local".to_string(),
+            peers: Vec::new(),
+            secret_env: "ARB_PEER_SECRET".to_string(),
+            max_clock_skew_ms: 5_000,
+            timeout_ms: 2_000,
+        }
+    }
+}
+
+// 私有 bundle 提交：鏈上腿經 Flashbots 中繼發送以免在公開內存池被夾，目標區塊內未被打包時退回公開發送
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BundleConfig {
+    pub enabled: bool,
+    // flashbots（eth_sendBundle）或 mev_share（mev_sendBundle）
+    pub relay: String,
+    pub relay_url: String,
+    // 簽署 X-Flashbots-Signature 的身份私鑰所在環境變量，應與交易錢包分開
+    pub signing_key_env: String,
+    // 從下一區塊起連續作為目標的區塊數
+    pub max_blocks: u64,
+    // bundle 內交易的小費上限，私有提交無需與公開內存池競價
+    pub max_priority_fee_gwei: f64,
+    // MEV-Share 向搜索者公開的提示
+    pub mev_share_hints: Vec<String>,
+}
+
+impl Default for BundleConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            relay: "flashbots".to_string(),
+            relay_url: "https://relay.flashbots.net".to_string(),
+            signing_key_env: "FLASHBOTS_SIGNING_KEY".to_string(),
+            max_blocks: 3,
+            max_priority_fee_gwei: 2.0,
+            mev_share_hints: vec!["hash".to_string()],
+        }
+    }
+}
+
+// 交易前風險預覽：參數法 VaR 與各項限額，限額為 None 時只報告不檢查
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RiskConfig {
+    // 置信水平對應的標準正態分位數（99% 為 2.33）
+    pub var_z_score: f64,
+    pub horizon_days: f64,
+    // 資產 -> 日波動率，未配置的資產使用 default_daily_volatility
+    pub daily_volatility: HashMap<String, f64>,
+    pub default_daily_volatility: f64,
+    // 不同資產之間統一使用的相關係數
+    pub correlation: f64,
+    // 單一資產佔總敞口的比例上限
+    pub max_asset_concentration: Option<f64>,
+    pub max_margin_utilization: Option<f64>,
+    pub max_var_usd: Option<f64>,
+    // 各 strategy_id 共用同一資金池時的分配額度
+    pub capital: CapitalConfig,
+    // 觸發後暫停交易至 UTC 日終或管理員解除
+    pub daily_limits: DailyLimitsConfig,
+}
+
+// 當日已實現盈虧的止損限額，None 時不限制；回撤為當日盈虧高點到當前值的跌幅
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PnlLimits {
+    pub max_daily_loss: Option<f64>,
+    pub max_drawdown: Option<f64>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DailyLimitsConfig {
+    // 所有策略合計，觸發後暫停整個引擎
+    pub global: PnlLimits,
+    // strategy_id -> 限額，觸發後只暫停該 strategy_id
+    pub strategies: HashMap<String, PnlLimits>,
+}
+
+// 單個 strategy_id 的資金分配，各項為 None 時不限制
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StrategyAllocation {
+    // 執行中請求的名義金額合計上限
+    pub max_notional: Option<f64>,
+    pub max_concurrent: Option<usize>,
+    // 當日（UTC）已實現虧損達到該值後拒絕新執行
+    pub daily_loss_budget: Option<f64>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CapitalConfig {
+    // 未單獨配置的 strategy_id 使用的額度
+    pub default: StrategyAllocation,
+    pub strategies: HashMap<String, StrategyAllocation>,
+}
+
+impl CapitalConfig {
+    pub fn allocation(&self, strategy_id: &str) -> &StrategyAllocation {
+        self.strategies.get(strategy_id).unwrap_or(&self.default)
+    }
+}
+
+impl Default for RiskConfig {
+    fn default() -> Self {
+        Self {
+            var_z_score: 2.33,
+            horizon_days: 1.0,
+            daily_volatility: HashMap::from([("BTC".to_string(), 0.035), ("ETH".to_string(), 0.045)]),
+            default_daily_volatility: 0.06,
+            correlation: 0.8,
+            max_asset_concentration: None,
+            max_margin_utilization: Some(0.8),
+            max_var_usd: Some(50_000.0),
+            capital: CapitalConfig::default(),
+            daily_limits: DailyLimitsConfig::default(),
+        }
+    }
+}
+
+// 待確認交易監控：發現經過目標池子的大額兌換時重新報價或放棄 DEX 腿
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MempoolConfig {
+    // 支持 eth_subscribe 的 WebSocket 節點（也可指向 bloXroute 等兼容網關），未配置時不監控
+    pub ws_url: Option<String>,
+    // 訂閱完整交易對象；節點不支持時改為只推送哈希，再逐筆查詢
+    pub full_transactions: bool,
+    // 低於該美元價值的兌換視為不影響報價
+    pub min_swap_usd: f64,
+    // 觀察到的待確認兌換在該時長內視為衝突
+    pub conflict_window_secs: u64,
+    pub reconnect_secs: u64,
+}
+
+impl Default for MempoolConfig {
+    fn default() -> Self {
+        Self {
+            ws_url: None,
+            full_transactions: true,
+            min_swap_usd: 100_000.0,
+            conflict_window_secs: 15,
+            reconnect_secs: 5,
+        }
+    }
+}
+
+// DEX 現貨腿：與 flash_loan 共用 RPC 與熱錢包，路由合約的代幣授權見 approvals
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DexConfig {
+    // 啟用的 DEX（uniswap_v3 / curve），可作為現貨套利的 primary/secondary_exchange
+    pub venues: Vec<String>,
+    // 最少成交量 = 報價 × (1 - slippage_bps / 10000)
+    pub slippage_bps: f64,
+    pub deadline_secs: u64,
+    // 資產 -> 代幣合約，ETH/BTC 對應 WETH/WBTC
+    pub tokens: HashMap<String, TokenConfig>,
+    pub uniswap_v3: UniswapV3Config,
+    pub curve: CurveConfig,
+    pub approvals: ApprovalConfig,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TokenConfig {
+    pub address: String,
+    pub decimals: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UniswapV3Config {
+    pub quoter_address: String,
+    pub router_address: String,
+    // 池子手續費檔位（百萬分之一），500 即 0.05%
+    pub fee_tier: u32,
+}
+
+impl Default for UniswapV3Config {
+    fn default() -> Self {
+        Self {
+            // 以太坊主網 QuoterV2 與 SwapRouter
+            quoter_address: "0x61fFE014bA17989E743c5F6cB21bF9697530B21e".to_string(),
+            router_address: "0xE592427A0AEce92De3Edee1F18E0157C05861564".to_string(),
+            fee_tier: 500,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CurveConfig {
+    pub pool_address: String,
+    // 池內代幣順序（資產名），決定 get_dy / exchange 的 i、j
+    pub coins: Vec<String>,
+    pub fee_bps: f64,
+}
+
+impl Default for CurveConfig {
+    fn default() -> Self {
+        Self {
+            // 以太坊主網 3pool
+            pool_address: "0xbEbc44782C7dB0a1A60Cb6fe97d0b483032FF1C7".to_string(),
+            coins: vec!["DAI".to_string(), "USDC".to_string(), "USDT".to_string()],
+            fee_bps: 1.0,
+        }
+    }
+}
+
+impl Default for DexConfig {
+    fn default() -> Self {
+        let token = |address: &str, decimals| TokenConfig {
+            address: address.to_string(),
+            decimals,
+        };
+        Self {
+            venues: vec!["uniswap_v3".to_string(), "curve".to_string()],
+            slippage_bps: 30.0,
+            deadline_secs: 60,
+            tokens: HashMap::from([
+                ("USDC".to_string(), token("0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48", 6)),
+                ("USDT".to_string(), token("0xdAC17F958D2ee523a2206206994597C13D831ec7", 6)),
+                ("DAI".to_string(), token("0x6B175474E89094C44Da98b954EedeAC495271d0F", 18)),
+                ("ETH".to_string(), token("0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2", 18)),
+                ("BTC".to_string(), token("0x2260FAC5E5542a773Aa44fBCfeDf7C193bc2C599", 8)),
+            ]),
+            uniswap_v3: UniswapV3Config::default(),
+            curve: CurveConfig::default(),
+            approvals: ApprovalConfig::default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ApprovalMode {
+    // 一次性授權最大額度
+    Max,
+    // 按 exact_amounts 授權，額度不足時補足
+    Exact,
+}
+
+// 路由合約的代幣授權：兌換時授權不足不等待上鏈，直接放棄本次兌換並由後台補授權
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ApprovalConfig {
+    pub mode: ApprovalMode,
+    // exact 模式下各資產的授權數量（資產單位）；未配置的資產只授權兌換所需數量
+    pub exact_amounts: HashMap<String, f64>,
+    // 啟動時為每個熱錢包預先授權全部代幣與路由
+    pub approve_on_startup: bool,
+    // 後台處理授權缺口的間隔
+    pub interval_ms: u64,
+    // 通過 Permit2 拉取代幣的 DEX：代幣先授權給 Permit2，再由 Permit2 授權給路由
+    pub permit2_venues: Vec<String>,
+    pub permit2_address: String,
+    pub permit2_expiration_secs: u64,
+    // 已有非零授權時必須先清零才能改授權的資產（如 USDT）
+    pub reset_before_approve: Vec<String>,
+}
+
+impl Default for ApprovalConfig {
+    fn default() -> Self {
+        Self {
+            mode: ApprovalMode::Max,
+            exact_amounts: HashMap::new(),
+            approve_on_startup: true,
+            interval_ms: 5_000,
+            permit2_venues: Vec::new(),
+            permit2_address: "0x000000000022D473030F116dDEE9F6B43aC78BA3".to_string(),
+            permit2_expiration_secs: 30 * 86_400,
+            reset_before_approve: vec!["USDT".to_string()],
+        }
+    }
+}
+
+// EIP-1559 gas 報價與每次執行的花費上限
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GasConfig {
+    // 未配置時沿用 flash_loan.rpc_url；兩者都為空則只用靜態報價
+    pub rpc_url: Option<String>,
+    pub poll_interval_ms: u64,
+    // eth_feeHistory 回看的區塊數
+    pub fee_history_blocks: u64,
+    // 超過該時長未更新的費率視為過期，退回靜態報價
+    pub stale_after_ms: u64,
+    // 按 min_priority 升序排列，請求 priority 落入的最高檔位決定小費百分位與基礎費餘量
+    pub urgency_tiers: Vec<UrgencyTier>,
+    pub max_gas_limit: u64,
+    // 單次執行 gas_limit × max_fee_per_gas 的上限
+    pub max_fee_per_execution_eth: f64,
+    pub fallback_max_fee_gwei: f64,
+    pub fallback_priority_fee_gwei: f64,
+    // 策略類型 -> 每個 UTC 自然日的鏈上 gas 預算（ETH）；用盡後該策略只走交易所腿，未配置的策略不限
+    pub daily_budget_eth: HashMap<String, f64>,
+    // 鏈上操作（flash_loan / swap / lend）-> 預估消耗的 gas 單位，用於執行前的淨收益檢查
+    pub estimated_gas: HashMap<String, u64>,
+    // 預期收益扣除 gas 與手續費後的最低淨收益（USDT），不足時拒絕執行鏈上路徑
+    pub min_net_profit_usdt: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UrgencyTier {
+    pub name: String,
+    pub min_priority: i32,
+    pub reward_percentile: f64,
+    pub base_fee_multiplier: f64,
+}
+
+impl Default for GasConfig {
+    fn default() -> Self {
+        let tier = |name: &str, min_priority, reward_percentile, base_fee_multiplier| UrgencyTier {
+            name: name.to_string(),
+            min_priority,
+            reward_percentile,
+            base_fee_multiplier,
+        };
+        Self {
+            rpc_url: None,
+            poll_interval_ms: 3_000,
+            fee_history_blocks: 20,
+            stale_after_ms: 30_000,
+            urgency_tiers: vec![
+                tier("low", 0, 10.0, 1.125),
+                tier("normal", 4, 50.0, 1.5),
+                tier("high", 8, 90.0, 2.0),
+            ],
+            max_gas_limit: 5_000_000,
+            max_fee_per_execution_eth: 0.05,
+            fallback_max_fee_gwei: 20.0,
+            fallback_priority_fee_gwei: 1.5,
+            daily_budget_eth: HashMap::from([("funding_rate".to_string(), 0.5)]),
+            estimated_gas: HashMap::from([
+                ("flash_loan".to_string(), 400_000),
+                ("swap".to_string(), 180_000),
+                ("lend".to_string(), 150_000),
+            ]),
+            min_net_profit_usdt: 1.0,
+        }
+    }
+}
+
+// 現貨槓桿：無永續合約的交易對可借幣賣出現貨構建空頭腿
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MarginConfig {
+    // 交易所 -> 未上線永續合約的交易對
+    pub missing_perps: HashMap<String, Vec<String>>,
+    // 交易所 -> 資產 -> 每個資金費週期（8 小時）的借幣利率
+    pub borrow_rates: HashMap<String, HashMap<String, f64>>,
+    // 交易所 -> 資產 -> 可借數量
+    pub borrow_limits: HashMap<String, HashMap<String, f64>>,
+}
+
+// 鏈上閃電貸：未配置 rpc_url 或 receiver_address 時退回模擬執行
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FlashLoanConfig {
+    pub rpc_url: Option<String>,
+    pub chain_id: u64,
+    // 實現各來源回調（executeOperation / receiveFlashLoan / callFunction）的套利合約
+    pub receiver_address: Option<String>,
+    // 借入資產及其精度
+    pub asset_address: String,
+    pub asset_decimals: u32,
+    // 收益歸集地址，默認為套利合約本身
+    pub profit_recipient: Option<String>,
+    // 簽名用熱錢包，提交交易時輪流使用
+    pub wallets: Vec<WalletConfig>,
+    // 在 estimateGas 結果上預留的餘量倍數
+    pub gas_buffer: f64,
+    pub confirmations: usize,
+    // 等待回執達到確認數的上限；超時的交易視為已發送未確認，轉入後台對賬
+    pub receipt_timeout_secs: u64,
+    // 啟用的閃電貸來源（aave / balancer / dydx）；手續費相同時靠前者優先
+    pub providers: Vec<String>,
+    pub aave: AaveConfig,
+    pub balancer: BalancerConfig,
+    pub dydx: DydxConfig,
+}
+
+// 熱錢包私鑰來源：加密 keystore 或環境變量，私鑰本身不寫入配置
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WalletConfig {
+    pub name: String,
+    // Web3 Secret Storage（V3 JSON）keystore 路徑，密碼從 password_env 讀取
+    pub keystore_path: Option<String>,
+    pub password_env: Option<String>,
+    // 未配置 keystore 時從該環境變量讀取十六進制私鑰
+    pub private_key_env: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AaveConfig {
+    pub pool_address: String,
+    // 借入資產對應的 aToken，其持有的資產餘額即可借上限
+    pub a_token_address: String,
+    pub fee_bps: f64,
+    pub referral_code: u16,
+}
+
+impl Default for AaveConfig {
+    fn default() -> Self {
+        Self {
+            // 以太坊主網 Aave V3 Pool 與 aEthUSDC
+            pool_address: "0x87870Bca3F3fD6335C3F4ce8392D69350B4fA4E2".to_string(),
+            a_token_address: "0x98C23E9d8f34FEFb1B7BD6a91B7FF122F4e16F5c".to_string(),
+            fee_bps: 5.0,
+            referral_code: 0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BalancerConfig {
+    pub vault_address: String,
+}
+
+impl Default for BalancerConfig {
+    fn default() -> Self {
+        Self {
+            vault_address: "0xBA12222222228d8Ba445958a75a0704d566BF2C8".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DydxConfig {
+    pub solo_margin_address: String,
+    // 借入資產在 Solo 中的市場編號（USDC 為 2）
+    pub market_id: u64,
+}
+
+impl Default for DydxConfig {
+    fn default() -> Self {
+        Self {
+            solo_margin_address: "0x1E0447b19BB6EcFdAe1e4AE1694b0C3659614e4e".to_string(),
+            market_id: 2,
+        }
+    }
+}
+
+impl Default for FlashLoanConfig {
+    fn default() -> Self {
+        Self {
+            rpc_url: None,
+            chain_id: 1,
+            receiver_address: None,
+            // 以太坊主網 USDC
+            asset_address: "0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48".to_string(),
+            asset_decimals: 6,
+            profit_recipient: None,
+            wallets: vec![WalletConfig {
+                name: "default".to_string(),
+                private_key_env: Some("ARB_WALLET_PRIVATE_KEY".to_string()),
+                ..WalletConfig::default()
+            }],
+            gas_buffer: 1.2,
+            confirmations: 1,
+            receipt_timeout_secs: 180,
+            providers: vec!["balancer".to_string(), "dydx".to_string(), "aave".to_string()],
+            aave: AaveConfig::default(),
+            balancer: BalancerConfig::default(),
+            dydx: DydxConfig::default(),
+        }
+    }
+}
+
+// 跨交易所現貨套利：收益門檻與初始庫存；提幣成本與轉賬時間見 transfer_costs
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SpotArbitrageConfig {
+    pub min_net_edge_bps: f64,
+    // 交易所 -> 資產 -> 初始可用庫存
+    pub inventory: HashMap<String, HashMap<String, f64>>,
+}
+
+impl Default for SpotArbitrageConfig {
+    fn default() -> Self {
+        Self {
+            min_net_edge_bps: 2.0,
+            inventory: HashMap::new(),
+        }
+    }
+}
+
+// 下單前的價格合理性檢查：交易對在各交易所的中間價相對中位數的偏離超過 max_divergence_bps，
+// 或報價/基礎資產中的穩定幣相對其他穩定幣偏離 1 超過 max_stablecoin_deviation_bps 時拒絕執行
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PriceGuardConfig {
+    pub enabled: bool,
+    pub max_divergence_bps: f64,
+    pub max_stablecoin_deviation_bps: f64,
+    // 獨立報價來源少於 min_sources 時無法交叉驗證，同樣拒絕
+    pub min_sources: usize,
+    pub stablecoins: Vec<String>,
+}
+
+impl Default for PriceGuardConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            max_divergence_bps: 100.0,
+            max_stablecoin_deviation_bps: 50.0,
+            min_sources: 2,
+            stablecoins: vec!["USDT".to_string(), "USDC".to_string(), "DAI".to_string()],
+        }
+    }
+}
+
+// 預言機價格：Chainlink 聚合器合約讀取與 Pyth Hermes 接口，以美元計價。作為交易所報價之外的獨立參照，
+// 並用於鏈上抵押品估值；未配置喂價的資產不受影響
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OracleConfig {
+    pub refresh_interval_ms: u64,
+    // 喂價更新時間超過該時長視為過期，不再使用
+    pub max_staleness_secs: u64,
+    // 交易所報價中位數相對預言機價格的偏離上限（基點），超過則價格合理性檢查不通過
+    pub max_deviation_bps: f64,
+    pub hermes_url: String,
+    pub feeds: Vec<OracleFeedConfig>,
+}
+
+impl Default for OracleConfig {
+    fn default() -> Self {
+        Self {
+            refresh_interval_ms: 10_000,
+            max_staleness_secs: 3_600,
+            max_deviation_bps: 150.0,
+            hermes_url: "https://hermes.pyth.network".to_string(),
+            feeds: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OracleSource {
+    Chainlink,
+    Pyth,
+}
+
+// 同一資產配置多個喂價時取未過期喂價的中位數
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OracleFeedConfig {
+    pub asset: String,
+    pub source: OracleSource,
+    // Chainlink 聚合器所在鏈，缺省為默認鏈
+    #[serde(default)]
+    pub chain: Option<String>,
+    // Chainlink 的 AggregatorV3 合約地址
+    #[serde(default)]
+    pub address: String,
+    // Pyth 的價格 ID（十六進制）
+    #[serde(default)]
+    pub price_id: String,
+}
+
+// 跨交易所劃轉的成本與耗時：每個資產可經多條網絡提幣，取兩邊交易所都支持、且數量不低於最小提幣量的
+// 網絡中成本最低的一條。成本為提幣手續費加轉賬期間的價格風險
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TransferCostConfig {
+    // 資產 -> 網絡 -> 成本
+    pub networks: BTreeMap<String, BTreeMap<String, NetworkCost>>,
+    // 轉賬期間每分鐘價格風險（基點）
+    pub price_risk_bps_per_minute: f64,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NetworkCost {
+    // 提幣手續費與最小提幣量（以該資產計）
+    pub withdrawal_fee: f64,
+    pub min_withdrawal: f64,
+    // 提幣到充值入賬的典型耗時（分鐘）
+    pub minutes: f64,
+    // 支持該網絡提幣與充值的交易所，空為全部
+    pub exchanges: Vec<String>,
+}
+
+impl Default for TransferCostConfig {
+    fn default() -> Self {
+        let network = |withdrawal_fee, min_withdrawal, minutes| NetworkCost {
+            withdrawal_fee,
+            min_withdrawal,
+            minutes,
+            exchanges: Vec::new(),
+        };
+        let networks = [
+            ("BTC", vec![("BTC", network(0.0002, 0.001, 30.0))]),
+            ("ETH", vec![("ERC20", network(0.002, 0.01, 5.0)), ("ARBITRUM", network(0.0001, 0.001, 2.0))]),
+            ("USDT", vec![("ERC20", network(5.0, 20.0, 5.0)), ("TRC20", network(1.0, 10.0, 3.0))]),
+            ("USDC", vec![("ERC20", network(5.0, 20.0, 5.0)), ("ARBITRUM", network(0.5, 10.0, 2.0))]),
+        ]
+        .into_iter()
+        .map(|(asset, networks)| {
+            let networks = networks.into_iter().map(|(name, cost)| (name.to_string(), cost)).collect();
+            (asset.to_string(), networks)
+        })
+        .collect();
+        Self {
+            networks,
+            price_risk_bps_per_minute: 0.1,
+        }
+    }
+}
+
+// 智能訂單路由：拆單的交易所數上限與最小子訂單名義金額
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OrderRouterConfig {
+    // 關閉時每條腿只在請求指定的交易所逐檔成交
+    pub enabled: bool,
+    pub max_venues: usize,
+    // 以報價資產計；低於該值的子訂單併入其他交易所
+    pub min_child_notional: f64,
+}
+
+impl Default for OrderRouterConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            max_venues: 3,
+            min_child_notional: 500.0,
+        }
+    }
+}
+
+// TWAP / 冰山單的中止條件與補單節奏
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ExecutionAlgoConfig {
+    // 單片實際滑點超過該值時中止
+    pub max_slice_slippage_bps: f64,
+    // 每片下單前的費率差低於該值或反向時中止
+    pub min_rate_diff: f64,
+    // 連續失敗片數超過該值時中止
+    pub max_failed_slices: u32,
+    pub iceberg_refill_ms: u64,
+    pub max_duration_minutes: f64,
+    pub max_slices: usize,
+}
+
+impl Default for ExecutionAlgoConfig {
+    fn default() -> Self {
+        Self {
+            max_slice_slippage_bps: 15.0,
+            min_rate_diff: 0.0001,
+            max_failed_slices: 2,
+            iceberg_refill_ms: 500,
+            max_duration_minutes: 240.0,
+            max_slices: 500,
+        }
+    }
+}
+
+// 運行時隔離：啟用後客戶端連接、執行與下單在單線程運行時上運行（可綁定到隔離的 CPU 核），
+// 行情採集、掃描、管理接口等後台任務在另一個多線程運行時上運行，避免互相搶佔調度。只在啟動時讀取
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RuntimeConfig {
+    pub isolate_hot_path: bool,
+    // 執行運行時所在線程綁定的 CPU 核；未配置時不綁定
+    pub hot_path_core: Option<usize>,
+    pub background_threads: usize,
+    // 後台運行時的工作線程按順序輪流綁定到這些核；為空時不綁定
+    pub background_cores: Vec<usize>,
+}
+
+impl Default for RuntimeConfig {
+    fn default() -> Self {
+        Self {
+            isolate_hot_path: false,
+            hot_path_core: None,
+            background_threads: 2,
+            background_cores: Vec::new(),
+        }
+    }
+}
+
+// 同機客戶端的 Unix 域套接字監聽：與 TCP 監聽並行，協議與認證相同，不經 TLS；
+// mode 為套接字文件的八進制權限（如 "660" 允許同組進程連接）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UnixSocketConfig {
+    pub enabled: bool,
+    pub path: String,
+    pub mode: String,
+}
+
+impl Default for UnixSocketConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            path: "/tmp/arb-engine.sock".to_string(),
+            mode: "600".to_string(),
+        }
+    }
+}
+
+impl UnixSocketConfig {
+    pub fn permissions(&self) -> Result<u32, String> {
+        file_mode("unix_socket", &self.mode)
+    }
+}
+
+fn file_mode(section: &str, mode: &str) -> Result<u32, String> {
+    u32::from_str_radix(mode, 8)
+        .ok()
+        .filter(|mode| *mode <= 0o777)
+        .ok_or_else(|| format!("{}.mode 不是有效的八進制權限: {}", section, mode))
+}
+
+// 同機客戶端的共享內存傳輸：內存映射文件上一對單生產者單消費者環形緩衝，每條記錄佔一個固定大小的槽位；
+// 引擎以輪詢線程收取請求，先自旋 spin_iterations 次，仍無請求才休眠 idle_sleep_us；自旋需要輪詢線程與客戶端
+// 各有獨立的 CPU 核，共用核時應設為 0。訪問控制依賴文件權限，不經客戶端認證
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SharedMemoryConfig {
+    pub enabled: bool,
+    pub path: String,
+    pub mode: String,
+    pub slots: u32,
+    pub slot_size: u32,
+    pub spin_iterations: u32,
+    pub idle_sleep_us: u64,
+}
+
+impl Default for SharedMemoryConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            path: "/dev/shm/arb-engine.ring".to_string(),
+            mode: "600".to_string(),
+            slots: 1024,
+            slot_size: 4096,
+            spin_iterations: 10_000,
+            idle_sleep_us: 50,
+        }
+    }
+}
+
+impl SharedMemoryConfig {
+    pub fn permissions(&self) -> Result<u32, String> {
+        file_mode("shared_memory", &self.mode)
+    }
+}
+
+// 各交易所下單確認延遲的指數加權平均，及據此估算機會在成交時剩餘收益的衰減模型
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AckLatencyConfig {
+    pub ewma_alpha: f64,
+    // 樣本數不足 min_samples 的交易所使用 default_ack_ms
+    pub min_samples: u64,
+    pub default_ack_ms: f64,
+    // 收益按 exp(-預計成交耗時 / edge_decay_ms) 衰減
+    pub edge_decay_ms: f64,
+}
+
+impl Default for AckLatencyConfig {
+    fn default() -> Self {
+        Self {
+            ewma_alpha: 0.2,
+            min_samples: 5,
+            default_ack_ms: 20.0,
+            edge_decay_ms: 500.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StalePolicy {
+    // 經 REST 重新獲取後執行
+    Refresh,
+    // 拒絕執行
+    Refuse,
+}
+
+// 行情新鮮度：緩存的費率超過 max_age_ms 未更新時按 on_stale 處理
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct QuoteConfig {
+    pub max_age_ms: u64,
+    pub on_stale: StalePolicy,
+}
+
+impl Default for QuoteConfig {
+    fn default() -> Self {
+        Self {
+            max_age_ms: 3_000,
+            on_stale: StalePolicy::Refresh,
+        }
+    }
+}
+
+// 掛單優先執行：請求未指定時使用的掛單有效期與價差內移動比例
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MakerConfig {
+    pub default_time_in_force_ms: u64,
+    pub max_time_in_force_ms: u64,
+    // 0 為掛在本方最優價，越接近 1 越靠近對手價、越容易成交
+    pub default_inside_spread: f64,
+}
+
+impl Default for MakerConfig {
+    fn default() -> Self {
+        Self {
+            default_time_in_force_ms: 2_000,
+            max_time_in_force_ms: 60_000,
+            default_inside_spread: 0.0,
+        }
+    }
+}
+
+// 期現套利的開平倉閾值
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BasisConfig {
+    // 開倉要求：基差 + 預期持有期資金費 - 往返手續費 的最低收益率
+    pub min_entry_edge: f64,
+    // 基差收斂到該值以下時平倉
+    pub exit_basis: f64,
+    pub holding_periods: u32,
+}
+
+impl Default for BasisConfig {
+    fn default() -> Self {
+        Self {
+            min_entry_edge: 0.001,
+            exit_basis: 0.0002,
+            holding_periods: 21,
+        }
+    }
+}
+
+// 借貸利差套利：在借款利率較低的貨幣市場抵押借入，存入存款利率較高的市場，或投入資金費期現頭寸
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LendingConfig {
+    // 後台監控：定期刷新利率，健康因子或利差跌破閾值時自動平倉
+    pub enabled: bool,
+    pub monitor_interval_secs: u64,
+    // 市場名 -> 配置，請求以市場名作為 primary_exchange（借款側）或 secondary_exchange（存款側）
+    pub markets: BTreeMap<String, LendingMarketConfig>,
+    // 開倉要求的最低年化利差：存款年化（或資金費年化）- 借款年化
+    pub min_spread_apr: f64,
+    // 利差收斂到該值以下時平倉
+    pub exit_spread_apr: f64,
+    // 開倉時按該健康因子計算需要存入的抵押品
+    pub target_health_factor: f64,
+    // 健康因子低於該值時平倉
+    pub min_health_factor: f64,
+    // 估算開倉收益時假設的持倉天數
+    pub holding_days: f64,
+    // estimateGas 結果的放大倍數與等待確認數，僅鏈上市場使用
+    pub gas_buffer: f64,
+    pub confirmations: usize,
+}
+
+impl Default for LendingConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            monitor_interval_secs: 60,
+            markets: BTreeMap::new(),
+            min_spread_apr: 0.02,
+            exit_spread_apr: 0.005,
+            target_health_factor: 1.8,
+            min_health_factor: 1.2,
+            holding_days: 30.0,
+            gas_buffer: 1.2,
+            confirmations: 1,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LendingProtocol {
+    CompoundV3,
+    MorphoBlue,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LendingMarketConfig {
+    pub protocol: LendingProtocol,
+    // 所在鏈，缺省為默認鏈；該鏈未配置 RPC 與熱錢包時按 simulation 的利率模型模擬
+    #[serde(default)]
+    pub chain: Option<String>,
+    // 借出/存入的資產與抵押資產；存款側為借貸市場時請求的 symbol 為 抵押資產+借出資產（如 ETHUSDC），
+    // 為交易所時為期現頭寸的交易對
+    pub asset: String,
+    pub collateral: String,
+    // Compound v3 為 Comet 合約，Morpho Blue 為 Morpho 合約
+    #[serde(default)]
+    pub address: String,
+    // Morpho Blue 的市場 id（bytes32）
+    #[serde(default)]
+    pub market_id: String,
+    #[serde(default)]
+    pub asset_token: Option<TokenConfig>,
+    #[serde(default)]
+    pub collateral_token: Option<TokenConfig>,
+    // 清算閾值（Compound 的 liquidateCollateralFactor、Morpho 的 LLTV）
+    pub liquidation_threshold: f64,
+    #[serde(default)]
+    pub simulation: SimulatedRateModel,
+}
+
+// 拐點利率模型：利用率低於 kink 時借款年化按 slope_apr 線性增長，超過後按 jump_apr 增長；
+// 存款年化 = 借款年化 × 利用率 × (1 - reserve_factor)。每次刷新利用率在 utilization 附近隨機遊走
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SimulatedRateModel {
+    pub utilization: f64,
+    pub base_apr: f64,
+    pub slope_apr: f64,
+    pub jump_apr: f64,
+    pub kink: f64,
+    pub reserve_factor: f64,
+}
+
+impl Default for SimulatedRateModel {
+    fn default() -> Self {
+        Self {
+            utilization: 0.8,
+            base_apr: 0.01,
+            slope_apr: 0.04,
+            jump_apr: 1.0,
+            kink: 0.9,
+            reserve_factor: 0.1,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ExchangeConfig {
+    pub maker_fee: f64,
+    pub taker_fee: f64,
+    // 費率查詢的備用端點；與 hedge_delay_ms 同時配置時啟用對沖請求
+    pub backup_base_url: Option<String>,
+    pub hedge_delay_ms: Option<u64>,
+    // 同一交易所其他區域/接入點的 REST 地址，探測延遲後選用最快的健康端點
+    pub gateways: GatewayConfig,
+    // REST 連接池、預熱與保活
+    pub connection_pool: ConnectionPoolConfig,
+    // 資金費結算間隔，結算時間從 UTC 零點起按該間隔對齊
+    pub funding_interval_hours: u64,
+    // 簽名請求允許的時間戳偏差窗口
+    pub recv_window_ms: u64,
+    pub rate_limit: RateLimitConfig,
+    pub retry: RetryConfig,
+    // 用戶數據流 listen key 的續期間隔；未配置表示該交易所不使用 listen key
+    pub listen_key_keepalive_secs: Option<u64>,
+    // 永續合約允許的最高槓桿
+    pub max_leverage: f64,
+    // 維持保證金率，用於計算強平價
+    pub maintenance_margin_rate: f64,
+    pub funding_model: FundingModelConfig,
+    pub simulation: SimulatedExchangeConfig,
+    // 交易環境：simulated 在進程內模擬，testnet/live 連接交易所的測試網或正式網
+    pub environment: TradingEnvironment,
+    // 覆蓋環境對應的默認端點（如 mock_exchange）；simulated 環境下配置後也經 HTTP/WebSocket 調用
+    pub endpoint: Option<EndpointConfig>,
+    // 保存 API 憑證的環境變量名；未配置時按交易所與環境取默認名，如 BINANCE_TESTNET_API_KEY
+    pub api_key_env: Option<String>,
+    pub secret_key_env: Option<String>,
+    pub passphrase_env: Option<String>,
+    // Vault / AWS Secrets Manager 中的密鑰路徑；未配置時為 arbitrage/<交易所>/<testnet|live>
+    pub secret_path: Option<String>,
+    // 內部交易對 -> 交易所原生代碼，覆蓋默認命名規則（如 PEPEUSDT -> 1000PEPEUSDT）
+    pub symbols: HashMap<String, String>,
+    // 子賬戶名 -> 子賬戶配置
+    pub accounts: BTreeMap<String, AccountConfig>,
+}
+
+// 交易所子賬戶：獨立的憑證、限頻配額與賬戶狀態，其餘設置沿用所屬交易所。
+// strategies 中的 strategy_id 未在請求中指定賬戶時使用該子賬戶
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AccountConfig {
+    pub strategies: Vec<String>,
+    // 未配置時按交易所、賬戶與環境取默認名，如 BINANCE_SUB1_TESTNET_API_KEY
+    pub api_key_env: Option<String>,
+    pub secret_key_env: Option<String>,
+    pub passphrase_env: Option<String>,
+    // 未配置時為 arbitrage/<交易所>/<賬戶>/<testnet|live>
+    pub secret_path: Option<String>,
+    // 未配置時與主賬戶的限頻配置相同（配額仍各自獨立）
+    pub rate_limit: Option<RateLimitConfig>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TradingEnvironment {
+    #[default]
+    Simulated,
+    // Binance Futures 測試網、Bybit 測試網、OKX 模擬盤
+    Testnet,
+    Live,
+}
+
+impl ExchangeConfig {
+    // 實際使用的端點：顯式配置優先，否則取環境對應的交易所默認端點；None 表示進程內模擬
+    pub fn resolved_endpoint(&self, exchange: &str) -> Option<EndpointConfig> {
+        if let Some(endpoint) = &self.endpoint {
+            return Some(endpoint.clone());
+        }
+        let (rest_url, ws_url) = match (exchange, self.environment) {
+            (_, TradingEnvironment::Simulated) => return None,
+            ("binance", TradingEnvironment::Testnet) => ("https://testnet.binancefuture.com", "wss://fstream.binancefuture.com"),
+            ("binance", TradingEnvironment::Live) => ("https://fapi.binance.com", "wss://fstream.binance.com"),
+            ("bybit", TradingEnvironment::Testnet) => ("https://api-testnet.bybit.com", "wss://stream-testnet.bybit.com"),
+            ("bybit", TradingEnvironment::Live) => ("https://api.bybit.com", "wss://stream.bybit.com"),
+            // OKX 模擬盤與正式網共用 REST 域名，以 x-simulated-trading 請求頭區分
+            ("okx", TradingEnvironment::Testnet) => ("https://www.okx.com", "wss://wspap.okx.com:8443"),
+            ("okx", TradingEnvironment::Live) => ("https://www.okx.com", "wss://ws.okx.com:8443"),
+            _ => return None,
+        };
+        Some(EndpointConfig {
+            rest_url: rest_url.to_string(),
+            ws_url: ws_url.to_string(),
+            ..EndpointConfig::default()
+        })
+    }
+
+    fn environment_name(&self) -> &'static str {
+        match self.environment {
+            TradingEnvironment::Testnet => "testnet",
+            _ => "live",
+        }
+    }
+
+    pub fn secret_path(&self, exchange: &str) -> String {
+        self.secret_path
+            .clone()
+            .unwrap_or_else(|| format!("arbitrage/{}/{}", exchange, self.environment_name()))
+    }
+
+    // 默認憑證環境變量名的前綴；測試網加 _TESTNET
+    fn env_prefix(&self, name: &str) -> String {
+        match self.environment {
+            TradingEnvironment::Testnet => format!("{}_TESTNET", name.to_uppercase()),
+            _ => name.to_uppercase(),
+        }
+    }
+
+    // 憑證所在環境變量名（API key、密鑰、口令）；正式網為 BINANCE_API_KEY，測試網為 BINANCE_TESTNET_API_KEY
+    pub fn credential_envs(&self, exchange: &str) -> [String; 3] {
+        let prefix = self.env_prefix(exchange);
+        [
+            self.api_key_env.clone().unwrap_or_else(|| format!("{}_API_KEY", prefix)),
+            self.secret_key_env.clone().unwrap_or_else(|| format!("{}_SECRET_KEY", prefix)),
+            self.passphrase_env.clone().unwrap_or_else(|| format!("{}_PASSPHRASE", prefix)),
+        ]
+    }
+
+    // 子賬戶連接器的設置：憑證位置與限頻按子賬戶配置，其餘沿用交易所設置
+    pub fn account_settings(&self, exchange: &str, name: &str, account: &AccountConfig) -> ExchangeConfig {
+        let prefix = self.env_prefix(&format!("{}_{}", exchange, name));
+        ExchangeConfig {
+            api_key_env: Some(account.api_key_env.clone().unwrap_or_else(|| format!("{}_API_KEY", prefix))),
+            secret_key_env: Some(account.secret_key_env.clone().unwrap_or_else(|| format!("{}_SECRET_KEY", prefix))),
+            passphrase_env: Some(account.passphrase_env.clone().unwrap_or_else(|| format!("{}_PASSPHRASE", prefix))),
+            secret_path: Some(
+                account
+                    .secret_path
+                    .clone()
+                    .unwrap_or_else(|| format!("arbitrage/{}/{}/{}", exchange, name, self.environment_name())),
+            ),
+            rate_limit: account.rate_limit.clone().unwrap_or_else(|| self.rate_limit.clone()),
+            accounts: BTreeMap::new(),
+            ..self.clone()
+        }
+    }
+}
+
+// 除默認端點外的候選 REST 端點；未配置時不探測
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GatewayConfig {
+    pub rest_urls: Vec<String>,
+    pub probe_interval_secs: u64,
+    // 連續失敗（探測或請求）達到該次數的端點標記為不健康，當前端點不健康時切換到最快的健康端點
+    pub failover_after: u32,
+}
+
+impl Default for GatewayConfig {
+    fn default() -> Self {
+        Self {
+            rest_urls: Vec::new(),
+            probe_interval_secs: 30,
+            failover_after: 2,
+        }
+    }
+}
+
+// 每個交易所共用一個 REST 客戶端：空閒連接保留在池中，啟動時與每隔 keepalive_interval_secs
+// 向各端點並發發送 warm_connections 個輕量請求，使空閒後的第一筆訂單不必重新建立 TLS 連接
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ConnectionPoolConfig {
+    pub max_idle_per_host: usize,
+    pub idle_timeout_secs: u64,
+    pub tcp_keepalive_secs: u64,
+    pub warm_connections: usize,
+    pub keepalive_interval_secs: u64,
+    // 直接以 HTTP/2 連接（prior knowledge），只對確認支持 HTTP/2 的端點開啟
+    pub http2: bool,
+}
+
+impl Default for ConnectionPoolConfig {
+    fn default() -> Self {
+        Self {
+            max_idle_per_host: 8,
+            idle_timeout_secs: 90,
+            tcp_keepalive_secs: 30,
+            warm_connections: 2,
+            keepalive_interval_secs: 20,
+            http2: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EndpointConfig {
+    pub rest_url: String,
+    pub ws_url: String,
+    // 單個 REST 請求的超時
+    pub timeout_ms: u64,
+}
+
+impl Default for EndpointConfig {
+    fn default() -> Self {
+        Self {
+            rest_url: String::new(),
+            ws_url: String::new(),
+            timeout_ms: 5_000,
+        }
+    }
+}
+
+// 模擬交易所的響應延遲、拒單與部分成交；時間與隨機數取自注入引擎的環境
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SimulatedExchangeConfig {
+    // 行情請求延遲的均勻分佈範圍（微秒），另有一定比例出現毫秒級長尾
+    pub request_latency_us: (u64, u64),
+    pub slow_request_rate: f64,
+    pub slow_request_ms: (u64, u64),
+    // 下單確認延遲
+    pub order_latency_us: u64,
+    // 每筆訂單被拒（排隊被搶先成交）的概率
+    pub reject_rate: f64,
+    // 部分成交的概率及部分成交時的最低成交比例
+    pub partial_fill_rate: f64,
+    pub min_fill_ratio: f64,
+    // 掛在本方最優價的 post-only 單在有效期內全部成交的概率，掛得越靠近對手價越高
+    pub maker_fill_rate: f64,
+    // 模擬賬戶初始的 USDT 保證金；spot_arbitrage.inventory 配置了 USDT 時以其為準
+    pub collateral_usdt: f64,
+}
+
+impl Default for SimulatedExchangeConfig {
+    fn default() -> Self {
+        Self {
+            request_latency_us: (200, 500),
+            slow_request_rate: 0.05,
+            slow_request_ms: (20, 50),
+            order_latency_us: 50,
+            reject_rate: 0.05,
+            partial_fill_rate: 0.0,
+            min_fill_ratio: 0.5,
+            maker_fill_rate: 0.6,
+            collateral_usdt: 100_000.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PredictionSource {
+    // 由溢價指數與利率分量自行計算
+    PremiumIndex,
+    // 使用交易所公佈的預測費率
+    Exchange,
+}
+
+// 預測資金費率模型；費率與利率均按每個結算週期計
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FundingModelConfig {
+    pub source: PredictionSource,
+    pub interest_rate: f64,
+    // 利率分量 I - P 的截斷範圍
+    pub premium_clamp: f64,
+    // 預測費率的絕對值上限
+    pub rate_cap: f64,
+    // 計算 impact bid / ask 的吃單名義金額
+    pub impact_notional: f64,
+}
+
+impl Default for FundingModelConfig {
+    fn default() -> Self {
+        Self {
+            source: PredictionSource::PremiumIndex,
+            interest_rate: 0.0001,
+            premium_clamp: 0.0005,
+            rate_cap: 0.0075,
+            impact_notional: 10_000.0,
+        }
+    }
+}
+
+impl Default for ExchangeConfig {
+    fn default() -> Self {
+        Self {
+            maker_fee: 0.0002,
+            taker_fee: 0.0005,
+            backup_base_url: None,
+            hedge_delay_ms: None,
+            gateways: GatewayConfig::default(),
+            connection_pool: ConnectionPoolConfig::default(),
+            funding_interval_hours: 8,
+            recv_window_ms: 5_000,
+            rate_limit: RateLimitConfig::default(),
+            retry: RetryConfig::default(),
+            listen_key_keepalive_secs: None,
+            max_leverage: 20.0,
+            maintenance_margin_rate: 0.005,
+            funding_model: FundingModelConfig::default(),
+            simulation: SimulatedExchangeConfig::default(),
+            environment: TradingEnvironment::Simulated,
+            endpoint: None,
+            api_key_env: None,
+            secret_key_env: None,
+            passphrase_env: None,
+            secret_path: None,
+            symbols: HashMap::new(),
+            accounts: BTreeMap::new(),
+        }
+    }
+}
+
+// 交易所會話重連退避與心跳
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SessionConfig {
+    pub initial_backoff_ms: u64,
+    pub max_backoff_ms: u64,
+    // 退避時間的隨機浮動比例
+    pub jitter: f64,
+    pub heartbeat_secs: u64,
+    // 連續重連失敗達到該次數後標記為降級
+    pub degraded_after_failures: u32,
+}
+
+impl Default for SessionConfig {
+    fn default() -> Self {
+        Self {
+            initial_backoff_ms: 500,
+            max_backoff_ms: 30_000,
+            jitter: 0.2,
+            heartbeat_secs: 15,
+            degraded_after_failures: 3,
+        }
+    }
+}
+
+// 強平距離低於 derisk_distance 時的處理方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeriskAction {
+    AlertOnly,
+    // 同時縮減該腿與對沖腿 reduce_fraction 的名義金額
+    Reduce,
+    // 逐倉持倉追加保證金使距離恢復到 target_distance，全倉或餘額不足時改為縮減
+    AddMargin,
+}
+
+// 強平距離監控
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LiquidationConfig {
+    pub enabled: bool,
+    pub interval_secs: u64,
+    // 標記價到強平價的相對距離閾值
+    pub alert_distance: f64,
+    pub derisk_distance: f64,
+    pub action: DeriskAction,
+    pub reduce_fraction: f64,
+    // 縮減後剩餘名義金額低於該值時直接平倉
+    pub min_position_notional: f64,
+    pub target_distance: f64,
+}
+
+impl Default for LiquidationConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            interval_secs: 5,
+            alert_distance: 0.15,
+            derisk_distance: 0.08,
+            action: DeriskAction::Reduce,
+            reduce_fraction: 0.25,
+            min_position_notional: 100.0,
+            target_distance: 0.15,
+        }
+    }
+}
+
+// 資金費率套利持倉對的結算跟蹤與平倉
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FundingExitConfig {
+    pub enabled: bool,
+    pub check_interval_secs: u64,
+    // 預測的下一期費率差低於該值即視為不利
+    pub exit_spread: f64,
+    // 連續多少次檢查不利後平倉
+    pub confirmations: u32,
+    // 保留的已平倉持倉對數
+    pub closed_history: usize,
+}
+
+impl Default for FundingExitConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            check_interval_secs: 60,
+            exit_spread: 0.0,
+            confirmations: 2,
+            closed_history: 100,
+        }
+    }
+}
+
+// 下單前保證金檢查
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PreTradeConfig {
+    pub enabled: bool,
+    // 請求未指定時資金費率套利兩條永續腿的槓桿倍數與保證金模式
+    pub default_leverage: f64,
+    pub default_margin_mode: MarginMode,
+    // 計入可用保證金的資產，按 1:1 計價
+    pub collateral_assets: Vec<String>,
+    // 初始保證金之外額外預留的比例
+    pub margin_buffer: f64,
+    // 保證金不足時是否縮減下單金額，縮減後低於請求金額的該比例則拒絕
+    pub allow_downsize: bool,
+    pub min_fill_fraction: f64,
+}
+
+impl Default for PreTradeConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            default_leverage: 2.0,
+            default_margin_mode: MarginMode::Cross,
+            collateral_assets: vec!["USDT".to_string(), "USDC".to_string()],
+            margin_buffer: 0.1,
+            allow_downsize: true,
+            min_fill_fraction: 0.25,
+        }
+    }
+}
+
+// 交易所 REST 請求遇到瞬時錯誤（5xx、超時、連接失敗）時按操作類別重試；業務拒絕與限頻不重試
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RetryConfig {
+    pub market_data: RetryPolicy,
+    // 重試下單前先按 client_order_id 查詢，訂單已到達交易所時不再重複提交
+    pub order: RetryPolicy,
+    pub cancel: RetryPolicy,
+}
+
+impl Default for RetryConfig {
+    fn default() -> Self {
+        Self {
+            market_data: RetryPolicy::default(),
+            order: RetryPolicy {
+                max_attempts: 2,
+                base_delay_ms: 50,
+                max_delay_ms: 200,
+                jitter: 0.2,
+            },
+            cancel: RetryPolicy {
+                max_attempts: 5,
+                base_delay_ms: 100,
+                max_delay_ms: 2_000,
+                jitter: 0.2,
+            },
+        }
+    }
+}
+
+// 第 n 次重試前等待 base_delay_ms * 2^(n-1)，不超過 max_delay_ms，再乘以 1 ± jitter 的隨機因子
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RetryPolicy {
+    // 含首次請求的總次數，1 表示不重試
+    pub max_attempts: u32,
+    pub base_delay_ms: u64,
+    pub max_delay_ms: u64,
+    pub jitter: f64,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            base_delay_ms: 100,
+            max_delay_ms: 1_000,
+            jitter: 0.2,
+        }
+    }
+}
+
+// 交易所限頻：窗口內請求權重上限與下單數上限
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RateLimitConfig {
+    pub weight_limit: u32,
+    pub weight_window_secs: u64,
+    pub order_limit: u32,
+    pub order_window_secs: u64,
+    pub order_weight: u32,
+    pub market_data_weight: u32,
+    // 為下單保留的權重比例，行情請求不能動用
+    pub reserved_weight_fraction: f64,
+    pub max_order_wait_ms: u64,
+    pub max_market_data_wait_ms: u64,
+    // 回報窗口內已用權重的響應頭（如 Binance 的 x-mbx-used-weight-1m）
+    pub used_weight_header: Option<String>,
+}
+
+impl RateLimitConfig {
+    fn validate(&self, path: &str) -> Result<(), String> {
+        if self.weight_limit == 0 || self.weight_window_secs == 0 || self.order_limit == 0 || self.order_window_secs == 0 {
+            return Err(format!("{} 的上限與窗口必須大於 0", path));
+        }
+        if self.order_weight > self.weight_limit || self.market_data_weight > self.weight_limit {
+            return Err(format!("{} 的單次請求權重不能超過 weight_limit", path));
+        }
+        if !(0.0..1.0).contains(&self.reserved_weight_fraction) {
+            return Err(format!("{}.reserved_weight_fraction 必須介於 0 與 1 之間", path));
+        }
+        Ok(())
+    }
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        Self {
+            weight_limit: 1200,
+            weight_window_secs: 60,
+            order_limit: 50,
+            order_window_secs: 10,
+            order_weight: 1,
+            market_data_weight: 1,
+            reserved_weight_fraction: 0.2,
+            max_order_wait_ms: 2000,
+            max_market_data_wait_ms: 200,
+            used_weight_header: None,
+        }
+    }
+}
+
+// 套利機會掃描器參數
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ScannerConfig {
+    pub enabled: bool,
+    pub interval_secs: u64,
+    // 扣除攤銷手續費後每期最低淨收益（資金費率單位）
+    pub min_net_edge: f64,
+    // 開平倉手續費按預期持有的資金費期數攤銷
+    pub holding_periods: u32,
+    pub publish_top: usize,
+    // 自動執行及其限額
+    pub auto_execute: bool,
+    pub auto_execute_amount: f64,
+    pub max_auto_executions_per_hour: u32,
+}
+
+impl Default for ScannerConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            interval_secs: 10,
+            min_net_edge: 0.00005,
+            holding_periods: 21,
+            publish_top: 10,
+            auto_execute: false,
+            auto_execute_amount: 1_000.0,
+            max_auto_executions_per_hour: 10,
+        }
+    }
+}
+
+// 最大名義金額自動校準參數（金額單位 USDT）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SizingConfig {
+    // 硬性上下限，校準結果永遠不會超出
+    pub min_notional: f64,
+    pub max_notional: f64,
+    pub initial_notional: f64,
+    // 滑點模型：參考金額下的預期滑點，按金額平方根縮放
+    pub slippage_model_bps: f64,
+    pub model_reference_notional: f64,
+    // 實際滑點超過模型 (1 + tolerance) 倍時收縮
+    pub shrink_tolerance: f64,
+    pub shrink_factor: f64,
+    // 連續若干筆成交優於模型後緩慢放大
+    pub growth_factor: f64,
+    pub grow_after_fills: u32,
+    // 請求指定 sizing 時按風險預算與實現波動率計算金額
+    pub volatility: VolatilitySizingConfig,
+}
+
+// 波動率定倉：金額 = 風險預算 / (日波動率 × √持有天數)，再以訂單簿深度的一定比例封頂，不超過請求金額
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct VolatilitySizingConfig {
+    // 請求未指定時使用的風險預算（USDT，一個標準差的價格波動造成的損失）
+    pub risk_budget: f64,
+    pub horizon_days: f64,
+    // 每個交易對保留的價格樣本數（每分鐘一個），樣本不足 min_samples 時使用 risk.daily_volatility
+    pub lookback: usize,
+    pub min_samples: usize,
+    // 兩側訂單簿前若干檔中較淺一側可吃掉的比例
+    pub max_depth_fraction: f64,
+}
+
+impl Default for VolatilitySizingConfig {
+    fn default() -> Self {
+        Self {
+            risk_budget: 1_000.0,
+            horizon_days: 1.0,
+            lookback: 24 * 60,
+            min_samples: 30,
+            max_depth_fraction: 0.1,
+        }
+    }
+}
+
+impl Default for SizingConfig {
+    fn default() -> Self {
+        Self {
+            min_notional: 100.0,
+            max_notional: 100_000.0,
+            initial_notional: 20_000.0,
+            slippage_model_bps: 2.0,
+            model_reference_notional: 10_000.0,
+            shrink_tolerance: 0.2,
+            shrink_factor: 0.8,
+            growth_factor: 1.05,
+            grow_after_fills: 10,
+            volatility: VolatilitySizingConfig::default(),
+        }
+    }
+}
+
+impl EngineConfig {
+    // 未設置 ARB_ENGINE_CONFIG 且默認文件不存在時使用內置默認值
+    pub fn load() -> Result<Self, String> {
+        match Self::path() {
+            Some(path) => Self::load_from(&path),
+            None => Ok(Self::default()),
+        }
+    }
+
+    // 配置文件路徑：ARB_ENGINE_CONFIG，否則為存在的默認文件
+    pub fn path() -> Option<String> {
+        match std::env::var("ARB_ENGINE_CONFIG") {
+            Ok(path) => Some(path),
+            Err(_) if Path::new(DEFAULT_CONFIG_PATH).exists() => Some(DEFAULT_CONFIG_PATH.to_string()),
+            Err(_) => None,
+        }
+    }
+
+    pub fn load_from(path: &str) -> Result<Self, String> {
+        let content = std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+        let config: Self = serde_json::from_str(&content).map_err(|e| format!("{}: {}", path, e))?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    // 相對當前配置需重啟才能生效的頂層配置項。可熱加載的是 risk、disabled_strategies、config_reload、
+    // scanner 與 liquidation 中除 enabled/interval_secs 外的字段，以及各交易所的 maker_fee/taker_fee
+    pub fn restart_required(&self, new: &EngineConfig) -> Vec<String> {
+        let mut candidate = new.clone();
+        candidate.risk = self.risk.clone();
+        candidate.disabled_strategies = self.disabled_strategies.clone();
+        candidate.config_reload = self.config_reload.clone();
+        candidate.scanner = ScannerConfig {
+            enabled: new.scanner.enabled,
+            interval_secs: new.scanner.interval_secs,
+            ..self.scanner.clone()
+        };
+        candidate.liquidation = LiquidationConfig {
+            enabled: new.liquidation.enabled,
+            interval_secs: new.liquidation.interval_secs,
+            ..self.liquidation.clone()
+        };
+        // 未配置的交易所按默認設置比較
+        let mut current = self.clone();
+        for name in self.exchanges.keys().chain(new.exchanges.keys()) {
+            let settings = current.exchanges.entry(name.clone()).or_default().clone();
+            let exchange = candidate.exchanges.entry(name.clone()).or_default();
+            exchange.maker_fee = settings.maker_fee;
+            exchange.taker_fee = settings.taker_fee;
+        }
+        let (serde_json::Value::Object(current), serde_json::Value::Object(candidate)) =
+            (serde_json::json!(current), serde_json::json!(candidate))
+        else {
+            return vec!["engine".to_string()];
+        };
+        current
+            .iter()
+            .filter(|(key, value)| candidate.get(*key) != Some(*value))
+            .map(|(key, _)| key.clone())
+            .collect()
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        let sizing = &self.sizing;
+        if !(0.0 < sizing.min_notional && sizing.min_notional <= sizing.max_notional) {
+            return Err("sizing.min_notional 必須大於 0 且不超過 max_notional".to_string());
+        }
+        if !(0.0 < sizing.shrink_factor && sizing.shrink_factor < 1.0) {
+            return Err("sizing.shrink_factor 必須介於 0 與 1 之間".to_string());
+        }
+        if sizing.growth_factor < 1.0 {
+            return Err("sizing.growth_factor 不能小於 1".to_string());
+        }
+        let volatility = &sizing.volatility;
+        if !(volatility.risk_budget > 0.0 && volatility.horizon_days > 0.0) {
+            return Err("sizing.volatility.risk_budget 與 horizon_days 必須大於 0".to_string());
+        }
+        if volatility.min_samples < 2 || volatility.lookback < volatility.min_samples {
+            return Err("sizing.volatility.min_samples 至少為 2 且不超過 lookback".to_string());
+        }
+        if !(0.0 < volatility.max_depth_fraction && volatility.max_depth_fraction <= 1.0) {
+            return Err("sizing.volatility.max_depth_fraction 必須介於 0 與 1 之間".to_string());
+        }
+        if self.scanner.interval_secs == 0 || self.scanner.holding_periods == 0 {
+            return Err("scanner.interval_secs 與 scanner.holding_periods 必須大於 0".to_string());
+        }
+        match self.secrets.provider {
+            SecretsBackend::Vault if !self.secrets.vault.address.starts_with("http://") && !self.secrets.vault.address.starts_with("https://") => {
+                return Err("secrets.vault.address 必須是 http(s) 地址".to_string());
+            }
+            SecretsBackend::AwsSecretsManager if self.secrets.aws.region.is_empty() => {
+                return Err("secrets.aws.region 不能為空".to_string());
+            }
+            _ => {}
+        }
+        for (index, sink) in self.alerts.sinks.iter().enumerate() {
+            let missing = match sink.kind {
+                AlertSinkKind::Telegram => sink.token_env.is_none() || sink.chat_id.is_none(),
+                _ => sink.url_env.is_none(),
+            };
+            if missing {
+                return Err(format!("alerts.sinks[{}]：telegram 需配置 token_env 與 chat_id，其他通道需配置 url_env", index));
+            }
+        }
+        let bus = &self.event_bus;
+        if bus.enabled && (bus.batch_size == 0 || bus.poll_interval_ms == 0 || bus.retry_secs == 0 || bus.timeout_ms == 0) {
+            return Err("event_bus.batch_size、poll_interval_ms、retry_secs 與 timeout_ms 必須大於 0".to_string());
+        }
+        let reconciliation = &self.reconciliation;
+        if reconciliation.interval_secs == 0 || !(reconciliation.position_tolerance >= 0.0 && reconciliation.balance_tolerance >= 0.0) {
+            return Err("reconciliation.interval_secs 必須大於 0，position_tolerance 與 balance_tolerance 不能為負".to_string());
+        }
+        let scheduler = &self.scheduler;
+        if scheduler.enabled && (scheduler.tick_ms == 0 || scheduler.max_pending == 0) {
+            return Err("scheduler.tick_ms 與 max_pending 必須大於 0".to_string());
+        }
+        let guard = &self.price_guard;
+        if !(guard.max_divergence_bps > 0.0 && guard.max_stablecoin_deviation_bps > 0.0) || guard.min_sources == 0 {
+            return Err("price_guard.max_divergence_bps 與 max_stablecoin_deviation_bps 必須大於 0，min_sources 至少為 1".to_string());
+        }
+        let oracle = &self.oracle;
+        if !(oracle.refresh_interval_ms > 0 && oracle.max_staleness_secs > 0 && oracle.max_deviation_bps > 0.0) {
+            return Err("oracle.refresh_interval_ms、max_staleness_secs 與 max_deviation_bps 必須大於 0".to_string());
+        }
+        for feed in &oracle.feeds {
+            let missing = match feed.source {
+                OracleSource::Chainlink => feed.address.is_empty(),
+                OracleSource::Pyth => feed.price_id.is_empty(),
+            };
+            if feed.asset.is_empty() || missing {
+                return Err(format!("oracle.feeds 中 {} 的 {:?} 喂價缺少資產、address 或 price_id", feed.asset, feed.source));
+            }
+            if let Some(chain) = feed.chain.as_deref().filter(|chain| *chain != DEFAULT_CHAIN && !self.chains.contains_key(*chain)) {
+                return Err(format!("oracle.feeds 中 {} 的喂價使用了未配置的鏈 {}", feed.asset, chain));
+            }
+        }
+        let maker = &self.maker;
+        if maker.default_time_in_force_ms > maker.max_time_in_force_ms || !(0.0..1.0).contains(&maker.default_inside_spread) {
+            return Err("maker.default_time_in_force_ms 不能超過 max_time_in_force_ms，default_inside_spread 必須在 [0, 1) 內".to_string());
+        }
+        if self.runtime.background_threads == 0 {
+            return Err("runtime.background_threads 必須大於 0".to_string());
+        }
+        if self.runtime.hot_path_core.is_some_and(|core| self.runtime.background_cores.contains(&core)) {
+            return Err("runtime.hot_path_core 不能同時出現在 background_cores 中".to_string());
+        }
+        if self.unix_socket.enabled {
+            if self.unix_socket.path.is_empty() {
+                return Err("unix_socket 啟用時需要配置 path".to_string());
+            }
+            self.unix_socket.permissions()?;
+        }
+        let shared_memory = &self.shared_memory;
+        if shared_memory.enabled {
+            if shared_memory.path.is_empty() || shared_memory.slots == 0 {
+                return Err("shared_memory 啟用時需要配置 path，slots 必須大於 0".to_string());
+            }
+            // 槽位按 8 字節對齊，除記錄頭外至少容納一條常規響應
+            if shared_memory.slot_size < 1024 || !shared_memory.slot_size.is_multiple_of(8) {
+                return Err("shared_memory.slot_size 至少為 1024 且為 8 的倍數".to_string());
+            }
+            if self.client_auth.enabled {
+                return Err("shared_memory 不經客戶端認證，不能與 client_auth 同時啟用".to_string());
+            }
+            shared_memory.permissions()?;
+        }
+        let ack = &self.ack_latency;
+        if !(0.0 < ack.ewma_alpha && ack.ewma_alpha <= 1.0) || ack.default_ack_ms.is_nan() || ack.default_ack_ms < 0.0 || ack.edge_decay_ms.is_nan() || ack.edge_decay_ms <= 0.0 {
+            return Err("ack_latency.ewma_alpha 必須介於 0 與 1 之間，default_ack_ms 不能為負，edge_decay_ms 必須大於 0".to_string());
+        }
+        if self.quotes.max_age_ms == 0 {
+            return Err("quotes.max_age_ms 必須大於 0".to_string());
+        }
+        let price_risk = self.transfer_costs.price_risk_bps_per_minute;
+        if price_risk.is_nan() || price_risk < 0.0 {
+            return Err("transfer_costs.price_risk_bps_per_minute 不能為負".to_string());
+        }
+        for (asset, networks) in &self.transfer_costs.networks {
+            for (network, cost) in networks {
+                if [cost.withdrawal_fee, cost.min_withdrawal, cost.minutes].iter().any(|value| value.is_nan() || *value < 0.0) {
+                    return Err(format!("transfer_costs.networks.{}.{} 的手續費、最小提幣量與耗時不能為負", asset, network));
+                }
+            }
+        }
+        let rebalance = &self.rebalance;
+        if rebalance.interval_secs == 0 || !(rebalance.tolerance > 0.0 && rebalance.tolerance < 1.0) {
+            return Err("rebalance.interval_secs 必須大於 0，tolerance 必須介於 0 與 1 之間".to_string());
+        }
+        if !(rebalance.min_transfer > 0.0 && rebalance.min_transfer <= rebalance.max_transfer && rebalance.max_transfer <= rebalance.daily_limit) {
+            return Err("rebalance 需滿足 0 < min_transfer <= max_transfer <= daily_limit".to_string());
+        }
+        for (asset, targets) in &rebalance.targets {
+            if targets.len() < 2 || targets.values().any(|weight| weight.is_nan() || *weight < 0.0) || (targets.values().sum::<f64>() - 1.0).abs() > 1e-6 {
+                return Err(format!("rebalance.targets.{} 需至少兩個賬戶，佔比非負且之和為 1", asset));
+            }
+            for key in targets.keys() {
+                let configured = match key.split_once('@') {
+                    Some((exchange, account)) => self.exchanges.get(exchange).is_some_and(|settings| settings.accounts.contains_key(account)),
+                    None => true,
+                };
+                if !configured {
+                    return Err(format!("rebalance.targets.{} 中的 {} 未在 exchanges 中配置", asset, key));
+                }
+            }
+        }
+        for (exchange, addresses) in &rebalance.withdrawal_addresses {
+            if addresses.values().any(|address| address.address.is_empty() || address.network.is_empty()) {
+                return Err(format!("rebalance.withdrawal_addresses.{} 的地址與網絡不能為空", exchange));
+            }
+        }
+        if self.config_reload.watch && self.config_reload.poll_interval_secs == 0 {
+            return Err("config_reload.poll_interval_secs 必須大於 0".to_string());
+        }
+        for (name, exchange) in &self.exchanges {
+            // 掛單費率可為負（返佣），吃單費率不能
+            if exchange.taker_fee < 0.0 {
+                return Err(format!("exchanges.{}.taker_fee 不能為負", name));
+            }
+            crate::symbols::VenueSymbols::new(name, &exchange.symbols)?;
+            if exchange.hedge_delay_ms.is_some() && exchange.backup_base_url.is_none() {
+                return Err(format!("exchanges.{}.hedge_delay_ms 需要同時配置 backup_base_url", name));
+            }
+            if exchange.funding_interval_hours == 0 || 24 % exchange.funding_interval_hours != 0 {
+                return Err(format!("exchanges.{}.funding_interval_hours 必須能整除 24", name));
+            }
+            if exchange.recv_window_ms == 0 || exchange.recv_window_ms > 60_000 {
+                return Err(format!("exchanges.{}.recv_window_ms 必須在 1 ~ 60000 之間", name));
+            }
+            exchange.rate_limit.validate(&format!("exchanges.{}.rate_limit", name))?;
+            let mut assigned = HashSet::new();
+            for (account, settings) in &exchange.accounts {
+                if account.is_empty() || account == MAIN_ACCOUNT || !account.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+                    return Err(format!(
+                        "exchanges.{}.accounts 的賬戶名 {:?} 只能包含字母、數字與下劃線，且不能為 {}",
+                        name, account, MAIN_ACCOUNT
+                    ));
+                }
+                if let Some(limit) = &settings.rate_limit {
+                    limit.validate(&format!("exchanges.{}.accounts.{}.rate_limit", name, account))?;
+                }
+                if let Some(strategy_id) = settings.strategies.iter().find(|strategy_id| !assigned.insert(*strategy_id)) {
+                    return Err(format!("exchanges.{}.accounts 中 {} 對應多個子賬戶", name, strategy_id));
+                }
+            }
+            let retry = &exchange.retry;
+            for (operation, policy) in [("market_data", &retry.market_data), ("order", &retry.order), ("cancel", &retry.cancel)] {
+                if policy.max_attempts == 0 || policy.base_delay_ms > policy.max_delay_ms || !(0.0..=1.0).contains(&policy.jitter) {
+                    return Err(format!(
+                        "exchanges.{}.retry.{}：max_attempts 必須大於 0，base_delay_ms 不能超過 max_delay_ms，jitter 必須在 0 ~ 1 之間",
+                        name, operation
+                    ));
+                }
+            }
+            if exchange.max_leverage < 1.0 {
+                return Err(format!("exchanges.{}.max_leverage 不能小於 1", name));
+            }
+            if !(0.0..1.0).contains(&exchange.maintenance_margin_rate) {
+                return Err(format!("exchanges.{}.maintenance_margin_rate 必須介於 0 與 1 之間", name));
+            }
+            let model = &exchange.funding_model;
+            if model.premium_clamp < 0.0 || model.rate_cap <= 0.0 || model.impact_notional <= 0.0 {
+                return Err(format!(
+                    "exchanges.{}.funding_model 的 premium_clamp 不能為負，rate_cap 與 impact_notional 必須大於 0",
+                    name
+                ));
+            }
+            if exchange.listen_key_keepalive_secs == Some(0) {
+                return Err(format!("exchanges.{}.listen_key_keepalive_secs 必須大於 0", name));
+            }
+            let simulation = &exchange.simulation;
+            let probabilities = [
+                simulation.slow_request_rate,
+                simulation.reject_rate,
+                simulation.partial_fill_rate,
+                simulation.maker_fill_rate,
+            ];
+            if probabilities.iter().any(|p| !(0.0..=1.0).contains(p)) || !(0.0..=1.0).contains(&simulation.min_fill_ratio) {
+                return Err(format!("exchanges.{}.simulation 的概率與 min_fill_ratio 必須介於 0 與 1 之間", name));
+            }
+            if simulation.request_latency_us.0 > simulation.request_latency_us.1
+                || simulation.slow_request_ms.0 > simulation.slow_request_ms.1
+            {
+                return Err(format!("exchanges.{}.simulation 的延遲範圍下限不能超過上限", name));
+            }
+            if exchange.environment != TradingEnvironment::Simulated && !["binance", "bybit", "okx"].contains(&name.as_str()) {
+                return Err(format!("exchanges.{} 沒有 REST 接口實現，只能使用 simulated 環境", name));
+            }
+            if exchange.gateways.rest_urls.iter().any(|url| !url.starts_with("http://") && !url.starts_with("https://")) {
+                return Err(format!("exchanges.{}.gateways.rest_urls 必須是 http(s) 地址", name));
+            }
+            let pool = &exchange.connection_pool;
+            if pool.keepalive_interval_secs == 0 || pool.keepalive_interval_secs >= pool.idle_timeout_secs {
+                return Err(format!("exchanges.{}.connection_pool.keepalive_interval_secs 必須大於 0 且小於 idle_timeout_secs", name));
+            }
+            if pool.warm_connections > pool.max_idle_per_host {
+                return Err(format!("exchanges.{}.connection_pool.warm_connections 不能超過 max_idle_per_host", name));
+            }
+            if exchange.gateways.probe_interval_secs == 0 || exchange.gateways.failover_after == 0 {
+                return Err(format!("exchanges.{}.gateways 的 probe_interval_secs 與 failover_after 必須大於 0", name));
+            }
+            if let Some(endpoint) = &exchange.endpoint {
+                if !endpoint.rest_url.starts_with("http://") && !endpoint.rest_url.starts_with("https://") {
+                    return Err(format!("exchanges.{}.endpoint.rest_url 必須是 http(s) 地址", name));
+                }
+                if !endpoint.ws_url.starts_with("ws://") && !endpoint.ws_url.starts_with("wss://") {
+                    return Err(format!("exchanges.{}.endpoint.ws_url 必須是 ws(s) 地址", name));
+                }
+                if endpoint.timeout_ms == 0 {
+                    return Err(format!("exchanges.{}.endpoint.timeout_ms 必須大於 0", name));
+                }
+            }
+        }
+        let order_router = &self.order_router;
+        if order_router.max_venues == 0 || order_router.min_child_notional < 0.0 {
+            return Err("order_router.max_venues 必須大於 0 且 min_child_notional 不能為負".to_string());
+        }
+        let sessions = &self.sessions;
+        if sessions.initial_backoff_ms == 0 || sessions.max_backoff_ms < sessions.initial_backoff_ms {
+            return Err("sessions.initial_backoff_ms 必須大於 0 且不超過 max_backoff_ms".to_string());
+        }
+        if !(0.0..1.0).contains(&sessions.jitter) || sessions.heartbeat_secs == 0 || sessions.degraded_after_failures == 0 {
+            return Err("sessions.jitter 必須介於 0 與 1 之間，heartbeat_secs 與 degraded_after_failures 必須大於 0".to_string());
+        }
+        let pre_trade = &self.pre_trade;
+        if pre_trade.default_leverage < 1.0 || pre_trade.margin_buffer < 0.0 || !(0.0..=1.0).contains(&pre_trade.min_fill_fraction) {
+            return Err("pre_trade.default_leverage 不能小於 1，margin_buffer 不能為負且 min_fill_fraction 介於 0 與 1 之間".to_string());
+        }
+        let liquidation = &self.liquidation;
+        if liquidation.interval_secs == 0 || !(0.0 < liquidation.derisk_distance && liquidation.derisk_distance <= liquidation.alert_distance) {
+            return Err("liquidation.interval_secs 必須大於 0 且 0 < derisk_distance <= alert_distance".to_string());
+        }
+        if !(0.0 < liquidation.reduce_fraction && liquidation.reduce_fraction <= 1.0) || liquidation.target_distance <= liquidation.derisk_distance || liquidation.min_position_notional < 0.0 {
+            return Err("liquidation.reduce_fraction 必須介於 0 與 1 之間，target_distance 大於 derisk_distance 且 min_position_notional 不能為負".to_string());
+        }
+        let funding_exit = &self.funding_exit;
+        if funding_exit.check_interval_secs == 0 || funding_exit.confirmations == 0 {
+            return Err("funding_exit.check_interval_secs 與 funding_exit.confirmations 必須大於 0".to_string());
+        }
+        let algo = &self.execution_algo;
+        if algo.max_slice_slippage_bps <= 0.0 || algo.max_duration_minutes <= 0.0 || algo.max_slices == 0 {
+            return Err("execution_algo.max_slice_slippage_bps、max_duration_minutes 與 max_slices 必須大於 0".to_string());
+        }
+        if self.chains.contains_key(DEFAULT_CHAIN) {
+            return Err(format!("chains 不能包含默認鏈 {}，請使用頂層 flash_loan / gas / dex", DEFAULT_CHAIN));
+        }
+        let mut chain_ids = HashMap::new();
+        for (name, chain) in &self.chain_configs() {
+            if let Some(other) = chain_ids.insert(chain.chain_id, name) {
+                return Err(format!("鏈 {} 與 {} 的 chain_id 相同: {}", name, other, chain.chain_id));
+            }
+            chain.validate(name)?;
+        }
+        let mempool = &self.mempool;
+        if mempool.min_swap_usd < 0.0 || mempool.conflict_window_secs == 0 || mempool.reconnect_secs == 0 {
+            return Err("mempool.min_swap_usd 不能為負且 conflict_window_secs 與 reconnect_secs 必須大於 0".to_string());
+        }
+        let risk = &self.risk;
+        if risk.var_z_score <= 0.0 || risk.horizon_days <= 0.0 || !(-1.0..=1.0).contains(&risk.correlation) {
+            return Err("risk.var_z_score 與 risk.horizon_days 必須大於 0 且 risk.correlation 介於 -1 與 1 之間".to_string());
+        }
+        if let Some((asset, _)) = risk.daily_volatility.iter().find(|(_, volatility)| **volatility < 0.0) {
+            return Err(format!("risk.daily_volatility.{} 不能為負", asset));
+        }
+        let allocations = std::iter::once(("default", &risk.capital.default))
+            .chain(risk.capital.strategies.iter().map(|(strategy_id, allocation)| (strategy_id.as_str(), allocation)));
+        for (strategy_id, allocation) in allocations {
+            let negative = [allocation.max_notional, allocation.daily_loss_budget].into_iter().flatten().any(|limit| limit < 0.0);
+            if negative || allocation.max_concurrent == Some(0) {
+                return Err(format!("risk.capital.{} 的 max_notional 與 daily_loss_budget 不能為負且 max_concurrent 必須大於 0", strategy_id));
+            }
+        }
+        let limits = std::iter::once(("global", &risk.daily_limits.global))
+            .chain(risk.daily_limits.strategies.iter().map(|(strategy_id, limits)| (strategy_id.as_str(), limits)));
+        for (scope, limits) in limits {
+            if [limits.max_daily_loss, limits.max_drawdown].into_iter().flatten().any(|limit| limit < 0.0) {
+                return Err(format!("risk.daily_limits.{} 的 max_daily_loss 與 max_drawdown 不能為負", scope));
+            }
+        }
+        let bundle = &self.bundle;
+        if !matches!(bundle.relay.as_str(), "flashbots" | "mev_share") {
+            return Err(format!("bundle.relay 必須為 flashbots 或 mev_share: {}", bundle.relay));
+        }
+        if bundle.max_blocks == 0 || bundle.max_priority_fee_gwei <= 0.0 {
+            return Err("bundle.max_blocks 與 bundle.max_priority_fee_gwei 必須大於 0".to_string());
+        }
+        let routing = &self.routing;
+        if routing.max_clock_skew_ms == 0 || routing.timeout_ms == 0 {
+            return Err("routing.max_clock_skew_ms 與 routing.timeout_ms 必須大於 0".to_string());
+        }
+        let tls = &self.tls;
+        if tls.enabled {
+            if tls.cert_path.is_empty() || tls.key_path.is_empty() {
+                return Err("tls 啟用時需要配置 cert_path 與 key_path".to_string());
+            }
+            if tls.client_ca_path.is_none() && (tls.require_client_cert || !routing.peers.is_empty()) {
+                return Err("tls.require_client_cert 或區域實例互連需要配置 tls.client_ca_path".to_string());
+            }
+        }
+        let timeouts = &self.stage_timeouts;
+        if timeouts.rate_fetch_ms == 0 || timeouts.leg_submission_ms == 0 || timeouts.fill_confirmation_ms == 0 {
+            return Err("stage_timeouts 各階段超時必須大於 0".to_string());
+        }
+        let time_sync = &self.time_sync;
+        if time_sync.enabled && (time_sync.interval_secs == 0 || time_sync.samples == 0) {
+            return Err("time_sync 的 interval_secs 與 samples 必須大於 0".to_string());
+        }
+        let queue = &self.execution_queue;
+        if queue.enabled && (queue.max_pending == 0 || queue.max_concurrent_per_exchange == 0 || queue.max_wait_ms == 0) {
+            return Err("execution_queue 的 max_pending、max_concurrent_per_exchange 與 max_wait_ms 必須大於 0".to_string());
+        }
+        if queue.preemption.enabled && queue.preemption.min_priority_gap < 1 {
+            return Err("execution_queue.preemption.min_priority_gap 必須大於 0".to_string());
+        }
+        if self.shutdown.drain_timeout_secs == 0 {
+            return Err("shutdown.drain_timeout_secs 必須大於 0".to_string());
+        }
+        let connections = &self.connections;
+        if connections.heartbeat_interval_ms == 0 || connections.idle_timeout_ms <= connections.heartbeat_interval_ms {
+            return Err("connections.heartbeat_interval_ms 必須大於 0 且小於 idle_timeout_ms".to_string());
+        }
+        let client_auth = &self.client_auth;
+        if client_auth.enabled {
+            if client_auth.clients.is_empty() || client_auth.max_clock_skew_ms == 0 {
+                return Err("client_auth 啟用時需要至少一個客戶端且 max_clock_skew_ms 大於 0".to_string());
+            }
+            let mut names = HashSet::new();
+            for client in &client_auth.clients {
+                if client.name.is_empty() || client.secret_env.is_empty() || client.scopes.is_empty() {
+                    return Err("client_auth.clients 需要配置 name、secret_env 與 scopes".to_string());
+                }
+                if !names.insert(&client.name) {
+                    return Err(format!("重複的客戶端: {}", client.name));
+                }
+            }
+        }
+        let admin_api = &self.admin_api;
+        if admin_api.enabled && (admin_api.listen_address.is_empty() || admin_api.token_env.is_empty()) {
+            return Err("admin_api 啟用時需要配置 listen_address 與 token_env".to_string());
+        }
+        if admin_api.max_market_data_age_secs == 0 {
+            return Err("admin_api.max_market_data_age_secs 必須大於 0".to_string());
+        }
+        let lending = &self.lending;
+        if lending.monitor_interval_secs == 0 || lending.gas_buffer < 1.0 {
+            return Err("lending.monitor_interval_secs 必須大於 0，gas_buffer 不能小於 1".to_string());
+        }
+        if !(1.0 < lending.min_health_factor && lending.min_health_factor < lending.target_health_factor) {
+            return Err("lending 需滿足 1 < min_health_factor < target_health_factor".to_string());
+        }
+        if lending.holding_days <= 0.0 {
+            return Err("lending.holding_days 必須大於 0".to_string());
+        }
+        if lending.exit_spread_apr >= lending.min_spread_apr {
+            return Err("lending.exit_spread_apr 必須小於 min_spread_apr".to_string());
+        }
+        for (name, market) in &lending.markets {
+            if self.exchanges.contains_key(name) {
+                return Err(format!("lending.markets.{} 與交易所同名", name));
+            }
+            if let Some(chain) = market.chain.as_deref().filter(|chain| *chain != DEFAULT_CHAIN && !self.chains.contains_key(*chain)) {
+                return Err(format!("lending.markets.{} 的鏈 {} 未配置", name, chain));
+            }
+            if market.asset.is_empty() || market.collateral.is_empty() || market.asset == market.collateral {
+                return Err(format!("lending.markets.{} 的 asset 與 collateral 不能為空且不能相同", name));
+            }
+            if !(0.0 < market.liquidation_threshold && market.liquidation_threshold < 1.0) {
+                return Err(format!("lending.markets.{}.liquidation_threshold 必須介於 0 與 1 之間", name));
+            }
+            if market.protocol == LendingProtocol::MorphoBlue && market.market_id.is_empty() {
+                return Err(format!("lending.markets.{} 使用 Morpho Blue 時需要配置 market_id", name));
+            }
+            let model = &market.simulation;
+            let in_unit = |value: f64| (0.0..1.0).contains(&value);
+            if !(in_unit(model.utilization) && in_unit(model.reserve_factor) && model.kink > 0.0 && model.kink < 1.0) {
+                return Err(format!("lending.markets.{}.simulation 的 utilization、kink 與 reserve_factor 必須介於 0 與 1 之間", name));
+            }
+        }
+        let mut routed = HashMap::new();
+        for peer in &routing.peers {
+            if peer.name.is_empty() || peer.address.is_empty() {
+                return Err("routing.peers 中的實例需要配置 name 與 address".to_string());
+            }
+            for venue in &peer.venues {
+                if let Some(other) = routed.insert(venue, &peer.name) {
+                    return Err(format!("交易所 {} 同時路由到 {} 與 {}", venue, other, peer.name));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // 默認鏈在前，其餘按名稱排列
+    pub fn chain_configs(&self) -> Vec<(String, ChainConfig)> {
+        let default = ChainConfig {
+            chain_id: self.flash_loan.chain_id,
+            rpc_url: self.flash_loan.rpc_url.clone(),
+            rpc: self.rpc.clone(),
+            native_token: "ETH".to_string(),
+            native_token_price_usd: 0.0,
+            flash_loan: self.flash_loan.clone(),
+            gas: self.gas.clone(),
+            dex: self.dex.clone(),
+            tx_simulation: self.tx_simulation.clone(),
+            subscription: self.subscription.clone(),
+        };
+        std::iter::once((DEFAULT_CHAIN.to_string(), default))
+            .chain(self.chains.iter().map(|(name, chain)| (name.clone(), chain.clone())))
+            .collect()
+    }
+}
@@ -0,0 +1,157 @@
+use super::config::{self, EngineConfig};
+use super::{events, ExecutionEngine};
+use serde::Serialize;
+use std::collections::{BTreeSet, HashSet};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+use tracing::{debug, error, info};
+
+// 校驗新配置並應用其中可熱加載的部分，返回變更的配置項。校驗失敗或含需重啟的變更時整體拒絕，
+// 不應用任何一項；通過後各子系統依次替換配置，並發的加載按 applied_config 的鎖串行執行
+pub fn apply(engine: &ExecutionEngine, new: EngineConfig) -> Result<Vec<String>, String> {
+    new.validate()?;
+    let mut applied = engine.applied_config.lock().unwrap();
+    let restart = applied.restart_required(&new);
+    if !restart.is_empty() {
+        return Err(format!("以下配置需重啟才能生效: {}", restart.join(", ")));
+    }
+
+    let mut changes = Vec::new();
+    if changed(&applied.risk, &new.risk) {
+        engine.risk.reconfigure(new.risk.clone());
+        changes.push(("risk".to_string(), serde_json::json!(new.risk)));
+    }
+    if changed(&applied.scanner, &new.scanner) {
+        engine.scanner.reconfigure(new.scanner.clone());
+        changes.push(("scanner".to_string(), serde_json::json!(new.scanner)));
+    }
+    if changed(&applied.liquidation, &new.liquidation) {
+        engine.liquidation.reconfigure(new.liquidation.clone());
+        changes.push(("liquidation".to_string(), serde_json::json!(new.liquidation)));
+    }
+    // 未配置的交易所使用默認費率
+    let names: BTreeSet<&String> = applied.exchanges.keys().chain(new.exchanges.keys()).collect();
+    for name in names {
+        let current = applied.exchanges.get(name).cloned().unwrap_or_default();
+        let exchange = new.exchanges.get(name).cloned().unwrap_or_default();
+        if current.taker_fee != exchange.taker_fee {
+            // 子賬戶沿用所屬交易所的費率
+            for (_, connector) in engine.exchanges.iter().filter(|(key, _)| config::venue(key) == name.as_str()) {
+                *connector.taker_fee.write().unwrap() = exchange.taker_fee;
+            }
+            changes.push((format!("exchanges.{}.taker_fee", name), serde_json::json!(exchange.taker_fee)));
+        }
+        if current.maker_fee != exchange.maker_fee {
+            changes.push((format!("exchanges.{}.maker_fee", name), serde_json::json!(exchange.maker_fee)));
+        }
+    }
+    let disabled: HashSet<_> = new.disabled_strategies.iter().copied().collect();
+    if applied.disabled_strategies.iter().copied().collect::<HashSet<_>>() != disabled {
+        *engine.disabled_strategies.write().unwrap() = disabled;
+        changes.push(("disabled_strategies".to_string(), serde_json::json!(new.disabled_strategies)));
+    }
+    if changed(&applied.config_reload, &new.config_reload) {
+        changes.push(("config_reload".to_string(), serde_json::json!(new.config_reload)));
+    }
+
+    *applied = new;
+    let keys: Vec<String> = changes.iter().map(|(key, _)| key.clone()).collect();
+    for (key, value) in changes {
+        engine.events.append(events::EngineEvent::ConfigChanged { key, value });
+    }
+    if !keys.is_empty() {
+        info!(changed = ?keys, "配置已熱加載");
+    }
+    Ok(keys)
+}
+
+fn changed<T: Serialize>(current: &T, new: &T) -> bool {
+    serde_json::json!(current) != serde_json::json!(new)
+}
+
+// 按 config_reload.poll_interval_secs 輪詢配置文件的修改時間，變更後重新加載；
+// 被拒絕的配置只記錄錯誤，引擎保持當前配置繼續運行
+pub fn spawn(engine: Arc<ExecutionEngine>) {
+    let Some(path) = EngineConfig::path() else {
+        info!("未使用配置文件，不監視配置變更");
+        return;
+    };
+    if !engine.config.config_reload.watch {
+        info!("配置文件監視未啟用");
+        return;
+    }
+    tokio::spawn(async move {
+        let mut modified = modified_at(&path);
+        loop {
+            let reload = engine.applied_config.lock().unwrap().config_reload.clone();
+            tokio::time::sleep(Duration::from_secs(reload.poll_interval_secs)).await;
+            let current = modified_at(&path);
+            if !reload.watch || current == modified {
+                continue;
+            }
+            modified = current;
+            match EngineConfig::load_from(&path).and_then(|config| apply(&engine, config)) {
+                Ok(changed) if changed.is_empty() => debug!(%path, "配置文件已修改，無可熱加載的變更"),
+                Ok(_) => {}
+                Err(error) => error!(%path, %error, "配置熱加載被拒絕，保留當前配置"),
+            }
+        }
+    });
+}
+
+fn modified_at(path: &str) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|metadata| metadata.modified()).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::deterministic_sim::build;
+    use crate::{Environment, StrategyType};
+
+    fn engine(name: &str) -> (ExecutionEngine, std::path::PathBuf) {
+        build(&format!("reload-{}", name), config::EngineConfig::default(), Environment::simulated(0, 1))
+    }
+
+    #[tokio::test]
+    async fn reload_applies_limits_fees_and_strategies() {
+        let (engine, path) = engine("apply");
+        let mut new = config::EngineConfig::default();
+        new.risk.max_var_usd = Some(1_000.0);
+        new.scanner.min_net_edge = 0.002;
+        new.exchanges.entry("okx".to_string()).or_default().taker_fee = 0.0003;
+        new.disabled_strategies = vec![StrategyType::Triangular];
+
+        let mut changed = apply(&engine, new.clone()).unwrap();
+        changed.sort();
+        assert_eq!(changed, ["disabled_strategies", "exchanges.okx.taker_fee", "risk", "scanner"]);
+        assert_eq!(engine.taker_fee("okx"), 0.0003);
+        assert_eq!(engine.scanner.config().min_net_edge, 0.002);
+        assert!(engine.disabled_strategies.read().unwrap().contains(&StrategyType::Triangular));
+        assert_eq!(engine.events.current().config["exchanges.okx.taker_fee"], serde_json::json!(0.0003));
+        // 再次加載相同配置沒有變更
+        assert!(apply(&engine, new).unwrap().is_empty());
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[tokio::test]
+    async fn reload_rejects_restart_only_and_invalid_changes() {
+        let (engine, path) = engine("reject");
+        let mut restart = config::EngineConfig::default();
+        restart.risk.max_var_usd = Some(1_000.0);
+        restart.scanner.interval_secs = 5;
+        restart.exchanges.entry("binance".to_string()).or_default().recv_window_ms = 10_000;
+        let error = apply(&engine, restart).unwrap_err();
+        assert!(error.contains("exchanges") && error.contains("scanner"), "{}", error);
+
+        let mut invalid = config::EngineConfig::default();
+        invalid.exchanges.entry("okx".to_string()).or_default().taker_fee = 0.0003;
+        invalid.liquidation.derisk_distance = invalid.liquidation.alert_distance * 2.0;
+        assert!(apply(&engine, invalid).is_err());
+
+        // 被拒絕的配置一項都不生效
+        assert_eq!(engine.taker_fee("okx"), config::ExchangeConfig::default().taker_fee);
+        assert_eq!(engine.applied_config.lock().unwrap().risk.max_var_usd, config::RiskConfig::default().max_var_usd);
+        let _ = std::fs::remove_file(path);
+    }
+}
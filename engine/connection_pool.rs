@@ -0,0 +1,67 @@
+use super::{rate_limit, ExchangeConnector, ExecutionEngine};
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{debug, warn};
+
+pub fn spawn(engine: Arc<ExecutionEngine>) {
+    for (exchange, connector) in engine.exchanges.iter() {
+        // 進程內模擬的連接器沒有 HTTP 連接
+        if connector.api.is_none() || connector.settings.connection_pool.warm_connections == 0 {
+            continue;
+        }
+        let engine = Arc::clone(&engine);
+        let exchange = exchange.clone();
+        tokio::spawn(async move {
+            let Some(connector) = engine.exchanges.get(&exchange) else { return };
+            let interval = Duration::from_secs(connector.settings.connection_pool.keepalive_interval_secs);
+            while !engine.shutting_down.load(Ordering::Relaxed) {
+                if let Err(error) = warm(connector).await {
+                    warn!(%exchange, %error, "預熱 REST 連接失敗");
+                }
+                tokio::time::sleep(interval).await;
+            }
+        });
+    }
+}
+
+// 向每個候選端點並發發送 warm_connections 個服務器時間請求，在連接池中建立或保持同等數量的連接；
+// 返回成功的請求數
+pub(crate) async fn warm(connector: &ExchangeConnector) -> Result<usize, String> {
+    let Some(api) = &connector.api else { return Ok(0) };
+    let connections = connector.settings.connection_pool.warm_connections;
+    let mut warmed = 0;
+    for url in connector.gateways.urls() {
+        connector
+            .scheduler
+            .acquire(rate_limit::RequestKind::MarketData, connections as u32)
+            .await?;
+        let started_ms = connector.env.now_ms();
+        let results = futures::future::join_all((0..connections).map(|_| api.server_time(&url))).await;
+        let succeeded = results.iter().filter(|result| result.is_ok()).count();
+        debug!(exchange = %connector.name, %url, succeeded, elapsed_ms = connector.env.now_ms() - started_ms, "REST 連接已預熱");
+        warmed += succeeded;
+    }
+    Ok(warmed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock_exchange::Scenario;
+    use crate::mock_exchange_e2e::engine;
+    use crate::config;
+
+    #[tokio::test]
+    async fn connection_pool_warms_each_gateway() {
+        let (mock, engine, path) = engine("pool", Scenario::default()).await;
+        let binance = &engine.exchanges["binance"];
+        assert_eq!(warm(binance).await.unwrap(), 2);
+        assert_eq!(mock.requests().iter().filter(|request| *request == "GET /fapi/v1/time").count(), 2);
+
+        let mut config = config::EngineConfig::default();
+        config.exchanges.entry("binance".to_string()).or_default().connection_pool.warm_connections = 9;
+        assert!(config.validate().is_err());
+        let _ = std::fs::remove_file(path);
+    }
+}
@@ -0,0 +1,175 @@
+use super::scanner::Opportunity;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::sync::Mutex;
+use std::time::Instant;
+
+// 指數加權平均的權重
+const EWMA_ALPHA: f64 = 0.2;
+// 價差平均存活時間等於該值時，存活時間分項為 1/e
+const REFERENCE_LIFETIME_SECS: f64 = 60.0;
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct CrowdingMetric {
+    pub symbol: String,
+    pub attempts: u64,
+    pub beaten: u64,
+    pub beaten_rate: f64,
+    pub collapses: u64,
+    pub mean_lifetime_secs: Option<f64>,
+    pub score: f64,
+}
+
+#[derive(Default)]
+struct State {
+    metrics: HashMap<String, CrowdingMetric>,
+    // 仍然存在的機會 (symbol, short, long) -> 首次發現時間
+    open: HashMap<(String, String, String), Instant>,
+}
+
+pub struct CrowdingTracker {
+    state: Mutex<State>,
+}
+
+fn ewma(previous: Option<f64>, sample: f64) -> f64 {
+    match previous {
+        Some(p) => p + EWMA_ALPHA * (sample - p),
+        None => sample,
+    }
+}
+
+// 兩個分項各佔一半：被搶先比例，以及價差存活時間越短越擁擠
+fn update_score(metric: &mut CrowdingMetric) {
+    let collapse = metric
+        .mean_lifetime_secs
+        .map(|t| (-t / REFERENCE_LIFETIME_SECS).exp())
+        .unwrap_or(0.0);
+    metric.score = (0.5 * metric.beaten_rate + 0.5 * collapse).clamp(0.0, 1.0);
+}
+
+impl CrowdingTracker {
+    pub fn new() -> Self {
+        Self {
+            state: Mutex::new(State::default()),
+        }
+    }
+
+    // 比較本輪與上一輪的機會集合，記錄已消失機會的存活時間
+    pub fn observe_scan(&self, opportunities: &[Opportunity]) {
+        let now = Instant::now();
+        let mut state = self.state.lock().unwrap();
+        let current: HashSet<(String, String, String)> = opportunities
+            .iter()
+            .map(|o| (o.symbol.clone(), o.short_exchange.clone(), o.long_exchange.clone()))
+            .collect();
+
+        let collapsed: Vec<_> = state
+            .open
+            .iter()
+            .filter(|(key, _)| !current.contains(*key))
+            .map(|(key, first_seen)| (key.clone(), now.duration_since(*first_seen).as_secs_f64()))
+            .collect();
+        for (key, lifetime) in collapsed {
+            state.open.remove(&key);
+            let metric = state.metrics.entry(key.0.clone()).or_insert_with(|| CrowdingMetric {
+                symbol: key.0.clone(),
+                ..CrowdingMetric::default()
+            });
+            metric.collapses += 1;
+            metric.mean_lifetime_secs = Some(ewma(metric.mean_lifetime_secs, lifetime));
+            update_score(metric);
+        }
+        for key in current {
+            state.open.entry(key).or_insert(now);
+        }
+    }
+
+    // beaten 表示訂單因價差已被他人吃掉或排隊落後而未成交
+    pub fn record_attempt(&self, symbol: &str, beaten: bool) {
+        let mut state = self.state.lock().unwrap();
+        let metric = state.metrics.entry(symbol.to_string()).or_insert_with(|| CrowdingMetric {
+            symbol: symbol.to_string(),
+            ..CrowdingMetric::default()
+        });
+        metric.attempts += 1;
+        if beaten {
+            metric.beaten += 1;
+        }
+        let sample = if beaten { 1.0 } else { 0.0 };
+        metric.beaten_rate = ewma((metric.attempts > 1).then_some(metric.beaten_rate), sample);
+        update_score(metric);
+    }
+
+    pub fn score(&self, symbol: &str) -> f64 {
+        self.state
+            .lock()
+            .unwrap()
+            .metrics
+            .get(symbol)
+            .map(|m| m.score)
+            .unwrap_or(0.0)
+    }
+
+    pub fn snapshot(&self) -> Vec<CrowdingMetric> {
+        let state = self.state.lock().unwrap();
+        let mut metrics: Vec<_> = state.metrics.values().cloned().collect();
+        metrics.sort_by(|a, b| b.score.total_cmp(&a.score));
+        metrics
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bench::START_MS;
+    use crate::deterministic_sim::{build, connect_sessions, funded_config, request};
+    use crate::{funding_history, ArbitrageRequest, EngineCommand, Environment};
+    use std::sync::Arc;
+
+    // 重驗時費率差已被抹平計為被搶先；機會在下一輪掃描中消失記一次價差崩塌，存活越短越擁擠
+    #[tokio::test]
+    async fn crowding_scores_beaten_executions_and_collapsing_spreads() {
+        let (engine, path) = build("crowding", funded_config(), Environment::simulated(START_MS, 1));
+        let engine = Arc::new(engine);
+        connect_sessions(&engine).await;
+        for (exchange, btc, eth) in [("binance", 0.0001, 0.0008), ("bybit", 0.0001, 0.0001)] {
+            engine.quotes.record_predicted_rate(exchange, "BTCUSDT", btc, START_MS);
+            engine.quotes.record_predicted_rate(exchange, "ETHUSDT", eth, START_MS);
+        }
+        let beaten = engine.execute_funding_rate_arbitrage(request(1_000.0)).await;
+        assert_eq!(beaten.error_message.as_deref(), Some("資金費率差異太小"));
+        let filled = engine.execute_funding_rate_arbitrage(ArbitrageRequest { symbol: "ETHUSDT".to_string(), ..request(1_000.0) }).await;
+        assert_eq!(filled.status, "success", "{:?}", filled.error_message);
+        // 只有被搶先比例一項
+        assert_eq!(engine.crowding.score("BTCUSDT"), 0.5);
+        assert_eq!(engine.crowding.score("ETHUSDT"), 0.0);
+        assert_eq!(engine.crowding.score("SOLUSDT"), 0.0);
+
+        let sample = |exchange: &str, predicted_rate: f64| funding_history::FundingSample {
+            exchange: exchange.to_string(),
+            symbol: "BTCUSDT".to_string(),
+            sampled_at_ms: START_MS,
+            funding_rate: predicted_rate,
+            predicted_rate,
+        };
+        let opportunities = engine.scanner.rank(&[sample("binance", 0.0010), sample("bybit", 0.0001)], |_| 0.0);
+        assert_eq!(opportunities.len(), 1);
+        engine.crowding.observe_scan(&opportunities);
+        engine.crowding.observe_scan(&opportunities);
+        assert_eq!(engine.crowding.score("BTCUSDT"), 0.5);
+        // 機會幾乎立即消失，存活時間分項接近 1
+        engine.crowding.observe_scan(&[]);
+        assert!(engine.crowding.score("BTCUSDT") > 0.99);
+
+        let response = engine.handle_command(EngineCommand::GetCrowding).await;
+        // 按擁擠度從高到低排列
+        let metrics = response.data.unwrap();
+        let summary: Vec<_> = metrics
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|m| (m["symbol"].as_str().unwrap(), m["attempts"].as_u64().unwrap(), m["beaten"].as_u64().unwrap(), m["collapses"].as_u64().unwrap()))
+            .collect();
+        assert_eq!(summary, [("BTCUSDT", 1, 1, 1), ("ETHUSDT", 1, 0, 0)]);
+        let _ = std::fs::remove_file(path);
+    }
+}
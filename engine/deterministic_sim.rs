@@ -0,0 +1,324 @@
+use super::*;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use bench::START_MS;
+
+pub(crate) fn engine(name: &str, seed: u64, simulation: config::SimulatedExchangeConfig) -> (ExecutionEngine, PathBuf) {
+    let mut config = config::EngineConfig::default();
+    for exchange in ["binance", "bybit", "okx"] {
+        config.exchanges.entry(exchange.to_string()).or_default().simulation = simulation.clone();
+    }
+    build(&format!("{}-{}", name, seed), config, Environment::simulated(START_MS, seed))
+}
+
+// 不啟動後台任務的引擎，見 bench::assemble
+pub(crate) fn build(name: &str, config: config::EngineConfig, env: Environment) -> (ExecutionEngine, PathBuf) {
+    bench::assemble(name, config, env).unwrap()
+}
+
+pub(crate) fn request(amount: f64) -> ArbitrageRequest {
+    serde_json::from_value(serde_json::json!({
+        "strategy_id": "sim",
+        "symbol": "BTCUSDT",
+        "primary_exchange": "binance",
+        "secondary_exchange": "bybit",
+        "amount": amount,
+        "priority": 5,
+        "timestamp": START_MS.to_string()
+    }))
+    .unwrap()
+}
+
+// binance 與 bybit 各有 100k USDT 保證金且不拒單的配置
+pub(crate) fn funded_config() -> config::EngineConfig {
+    let mut config = config::EngineConfig::default();
+    for exchange in ["binance", "bybit", "okx"] {
+        config.exchanges.entry(exchange.to_string()).or_default().simulation.reject_rate = 0.0;
+        config.spot_arbitrage.inventory.insert(exchange.to_string(), [("USDT".to_string(), 100_000.0)].into());
+    }
+    config
+}
+
+// 啟動會話監督並等待 binance 與 bybit 的模擬連接就緒
+pub(crate) async fn connect_sessions(engine: &Arc<ExecutionEngine>) {
+    session::spawn(Arc::clone(engine));
+    for _ in 0..250 {
+        if ["binance", "bybit"].iter().all(|exchange| engine.sessions.is_available(exchange)) {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+}
+
+// 連續執行若干筆，記錄每筆的成交比例（被拒為 None）與利潤
+pub(crate) async fn run(engine: &ExecutionEngine, executions: usize) -> Vec<Option<(f64, f64)>> {
+    let gas_quote = engine.default_chain().gas_optimizer.quote(5);
+    let mut results = Vec::new();
+    for _ in 0..executions {
+        let result = engine.execute_flash_loan_arbitrage("sim", &request(1_000.0), 0.0005, &gas_quote, false, None).await;
+        results.push(result.ok().map(|outcome| (outcome.fill_ratio(), outcome.profit)));
+    }
+    results
+}
+
+#[tokio::test]
+async fn same_seed_reproduces_outcomes() {
+    let simulation = config::SimulatedExchangeConfig {
+        reject_rate: 0.3,
+        partial_fill_rate: 0.3,
+        ..Default::default()
+    };
+    let (first, first_path) = engine("repro-a", 7, simulation.clone());
+    let (second, second_path) = engine("repro-b", 7, simulation.clone());
+    let (other, other_path) = engine("repro-c", 8, simulation);
+    let expected = run(&first, 50).await;
+    assert_eq!(expected, run(&second, 50).await);
+    assert_ne!(expected, run(&other, 50).await);
+    assert!(expected.iter().any(Option::is_none), "拒單率 0.3 下 50 筆應有被拒");
+    assert_eq!(first.env.now_ms(), second.env.now_ms());
+    for path in [first_path, second_path, other_path] {
+        let _ = std::fs::remove_file(path);
+    }
+}
+
+// 歷史存儲為臨時目錄中的 SQLite 文件，記帳寫入該存儲
+pub(crate) async fn with_history(engine: ExecutionEngine, name: &str) -> (ExecutionEngine, Arc<storage::HistoryStore>, PathBuf) {
+    let db = std::env::temp_dir().join(format!("arb-sim-{}-{}.db", name, std::process::id()));
+    let _ = std::fs::remove_file(&db);
+    let history = Arc::new(storage::HistoryStore::connect(&format!("sqlite://{}?mode=rwc", db.display())).await.unwrap());
+    let engine = ExecutionEngine {
+        bookkeeper: bookkeeping::Bookkeeper::spawn(Some(Arc::clone(&history))),
+        history: Some(Arc::clone(&history)),
+        ..engine
+    };
+    (engine, history, db)
+}
+
+#[tokio::test]
+async fn rejected_orders_fail_execution() {
+    let simulation = config::SimulatedExchangeConfig {
+        reject_rate: 1.0,
+        ..Default::default()
+    };
+    let (engine, path) = engine("reject", 1, simulation);
+    assert!(run(&engine, 5).await.iter().all(Option::is_none));
+    let _ = std::fs::remove_file(path);
+}
+
+// 只有第二條腿被拒時反向平掉已成交的第一條腿，原腿與平倉單一併記賬
+#[tokio::test]
+async fn rejected_second_leg_unwinds_the_filled_leg() {
+    let mut config = funded_config();
+    config.exchanges.get_mut("bybit").unwrap().simulation.reject_rate = 1.0;
+    let (engine, path) = build("second-leg-reject", config, Environment::simulated(START_MS, 1));
+    let engine = Arc::new(engine);
+    let gas_quote = engine.default_chain().gas_optimizer.quote(5);
+    let failure = engine.execute_flash_loan_arbitrage("sim", &request(1_000.0), 0.0005, &gas_quote, false, None).await.err().unwrap();
+    assert!(failure.error.starts_with("套利執行失敗"), "{}", failure.error);
+    let legs: Vec<(&str, &str, &str, f64)> = failure
+        .orders
+        .iter()
+        .map(|order| (order.leg.as_str(), order.exchange.as_str(), order.side.as_str(), order.filled_quantity))
+        .collect();
+    assert_eq!(legs, [("short", "binance", "sell", 1_000.0), ("unwind_short", "binance", "buy", 1_000.0)]);
+    assert_eq!(engine.exchanges["binance"].position("BTCUSDT"), 0.0);
+
+    // 經完整執行路徑時兩條腿寫入事件日誌，平倉的手續費計入已實現盈虧
+    connect_sessions(&engine).await;
+    engine.quotes.record_predicted_rate("binance", "BTCUSDT", 0.0060, START_MS);
+    engine.quotes.record_predicted_rate("bybit", "BTCUSDT", 0.0010, START_MS);
+    let response = engine.execute_funding_rate_arbitrage(request(1_000.0)).await;
+    assert_eq!(response.status, "error");
+    let execution_id = response.execution_id.unwrap();
+    let mut legs = Vec::new();
+    let mut settled_fees = None;
+    for envelope in engine.events.read(0, 1_000) {
+        match envelope.event {
+            events::EngineEvent::OrderFilled { execution_id: id, leg, fee, .. } if id == execution_id => legs.push((leg, fee)),
+            events::EngineEvent::ExecutionSettled { execution_id: id, fees, .. } if id == execution_id => settled_fees = Some(fees),
+            _ => {}
+        }
+    }
+    let leg_names: Vec<&str> = legs.iter().map(|(leg, _)| leg.as_str()).collect();
+    assert_eq!(leg_names, ["short", "unwind_short"]);
+    let fees: f64 = legs.iter().map(|(_, fee)| fee).sum();
+    assert!(fees > 0.0 && settled_fees == Some(fees));
+    assert_eq!(engine.exchanges["binance"].position("BTCUSDT"), 0.0);
+    let _ = std::fs::remove_file(path);
+}
+
+#[tokio::test]
+async fn partial_fills_scale_legs() {
+    let simulation = config::SimulatedExchangeConfig {
+        reject_rate: 0.0,
+        partial_fill_rate: 1.0,
+        min_fill_ratio: 0.25,
+        ..Default::default()
+    };
+    let (engine, path) = engine("partial", 3, simulation);
+    let gas_quote = engine.default_chain().gas_optimizer.quote(5);
+    let outcome = engine.execute_flash_loan_arbitrage("sim", &request(1_000.0), 0.0005, &gas_quote, false, None).await.unwrap();
+    let ratio = outcome.fill_ratio();
+    assert!((0.25..1.0).contains(&ratio));
+    // 各腿按自身成交記錄，成交較多的一腿反向平掉超出較少一腿的部分
+    let [short, long, unwind] = &outcome.orders[..] else { panic!("{:?}", outcome.orders) };
+    for order in [short, long] {
+        assert_eq!(order.status, "partially_filled");
+        assert!((0.25 * 1_000.0..1_000.0).contains(&order.filled_quantity));
+    }
+    let (larger, smaller) = if short.filled_quantity > long.filled_quantity { (short, long) } else { (long, short) };
+    assert!((smaller.filled_quantity - 1_000.0 * ratio).abs() < 1e-9);
+    assert_eq!(unwind.leg, format!("unwind_{}", larger.leg));
+    assert_ne!(unwind.side, larger.side);
+    assert!((unwind.filled_quantity - (larger.filled_quantity - smaller.filled_quantity)).abs() < 1e-9);
+    let fees: f64 = outcome.orders.iter().map(|order| order.fee).sum();
+    assert!((outcome.fees - fees).abs() < 1e-9);
+    assert!((outcome.profit - (1_000.0 * 0.0005 * ratio - fees)).abs() < 1e-9);
+    let _ = std::fs::remove_file(path);
+}
+
+#[tokio::test]
+async fn simulated_latency_does_not_wait() {
+    let simulation = config::SimulatedExchangeConfig {
+        slow_request_rate: 1.0,
+        slow_request_ms: (1_000, 1_000),
+        order_latency_us: 2_000_000,
+        reject_rate: 0.0,
+        ..Default::default()
+    };
+    let (engine, path) = engine("latency", 5, simulation);
+    let started = Instant::now();
+    engine.exchanges["binance"].fetch_funding_rate("https://fapi.binance.com", "BTCUSDT").await.unwrap();
+    run(&engine, 1).await;
+    // 一次行情請求 1 秒，兩條腿各 2 秒確認延遲
+    assert_eq!(engine.env.now_ms(), START_MS + 5_000);
+    assert!(started.elapsed() < Duration::from_secs(1));
+    let _ = std::fs::remove_file(path);
+}
+
+// 固定兩條腿的自定義策略，記錄成交回調；restock 時兩腿成交後再把 USDT 劃回 binance
+pub(crate) struct PairStrategy {
+    pub(crate) fills: std::sync::Mutex<Vec<String>>,
+    pub(crate) restock: bool,
+}
+
+impl strategy::Strategy for PairStrategy {
+    fn name(&self) -> &str {
+        "pair"
+    }
+
+    fn evaluate(&self, market: &strategy::MarketView<'_>) -> Vec<strategy::StrategyOpportunity> {
+        [0.001, 0.003]
+            .into_iter()
+            .map(|expected_edge| strategy::StrategyOpportunity {
+                symbol: "BTCUSDT".to_string(),
+                primary_exchange: "binance".to_string(),
+                secondary_exchange: "bybit".to_string(),
+                expected_edge,
+                detail: serde_json::json!({ "exchanges": market.exchanges().len() }),
+            })
+            .collect()
+    }
+
+    fn build_execution_plan(&self, request: &ArbitrageRequest, _market: &strategy::MarketView<'_>) -> Result<strategy::ExecutionPlan, String> {
+        let step = |leg: &str, action: strategy::Action, depends_on: &[&str]| strategy::PlannedStep {
+            leg: leg.to_string(),
+            action,
+            depends_on: depends_on.iter().map(|leg| leg.to_string()).collect(),
+            unwind: None,
+        };
+        let mut steps = vec![
+            step("long", strategy::Action::order("binance", &request.symbol, "buy", request.amount), &[]),
+            step("short", strategy::Action::order("bybit", &request.symbol, "sell", request.amount), &[]),
+        ];
+        if self.restock {
+            let transfer = strategy::Action::Transfer {
+                asset: "USDT".to_string(),
+                from: "bybit".to_string(),
+                to: "binance".to_string(),
+                amount: request.amount,
+            };
+            steps.push(step("restock", transfer, &["long", "short"]));
+        }
+        Ok(strategy::ExecutionPlan {
+            steps,
+            expected_profit: request.amount * 0.002,
+        })
+    }
+
+    fn on_fill(&self, _execution_id: &str, order: &ChildOrder) {
+        self.fills.lock().unwrap().push(order.leg.clone());
+    }
+}
+
+// 閃電貸上鏈流程的模擬節點：balanceOf 按持有地址返回 liquidity 中的餘額，收益地址在區塊 15、16 分別持有 1000 與 1020 USDC；
+// 發出的交易在區塊 16 成功上鏈，回執帶一條 USDC 轉賬日誌
+pub(crate) async fn flash_loan_node(liquidity: Arc<std::sync::Mutex<HashMap<web3::types::Address, u64>>>, recipient: web3::types::Address) -> String {
+    use web3::ethabi::Token;
+    use web3::types::{Address, H256, U256};
+    let asset: Address = config::FlashLoanConfig::default().asset_address.parse().unwrap();
+    let transfer = H256::from(web3::signing::keccak256(b"Transfer(address,address,uint256)"));
+    mock_exchange_e2e::rpc_node(Arc::new(move |method, params| {
+        Ok(match method {
+            "eth_call" if params[0]["to"].as_str().and_then(|to| to.parse::<Address>().ok()) == Some(asset) => {
+                let data = params[0]["data"].as_str().or(params[0]["input"].as_str()).unwrap_or_default();
+                let holder = Address::from_slice(&hex::decode(&data[data.len() - 40..]).unwrap());
+                let balance = match (holder == recipient, params.get(1).and_then(|block| block.as_str())) {
+                    (true, Some("0xf")) => 1_000_000_000,
+                    (true, _) => 1_020_000_000,
+                    (false, _) => liquidity.lock().unwrap().get(&holder).copied().unwrap_or_default(),
+                };
+                mock_exchange_e2e::abi_hex(&[Token::Uint(balance.into())])
+            }
+            // 交易發送前的 eth_call 模擬
+            "eth_call" => serde_json::json!("0x"),
+            "eth_estimateGas" => serde_json::json!("0x30d40"),
+            "eth_getTransactionCount" => serde_json::json!("0x0"),
+            "eth_blockNumber" => serde_json::json!("0x10"),
+            "eth_sendRawTransaction" => serde_json::json!(format!("{:?}", H256::from_low_u64_be(0x51))),
+            "eth_getTransactionReceipt" => serde_json::json!({
+                "transactionHash": params[0],
+                "transactionIndex": "0x0",
+                "blockNumber": "0x10",
+                "blockHash": format!("{:?}", H256::from_low_u64_be(16)),
+                "cumulativeGasUsed": "0x30d40",
+                "gasUsed": "0x30d40",
+                "effectiveGasPrice": "0x3b9aca00",
+                "status": "0x1",
+                "logsBloom": format!("0x{}", "0".repeat(512)),
+                "logs": [{
+                    "address": format!("{:?}", asset),
+                    "topics": [format!("{:?}", transfer), format!("{:?}", H256::from(Address::from_low_u64_be(0xba))), format!("{:?}", H256::from(recipient))],
+                    "data": mock_exchange_e2e::abi_hex(&[Token::Uint(U256::from(20_000_000u64))]),
+                    "logIndex": "0x0",
+                    "removed": false,
+                }],
+            }),
+            "eth_getBlockByNumber" => serde_json::json!(web3::types::Block::<H256> {
+                number: Some(16.into()),
+                hash: Some(H256::from_low_u64_be(16)),
+                ..Default::default()
+            }),
+            other => return Err(format!("unexpected {}", other)),
+        })
+    }))
+    .await
+}
+
+pub(crate) const WALLET_KEY_ENV: &str = "SIM_WALLET_KEY";
+
+// 熱錢包私鑰只在引擎的變量表中，不寫進程環境
+pub(crate) fn wallet_env() -> Environment {
+    Environment::simulated(START_MS, 1).with_vars([(WALLET_KEY_ENV, "4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318")])
+}
+
+pub(crate) fn flash_loan_config(url: String) -> config::EngineConfig {
+    let mut config = funded_config();
+    config.flash_loan.rpc_url = Some(url);
+    config.flash_loan.receiver_address = Some(format!("{:?}", web3::types::Address::from_low_u64_be(0xf1)));
+    config.flash_loan.wallets[0].private_key_env = Some(WALLET_KEY_ENV.to_string());
+    config
+}
@@ -0,0 +1,718 @@
+use super::approvals::ApprovalManager;
+use super::config::{ApprovalMode, DexConfig, FlashLoanConfig};
+use super::flash_loan::{encode_call, load_abi, token_balance, ProviderFuture};
+use super::gas::{GasOptimizer, GasQuote};
+use super::tx_simulation::{self, OnchainError};
+use super::wallet::{HotWallet, WalletManager};
+use super::rpc::RpcTransport;
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tracing::{debug, info, warn};
+use web3::ethabi::{Contract, Token};
+use web3::types::{Address, BlockId, BlockNumber, Bytes, CallRequest, TransactionParameters, U256};
+use web3::Web3;
+
+const UNISWAP_QUOTER_ABI: &str = r#"[
+    {"type":"function","name":"quoteExactInputSingle","stateMutability":"nonpayable","inputs":[
+        {"name":"params","type":"tuple","components":[
+            {"name":"tokenIn","type":"address"},
+            {"name":"tokenOut","type":"address"},
+            {"name":"amountIn","type":"uint256"},
+            {"name":"fee","type":"uint24"},
+            {"name":"sqrtPriceLimitX96","type":"uint160"}]}],
+     "outputs":[
+        {"name":"amountOut","type":"uint256"},
+        {"name":"sqrtPriceX96After","type":"uint160"},
+        {"name":"initializedTicksCrossed","type":"uint32"},
+        {"name":"gasEstimate","type":"uint256"}]}
+]"#;
+const UNISWAP_ROUTER_ABI: &str = r#"[
+    {"type":"function","name":"exactInputSingle","stateMutability":"payable","inputs":[
+        {"name":"params","type":"tuple","components":[
+            {"name":"tokenIn","type":"address"},
+            {"name":"tokenOut","type":"address"},
+            {"name":"fee","type":"uint24"},
+            {"name":"recipient","type":"address"},
+            {"name":"deadline","type":"uint256"},
+            {"name":"amountIn","type":"uint256"},
+            {"name":"amountOutMinimum","type":"uint256"},
+            {"name":"sqrtPriceLimitX96","type":"uint160"}]}],
+     "outputs":[{"name":"amountOut","type":"uint256"}]}
+]"#;
+const CURVE_POOL_ABI: &str = r#"[
+    {"type":"function","name":"get_dy","stateMutability":"view","inputs":[
+        {"name":"i","type":"int128"},
+        {"name":"j","type":"int128"},
+        {"name":"dx","type":"uint256"}],"outputs":[{"name":"","type":"uint256"}]},
+    {"type":"function","name":"exchange","stateMutability":"nonpayable","outputs":[],"inputs":[
+        {"name":"i","type":"int128"},
+        {"name":"j","type":"int128"},
+        {"name":"dx","type":"uint256"},
+        {"name":"min_dy","type":"uint256"}]}
+]"#;
+
+// 精確輸入兌換：成交量低於 min_amount_out 或超過 deadline（Unix 秒）時回滾
+pub struct Swap {
+    pub token_in: Address,
+    pub token_out: Address,
+    pub amount_in: U256,
+    pub min_amount_out: U256,
+    pub recipient: Address,
+    pub deadline: u64,
+}
+
+pub trait DexConnector: Send + Sync {
+    fn name(&self) -> &'static str;
+    // 兌換交易的目標合約
+    fn router(&self) -> Address;
+    // 池子手續費，已反映在報價中，僅用於記帳
+    fn fee_bps(&self) -> f64;
+    // 精確輸入可得的輸出數量（最小單位）；block 為 None 時使用最新區塊
+    fn quote<'a>(
+        &'a self,
+        web3: &'a Web3<RpcTransport>,
+        token_in: Address,
+        token_out: Address,
+        amount_in: U256,
+        block: Option<BlockId>,
+    ) -> ProviderFuture<'a, U256>;
+    // 合約本身不支持截止時間的場所忽略 deadline，由執行器在發送前檢查
+    fn build_swap(&self, swap: &Swap) -> Result<Vec<u8>, String>;
+    fn enforces_deadline(&self) -> bool;
+    // 兩個代幣兌換所經過的池子
+    fn pool(&self, token_a: Address, token_b: Address) -> PoolKey;
+    // 解碼發往 router() 的兌換 calldata，返回 (池子, 輸入代幣, 輸入數量)；不經過我們配置的池子時返回 None
+    fn decode_swap(&self, input: &[u8]) -> Option<(PoolKey, Address, U256)>;
+}
+
+// 池子標識：Uniswap 為 (排序後的代幣對, 手續費檔位)，Curve 為池子地址
+pub type PoolKey = (Address, Address, u32);
+
+fn sorted(a: Address, b: Address) -> (Address, Address) {
+    if a <= b { (a, b) } else { (b, a) }
+}
+
+// 去掉 4 字節選擇器後按函數定義解碼
+fn decode_input(abi: &Contract, function: &str, input: &[u8]) -> Option<Vec<Token>> {
+    let function = abi.function(function).ok()?;
+    let (selector, data) = (input.get(..4)?, input.get(4..)?);
+    if selector != function.short_signature() {
+        return None;
+    }
+    function.decode_input(data).ok()
+}
+
+// 只讀調用，返回解碼後的全部輸出
+pub(crate) async fn call(
+    web3: &Web3<RpcTransport>,
+    abi: &Contract,
+    to: Address,
+    function: &str,
+    tokens: &[Token],
+    block: Option<BlockId>,
+) -> Result<Vec<Token>, String> {
+    let data = encode_call(abi, function, tokens)?;
+    let output = web3
+        .eth()
+        .call(
+            CallRequest {
+                to: Some(to),
+                data: Some(Bytes(data)),
+                ..CallRequest::default()
+            },
+            block,
+        )
+        .await
+        .map_err(|e| format!("{} 調用失敗: {}", function, e))?;
+    abi.function(function)
+        .and_then(|f| f.decode_output(&output.0))
+        .map_err(|e| format!("{} 返回值解碼失敗: {}", function, e))
+}
+
+// 只讀調用，返回第一個輸出值
+pub(crate) async fn call_uint(
+    web3: &Web3<RpcTransport>,
+    abi: &Contract,
+    to: Address,
+    function: &str,
+    tokens: &[Token],
+    block: Option<BlockId>,
+) -> Result<U256, String> {
+    call(web3, abi, to, function, tokens, block)
+        .await?
+        .into_iter()
+        .next()
+        .and_then(Token::into_uint)
+        .ok_or_else(|| format!("{} 沒有返回數量", function))
+}
+
+struct UniswapV3 {
+    quoter: Address,
+    router: Address,
+    fee_tier: u32,
+    quoter_abi: Contract,
+    router_abi: Contract,
+}
+
+impl DexConnector for UniswapV3 {
+    fn name(&self) -> &'static str {
+        "uniswap_v3"
+    }
+
+    fn router(&self) -> Address {
+        self.router
+    }
+
+    fn fee_bps(&self) -> f64 {
+        self.fee_tier as f64 / 100.0
+    }
+
+    fn quote<'a>(
+        &'a self,
+        web3: &'a Web3<RpcTransport>,
+        token_in: Address,
+        token_out: Address,
+        amount_in: U256,
+        block: Option<BlockId>,
+    ) -> ProviderFuture<'a, U256> {
+        Box::pin(async move {
+            let params = Token::Tuple(vec![
+                Token::Address(token_in),
+                Token::Address(token_out),
+                Token::Uint(amount_in),
+                Token::Uint(self.fee_tier.into()),
+                Token::Uint(U256::zero()),
+            ]);
+            call_uint(web3, &self.quoter_abi, self.quoter, "quoteExactInputSingle", &[params], block).await
+        })
+    }
+
+    fn build_swap(&self, swap: &Swap) -> Result<Vec<u8>, String> {
+        let params = Token::Tuple(vec![
+            Token::Address(swap.token_in),
+            Token::Address(swap.token_out),
+            Token::Uint(self.fee_tier.into()),
+            Token::Address(swap.recipient),
+            Token::Uint(swap.deadline.into()),
+            Token::Uint(swap.amount_in),
+            Token::Uint(swap.min_amount_out),
+            Token::Uint(U256::zero()),
+        ]);
+        encode_call(&self.router_abi, "exactInputSingle", &[params])
+    }
+
+    fn enforces_deadline(&self) -> bool {
+        true
+    }
+
+    fn pool(&self, token_a: Address, token_b: Address) -> PoolKey {
+        let (a, b) = sorted(token_a, token_b);
+        (a, b, self.fee_tier)
+    }
+
+    fn decode_swap(&self, input: &[u8]) -> Option<(PoolKey, Address, U256)> {
+        let params = decode_input(&self.router_abi, "exactInputSingle", input)?.into_iter().next()?.into_tuple()?;
+        let token_in = params.first()?.clone().into_address()?;
+        let token_out = params.get(1)?.clone().into_address()?;
+        let fee = params.get(2)?.clone().into_uint()?.low_u32();
+        let amount_in = params.get(5)?.clone().into_uint()?;
+        (fee == self.fee_tier).then(|| (self.pool(token_in, token_out), token_in, amount_in))
+    }
+}
+
+struct Curve {
+    pool: Address,
+    // 池內代幣順序
+    coins: Vec<Address>,
+    fee_bps: f64,
+    abi: Contract,
+}
+
+impl Curve {
+    fn index(&self, token: Address) -> Result<Token, String> {
+        self.coins
+            .iter()
+            .position(|coin| *coin == token)
+            .map(|i| Token::Int(U256::from(i)))
+            .ok_or_else(|| format!("Curve 池不包含代幣 {:?}", token))
+    }
+}
+
+impl DexConnector for Curve {
+    fn name(&self) -> &'static str {
+        "curve"
+    }
+
+    fn router(&self) -> Address {
+        self.pool
+    }
+
+    fn fee_bps(&self) -> f64 {
+        self.fee_bps
+    }
+
+    fn quote<'a>(
+        &'a self,
+        web3: &'a Web3<RpcTransport>,
+        token_in: Address,
+        token_out: Address,
+        amount_in: U256,
+        block: Option<BlockId>,
+    ) -> ProviderFuture<'a, U256> {
+        Box::pin(async move {
+            let tokens = [self.index(token_in)?, self.index(token_out)?, Token::Uint(amount_in)];
+            call_uint(web3, &self.abi, self.pool, "get_dy", &tokens, block).await
+        })
+    }
+
+    fn build_swap(&self, swap: &Swap) -> Result<Vec<u8>, String> {
+        encode_call(
+            &self.abi,
+            "exchange",
+            &[
+                self.index(swap.token_in)?,
+                self.index(swap.token_out)?,
+                Token::Uint(swap.amount_in),
+                Token::Uint(swap.min_amount_out),
+            ],
+        )
+    }
+
+    fn enforces_deadline(&self) -> bool {
+        false
+    }
+
+    // 池內任意兌換都會改變所有代幣的價格
+    fn pool(&self, _token_a: Address, _token_b: Address) -> PoolKey {
+        (self.pool, self.pool, 0)
+    }
+
+    fn decode_swap(&self, input: &[u8]) -> Option<(PoolKey, Address, U256)> {
+        let tokens = decode_input(&self.abi, "exchange", input)?;
+        let i = tokens.first()?.clone().into_int()?.low_u64() as usize;
+        let amount_in = tokens.get(2)?.clone().into_uint()?;
+        Some((self.pool(self.pool, self.pool), *self.coins.get(i)?, amount_in))
+    }
+}
+
+// 已確認的兌換；amount_out 取自收款地址在交易所在區塊前後的餘額變化
+pub struct DexFill {
+    pub tx_hash: String,
+    pub amount_out: f64,
+    pub gas_used: u64,
+    // 以該鏈原生代幣計
+    pub gas_cost: f64,
+}
+
+pub struct DexExecutor {
+    wallets: Arc<WalletManager>,
+    connectors: Vec<Box<dyn DexConnector>>,
+    // 資產 -> (代幣地址, 精度)
+    tokens: HashMap<String, (Address, u32)>,
+    config: DexConfig,
+    gas_buffer: f64,
+    confirmations: usize,
+    approvals: ApprovalManager,
+}
+
+fn address(field: &str, value: &str) -> Result<Address, String> {
+    Address::from_str(value).map_err(|e| format!("dex.{} 不是有效地址: {}", field, e))
+}
+
+pub(crate) fn to_units(amount: f64, decimals: u32) -> U256 {
+    U256::from((amount * 10f64.powi(decimals as i32)).round() as u128)
+}
+
+fn from_units(amount: U256, decimals: u32) -> f64 {
+    amount.low_u128() as f64 / 10f64.powi(decimals as i32)
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+impl DexExecutor {
+    // 共用閃電貸的 RPC、熱錢包、gas 餘量與確認數；未加載錢包時不啟用
+    pub fn connect(
+        config: &DexConfig,
+        chain: &FlashLoanConfig,
+        wallets: Option<Arc<WalletManager>>,
+    ) -> Result<Option<Self>, String> {
+        let Some(wallets) = wallets.filter(|_| !config.venues.is_empty()) else {
+            return Ok(None);
+        };
+        let mut tokens = HashMap::new();
+        for (asset, token) in &config.tokens {
+            tokens.insert(
+                asset.to_uppercase(),
+                (address(&format!("tokens.{}.address", asset), &token.address)?, token.decimals),
+            );
+        }
+
+        let mut connectors: Vec<Box<dyn DexConnector>> = Vec::new();
+        for venue in &config.venues {
+            match venue.as_str() {
+                "uniswap_v3" => connectors.push(Box::new(UniswapV3 {
+                    quoter: address("uniswap_v3.quoter_address", &config.uniswap_v3.quoter_address)?,
+                    router: address("uniswap_v3.router_address", &config.uniswap_v3.router_address)?,
+                    fee_tier: config.uniswap_v3.fee_tier,
+                    quoter_abi: load_abi(UNISWAP_QUOTER_ABI),
+                    router_abi: load_abi(UNISWAP_ROUTER_ABI),
+                })),
+                "curve" => connectors.push(Box::new(Curve {
+                    pool: address("curve.pool_address", &config.curve.pool_address)?,
+                    coins: config
+                        .curve
+                        .coins
+                        .iter()
+                        .map(|coin| {
+                            tokens
+                                .get(&coin.to_uppercase())
+                                .map(|(token, _)| *token)
+                                .ok_or_else(|| format!("dex.curve.coins 中的 {} 未配置代幣地址", coin))
+                        })
+                        .collect::<Result<_, _>>()?,
+                    fee_bps: config.curve.fee_bps,
+                    abi: load_abi(CURVE_POOL_ABI),
+                })),
+                other => return Err(format!("未知的 DEX: {}", other)),
+            }
+        }
+        info!(venues = ?config.venues, "DEX 執行已配置");
+        Ok(Some(Self {
+            wallets,
+            connectors,
+            tokens,
+            config: config.clone(),
+            gas_buffer: chain.gas_buffer,
+            confirmations: chain.confirmations,
+            approvals: ApprovalManager::new(&config.approvals)?,
+        }))
+    }
+
+    pub fn approvals(&self) -> &ApprovalManager {
+        &self.approvals
+    }
+
+    // 為每個熱錢包、代幣與路由登記授權：max 模式已有額度的不重複授權，exact 模式只登記配置了數量的資產
+    pub async fn request_all_approvals(&self) {
+        for owner in self.wallets.addresses() {
+            for connector in &self.connectors {
+                for (asset, (token, decimals)) in &self.tokens {
+                    let needed = match self.config.approvals.mode {
+                        ApprovalMode::Max => U256::one(),
+                        ApprovalMode::Exact => match self.config.approvals.exact_amounts.get(asset) {
+                            Some(amount) => to_units(*amount, *decimals),
+                            None => continue,
+                        },
+                    };
+                    let grant = self.approvals.grant(owner, *token, connector.router(), connector.name());
+                    match self.approvals.refresh(&self.wallets, grant).await {
+                        Ok(allowance) if allowance >= needed => {}
+                        Ok(_) => self.approvals.request(grant, needed),
+                        Err(error) => warn!(venue = connector.name(), %asset, %error, "讀取代幣授權失敗"),
+                    }
+                }
+            }
+        }
+    }
+
+    pub async fn process_approvals(&self, gas: &GasOptimizer) {
+        let assets = self.tokens.iter().map(|(asset, (token, decimals))| (*token, (asset.clone(), *decimals))).collect();
+        self.approvals
+            .process(&self.wallets, gas, self.gas_buffer, self.confirmations, &assets)
+            .await;
+    }
+
+    pub fn has_venue(&self, venue: &str) -> bool {
+        self.connectors.iter().any(|connector| connector.name() == venue)
+    }
+
+    pub fn fee_bps(&self, venue: &str) -> f64 {
+        self.connector(venue).map(|connector| connector.fee_bps()).unwrap_or_default()
+    }
+
+    fn connector(&self, venue: &str) -> Result<&dyn DexConnector, String> {
+        self.connectors
+            .iter()
+            .find(|connector| connector.name() == venue)
+            .map(|connector| connector.as_ref())
+            .ok_or_else(|| format!("未啟用的 DEX: {}", venue))
+    }
+
+    fn token(&self, asset: &str) -> Result<(Address, u32), String> {
+        self.tokens
+            .get(&asset.to_uppercase())
+            .copied()
+            .ok_or_else(|| format!("未配置 {} 的代幣地址", asset))
+    }
+
+    // 賣出 amount_in 個 asset_in 可得的 asset_out 數量
+    pub async fn quote(&self, venue: &str, asset_in: &str, asset_out: &str, amount_in: f64) -> Result<f64, String> {
+        self.quote_at(venue, asset_in, asset_out, amount_in, None).await
+    }
+
+    // 基於 pending 狀態重新報價，節點會把已知的待確認交易計入
+    pub async fn quote_pending(&self, venue: &str, asset_in: &str, asset_out: &str, amount_in: f64) -> Result<f64, String> {
+        self.quote_at(venue, asset_in, asset_out, amount_in, Some(BlockId::Number(BlockNumber::Pending)))
+            .await
+    }
+
+    async fn quote_at(
+        &self,
+        venue: &str,
+        asset_in: &str,
+        asset_out: &str,
+        amount_in: f64,
+        block: Option<BlockId>,
+    ) -> Result<f64, String> {
+        let connector = self.connector(venue)?;
+        let (token_in, decimals_in) = self.token(asset_in)?;
+        let (token_out, decimals_out) = self.token(asset_out)?;
+        let amount_out = connector
+            .quote(self.wallets.web3(), token_in, token_out, to_units(amount_in, decimals_in), block)
+            .await?;
+        Ok(from_units(amount_out, decimals_out))
+    }
+
+    pub fn pool(&self, venue: &str, asset_a: &str, asset_b: &str) -> Result<PoolKey, String> {
+        Ok(self.connector(venue)?.pool(self.token(asset_a)?.0, self.token(asset_b)?.0))
+    }
+
+    // 新報價仍不低於原報價的最少成交量
+    pub fn within_slippage(&self, quoted_out: f64, repriced_out: f64) -> bool {
+        repriced_out >= quoted_out * (1.0 - self.config.slippage_bps / 10_000.0)
+    }
+
+    // 識別發往我們所用 router / 池子的待確認兌換，返回 (場所, 池子, 輸入資產, 輸入數量)
+    pub fn decode_pending(&self, to: Address, input: &[u8]) -> Option<(&'static str, PoolKey, String, f64)> {
+        let connector = self.connectors.iter().find(|connector| connector.router() == to)?;
+        let (pool, token_in, amount_in) = connector.decode_swap(input)?;
+        let (asset, (_, decimals)) = self.tokens.iter().find(|(_, (token, _))| *token == token_in)?;
+        Some((connector.name(), pool, asset.clone(), from_units(amount_in, *decimals)))
+    }
+
+    // 按報價扣除滑點容忍度設置最少成交量；路由合約對 asset_in 的授權不足時直接返回錯誤，由後台補授權
+    #[allow(clippy::too_many_arguments)]
+    pub async fn swap(
+        &self,
+        venue: &str,
+        asset_in: &str,
+        asset_out: &str,
+        amount_in: f64,
+        quoted_out: f64,
+        gas: &GasOptimizer,
+        quote: &GasQuote,
+    ) -> Result<DexFill, OnchainError> {
+        let connector = self.connector(venue)?;
+        let (token_in, decimals_in) = self.token(asset_in)?;
+        let (token_out, decimals_out) = self.token(asset_out)?;
+        let wallet = self.wallets.next();
+        let amount_units = to_units(amount_in, decimals_in);
+        let grant = self.approvals.grant(wallet.address(), token_in, connector.router(), venue);
+        self.approvals.require(&self.wallets, grant, amount_units).await?;
+        let deadline = now_secs() + self.config.deadline_secs;
+        let calldata = connector.build_swap(&Swap {
+            token_in,
+            token_out,
+            amount_in: amount_units,
+            min_amount_out: to_units(quoted_out * (1.0 - self.config.slippage_bps / 10_000.0), decimals_out),
+            recipient: wallet.address(),
+            deadline,
+        })?;
+
+        let result = self.send_swap(venue, connector, wallet, calldata, deadline, (token_out, decimals_out), gas, quote).await;
+        match &result {
+            Ok(_) => self.approvals.consume(grant, amount_units),
+            // 失敗可能源於授權已被撤銷或過期，丟棄緩存的額度
+            Err(_) => self.approvals.invalidate(grant),
+        }
+        result
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    async fn send_swap(
+        &self,
+        venue: &str,
+        connector: &dyn DexConnector,
+        wallet: &HotWallet,
+        calldata: Vec<u8>,
+        deadline: u64,
+        (token_out, decimals_out): (Address, u32),
+        gas: &GasOptimizer,
+        quote: &GasQuote,
+    ) -> Result<DexFill, OnchainError> {
+        // estimateGas 在最少成交量無法滿足時直接失敗，不必上鏈
+        let web3 = self.wallets.web3();
+        let estimated = self
+            .wallets
+            .verified_web3()
+            .eth()
+            .estimate_gas(
+                CallRequest {
+                    from: Some(wallet.address()),
+                    to: Some(connector.router()),
+                    data: Some(Bytes(calldata.clone())),
+                    ..CallRequest::default()
+                },
+                None,
+            )
+            .await
+            .map_err(|e| format!("{} 兌換 gas 估算失敗: {}", venue, e))?;
+        let estimated = u64::try_from(estimated).map_err(|_| format!("{} 兌換 gas 估算超出範圍: {}", venue, estimated))?;
+        let gas_limit = (estimated as f64 * self.gas_buffer) as u64;
+        gas.check_budget(quote, gas_limit).map_err(|e| format!("{} 兌換{}", venue, e))?;
+        if !connector.enforces_deadline() && now_secs() > deadline {
+            return Err(format!("{} 兌換已超過截止時間", venue).into());
+        }
+
+        let hash = self
+            .wallets
+            .submit(
+                wallet,
+                TransactionParameters {
+                    to: Some(connector.router()),
+                    gas: gas_limit.into(),
+                    data: Bytes(calldata),
+                    transaction_type: Some(2.into()),
+                    max_fee_per_gas: Some(quote.max_fee_per_gas.into()),
+                    max_priority_fee_per_gas: Some(quote.max_priority_fee_per_gas.into()),
+                    ..TransactionParameters::default()
+                },
+            )
+            .await?;
+        let tx_hash = format!("{:?}", hash);
+        debug!(%venue, %tx_hash, wallet = wallet.name(), "DEX 兌換已發送");
+        let receipt = self
+            .wallets
+            .confirm(hash, self.confirmations, &format!("{} 兌換", venue))
+            .await?;
+        if receipt.status != Some(1.into()) {
+            let revert = tx_simulation::inspect_revert(web3, &receipt, quote.max_fee_per_gas).await;
+            return Err(OnchainError::reverted(&format!("{} 兌換", venue), revert));
+        }
+        let block = receipt
+            .block_number
+            .ok_or_else(|| format!("兌換回執缺少區塊號: {}", tx_hash))?
+            .as_u64();
+
+        let before = token_balance(web3, token_out, wallet.address(), Some(block.saturating_sub(1))).await?;
+        let after = token_balance(web3, token_out, wallet.address(), Some(block)).await?;
+        let amount_out = from_units(after.saturating_sub(before), decimals_out);
+        let gas_used = receipt.gas_used.unwrap_or_default().as_u64();
+        let gas_price = receipt
+            .effective_gas_price
+            .map(|price| price.as_u64())
+            .unwrap_or(quote.max_fee_per_gas);
+        info!(%venue, %tx_hash, block, amount_out, gas_used, "DEX 兌換已確認");
+        Ok(DexFill {
+            tx_hash,
+            amount_out,
+            gas_used,
+            gas_cost: gas_used as f64 * gas_price as f64 / 1e18,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock_exchange_e2e::{abi_hex, hot_wallets, mined_receipt, rpc_node};
+    use crate::{config, gas};
+
+    // Uniswap V3 經 Quoter、Curve 經 get_dy 報價（pending 報價帶 pending 區塊標籤）；兌換的最少成交量按滑點容忍度從報價扣減，
+    // 只有 Uniswap 的 calldata 帶截止時間；成交量取自收款地址在回執區塊前後的餘額差
+    #[tokio::test]
+    async fn dex_legs_quote_and_swap_within_slippage_and_deadline() {
+        use web3::ethabi::Token;
+        use web3::types::{Address, U256};
+        let dex_config = config::DexConfig::default();
+        let token = |asset: &str| dex_config.tokens[asset].address.parse::<Address>().unwrap();
+        let quoter: Address = dex_config.uniswap_v3.quoter_address.parse().unwrap();
+        let router: Address = dex_config.uniswap_v3.router_address.parse().unwrap();
+        let pool: Address = dex_config.curve.pool_address.parse().unwrap();
+        let (weth, usdt) = (token("ETH"), token("USDT"));
+        // 報價調用的 (calldata, 區塊標籤) 與兌換的 estimateGas calldata
+        let calls = Arc::new(std::sync::Mutex::new(Vec::<(Vec<u8>, String)>::new()));
+        let swaps = Arc::new(std::sync::Mutex::new(Vec::<(Address, Vec<u8>)>::new()));
+        let (node_calls, node_swaps) = (Arc::clone(&calls), Arc::clone(&swaps));
+        let url = rpc_node(Arc::new(move |method, params| {
+            let to = params.first().and_then(|call| call["to"].as_str()).and_then(|to| to.parse::<Address>().ok());
+            let data = params.first().and_then(|call| call["data"].as_str()).map(|data| hex::decode(&data[2..]).unwrap()).unwrap_or_default();
+            let block = params.get(1).and_then(|block| block.as_str()).unwrap_or_default().to_string();
+            Ok(match method {
+                "eth_call" if to == Some(quoter) => {
+                    node_calls.lock().unwrap().push((data, block));
+                    abi_hex(&[Token::Uint(U256::exp10(18)), Token::Uint(U256::zero()), Token::Uint(U256::one()), Token::Uint(80_000.into())])
+                }
+                "eth_call" if to == Some(pool) => {
+                    node_calls.lock().unwrap().push((data, block));
+                    abi_hex(&[Token::Uint(999_500_000u64.into())])
+                }
+                // allowance 返回最大額度；balanceOf 在回執區塊前為 0
+                "eth_call" if data[..4] == [0xdd, 0x62, 0xed, 0x3e] => abi_hex(&[Token::Uint(U256::MAX)]),
+                "eth_call" if block == "0xf" => abi_hex(&[Token::Uint(U256::zero())]),
+                "eth_call" if to == Some(weth) => abi_hex(&[Token::Uint(U256::exp10(15) * 998)]),
+                "eth_call" if to == Some(usdt) => abi_hex(&[Token::Uint(999_000_000u64.into())]),
+                "eth_estimateGas" => {
+                    node_swaps.lock().unwrap().push((to.unwrap(), data));
+                    serde_json::json!("0x30d40")
+                }
+                "eth_getTransactionCount" => serde_json::json!("0x0"),
+                "eth_blockNumber" => serde_json::json!("0x10"),
+                "eth_sendRawTransaction" => serde_json::json!(format!("{:?}", web3::types::H256::from_low_u64_be(0x5a))),
+                "eth_getTransactionReceipt" => mined_receipt(&params[0]),
+                other => return Err(format!("unexpected {}", other)),
+            })
+        }))
+        .await;
+        let wallets = Arc::new(hot_wallets(&url));
+        let dex = DexExecutor::connect(&dex_config, &config::FlashLoanConfig::default(), Some(Arc::clone(&wallets)))
+            .unwrap()
+            .unwrap();
+        assert!(dex.has_venue("uniswap_v3") && dex.has_venue("curve"));
+        assert_eq!(dex.fee_bps("uniswap_v3"), 5.0);
+        assert!(dex.quote("sushiswap", "USDC", "ETH", 1.0).await.unwrap_err().contains("未啟用"));
+        let word = |data: &[u8], index: usize| U256::from_big_endian(&data[4 + index * 32..4 + (index + 1) * 32]);
+
+        // Quoter 參數 (tokenIn, tokenOut, amountIn, fee, sqrtPriceLimitX96)；Curve 按池內代幣順序取 i、j
+        assert_eq!(dex.quote("uniswap_v3", "USDC", "ETH", 3000.0).await.unwrap(), 1.0);
+        assert_eq!(dex.quote_pending("curve", "USDC", "USDT", 1000.0).await.unwrap(), 999.5);
+        let calls = std::mem::take(&mut *calls.lock().unwrap());
+        assert_eq!((word(&calls[0].0, 2), word(&calls[0].0, 3)), (U256::from(3_000_000_000u64), U256::from(500)));
+        assert_eq!(calls[0].1, "latest");
+        assert_eq!((word(&calls[1].0, 0), word(&calls[1].0, 1)), (U256::one(), U256::from(2)));
+        assert_eq!(calls[1].1, "pending");
+        assert!(dex.within_slippage(1.0, 0.9975) && !dex.within_slippage(1.0, 0.996));
+
+        // exactInputSingle 參數第 5、7 個為 deadline 與 amountOutMinimum
+        let gas = gas::GasOptimizer::connect(config::GasConfig::default(), None);
+        let quote = gas.quote(0);
+        let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_secs();
+        let fill = dex.swap("uniswap_v3", "USDC", "ETH", 3000.0, 1.0, &gas, &quote).await.unwrap();
+        assert!((fill.amount_out - 0.998).abs() < 1e-12);
+        assert_eq!(fill.gas_used, 50_000);
+        assert!((fill.gas_cost - 5e-5).abs() < 1e-12);
+        let (to, calldata) = swaps.lock().unwrap().remove(0);
+        assert_eq!(to, router);
+        let deadline = word(&calldata, 4).as_u64();
+        assert!((now + 60..=now + 61).contains(&deadline));
+        assert_eq!(word(&calldata, 6), U256::exp10(15) * 997);
+        let (venue, _, asset, amount) = dex.decode_pending(router, &calldata).unwrap();
+        assert_eq!((venue, asset.as_str(), amount), ("uniswap_v3", "USDC", 3000.0));
+
+        // exchange(i, j, dx, min_dy) 沒有截止時間參數
+        let fill = dex.swap("curve", "USDC", "USDT", 1000.0, 999.5, &gas, &quote).await.unwrap();
+        assert_eq!(fill.amount_out, 999.0);
+        let (to, calldata) = swaps.lock().unwrap().remove(0);
+        assert_eq!(to, pool);
+        assert_eq!(calldata.len(), 4 + 4 * 32);
+        assert_eq!(word(&calldata, 3), U256::from(996_501_500u64));
+        assert_eq!(dex.decode_pending(pool, &calldata).unwrap().0, "curve");
+    }
+}
@@ -0,0 +1,2865 @@
+use crate::{
+    ack_latency, admin_api, alerts, approvals, backtest, block_watcher, balance, basis, bookkeeping, chain, client_auth, config, config_reload, connection_pool, crowding, event_bus, events,
+    execution_algo, execution_plan, execution_queue, flash_loan, funding_history, gateways, funding_model, journal, lending,
+    funding_settlement, gas, hedging, latency, liquidation, maker, margin, market_data, mempool,
+    oracle, order_ids, order_router, price_guard, protocol, quotes, rate_limit, rebalance, reconciliation, risk, routing, runtime, scanner, scheduler, secrets, session, sizing, spot_arbitrage, strategy,
+    storage, symbols, time_sync, tls, transfer_cost, triangular, tx_simulation, user_stream, validation, wallet, ArbitrageRequest, ArbitrageResponse, BatchItem,
+    BatchRequest, BatchResponse, ChildOrder, CommandResponse, EngineCommand, Environment, ExchangeConnector, LeverageSetting,
+    MarketContext, StrategyType,
+};
+use crate::config::Severity;
+use dashmap::DashMap;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Duration;
+use tracing::{debug, error, info, info_span, warn, Instrument};
+use tracing_subscriber::{reload, EnvFilter, Registry};
+
+/// 套利執行引擎。
+///
+/// 持有交易所連接、鏈上執行棧、風控與記帳等全部子系統狀態。以 [`ExecutionEngine::start`] 按配置建立並啟動
+/// 後台任務，以 [`ExecutionEngine::execute`] 提交套利請求，以 [`ExecutionEngine::handle_command`] 執行控制指令，
+/// 退出前調用 [`ExecutionEngine::shutdown`]。
+pub struct ExecutionEngine {
+    pub(crate) exchanges: HashMap<String, ExchangeConnector>,
+    pub(crate) symbols: symbols::SymbolRegistry,
+    // 鏈名 -> 鏈上執行棧，至少包含默認鏈
+    pub(crate) chains: BTreeMap<String, chain::ChainStack>,
+    pub(crate) gas_budget: gas::GasBudget,
+    // 每次執行都讀取，僅在管理指令中修改
+    pub(crate) disabled_strategies: RwLock<HashSet<StrategyType>>,
+    pub(crate) log_handle: Option<LogHandle>,
+    pub(crate) bookkeeper: bookkeeping::Bookkeeper,
+    pub(crate) history: Option<Arc<storage::HistoryStore>>,
+    pub(crate) funding_history: funding_history::FundingHistory,
+    pub(crate) sizing: sizing::NotionalCalibrator,
+    pub(crate) events: events::EventStore,
+    // 執行預寫日誌：重啟時找回崩潰前已下單但未結束的執行
+    pub(crate) journal: journal::Journal,
+    pub(crate) scanner: scanner::Scanner,
+    // 經 register_strategy 註冊的自定義策略
+    pub(crate) strategies: strategy::Registry,
+    pub(crate) crowding: crowding::CrowdingTracker,
+    pub(crate) basis: basis::BasisTracker,
+    pub(crate) lending: lending::LendingBook,
+    pub(crate) spot_arbitrage: spot_arbitrage::SpotArbitrage,
+    pub(crate) order_router: order_router::OrderRouter,
+    pub(crate) margin: margin::MarginDesk,
+    pub(crate) mempool: mempool::MempoolMonitor,
+    pub(crate) risk: risk::RiskModel,
+    pub(crate) routing: routing::LegRouter,
+    pub(crate) auth: client_auth::Authenticator,
+    pub(crate) algos: execution_algo::AlgoMonitor,
+    pub(crate) sessions: session::SessionSupervisor,
+    pub(crate) user_streams: user_stream::UserStreams,
+    pub(crate) order_ids: order_ids::OrderIds,
+    pub(crate) balances: balance::BalanceService,
+    pub(crate) liquidation: liquidation::LiquidationMonitor,
+    pub(crate) funding_pairs: funding_settlement::FundingTracker,
+    pub(crate) funding_model: funding_model::FundingModel,
+    pub(crate) queue: execution_queue::ExecutionQueue,
+    pub(crate) latency: latency::LatencyRecorder,
+    pub(crate) alerts: alerts::Alerter,
+    pub(crate) event_bus: event_bus::EventBus,
+    pub(crate) reconciler: reconciliation::Reconciler,
+    pub(crate) scheduler: scheduler::Scheduler,
+    pub(crate) rebalancer: rebalance::Rebalancer,
+    pub(crate) transfer_costs: transfer_cost::TransferCostModel,
+    pub(crate) price_guard: price_guard::PriceGuard,
+    pub(crate) oracle: oracle::OracleFeeds,
+    pub(crate) maker: maker::MakerStats,
+    pub(crate) quotes: quotes::QuoteCache,
+    pub(crate) ack_latency: ack_latency::AckLatencyModel,
+    // 時間戳、模擬延遲與模擬結果的來源
+    pub(crate) env: Environment,
+    // 啟動時加載的配置（只含密鑰所在的環境變量名），供管理接口查看
+    pub(crate) config: config::EngineConfig,
+    // 最近一次生效的配置（啟動或熱加載），熱加載時據此比較變更並串行化並發的加載
+    pub(crate) applied_config: Mutex<config::EngineConfig>,
+    // 緊急停止：啟用後拒絕所有新執行，強平降風險與資金費平倉不受影響
+    pub(crate) kill_switch: AtomicBool,
+    // 收到退出信號後拒絕新執行，等待執行中的請求完成
+    pub(crate) shutting_down: AtomicBool,
+    // 各連接並發增刪，按鍵分片加鎖
+    pub(crate) open_executions: DashMap<String, OpenExecution>,
+    // 客戶端 TCP 協議的監聽地址：綁定後設置，停止接受連接時清除，供就緒檢查
+    pub(crate) listen_address: RwLock<Option<String>>,
+}
+
+// 正在執行中的請求
+#[derive(Debug, Clone, Serialize)]
+pub(crate) struct OpenExecution {
+    pub(crate) strategy_id: String,
+    pub(crate) strategy_type: StrategyType,
+    pub(crate) symbol: String,
+    pub(crate) primary_exchange: String,
+    pub(crate) secondary_exchange: String,
+    pub(crate) amount: f64,
+    pub(crate) started_at_ms: i64,
+}
+
+// 一次套利執行的結果
+pub(crate) struct ExecutionOutcome {
+    pub(crate) profit: f64,
+    pub(crate) fees: f64,
+    pub(crate) orders: Vec<ChildOrder>,
+    // 滑點模型預期值與實際成交滑點（基點）
+    pub(crate) expected_slippage_bps: f64,
+    pub(crate) realized_slippage_bps: f64,
+    // 鏈上執行實際消耗的 gas、花費（ETH）與結算證明；模擬執行時為 None
+    pub(crate) gas_used: Option<u64>,
+    pub(crate) gas_cost_eth: Option<f64>,
+    pub(crate) settlement_proof: Option<flash_loan::SettlementProof>,
+    // 執行中途已反向平倉並完成模擬成交的腿與平倉單，只記賬
+    pub(crate) unwound: Vec<ChildOrder>,
+}
+
+// 一條腿相對請求金額的成交比例，其中以 maker 成交的部分
+pub(crate) struct LegFill {
+    pub(crate) ratio: f64,
+    pub(crate) maker_ratio: f64,
+}
+
+// 執行失敗的原因，以及已成交後被反向平掉的腿與其平倉單，供記賬
+#[derive(Debug)]
+pub(crate) struct ExecutionFailure {
+    pub(crate) error: String,
+    pub(crate) orders: Vec<ChildOrder>,
+}
+
+impl From<String> for ExecutionFailure {
+    fn from(error: String) -> Self {
+        Self { error, orders: Vec::new() }
+    }
+}
+
+// 通過准入後的定倉結果與預留的保證金，結束時歸還
+struct Admitted {
+    sizing: Option<sizing::SizingDecision>,
+    max_notional: f64,
+    leverage: LeverageSetting,
+    reservation: Option<balance::Reservation>,
+}
+
+// 待記賬的手續費、訂單與鏈上花費
+struct Settlement {
+    fees: f64,
+    orders: Vec<ChildOrder>,
+    gas_spent: Option<u64>,
+    gas_cost_eth: Option<f64>,
+    settlement_proof: Option<flash_loan::SettlementProof>,
+}
+
+// 資金費率策略的永續腿及其平倉單，模擬成交時按槓桿凍結保證金
+pub(crate) fn perp_leg(request: &ArbitrageRequest, leg: &str) -> bool {
+    request.strategy_type == StrategyType::FundingRate && matches!(leg.trim_start_matches("unwind_"), "short" | "long")
+}
+
+// 執行前被拒絕：一般錯誤，或派發後已被搶佔
+enum Rejection {
+    Error(String),
+    Preempted(String),
+}
+
+impl From<String> for Rejection {
+    fn from(error: String) -> Self {
+        Self::Error(error)
+    }
+}
+
+// 執行前被拒絕的響應，帶上執行 ID
+fn fail(execution_id: &str, rejection: Rejection) -> ArbitrageResponse {
+    let response = match rejection {
+        Rejection::Error(error) => ArbitrageResponse::error(error),
+        Rejection::Preempted(error) => ArbitrageResponse::preempted(error),
+    };
+    ArbitrageResponse {
+        execution_id: Some(execution_id.to_string()),
+        ..response
+    }
+}
+
+impl ExecutionOutcome {
+    // 各腿成交比例的最小值：對沖只覆蓋兩腿都成交的部分
+    pub(crate) fn fill_ratio(&self) -> f64 {
+        self.orders
+            .iter()
+            .filter(|order| order.quantity > 0.0)
+            .map(|order| order.filled_quantity / order.quantity)
+            .fold(1.0, f64::min)
+    }
+}
+
+/// 運行時調整日誌過濾的句柄，由 `set_log_level` 指令使用。
+pub type LogHandle = reload::Handle<EnvFilter, Registry>;
+
+impl ExecutionEngine {
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn new(
+        config: config::EngineConfig,
+        log_handle: Option<LogHandle>,
+        history: Option<storage::HistoryStore>,
+        events: events::EventStore,
+        journal: journal::Journal,
+        chains: BTreeMap<String, chain::ChainStack>,
+        routing: routing::LegRouter,
+        auth: client_auth::Authenticator,
+        env: Environment,
+    ) -> Result<Self, String> {
+        let loaded = config.clone();
+        let history = history.map(Arc::new);
+        let settings = |name: &str| config.exchanges.get(name).cloned().unwrap_or_default();
+        let balances = |name: &str| config.spot_arbitrage.inventory.get(name);
+        let connector = |name: &str, base_url: &str, settings: config::ExchangeConfig| {
+            ExchangeConnector::new(name, base_url, settings, balances(name), env.fork(name))
+                .map_err(|e| format!("初始化 {} 連接器失敗: {}", name, e))
+        };
+        let mut exchanges = HashMap::new();
+        
+        // 初始化交易所連接器；每個連接器使用獨立的隨機數流，並發請求的先後不影響各自的模擬結果
+        let venues = [("binance", "https://fapi.binance.com"), ("bybit", "https://api.bybit.com"), ("okx", "https://www.okx.com")];
+        for (name, base_url) in venues {
+            exchanges.insert(name.to_string(), connector(name, base_url, settings(name))?);
+        }
+        
+        // 未配置的交易所按默認設置（無覆蓋項）
+        let default_settings = config::ExchangeConfig::default();
+        let symbols = symbols::SymbolRegistry::new(
+            exchanges.keys().map(|name| (name.as_str(), config.exchanges.get(name).unwrap_or(&default_settings))),
+        )?;
+        
+        // 子賬戶各自一個連接器（鍵為 交易所@賬戶）：獨立的憑證、限頻配額、餘額、持倉與會話
+        for (name, base_url) in venues {
+            let venue_settings = settings(name);
+            for (account, account_config) in &venue_settings.accounts {
+                let key = config::account_key(name, account);
+                let connector = connector(&key, base_url, venue_settings.account_settings(name, account, account_config))?;
+                exchanges.insert(key, connector);
+            }
+        }
+        let mut supervised: Vec<&str> = exchanges.keys().map(String::as_str).collect();
+        supervised.sort();
+        let sessions = session::SessionSupervisor::new(config.sessions, supervised);
+        
+        // 當日事件：恢復各 strategy_id 的已實現盈虧與交易暫停狀態
+        let today = events.since(risk::day_start_ms(env.now_ms()));
+        let event_bus = event_bus::EventBus::new(config.event_bus.clone(), events.last_sequence());
+        
+        Ok(Self {
+            exchanges,
+            symbols,
+            chains,
+            gas_budget: gas::GasBudget::new(
+                config.gas.daily_budget_eth.clone(),
+                events.gas_spent_since(gas::utc_day_start_ms()),
+            ),
+            disabled_strategies: RwLock::new(config.disabled_strategies.iter().copied().collect()),
+            log_handle,
+            bookkeeper: bookkeeping::Bookkeeper::spawn(history.clone()),
+            history,
+            funding_history: funding_history::FundingHistory::new(),
+            sizing: sizing::NotionalCalibrator::new(config.sizing),
+            events,
+            journal,
+            scanner: scanner::Scanner::new(config.scanner),
+            strategies: strategy::Registry::default(),
+            crowding: crowding::CrowdingTracker::new(),
+            basis: basis::BasisTracker::new(config.basis),
+            lending: lending::LendingBook::new(config.lending),
+            spot_arbitrage: spot_arbitrage::SpotArbitrage::new(config.spot_arbitrage),
+            order_router: order_router::OrderRouter::new(config.order_router),
+            margin: margin::MarginDesk::new(config.margin),
+            mempool: mempool::MempoolMonitor::new(config.mempool),
+            risk: risk::RiskModel::new(config.risk, &today),
+            routing,
+            auth,
+            algos: execution_algo::AlgoMonitor::new(config.execution_algo),
+            sessions,
+            user_streams: user_stream::UserStreams::new(),
+            order_ids: order_ids::OrderIds::new(),
+            balances: balance::BalanceService::new(config.pre_trade),
+            liquidation: liquidation::LiquidationMonitor::new(config.liquidation),
+            funding_pairs: funding_settlement::FundingTracker::new(config.funding_exit),
+            funding_model: funding_model::FundingModel::new(),
+            queue: execution_queue::ExecutionQueue::new(config.execution_queue.clone()),
+            latency: latency::LatencyRecorder::new(),
+            alerts: alerts::Alerter::new(&config.alerts, env.vars.as_ref())?,
+            event_bus,
+            reconciler: reconciliation::Reconciler::new(config.reconciliation),
+            scheduler: scheduler::Scheduler::new(config.scheduler),
+            rebalancer: rebalance::Rebalancer::new(config.rebalance),
+            transfer_costs: transfer_cost::TransferCostModel::new(config.transfer_costs),
+            price_guard: price_guard::PriceGuard::new(config.price_guard),
+            oracle: oracle::OracleFeeds::new(config.oracle),
+            maker: maker::MakerStats::new(config.maker),
+            quotes: quotes::QuoteCache::new(config.quotes),
+            ack_latency: ack_latency::AckLatencyModel::new(config.ack_latency),
+            env,
+            applied_config: Mutex::new(loaded.clone()),
+            config: loaded,
+            kill_switch: AtomicBool::new(false),
+            shutting_down: AtomicBool::new(false),
+            open_executions: DashMap::new(),
+            listen_address: RwLock::new(None),
+        })
+    }
+    
+    /// 按配置建立引擎並啟動後台任務：資金費率採集、機會掃描、gas 與 mempool 監控、交易所會話、時鐘同步、
+    /// 強平與資金費結算監控、執行隊列，以及啟用時的 HTTP 管理接口。需在 tokio 運行時中調用。
+    ///
+    /// 歷史存儲連接 `ARB_DATABASE_URL`（缺省本地 SQLite），不可用時不落盤繼續運行；事件日誌位於
+    /// `ARB_EVENT_LOG`，啟動時回放重建持倉與 PnL 投影；執行預寫日誌位於 `ARB_EXECUTION_JOURNAL`，
+    /// 啟動時按交易所訂單狀態恢復或反向平掉崩潰前未結束的執行。`log_handle` 為空時 `set_log_level` 指令不可用。
+    pub async fn start(config: config::EngineConfig, log_handle: Option<LogHandle>) -> Result<Arc<Self>, String> {
+        Self::start_with(config, log_handle, Environment::default()).await
+    }
+    
+    /// 與 [`ExecutionEngine::start`] 相同，但時間、隨機數與上述變量取自 `env`；集成測試以 [`Environment::simulated`]
+    /// 注入模擬時鐘與固定種子，使交易所延遲、拒單與部分成交可重現。
+    pub async fn start_with(
+        config: config::EngineConfig,
+        log_handle: Option<LogHandle>,
+        env: Environment,
+    ) -> Result<Arc<Self>, String> {
+        let database_url = env
+            .var("ARB_DATABASE_URL")
+            .unwrap_or_else(|| storage::DEFAULT_DATABASE_URL.to_string());
+        let history = match storage::HistoryStore::connect(&database_url).await {
+            Ok(store) => Some(store),
+            Err(e) => {
+                error!(error = %e, "歷史存儲不可用");
+                None
+            }
+        };
+        
+        let event_log_path = env
+            .var("ARB_EVENT_LOG")
+            .unwrap_or_else(|| events::DEFAULT_EVENT_LOG_PATH.to_string());
+        let events = events::EventStore::open(&event_log_path, Arc::clone(&env.clock))
+            .map_err(|e| format!("打開事件日誌 {} 失敗: {}", event_log_path, e))?;
+        events.append(events::EngineEvent::ConfigChanged {
+            key: "engine".to_string(),
+            value: serde_json::json!(config),
+        });
+        let journal_path = env
+            .var("ARB_EXECUTION_JOURNAL")
+            .unwrap_or_else(|| journal::DEFAULT_JOURNAL_PATH.to_string());
+        let journal = journal::Journal::open(&journal_path)
+            .map_err(|e| format!("打開執行日誌 {} 失敗: {}", journal_path, e))?;
+        
+        // 每條鏈各自的熱錢包由閃電貸與 DEX 腿共用，nonce 按鏈統一管理
+        let mut chains = BTreeMap::new();
+        for (name, chain_config) in config.chain_configs() {
+            let stack = chain::ChainStack::connect(&name, &chain_config, &config.bundle, env.vars.as_ref())
+                .map_err(|e| format!("初始化鏈 {} 的執行棧失敗: {}", name, e))?;
+            chains.insert(name, stack);
+        }
+        let routing = tls::peer_connector(&config.tls)
+            .and_then(|connector| routing::LegRouter::new(config.routing.clone(), connector, env.vars.as_ref()))
+            .map_err(|e| format!("初始化多區域路由失敗: {}", e))?;
+        let auth = client_auth::Authenticator::new(config.client_auth.clone(), env.vars.as_ref())
+            .map_err(|e| format!("初始化客戶端認證失敗: {}", e))?;
+        
+        let secrets_provider = secrets::provider(&config.secrets, &env.vars).map_err(|e| format!("初始化密鑰服務失敗: {}", e))?;
+        let refresh_interval_secs = config.secrets.refresh_interval_secs;
+        let publisher = match config.event_bus.enabled {
+            true => Some(event_bus::publisher(&config.event_bus, env.vars.as_ref()).map_err(|e| format!("初始化事件總線失敗: {}", e))?),
+            false => None,
+        };
+        
+        let admin_api = config.admin_api.clone();
+        let engine = Arc::new(Self::new(config, log_handle, history, events, journal, chains, routing, auth, env)?);
+        secrets::load(&engine, secrets_provider.as_ref()).await?;
+        // 憑證就緒後、接收新請求前處理崩潰前未結束的執行
+        journal::recover(&engine).await;
+        // 執行派發與強平降風險留在當前（執行）運行時；隔離運行時時其餘後台任務派發到後台運行時
+        execution_queue::spawn(Arc::clone(&engine));
+        liquidation::spawn(Arc::clone(&engine));
+        runtime::in_background(|| {
+            secrets::spawn(Arc::clone(&engine), secrets_provider, refresh_interval_secs);
+            funding_history::spawn_collector(Arc::clone(&engine));
+            scanner::spawn(Arc::clone(&engine));
+            gas::spawn(Arc::clone(&engine));
+            mempool::spawn(Arc::clone(&engine));
+            session::spawn(Arc::clone(&engine));
+            time_sync::spawn(Arc::clone(&engine));
+            gateways::spawn(Arc::clone(&engine));
+            connection_pool::spawn(Arc::clone(&engine));
+            funding_settlement::spawn(Arc::clone(&engine));
+            admin_api::spawn(Arc::clone(&engine), admin_api);
+            config_reload::spawn(Arc::clone(&engine));
+            event_bus::spawn(Arc::clone(&engine), publisher);
+            reconciliation::spawn(Arc::clone(&engine));
+            scheduler::spawn(Arc::clone(&engine));
+            rebalance::spawn(Arc::clone(&engine));
+            lending::spawn(Arc::clone(&engine));
+            oracle::spawn(Arc::clone(&engine));
+            approvals::spawn(Arc::clone(&engine));
+            block_watcher::spawn(Arc::clone(&engine));
+            wallet::spawn(Arc::clone(&engine));
+        });
+        Ok(engine)
+    }
+    
+    /// 註冊自定義策略，見 [`strategy::Strategy`]；策略名重複時返回錯誤。
+    pub fn register_strategy(&self, strategy: Arc<dyn strategy::Strategy>) -> Result<(), String> {
+        self.strategies.register(strategy)
+    }
+    
+    /// 提交一筆套利請求並等待執行結果。
+    ///
+    /// 請求先按字段約束校驗，未通過時返回帶 `violations` 的錯誤響應；之後經執行隊列按優先級與預期收益排序，受各交易所並發上限約束；隊列已滿被擠出、排隊超時、
+    /// 超過 `deadline_ms`、緊急停止或引擎關閉時返回錯誤響應。執行失敗同樣以 `status == "error"` 的響應表示。
+    /// 啟用搶佔時，下單前讓出額度給更高優先級請求的執行以 `status == "preempted"` 返回，或推送 preempted 進度後重新排隊。
+    pub async fn execute(&self, request: ArbitrageRequest) -> ArbitrageResponse {
+        self.submit(request).await
+    }
+    
+    // 經執行隊列排隊後執行；未啟用隊列時直接執行
+    pub(crate) async fn submit(&self, mut request: ArbitrageRequest) -> ArbitrageResponse {
+        // 字段校驗先於排隊與任何行情、賬戶訪問
+        let violations = validation::validate(self, &request);
+        if !violations.is_empty() {
+            warn!(strategy_id = %request.strategy_id, ?violations, "請求校驗失敗");
+            return ArbitrageResponse::invalid(violations);
+        }
+        // 排隊前統一交易對格式，預期收益按內部格式查詢費率預測
+        match self.symbols.canonical(&request.symbol) {
+            Ok(symbol) => request.symbol = symbol,
+            Err(e) => return ArbitrageResponse::error(e),
+        }
+        if request.execute_at_ms.is_some() || request.execute_before_funding_secs.is_some() {
+            return scheduler::schedule(self, request);
+        }
+        if !self.queue.enabled() {
+            return self.execute_funding_rate_arbitrage(request).await;
+        }
+        let expected_edge = self.expected_edge(&request);
+        match self.queue.push(request, expected_edge) {
+            Ok(receiver) => receiver
+                .await
+                .unwrap_or_else(|_| ArbitrageResponse::error("執行隊列已關閉")),
+            Err(e) => ArbitrageResponse::error(e),
+        }
+    }
+    
+    // 批量請求各項並發提交，結果按請求順序返回
+    pub(crate) async fn submit_batch(&self, batch: BatchRequest) -> BatchResponse {
+        if batch.requests.is_empty() {
+            return BatchResponse::error("批量請求不能為空");
+        }
+        let results = futures::future::join_all(batch.requests.into_iter().map(|request| async move {
+            let symbol = request.symbol.clone();
+            BatchItem {
+                symbol,
+                response: self.submit(request).await,
+            }
+        }))
+        .await;
+        BatchResponse::from_results(results)
+    }
+    
+    // 以最近一次預測費率估算的預期淨收益，只用於排隊排序；沒有預測時為 0
+    pub(crate) fn expected_edge(&self, request: &ArbitrageRequest) -> f64 {
+        let rate = |exchange: &str| self.funding_model.latest_rate(exchange, &request.symbol);
+        match (rate(&request.primary_exchange), rate(&request.secondary_exchange)) {
+            (Some(primary), Some(secondary)) => {
+                (primary - secondary).abs()
+                    - self.taker_fee(&request.primary_exchange)
+                    - self.taker_fee(&request.secondary_exchange)
+            }
+            _ => 0.0,
+        }
+    }
+    
+    pub(crate) async fn execute_funding_rate_arbitrage(&self, request: ArbitrageRequest) -> ArbitrageResponse {
+        self.execute_as(self.env.uuid().to_string(), request).await
+    }
+    
+    // 以預先分配的 execution_id 執行，定時請求到點時沿用登記時返回的 ID
+    pub(crate) async fn execute_as(&self, execution_id: String, mut request: ArbitrageRequest) -> ArbitrageResponse {
+        let resolved = self
+            .symbols
+            .canonical(&request.symbol)
+            .map(|symbol| request.symbol = symbol)
+            .and_then(|_| self.resolve_accounts(&mut request));
+        if let Err(e) = resolved {
+            let mut response = ArbitrageResponse::error(e);
+            response.execution_id = Some(execution_id);
+            return response;
+        }
+        let span = info_span!(
+            "execution",
+            %execution_id,
+            strategy_id = %request.strategy_id,
+            symbol = %request.symbol,
+            primary_exchange = %request.primary_exchange,
+            secondary_exchange = %request.secondary_exchange,
+            fast_path = request.fast_path,
+        );
+        let open = OpenExecution {
+            strategy_id: request.strategy_id.clone(),
+            strategy_type: request.strategy_type,
+            symbol: request.symbol.clone(),
+            primary_exchange: request.primary_exchange.clone(),
+            secondary_exchange: request.secondary_exchange.clone(),
+            amount: request.amount,
+            started_at_ms: self.env.now_ms(),
+        };
+        self.events.append(events::EngineEvent::RequestReceived {
+            execution_id: execution_id.clone(),
+            strategy_id: open.strategy_id.clone(),
+            strategy_type: open.strategy_type,
+            symbol: open.symbol.clone(),
+            primary_exchange: open.primary_exchange.clone(),
+            secondary_exchange: open.secondary_exchange.clone(),
+            amount: open.amount,
+        });
+        self.open_executions.insert(execution_id.clone(), open);
+        self.journal.started(&execution_id, &request, self.env.now_ms());
+        let response = self.execute_in_span(execution_id.clone(), request).instrument(span).await;
+        self.journal.finished(&execution_id);
+        self.open_executions.remove(&execution_id);
+        response
+    }
+    
+    pub(crate) async fn execute_in_span(&self, execution_id: String, mut request: ArbitrageRequest) -> ArbitrageResponse {
+        let started_at_ms = self.env.now_ms();
+        let timer = latency::StageTimer::start();
+        
+        info!(amount = request.amount, priority = request.priority, "Rust 引擎執行高頻套利");
+        if let Err(rejection) = self.admit(&request) {
+            return fail(&execution_id, rejection);
+        }
+        self.funding_history.track(&request.symbol, self.env.now_ms());
+        for venue in [&request.primary_exchange, &request.secondary_exchange] {
+            let topic = format!("{}@markPrice", request.symbol.to_lowercase());
+            if let Some(connector) = self.exchanges.get(venue).filter(|_| self.sessions.subscribe(venue, &topic)) {
+                connector.send_subscribe(&topic).await;
+            }
+        }
+        let admitted = match self.reserve(&mut request).await {
+            Ok(admitted) => admitted,
+            Err(rejection) => return fail(&execution_id, rejection),
+        };
+        timer.mark("risk_check");
+        self.report_progress(&request, protocol::ExecutionProgress::stage(&execution_id, "risk_approved", self.env.now_ms()));
+        
+        let chain = match self.chain(request.chain.as_deref()) {
+            Ok(chain) => chain,
+            Err(error) => {
+                self.release(&request, &admitted);
+                return fail(&execution_id, Rejection::Error(error));
+            }
+        };
+        let gas_quote = chain.gas_optimizer.quote(request.priority);
+        debug!(chain = %chain.name, chain_id = chain.chain_id, ?gas_quote, "gas 報價");
+        let mut context = MarketContext {
+            captured_at_ms: self.env.now_ms(),
+            max_notional: admitted.max_notional,
+            gas_price: gas_quote.max_fee_per_gas,
+            max_gas_limit: chain.gas_optimizer.max_gas_limit(),
+            ..MarketContext::default()
+        };
+        
+        let result = self.run_strategy(&execution_id, &request, &gas_quote, &mut context, &timer).await;
+        let result = self.confirm_fills(&execution_id, &request, result, admitted.leverage).await;
+        timer.mark("fill_confirmation");
+        self.release(&request, &admitted);
+        if let Ok(outcome) = &result {
+            if request.strategy_type == StrategyType::FundingRate {
+                self.funding_pairs.open(&execution_id, &request.symbol, &outcome.orders, self.env.now_ms());
+            }
+            for order in outcome.orders.iter().filter(|order| !self.routing.is_remote(&order.exchange)) {
+                if let Some(connector) = self.exchanges.get(&order.exchange) {
+                    let perp = perp_leg(&request, &order.leg);
+                    connector.simulate_fill(&execution_id, order, perp.then_some(admitted.leverage.leverage));
+                }
+            }
+        }
+        let (mut response, settlement) = self.respond(&request, result, gas_quote.max_fee_per_gas, started_at_ms);
+        response.execution_id = Some(execution_id.clone());
+        response.sizing = admitted.sizing;
+        if request.include_market_context {
+            response.market_context = Some(context);
+        }
+        self.settle(&execution_id, &request, &response, settlement).await;
+        timer.mark("settle");
+        self.report_progress(
+            &request,
+            protocol::ExecutionProgress {
+                status: Some(response.status.clone()),
+                ..protocol::ExecutionProgress::stage(&execution_id, "settled", self.env.now_ms())
+            },
+        );
+        
+        let timings = timer.finish();
+        self.latency.record_timings(&timings);
+        response.timings = Some(timings);
+        response
+    }
+    
+    // 訪問交易所前的准入檢查：引擎狀態、風控暫停、請求參數、搶佔、交易所連接與報價可信度
+    fn admit(&self, request: &ArbitrageRequest) -> Result<(), Rejection> {
+        if self.shutting_down.load(Ordering::Relaxed) {
+            warn!("引擎正在關閉，拒絕執行");
+            return Err(Rejection::Error("引擎正在關閉，拒絕執行".to_string()));
+        }
+        if self.kill_switch.load(Ordering::Relaxed) {
+            warn!("緊急停止已啟用，拒絕執行");
+            return Err(Rejection::Error("緊急停止已啟用，拒絕執行".to_string()));
+        }
+        if let Some(halt) = self.risk.halted(&request.strategy_id, self.env.now_ms()) {
+            warn!(reason = %halt.reason, scope = ?halt.strategy_id, "當日限額已觸發，交易暫停中");
+            return Err(Rejection::Error(format!("交易已暫停至 UTC 日終: {}", halt.reason)));
+        }
+        if self.disabled_strategies.read().unwrap().contains(&request.strategy_type) {
+            warn!(strategy = request.strategy_type.name(), "策略已停用，拒絕執行");
+            return Err(Rejection::Error(format!("策略 {} 已停用", request.strategy_type.name())));
+        }
+        let leverage_requested = request.leverage.is_some() || request.margin_mode.is_some();
+        if leverage_requested && request.strategy_type != StrategyType::FundingRate {
+            return Err(Rejection::Error("槓桿與保證金模式僅支持資金費率策略".to_string()));
+        }
+        if request.deadline_ms.is_some_and(|deadline| deadline <= self.env.now_ms()) {
+            warn!(deadline_ms = ?request.deadline_ms, "請求已過執行截止時間");
+            return Err(Rejection::Error("請求已過執行截止時間".to_string()));
+        }
+        if request.leverage.is_some_and(|leverage| leverage.is_nan() || leverage < 1.0) {
+            return Err(Rejection::Error("槓桿不能小於 1".to_string()));
+        }
+        if let Some(algo) = &request.execution_algo {
+            let result = if request.strategy_type == StrategyType::FundingRate {
+                algo.validate(request.amount, self.algos.config())
+            } else {
+                Err("執行算法僅支持資金費率策略".to_string())
+            };
+            result?;
+        }
+        if let Some(maker) = &request.maker_first {
+            let result = if request.strategy_type == StrategyType::FundingRate {
+                maker.validate(self.maker.config())
+            } else {
+                Err("掛單優先僅支持資金費率策略".to_string())
+            };
+            result?;
+        }
+        // 派發後、訪問交易所前已被搶佔
+        if execution_queue::preempted(request) {
+            return Err(Rejection::Preempted(execution_queue::PREEMPTED_MESSAGE.to_string()));
+        }
+        // 連接未就緒的交易所暫停執行；轉交其他區域的腿由對方實例判斷，三角套利與自定義策略可不填 secondary_exchange，
+        // 借貸市場不經交易所會話
+        let unavailable = [&request.primary_exchange, &request.secondary_exchange].into_iter().find(|venue| {
+            !venue.is_empty() && !self.routing.is_remote(venue) && !self.lending.has_market(venue) && !self.sessions.is_available(venue)
+        });
+        if let Some(venue) = unavailable {
+            warn!(%venue, health = ?self.sessions.health(venue), "交易所連接未就緒，暫停執行");
+            return Err(Rejection::Error(format!("交易所 {} 連接未就緒，暫停執行", venue)));
+        }
+        // 穩定幣脫錨或報價異常時價差不可信
+        Ok(self.price_guard.verify(self, &request.symbol)?)
+    }
+    
+    // 定倉並縮減到校準上限，設置槓桿後預留保證金與策略資金額度；失敗時已預留的部分會歸還
+    async fn reserve(&self, request: &mut ArbitrageRequest) -> Result<Admitted, Rejection> {
+        // 請求指定定倉方式時按風險預算、實現波動率與訂單簿深度計算金額，不超過請求金額
+        let sizing = match &request.sizing {
+            Some(mode) => match sizing::size(self, request, mode) {
+                Ok(decision) => {
+                    info!(requested = decision.requested_amount, applied = decision.applied_amount, reason = %decision.reason, "已按波動率定倉");
+                    request.amount = decision.applied_amount;
+                    Some(decision)
+                }
+                Err(error) => {
+                    warn!(%error, "定倉失敗");
+                    return Err(Rejection::Error(error));
+                }
+            },
+            None => None,
+        };
+
+        // 按近期成交滑點校準後的上限縮減下單金額
+        let max_notional = self.sizing.max_notional(&request.symbol);
+        if request.amount > max_notional {
+            warn!(requested = request.amount, max_notional, "下單金額超過校準上限，已縮減");
+            request.amount = max_notional;
+        }
+        
+        // 兩邊設置相同的槓桿與保證金模式，確認生效後按用戶數據流緩存的可用保證金
+        // 預留兩條永續腿的保證金，不足時縮減或拒絕
+        let leverage = self.leverage_setting(request);
+        if let Err(error) = execution_queue::preemptible(request, self.configure_leverage(request, leverage)).await {
+            warn!(%error, "槓桿設置失敗");
+            if execution_queue::preempted(request) {
+                return Err(Rejection::Preempted(error));
+            }
+            return Err(Rejection::Error(error));
+        }
+        let reservation = self
+            .reserve_margin(request, leverage)
+            .inspect_err(|error| warn!(%error, "保證金檢查未通過"))?;
+        if let Some(reservation) = reservation.as_ref().filter(|reservation| reservation.notional < request.amount) {
+            warn!(requested = request.amount, sized = reservation.notional, "可用保證金不足，已縮減下單金額");
+            request.amount = reservation.notional;
+        }
+        // 按 strategy_id 的資金分配佔用額度，以縮減後的金額計
+        if let Err(error) = self.risk.reserve_capital(&request.strategy_id, request.amount, self.env.now_ms()) {
+            warn!(%error, "策略資金分配檢查未通過");
+            if let Some(reservation) = &reservation {
+                self.balances.release(reservation);
+            }
+            return Err(Rejection::Error(error));
+        }
+        Ok(Admitted { sizing, max_notional, leverage, reservation })
+    }
+    
+    // 歸還 reserve 預留的保證金與策略資金額度
+    fn release(&self, request: &ArbitrageRequest, admitted: &Admitted) {
+        if let Some(reservation) = &admitted.reservation {
+            self.balances.release(reservation);
+        }
+        self.risk.release_capital(&request.strategy_id, request.amount);
+    }
+    
+    // 按策略類型執行下單
+    async fn run_strategy(
+        &self,
+        execution_id: &str,
+        request: &ArbitrageRequest,
+        gas_quote: &gas::GasQuote,
+        context: &mut MarketContext,
+        timer: &latency::StageTimer,
+    ) -> Result<ExecutionOutcome, ExecutionFailure> {
+        let result = match request.strategy_type {
+            StrategyType::FundingRate => {
+                return self.perform_high_frequency_arbitrage(execution_id, request, gas_quote, context, timer).await;
+            }
+            // 其他策略沒有下單前的行情階段，此處即開始下單
+            _ if execution_queue::commit(request).is_err() => Err(execution_queue::PREEMPTED_MESSAGE.to_string()),
+            StrategyType::Triangular => triangular::execute(self, request).await,
+            StrategyType::CashAndCarry => basis::execute(self, request).await,
+            StrategyType::SpotArbitrage => spot_arbitrage::execute(self, execution_id, request).await,
+            StrategyType::Plugin => strategy::execute(self, execution_id, request).await,
+            StrategyType::LendingRate => lending::execute(self, execution_id, request).await,
+        };
+        timer.mark("execution");
+        Ok(result?)
+    }
+    
+    // 交易所由其他區域實例負責的腿轉交該實例執行，以其成交回報為準；
+    // 確認超時或失敗時反向平掉本地已成交的腿，避免留下單腿持倉
+    async fn confirm_fills(
+        &self,
+        execution_id: &str,
+        request: &ArbitrageRequest,
+        result: Result<ExecutionOutcome, ExecutionFailure>,
+        leverage: LeverageSetting,
+    ) -> Result<ExecutionOutcome, ExecutionFailure> {
+        let outcome = result?;
+        let timeout_ms = self.config.stage_timeouts.fill_confirmation_ms;
+        let dispatched = self
+            .within_stage("fill_confirmation", timeout_ms, request.deadline_ms, self.routing.dispatch(outcome.orders.clone()))
+            .await;
+        match dispatched {
+            Ok(orders) => Ok(ExecutionOutcome { orders, ..outcome }),
+            Err(error) => {
+                let mut unwound = self.unwind_local_legs(execution_id, request, &outcome.orders, leverage);
+                if !unwound.is_empty() {
+                    let message = format!(
+                        "{} {} {}/{}：{}，已反向平掉 {} 條本地腿",
+                        execution_id, request.symbol, request.primary_exchange, request.secondary_exchange, error, unwound.len() / 2
+                    );
+                    self.alert(Severity::Critical, "執行未能確認，已反向平倉", message);
+                }
+                unwound.extend(outcome.unwound);
+                Err(ExecutionFailure { error, orders: unwound })
+            }
+        }
+    }
+    
+    // 按執行結果生成響應與待記賬的內容；失敗時只記反向平倉的腿
+    fn respond(
+        &self,
+        request: &ArbitrageRequest,
+        result: Result<ExecutionOutcome, ExecutionFailure>,
+        quoted_gas: u64,
+        started_at_ms: i64,
+    ) -> (ArbitrageResponse, Settlement) {
+        match result {
+            Ok(outcome) => {
+                self.sizing.observe(&request.symbol, outcome.expected_slippage_bps, outcome.realized_slippage_bps);
+                let execution_time = self.env.now_ms() - started_at_ms;
+                info!(profit = outcome.profit, execution_time_ms = execution_time as u64, "套利執行成功");
+                let response = ArbitrageResponse {
+                    version: protocol::CURRENT_VERSION,
+                    execution_id: None,
+                    status: "success".to_string(),
+                    profit: Some(outcome.profit),
+                    execution_time: format!("{}ms", execution_time),
+                    gas_used: Some(outcome.gas_used.unwrap_or(quoted_gas)),
+                    error_message: None,
+                    market_context: None,
+                    timings: None,
+                    scheduled_for_ms: None,
+                    sizing: None,
+                    violations: Vec::new(),
+                };
+                let settlement = Settlement {
+                    fees: outcome.fees,
+                    orders: outcome.orders.into_iter().chain(outcome.unwound).collect(),
+                    gas_spent: outcome.gas_used,
+                    gas_cost_eth: outcome.gas_cost_eth,
+                    settlement_proof: outcome.settlement_proof,
+                };
+                (response, settlement)
+            }
+            Err(ExecutionFailure { error, orders }) => {
+                warn!(%error, "套利執行失敗");
+                let response = if execution_queue::preempted(request) {
+                    ArbitrageResponse::preempted(error)
+                } else {
+                    ArbitrageResponse::error(error)
+                };
+                let settlement = Settlement {
+                    fees: orders.iter().map(|order| order.fee).sum(),
+                    orders,
+                    gas_spent: None,
+                    gas_cost_eth: None,
+                    settlement_proof: None,
+                };
+                (response, settlement)
+            }
+        }
+    }
+    
+    // 寫入事件日誌、已實現盈虧、gas 花費與結算證明，並記賬
+    async fn settle(&self, execution_id: &str, request: &ArbitrageRequest, response: &ArbitrageResponse, settlement: Settlement) {
+        let Settlement { fees, orders, gas_spent, gas_cost_eth, settlement_proof } = settlement;
+        self.record_execution_events(execution_id, request, response, fees, &orders);
+        self.record_realized_pnl(&request.strategy_id, events::settled_pnl(&response.status, response.profit.unwrap_or(0.0), fees));
+        if let (Some(gas_used), Some(cost_eth)) = (gas_spent, gas_cost_eth) {
+            let strategy = request.strategy_type.name();
+            self.gas_budget.record(strategy, cost_eth);
+            self.events.append(events::EngineEvent::GasSpent {
+                execution_id: execution_id.to_string(),
+                strategy: strategy.to_string(),
+                gas_used,
+                cost_eth,
+            });
+        }
+        if let Some(proof) = settlement_proof {
+            self.events.append(events::EngineEvent::SettlementProof {
+                execution_id: execution_id.to_string(),
+                proof,
+            });
+        }
+        
+        let record = bookkeeping::ExecutionRecord::new(execution_id.to_string(), request, response, fees, orders, self.env.now_ms());
+        if request.fast_path {
+            self.bookkeeper.defer(record);
+        } else {
+            self.bookkeeper.record(record).await;
+        }
+    }
+    
+    pub(crate) async fn perform_high_frequency_arbitrage(
+        &self,
+        execution_id: &str,
+        request: &ArbitrageRequest,
+        gas_quote: &gas::GasQuote,
+        context: &mut MarketContext,
+        timer: &latency::StageTimer,
+    ) -> Result<ExecutionOutcome, ExecutionFailure> {
+        // 1. 獲取預測的下一期資金費率；沒有永續合約的一側只能借幣賣出現貨做空，
+        //    不收資金費而是支付借幣利息，按負費率參與比較
+        let margin_exchange = match (
+            self.margin.has_perp(&request.primary_exchange, &request.symbol),
+            self.margin.has_perp(&request.secondary_exchange, &request.symbol),
+        ) {
+            (true, true) => None,
+            (false, true) => Some(&request.primary_exchange),
+            (true, false) => Some(&request.secondary_exchange),
+            (false, false) => return Err(format!("兩個交易所均無 {} 永續合約", request.symbol).into()),
+        };
+        let (base, _) = market_data::split_symbol(&request.symbol)
+            .ok_or_else(|| format!("無法解析交易對: {}", request.symbol))?;
+        let mut effective_rate = |exchange: &String, rate: Option<f64>| -> Result<f64, String> {
+            match rate {
+                Some(rate) => Ok(rate),
+                None => {
+                    let borrow_rate = self.margin.borrow_rate(exchange, &base)?;
+                    context.borrow_rate = Some(borrow_rate);
+                    Ok(-borrow_rate)
+                }
+            }
+        };
+        let timeouts = &self.config.stage_timeouts;
+        let rates = async {
+            let primary_rate = match margin_exchange {
+                Some(exchange) if *exchange == request.primary_exchange => None,
+                _ => Some(self.fresh_predicted_rate(&request.primary_exchange, &request.symbol).await?),
+            };
+            let secondary_rate = match margin_exchange {
+                Some(exchange) if *exchange == request.secondary_exchange => None,
+                _ => Some(self.fresh_predicted_rate(&request.secondary_exchange, &request.symbol).await?),
+            };
+            Ok((primary_rate, secondary_rate))
+        };
+        let rates = self.within_stage("rate_fetch", timeouts.rate_fetch_ms, request.deadline_ms, rates);
+        let (primary_rate, secondary_rate) = execution_queue::preemptible(request, rates).await?;
+        timer.mark("rate_lookup");
+        context.primary_rate = primary_rate;
+        context.secondary_rate = secondary_rate;
+        let primary_rate = effective_rate(&request.primary_exchange, primary_rate)?;
+        let secondary_rate = effective_rate(&request.secondary_exchange, secondary_rate)?;
+        
+        debug!(primary_rate, secondary_rate, borrow_rate = ?context.borrow_rate, "資金費率");
+        
+        // 2. 計算套利機會（快速通道由客戶端承擔風險，不再重驗）
+        let rate_diff = primary_rate - secondary_rate;
+        context.rate_diff = Some(rate_diff);
+        context.expected_slippage_bps = Some(self.sizing.expected_slippage_bps(request.amount));
+        if !request.fast_path && rate_diff.abs() < 0.0001 {
+            // 價差在我們下單前已被抹平，視為被其他套利者搶先
+            self.crowding.record_attempt(&request.symbol, true);
+            return Err("資金費率差異太小".to_string().into());
+        }
+        
+        // 之後預留借幣並下單，不再可被搶佔
+        execution_queue::commit(request)?;
+        
+        // 3. 現貨槓桿一側必須是空頭腿，下單前預留借幣額度
+        let short_exchange = if rate_diff > 0.0 { &request.primary_exchange } else { &request.secondary_exchange };
+        let margin_borrow = match margin_exchange {
+            Some(exchange) if exchange != short_exchange => {
+                return Err(format!("{} 無 {} 永續合約，現貨槓桿只能承擔空頭腿", exchange, request.symbol).into());
+            }
+            Some(exchange) => {
+                let quantity = self.margin.borrow_quantity(self.env.rng.as_ref(), exchange, &request.symbol, request.amount)?;
+                self.margin.reserve(exchange, &base, quantity)?;
+                Some((exchange, quantity))
+            }
+            None => None,
+        };
+        
+        // 4. 執行閃電貸套利（指定執行算法時分片執行）；失敗時歸還預留的借幣額度，算法中止時歸還未成交部分。
+        //    分片算法按自身時長執行，只在開始前檢查截止時間
+        //    已反向平倉的現貨槓桿空頭腿在平倉時歸還借幣，同樣不再按未成交部分歸還
+        let unwound_short = |orders: &[ChildOrder]| {
+            orders.iter().filter(|order| order.leg.starts_with("margin_short")).map(|order| order.filled_quantity).sum::<f64>()
+        };
+        let (result, filled_notional) = match &request.execution_algo {
+            Some(_) if request.deadline_ms.is_some_and(|deadline| deadline <= self.env.now_ms()) => {
+                (Err("已超過執行截止時間，放棄 leg_submission 階段".to_string().into()), 0.0)
+            }
+            Some(algo) => {
+                let result =
+                    execution_algo::run(self, execution_id, request, algo, rate_diff, gas_quote, margin_borrow.is_some())
+                        .await;
+                timer.mark("execution");
+                match result {
+                    Ok((outcome, filled_notional)) => {
+                        let filled_notional = filled_notional + unwound_short(&outcome.unwound);
+                        (Ok(outcome), filled_notional)
+                    }
+                    Err(failure) => {
+                        let filled_notional = unwound_short(&failure.orders);
+                        (Err(failure), filled_notional)
+                    }
+                }
+            }
+            None => {
+                let submission = async {
+                    Ok(self.execute_flash_loan_arbitrage(execution_id, request, rate_diff, gas_quote, margin_borrow.is_some(), Some(timer)).await)
+                };
+                let result = match self.within_stage("leg_submission", timeouts.leg_submission_ms, request.deadline_ms, submission).await {
+                    Ok(result) => result,
+                    Err(error) => Err(self.unwind_journaled_legs(execution_id, request, error)),
+                };
+                let filled_notional = match &result {
+                    Ok(outcome) => request.amount * outcome.fill_ratio(),
+                    Err(failure) => unwound_short(&failure.orders),
+                };
+                (result, filled_notional)
+            }
+        };
+        if let Some((exchange, quantity)) = margin_borrow {
+            let unfilled = (1.0 - filled_notional / request.amount).clamp(0.0, 1.0);
+            if unfilled > 0.0 {
+                self.margin.release(exchange, &base, quantity * unfilled);
+            }
+        }
+        result
+    }
+    
+    // 算法執行的每片下單前重新計算預測費率差；無永續合約的一側按負借幣利率參與比較
+    pub(crate) async fn current_rate_diff(&self, request: &ArbitrageRequest) -> Result<f64, String> {
+        let (base, _) = market_data::split_symbol(&request.symbol)
+            .ok_or_else(|| format!("無法解析交易對: {}", request.symbol))?;
+        let mut rates = Vec::new();
+        for exchange in [&request.primary_exchange, &request.secondary_exchange] {
+            rates.push(if self.margin.has_perp(exchange, &request.symbol) {
+                self.fresh_predicted_rate(exchange, &request.symbol).await?
+            } else {
+                -self.margin.borrow_rate(exchange, &base)?
+            });
+        }
+        Ok(rates[0] - rates[1])
+    }
+    
+    pub(crate) async fn get_funding_rate(&self, exchange: &str, symbol: &str) -> Result<f64, String> {
+        let connector = self
+            .exchanges
+            .get(exchange)
+            .ok_or_else(|| format!("不支持的交易所: {}", exchange))?;
+        if !self.margin.has_perp(exchange, symbol) {
+            return Err(format!("{} 無 {} 永續合約", exchange, symbol));
+        }
+        
+        // 配置了備用端點時發送對沖請求：主端點超過延遲未返回則同時請求備用端點，取先返回者
+        let base_url = connector.base_url();
+        let primary = connector.fetch_funding_rate(&base_url, symbol);
+        let rate = match (&connector.settings.backup_base_url, connector.settings.hedge_delay_ms) {
+            (Some(backup_url), Some(delay_ms)) => {
+                hedging::hedged(
+                    Duration::from_millis(delay_ms),
+                    primary,
+                    || connector.fetch_funding_rate(backup_url, symbol),
+                )
+                .await?
+            }
+            _ => primary.await?,
+        };
+        self.quotes.record_funding_rate(exchange, symbol, rate, self.env.now_ms());
+        Ok(rate)
+    }
+    
+    // 費率較高的一方做空；金額按校準上限縮減，開倉吃單費計入成本
+    pub(crate) async fn simulate_opportunity(
+        &self,
+        symbol: &str,
+        primary_exchange: &str,
+        secondary_exchange: &str,
+        amount: Option<f64>,
+        priority: i32,
+    ) -> Result<serde_json::Value, String> {
+        let primary_rate = self.get_predicted_funding_rate(primary_exchange, symbol).await?;
+        let secondary_rate = self.get_predicted_funding_rate(secondary_exchange, symbol).await?;
+        let max_notional = self.sizing.max_notional(symbol);
+        let amount = amount.unwrap_or(max_notional).min(max_notional);
+        let rate_diff = primary_rate - secondary_rate;
+        let gross_profit = amount * rate_diff.abs();
+        let fee_cost = amount * (self.taker_fee(primary_exchange) + self.taker_fee(secondary_exchange));
+        let expected_slippage_bps = self.sizing.expected_slippage_bps(amount);
+        let slippage_cost = amount * expected_slippage_bps / 10_000.0;
+        Ok(serde_json::json!({
+            "symbol": symbol,
+            "short_exchange": if rate_diff > 0.0 { primary_exchange } else { secondary_exchange },
+            "long_exchange": if rate_diff > 0.0 { secondary_exchange } else { primary_exchange },
+            "primary_rate": primary_rate,
+            "secondary_rate": secondary_rate,
+            "rate_diff": rate_diff,
+            "amount": amount,
+            "max_notional": max_notional,
+            "gross_profit": gross_profit,
+            "fee_cost": fee_cost,
+            "expected_slippage_bps": expected_slippage_bps,
+            "net_profit": gross_profit - fee_cost - slippage_cost,
+            "gas_quote": self.default_chain().gas_optimizer.quote(priority),
+        }))
+    }
+    
+    // 按執行時的方向推斷擬下單的各條腿，疊加到當前持倉上估算風險變化
+    pub(crate) async fn preview_risk(&self, request: &ArbitrageRequest) -> Result<risk::RiskPreview, String> {
+        let (base, quote) = market_data::split_symbol(&request.symbol)
+            .ok_or_else(|| format!("無法解析交易對: {}", request.symbol))?;
+        let (primary, secondary) = (&request.primary_exchange, &request.secondary_exchange);
+        let leg = |exchange: &str, notional: f64| risk::Leg {
+            exchange: exchange.to_string(),
+            symbol: request.symbol.clone(),
+            notional,
+        };
+        let mut borrow = None;
+        let legs = match request.strategy_type {
+            StrategyType::FundingRate => {
+                // 與執行路徑一致：按預測費率比較，無永續合約的一側按負借幣利率比較
+                let mut rates = Vec::new();
+                for exchange in [primary, secondary] {
+                    rates.push(if self.margin.has_perp(exchange, &request.symbol) {
+                        self.get_predicted_funding_rate(exchange, &request.symbol).await?
+                    } else {
+                        -self.margin.borrow_rate(exchange, &base)?
+                    });
+                }
+                let (short, long) = if rates[0] > rates[1] { (primary, secondary) } else { (secondary, primary) };
+                if !self.margin.has_perp(long, &request.symbol) {
+                    return Err(format!("{} 無 {} 永續合約，現貨槓桿只能承擔空頭腿", long, request.symbol));
+                }
+                if !self.margin.has_perp(short, &request.symbol) {
+                    borrow = Some(risk::Borrow {
+                        exchange: short.clone(),
+                        asset: base.clone(),
+                        quantity: self.margin.borrow_quantity(self.env.rng.as_ref(), short, &request.symbol, request.amount)?,
+                    });
+                }
+                vec![leg(short, -request.amount), leg(long, request.amount)]
+            }
+            StrategyType::CashAndCarry => vec![leg(primary, request.amount), leg(secondary, -request.amount)],
+            StrategyType::SpotArbitrage => {
+                // DEX 沒有本地訂單簿，無法比較時按 primary 買入估算
+                let mid = |exchange: &str| {
+                    market_data::simulated_spot_book(self.env.rng.as_ref(), exchange, &base, &quote)
+                        .ok()
+                        .and_then(|book| book.mid())
+                };
+                let buy_primary = match (mid(primary), mid(secondary)) {
+                    (Some(primary_mid), Some(secondary_mid)) => primary_mid <= secondary_mid,
+                    _ => true,
+                };
+                let (buy, sell) = if buy_primary { (primary, secondary) } else { (secondary, primary) };
+                vec![leg(buy, request.amount), leg(sell, -request.amount)]
+            }
+            // 三角套利在同一交易所閉環，不留下淨頭寸
+            StrategyType::Triangular => Vec::new(),
+            // 借貸頭寸的風險由健康因子監控，期現部署的兩條腿互相對沖
+            StrategyType::LendingRate => Vec::new(),
+            // 按策略計劃中的交易所單估算，買入為正、賣出為負
+            StrategyType::Plugin => strategy::plan(self, request)?
+                .1
+                .steps()
+                .iter()
+                .filter_map(|step| match &step.action {
+                    execution_plan::Action::Order { exchange, symbol, side, quantity } => Some(risk::Leg {
+                        notional: if side == "sell" { -quantity } else { *quantity },
+                        exchange: exchange.clone(),
+                        symbol: symbol.clone(),
+                    }),
+                    _ => None,
+                })
+                .collect(),
+        };
+        let positions = self.events.current().positions;
+        let mut preview = self.risk.preview(&positions, &legs, borrow.as_ref(), &self.margin.snapshot());
+        let mut unavailable: Vec<&str> = legs
+            .iter()
+            .map(|leg| leg.exchange.as_str())
+            .filter(|exchange| !self.sessions.is_available(exchange))
+            .collect();
+        unavailable.dedup();
+        preview
+            .breaches
+            .extend(unavailable.into_iter().map(|exchange| format!("交易所 {} 連接未就緒，執行將暫停", exchange)));
+        if let Some(legs) = self.margin_legs(request) {
+            match self.balances.check(&self.user_streams, &legs, request.amount, self.leverage_setting(request).leverage) {
+                Ok(sized) if sized < request.amount => {
+                    preview.breaches.push(format!("可用保證金不足，下單金額將縮減至 {:.2}", sized));
+                }
+                Ok(_) => {}
+                Err(error) => preview.breaches.push(error),
+            }
+        }
+        Ok(preview)
+    }
+    
+    // 資金費率策略中本實例負責且有永續合約的交易所
+    pub(crate) fn perp_venues<'a>(&self, request: &'a ArbitrageRequest) -> Vec<&'a String> {
+        if request.strategy_type != StrategyType::FundingRate {
+            return Vec::new();
+        }
+        [&request.primary_exchange, &request.secondary_exchange]
+            .into_iter()
+            .filter(|exchange| !self.routing.is_remote(exchange) && self.margin.has_perp(exchange, &request.symbol))
+            .collect()
+    }
+    
+    // 需要保證金檢查的腿：永續腿所在交易所及其手續費率
+    pub(crate) fn margin_legs<'a>(&self, request: &'a ArbitrageRequest) -> Option<Vec<(&'a str, f64)>> {
+        if !self.balances.enabled() {
+            return None;
+        }
+        Some(
+            self.perp_venues(request)
+                .into_iter()
+                .map(|exchange| (exchange.as_str(), self.taker_fee(exchange)))
+                .collect(),
+        )
+    }
+    
+    pub(crate) fn leverage_setting(&self, request: &ArbitrageRequest) -> LeverageSetting {
+        let default = self.balances.default_setting();
+        LeverageSetting {
+            leverage: request.leverage.unwrap_or(default.leverage),
+            margin_mode: request.margin_mode.unwrap_or(default.margin_mode),
+        }
+    }
+    
+    pub(crate) fn reserve_margin(
+        &self,
+        request: &ArbitrageRequest,
+        setting: LeverageSetting,
+    ) -> Result<Option<balance::Reservation>, String> {
+        match self.margin_legs(request) {
+            Some(legs) => self
+                .balances
+                .reserve(&self.user_streams, &legs, request.amount, setting.leverage)
+                .map(Some),
+            None => Ok(None),
+        }
+    }
+    
+    // 在兩邊交易所設置槓桿與保證金模式並回查確認；已是目標設置時跳過
+    pub(crate) async fn configure_leverage(&self, request: &ArbitrageRequest, setting: LeverageSetting) -> Result<(), String> {
+        let venues = self.perp_venues(request);
+        let results = futures::future::join_all(venues.into_iter().map(|venue| async move {
+            let connector = self.exchanges.get(venue).ok_or_else(|| format!("未知交易所: {}", venue))?;
+            if connector.fetch_leverage(&request.symbol).await? == Some(setting) {
+                return Ok(());
+            }
+            connector.set_leverage(&request.symbol, setting).await?;
+            match connector.fetch_leverage(&request.symbol).await? {
+                Some(actual) if actual == setting => {
+                    info!(%venue, leverage = setting.leverage, margin_mode = ?setting.margin_mode, "槓桿設置已生效");
+                    Ok(())
+                }
+                actual => Err(format!("{} {} 槓桿設置未生效: 期望 {:?}，實際 {:?}", venue, request.symbol, setting, actual)),
+            }
+        }))
+        .await;
+        results.into_iter().collect()
+    }
+    
+    // 回測不觸及引擎的運行狀態：數據文件的讀取與回放在阻塞線程池中進行
+    pub(crate) async fn run_backtest(&self, params: backtest::BacktestParams) -> Result<backtest::BacktestReport, String> {
+        let stored = match (&params.data_path, &self.history) {
+            (Some(_), _) => Vec::new(),
+            (None, Some(history)) => history
+                .funding_samples(params.from_ms, params.to_ms)
+                .await
+                .map_err(|e| format!("讀取資金費率樣本失敗: {}", e))?,
+            (None, None) => return Err("未指定 data_path 且未啟用歷史存儲".to_string()),
+        };
+        let config = self.config.clone();
+        tokio::task::spawn_blocking(move || {
+            let events = match &params.data_path {
+                Some(path) => backtest::load_recording(path)?,
+                None => stored.into_iter().map(backtest::RecordedEvent::from).collect(),
+            };
+            Ok(backtest::run(&config, &params, events))
+        })
+        .await
+        .map_err(|e| format!("回測任務失敗: {}", e))?
+    }
+    
+    // 預覽拆單計劃，不計庫存約束
+    pub(crate) fn plan_order(
+        &self,
+        symbol: &str,
+        side: order_router::Side,
+        quantity: f64,
+        exchanges: Option<&[String]>,
+    ) -> Result<order_router::RoutePlan, String> {
+        if quantity <= 0.0 {
+            return Err("數量必須為正".to_string());
+        }
+        let (base, quote) = market_data::split_symbol(symbol).ok_or_else(|| format!("無法解析交易對: {}", symbol))?;
+        let mut names: Vec<&String> = match exchanges {
+            Some(exchanges) => exchanges.iter().collect(),
+            None => self.venues(),
+        };
+        names.sort();
+        let mut venues = Vec::new();
+        for exchange in names {
+            if !self.exchanges.contains_key(exchange) {
+                return Err(format!("不支持的交易所: {}", exchange));
+            }
+            venues.push(order_router::Venue {
+                exchange: exchange.clone(),
+                book: market_data::simulated_spot_book(self.env.rng.as_ref(), exchange, &base, &quote)?,
+                fee_rate: self.taker_fee(exchange),
+                capacity: f64::INFINITY,
+                transfer_cost: 0.0,
+            });
+        }
+        Ok(self.order_router.plan(side, quantity, &venues))
+    }
+    
+    // 按腿獲取各交易所的下單配額；轉交其他區域實例的腿由對方計入
+    pub(crate) async fn acquire_orders(&self, exchanges: &[&str]) -> Result<(), String> {
+        for exchange in exchanges {
+            if self.routing.is_remote(exchange) {
+                continue;
+            }
+            if let Some(connector) = self.exchanges.get(*exchange) {
+                connector
+                    .scheduler
+                    .acquire(rate_limit::RequestKind::Order, connector.settings.rate_limit.order_weight)
+                    .await?;
+            }
+        }
+        Ok(())
+    }
+    
+    // 在本區域的交易所上執行其他實例轉發的腿
+    pub(crate) async fn execute_routed_leg(&self, envelope: routing::LegEnvelope) -> Result<ChildOrder, String> {
+        let order = self.routing.verify(&envelope)?;
+        if self.shutting_down.load(Ordering::Relaxed) {
+            return Err("引擎正在關閉，拒絕執行".to_string());
+        }
+        if self.kill_switch.load(Ordering::Relaxed) {
+            return Err("緊急停止已啟用，拒絕執行".to_string());
+        }
+        if !self.exchanges.contains_key(&order.exchange) || self.routing.is_remote(&order.exchange) {
+            return Err(format!("{} 不由本實例執行", order.exchange));
+        }
+        self.acquire_orders(&[&order.exchange]).await?;
+        // 轉發的腿以發起區域代替策略、leg_id 代替執行 ID 生成訂單 ID
+        let client_order_id = self.order_ids.assign(&envelope.origin, &envelope.leg_id, &order.leg);
+        let ratio = self.exchanges[&order.exchange]
+            .submit_order(&order.symbol, &order.side, order.quantity, &client_order_id)
+            .await?;
+        let order = ChildOrder {
+            filled_quantity: order.quantity * ratio,
+            status: if ratio < 1.0 { "partially_filled" } else { "filled" }.to_string(),
+            ..order
+        };
+        // 轉發的腿不帶槓桿信息，不凍結保證金
+        self.exchanges[&order.exchange].simulate_fill(&envelope.leg_id, &order, None);
+        info!(
+            origin = %envelope.origin,
+            leg_id = %envelope.leg_id,
+            exchange = %order.exchange,
+            side = %order.side,
+            quantity = order.quantity,
+            "已執行轉發的腿"
+        );
+        Ok(order)
+    }
+    
+    // 未指定鏈時使用默認鏈
+    pub(crate) fn chain(&self, name: Option<&str>) -> Result<&chain::ChainStack, String> {
+        let name = name.unwrap_or(config::DEFAULT_CHAIN);
+        self.chains.get(name).ok_or_else(|| format!("未配置鏈: {}", name))
+    }
+    
+    pub(crate) fn default_chain(&self) -> &chain::ChainStack {
+        &self.chains[config::DEFAULT_CHAIN]
+    }
+    
+    // 把兩側交易所換成所用賬戶的連接器鍵（如 binance@sub1）：請求指定的 account 優先，其次是 strategy_id
+    // 所屬的子賬戶，否則為主賬戶。之後的下單、持倉、餘額與限頻都按該連接器計
+    pub(crate) fn resolve_accounts(&self, request: &mut ArbitrageRequest) -> Result<(), String> {
+        for exchange in [&mut request.primary_exchange, &mut request.secondary_exchange] {
+            if exchange.contains('@') || !self.exchanges.contains_key(exchange.as_str()) {
+                continue;
+            }
+            let accounts = self.config.exchanges.get(exchange.as_str()).map(|settings| &settings.accounts);
+            let account = match &request.account {
+                Some(account) => account.clone(),
+                None => accounts
+                    .and_then(|accounts| {
+                        accounts
+                            .iter()
+                            .find(|(_, account)| account.strategies.contains(&request.strategy_id))
+                            .map(|(name, _)| name.clone())
+                    })
+                    .unwrap_or_else(|| config::MAIN_ACCOUNT.to_string()),
+            };
+            let key = config::account_key(exchange, &account);
+            if !self.exchanges.contains_key(&key) {
+                return Err(format!("交易所 {} 未配置賬戶 {}", exchange, account));
+            }
+            *exchange = key;
+        }
+        Ok(())
+    }
+    
+    // 各交易所主賬戶的連接器鍵（不含子賬戶），按名稱排序；行情與跨交易所報價按此遍歷
+    pub(crate) fn venues(&self) -> Vec<&String> {
+        let mut venues: Vec<&String> = self.exchanges.keys().filter(|name| !name.contains('@')).collect();
+        venues.sort();
+        venues
+    }
+    
+    pub(crate) fn maker_fee(&self, exchange: &str) -> f64 {
+        self.exchanges
+            .get(exchange)
+            .map(|c| c.settings.maker_fee)
+            .unwrap_or_else(|| config::ExchangeConfig::default().maker_fee)
+    }
+    
+    pub(crate) fn taker_fee(&self, exchange: &str) -> f64 {
+        self.exchanges
+            .get(exchange)
+            .map(|c| *c.taker_fee.read().unwrap())
+            .unwrap_or_else(|| config::ExchangeConfig::default().taker_fee)
+    }
+    
+    // 按交易所配置的來源計算下一期預測費率
+    pub(crate) async fn get_predicted_funding_rate(&self, exchange: &str, symbol: &str) -> Result<f64, String> {
+        let connector = self
+            .exchanges
+            .get(exchange)
+            .ok_or_else(|| format!("不支持的交易所: {}", exchange))?;
+        if !self.margin.has_perp(exchange, symbol) {
+            return Err(format!("{} 無 {} 永續合約", exchange, symbol));
+        }
+        let settings = &connector.settings;
+        let rate = match settings.funding_model.source {
+            config::PredictionSource::Exchange => {
+                let rate = connector.fetch_predicted_funding_rate(symbol).await?;
+                let now_ms = self.env.now_ms();
+                self.funding_model.published(exchange, symbol, rate, now_ms)
+            }
+            config::PredictionSource::PremiumIndex => {
+                let index = connector.fetch_premium_index(symbol).await?;
+                self.funding_model.observe(
+                    exchange,
+                    symbol,
+                    settings.funding_interval_hours,
+                    &index,
+                    &settings.funding_model,
+                )
+            }
+        };
+        self.quotes.record_predicted_rate(exchange, symbol, rate, self.env.now_ms());
+        Ok(rate)
+    }
+    
+    // 執行使用的預測費率：緩存未過期時直接使用，缺失或過期時按 quotes.on_stale 經 REST 刷新或拒絕執行
+    pub(crate) async fn fresh_predicted_rate(&self, exchange: &str, symbol: &str) -> Result<f64, String> {
+        match self.quotes.predicted_rate(exchange, symbol, self.env.now_ms()) {
+            Ok(rate) => Ok(rate),
+            Err(reason) if self.quotes.policy() == config::StalePolicy::Refresh => {
+                debug!(%exchange, %symbol, %reason, "行情過期，經 REST 刷新");
+                self.get_predicted_funding_rate(exchange, symbol).await
+            }
+            Err(reason) => {
+                warn!(%exchange, %symbol, %reason, "行情過期，拒絕執行");
+                Err(format!("行情過期: {}", reason))
+            }
+        }
+    }
+    
+    // 在階段預算與請求截止時間中較早者之前完成，否則放棄該階段
+    pub(crate) async fn within_stage<T>(
+        &self,
+        stage: &str,
+        budget_ms: u64,
+        deadline_ms: Option<i64>,
+        future: impl std::future::Future<Output = Result<T, String>>,
+    ) -> Result<T, String> {
+        let mut budget = Duration::from_millis(budget_ms);
+        if let Some(deadline) = deadline_ms {
+            let remaining = deadline - self.env.now_ms();
+            if remaining <= 0 {
+                return Err(format!("已超過執行截止時間，放棄 {} 階段", stage));
+            }
+            budget = budget.min(Duration::from_millis(remaining as u64));
+        }
+        tokio::time::timeout(budget, future).await.map_err(|_| {
+            warn!(stage, budget_ms = budget.as_millis() as u64, "執行階段超時");
+            format!("{} 階段超時（{}ms）", stage, budget.as_millis())
+        })?
+    }
+    
+    // 本地腿已成交但整體未能確認時，按成交量反向下單平倉；返回原腿與平倉腿供記帳
+    pub(crate) fn unwind_local_legs(
+        &self,
+        execution_id: &str,
+        request: &ArbitrageRequest,
+        orders: &[ChildOrder],
+        leverage: LeverageSetting,
+    ) -> Vec<ChildOrder> {
+        let mut recorded = Vec::new();
+        let local = orders
+            .iter()
+            .filter(|order| order.filled_quantity > 0.0 && !self.routing.is_remote(&order.exchange));
+        for order in local {
+            let Some(connector) = self.exchanges.get(&order.exchange) else { continue };
+            let perp = perp_leg(request, &order.leg);
+            let unwind = ChildOrder {
+                leg: format!("unwind_{}", order.leg),
+                exchange: order.exchange.clone(),
+                symbol: order.symbol.clone(),
+                side: if order.side == "buy" { "sell" } else { "buy" }.to_string(),
+                quantity: order.filled_quantity,
+                filled_quantity: order.filled_quantity,
+                fee: order.filled_quantity * self.taker_fee(&order.exchange),
+                status: "filled".to_string(),
+            };
+            connector.simulate_fill(execution_id, order, perp.then_some(leverage.leverage));
+            connector.simulate_fill(execution_id, &unwind, perp.then_some(leverage.leverage));
+            // 現貨槓桿空頭買回後歸還借幣
+            if order.leg == "margin_short" {
+                let (base, _) = market_data::split_symbol(&order.symbol).unwrap_or_default();
+                if let Ok(borrowed) = self.margin.borrow_quantity(self.env.rng.as_ref(), &order.exchange, &order.symbol, order.filled_quantity) {
+                    self.margin.release(&order.exchange, &base, borrowed);
+                }
+            }
+            warn!(exchange = %order.exchange, leg = %order.leg, quantity = order.filled_quantity, "已反向平掉未能確認的執行中本地成交的腿");
+            recorded.push(order.clone());
+            recorded.push(unwind);
+        }
+        recorded
+    }
+    
+    // 超時放棄下單時已送出的訂單不會再有結果返回：按執行日誌反向平掉已確認成交的腿，
+    // 未收到確認的腿無法判斷是否成交，告警人工核對
+    fn unwind_journaled_legs(&self, execution_id: &str, request: &ArbitrageRequest, error: String) -> ExecutionFailure {
+        let Some(execution) = self.journal.execution(execution_id) else { return error.into() };
+        let (acked, pending): (Vec<_>, Vec<_>) = execution.legs.into_iter().partition(|leg| leg.filled_quantity.is_some());
+        let filled: Vec<ChildOrder> = acked
+            .iter()
+            .map(|leg| leg.order(leg.filled_quantity.unwrap_or_default(), self.taker_fee(&leg.exchange)))
+            .collect();
+        let orders = self.unwind_local_legs(execution_id, request, &filled, self.leverage_setting(request));
+        if !orders.is_empty() || !pending.is_empty() {
+            let pending: Vec<String> = pending.iter().map(|leg| format!("{}@{}", leg.client_order_id, leg.exchange)).collect();
+            let message = format!(
+                "{} {}：{}，已反向平掉 {} 條腿，未確認的訂單 [{}] 需人工核對",
+                execution_id, request.symbol, error, orders.len() / 2, pending.join(", ")
+            );
+            self.alert(Severity::Critical, "下單超時，已反向平倉", message);
+        }
+        ExecutionFailure { error, orders }
+    }
+    
+    pub(crate) async fn execute_flash_loan_arbitrage(
+        &self,
+        execution_id: &str,
+        request: &ArbitrageRequest,
+        rate_diff: f64,
+        gas_quote: &gas::GasQuote,
+        margin_short: bool,
+        // 分片執行時各片不單獨計時
+        timer: Option<&latency::StageTimer>,
+    ) -> Result<ExecutionOutcome, ExecutionFailure> {
+        let chain = self.chain(request.chain.as_deref())?;
+        let mark = |stage: &str| {
+            if let Some(timer) = timer {
+                timer.mark(stage);
+            }
+        };
+        debug!(chain = %chain.name, onchain = chain.flash_loan.is_some(), "執行閃電貸套利");
+        
+        // 計算預期利潤
+        let expected_profit = request.amount * rate_diff.abs();
+        
+        // 當日 gas 預算用盡時不再上鏈，改走純交易所腿
+        let strategy = request.strategy_type.name();
+        let onchain = chain.flash_loan.as_ref().filter(|_| self.gas_budget.allows(strategy));
+        if chain.flash_loan.is_some() && onchain.is_none() {
+            debug!(strategy, "gas 預算已用盡，改為純交易所執行");
+        }
+        if let Some(flash_loan) = onchain {
+            // 按最便宜來源的手續費與實時 gas 估算檢查淨收益，估算在發送前由 flash_loan 取得
+            let premium = request.amount * flash_loan.min_fee_bps() / 10_000.0;
+            let profitable = self.profit_gate(chain, &request.symbol, expected_profit, premium, gas_quote);
+            let receipt = flash_loan
+                .execute(request.amount, &request.symbol, expected_profit, &chain.gas_optimizer, gas_quote, &profitable)
+                .await;
+            mark("flash_loan");
+            self.crowding.record_attempt(&request.symbol, receipt.is_err());
+            let receipt = receipt.map_err(|error| self.record_burned_gas(execution_id, request, chain, error))?;
+            info!(provider = receipt.provider, tx_hash = %receipt.tx_hash, premium = receipt.premium, "閃電貸已結算");
+            let expected_slippage_bps = self.sizing.expected_slippage_bps(request.amount);
+            return Ok(ExecutionOutcome {
+                profit: receipt.profit,
+                fees: receipt.premium,
+                orders: vec![ChildOrder {
+                    leg: "flash_loan".to_string(),
+                    exchange: receipt.provider.to_string(),
+                    symbol: request.symbol.clone(),
+                    side: "borrow".to_string(),
+                    quantity: request.amount,
+                    filled_quantity: request.amount,
+                    fee: receipt.premium,
+                    status: "filled".to_string(),
+                }],
+                // 鏈上原子執行，沒有盤口滑點可觀測
+                expected_slippage_bps,
+                realized_slippage_bps: expected_slippage_bps,
+                gas_used: Some(receipt.gas_used),
+                gas_cost_eth: Some(chain.to_eth(receipt.gas_cost)),
+                settlement_proof: Some(receipt.proof),
+                unwound: Vec::new(),
+            });
+        }
+        
+        // 未配置鏈上執行時模擬閃電貸套利
+        self.acquire_orders(&[&request.primary_exchange, &request.secondary_exchange]).await?;
+        // 費率較高的一方做空收取資金費，另一方做多對沖；任一腿被拒視為排隊被搶先成交
+        let (short_exchange, long_exchange) = if rate_diff > 0.0 {
+            (&request.primary_exchange, &request.secondary_exchange)
+        } else {
+            (&request.secondary_exchange, &request.primary_exchange)
+        };
+        let short_leg = if margin_short { "margin_short" } else { "short" };
+        let short = self.submit_leg_with_progress(execution_id, request, "leg1", short_leg, short_exchange, "sell").await;
+        mark("leg1_ack");
+        let long = self.submit_leg_with_progress(execution_id, request, "leg2", "long", long_exchange, "buy").await;
+        mark("leg2_ack");
+        let (short, long) = match (short, long) {
+            (Ok(short), Ok(long)) => (short, long),
+            (short, long) => {
+                self.crowding.record_attempt(&request.symbol, true);
+                // 另一腿已成交時按成交量反向平倉，原腿與平倉單一併帶回記賬
+                let filled: Vec<ChildOrder> = [(short_leg, short_exchange, "sell", &short), ("long", long_exchange, "buy", &long)]
+                    .into_iter()
+                    .filter_map(|(leg, exchange, side, fill)| {
+                        let filled = request.amount * fill.as_ref().ok()?.ratio;
+                        Some(ChildOrder {
+                            leg: leg.to_string(),
+                            exchange: exchange.to_string(),
+                            symbol: request.symbol.clone(),
+                            side: side.to_string(),
+                            quantity: request.amount,
+                            filled_quantity: filled,
+                            fee: filled * self.taker_fee(exchange),
+                            status: if filled < request.amount { "partially_filled" } else { "filled" }.to_string(),
+                        })
+                    })
+                    .collect();
+                let orders = self.unwind_local_legs(execution_id, request, &filled, self.leverage_setting(request));
+                let error = [short, long].into_iter().find_map(Result::err).unwrap_or_default();
+                return Err(ExecutionFailure {
+                    error: format!("套利執行失敗: {}", error),
+                    orders,
+                });
+            }
+        };
+        self.crowding.record_attempt(&request.symbol, false);
+        
+        // 對沖只覆蓋兩腿都成交的部分：各腿按實際成交記錄，成交較多的一腿反向平掉超出部分
+        let ratio = short.ratio.min(long.ratio);
+        let filled = request.amount * ratio;
+        // 掛單優先時按各腿 maker 與吃單成交的比例和交易所費率計費
+        let leg_fee = |exchange: &str, fill: &LegFill| match &request.maker_first {
+            Some(_) => {
+                let maker_share = if fill.ratio > 0.0 { (fill.maker_ratio / fill.ratio).min(1.0) } else { 0.0 };
+                request.amount * fill.ratio * (maker_share * self.maker_fee(exchange) + (1.0 - maker_share) * self.taker_fee(exchange))
+            }
+            None => expected_profit * fill.ratio * 0.05 / 2.0,
+        };
+        let leg = |leg: &str, exchange: &str, side: &str, fill: &LegFill| ChildOrder {
+            leg: leg.to_string(),
+            exchange: exchange.to_string(),
+            symbol: request.symbol.clone(),
+            side: side.to_string(),
+            quantity: request.amount,
+            filled_quantity: request.amount * fill.ratio,
+            fee: leg_fee(exchange, fill),
+            status: if fill.ratio < 1.0 { "partially_filled" } else { "filled" }.to_string(),
+        };
+        let mut orders = vec![leg(short_leg, short_exchange, "sell", &short), leg("long", long_exchange, "buy", &long)];
+        let excess: Vec<ChildOrder> = orders
+            .iter()
+            .filter(|order| order.filled_quantity > filled)
+            .map(|order| {
+                let quantity = order.filled_quantity - filled;
+                warn!(exchange = %order.exchange, leg = %order.leg, quantity, "兩腿成交不一致，反向平掉超出對沖的部分");
+                ChildOrder {
+                    leg: format!("unwind_{}", order.leg),
+                    exchange: order.exchange.clone(),
+                    symbol: order.symbol.clone(),
+                    side: if order.side == "buy" { "sell" } else { "buy" }.to_string(),
+                    quantity,
+                    filled_quantity: quantity,
+                    fee: quantity * self.taker_fee(&order.exchange),
+                    status: "filled".to_string(),
+                }
+            })
+            .collect();
+        orders.extend(excess);
+        let fees = orders.iter().map(|order| order.fee).sum();
+        
+        // 模擬成交滑點：在模型預期值的 0.5 ~ 1.5 倍之間
+        let expected_slippage_bps = self.sizing.expected_slippage_bps(filled);
+        let realized_slippage_bps = expected_slippage_bps * (0.5 + self.env.rng.next_f64());
+        
+        Ok(ExecutionOutcome {
+            profit: expected_profit * ratio - fees, // 兩腿成交一致時為成交部分預期利潤的 95%
+            fees,
+            orders,
+            expected_slippage_bps,
+            realized_slippage_bps,
+            gas_used: None,
+            gas_cost_eth: None,
+            settlement_proof: None,
+            unwound: Vec::new(),
+        })
+    }
+}
+
+impl ExecutionEngine {
+    // 下單前後推送 {stage}_submitted / {stage}_filled 進度
+    async fn submit_leg_with_progress(
+        &self,
+        execution_id: &str,
+        request: &ArbitrageRequest,
+        stage: &str,
+        leg: &str,
+        exchange: &str,
+        side: &str,
+    ) -> Result<LegFill, String> {
+        let progress = |suffix: &str| protocol::ExecutionProgress {
+            exchange: Some(exchange.to_string()),
+            ..protocol::ExecutionProgress::stage(execution_id, &format!("{}_{}", stage, suffix), self.env.now_ms())
+        };
+        self.report_progress(request, progress("submitted"));
+        let fill = self.submit_leg(execution_id, request, leg, exchange, side).await?;
+        self.report_progress(
+            request,
+            protocol::ExecutionProgress {
+                filled_quantity: Some(request.amount * fill.ratio),
+                ..progress("filled")
+            },
+        );
+        Ok(fill)
+    }
+    
+    // 請求未要求進度推送或連接已斷開時忽略
+    fn report_progress(&self, request: &ArbitrageRequest, progress: protocol::ExecutionProgress) {
+        if let Some(sink) = &request.progress {
+            let _ = sink.send(progress);
+        }
+    }
+    
+    // 掛單優先的腿先掛 post-only 單，有效期內未成交或被拒（會吃單）的部分改為吃單；
+    // 回退吃單失敗時保留已以 maker 成交的部分
+    async fn submit_leg(
+        &self,
+        execution_id: &str,
+        request: &ArbitrageRequest,
+        leg: &str,
+        exchange: &str,
+        side: &str,
+    ) -> Result<LegFill, String> {
+        let Some(maker) = request.maker_first.as_ref().filter(|maker| maker.applies(leg)) else {
+            let ratio = self.submit_journaled(execution_id, request, leg, exchange, &request.symbol, side, request.amount, None).await?;
+            return Ok(LegFill { ratio, maker_ratio: 0.0 });
+        };
+        let (base, quote) = market_data::split_symbol(&request.symbol)
+            .ok_or_else(|| format!("無法解析交易對: {}", request.symbol))?;
+        let book = market_data::simulated_perp_book(self.env.rng.as_ref(), exchange, &base, &quote)?;
+        let order = maker.order(self.maker.config(), &book, side)?;
+        let maker_ratio = match self.submit_journaled(execution_id, request, leg, exchange, &request.symbol, side, request.amount, Some(order)).await {
+            Ok(ratio) => ratio,
+            Err(error) => {
+                warn!(%exchange, %leg, %error, "post-only 掛單失敗，改為吃單");
+                0.0
+            }
+        };
+        let remaining = 1.0 - maker_ratio;
+        let taker_ratio = if remaining > 0.0 {
+            match self.submit_journaled(execution_id, request, leg, exchange, &request.symbol, side, request.amount * remaining, None).await {
+                Ok(ratio) => ratio * remaining,
+                Err(error) if maker_ratio > 0.0 => {
+                    warn!(%exchange, %leg, %error, maker_ratio, "回退吃單失敗，只保留 maker 成交部分");
+                    0.0
+                }
+                Err(error) => return Err(error),
+            }
+        } else {
+            0.0
+        };
+        debug!(%exchange, %leg, maker_ratio, taker_ratio, "掛單優先成交");
+        self.maker.record(exchange, request.amount * maker_ratio, request.amount * taker_ratio);
+        Ok(LegFill {
+            ratio: maker_ratio + taker_ratio,
+            maker_ratio,
+        })
+    }
+    
+    // 先把腿寫入執行預寫日誌再下單，交易所確認後記下成交數量；返回相對 quantity 的成交比例，
+    // post_only 非空時掛 post-only 限價單
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) async fn submit_journaled(
+        &self,
+        execution_id: &str,
+        request: &ArbitrageRequest,
+        leg: &str,
+        exchange: &str,
+        symbol: &str,
+        side: &str,
+        quantity: f64,
+        post_only: Option<maker::PostOnlyOrder>,
+    ) -> Result<f64, String> {
+        let client_order_id = self.order_ids.assign(&request.strategy_id, execution_id, leg);
+        self.journal.leg_submitted(
+            execution_id,
+            journal::JournalLeg {
+                leg: leg.to_string(),
+                exchange: exchange.to_string(),
+                symbol: symbol.to_string(),
+                side: side.to_string(),
+                quantity,
+                client_order_id: client_order_id.clone(),
+                filled_quantity: None,
+            },
+        );
+        let connector = &self.exchanges[exchange];
+        let ratio = match post_only {
+            Some(order) => connector.submit_post_only(symbol, side, quantity, order, &client_order_id).await?,
+            None => {
+                // 只統計吃單確認延遲：post-only 單的耗時包含掛單等待
+                let started_us = self.env.clock.now_us();
+                let ratio = connector.submit_order(symbol, side, quantity, &client_order_id).await?;
+                self.ack_latency.record(exchange, self.env.clock.now_us() - started_us);
+                ratio
+            }
+        };
+        self.journal.leg_acked(execution_id, &client_order_id, quantity * ratio);
+        Ok(ratio)
+    }
+    
+    // 將執行結果寫入事件日誌：每條腿的下單與成交，以及最終結算
+    pub(crate) fn record_execution_events(
+        &self,
+        execution_id: &str,
+        request: &ArbitrageRequest,
+        response: &ArbitrageResponse,
+        fees: f64,
+        orders: &[ChildOrder],
+    ) {
+        for order in orders {
+            self.events.append(events::EngineEvent::OrderPlaced {
+                execution_id: execution_id.to_string(),
+                strategy_id: request.strategy_id.clone(),
+                leg: order.leg.clone(),
+                exchange: order.exchange.clone(),
+                symbol: order.symbol.clone(),
+                side: order.side.clone(),
+                quantity: order.quantity,
+            });
+            self.events.append(events::EngineEvent::OrderFilled {
+                execution_id: execution_id.to_string(),
+                strategy_id: request.strategy_id.clone(),
+                leg: order.leg.clone(),
+                exchange: order.exchange.clone(),
+                symbol: order.symbol.clone(),
+                side: order.side.clone(),
+                filled_quantity: order.filled_quantity,
+                fee: order.fee,
+            });
+        }
+        self.events.append(events::EngineEvent::ExecutionSettled {
+            execution_id: execution_id.to_string(),
+            strategy_id: request.strategy_id.clone(),
+            symbol: request.symbol.clone(),
+            status: response.status.clone(),
+            pnl: response.profit.unwrap_or(0.0),
+            fees,
+        });
+    }
+    
+    pub(crate) fn alert(&self, severity: Severity, title: &str, message: String) {
+        self.alerts.notify(severity, title, message, self.env.now_ms());
+    }
+
+    // 計入策略資金預算與當日限額，新觸發的交易暫停寫入事件日誌以便重啟後恢復
+    pub(crate) fn record_realized_pnl(&self, strategy_id: &str, pnl: f64) {
+        for halt in self.risk.record_pnl(strategy_id, pnl, self.env.now_ms()) {
+            error!(reason = %halt.reason, scope = ?halt.strategy_id, "觸發當日限額，暫停交易至 UTC 日終");
+            let scope = halt.strategy_id.as_deref().unwrap_or("全部策略");
+            self.alert(Severity::Critical, "觸發當日限額，交易暫停", format!("{}：{}", scope, halt.reason));
+            self.events.append(events::EngineEvent::TradingHalted {
+                strategy_id: halt.strategy_id,
+                reason: halt.reason,
+            });
+        }
+    }
+
+    // 鏈上路徑唯一的淨收益門檻：預期收益與手續費（symbol 計價資產）扣除 gas 費（USDT）後低於 gas.min_net_profit_usdt
+    // 時拒絕執行；gas 費一律按基礎費加小費計，返回折算為計價資產的 gas 費
+    pub(crate) fn check_profit_after_gas(&self, chain: &chain::ChainStack, symbol: &str, profit: f64, fees: f64, gas_usdt: f64) -> Result<f64, String> {
+        let usdt_per_quote = market_data::split_symbol(symbol)
+            .and_then(|(_, quote)| Some(market_data::reference_price(&quote)? / market_data::reference_price("USDT")?))
+            .ok_or_else(|| format!("無法把 {} 的收益折算為 USDT", symbol))?;
+        let net_usdt = (profit - fees) * usdt_per_quote - gas_usdt;
+        let minimum = chain.gas_optimizer.min_net_profit_usdt();
+        debug!(chain = %chain.name, %symbol, profit, fees, gas_usdt, net_usdt, "鏈上路徑淨收益評估");
+        if net_usdt < minimum {
+            return Err(format!(
+                "扣除 gas 約 {:.4} USDT 與手續費後淨收益 {:.4} USDT 低於最低要求 {} USDT",
+                gas_usdt, net_usdt, minimum
+            ));
+        }
+        Ok(gas_usdt / usdt_per_quote)
+    }
+
+    // 按發送前實時估算的 gas 單位套用 check_profit_after_gas，交給閃電貸在估算後、模擬後調用
+    pub(crate) fn profit_gate<'a>(
+        &'a self,
+        chain: &'a chain::ChainStack,
+        symbol: &'a str,
+        profit: f64,
+        fees: f64,
+        quote: &'a gas::GasQuote,
+    ) -> impl Fn(u64) -> Result<(), String> + Send + Sync + 'a {
+        move |gas| self.check_profit_after_gas(chain, symbol, profit, fees, chain.gas_usdt(gas, quote)?).map(drop)
+    }
+
+    // 已上鏈但回滾的交易照常扣除 gas：計入當日 gas 預算與事件日誌，並按參考價折算為請求計價資產的已實現虧損；
+    // 返回錯誤信息供調用方繼續傳遞
+    pub(crate) fn record_burned_gas(&self, execution_id: &str, request: &ArbitrageRequest, chain: &chain::ChainStack, error: tx_simulation::OnchainError) -> String {
+        let Some(revert) = &error.reverted else {
+            return error.message;
+        };
+        let strategy = request.strategy_type.name();
+        let cost_eth = chain.to_eth(revert.gas_cost);
+        self.gas_budget.record(strategy, cost_eth);
+        self.events.append(events::EngineEvent::GasSpent {
+            execution_id: execution_id.to_string(),
+            strategy: strategy.to_string(),
+            gas_used: revert.gas_used,
+            cost_eth,
+        });
+        let loss = market_data::split_symbol(&request.symbol)
+            .and_then(|(_, quote)| chain.native_price(&quote))
+            .map(|price| revert.gas_cost * price);
+        match loss {
+            Some(loss) => self.record_realized_pnl(&request.strategy_id, -loss),
+            None => warn!(symbol = %request.symbol, "無法折算回滾交易的 gas 費，僅計入 gas 預算"),
+        }
+        warn!(
+            chain = %chain.name,
+            tx_hash = %revert.tx_hash,
+            kind = revert.kind.label(),
+            reason = %revert.reason,
+            gas_used = revert.gas_used,
+            cost_eth,
+            "鏈上交易回滾"
+        );
+        error.message
+    }
+
+    // 按執行 ID 排序的執行中請求快照
+    pub(crate) fn open_execution_snapshot(&self) -> BTreeMap<String, OpenExecution> {
+        self.open_executions
+            .iter()
+            .map(|entry| (entry.key().clone(), entry.value().clone()))
+            .collect()
+    }
+    
+    /// 停止接收新執行，限時等待執行中的請求結束，再落盤記帳與事件並關閉交易所會話。
+    ///
+    /// 超時仍未結束的執行不在此平倉（其任務可能仍在下單），執行日誌中的條目原樣保留：
+    /// 下次啟動時按 client_order_id 查詢成交，兩側一致則恢復對沖，否則反向平掉已成交的腿。
+    pub async fn shutdown(&self, drain_timeout: Duration) {
+        self.shutting_down.store(true, Ordering::Relaxed);
+        let deadline = tokio::time::Instant::now() + drain_timeout;
+        loop {
+            let open = self.open_executions.len();
+            if open == 0 {
+                break;
+            }
+            if tokio::time::Instant::now() >= deadline {
+                // 超時仍未結束的執行可能留有單腿持倉；執行日誌保留其條目，下次啟動時恢復或平倉
+                for (execution_id, execution) in &self.open_execution_snapshot() {
+                    error!(%execution_id, symbol = %execution.symbol, primary_exchange = %execution.primary_exchange,
+                        secondary_exchange = %execution.secondary_exchange, amount = execution.amount,
+                        "執行未在關閉時限內完成，保留在執行日誌中待下次啟動恢復");
+                    let message = format!(
+                        "{} {} {}/{} 金額 {}，下次啟動時按執行日誌恢復或平倉",
+                        execution_id, execution.symbol, execution.primary_exchange, execution.secondary_exchange, execution.amount
+                    );
+                    self.alert(Severity::Critical, "執行未在關閉時限內完成", message);
+                }
+                break;
+            }
+            info!(open, "等待執行中的請求完成");
+            tokio::time::sleep(Duration::from_millis(200)).await;
+        }
+        self.bookkeeper.flush().await;
+        if let Err(e) = self.events.sync() {
+            error!(error = %e, "事件日誌落盤失敗");
+        }
+        if let Some(history) = &self.history {
+            history.close().await;
+        }
+        session::close(self).await;
+        info!("引擎已關閉");
+    }
+    
+    /// 執行一條控制指令（查詢狀態、調整運行參數等）。連接級指令 `authenticate` 與 `set_encoding` 在此返回錯誤。
+    pub async fn handle_command(&self, command: EngineCommand) -> CommandResponse {
+        match command {
+            EngineCommand::SetLogLevel { filter } => {
+                let result = match &self.log_handle {
+                    Some(handle) => EnvFilter::try_new(&filter)
+                        .map_err(|e| format!("無效的日誌過濾器: {}", e))
+                        .and_then(|f| handle.reload(f).map_err(|e| e.to_string())),
+                    None => Err("日誌級別不可調整".to_string()),
+                };
+                match result {
+                    Ok(()) => {
+                        info!(%filter, "日誌級別已更新");
+                        self.events.append(events::EngineEvent::ConfigChanged {
+                            key: "log_filter".to_string(),
+                            value: serde_json::json!(filter),
+                        });
+                        CommandResponse::ok(None)
+                    }
+                    Err(e) => CommandResponse::error(e),
+                }
+            }
+            EngineCommand::GetHistory(filter) => {
+                let Some(history) = &self.history else {
+                    return CommandResponse::error("未啟用歷史存儲");
+                };
+                match history.query(&filter).await {
+                    Ok(entries) => CommandResponse::ok(Some(serde_json::json!(entries))),
+                    Err(e) => CommandResponse::error(format!("查詢歷史失敗: {}", e)),
+                }
+            }
+            EngineCommand::GetSpreadStats { symbol } => {
+                let stats = self.funding_history.spread_stats(symbol.as_deref());
+                CommandResponse::ok(Some(serde_json::json!(stats)))
+            }
+            EngineCommand::GetFundingCalendar => {
+                let now_ms = self.env.now_ms();
+                let mut venues: Vec<(&str, u64, i64)> = self
+                    .exchanges
+                    .values()
+                    .map(|connector| {
+                        (connector.name.as_str(), connector.settings.funding_interval_hours, connector.clock.now_ms())
+                    })
+                    .collect();
+                venues.sort();
+                let entries = self
+                    .funding_history
+                    .calendar(&venues, |exchange, symbol| self.margin.has_perp(exchange, symbol));
+                CommandResponse::ok(Some(serde_json::json!({"generated_at_ms": now_ms, "entries": entries})))
+            }
+            EngineCommand::GetSizingLimits => {
+                CommandResponse::ok(Some(serde_json::json!(self.sizing.snapshot())))
+            }
+            EngineCommand::GetPositions { at_ms, at_sequence } => {
+                let projection = if at_ms.is_none() && at_sequence.is_none() {
+                    self.events.current()
+                } else {
+                    self.events.rebuild_until(at_ms, at_sequence)
+                };
+                CommandResponse::ok(Some(serde_json::json!(projection)))
+            }
+            EngineCommand::GetOpportunities => {
+                CommandResponse::ok(Some(serde_json::json!(self.scanner.latest())))
+            }
+            EngineCommand::SubscribeOpportunities => CommandResponse::error("訂閱僅支持 TCP 長連接"),
+            EngineCommand::GetCrowding => CommandResponse::ok(Some(serde_json::json!(self.crowding.snapshot()))),
+            EngineCommand::GetBasis => CommandResponse::ok(Some(self.basis.snapshot())),
+            EngineCommand::GetAccounts { exchange } => match exchange {
+                Some(exchange) => match self.user_streams.account(&exchange) {
+                    Some(account) => CommandResponse::ok(Some(serde_json::json!(account))),
+                    None => CommandResponse::error(format!("{} 沒有用戶數據", exchange)),
+                },
+                None => CommandResponse::ok(Some(serde_json::json!(self.user_streams.snapshot()))),
+            },
+            // 連接級狀態，由 handle_connection 處理
+            EngineCommand::Authenticate(_) => CommandResponse::error("認證僅在客戶端連接上有效"),
+            EngineCommand::SetEncoding { .. } => CommandResponse::error("線路編碼僅在客戶端連接上有效"),
+            EngineCommand::Ping => CommandResponse::ok(Some(serde_json::json!({ "at_ms": self.env.now_ms() }))),
+            EngineCommand::Pong { .. } => CommandResponse::error("心跳回應僅在客戶端連接上有效"),
+            EngineCommand::GetConfig => CommandResponse::ok(Some(serde_json::json!({
+                "loaded": self.config,
+                "applied": *self.applied_config.lock().unwrap(),
+                "runtime": self.events.current().config,
+            }))),
+            EngineCommand::ReloadConfig => match config::EngineConfig::path() {
+                Some(path) => match config::EngineConfig::load_from(&path).and_then(|new| config_reload::apply(self, new)) {
+                    Ok(changed) => CommandResponse::ok(Some(serde_json::json!({ "path": path, "changed": changed }))),
+                    Err(e) => CommandResponse::error(format!("配置熱加載被拒絕: {}", e)),
+                },
+                None => CommandResponse::error("未找到配置文件，請設置 ARB_ENGINE_CONFIG"),
+            },
+            EngineCommand::GetOpenExecutions => {
+                CommandResponse::ok(Some(serde_json::json!(self.open_execution_snapshot())))
+            }
+            EngineCommand::GetExecutionQueue => CommandResponse::ok(Some(serde_json::json!(self.queue.snapshot()))),
+            EngineCommand::GetLatency => CommandResponse::ok(Some(serde_json::json!(self.latency.snapshot()))),
+            EngineCommand::GetMakerStats => CommandResponse::ok(Some(serde_json::json!(self.maker.snapshot()))),
+            EngineCommand::GetQuotes => CommandResponse::ok(Some(serde_json::json!(self.quotes.snapshot(self.env.now_ms())))),
+            EngineCommand::GetVenueLatency => CommandResponse::ok(Some(serde_json::json!(self.ack_latency.snapshot()))),
+            EngineCommand::GetGateways => {
+                let gateways: BTreeMap<&str, gateways::GatewaySnapshot> = self
+                    .exchanges
+                    .iter()
+                    .map(|(exchange, connector)| (exchange.as_str(), connector.gateways.snapshot()))
+                    .collect();
+                CommandResponse::ok(Some(serde_json::json!(gateways)))
+            }
+            EngineCommand::GetClockSync => {
+                let clocks: BTreeMap<&str, time_sync::ClockStatus> = self
+                    .exchanges
+                    .iter()
+                    .map(|(name, connector)| (name.as_str(), connector.clock.status()))
+                    .collect();
+                CommandResponse::ok(Some(serde_json::json!(clocks)))
+            }
+            EngineCommand::SetKillSwitch { engaged } => {
+                self.kill_switch.store(engaged, Ordering::Relaxed);
+                warn!(engaged, "緊急停止狀態已更新");
+                if engaged {
+                    self.alert(Severity::Critical, "緊急停止已啟用", "拒絕所有新執行".to_string());
+                } else {
+                    self.alert(Severity::Warning, "緊急停止已解除", "恢復接收新執行".to_string());
+                }
+                self.events.append(events::EngineEvent::ConfigChanged {
+                    key: "kill_switch".to_string(),
+                    value: serde_json::json!(engaged),
+                });
+                CommandResponse::ok(None)
+            }
+            EngineCommand::GetPredictedFunding => CommandResponse::ok(Some(serde_json::json!(self.funding_model.snapshot()))),
+            EngineCommand::GetFundingPairs => CommandResponse::ok(Some(serde_json::json!(self.funding_pairs.snapshot()))),
+            EngineCommand::GetLiquidationRisk => CommandResponse::ok(Some(serde_json::json!(self.liquidation.snapshot()))),
+            EngineCommand::GetMarginAvailability => {
+                CommandResponse::ok(Some(serde_json::json!(self.balances.snapshot(&self.user_streams))))
+            }
+            EngineCommand::GetSessions => CommandResponse::ok(Some(serde_json::json!(self.sessions.snapshot()))),
+            EngineCommand::GetRateLimits => {
+                let limits: BTreeMap<&str, rate_limit::SchedulerSnapshot> = self
+                    .exchanges
+                    .values()
+                    .map(|connector| (connector.name.as_str(), connector.scheduler.snapshot()))
+                    .collect();
+                CommandResponse::ok(Some(serde_json::json!(limits)))
+            }
+            EngineCommand::GetInventory => {
+                CommandResponse::ok(Some(serde_json::json!(self.spot_arbitrage.inventory())))
+            }
+            EngineCommand::GetMargin => CommandResponse::ok(Some(serde_json::json!(self.margin.snapshot()))),
+            EngineCommand::GetSettlementProof { execution_id } => match self.events.settlement_proof(&execution_id) {
+                Some(proof) => CommandResponse::ok(Some(serde_json::json!(proof))),
+                None => CommandResponse::error(format!("執行 {} 沒有鏈上結算證明", execution_id)),
+            },
+            EngineCommand::GetGasBudget => CommandResponse::ok(Some(serde_json::json!(self.gas_budget.snapshot()))),
+            EngineCommand::GetCapital => CommandResponse::ok(Some(serde_json::json!(self.risk.allocations(self.env.now_ms())))),
+            EngineCommand::GetEventBus => CommandResponse::ok(Some(serde_json::json!(self.event_bus.snapshot(self.events.last_sequence())))),
+            EngineCommand::GetJournal => CommandResponse::ok(Some(serde_json::json!({
+                "in_flight": self.journal.in_flight(),
+                "recoveries": self.journal.recoveries(),
+            }))),
+            EngineCommand::GetSymbol { symbol } => match self.symbols.resolve(&symbol) {
+                Ok(mapping) => CommandResponse::ok(Some(serde_json::json!(mapping))),
+                Err(e) => CommandResponse::error(e),
+            },
+            EngineCommand::GetReconciliation => CommandResponse::ok(Some(serde_json::json!(self.reconciler.last_report()))),
+            EngineCommand::Reconcile { auto_correct } => {
+                let auto_correct = auto_correct.unwrap_or(self.reconciler.config().auto_correct);
+                CommandResponse::ok(Some(serde_json::json!(reconciliation::run(self, auto_correct).await)))
+            }
+            EngineCommand::GetPriceCheck { symbol } => match self.symbols.canonical(&symbol).and_then(|symbol| self.price_guard.check(self, &symbol)) {
+                Ok(check) => CommandResponse::ok(Some(serde_json::json!(check))),
+                Err(e) => CommandResponse::error(e),
+            },
+            EngineCommand::GetApprovals { chain } => match self.chain(chain.as_deref()).map(|chain| &chain.dex) {
+                Ok(Some(dex)) => CommandResponse::ok(Some(serde_json::json!(dex.approvals().snapshot()))),
+                Ok(None) => CommandResponse::error("未配置 DEX 執行"),
+                Err(e) => CommandResponse::error(e),
+            },
+            EngineCommand::GetRpcProviders { chain } => match self.chain(chain.as_deref()).map(|chain| &chain.rpc) {
+                Ok(Some(rpc)) => CommandResponse::ok(Some(serde_json::json!(rpc.status()))),
+                Ok(None) => CommandResponse::error("未配置鏈上執行"),
+                Err(e) => CommandResponse::error(e),
+            },
+            EngineCommand::GetBlockSubscription { chain } => match self.chain(chain.as_deref()).map(|chain| &chain.wallets) {
+                Ok(Some(wallets)) => CommandResponse::ok(Some(serde_json::json!(wallets.watcher().status()))),
+                Ok(None) => CommandResponse::error("未配置鏈上執行"),
+                Err(e) => CommandResponse::error(e),
+            },
+            EngineCommand::GetOraclePrices => CommandResponse::ok(Some(serde_json::json!(self.oracle.snapshot()))),
+            EngineCommand::GetRebalance => CommandResponse::ok(Some(serde_json::json!(self.rebalancer.snapshot(self.env.now_ms())))),
+            EngineCommand::Rebalance { dry_run } => match rebalance::run(self, dry_run).await {
+                Ok(report) => CommandResponse::ok(Some(serde_json::json!(report))),
+                Err(e) => CommandResponse::error(e),
+            },
+            EngineCommand::GetScheduled => CommandResponse::ok(Some(serde_json::json!(self.scheduler.snapshot()))),
+            EngineCommand::CancelScheduled { execution_id } => match self.scheduler.cancel(&execution_id) {
+                Ok(scheduled) => {
+                    info!(%execution_id, trigger_at_ms = scheduled.trigger_at_ms, "已取消定時執行");
+                    CommandResponse::ok(Some(serde_json::json!(scheduled)))
+                }
+                Err(e) => CommandResponse::error(e),
+            },
+            EngineCommand::GetTradingHalts => CommandResponse::ok(Some(serde_json::json!(self.risk.halts(self.env.now_ms())))),
+            EngineCommand::ResumeTrading { strategy_id } => match self.risk.resume(strategy_id.clone(), self.env.now_ms()) {
+                Some(halt) => {
+                    warn!(scope = ?strategy_id, reason = %halt.reason, "管理員解除交易暫停");
+                    let scope = strategy_id.as_deref().unwrap_or("全部策略");
+                    self.alert(Severity::Warning, "管理員解除交易暫停", format!("{}：{}", scope, halt.reason));
+                    self.events.append(events::EngineEvent::TradingResumed { strategy_id });
+                    CommandResponse::ok(None)
+                }
+                None => CommandResponse::error("當前未處於交易暫停狀態".to_string()),
+            },
+            EngineCommand::GetMempool => CommandResponse::ok(Some(serde_json::json!(self.mempool.snapshot()))),
+            EngineCommand::GetAlgoExecutions => CommandResponse::ok(Some(serde_json::json!(self.algos.snapshot()))),
+            EngineCommand::GetStrategies => {
+                let disabled = self.disabled_strategies.read().unwrap();
+                let strategies: serde_json::Map<String, serde_json::Value> = StrategyType::ALL
+                    .iter()
+                    .map(|strategy| (strategy.name().to_string(), serde_json::json!(!disabled.contains(strategy))))
+                    .collect();
+                CommandResponse::ok(Some(serde_json::Value::Object(strategies)))
+            }
+            EngineCommand::GetStrategyPlugins => CommandResponse::ok(Some(serde_json::json!(self.strategies.snapshot()))),
+            EngineCommand::GetLending => CommandResponse::ok(Some(serde_json::json!(self.lending.snapshot()))),
+            EngineCommand::SetStrategyEnabled { strategy, enabled } => {
+                let mut disabled = self.disabled_strategies.write().unwrap();
+                if enabled {
+                    disabled.remove(&strategy);
+                } else {
+                    disabled.insert(strategy);
+                }
+                info!(strategy = strategy.name(), enabled, "策略開關已更新");
+                self.events.append(events::EngineEvent::ConfigChanged {
+                    key: format!("strategies.{}.enabled", strategy.name()),
+                    value: serde_json::json!(enabled),
+                });
+                CommandResponse::ok(None)
+            }
+            EngineCommand::SimulateOpportunity { symbol, primary_exchange, secondary_exchange, amount, priority } => {
+                match self
+                    .simulate_opportunity(&symbol, &primary_exchange, &secondary_exchange, amount, priority.unwrap_or(0))
+                    .await
+                {
+                    Ok(simulation) => CommandResponse::ok(Some(simulation)),
+                    Err(e) => CommandResponse::error(e),
+                }
+            }
+            EngineCommand::PlanOrder { symbol, side, quantity, exchanges } => {
+                match self.plan_order(&symbol, side, quantity, exchanges.as_deref()) {
+                    Ok(plan) => CommandResponse::ok(Some(serde_json::json!(plan))),
+                    Err(e) => CommandResponse::error(e),
+                }
+            }
+            EngineCommand::PreviewRisk { request } => match self.preview_risk(&request).await {
+                Ok(preview) => CommandResponse::ok(Some(serde_json::json!(preview))),
+                Err(e) => CommandResponse::error(e),
+            },
+            EngineCommand::ExecuteLeg(envelope) => match self.execute_routed_leg(envelope).await {
+                Ok(order) => CommandResponse::ok(Some(serde_json::json!(order))),
+                Err(e) => CommandResponse::error(e),
+            },
+            EngineCommand::GetWallets { chain } => match self.chain(chain.as_deref()).map(|chain| &chain.flash_loan) {
+                Ok(Some(flash_loan)) => CommandResponse::ok(Some(serde_json::json!(flash_loan.wallet_status().await))),
+                Ok(None) => CommandResponse::error("未配置鏈上執行"),
+                Err(e) => CommandResponse::error(e),
+            },
+            EngineCommand::GetEvents { from_sequence, limit } => {
+                let events = self.events.read(from_sequence.unwrap_or(1), limit.unwrap_or(100));
+                CommandResponse::ok(Some(serde_json::json!(events)))
+            }
+            EngineCommand::RunBacktest(params) => match self.run_backtest(params).await {
+                Ok(report) => CommandResponse::ok(Some(serde_json::json!(report))),
+                Err(e) => CommandResponse::error(e),
+            },
+            EngineCommand::RecordTransfer { asset, from_exchange, to_exchange, amount } => {
+                if amount <= 0.0 || from_exchange == to_exchange {
+                    return CommandResponse::error("劃轉金額必須為正且交易所不能相同");
+                }
+                let envelope = self.events.append(events::EngineEvent::Transfer {
+                    asset,
+                    from_exchange,
+                    to_exchange,
+                    amount,
+                });
+                CommandResponse::ok(Some(serde_json::json!({ "sequence": envelope.sequence })))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bench::START_MS;
+    use crate::deterministic_sim::{build, connect_sessions, engine, funded_config, request, wallet_env, with_history, WALLET_KEY_ENV};
+    use crate::{mock_exchange_e2e, strategy, MarginMode};
+    use std::time::Instant;
+
+    // 已過截止時間的請求不執行；轉發的腿超過確認階段預算或截止時間時放棄，並反向平掉本地已成交的腿
+    #[tokio::test]
+    async fn stage_timeouts_and_deadlines_abort_and_unwind_local_legs() {
+        // 對端實例接受連接但從不回報成交
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        tokio::spawn(async move {
+            let mut held = Vec::new();
+            while let Ok((stream, _)) = listener.accept().await {
+                held.push(stream);
+            }
+        });
+        let routing = config::RoutingConfig {
+            region: "london".to_string(),
+            peers: vec![config::PeerConfig {
+                name: "tokyo-1".to_string(),
+                region: "tokyo".to_string(),
+                address: address.clone(),
+                venues: vec!["bybit".to_string()],
+            }],
+            secret_env: "SIM_PEER_SECRET".to_string(),
+            timeout_ms: 60_000,
+            ..config::RoutingConfig::default()
+        };
+        let mut config = funded_config();
+        config.stage_timeouts.fill_confirmation_ms = 100;
+        config.routing = routing.clone();
+        let env = || Environment::simulated(START_MS, 1).with_vars([("SIM_PEER_SECRET", "sim-peer")]);
+        let (engine, path) = build("stage-timeouts", config, env());
+        let engine = Arc::new(engine);
+        connect_sessions(&engine).await;
+        engine.quotes.record_predicted_rate("binance", "BTCUSDT", 0.0060, START_MS);
+        engine.quotes.record_predicted_rate("bybit", "BTCUSDT", 0.0010, START_MS);
+
+        let expired = ArbitrageRequest { deadline_ms: Some(engine.env.now_ms()), ..request(1_000.0) };
+        let response = engine.execute_funding_rate_arbitrage(expired).await;
+        assert_eq!(response.error_message.as_deref(), Some("請求已過執行截止時間"));
+        assert!(engine.events.read(0, 1_000).iter().all(|envelope| !matches!(envelope.event, events::EngineEvent::OrderPlaced { .. })));
+
+        let started = Instant::now();
+        let response = engine.execute_funding_rate_arbitrage(request(1_000.0)).await;
+        assert!(started.elapsed() < Duration::from_secs(5));
+        assert_eq!(response.status, "error");
+        assert!(response.error_message.unwrap().contains("fill_confirmation 階段超時（100ms）"));
+        let execution_id = response.execution_id.unwrap();
+        let legs: Vec<String> = engine
+            .events
+            .read(0, 1_000)
+            .into_iter()
+            .filter_map(|envelope| match envelope.event {
+                events::EngineEvent::OrderPlaced { execution_id: id, leg, exchange, .. } if id == execution_id => {
+                    Some(format!("{}@{}", leg, exchange))
+                }
+                _ => None,
+            })
+            .collect();
+        // 只有本地 binance 的空頭腿被反向平掉，bybit 的腿由對端負責
+        assert_eq!(legs, ["short@binance", "unwind_short@binance"]);
+        assert_eq!(engine.exchanges["binance"].position("BTCUSDT"), 0.0);
+
+        // 截止時間早於階段預算時以剩餘時間為準
+        let deadline = ArbitrageRequest { deadline_ms: Some(engine.env.now_ms() + 40), ..request(1_000.0) };
+        let response = engine.execute_funding_rate_arbitrage(deadline).await;
+        assert!(response.error_message.unwrap().contains("fill_confirmation 階段超時（40ms）"));
+        assert_eq!(engine.exchanges["binance"].position("BTCUSDT"), 0.0);
+        let _ = std::fs::remove_file(path);
+
+        // bybit 的 REST 端點接受連接但從不響應：binance 的腿成交後下單階段超時，
+        // 按執行日誌反向平掉已確認成交的腿
+        let mut config = funded_config();
+        config.stage_timeouts.leg_submission_ms = 100;
+        config.routing = routing;
+        config.exchanges.get_mut("bybit").unwrap().endpoint = Some(config::EndpointConfig {
+            rest_url: format!("http://{}", address),
+            ws_url: format!("ws://{}", address),
+            ..config::EndpointConfig::default()
+        });
+        let (engine, path) = build("leg-submission-timeout", config, env());
+        let engine = Arc::new(engine);
+        session::spawn(Arc::clone(&engine));
+        for _ in 0..250 {
+            if engine.sessions.is_available("binance") {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        engine.quotes.record_predicted_rate("binance", "BTCUSDT", 0.0060, START_MS);
+        engine.quotes.record_predicted_rate("bybit", "BTCUSDT", 0.0010, START_MS);
+        let response = engine.execute_funding_rate_arbitrage(request(1_000.0)).await;
+        assert!(response.error_message.unwrap().contains("leg_submission 階段超時（100ms）"));
+        let execution_id = response.execution_id.unwrap();
+        let legs: Vec<(String, f64)> = engine
+            .events
+            .read(0, 1_000)
+            .into_iter()
+            .filter_map(|envelope| match envelope.event {
+                events::EngineEvent::OrderFilled { execution_id: id, leg, filled_quantity, .. } if id == execution_id => {
+                    Some((leg, filled_quantity))
+                }
+                _ => None,
+            })
+            .collect();
+        assert_eq!(legs, [("short".to_string(), 1_000.0), ("unwind_short".to_string(), 1_000.0)]);
+        assert_eq!(engine.exchanges["binance"].position("BTCUSDT"), 0.0);
+        assert!(engine.journal.execution(&execution_id).is_none());
+        let _ = std::fs::remove_file(path);
+    }
+
+    // 日誌以 JSON 輸出，事件帶有所在執行 span 的字段；set_log_level 在運行時收緊過濾
+    #[tokio::test]
+    async fn structured_logs_carry_execution_fields_and_follow_set_log_level() {
+        use tracing_subscriber::{fmt, prelude::*, reload, EnvFilter};
+        #[derive(Clone, Default)]
+        struct Captured(Arc<std::sync::Mutex<Vec<u8>>>);
+        impl std::io::Write for Captured {
+            fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+                self.0.lock().unwrap().extend_from_slice(buf);
+                Ok(buf.len())
+            }
+            fn flush(&mut self) -> std::io::Result<()> {
+                Ok(())
+            }
+        }
+        let captured = Captured::default();
+        let (filter, handle) = reload::Layer::new(EnvFilter::new("info"));
+        let writer = captured.clone();
+        let subscriber = tracing_subscriber::registry()
+            .with(filter)
+            .with(fmt::layer().json().with_current_span(true).with_writer(move || writer.clone()));
+        let _guard = tracing::subscriber::set_default(subscriber);
+        let lines = || -> Vec<serde_json::Value> {
+            let output = std::mem::take(&mut *captured.0.lock().unwrap());
+            String::from_utf8(output).unwrap().lines().map(|line| serde_json::from_str(line).unwrap()).collect()
+        };
+
+        let (engine, path) = engine("tracing", 1, config::SimulatedExchangeConfig::default());
+        let engine = ExecutionEngine { log_handle: Some(handle), ..engine };
+        let response = engine.execute_funding_rate_arbitrage(request(1_000.0)).await;
+        let execution_id = response.execution_id.unwrap();
+        let started = lines()
+            .into_iter()
+            .find(|line| line["fields"]["message"] == "Rust 引擎執行高頻套利")
+            .unwrap();
+        assert_eq!(started["level"], "INFO");
+        assert_eq!((started["fields"]["amount"].as_f64(), started["fields"]["priority"].as_u64()), (Some(1_000.0), Some(5)));
+        assert_eq!(started["span"]["execution_id"], execution_id.as_str());
+        assert_eq!(started["span"]["symbol"], "BTCUSDT");
+
+        let invalid = engine.handle_command(EngineCommand::SetLogLevel { filter: "[".to_string() }).await;
+        assert!(invalid.error_message.unwrap().contains("無效的日誌過濾器"));
+        assert_eq!(engine.handle_command(EngineCommand::SetLogLevel { filter: "warn".to_string() }).await.status, "success");
+        lines();
+        engine.handle_command(EngineCommand::SetKillSwitch { engaged: true }).await;
+        engine.execute_funding_rate_arbitrage(request(1_000.0)).await;
+        let after = lines();
+        assert!(after.iter().all(|line| line["level"] != "INFO"));
+        assert!(after.iter().any(|line| line["fields"]["message"] == "緊急停止已啟用，拒絕執行"));
+        assert_eq!(engine.events.current().config["log_filter"], "warn");
+        let _ = std::fs::remove_file(path);
+    }
+
+    // 兩邊預測費率相同：常規請求重驗費率差後放棄，快速通道照常下單並延後寫入執行記錄
+    #[tokio::test]
+    async fn fast_path_skips_rate_recheck_and_defers_bookkeeping() {
+        let (engine, path) = build("fast-path", funded_config(), Environment::simulated(START_MS, 1));
+        let (engine, history, db) = with_history(engine, "fast-path").await;
+        let engine = Arc::new(engine);
+        connect_sessions(&engine).await;
+        for exchange in ["binance", "bybit"] {
+            engine.quotes.record_predicted_rate(exchange, "BTCUSDT", 0.0001, START_MS);
+        }
+        let checked = engine.execute_funding_rate_arbitrage(request(1_000.0)).await;
+        assert_eq!(checked.error_message.as_deref(), Some("資金費率差異太小"));
+        let fast = engine.execute_funding_rate_arbitrage(ArbitrageRequest { fast_path: true, ..request(1_000.0) }).await;
+        assert_eq!(fast.status, "success", "{:?}", fast.error_message);
+
+        engine.bookkeeper.flush().await;
+        let entries = history.query(&storage::HistoryFilter::default()).await.unwrap();
+        let recorded = |id: &Option<String>| entries.iter().find(|entry| Some(&entry.execution_id) == id.as_ref()).unwrap();
+        assert!(!recorded(&checked.execution_id).fast_path);
+        let fast_entry = recorded(&fast.execution_id);
+        assert!(fast_entry.fast_path && fast_entry.status == "success");
+        assert_eq!(fast_entry.orders.len(), 2);
+        history.close().await;
+        let _ = std::fs::remove_file(path);
+        let _ = std::fs::remove_file(db);
+    }
+
+    // 請求 include_market_context 時響應帶上入場判斷所用的費率、名義上限、預期滑點與 gas 報價，失敗時同樣附帶
+    #[tokio::test]
+    async fn market_context_reports_entry_inputs_when_requested() {
+        let (engine, path) = build("market-context", funded_config(), Environment::simulated(START_MS, 1));
+        let engine = Arc::new(engine);
+        connect_sessions(&engine).await;
+        engine.quotes.record_predicted_rate("binance", "BTCUSDT", 0.0005, START_MS);
+        engine.quotes.record_predicted_rate("bybit", "BTCUSDT", 0.0001, START_MS);
+        let with_context = |amount| ArbitrageRequest { include_market_context: true, ..request(amount) };
+
+        assert!(engine.execute_funding_rate_arbitrage(request(1_000.0)).await.market_context.is_none());
+        let response = engine.execute_funding_rate_arbitrage(with_context(1_000.0)).await;
+        assert_eq!(response.status, "success", "{:?}", response.error_message);
+        let context = response.market_context.unwrap();
+        assert_eq!((context.primary_rate, context.secondary_rate), (Some(0.0005), Some(0.0001)));
+        assert!((context.rate_diff.unwrap() - 0.0004).abs() < 1e-12);
+        assert_eq!((context.borrow_rate, context.max_notional), (None, 20_000.0));
+        assert!((context.expected_slippage_bps.unwrap() - 2.0 * 0.1f64.sqrt()).abs() < 1e-9);
+        let quote = engine.default_chain().gas_optimizer.quote(5);
+        assert_eq!((context.gas_price, context.max_gas_limit), (quote.max_fee_per_gas, engine.default_chain().gas_optimizer.max_gas_limit()));
+        assert!(context.captured_at_ms >= START_MS);
+
+        engine.quotes.record_predicted_rate("bybit", "BTCUSDT", 0.0005, START_MS);
+        let response = engine.execute_funding_rate_arbitrage(with_context(1_000.0)).await;
+        assert_eq!(response.error_message.as_deref(), Some("資金費率差異太小"));
+        assert_eq!(response.market_context.unwrap().rate_diff, Some(0.0));
+        let _ = std::fs::remove_file(path);
+    }
+
+    // 開倉前在兩條永續腿的交易所設置相同的槓桿與保證金模式並回查確認；超過交易所上限、
+    // 持倉未平時切換模式均失敗，非資金費率策略不接受槓桿參數
+    #[tokio::test]
+    async fn leverage_is_set_and_verified_on_both_perp_legs() {
+        let mut config = funded_config();
+        config.exchanges.get_mut("bybit").unwrap().max_leverage = 5.0;
+        let (engine, path) = build("leverage", config, Environment::simulated(START_MS, 1));
+        let isolated = LeverageSetting {
+            leverage: 5.0,
+            margin_mode: MarginMode::Isolated,
+        };
+        let leveraged = ArbitrageRequest {
+            leverage: Some(5.0),
+            margin_mode: Some(MarginMode::Isolated),
+            ..request(1_000.0)
+        };
+        assert_eq!(engine.leverage_setting(&leveraged), isolated);
+        assert_eq!(engine.leverage_setting(&request(1_000.0)).leverage, 2.0);
+        engine.configure_leverage(&leveraged, isolated).await.unwrap();
+        for exchange in ["binance", "bybit"] {
+            assert_eq!(engine.exchanges[exchange].fetch_leverage("BTCUSDT").await.unwrap(), Some(isolated));
+        }
+        assert_eq!(engine.exchanges["okx"].fetch_leverage("BTCUSDT").await.unwrap(), None);
+
+        let error = engine
+            .configure_leverage(&leveraged, LeverageSetting { leverage: 10.0, ..isolated })
+            .await
+            .unwrap_err();
+        assert_eq!(error, "bybit BTCUSDT 最高槓桿為 5");
+
+        // binance 有持倉時不能改為全倉
+        let fill = ChildOrder {
+            leg: "long".to_string(),
+            exchange: "binance".to_string(),
+            symbol: "BTCUSDT".to_string(),
+            side: "buy".to_string(),
+            quantity: 0.1,
+            filled_quantity: 0.1,
+            fee: 0.0,
+            status: "filled".to_string(),
+        };
+        engine.exchanges["binance"].simulate_fill("e1", &fill, Some(5.0));
+        let cross = LeverageSetting {
+            margin_mode: MarginMode::Cross,
+            ..isolated
+        };
+        let error = engine.configure_leverage(&leveraged, cross).await.unwrap_err();
+        assert_eq!(error, "binance BTCUSDT 持倉未平，無法切換保證金模式");
+        let binance = engine.exchanges["binance"].fetch_leverage("BTCUSDT").await.unwrap();
+        assert_eq!(binance.map(|setting| setting.margin_mode), Some(MarginMode::Isolated));
+
+        let triangular = ArbitrageRequest {
+            strategy_type: StrategyType::Triangular,
+            ..leveraged
+        };
+        let response = engine.execute_funding_rate_arbitrage(triangular).await;
+        assert_eq!(response.error_message.as_deref(), Some("槓桿與保證金模式僅支持資金費率策略"));
+        let _ = std::fs::remove_file(path);
+    }
+
+    // 已上鏈但回滾的交易照常扣除 gas：寫入 GasSpent 事件、計入當日 gas 預算，並按 ETH 參考價記為策略的已實現虧損
+    #[tokio::test]
+    async fn reverted_transactions_charge_burned_gas() {
+        let (engine, path) = engine("burned-gas", 1, Default::default());
+        let chain = engine.default_chain();
+        let request = request(1_000.0);
+        let strategy = request.strategy_type.name();
+        let unsent = engine.record_burned_gas("burn-0", &request, chain, tx_simulation::OnchainError::from("閃電貸 gas 估算失敗".to_string()));
+        assert_eq!(unsent, "閃電貸 gas 估算失敗");
+        assert!(engine.events.gas_spent_since(0).is_empty());
+
+        let revert = tx_simulation::Revert {
+            kind: tx_simulation::RevertKind::Slippage,
+            reason: "Too little received".to_string(),
+            tx_hash: "0xab".to_string(),
+            gas_used: 210_000,
+            gas_cost: 0.0042,
+        };
+        let message = engine.record_burned_gas("burn-1", &request, chain, tx_simulation::OnchainError::reverted("閃電貸", revert));
+        assert_eq!(message, "閃電貸回滾（滑點）: 0xab: Too little received");
+        let spent: Vec<(String, u64, f64)> = engine
+            .events
+            .since(0)
+            .into_iter()
+            .filter_map(|envelope| match envelope.event {
+                events::EngineEvent::GasSpent { execution_id, gas_used, cost_eth, .. } => Some((execution_id, gas_used, cost_eth)),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(spent, [("burn-1".to_string(), 210_000, 0.0042)]);
+        let budget = engine.gas_budget.snapshot().into_iter().find(|status| status.strategy == strategy).unwrap();
+        assert!((budget.spent_eth - 0.0042).abs() < 1e-12);
+        let allocation = engine.risk.allocations(engine.env.now_ms()).into_iter().find(|status| status.strategy_id == "sim").unwrap();
+        assert!((allocation.pnl_today + 0.0042 * 3_000.0).abs() < 1e-9, "{}", allocation.pnl_today);
+        let _ = std::fs::remove_file(path);
+    }
+
+    // 關閉時限內等待排隊等權重的執行完成；超時則不清除執行日誌，重啟時由恢復流程處理
+    #[tokio::test]
+    async fn shutdown_drains_in_flight_executions_or_keeps_their_journal() {
+        for (name, drain_timeout) in [("drain", Duration::from_secs(10)), ("timeout", Duration::from_millis(50))] {
+            let mut config = config::EngineConfig::default();
+            config.execution_queue.enabled = false;
+            for exchange in ["binance", "bybit"] {
+                let settings = config.exchanges.entry(exchange.to_string()).or_default();
+                settings.simulation.reject_rate = 0.0;
+                settings.simulation.partial_fill_rate = 0.0;
+                // 權重每 200ms 恢復 1，耗盡後執行需排隊等待
+                settings.rate_limit.weight_limit = 10;
+                settings.rate_limit.weight_window_secs = 2;
+                settings.rate_limit.reserved_weight_fraction = 0.0;
+                settings.rate_limit.max_market_data_wait_ms = 2_000;
+            }
+            let (engine, path) = build(&format!("shutdown-{}", name), config, Environment::simulated(START_MS, 1));
+            let engine = Arc::new(engine);
+            connect_sessions(&engine).await;
+            for _ in 0..10 {
+                engine.exchanges["binance"].scheduler.acquire(rate_limit::RequestKind::MarketData, 1).await.unwrap();
+            }
+            let execution = tokio::spawn({
+                let engine = Arc::clone(&engine);
+                async move { engine.execute_funding_rate_arbitrage(request(1_000.0)).await }
+            });
+            while engine.open_execution_snapshot().is_empty() {
+                tokio::time::sleep(Duration::from_millis(5)).await;
+            }
+            assert_eq!(engine.journal.in_flight().len(), 1);
+
+            engine.shutdown(drain_timeout).await;
+            let rejected = engine.execute_funding_rate_arbitrage(request(1_000.0)).await;
+            assert_eq!(rejected.error_message.as_deref(), Some("引擎正在關閉，拒絕執行"));
+            if name == "drain" {
+                assert!(engine.open_execution_snapshot().is_empty());
+                assert!(engine.journal.in_flight().is_empty());
+                assert!(execution.await.unwrap().execution_id.is_some());
+            } else {
+                let in_flight = engine.journal.in_flight();
+                assert_eq!(in_flight.len(), 1);
+                assert_eq!(in_flight[0].request.amount, 1_000.0);
+                let replayed = journal::Journal::open(path.with_extension("journal").to_str().unwrap()).unwrap().in_flight();
+                assert_eq!(replayed.len(), 1);
+                execution.abort();
+            }
+            let _ = std::fs::remove_file(path.with_extension("journal"));
+            let _ = std::fs::remove_file(path);
+        }
+    }
+
+    #[tokio::test]
+    async fn batch_reports_each_item_in_request_order() {
+        let mut config = config::EngineConfig::default();
+        // 測試中沒有啟動隊列調度
+        config.execution_queue.enabled = false;
+        let (engine, path) = build("batch", config, Environment::simulated(START_MS, 1));
+        assert_eq!(engine.submit_batch(BatchRequest { requests: Vec::new() }).await.status, "error");
+
+        let invalid = ArbitrageRequest {
+            symbol: "BTC/".to_string(),
+            ..request(1_000.0)
+        };
+        let response = engine.submit_batch(BatchRequest { requests: vec![request(1_000.0), invalid] }).await;
+        let symbols: Vec<&str> = response.results.iter().map(|item| item.symbol.as_str()).collect();
+        assert_eq!(symbols, ["BTCUSDT", "BTC/"]);
+        assert_eq!(response.succeeded + response.failed, 2);
+        assert!(response.results[1].response.error_message.as_deref().unwrap().contains("無法解析交易對"));
+        assert_eq!(response.status, if response.succeeded == 0 { "error" } else { "partial" });
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[tokio::test]
+    async fn sub_accounts_get_own_connectors_and_resolve_per_strategy() {
+        let mut config = config::EngineConfig::default();
+        let binance = config.exchanges.entry("binance".to_string()).or_default();
+        binance.accounts.insert(
+            "iso".to_string(),
+            config::AccountConfig {
+                strategies: vec!["iso".to_string()],
+                ..Default::default()
+            },
+        );
+        let (engine, path) = build("accounts", config, Environment::simulated(START_MS, 1));
+
+        // 子賬戶有自己的憑證位置、限頻配額與會話，行情遍歷不重複計入
+        let sub = &engine.exchanges["binance@iso"];
+        assert_eq!(sub.settings.api_key_env.as_deref(), Some("BINANCE_ISO_API_KEY"));
+        assert!(engine.sessions.snapshot().contains_key("binance@iso"));
+        assert_eq!(engine.venues(), ["binance", "bybit", "okx"]);
+
+        let mut routed = ArbitrageRequest {
+            strategy_id: "iso".to_string(),
+            ..request(1_000.0)
+        };
+        engine.resolve_accounts(&mut routed).unwrap();
+        assert_eq!((routed.primary_exchange.as_str(), routed.secondary_exchange.as_str()), ("binance@iso", "bybit"));
+
+        let mut main = ArbitrageRequest {
+            account: Some(config::MAIN_ACCOUNT.to_string()),
+            ..routed.clone()
+        };
+        main.primary_exchange = "binance".to_string();
+        engine.resolve_accounts(&mut main).unwrap();
+        assert_eq!(main.primary_exchange, "binance");
+
+        let missing = ArbitrageRequest {
+            account: Some("nope".to_string()),
+            ..request(1_000.0)
+        };
+        let response = engine.execute_funding_rate_arbitrage(missing).await;
+        assert_eq!(response.error_message.as_deref(), Some("交易所 binance 未配置賬戶 nope"));
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn sharded_state_stays_consistent_under_concurrent_writers() {
+        let (engine, path) = engine("sharded", 1, config::SimulatedExchangeConfig::default());
+        let engine = Arc::new(engine);
+        let tasks: Vec<_> = (0..8)
+            .map(|task| {
+                let engine = Arc::clone(&engine);
+                tokio::spawn(async move {
+                    let mut ids = Vec::new();
+                    for n in 0..50 {
+                        // 同一執行的同一條腿並發分片下單
+                        ids.push(engine.order_ids.assign("sim", "e1", "short"));
+                        engine.funding_history.track(&format!("T{}USDT", n % 5), START_MS);
+                        engine.quotes.record_predicted_rate("binance", &format!("T{}USDT", task), 0.0001, START_MS);
+                        engine.latency.record(&format!("stage{}", n % 3), 10);
+                    }
+                    ids
+                })
+            })
+            .collect();
+        let mut ids = Vec::new();
+        for task in tasks {
+            ids.extend(task.await.unwrap());
+        }
+        let unique: std::collections::HashSet<_> = ids.iter().collect();
+        assert_eq!(unique.len(), 400);
+        assert!(ids.iter().all(|id| engine.order_ids.lookup(id).is_some_and(|tag| tag.leg == "short")));
+        assert_eq!(engine.funding_history.symbols().iter().filter(|symbol| symbol.starts_with('T')).count(), 5);
+        assert_eq!(engine.quotes.snapshot(START_MS).len(), 8);
+        assert_eq!(engine.latency.snapshot().values().map(|stage| stage.count).sum::<u64>(), 400);
+        let _ = std::fs::remove_file(path);
+    }
+
+    // 單步閃電貸計劃：計劃收益按 amount 的 0.6% 預估，合約保證其中一半
+    struct FlashLoanStrategy;
+
+    impl strategy::Strategy for FlashLoanStrategy {
+        fn name(&self) -> &str {
+            "flash"
+        }
+
+        fn evaluate(&self, _market: &strategy::MarketView<'_>) -> Vec<strategy::StrategyOpportunity> {
+            Vec::new()
+        }
+
+        fn build_execution_plan(&self, request: &ArbitrageRequest, _market: &strategy::MarketView<'_>) -> Result<strategy::ExecutionPlan, String> {
+            let flash_loan = strategy::Action::FlashLoan {
+                chain: None,
+                symbol: request.symbol.clone(),
+                amount: request.amount,
+                min_profit: request.amount * 0.003,
+            };
+            Ok(strategy::ExecutionPlan {
+                steps: vec![strategy::PlannedStep {
+                    leg: "flash_loan".to_string(),
+                    action: flash_loan,
+                    depends_on: Vec::new(),
+                    unwind: None,
+                }],
+                expected_profit: request.amount * 0.006,
+            })
+        }
+
+        fn on_fill(&self, _execution_id: &str, _order: &ChildOrder) {}
+    }
+
+    // 閃電貸與計劃路徑共用同一淨收益門檻：gas 按基礎費加小費計，閃電貸取實時估算，計劃先按配置的 gas 單位預篩。
+    // 節點估算 400k gas，靜態報價 20 gwei、ETH 3000 USDT，gas 費 24 USDT；門檻通過後發送交易由節點拒絕
+    #[tokio::test]
+    async fn onchain_paths_require_min_net_profit_after_gas() {
+        use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+        use web3::ethabi::Token;
+        let calls = Arc::new((AtomicUsize::new(0), AtomicUsize::new(0), AtomicBool::new(false)));
+        let node = Arc::clone(&calls);
+        let url = mock_exchange_e2e::rpc_node(Arc::new(move |method, _| {
+            Ok(match method {
+                "eth_call" => mock_exchange_e2e::abi_hex(&[Token::Uint(web3::types::U256::MAX >> 128)]),
+                "eth_estimateGas" => {
+                    node.0.fetch_add(1, Ordering::SeqCst);
+                    // 節點返回超出 u64 的估算時報錯而非 panic
+                    if node.2.load(Ordering::SeqCst) {
+                        serde_json::json!("0x10000000000000000")
+                    } else {
+                        serde_json::json!("0x61a80")
+                    }
+                }
+                "eth_getTransactionCount" => serde_json::json!("0x0"),
+                "eth_sendRawTransaction" => {
+                    node.1.fetch_add(1, Ordering::SeqCst);
+                    return Err("rejected by stub".to_string());
+                }
+                other => return Err(format!("unexpected {}", other)),
+            })
+        }))
+        .await;
+        let mut config = config::EngineConfig::default();
+        config.flash_loan.rpc_url = Some(url);
+        config.flash_loan.receiver_address = Some(format!("{:?}", web3::types::Address::from_low_u64_be(0xf1)));
+        config.flash_loan.providers = vec!["aave".to_string()];
+        config.flash_loan.wallets[0].private_key_env = Some(WALLET_KEY_ENV.to_string());
+        config.gas.min_net_profit_usdt = 5.0;
+        for exchange in ["binance", "bybit", "okx"] {
+            config.exchanges.entry(exchange.to_string()).or_default();
+        }
+        let (engine, path) = build("profit-gate", config, wallet_env());
+        let (estimates, sends) = (|| calls.0.load(Ordering::SeqCst), || calls.1.load(Ordering::SeqCst));
+        let quote = engine.default_chain().gas_optimizer.quote(5);
+        assert!(!quote.live);
+
+        // 閃電貸：收益 20，扣除手續費 5 與 gas 24 後為負，估算後拒絕，不發送
+        let funding = request(10_000.0);
+        let flash = async |rate_diff| {
+            engine
+                .execute_flash_loan_arbitrage("gate", &funding, rate_diff, &quote, false, None)
+                .await
+                .map_err(|failure| failure.error)
+        };
+        let error = flash(0.002).await.err().unwrap();
+        assert!(error.contains("閃電貸扣除 gas") && error.contains("低於最低要求 5"), "{}", error);
+        assert_eq!((estimates(), sends()), (1, 0));
+        // 收益 50，淨收益 21 高於門檻，交易發往節點
+        let error = flash(0.005).await.err().unwrap();
+        assert!(error.contains("發送交易失敗"), "{}", error);
+        assert_eq!((estimates(), sends()), (2, 1));
+
+        // 計劃：預估收益 18 按配置的 400k gas 預篩即拒絕，不訪問節點
+        engine.register_strategy(Arc::new(FlashLoanStrategy)).unwrap();
+        let plan = |amount| ArbitrageRequest {
+            strategy_type: StrategyType::Plugin,
+            plugin: Some("flash".to_string()),
+            ..request(amount)
+        };
+        let error = strategy::execute(&engine, "plan-gate-1", &plan(3_000.0)).await.err().unwrap();
+        assert!(error.contains("低於最低要求 5") && !error.contains("閃電貸"), "{}", error);
+        assert_eq!((estimates(), sends()), (2, 1));
+        // 預估收益 30 通過預篩，但合約保證的 15 扣除實時估算的 gas 後不足
+        let error = strategy::execute(&engine, "plan-gate-2", &plan(5_000.0)).await.err().unwrap();
+        assert!(error.contains("閃電貸扣除 gas"), "{}", error);
+        assert_eq!((estimates(), sends()), (3, 1));
+        // 保證收益 30，淨收益 6 高於門檻
+        let error = strategy::execute(&engine, "plan-gate-3", &plan(10_000.0)).await.err().unwrap();
+        assert!(error.contains("發送交易失敗"), "{}", error);
+        assert_eq!((estimates(), sends()), (4, 2));
+
+        calls.2.store(true, Ordering::SeqCst);
+        let error = flash(0.005).await.err().unwrap();
+        assert!(error.contains("gas 估算超出範圍"), "{}", error);
+        assert_eq!((estimates(), sends()), (5, 2));
+        let _ = std::fs::remove_file(path);
+    }
+}
@@ -0,0 +1,232 @@
+use futures::future::BoxFuture;
+use rand::{Rng as _, RngCore, SeedableRng};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// 引擎讀取時間與等待模擬延遲的來源。
+pub trait Clock: Send + Sync {
+    /// 當前 Unix 時間（毫秒）
+    fn now_ms(&self) -> i64;
+
+    /// 當前 Unix 時間（微秒），用於測量下單確認等亞毫秒延遲
+    fn now_us(&self) -> i64;
+
+    /// 等待一段模擬延遲（交易所響應、下單確認等）
+    fn sleep(&self, duration: Duration) -> BoxFuture<'static, ()>;
+}
+
+/// 系統時鐘：讀取本機時間，延遲真實等待。
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now_ms(&self) -> i64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis() as i64)
+            .unwrap_or_default()
+    }
+
+    fn now_us(&self) -> i64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_micros() as i64)
+            .unwrap_or_default()
+    }
+
+    fn sleep(&self, duration: Duration) -> BoxFuture<'static, ()> {
+        Box::pin(tokio::time::sleep(duration))
+    }
+}
+
+/// 模擬時鐘：時間只在等待模擬延遲或調用 [`SimulatedClock::advance`] 時前進，等待不佔用真實時間。
+pub struct SimulatedClock {
+    now_us: AtomicI64,
+}
+
+impl SimulatedClock {
+    pub fn new(start_ms: i64) -> Self {
+        Self {
+            now_us: AtomicI64::new(start_ms * 1_000),
+        }
+    }
+
+    pub fn advance(&self, duration: Duration) {
+        self.now_us.fetch_add(duration.as_micros() as i64, Ordering::Relaxed);
+    }
+}
+
+impl Clock for SimulatedClock {
+    fn now_ms(&self) -> i64 {
+        self.now_us.load(Ordering::Relaxed) / 1_000
+    }
+
+    fn now_us(&self) -> i64 {
+        self.now_us.load(Ordering::Relaxed)
+    }
+
+    fn sleep(&self, duration: Duration) -> BoxFuture<'static, ()> {
+        self.advance(duration);
+        Box::pin(tokio::task::yield_now())
+    }
+}
+
+/// 模擬行情、延遲與成交結果使用的隨機數來源。
+pub trait Rng: Send + Sync {
+    /// [0, 1) 上的均勻分佈
+    fn next_f64(&self) -> f64;
+
+    fn next_u64(&self) -> u64;
+
+    /// 派生一個獨立的隨機數流，並發任務各用一個流，取數順序互不影響
+    fn fork(&self, stream: &str) -> Arc<dyn Rng>;
+}
+
+/// 線程本地隨機數，不可重現。
+pub struct SystemRng;
+
+impl Rng for SystemRng {
+    fn next_f64(&self) -> f64 {
+        rand::random()
+    }
+
+    fn next_u64(&self) -> u64 {
+        rand::random()
+    }
+
+    fn fork(&self, _stream: &str) -> Arc<dyn Rng> {
+        Arc::new(SystemRng)
+    }
+}
+
+/// 固定種子的隨機數：同一種子、同一取數順序得到同一序列；派生流的種子由父種子與流名決定。
+pub struct SeededRng {
+    seed: u64,
+    state: Mutex<rand::rngs::StdRng>,
+}
+
+impl SeededRng {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            state: Mutex::new(rand::rngs::StdRng::seed_from_u64(seed)),
+        }
+    }
+}
+
+impl Rng for SeededRng {
+    fn next_f64(&self) -> f64 {
+        self.state.lock().unwrap().gen()
+    }
+
+    fn next_u64(&self) -> u64 {
+        self.state.lock().unwrap().next_u64()
+    }
+
+    fn fork(&self, stream: &str) -> Arc<dyn Rng> {
+        let mut hasher = DefaultHasher::new();
+        (self.seed, stream).hash(&mut hasher);
+        Arc::new(SeededRng::new(hasher.finish()))
+    }
+}
+
+/// 密鑰、token 與存儲路徑等配置中按名稱引用的變量來源。
+pub trait Vars: Send + Sync {
+    /// 未設置時為 None
+    fn var(&self, name: &str) -> Option<String>;
+}
+
+/// 進程環境變量。
+pub struct SystemVars;
+
+impl Vars for SystemVars {
+    fn var(&self, name: &str) -> Option<String> {
+        std::env::var(name).ok()
+    }
+}
+
+/// 固定的變量表，不讀進程環境；並行運行的測試各持一份，互不覆蓋。
+#[derive(Default)]
+pub struct FixedVars(HashMap<String, String>);
+
+impl FixedVars {
+    pub fn new<K: Into<String>, V: Into<String>>(vars: impl IntoIterator<Item = (K, V)>) -> Self {
+        Self(vars.into_iter().map(|(name, value)| (name.into(), value.into())).collect())
+    }
+}
+
+impl Vars for FixedVars {
+    fn var(&self, name: &str) -> Option<String> {
+        self.0.get(name).cloned()
+    }
+}
+
+/// 注入引擎的時鐘、隨機數與變量來源。
+///
+/// 引擎與各交易所連接器的時間戳、模擬延遲、模擬行情與成交結果、執行 ID 都取自這裡；以
+/// [`Environment::simulated`] 啟動的引擎在相同輸入下行為可重現。客戶端認證、區域間轉發簽名與鏈上交易截止時間
+/// 需與外部系統比對，始終使用系統時間。密鑰與存儲路徑經 `vars` 讀取，模擬環境默認不讀進程環境變量。
+#[derive(Clone)]
+pub struct Environment {
+    pub clock: Arc<dyn Clock>,
+    pub rng: Arc<dyn Rng>,
+    pub vars: Arc<dyn Vars>,
+}
+
+impl Default for Environment {
+    fn default() -> Self {
+        Self::system()
+    }
+}
+
+impl Environment {
+    pub fn system() -> Self {
+        Self {
+            clock: Arc::new(SystemClock),
+            rng: Arc::new(SystemRng),
+            vars: Arc::new(SystemVars),
+        }
+    }
+
+    /// 從 `start_ms` 開始的模擬時鐘與固定種子的隨機數
+    pub fn simulated(start_ms: i64, seed: u64) -> Self {
+        Self {
+            clock: Arc::new(SimulatedClock::new(start_ms)),
+            rng: Arc::new(SeededRng::new(seed)),
+            vars: Arc::new(FixedVars::default()),
+        }
+    }
+
+    /// 以給定的變量表替換變量來源
+    pub fn with_vars<K: Into<String>, V: Into<String>>(self, vars: impl IntoIterator<Item = (K, V)>) -> Self {
+        Self {
+            vars: Arc::new(FixedVars::new(vars)),
+            ..self
+        }
+    }
+
+    pub fn var(&self, name: &str) -> Option<String> {
+        self.vars.var(name)
+    }
+
+    pub fn now_ms(&self) -> i64 {
+        self.clock.now_ms()
+    }
+
+    /// 共用時鐘、使用獨立隨機數流的環境
+    pub fn fork(&self, stream: &str) -> Self {
+        Self {
+            clock: Arc::clone(&self.clock),
+            rng: self.rng.fork(stream),
+            vars: Arc::clone(&self.vars),
+        }
+    }
+
+    pub fn uuid(&self) -> uuid::Uuid {
+        let bytes = (u128::from(self.rng.next_u64()) << 64 | u128::from(self.rng.next_u64())).to_le_bytes();
+        uuid::Builder::from_random_bytes(bytes).into_uuid()
+    }
+}
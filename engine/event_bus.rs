@@ -0,0 +1,570 @@
+use super::config::{EventBusBackend, EventBusConfig, KafkaConfig, NatsConfig, RedisStreamConfig};
+use super::events::{settled_pnl, EngineEvent, EventEnvelope};
+use super::environment::Vars;
+use super::{ExecutionEngine, StrategyType};
+use serde::Serialize;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::TcpStream;
+use tracing::{info, warn};
+
+pub type PublishFuture<'a> = Pin<Box<dyn Future<Output = Result<(), String>> + Send + 'a>>;
+
+// 發布到下游的結構化事件；id 由日誌序號與類型組成，消費方據此去重
+#[derive(Debug, Clone, Serialize)]
+pub struct BusMessage {
+    pub id: String,
+    pub sequence: u64,
+    pub recorded_at_ms: i64,
+    pub execution_id: String,
+    #[serde(flatten)]
+    pub event: BusEvent,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum BusEvent {
+    RequestReceived {
+        strategy_id: String,
+        strategy_type: StrategyType,
+        symbol: String,
+        primary_exchange: String,
+        secondary_exchange: String,
+        amount: f64,
+    },
+    LegFilled {
+        strategy_id: String,
+        leg: String,
+        exchange: String,
+        symbol: String,
+        side: String,
+        filled_quantity: f64,
+        fee: f64,
+    },
+    ExecutionSettled {
+        strategy_id: String,
+        symbol: String,
+        status: String,
+        fees: f64,
+    },
+    // 計入策略預算與當日限額的已實現盈虧
+    PnlRealized {
+        strategy_id: String,
+        pnl: f64,
+    },
+}
+
+impl BusEvent {
+    fn name(&self) -> &'static str {
+        match self {
+            BusEvent::RequestReceived { .. } => "request_received",
+            BusEvent::LegFilled { .. } => "leg_filled",
+            BusEvent::ExecutionSettled { .. } => "execution_settled",
+            BusEvent::PnlRealized { .. } => "pnl_realized",
+        }
+    }
+}
+
+// 只發布與執行相關的事件，一條結算事件拆為結算與已實現盈虧兩條消息
+pub fn messages(envelope: &EventEnvelope) -> Vec<BusMessage> {
+    let (execution_id, events) = match &envelope.event {
+        EngineEvent::RequestReceived {
+            execution_id,
+            strategy_id,
+            strategy_type,
+            symbol,
+            primary_exchange,
+            secondary_exchange,
+            amount,
+        } => (
+            execution_id,
+            vec![BusEvent::RequestReceived {
+                strategy_id: strategy_id.clone(),
+                strategy_type: *strategy_type,
+                symbol: symbol.clone(),
+                primary_exchange: primary_exchange.clone(),
+                secondary_exchange: secondary_exchange.clone(),
+                amount: *amount,
+            }],
+        ),
+        EngineEvent::OrderFilled { execution_id, strategy_id, leg, exchange, symbol, side, filled_quantity, fee } => (
+            execution_id,
+            vec![BusEvent::LegFilled {
+                strategy_id: strategy_id.clone(),
+                leg: leg.clone(),
+                exchange: exchange.clone(),
+                symbol: symbol.clone(),
+                side: side.clone(),
+                filled_quantity: *filled_quantity,
+                fee: *fee,
+            }],
+        ),
+        EngineEvent::ExecutionSettled { execution_id, strategy_id, symbol, status, pnl, fees } => (
+            execution_id,
+            vec![
+                BusEvent::ExecutionSettled {
+                    strategy_id: strategy_id.clone(),
+                    symbol: symbol.clone(),
+                    status: status.clone(),
+                    fees: *fees,
+                },
+                BusEvent::PnlRealized {
+                    strategy_id: strategy_id.clone(),
+                    pnl: settled_pnl(status, *pnl, *fees),
+                },
+            ],
+        ),
+        _ => return Vec::new(),
+    };
+    events
+        .into_iter()
+        .map(|event| BusMessage {
+            id: format!("{}:{}", envelope.sequence, event.name()),
+            sequence: envelope.sequence,
+            recorded_at_ms: envelope.recorded_at_ms,
+            execution_id: execution_id.clone(),
+            event,
+        })
+        .collect()
+}
+
+pub trait Publisher: Send {
+    fn name(&self) -> &'static str;
+    // 整批確認後才返回 Ok；失敗時調用方從同一序號重發
+    fn publish<'a>(&'a mut self, batch: &'a [BusMessage]) -> PublishFuture<'a>;
+}
+
+pub fn publisher(config: &EventBusConfig, vars: &dyn Vars) -> Result<Box<dyn Publisher>, String> {
+    let timeout = Duration::from_millis(config.timeout_ms);
+    Ok(match config.backend {
+        EventBusBackend::Kafka => Box::new(KafkaRest::new(&config.kafka, timeout)?),
+        EventBusBackend::Nats => Box::new(Nats::new(&config.nats, vars)?),
+        EventBusBackend::Redis => Box::new(RedisStreams::new(&config.redis, vars)?),
+    })
+}
+
+fn env_secret(vars: &dyn Vars, name: &Option<String>) -> Result<Option<String>, String> {
+    match name {
+        Some(name) => vars
+            .var(name)
+            .filter(|value| !value.is_empty())
+            .map(Some)
+            .ok_or_else(|| format!("未設置事件總線環境變量 {}", name)),
+        None => Ok(None),
+    }
+}
+
+// POST /topics/<topic>，響應中任一 offset 帶 error_code 即整批失敗
+pub struct KafkaRest {
+    client: reqwest::Client,
+    url: String,
+}
+
+impl KafkaRest {
+    pub fn new(config: &KafkaConfig, timeout: Duration) -> Result<Self, String> {
+        let client = reqwest::Client::builder().timeout(timeout).build().map_err(|e| e.to_string())?;
+        Ok(Self {
+            client,
+            url: format!("{}/topics/{}", config.rest_url.trim_end_matches('/'), config.topic),
+        })
+    }
+}
+
+impl Publisher for KafkaRest {
+    fn name(&self) -> &'static str {
+        "kafka"
+    }
+
+    fn publish<'a>(&'a mut self, batch: &'a [BusMessage]) -> PublishFuture<'a> {
+        Box::pin(async move {
+            let records: Vec<_> = batch
+                .iter()
+                .map(|message| serde_json::json!({"key": message.execution_id, "value": message}))
+                .collect();
+            let response = self
+                .client
+                .post(&self.url)
+                .header("Content-Type", "application/vnd.kafka.json.v2+json")
+                .body(serde_json::json!({ "records": records }).to_string())
+                .send()
+                .await
+                .map_err(|e| e.to_string())?;
+            if !response.status().is_success() {
+                return Err(format!("Kafka REST Proxy 返回 {}", response.status()));
+            }
+            let body: serde_json::Value = response.json().await.map_err(|e| e.to_string())?;
+            let failed = body["offsets"]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .find(|offset| !offset["error_code"].is_null());
+            match failed {
+                Some(offset) => Err(format!("Kafka 寫入失敗: {}", offset["error"])),
+                None => Ok(()),
+            }
+        })
+    }
+}
+
+// NATS 文本協議：逐條 PUB 後以 PING/PONG 確認服務端已處理整批
+pub struct Nats {
+    address: String,
+    subject_prefix: String,
+    token: Option<String>,
+    connection: Option<BufReader<TcpStream>>,
+}
+
+impl Nats {
+    pub fn new(config: &NatsConfig, vars: &dyn Vars) -> Result<Self, String> {
+        Ok(Self {
+            address: config.address.clone(),
+            subject_prefix: config.subject_prefix.clone(),
+            token: env_secret(vars, &config.token_env)?,
+            connection: None,
+        })
+    }
+
+    async fn connect(&self) -> Result<BufReader<TcpStream>, String> {
+        let stream = TcpStream::connect(&self.address).await.map_err(|e| format!("連接 NATS {} 失敗: {}", self.address, e))?;
+        let mut connection = BufReader::new(stream);
+        let info = read_line(&mut connection).await?;
+        if !info.starts_with("INFO") {
+            return Err(format!("NATS 握手異常: {}", info));
+        }
+        let options = serde_json::json!({"verbose": false, "pedantic": false, "name": "arbitrage-engine", "auth_token": self.token});
+        connection
+            .write_all(format!("CONNECT {}\r\n", options).as_bytes())
+            .await
+            .map_err(|e| e.to_string())?;
+        Ok(connection)
+    }
+
+    async fn send(&self, connection: &mut BufReader<TcpStream>, batch: &[BusMessage]) -> Result<(), String> {
+        let mut buffer = Vec::new();
+        for message in batch {
+            let payload = serde_json::to_vec(message).map_err(|e| e.to_string())?;
+            let subject = format!("{}.{}", self.subject_prefix, message.event.name());
+            buffer.extend_from_slice(format!("PUB {} {}\r\n", subject, payload.len()).as_bytes());
+            buffer.extend_from_slice(&payload);
+            buffer.extend_from_slice(b"\r\n");
+        }
+        buffer.extend_from_slice(b"PING\r\n");
+        connection.write_all(&buffer).await.map_err(|e| e.to_string())?;
+        loop {
+            let line = read_line(connection).await?;
+            match line.as_str() {
+                "PONG" => return Ok(()),
+                "PING" => connection.write_all(b"PONG\r\n").await.map_err(|e| e.to_string())?,
+                _ if line.starts_with("-ERR") => return Err(format!("NATS 錯誤: {}", line)),
+                _ => {}
+            }
+        }
+    }
+}
+
+impl Publisher for Nats {
+    fn name(&self) -> &'static str {
+        "nats"
+    }
+
+    fn publish<'a>(&'a mut self, batch: &'a [BusMessage]) -> PublishFuture<'a> {
+        Box::pin(async move {
+            let mut connection = match self.connection.take() {
+                Some(connection) => connection,
+                None => self.connect().await?,
+            };
+            self.send(&mut connection, batch).await?;
+            // 出錯時丟棄連接，下一次重新建立
+            self.connection = Some(connection);
+            Ok(())
+        })
+    }
+}
+
+// Redis Streams：管道化 XADD，逐條讀取回覆，任一錯誤回覆即整批失敗
+pub struct RedisStreams {
+    address: String,
+    stream: String,
+    password: Option<String>,
+    max_len: u64,
+    connection: Option<BufReader<TcpStream>>,
+}
+
+impl RedisStreams {
+    pub fn new(config: &RedisStreamConfig, vars: &dyn Vars) -> Result<Self, String> {
+        Ok(Self {
+            address: config.address.clone(),
+            stream: config.stream.clone(),
+            password: env_secret(vars, &config.password_env)?,
+            max_len: config.max_len,
+            connection: None,
+        })
+    }
+
+    async fn connect(&self) -> Result<BufReader<TcpStream>, String> {
+        let stream = TcpStream::connect(&self.address).await.map_err(|e| format!("連接 Redis {} 失敗: {}", self.address, e))?;
+        let mut connection = BufReader::new(stream);
+        if let Some(password) = &self.password {
+            connection.write_all(&resp(&["AUTH", password])).await.map_err(|e| e.to_string())?;
+            read_reply(&mut connection).await?;
+        }
+        Ok(connection)
+    }
+
+    async fn send(&self, connection: &mut BufReader<TcpStream>, batch: &[BusMessage]) -> Result<(), String> {
+        let max_len = self.max_len.to_string();
+        let mut buffer = Vec::new();
+        for message in batch {
+            let data = serde_json::to_string(message).map_err(|e| e.to_string())?;
+            buffer.extend(resp(&[
+                "XADD", &self.stream, "MAXLEN", "~", &max_len, "*",
+                "id", &message.id, "type", message.event.name(), "data", &data,
+            ]));
+        }
+        connection.write_all(&buffer).await.map_err(|e| e.to_string())?;
+        for _ in batch {
+            read_reply(connection).await?;
+        }
+        Ok(())
+    }
+}
+
+impl Publisher for RedisStreams {
+    fn name(&self) -> &'static str {
+        "redis"
+    }
+
+    fn publish<'a>(&'a mut self, batch: &'a [BusMessage]) -> PublishFuture<'a> {
+        Box::pin(async move {
+            let mut connection = match self.connection.take() {
+                Some(connection) => connection,
+                None => self.connect().await?,
+            };
+            self.send(&mut connection, batch).await?;
+            self.connection = Some(connection);
+            Ok(())
+        })
+    }
+}
+
+// RESP 數組形式的命令
+fn resp(args: &[&str]) -> Vec<u8> {
+    let mut buffer = format!("*{}\r\n", args.len()).into_bytes();
+    for arg in args {
+        buffer.extend_from_slice(format!("${}\r\n", arg.len()).as_bytes());
+        buffer.extend_from_slice(arg.as_bytes());
+        buffer.extend_from_slice(b"\r\n");
+    }
+    buffer
+}
+
+async fn read_line(connection: &mut BufReader<TcpStream>) -> Result<String, String> {
+    let mut line = String::new();
+    if connection.read_line(&mut line).await.map_err(|e| e.to_string())? == 0 {
+        return Err("連接已關閉".to_string());
+    }
+    Ok(line.trim_end().to_string())
+}
+
+// 只需區分成功與錯誤回覆；批量字符串（XADD 返回的條目 ID）跳過內容
+async fn read_reply(connection: &mut BufReader<TcpStream>) -> Result<(), String> {
+    let line = read_line(connection).await?;
+    match line.chars().next() {
+        Some('-') => Err(format!("Redis 錯誤: {}", &line[1..])),
+        Some('$') => {
+            if line != "$-1" {
+                read_line(connection).await?;
+            }
+            Ok(())
+        }
+        Some('+' | ':') => Ok(()),
+        _ => Err(format!("Redis 回覆無法識別: {}", line)),
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BusStatus {
+    pub backend: Option<&'static str>,
+    // 已確認發布的最後一個日誌序號
+    pub delivered_sequence: u64,
+    pub pending_events: u64,
+    pub last_delivered_ms: Option<i64>,
+    pub last_error: Option<String>,
+}
+
+// 發布進度；cursor 文件只在整批確認後更新
+pub struct EventBus {
+    config: EventBusConfig,
+    status: Mutex<BusStatus>,
+}
+
+impl EventBus {
+    // 未啟用時不讀取 cursor 文件
+    pub fn new(config: EventBusConfig, last_sequence: u64) -> Self {
+        let delivered_sequence = if config.enabled {
+            std::fs::read_to_string(&config.cursor_path)
+                .ok()
+                .and_then(|cursor| cursor.trim().parse().ok())
+                .unwrap_or(last_sequence)
+        } else {
+            last_sequence
+        };
+        Self {
+            config,
+            status: Mutex::new(BusStatus {
+                backend: None,
+                delivered_sequence,
+                pending_events: 0,
+                last_delivered_ms: None,
+                last_error: None,
+            }),
+        }
+    }
+
+    pub fn snapshot(&self, last_sequence: u64) -> BusStatus {
+        let mut status = self.status.lock().unwrap().clone();
+        status.pending_events = last_sequence.saturating_sub(status.delivered_sequence);
+        status
+    }
+}
+
+// 發布下一批事件，返回本批推進的日誌條數；沒有新事件時返回 0
+pub async fn drain_once(engine: &ExecutionEngine, publisher: &mut dyn Publisher) -> Result<usize, String> {
+    let bus = &engine.event_bus;
+    let from = bus.status.lock().unwrap().delivered_sequence + 1;
+    let envelopes = engine.events.read(from, bus.config.batch_size);
+    let Some(last) = envelopes.last().map(|envelope| envelope.sequence) else {
+        return Ok(0);
+    };
+    let batch: Vec<BusMessage> = envelopes.iter().flat_map(messages).collect();
+    let result = if batch.is_empty() {
+        Ok(())
+    } else {
+        let timeout = Duration::from_millis(bus.config.timeout_ms);
+        tokio::time::timeout(timeout, publisher.publish(&batch))
+            .await
+            .unwrap_or_else(|_| Err(format!("發布超時（{}ms）", bus.config.timeout_ms)))
+    };
+    let mut status = bus.status.lock().unwrap();
+    status.backend = Some(publisher.name());
+    if let Err(error) = result {
+        status.last_error = Some(error.clone());
+        return Err(error);
+    }
+    if let Err(e) = std::fs::write(&bus.config.cursor_path, last.to_string()) {
+        // 未寫入的進度在重啟後重發，下游按消息 ID 去重
+        warn!(error = %e, path = %bus.config.cursor_path, "寫入事件總線 cursor 失敗");
+    }
+    status.delivered_sequence = last;
+    status.last_delivered_ms = Some(engine.env.now_ms());
+    status.last_error = None;
+    Ok(envelopes.len())
+}
+
+pub fn spawn(engine: Arc<ExecutionEngine>, publisher: Option<Box<dyn Publisher>>) {
+    let Some(mut publisher) = publisher else { return };
+    let config = engine.event_bus.config.clone();
+    info!(backend = publisher.name(), from_sequence = engine.event_bus.status.lock().unwrap().delivered_sequence, "事件總線已啟動");
+    tokio::spawn(async move {
+        loop {
+            let delay = match drain_once(&engine, publisher.as_mut()).await {
+                Ok(0) => Duration::from_millis(config.poll_interval_ms),
+                Ok(_) => continue,
+                Err(error) => {
+                    warn!(backend = publisher.name(), %error, "事件發布失敗，事件保留在日誌中稍後重發");
+                    Duration::from_secs(config.retry_secs)
+                }
+            };
+            tokio::time::sleep(delay).await;
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::deterministic_sim::{build, request};
+    use crate::{config, events, ArbitrageResponse, ChildOrder, Environment};
+
+    // Kafka REST Proxy 第一次返回 503，重發後整批寫入並推進 cursor
+    #[tokio::test]
+    async fn event_bus_redelivers_after_broker_outage() {
+        use axum::routing::post;
+        let records = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let calls = std::sync::Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let (received, attempts) = (std::sync::Arc::clone(&records), std::sync::Arc::clone(&calls));
+        let app = axum::Router::new().route(
+            "/topics/arbitrage.executions",
+            post(move |body: String| async move {
+                if attempts.fetch_add(1, std::sync::atomic::Ordering::SeqCst) == 0 {
+                    return Err(axum::http::StatusCode::SERVICE_UNAVAILABLE);
+                }
+                let body: serde_json::Value = serde_json::from_str(&body).unwrap();
+                let batch = body["records"].as_array().unwrap().clone();
+                let offsets: Vec<_> = batch.iter().map(|_| serde_json::json!({"partition": 0, "offset": 1})).collect();
+                received.lock().unwrap().extend(batch);
+                Ok(axum::Json(serde_json::json!({ "offsets": offsets })))
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await });
+
+        let cursor = std::env::temp_dir().join(format!("arb-bus-{}.cursor", std::process::id()));
+        let _ = std::fs::remove_file(&cursor);
+        let mut config = config::EngineConfig::default();
+        config.event_bus.enabled = true;
+        config.event_bus.kafka.rest_url = format!("http://{}", address);
+        config.event_bus.cursor_path = cursor.to_str().unwrap().to_string();
+        let (engine, path) = build("event-bus", config.clone(), Environment::system());
+        let mut publisher = publisher(&config.event_bus, engine.env.vars.as_ref()).unwrap();
+        let request = request(10.0);
+        engine.events.append(events::EngineEvent::RequestReceived {
+            execution_id: "e1".to_string(),
+            strategy_id: request.strategy_id.clone(),
+            strategy_type: request.strategy_type,
+            symbol: request.symbol.clone(),
+            primary_exchange: request.primary_exchange.clone(),
+            secondary_exchange: request.secondary_exchange.clone(),
+            amount: request.amount,
+        });
+        let response = ArbitrageResponse {
+            profit: Some(0.4),
+            status: "success".to_string(),
+            ..ArbitrageResponse::error("")
+        };
+        let order = ChildOrder {
+            leg: "long".to_string(),
+            exchange: "binance".to_string(),
+            symbol: "BTCUSDT".to_string(),
+            side: "buy".to_string(),
+            quantity: 10.0,
+            filled_quantity: 10.0,
+            fee: 0.1,
+            status: "filled".to_string(),
+        };
+        engine.record_execution_events("e1", &request, &response, 0.1, &[order]);
+        // 與執行無關的事件只推進 cursor
+        engine.events.append(events::EngineEvent::TradingResumed { strategy_id: None });
+
+        assert!(drain_once(&engine, publisher.as_mut()).await.unwrap_err().contains("503"));
+        assert_eq!(engine.event_bus.snapshot(engine.events.last_sequence()).pending_events, 5);
+        assert_eq!(drain_once(&engine, publisher.as_mut()).await.unwrap(), 5);
+        assert_eq!(drain_once(&engine, publisher.as_mut()).await.unwrap(), 0);
+        let records = records.lock().unwrap().clone();
+        let types: Vec<_> = records.iter().map(|record| record["value"]["type"].as_str().unwrap()).collect();
+        assert_eq!(types, ["request_received", "leg_filled", "execution_settled", "pnl_realized"]);
+        assert!(records.iter().all(|record| record["key"] == "e1"));
+        assert_eq!(records[3]["value"]["pnl"], 0.4);
+
+        // 重啟後從 cursor 繼續
+        let last = engine.events.last_sequence();
+        assert_eq!(std::fs::read_to_string(&cursor).unwrap(), last.to_string());
+        assert_eq!(EventBus::new(config.event_bus, 0).snapshot(last).delivered_sequence, last);
+        let _ = std::fs::remove_file(cursor);
+        let _ = std::fs::remove_file(path);
+    }
+}
@@ -0,0 +1,511 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufRead, BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::sync::{Arc, RwLock};
+use tracing::{error, info};
+
+use super::environment::Clock;
+use super::flash_loan::SettlementProof;
+use super::StrategyType;
+
+pub const DEFAULT_EVENT_LOG_PATH: &str = "engine_events.jsonl";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum EngineEvent {
+    // 開始執行的請求，供事件總線等下游按執行 ID 串聯
+    RequestReceived {
+        execution_id: String,
+        strategy_id: String,
+        strategy_type: StrategyType,
+        symbol: String,
+        primary_exchange: String,
+        secondary_exchange: String,
+        amount: f64,
+    },
+    OrderPlaced {
+        execution_id: String,
+        strategy_id: String,
+        leg: String,
+        exchange: String,
+        symbol: String,
+        side: String,
+        quantity: f64,
+    },
+    OrderFilled {
+        execution_id: String,
+        strategy_id: String,
+        leg: String,
+        exchange: String,
+        symbol: String,
+        side: String,
+        filled_quantity: f64,
+        fee: f64,
+    },
+    ExecutionSettled {
+        execution_id: String,
+        strategy_id: String,
+        symbol: String,
+        status: String,
+        pnl: f64,
+        fees: f64,
+    },
+    Transfer {
+        asset: String,
+        from_exchange: String,
+        to_exchange: String,
+        amount: f64,
+    },
+    ConfigChanged {
+        key: String,
+        value: serde_json::Value,
+    },
+    // 鏈上腿的結算證明，供外部審計核驗收益
+    SettlementProof {
+        execution_id: String,
+        proof: SettlementProof,
+    },
+    // 已確認的鏈上交易所在區塊被重組；reincluded_block 為 None 表示尚未重新打包
+    SettlementReorged {
+        chain: String,
+        execution_id: Option<String>,
+        tx_hash: String,
+        block_number: u64,
+        reincluded_block: Option<u64>,
+    },
+    // 交易所在結算時點實際收付的資金費，amount 為正表示收到
+    FundingPayment {
+        exchange: String,
+        symbol: String,
+        funding_time_ms: i64,
+        rate: f64,
+        position: f64,
+        amount: f64,
+    },
+    // 交易所用戶數據流回報的成交，以交易所為準的持倉與手續費
+    ExchangeFill {
+        execution_id: Option<String>,
+        exchange: String,
+        order_id: String,
+        symbol: String,
+        side: String,
+        quantity: f64,
+        price: f64,
+        fee: f64,
+    },
+    // 鏈上腿的 gas 花費，按策略類型計入每日預算
+    GasSpent {
+        execution_id: String,
+        strategy: String,
+        gas_used: u64,
+        cost_eth: f64,
+    },
+    // 觸發當日虧損或回撤限額後暫停交易；strategy_id 為 None 表示整個引擎
+    TradingHalted {
+        strategy_id: Option<String>,
+        reason: String,
+    },
+    // 管理員解除暫停，當日不再因同一限額暫停
+    TradingResumed {
+        strategy_id: Option<String>,
+    },
+    // 對賬按交易所快照修正本地持倉，quantity 為修正量
+    PositionAdjusted {
+        exchange: String,
+        symbol: String,
+        quantity: f64,
+        reason: String,
+    },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EventEnvelope {
+    pub sequence: u64,
+    pub recorded_at_ms: i64,
+    #[serde(flatten)]
+    pub event: EngineEvent,
+}
+
+// 持倉與 PnL 投影，鍵格式為 "exchange:symbol" / "exchange:asset"
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct PortfolioProjection {
+    pub as_of_sequence: u64,
+    pub as_of_ms: i64,
+    pub positions: BTreeMap<String, f64>,
+    pub realized_pnl: f64,
+    pub fees_paid: f64,
+    pub pnl_by_strategy: BTreeMap<String, f64>,
+    pub transfers: BTreeMap<String, f64>,
+    pub gas_spent_eth: BTreeMap<String, f64>,
+    pub config: BTreeMap<String, serde_json::Value>,
+    // 交易所成交回報確認的持倉與手續費，與引擎記錄的 positions / fees_paid 對照
+    pub confirmed_positions: BTreeMap<String, f64>,
+    pub confirmed_fees: f64,
+    // 各交易所/交易對累計收付的資金費
+    pub funding_pnl: BTreeMap<String, f64>,
+}
+
+impl PortfolioProjection {
+    pub fn apply(&mut self, envelope: &EventEnvelope) {
+        self.as_of_sequence = envelope.sequence;
+        self.as_of_ms = envelope.recorded_at_ms;
+        match &envelope.event {
+            // 下單不改變持倉，成交時才計入
+            EngineEvent::RequestReceived { .. } | EngineEvent::OrderPlaced { .. } => {}
+            EngineEvent::OrderFilled { exchange, symbol, side, filled_quantity, .. } => {
+                let signed = if side == "sell" { -filled_quantity } else { *filled_quantity };
+                *self.positions.entry(format!("{}:{}", exchange, symbol)).or_default() += signed;
+            }
+            EngineEvent::ExecutionSettled { strategy_id, pnl, fees, .. } => {
+                self.realized_pnl += pnl;
+                self.fees_paid += fees;
+                *self.pnl_by_strategy.entry(strategy_id.clone()).or_default() += pnl;
+            }
+            EngineEvent::Transfer { asset, from_exchange, to_exchange, amount } => {
+                *self.transfers.entry(format!("{}:{}", from_exchange, asset)).or_default() -= amount;
+                *self.transfers.entry(format!("{}:{}", to_exchange, asset)).or_default() += amount;
+            }
+            EngineEvent::ConfigChanged { key, value } => {
+                self.config.insert(key.clone(), value.clone());
+            }
+            EngineEvent::SettlementProof { .. } | EngineEvent::SettlementReorged { .. } => {}
+            EngineEvent::ExchangeFill { exchange, symbol, side, quantity, fee, .. } => {
+                let signed = if side == "sell" { -quantity } else { *quantity };
+                *self.confirmed_positions.entry(format!("{}:{}", exchange, symbol)).or_default() += signed;
+                self.confirmed_fees += fee;
+            }
+            EngineEvent::FundingPayment { exchange, symbol, amount, .. } => {
+                *self.funding_pnl.entry(format!("{}:{}", exchange, symbol)).or_default() += amount;
+            }
+            EngineEvent::GasSpent { strategy, cost_eth, .. } => {
+                *self.gas_spent_eth.entry(strategy.clone()).or_default() += cost_eth;
+            }
+            EngineEvent::TradingHalted { .. } | EngineEvent::TradingResumed { .. } => {}
+            EngineEvent::PositionAdjusted { exchange, symbol, quantity, .. } => {
+                *self.positions.entry(format!("{}:{}", exchange, symbol)).or_default() += quantity;
+            }
+        }
+    }
+}
+
+// 每隔多少個事件保存一次投影快照與文件偏移
+const CHECKPOINT_INTERVAL: u64 = 1024;
+
+// 日誌中某個事件之前的投影快照；歷史查詢從最近的快照與其文件偏移開始讀取，不在內存中保留整個日誌
+struct Checkpoint {
+    // 快照之後第一個事件的序號、記錄時間與其在文件中的起始偏移
+    sequence: u64,
+    recorded_at_ms: i64,
+    offset: u64,
+    projection: PortfolioProjection,
+}
+
+struct Inner {
+    writer: BufWriter<File>,
+    // 已寫入日誌的字節數，讀取時不越過此處，避免讀到正在追加的半行
+    offset: u64,
+    last_sequence: u64,
+    projection: PortfolioProjection,
+    checkpoints: Vec<Checkpoint>,
+    // 執行 ID -> 最近一筆結算證明的文件偏移
+    proofs: HashMap<String, u64>,
+    // 結算證明中的交易哈希 -> 執行 ID
+    tx_executions: HashMap<String, String>,
+}
+
+impl Inner {
+    // 記錄一個已寫入 offset 處的事件
+    fn index(&mut self, envelope: &EventEnvelope, offset: u64) {
+        if self.checkpoints.last().is_none_or(|c| envelope.sequence >= c.sequence + CHECKPOINT_INTERVAL) {
+            self.checkpoints.push(Checkpoint {
+                sequence: envelope.sequence,
+                recorded_at_ms: envelope.recorded_at_ms,
+                offset,
+                projection: self.projection.clone(),
+            });
+        }
+        if let EngineEvent::SettlementProof { execution_id, proof } = &envelope.event {
+            self.proofs.insert(execution_id.clone(), offset);
+            self.tx_executions.insert(proof.tx_hash.clone(), execution_id.clone());
+        }
+        self.projection.apply(envelope);
+        self.last_sequence = envelope.sequence;
+    }
+}
+
+pub struct EventStore {
+    path: String,
+    // 追加時獨佔，快照與回放並發讀取
+    inner: RwLock<Inner>,
+    // 事件記錄時間的來源
+    clock: Arc<dyn Clock>,
+}
+
+impl EventStore {
+    // 打開（或創建）日誌文件並回放全部事件重建當前投影
+    pub fn open(path: &str, clock: Arc<dyn Clock>) -> io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        let mut inner = Inner {
+            writer: BufWriter::new(file),
+            offset: 0,
+            last_sequence: 0,
+            projection: PortfolioProjection::default(),
+            checkpoints: Vec::new(),
+            proofs: HashMap::new(),
+            tx_executions: HashMap::new(),
+        };
+        let mut events = 0;
+        let mut reader = BufReader::new(File::open(path)?);
+        let mut line = String::new();
+        for index in 1.. {
+            line.clear();
+            let read = reader.read_line(&mut line)?;
+            if read == 0 {
+                break;
+            }
+            let offset = inner.offset;
+            inner.offset += read as u64;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let envelope: EventEnvelope = serde_json::from_str(&line).map_err(|e| {
+                io::Error::new(io::ErrorKind::InvalidData, format!("第 {} 行: {}", index, e))
+            })?;
+            inner.index(&envelope, offset);
+            events += 1;
+        }
+        info!(path, events, checkpoints = inner.checkpoints.len(), "事件日誌回放完成");
+
+        Ok(Self {
+            path: path.to_string(),
+            inner: RwLock::new(inner),
+            clock,
+        })
+    }
+
+    pub fn append(&self, event: EngineEvent) -> EventEnvelope {
+        let mut inner = self.inner.write().unwrap();
+        let envelope = EventEnvelope {
+            sequence: inner.last_sequence + 1,
+            recorded_at_ms: self.clock.now_ms(),
+            event,
+        };
+        let line = serde_json::to_string(&envelope).unwrap();
+        let offset = inner.offset;
+        match writeln!(inner.writer, "{}", line).and_then(|_| inner.writer.flush()) {
+            Ok(()) => inner.offset += line.len() as u64 + 1,
+            Err(e) => {
+                error!(error = %e, sequence = envelope.sequence, "寫入事件日誌失敗");
+                // 部分寫入時以文件實際長度為準
+                if let Ok(metadata) = inner.writer.get_ref().metadata() {
+                    inner.offset = metadata.len();
+                }
+            }
+        }
+        inner.index(&envelope, offset);
+        envelope
+    }
+
+    pub fn last_sequence(&self) -> u64 {
+        self.inner.read().unwrap().last_sequence
+    }
+
+    pub fn current(&self) -> PortfolioProjection {
+        self.inner.read().unwrap().projection.clone()
+    }
+
+    // 關閉前確保日誌已寫入磁盤
+    pub fn sync(&self) -> io::Result<()> {
+        let mut inner = self.inner.write().unwrap();
+        inner.writer.flush()?;
+        inner.writer.get_ref().sync_all()
+    }
+
+    // 從文件偏移 from 開始按順序讀取事件直到 visit 返回 false；不持有鎖
+    fn scan(&self, from: u64, end: u64, mut visit: impl FnMut(EventEnvelope) -> bool) {
+        let result = (|| -> io::Result<()> {
+            let mut file = File::open(&self.path)?;
+            file.seek(SeekFrom::Start(from))?;
+            for line in BufReader::new(file.take(end.saturating_sub(from))).lines() {
+                let line = line?;
+                if line.trim().is_empty() {
+                    continue;
+                }
+                let envelope: EventEnvelope =
+                    serde_json::from_str(&line).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+                if !visit(envelope) {
+                    break;
+                }
+            }
+            Ok(())
+        })();
+        if let Err(e) = result {
+            error!(path = %self.path, error = %e, "讀取事件日誌失敗");
+        }
+    }
+
+    // 滿足條件的最後一個快照的文件偏移與日誌當前末尾；記錄時間隨序號遞增
+    fn start(&self, usable: impl Fn(&Checkpoint) -> bool) -> (u64, u64, Option<PortfolioProjection>) {
+        let inner = self.inner.read().unwrap();
+        let checkpoint = inner.checkpoints.iter().rev().find(|c| usable(c));
+        (
+            checkpoint.map(|c| c.offset).unwrap_or_default(),
+            inner.offset,
+            checkpoint.map(|c| c.projection.clone()),
+        )
+    }
+
+    // 回放到指定時間點或序號（含）的歷史狀態
+    pub fn rebuild_until(&self, at_ms: Option<i64>, at_sequence: Option<u64>) -> PortfolioProjection {
+        let (from, end, projection) = self.start(|c| {
+            at_ms.is_none_or(|t| c.recorded_at_ms <= t) && at_sequence.is_none_or(|s| c.sequence <= s)
+        });
+        let mut projection = projection.unwrap_or_default();
+        self.scan(from, end, |envelope| {
+            if at_ms.is_some_and(|t| envelope.recorded_at_ms > t)
+                || at_sequence.is_some_and(|s| envelope.sequence > s)
+            {
+                return false;
+            }
+            projection.apply(&envelope);
+            true
+        });
+        projection
+    }
+
+    pub fn settlement_proof(&self, execution_id: &str) -> Option<SettlementProof> {
+        let (from, end) = {
+            let inner = self.inner.read().unwrap();
+            (*inner.proofs.get(execution_id)?, inner.offset)
+        };
+        let mut proof = None;
+        self.scan(from, end, |envelope| {
+            if let EngineEvent::SettlementProof { proof: found, .. } = envelope.event {
+                proof = Some(found);
+            }
+            false
+        });
+        proof
+    }
+
+    // 結算證明中交易哈希所屬的執行
+    pub fn execution_for_tx(&self, tx_hash: &str) -> Option<String> {
+        self.inner.read().unwrap().tx_executions.get(tx_hash).cloned()
+    }
+
+    // 指定時間點之後各策略的鏈上 gas 花費，啟動時用於恢復當日預算
+    pub fn gas_spent_since(&self, since_ms: i64) -> HashMap<String, f64> {
+        let mut spent = HashMap::new();
+        for envelope in self.since(since_ms) {
+            if let EngineEvent::GasSpent { strategy, cost_eth, .. } = envelope.event {
+                *spent.entry(strategy).or_default() += cost_eth;
+            }
+        }
+        spent
+    }
+
+    // 按記錄順序返回 since_ms 之後的事件
+    pub fn since(&self, since_ms: i64) -> Vec<EventEnvelope> {
+        // 快照之後第一個事件早於 since_ms 時，快照之前的事件都不在範圍內
+        let (from, end, _) = self.start(|c| c.recorded_at_ms < since_ms);
+        let mut events = Vec::new();
+        self.scan(from, end, |envelope| {
+            if envelope.recorded_at_ms >= since_ms {
+                events.push(envelope);
+            }
+            true
+        });
+        events
+    }
+
+    pub fn read(&self, from_sequence: u64, limit: usize) -> Vec<EventEnvelope> {
+        let (from, end, _) = self.start(|c| c.sequence <= from_sequence);
+        let mut events = Vec::new();
+        if limit == 0 {
+            return events;
+        }
+        self.scan(from, end, |envelope| {
+            if envelope.sequence >= from_sequence {
+                events.push(envelope);
+            }
+            events.len() < limit
+        });
+        events
+    }
+}
+
+// 一次執行結算後計入策略資金預算與當日限額的盈虧；成功執行的利潤已扣除手續費，失敗時計入平倉腿的手續費
+pub fn settled_pnl(status: &str, pnl: f64, fees: f64) -> f64 {
+    if status == "success" {
+        pnl
+    } else {
+        pnl - fees
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bench::START_MS;
+    use crate::{environment, flash_loan};
+    use std::time::Duration;
+
+    #[test]
+    fn event_store_pages_history_from_the_log_file() {
+        let path = std::env::temp_dir().join(format!("arb-sim-event-paging-{}.jsonl", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let clock = Arc::new(environment::SimulatedClock::new(START_MS));
+        let store = EventStore::open(&path.to_string_lossy(), clock.clone()).unwrap();
+        let proof = |n: u64| flash_loan::SettlementProof {
+            chain_id: 1,
+            provider: "aave".to_string(),
+            tx_hash: format!("0x{:064x}", n),
+            receipt: serde_json::Value::Null,
+            block_header: serde_json::Value::Null,
+            decoded_logs: Vec::new(),
+            profit_recipient: String::new(),
+            balance_before: "0".to_string(),
+            balance_after: n.to_string(),
+        };
+        // 跨越多個快照區間，每秒一個事件
+        for n in 1..=2500u64 {
+            let event = match n % 500 {
+                0 => EngineEvent::SettlementProof { execution_id: format!("e{}", n), proof: proof(n) },
+                250 => EngineEvent::GasSpent { execution_id: format!("e{}", n), strategy: "flash_loan".to_string(), gas_used: 21_000, cost_eth: 0.001 },
+                _ => EngineEvent::ExecutionSettled {
+                    execution_id: format!("e{}", n),
+                    strategy_id: "sim".to_string(),
+                    symbol: "BTCUSDT".to_string(),
+                    status: "success".to_string(),
+                    pnl: 1.0,
+                    fees: 0.0,
+                },
+            };
+            store.append(event);
+            clock.advance(Duration::from_secs(1));
+        }
+
+        for store in [store, EventStore::open(&path.to_string_lossy(), clock.clone()).unwrap()] {
+            assert_eq!(store.last_sequence(), 2500);
+            let page = store.read(1500, 3);
+            assert_eq!(page.iter().map(|e| e.sequence).collect::<Vec<_>>(), vec![1500, 1501, 1502]);
+            assert!(store.read(2501, 10).is_empty());
+            let since = store.since(START_MS + 2000 * 1000);
+            assert_eq!((since.len(), since[0].sequence), (500, 2001));
+            assert!((store.gas_spent_since(START_MS + 1000 * 1000).values().sum::<f64>() - 0.003).abs() < 1e-12);
+
+            // 快照加上其後的事件與從頭回放一致
+            let rebuilt = store.rebuild_until(None, Some(2100));
+            assert_eq!((rebuilt.as_of_sequence, rebuilt.realized_pnl), (2100, 2092.0));
+            let rebuilt = store.rebuild_until(Some(START_MS + 1024 * 1000), None);
+            assert_eq!((rebuilt.as_of_sequence, rebuilt.realized_pnl), (1025, 1021.0));
+            assert_eq!(store.current().realized_pnl, 2490.0);
+
+            assert_eq!(store.settlement_proof("e2000").unwrap().balance_after, "2000");
+            assert!(store.settlement_proof("e1999").is_none());
+            assert_eq!(store.execution_for_tx(&format!("0x{:064x}", 1500)).as_deref(), Some("e1500"));
+        }
+        let _ = std::fs::remove_file(path);
+    }
+}
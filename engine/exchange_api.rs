@@ -0,0 +1,540 @@
+use super::config::{ConnectionPoolConfig, EndpointConfig, TradingEnvironment};
+use super::secrets::Credentials;
+use super::symbols::VenueSymbols;
+use base64::Engine as _;
+use futures::{SinkExt, StreamExt};
+use hmac::Mac;
+use reqwest::header::HeaderMap;
+use reqwest::{Method, StatusCode};
+use serde_json::{json, Value};
+use std::sync::RwLock;
+use std::time::Duration;
+use tokio::net::TcpStream;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
+use tracing::debug;
+
+type Stream = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+// 等待心跳響應的上限
+const PONG_TIMEOUT: Duration = Duration::from_secs(5);
+
+pub enum ApiError {
+    // HTTP 429/418：按 Retry-After 暫停請求
+    Throttled(Duration),
+    // 5xx、超時或連接失敗，可按重試策略重試
+    Transient(String),
+    // 交易所拒絕（業務錯誤碼）或解析失敗
+    Failed(String),
+}
+
+impl From<reqwest::Error> for ApiError {
+    fn from(error: reqwest::Error) -> Self {
+        if error.is_timeout() || error.is_connect() {
+            ApiError::Transient(error.to_string())
+        } else {
+            ApiError::Failed(error.to_string())
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Venue {
+    Binance,
+    Bybit,
+    Okx,
+}
+
+/// 一個交易所的 REST/WebSocket 客戶端，路徑、簽名與響應格式按交易所區分。
+pub struct ExchangeApi {
+    exchange: String,
+    venue: Venue,
+    client: reqwest::Client,
+    ws_url: String,
+    // 內部交易對與交易所原生代碼的轉換
+    symbols: VenueSymbols,
+    // 憑證可在運行時輪換，每次簽名前讀取一份快照
+    credentials: RwLock<Credentials>,
+    // 私有數據流連接；心跳與重連由會話監督任務驅動
+    stream: tokio::sync::Mutex<Option<Stream>>,
+}
+
+impl ExchangeApi {
+    pub fn new(
+        exchange: &str,
+        endpoint: &EndpointConfig,
+        pool: &ConnectionPoolConfig,
+        environment: TradingEnvironment,
+        symbols: VenueSymbols,
+    ) -> Result<Self, String> {
+        let venue = match exchange {
+            "binance" => Venue::Binance,
+            "bybit" => Venue::Bybit,
+            "okx" => Venue::Okx,
+            _ => return Err(format!("{} 沒有 REST 接口實現", exchange)),
+        };
+        // OKX 模擬盤的 REST 請求需帶 x-simulated-trading: 1，否則按正式網處理
+        let mut headers = HeaderMap::new();
+        if venue == Venue::Okx && environment == TradingEnvironment::Testnet {
+            headers.insert("x-simulated-trading", reqwest::header::HeaderValue::from_static("1"));
+        }
+        // 同一交易所的請求共用連接池，空閒連接由保活任務定期使用，不會在 idle_timeout_secs 後被回收
+        let mut builder = reqwest::Client::builder()
+            .timeout(Duration::from_millis(endpoint.timeout_ms))
+            .default_headers(headers)
+            .pool_max_idle_per_host(pool.max_idle_per_host)
+            .pool_idle_timeout(Duration::from_secs(pool.idle_timeout_secs))
+            .tcp_keepalive(Duration::from_secs(pool.tcp_keepalive_secs))
+            .tcp_nodelay(true);
+        if pool.http2 {
+            builder = builder
+                .http2_prior_knowledge()
+                .http2_keep_alive_interval(Duration::from_secs(pool.keepalive_interval_secs))
+                .http2_keep_alive_while_idle(true);
+        }
+        let client = builder.build().map_err(|e| e.to_string())?;
+        Ok(Self {
+            exchange: exchange.to_string(),
+            venue,
+            client,
+            ws_url: endpoint.ws_url.trim_end_matches('/').to_string(),
+            symbols,
+            credentials: RwLock::new(Credentials::default()),
+            stream: tokio::sync::Mutex::new(None),
+        })
+    }
+
+    pub fn set_credentials(&self, credentials: Credentials) {
+        *self.credentials.write().unwrap() = credentials;
+    }
+
+    fn credentials(&self) -> Credentials {
+        self.credentials.read().unwrap().clone()
+    }
+
+    // 內部交易對轉為交易所原生代碼，如 OKX 的 BTCUSDT -> BTC-USDT-SWAP
+    fn native(&self, symbol: &str) -> Result<String, ApiError> {
+        self.symbols.native(symbol).map_err(ApiError::Failed)
+    }
+
+    // 返回當期資金費率與響應頭（供限頻校準）
+    pub async fn funding_rate(&self, base_url: &str, symbol: &str) -> Result<(f64, HeaderMap), ApiError> {
+        let symbol = self.native(symbol)?;
+        let (path, query) = match self.venue {
+            Venue::Binance => ("/fapi/v1/premiumIndex", format!("symbol={}", symbol)),
+            Venue::Bybit => ("/v5/market/tickers", format!("category=linear&symbol={}", symbol)),
+            Venue::Okx => ("/api/v5/public/funding-rate", format!("instId={}", symbol)),
+        };
+        let (body, headers) = self.public(base_url, path, &query).await?;
+        let rate = match self.venue {
+            Venue::Binance => &body["lastFundingRate"],
+            Venue::Bybit => &body["result"]["list"][0]["fundingRate"],
+            Venue::Okx => &body["data"][0]["fundingRate"],
+        };
+        Ok((number(rate, "fundingRate")?, headers))
+    }
+
+    pub async fn server_time(&self, base_url: &str) -> Result<i64, ApiError> {
+        let path = match self.venue {
+            Venue::Binance => "/fapi/v1/time",
+            Venue::Bybit => "/v5/market/time",
+            Venue::Okx => "/api/v5/public/time",
+        };
+        let (body, _) = self.public(base_url, path, "").await?;
+        let time_ms = match self.venue {
+            Venue::Binance => number(&body["serverTime"], "serverTime")?,
+            Venue::Bybit => number(&body["result"]["timeNano"], "timeNano")? / 1_000_000.0,
+            Venue::Okx => number(&body["data"][0]["ts"], "ts")?,
+        };
+        Ok(time_ms as i64)
+    }
+
+    // 市價下單，返回成交比例；Bybit 與 OKX 下單響應不含成交量，再查詢一次訂單。
+    // post_only_price 非空時改為該價格的 post-only 限價單，會立即吃單時被交易所拒絕。
+    // client_order_id 隨訂單提交，重啟後據此查詢崩潰前未確認的訂單
+    #[allow(clippy::too_many_arguments)]
+    pub async fn place_order(
+        &self,
+        base_url: &str,
+        symbol: &str,
+        side: &str,
+        quantity: f64,
+        post_only_price: Option<f64>,
+        client_order_id: &str,
+        timestamp_ms: i64,
+        recv_window_ms: u64,
+    ) -> Result<f64, ApiError> {
+        let symbol = &self.native(symbol)?;
+        match self.venue {
+            Venue::Binance => {
+                let order_type = match post_only_price {
+                    Some(price) => format!("LIMIT&timeInForce=GTX&price={}", price),
+                    None => "MARKET".to_string(),
+                };
+                let query = format!(
+                    "symbol={}&side={}&type={}&quantity={}&newClientOrderId={}&newOrderRespType=RESULT",
+                    symbol,
+                    side.to_uppercase(),
+                    order_type,
+                    quantity,
+                    client_order_id
+                );
+                let body = self
+                    .signed(base_url, Method::POST, "/fapi/v1/order", &query, None, timestamp_ms, recv_window_ms)
+                    .await?;
+                fill_ratio(&body["executedQty"], &body["origQty"])
+            }
+            Venue::Bybit => {
+                let mut order = json!({
+                    "category": "linear",
+                    "symbol": symbol,
+                    "side": if side == "buy" { "Buy" } else { "Sell" },
+                    "orderType": "Market",
+                    "qty": quantity.to_string(),
+                    "orderLinkId": client_order_id,
+                });
+                if let Some(price) = post_only_price {
+                    order["orderType"] = json!("Limit");
+                    order["timeInForce"] = json!("PostOnly");
+                    order["price"] = json!(price.to_string());
+                }
+                let body = self
+                    .signed(base_url, Method::POST, "/v5/order/create", "", Some(order), timestamp_ms, recv_window_ms)
+                    .await?;
+                let order_id = text(&body["result"]["orderId"], "orderId")?;
+                let query = format!("category=linear&symbol={}&orderId={}", symbol, order_id);
+                let body = self
+                    .signed(base_url, Method::GET, "/v5/order/realtime", &query, None, timestamp_ms, recv_window_ms)
+                    .await?;
+                let order = &body["result"]["list"][0];
+                fill_ratio(&order["cumExecQty"], &order["qty"])
+            }
+            Venue::Okx => {
+                let mut order = json!({
+                    "instId": symbol,
+                    "tdMode": "cross",
+                    "side": side,
+                    "ordType": "market",
+                    "sz": quantity.to_string(),
+                    "clOrdId": client_order_id,
+                });
+                if let Some(price) = post_only_price {
+                    order["ordType"] = json!("post_only");
+                    order["px"] = json!(price.to_string());
+                }
+                let body = self
+                    .signed(base_url, Method::POST, "/api/v5/trade/order", "", Some(order), timestamp_ms, recv_window_ms)
+                    .await?;
+                let order_id = text(&body["data"][0]["ordId"], "ordId")?;
+                let query = format!("instId={}&ordId={}", symbol, order_id);
+                let body = self
+                    .signed(base_url, Method::GET, "/api/v5/trade/order", &query, None, timestamp_ms, recv_window_ms)
+                    .await?;
+                let order = &body["data"][0];
+                fill_ratio(&order["accFillSz"], &order["sz"])
+            }
+        }
+    }
+
+    // 交易所因 client_order_id 重複拒絕下單：Binance -4116，Bybit 110072，OKX 51016
+    pub fn is_duplicate_order(error: &ApiError) -> bool {
+        matches!(error, ApiError::Failed(error) if ["-4116", "110072", "51016"].iter().any(|code| error.contains(code)))
+    }
+
+    // 按 client_order_id 查詢訂單的已成交數量；交易所沒有該訂單（未送達）時返回 None
+    pub async fn query_order(
+        &self,
+        base_url: &str,
+        symbol: &str,
+        client_order_id: &str,
+        timestamp_ms: i64,
+        recv_window_ms: u64,
+    ) -> Result<Option<f64>, ApiError> {
+        let symbol = self.native(symbol)?;
+        let (path, query) = match self.venue {
+            Venue::Binance => ("/fapi/v1/order", format!("symbol={}&origClientOrderId={}", symbol, client_order_id)),
+            Venue::Bybit => ("/v5/order/realtime", format!("category=linear&symbol={}&orderLinkId={}", symbol, client_order_id)),
+            Venue::Okx => ("/api/v5/trade/order", format!("instId={}&clOrdId={}", symbol, client_order_id)),
+        };
+        let body = match self.signed(base_url, Method::GET, path, &query, None, timestamp_ms, recv_window_ms).await {
+            Ok(body) => body,
+            // 訂單不存在：Binance -2013，OKX 51603
+            Err(ApiError::Failed(error)) if error.contains("-2013") || error.contains("51603") => return Ok(None),
+            Err(error) => return Err(error),
+        };
+        let filled = match self.venue {
+            Venue::Binance => &body["executedQty"],
+            Venue::Bybit => &body["result"]["list"][0]["cumExecQty"],
+            Venue::Okx => &body["data"][0]["accFillSz"],
+        };
+        if filled.is_null() {
+            return Ok(None);
+        }
+        number(filled, "成交數量").map(Some)
+    }
+
+    // 按 client_order_id 撤單；訂單不存在或已完結時返回 false
+    pub async fn cancel_order(
+        &self,
+        base_url: &str,
+        symbol: &str,
+        client_order_id: &str,
+        timestamp_ms: i64,
+        recv_window_ms: u64,
+    ) -> Result<bool, ApiError> {
+        let symbol = self.native(symbol)?;
+        let result = match self.venue {
+            Venue::Binance => {
+                let query = format!("symbol={}&origClientOrderId={}", symbol, client_order_id);
+                self.signed(base_url, Method::DELETE, "/fapi/v1/order", &query, None, timestamp_ms, recv_window_ms).await
+            }
+            Venue::Bybit => {
+                let body = json!({"category": "linear", "symbol": symbol, "orderLinkId": client_order_id});
+                self.signed(base_url, Method::POST, "/v5/order/cancel", "", Some(body), timestamp_ms, recv_window_ms).await
+            }
+            Venue::Okx => {
+                let body = json!({"instId": symbol, "clOrdId": client_order_id});
+                self.signed(base_url, Method::POST, "/api/v5/trade/cancel-order", "", Some(body), timestamp_ms, recv_window_ms).await
+            }
+        };
+        match result {
+            Ok(_) => Ok(true),
+            // 不存在或已完結：Binance -2011，Bybit 110001，OKX 51400 / 51401 / 51402
+            Err(ApiError::Failed(error)) if ["-2011", "110001", "51400", "51401", "51402"].iter().any(|code| error.contains(code)) => {
+                Ok(false)
+            }
+            Err(error) => Err(error),
+        }
+    }
+
+    // Binance 的 listen key 接口只需 API key，不簽名；其他交易所以登錄私有 WebSocket 代替 listen key
+    pub async fn listen_key(&self, base_url: &str, method: Method, listen_key: Option<&str>) -> Result<String, ApiError> {
+        if self.venue != Venue::Binance {
+            return Err(ApiError::Failed(format!("{} 不使用 listen key", self.exchange)));
+        }
+        let credentials = self.credentials();
+        let query = listen_key.map(|key| format!("listenKey={}", key)).unwrap_or_default();
+        let request = self
+            .client
+            .request(method, format!("{}/fapi/v1/listenKey?{}", base_url, query))
+            .header("X-MBX-APIKEY", &credentials.api_key);
+        let (body, _) = self.send(request).await?;
+        Ok(body["listenKey"].as_str().or(listen_key).unwrap_or_default().to_string())
+    }
+
+    // 連接私有數據流：Binance 以 listen key 作路徑，Bybit 與 OKX 連接後登錄並訂閱訂單推送
+    pub async fn open_stream(&self, listen_key: Option<&str>, timestamp_ms: i64) -> Result<(), String> {
+        let url = match (self.venue, listen_key) {
+            (Venue::Binance, Some(key)) => format!("{}/ws/{}", self.ws_url, key),
+            (Venue::Binance, None) => return Err("Binance 私有數據流需要 listen key".to_string()),
+            (Venue::Bybit, _) => format!("{}/v5/private", self.ws_url),
+            (Venue::Okx, _) => format!("{}/ws/v5/private", self.ws_url),
+        };
+        let (mut stream, _) = tokio_tungstenite::connect_async(url.as_str())
+            .await
+            .map_err(|e| format!("{} WebSocket 握手失敗: {}", self.exchange, e))?;
+        let credentials = self.credentials();
+        let handshake = match self.venue {
+            Venue::Binance => vec![],
+            Venue::Bybit => {
+                let expires = timestamp_ms + 10_000;
+                let signature = hex::encode(hmac(&credentials.secret_key, &format!("GET/realtime{}", expires)));
+                vec![
+                    json!({"op": "auth", "args": [credentials.api_key, expires, signature]}),
+                    json!({"op": "subscribe", "args": ["order", "position", "wallet"]}),
+                ]
+            }
+            Venue::Okx => {
+                let timestamp = (timestamp_ms / 1_000).to_string();
+                let signature = base64::engine::general_purpose::STANDARD
+                    .encode(hmac(&credentials.secret_key, &format!("{}GET/users/self/verify", timestamp)));
+                vec![
+                    json!({"op": "login", "args": [{
+                        "apiKey": credentials.api_key,
+                        "passphrase": credentials.passphrase,
+                        "timestamp": timestamp,
+                        "sign": signature,
+                    }]}),
+                    json!({"op": "subscribe", "args": [{"channel": "orders", "instType": "SWAP"}]}),
+                ]
+            }
+        };
+        for message in handshake {
+            stream
+                .send(Message::text(message.to_string()))
+                .await
+                .map_err(|e| format!("{} 私有數據流登錄失敗: {}", self.exchange, e))?;
+        }
+        *self.stream.lock().await = Some(stream);
+        Ok(())
+    }
+
+    // 發送心跳並等待響應，其間收到的推送只記錄日誌；連接已關閉或超時未響應時返回錯誤並丟棄連接
+    pub async fn ping(&self) -> Result<(), String> {
+        let mut guard = self.stream.lock().await;
+        let stream = guard.as_mut().ok_or_else(|| format!("{} 私有數據流未連接", self.exchange))?;
+        let result = tokio::time::timeout(PONG_TIMEOUT, async {
+            let ping = match self.venue {
+                Venue::Binance => Message::Ping(Vec::new()),
+                Venue::Bybit => Message::text(json!({"op": "ping"}).to_string()),
+                Venue::Okx => Message::text("ping"),
+            };
+            stream.send(ping).await.map_err(|e| e.to_string())?;
+            while let Some(message) = stream.next().await {
+                match message.map_err(|e| e.to_string())? {
+                    Message::Pong(_) if self.venue == Venue::Binance => return Ok(()),
+                    Message::Text(text) if self.is_pong(&text) => return Ok(()),
+                    Message::Text(text) => debug!(exchange = %self.exchange, %text, "私有數據流推送"),
+                    Message::Close(frame) => return Err(format!("連接已關閉: {:?}", frame)),
+                    _ => {}
+                }
+            }
+            Err("連接已關閉".to_string())
+        })
+        .await
+        .unwrap_or_else(|_| Err("心跳超時".to_string()));
+        if let Err(error) = result {
+            *guard = None;
+            return Err(format!("{} {}", self.exchange, error));
+        }
+        Ok(())
+    }
+
+    pub async fn close_stream(&self) {
+        if let Some(mut stream) = self.stream.lock().await.take() {
+            let _ = stream.close(None).await;
+        }
+    }
+
+    fn is_pong(&self, text: &str) -> bool {
+        match self.venue {
+            Venue::Okx => text == "pong",
+            _ => serde_json::from_str::<Value>(text).is_ok_and(|body| body["op"] == "pong"),
+        }
+    }
+
+    async fn public(&self, base_url: &str, path: &str, query: &str) -> Result<(Value, HeaderMap), ApiError> {
+        self.send(self.client.get(format!("{}{}?{}", base_url, path, query))).await
+    }
+
+    // 簽名請求：Binance 簽查詢串，Bybit 簽 時間戳+key+窗口+參數，OKX 簽 ISO 時間+方法+路徑+正文（base64）
+    #[allow(clippy::too_many_arguments)]
+    async fn signed(
+        &self,
+        base_url: &str,
+        method: Method,
+        path: &str,
+        query: &str,
+        body: Option<Value>,
+        timestamp_ms: i64,
+        recv_window_ms: u64,
+    ) -> Result<Value, ApiError> {
+        let credentials = self.credentials();
+        let body = body.map(|body| body.to_string()).unwrap_or_default();
+        let request = match self.venue {
+            Venue::Binance => {
+                let separator = if query.is_empty() { "" } else { "&" };
+                let payload = format!("{}{}timestamp={}&recvWindow={}", query, separator, timestamp_ms, recv_window_ms);
+                let signature = hex::encode(hmac(&credentials.secret_key, &payload));
+                self.client
+                    .request(method, format!("{}{}?{}&signature={}", base_url, path, payload, signature))
+                    .header("X-MBX-APIKEY", &credentials.api_key)
+            }
+            Venue::Bybit => {
+                let params = if method == Method::GET { query } else { body.as_str() };
+                let signature =
+                    hex::encode(hmac(&credentials.secret_key, &format!("{}{}{}{}", timestamp_ms, credentials.api_key, recv_window_ms, params)));
+                self.client
+                    .request(method, format!("{}{}?{}", base_url, path, query))
+                    .header("X-BAPI-API-KEY", &credentials.api_key)
+                    .header("X-BAPI-TIMESTAMP", timestamp_ms.to_string())
+                    .header("X-BAPI-RECV-WINDOW", recv_window_ms.to_string())
+                    .header("X-BAPI-SIGN", signature)
+                    .header("Content-Type", "application/json")
+                    .body(body)
+            }
+            Venue::Okx => {
+                let timestamp = chrono::DateTime::from_timestamp_millis(timestamp_ms)
+                    .unwrap_or_default()
+                    .format("%Y-%m-%dT%H:%M:%S%.3fZ")
+                    .to_string();
+                let request_path = if query.is_empty() { path.to_string() } else { format!("{}?{}", path, query) };
+                let signature = base64::engine::general_purpose::STANDARD
+                    .encode(hmac(&credentials.secret_key, &format!("{}{}{}{}", timestamp, method, request_path, body)));
+                self.client
+                    .request(method, format!("{}{}", base_url, request_path))
+                    .header("OK-ACCESS-KEY", &credentials.api_key)
+                    .header("OK-ACCESS-SIGN", signature)
+                    .header("OK-ACCESS-TIMESTAMP", timestamp)
+                    .header("OK-ACCESS-PASSPHRASE", &credentials.passphrase)
+                    .header("Content-Type", "application/json")
+                    .body(body)
+            }
+        };
+        self.send(request).await.map(|(body, _)| body)
+    }
+
+    // 發送請求並按交易所的錯誤格式檢查響應：Binance 以 HTTP 狀態與 code/msg，Bybit 以 retCode，OKX 以 code 與 sCode
+    async fn send(&self, request: reqwest::RequestBuilder) -> Result<(Value, HeaderMap), ApiError> {
+        let response = request.send().await?;
+        let status = response.status();
+        let headers = response.headers().clone();
+        if status == StatusCode::TOO_MANY_REQUESTS || status.as_u16() == 418 {
+            let retry_after = headers
+                .get(reqwest::header::RETRY_AFTER)
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| value.parse::<u64>().ok())
+                .unwrap_or(1);
+            return Err(ApiError::Throttled(Duration::from_secs(retry_after)));
+        }
+        if status.is_server_error() {
+            return Err(ApiError::Transient(format!("{} 返回 {}", self.exchange, status)));
+        }
+        let body: Value = response.json().await?;
+        let error = match self.venue {
+            Venue::Binance if !status.is_success() => Some(format!("{} {}", body["code"], body["msg"])),
+            Venue::Bybit if body["retCode"] != 0 => Some(format!("{} {}", body["retCode"], body["retMsg"])),
+            Venue::Okx if body["code"] != "0" => {
+                let detail = &body["data"][0];
+                Some(format!("{} {}", detail["sCode"].as_str().unwrap_or(""), detail["sMsg"].as_str().or(body["msg"].as_str()).unwrap_or("")))
+            }
+            _ if !status.is_success() => Some(status.to_string()),
+            _ => None,
+        };
+        match error {
+            Some(error) => Err(ApiError::Failed(format!("{} 拒絕請求: {}", self.exchange, error))),
+            None => Ok((body, headers)),
+        }
+    }
+}
+
+fn hmac(secret: &str, payload: &str) -> Vec<u8> {
+    let mut mac = hmac::Hmac::<sha2::Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC 接受任意長度密鑰");
+    mac.update(payload.as_bytes());
+    mac.finalize().into_bytes().to_vec()
+}
+
+
+// 交易所以字符串或數字返回數值
+fn number(value: &Value, field: &str) -> Result<f64, ApiError> {
+    value
+        .as_f64()
+        .or_else(|| value.as_str().and_then(|text| text.parse().ok()))
+        .ok_or_else(|| ApiError::Failed(format!("響應缺少 {}", field)))
+}
+
+fn text(value: &Value, field: &str) -> Result<String, ApiError> {
+    match value {
+        Value::String(text) => Ok(text.clone()),
+        Value::Number(number) => Ok(number.to_string()),
+        _ => Err(ApiError::Failed(format!("響應缺少 {}", field))),
+    }
+}
+
+fn fill_ratio(filled: &Value, quantity: &Value) -> Result<f64, ApiError> {
+    let quantity = number(quantity, "數量")?;
+    if quantity <= 0.0 {
+        return Err(ApiError::Failed("訂單數量為 0".to_string()));
+    }
+    Ok((number(filled, "成交數量")? / quantity).clamp(0.0, 1.0))
+}
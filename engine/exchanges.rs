@@ -0,0 +1,785 @@
+use crate::environment::Environment;
+use crate::exchange_api::{ApiError, ExchangeApi};
+use crate::symbols::VenueSymbols;
+use crate::{
+    config, funding_history, gateways, funding_model, funding_settlement, liquidation, maker, market_data,
+    rate_limit, retry, secrets, time_sync, user_stream, ChildOrder, LeverageSetting, MarginMode,
+};
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::sync::{Mutex, RwLock};
+use std::time::Duration;
+use tracing::{debug, info, warn};
+
+pub struct ExchangeConnector {
+    pub(crate) name: String,
+    // 默認端點與 gateways.rest_urls 中的候選端點，請求發往當前選中的端點
+    pub(crate) gateways: gateways::GatewaySelector,
+    // 由 secrets 模塊在啟動時寫入並按輪換刷新，不落盤
+    pub(crate) credentials: RwLock<secrets::Credentials>,
+    pub(crate) settings: config::ExchangeConfig,
+    // 吃單費率可熱加載，以此為準而非 settings.taker_fee
+    pub(crate) taker_fee: RwLock<f64>,
+    pub(crate) scheduler: rate_limit::RequestScheduler,
+    // 模擬交易所側賬戶，成交後經用戶數據流推送更新
+    pub(crate) account: Mutex<SimulatedAccount>,
+    pub(crate) user_events: tokio::sync::broadcast::Sender<user_stream::UserEvent>,
+    // 按時鐘同步結果校正的交易所時間
+    pub(crate) clock: time_sync::ExchangeClock,
+    // 模擬交易所服務器時鐘相對本機的偏差
+    pub(crate) server_skew_ms: i64,
+    // 本連接器的時鐘與獨立隨機數流
+    pub(crate) env: Environment,
+    // 配置了 endpoint 時經 REST/WebSocket 訪問交易所，否則在進程內模擬
+    pub(crate) api: Option<ExchangeApi>,
+}
+
+#[derive(Default)]
+pub(crate) struct SimulatedAccount {
+    pub(crate) positions: BTreeMap<String, f64>,
+    pub(crate) balances: BTreeMap<String, user_stream::Balance>,
+    pub(crate) next_order_id: u64,
+    pub(crate) leverage: BTreeMap<String, LeverageSetting>,
+    pub(crate) perps: BTreeMap<String, PerpPosition>,
+    // 已結算到的資金費時間與最近的資金費記錄
+    pub(crate) funding_settled_ms: Option<i64>,
+    pub(crate) funding_payments: VecDeque<funding_settlement::FundingPayment>,
+}
+
+// 模擬交易所保留的資金費記錄數
+pub(crate) const FUNDING_LEDGER_SIZE: usize = 1000;
+
+// 永續持倉的開倉均價與佔用保證金
+#[derive(Default)]
+pub(crate) struct PerpPosition {
+    pub(crate) entry_price: f64,
+    pub(crate) margin: f64,
+}
+
+impl ExchangeConnector {
+    pub(crate) fn new(
+        name: &str,
+        base_url: &str,
+        settings: config::ExchangeConfig,
+        balances: Option<&HashMap<String, f64>>,
+        env: Environment,
+    ) -> Result<Self, String> {
+        // 子賬戶連接器的 name 為 binance@sub1 形式，接口、端點與模擬行為按所屬交易所
+        let venue = config::venue(name);
+        let endpoint = settings.resolved_endpoint(venue);
+        let api = endpoint
+            .as_ref()
+            .map(|endpoint| {
+                let symbols = VenueSymbols::new(venue, &settings.symbols)?;
+                ExchangeApi::new(venue, endpoint, &settings.connection_pool, settings.environment, symbols)
+            })
+            .transpose()?;
+        let base_url = endpoint.as_ref().map_or(base_url, |endpoint| endpoint.rest_url.as_str());
+        if let Some(endpoint) = &endpoint {
+            info!(exchange = %name, environment = ?settings.environment, rest_url = %endpoint.rest_url, "連接交易所端點");
+        }
+        let mut balances: BTreeMap<String, user_stream::Balance> = balances
+            .into_iter()
+            .flatten()
+            .map(|(asset, free)| (asset.to_uppercase(), user_stream::Balance { free: *free, locked: 0.0 }))
+            .collect();
+        if api.is_none() {
+            balances.entry("USDT".to_string()).or_insert(user_stream::Balance {
+                free: settings.simulation.collateral_usdt,
+                locked: 0.0,
+            });
+        }
+        Ok(Self {
+            name: name.to_string(),
+            gateways: gateways::GatewaySelector::new(base_url, &settings.gateways),
+            credentials: RwLock::new(secrets::Credentials::default()),
+            scheduler: rate_limit::RequestScheduler::new(name, settings.rate_limit.clone()),
+            taker_fee: RwLock::new(settings.taker_fee),
+            settings,
+            account: Mutex::new(SimulatedAccount {
+                balances,
+                ..SimulatedAccount::default()
+            }),
+            user_events: tokio::sync::broadcast::channel(1024).0,
+            clock: time_sync::ExchangeClock::new(std::sync::Arc::clone(&env.clock)),
+            server_skew_ms: match venue {
+                "bybit" => -220,
+                "okx" => 1_800,
+                _ => 35,
+            },
+            env,
+            api,
+        })
+    }
+    
+    // 新憑證用於之後的簽名請求；已登錄的私有數據流在下次重連時使用
+    pub(crate) fn set_credentials(&self, credentials: secrets::Credentials) {
+        if let Some(api) = &self.api {
+            api.set_credentials(credentials.clone());
+        }
+        *self.credentials.write().unwrap() = credentials;
+    }
+    
+    // REST 請求失敗轉為錯誤信息；429 時按 Retry-After 暫停本交易所的請求
+    fn api_result<T>(&self, result: Result<T, ApiError>) -> Result<T, String> {
+        result.map_err(|error| match error {
+            ApiError::Throttled(retry_after) => {
+                self.scheduler.throttled(retry_after);
+                format!("{} 限頻（429），{} 秒後重試", self.name, retry_after.as_secs())
+            }
+            // 經重試仍無法連通時記入當前端點，連續失敗後切換到其他健康端點
+            ApiError::Transient(error) => {
+                if let Some((from, to)) = self.gateways.record_failure(&error) {
+                    warn!(exchange = %self.name, %from, %to, "REST 端點連續失敗，切換到其他健康端點");
+                }
+                error
+            }
+            ApiError::Failed(error) => error,
+        })
+    }
+    
+    // 當前選中的 REST 端點
+    pub(crate) fn base_url(&self) -> String {
+        self.gateways.current()
+    }
+    
+    // 模擬交易所側的服務器時間
+    pub(crate) fn server_time_ms(&self) -> i64 {
+        self.env.now_ms() + self.server_skew_ms
+    }
+    
+    // 模擬 GET /api/v3/time：往返數毫秒
+    pub(crate) async fn fetch_server_time(&self) -> Result<i64, String> {
+        self.scheduler.acquire(rate_limit::RequestKind::MarketData, 1).await?;
+        if let Some(api) = &self.api {
+            let result = retry::with_retry(&self.settings.retry.market_data, &self.env, &self.name, "server_time", |_| async move {
+                api.server_time(&self.base_url()).await
+            })
+            .await;
+            return self.api_result(result);
+        }
+        self.env.clock.sleep(Duration::from_micros(500 + self.env.rng.next_u64() % 2_500)).await;
+        let server_ms = self.server_time_ms();
+        self.env.clock.sleep(Duration::from_micros(500 + self.env.rng.next_u64() % 2_500)).await;
+        Ok(server_ms)
+    }
+    
+    // 簽名請求：時間戳取校正後的交易所時間並附上 recvWindow，HMAC-SHA256 簽名查詢串。
+    // 模擬交易所側校驗：時間戳超前服務器 1 秒以上或落後超過 recvWindow 時拒絕（Binance -1021），並觸發重新同步
+    pub(crate) fn sign_request(&self, endpoint: &str, params: &str) -> Result<(), String> {
+        use hmac::Mac;
+        let timestamp_ms = self.clock.now_ms();
+        let recv_window_ms = self.settings.recv_window_ms;
+        let separator = if params.is_empty() { "" } else { "&" };
+        let payload = format!("{}{}timestamp={}&recvWindow={}", params, separator, timestamp_ms, recv_window_ms);
+        let mut mac = hmac::Hmac::<sha2::Sha256>::new_from_slice(self.credentials.read().unwrap().secret_key.as_bytes()).expect("HMAC 接受任意長度密鑰");
+        mac.update(payload.as_bytes());
+        let query = format!("{}&signature={}", payload, hex::encode(mac.finalize().into_bytes()));
+        debug!(exchange = %self.name, %endpoint, %query, "簽名請求");
+        let server_ms = self.server_time_ms();
+        if timestamp_ms > server_ms + 1_000 || server_ms - timestamp_ms > recv_window_ms as i64 {
+            self.clock.request_resync();
+            return Err(format!(
+                "{} 拒絕 {}：時間戳 {} 超出 recvWindow（交易所時間 {}）",
+                self.name, endpoint, timestamp_ms, server_ms
+            ));
+        }
+        Ok(())
+    }
+    
+    // 模擬交易所成交回報：按參考價成交，更新交易所側持倉與報價資產餘額（扣除手續費；
+    // 永續腿開倉部分按槓桿凍結保證金，平倉部分按比例釋放），並向用戶數據流推送訂單、持倉與餘額更新
+    pub(crate) fn simulate_fill(&self, execution_id: &str, order: &ChildOrder, leverage: Option<f64>) {
+        if order.filled_quantity <= 0.0 {
+            return;
+        }
+        let (base, quote) = market_data::split_symbol(&order.symbol).unwrap_or_default();
+        let price = market_data::reference_price(&base)
+            .zip(market_data::reference_price(&quote))
+            .map(|(base, quote)| base / quote)
+            .unwrap_or_default();
+        let event_time_ms = self.env.now_ms();
+        let signed = if order.side == "sell" { -order.filled_quantity } else { order.filled_quantity };
+        let mut account = self.account.lock().unwrap();
+        account.next_order_id += 1;
+        let order_id = format!("{}-{}", self.name, account.next_order_id);
+        let previous = account.positions.get(&order.symbol).copied().unwrap_or_default();
+        let position = previous + signed;
+        account.positions.insert(order.symbol.clone(), position);
+        let margin_change = match leverage {
+            Some(leverage) => {
+                let (closed, opened) = if previous * position < 0.0 {
+                    (previous.abs(), position.abs())
+                } else {
+                    ((previous.abs() - position.abs()).max(0.0), (position.abs() - previous.abs()).max(0.0))
+                };
+                let perp = account.perps.entry(order.symbol.clone()).or_default();
+                let released = if previous != 0.0 { perp.margin * closed / previous.abs() } else { 0.0 };
+                let kept = position.abs() - opened;
+                if opened > 0.0 {
+                    perp.entry_price = (perp.entry_price * kept + price * opened) / position.abs();
+                }
+                perp.margin += opened / leverage - released;
+                if position == 0.0 {
+                    account.perps.remove(&order.symbol);
+                }
+                opened / leverage - released
+            }
+            None => 0.0,
+        };
+        let balance = account.balances.entry(quote.clone()).or_default();
+        balance.free -= order.fee + margin_change;
+        balance.locked += margin_change;
+        let balance = *balance;
+        drop(account);
+        // 沒有訂閱者（連接中斷）時推送丟失，重連後靠 REST 快照對賬
+        for event in [
+            user_stream::UserEvent::Order {
+                order_id,
+                client_order_id: format!("{}/{}", execution_id, order.leg),
+                symbol: order.symbol.clone(),
+                side: order.side.clone(),
+                status: "filled".to_string(),
+                quantity: order.quantity,
+                filled_quantity: order.filled_quantity,
+                last_fill_quantity: order.filled_quantity,
+                last_fill_price: price,
+                fee: order.fee,
+                event_time_ms,
+            },
+            user_stream::UserEvent::Position {
+                symbol: order.symbol.clone(),
+                quantity: position,
+                event_time_ms,
+            },
+            user_stream::UserEvent::Balance {
+                asset: quote,
+                free: balance.free,
+                locked: balance.locked,
+                event_time_ms,
+            },
+        ] {
+            let _ = self.user_events.send(event);
+        }
+    }
+    
+    // 模擬查詢交易對當前槓桿與保證金模式；從未設置過時返回 None
+    pub(crate) async fn fetch_leverage(&self, symbol: &str) -> Result<Option<LeverageSetting>, String> {
+        self.scheduler
+            .acquire(rate_limit::RequestKind::MarketData, self.scheduler.market_data_weight())
+            .await?;
+        self.sign_request("GET /fapi/v1/leverage", &format!("symbol={}", symbol))?;
+        Ok(self.account.lock().unwrap().leverage.get(symbol).copied())
+    }
+    
+    // 模擬設置槓桿與保證金模式：超過交易所上限或持倉未平時切換模式會被拒絕
+    pub(crate) async fn set_leverage(&self, symbol: &str, setting: LeverageSetting) -> Result<(), String> {
+        self.scheduler
+            .acquire(rate_limit::RequestKind::Order, self.settings.rate_limit.order_weight)
+            .await?;
+        self.sign_request("POST /fapi/v1/leverage", &format!("symbol={}&leverage={}", symbol, setting.leverage))?;
+        if setting.leverage > self.settings.max_leverage {
+            return Err(format!("{} {} 最高槓桿為 {}", self.name, symbol, self.settings.max_leverage));
+        }
+        let mut account = self.account.lock().unwrap();
+        let current = account.leverage.get(symbol).map(|current| current.margin_mode).unwrap_or_default();
+        let position = account.positions.get(symbol).copied().unwrap_or_default();
+        if current != setting.margin_mode && position != 0.0 {
+            return Err(format!("{} {} 持倉未平，無法切換保證金模式", self.name, symbol));
+        }
+        account.leverage.insert(symbol.to_string(), setting);
+        Ok(())
+    }
+    
+    // 模擬永續持倉風險查詢：標記價取永續盤口中間價，強平價按持倉保證金（全倉時加上報價資產可用餘額）計算
+    pub(crate) async fn fetch_position_risk(&self) -> Result<Vec<liquidation::PositionRisk>, String> {
+        self.scheduler
+            .acquire(rate_limit::RequestKind::MarketData, self.scheduler.market_data_weight())
+            .await?;
+        self.sign_request("GET /fapi/v2/positionRisk", "")?;
+        let account = self.account.lock().unwrap();
+        let mut risks = Vec::new();
+        for (symbol, perp) in &account.perps {
+            let quantity = account.positions.get(symbol).copied().unwrap_or_default();
+            if quantity == 0.0 {
+                continue;
+            }
+            let (base, quote) = market_data::split_symbol(symbol).ok_or_else(|| format!("無法解析交易對: {}", symbol))?;
+            let mark_price = market_data::simulated_perp_book(self.env.rng.as_ref(), &self.name, &base, &quote)?
+                .mid()
+                .ok_or_else(|| format!("{} 無標記價", symbol))?;
+            let setting = account.leverage.get(symbol).copied().unwrap_or(LeverageSetting {
+                leverage: 1.0,
+                margin_mode: MarginMode::Cross,
+            });
+            let available = match setting.margin_mode {
+                MarginMode::Cross => account.balances.get(&quote).map(|balance| balance.free.max(0.0)).unwrap_or_default(),
+                MarginMode::Isolated => 0.0,
+            };
+            let maintenance_margin_rate = self.settings.maintenance_margin_rate;
+            risks.push(liquidation::PositionRisk {
+                symbol: symbol.clone(),
+                quantity,
+                entry_price: perp.entry_price,
+                mark_price,
+                leverage: setting.leverage,
+                margin_mode: setting.margin_mode,
+                margin: perp.margin,
+                maintenance_margin_rate,
+                liquidation_price: liquidation::PositionRisk::liquidation_price(
+                    quantity,
+                    perp.entry_price,
+                    perp.margin + available,
+                    maintenance_margin_rate,
+                ),
+            });
+        }
+        Ok(risks)
+    }
+    
+    // 模擬為逐倉持倉追加保證金，從報價資產可用餘額劃轉
+    pub(crate) async fn add_isolated_margin(&self, symbol: &str, amount: f64) -> Result<(), String> {
+        self.scheduler
+            .acquire(rate_limit::RequestKind::Order, self.settings.rate_limit.order_weight)
+            .await?;
+        self.sign_request("POST /fapi/v1/positionMargin", &format!("symbol={}&amount={}&type=1", symbol, amount))?;
+        let (_, quote) = market_data::split_symbol(symbol).ok_or_else(|| format!("無法解析交易對: {}", symbol))?;
+        let mut account = self.account.lock().unwrap();
+        if account.leverage.get(symbol).map(|setting| setting.margin_mode) != Some(MarginMode::Isolated) {
+            return Err(format!("{} {} 不是逐倉持倉", self.name, symbol));
+        }
+        let balance = account.balances.entry(quote.clone()).or_default();
+        if !(amount > 0.0 && amount <= balance.free) {
+            return Err(format!("{} {} 可用餘額不足以追加保證金 {:.2}", self.name, quote, amount));
+        }
+        balance.free -= amount;
+        balance.locked += amount;
+        let balance = *balance;
+        account.perps.entry(symbol.to_string()).or_default().margin += amount;
+        drop(account);
+        let _ = self.user_events.send(user_stream::UserEvent::Balance {
+            asset: quote,
+            free: balance.free,
+            locked: balance.locked,
+            event_time_ms: self.env.now_ms(),
+        });
+        Ok(())
+    }
+    
+    // 模擬同一交易所賬戶之間的內部劃轉：從本賬戶可用餘額扣除，到賬由調用方記入目標賬戶
+    pub(crate) async fn internal_transfer(&self, asset: &str, amount: f64, to_account: &str) -> Result<(), String> {
+        self.scheduler
+            .acquire(rate_limit::RequestKind::Order, self.settings.rate_limit.order_weight)
+            .await?;
+        self.sign_request(
+            "POST /sapi/v1/sub-account/universalTransfer",
+            &format!("asset={}&amount={}&toAccount={}", asset, amount, to_account),
+        )?;
+        self.adjust_balance(asset, -amount)
+    }
+    
+    // 模擬提幣到白名單地址：從可用餘額扣除，返回交易所的提幣 ID
+    pub(crate) async fn withdraw(&self, asset: &str, amount: f64, address: &config::WithdrawalAddress) -> Result<String, String> {
+        self.scheduler
+            .acquire(rate_limit::RequestKind::Order, self.settings.rate_limit.order_weight)
+            .await?;
+        self.sign_request(
+            "POST /sapi/v1/capital/withdraw/apply",
+            &format!("coin={}&amount={}&address={}&network={}", asset, amount, address.address, address.network),
+        )?;
+        self.adjust_balance(asset, -amount)?;
+        let mut account = self.account.lock().unwrap();
+        account.next_order_id += 1;
+        Ok(format!("{}-withdraw-{}", self.name, account.next_order_id))
+    }
+    
+    // 模擬劃轉或充值到賬
+    pub(crate) fn credit(&self, asset: &str, amount: f64) -> Result<(), String> {
+        self.adjust_balance(asset, amount)
+    }
+    
+    // 調整可用餘額並推送餘額更新；扣減超過可用餘額時不變更
+    fn adjust_balance(&self, asset: &str, delta: f64) -> Result<(), String> {
+        let mut account = self.account.lock().unwrap();
+        let balance = account.balances.entry(asset.to_string()).or_default();
+        if balance.free + delta < 0.0 {
+            return Err(format!("{} {} 可用餘額不足: 可用 {:.2}，需要 {:.2}", self.name, asset, balance.free, -delta));
+        }
+        balance.free += delta;
+        let balance = *balance;
+        drop(account);
+        let _ = self.user_events.send(user_stream::UserEvent::Balance {
+            asset: asset.to_string(),
+            free: balance.free,
+            locked: balance.locked,
+            event_time_ms: self.env.now_ms(),
+        });
+        Ok(())
+    }
+    
+    pub(crate) fn position(&self, symbol: &str) -> f64 {
+        self.account.lock().unwrap().positions.get(symbol).copied().unwrap_or_default()
+    }
+    
+    pub(crate) fn current_leverage(&self, symbol: &str) -> Option<f64> {
+        self.account.lock().unwrap().leverage.get(symbol).map(|setting| setting.leverage)
+    }
+    
+    // 模擬資金費記錄查詢：交易所側在每個結算時點按當時持倉與費率收付資金費（多頭在正費率時支付），
+    // 計入報價資產餘額並推送餘額更新；返回 since_ms 之後的記錄
+    pub(crate) async fn fetch_funding_payments(&self, since_ms: i64) -> Result<Vec<funding_settlement::FundingPayment>, String> {
+        self.scheduler
+            .acquire(rate_limit::RequestKind::MarketData, self.scheduler.market_data_weight())
+            .await?;
+        self.sign_request("GET /fapi/v1/income", &format!("incomeType=FUNDING_FEE&startTime={}", since_ms))?;
+        let now_ms = self.server_time_ms();
+        let interval_hours = self.settings.funding_interval_hours;
+        let (settled_ms, due, positions) = {
+            let mut account = self.account.lock().unwrap();
+            let settled_ms = *account.funding_settled_ms.get_or_insert(now_ms);
+            let mut due = Vec::new();
+            let mut funding_time_ms = funding_history::next_funding_ms(settled_ms, interval_hours);
+            while funding_time_ms <= now_ms {
+                due.push(funding_time_ms);
+                funding_time_ms += interval_hours as i64 * 3_600_000;
+            }
+            let positions: Vec<(String, f64)> = account
+                .perps
+                .keys()
+                .filter_map(|symbol| account.positions.get(symbol).map(|quantity| (symbol.clone(), *quantity)))
+                .filter(|(_, quantity)| *quantity != 0.0)
+                .collect();
+            (settled_ms, due, positions)
+        };
+        let mut settled = Vec::new();
+        for funding_time_ms in due {
+            for (symbol, position) in &positions {
+                let rate = self.fetch_funding_rate(&self.base_url(), symbol).await?;
+                settled.push(funding_settlement::FundingPayment {
+                    symbol: symbol.clone(),
+                    funding_time_ms,
+                    rate,
+                    position: *position,
+                    amount: -position * rate,
+                });
+            }
+        }
+        let mut account = self.account.lock().unwrap();
+        // 併發查詢已完成這批結算時不重複入賬
+        if account.funding_settled_ms == Some(settled_ms) {
+            account.funding_settled_ms = Some(now_ms);
+            for payment in settled {
+                let (_, quote) = market_data::split_symbol(&payment.symbol).unwrap_or_default();
+                let balance = account.balances.entry(quote.clone()).or_default();
+                balance.free += payment.amount;
+                let _ = self.user_events.send(user_stream::UserEvent::Balance {
+                    asset: quote,
+                    free: balance.free,
+                    locked: balance.locked,
+                    event_time_ms: payment.funding_time_ms,
+                });
+                if account.funding_payments.len() == FUNDING_LEDGER_SIZE {
+                    account.funding_payments.pop_front();
+                }
+                account.funding_payments.push_back(payment);
+            }
+        }
+        Ok(account
+            .funding_payments
+            .iter()
+            .filter(|payment| payment.funding_time_ms > since_ms)
+            .cloned()
+            .collect())
+    }
+    
+    // 模擬溢價指數查詢：指數價取參考價，永續盤口溢價在交易所各自的中樞附近波動，
+    // 按 impact_notional 吃單得到 impact bid / ask
+    pub(crate) async fn fetch_premium_index(&self, symbol: &str) -> Result<funding_model::PremiumIndex, String> {
+        self.scheduler
+            .acquire(rate_limit::RequestKind::MarketData, self.scheduler.market_data_weight())
+            .await?;
+        let (base, quote) = market_data::split_symbol(symbol).ok_or_else(|| format!("無法解析交易對: {}", symbol))?;
+        let index_price = market_data::reference_price(&base)
+            .zip(market_data::reference_price(&quote))
+            .map(|(base, quote)| base / quote)
+            .ok_or_else(|| format!("{} 無指數價", symbol))?;
+        let center = match config::venue(&self.name) {
+            "bybit" => 0.0008,
+            "okx" => 0.0009,
+            _ => 0.0007,
+        };
+        let book = market_data::simulated_premium_book(&base, &quote, center + (self.env.rng.next_f64() - 0.5) * 0.0004)?;
+        let impact_notional = self.settings.funding_model.impact_notional;
+        Ok(funding_model::PremiumIndex {
+            index_price,
+            impact_bid: book.sell_base(impact_notional / index_price).average_price(),
+            impact_ask: book.buy_with_quote(impact_notional).average_price(),
+            sampled_at_ms: self.env.now_ms(),
+        })
+    }
+    
+    // 模擬交易所公佈的下一期預測費率：在當前費率附近波動
+    pub(crate) async fn fetch_predicted_funding_rate(&self, symbol: &str) -> Result<f64, String> {
+        let current = self.fetch_funding_rate(&self.base_url(), symbol).await?;
+        Ok(current + (self.env.rng.next_f64() - 0.5) * 0.0001)
+    }
+    
+    // 模擬 REST 賬戶快照
+    pub(crate) async fn fetch_account_snapshot(&self) -> Result<user_stream::AccountSnapshot, String> {
+        self.scheduler
+            .acquire(rate_limit::RequestKind::MarketData, self.scheduler.market_data_weight())
+            .await?;
+        self.sign_request("GET /api/v3/account", "")?;
+        let account = self.account.lock().unwrap();
+        Ok(user_stream::AccountSnapshot {
+            positions: account.positions.clone(),
+            balances: account.balances.clone(),
+        })
+    }
+    
+    // 模擬建立行情/用戶數據 WebSocket 連接：約 5% 失敗
+    pub(crate) async fn open_stream(&self, listen_key: Option<&str>) -> Result<(), String> {
+        if let Some(api) = &self.api {
+            return api.open_stream(listen_key, self.clock.now_ms()).await;
+        }
+        self.env.clock.sleep(Duration::from_millis(5 + self.env.rng.next_u64() % 20)).await;
+        if self.env.rng.next_f64() < 0.05 {
+            return Err(format!("{} WebSocket 握手失敗", self.name));
+        }
+        Ok(())
+    }
+    
+    // 模擬心跳：約 0.5% 的心跳發現連接已斷開
+    pub(crate) async fn ping(&self) -> Result<(), String> {
+        if let Some(api) = &self.api {
+            return api.ping().await;
+        }
+        if self.env.rng.next_f64() < 0.005 {
+            return Err(format!("{} 心跳超時", self.name));
+        }
+        Ok(())
+    }
+    
+    pub(crate) async fn send_subscribe(&self, topic: &str) {
+        debug!(exchange = %self.name, %topic, "發送訂閱");
+    }
+    
+    // 模擬 POST /api/v3/userDataStream
+    pub(crate) async fn create_listen_key(&self) -> Result<String, String> {
+        self.scheduler.acquire(rate_limit::RequestKind::MarketData, 1).await?;
+        if let Some(api) = &self.api {
+            return self.api_result(api.listen_key(&self.base_url(), reqwest::Method::POST, None).await);
+        }
+        Ok(self.env.uuid().simple().to_string())
+    }
+    
+    // 模擬 DELETE /api/v3/userDataStream
+    pub(crate) async fn close_listen_key(&self, listen_key: &str) -> Result<(), String> {
+        self.scheduler.acquire(rate_limit::RequestKind::MarketData, 1).await?;
+        debug!(exchange = %self.name, %listen_key, "註銷 listen key");
+        if let Some(api) = &self.api {
+            api.close_stream().await;
+            self.api_result(api.listen_key(&self.base_url(), reqwest::Method::DELETE, Some(listen_key)).await)?;
+        }
+        Ok(())
+    }
+    
+    // 模擬 PUT /api/v3/userDataStream：約 2% 返回 listen key 不存在
+    pub(crate) async fn keepalive_listen_key(&self, listen_key: &str) -> Result<(), String> {
+        self.scheduler.acquire(rate_limit::RequestKind::MarketData, 1).await?;
+        if let Some(api) = &self.api {
+            return self.api_result(api.listen_key(&self.base_url(), reqwest::Method::PUT, Some(listen_key)).await).map(|_| ());
+        }
+        if self.env.rng.next_f64() < 0.02 {
+            return Err(format!("listen key {} 不存在", listen_key));
+        }
+        Ok(())
+    }
+    
+    // 市價下單並返回成交比例。模擬時等待確認延遲後按拒單率拒絕，部分成交時比例在 min_fill_ratio 與 1 之間
+    pub(crate) async fn submit_order(&self, symbol: &str, side: &str, quantity: f64, client_order_id: &str) -> Result<f64, String> {
+        if let Some(api) = &self.api {
+            let recv_window_ms = self.settings.recv_window_ms;
+            let result = retry::with_retry(&self.settings.retry.order, &self.env, &self.name, "order", |attempt| async move {
+                // 上一次請求可能已到達交易所而響應丟失：查到該 client_order_id 的訂單時以其成交為準，不再重複提交
+                if attempt > 1 {
+                    let query = api.query_order(&self.base_url(), symbol, client_order_id, self.clock.now_ms(), recv_window_ms).await?;
+                    if let Some(filled) = query {
+                        info!(exchange = %self.name, %client_order_id, filled, "重試前查到已提交的訂單，不再重複下單");
+                        return Ok((filled / quantity).clamp(0.0, 1.0));
+                    }
+                }
+                match api.place_order(&self.base_url(), symbol, side, quantity, None, client_order_id, self.clock.now_ms(), recv_window_ms).await {
+                    // 同一 ID 的訂單已在交易所：以其成交為準
+                    Err(error) if ExchangeApi::is_duplicate_order(&error) => {
+                        let query = api.query_order(&self.base_url(), symbol, client_order_id, self.clock.now_ms(), recv_window_ms).await?;
+                        let filled = query.ok_or(error)?;
+                        info!(exchange = %self.name, %client_order_id, filled, "交易所按 client_order_id 去重，沿用已提交的訂單");
+                        Ok((filled / quantity).clamp(0.0, 1.0))
+                    }
+                    result => result,
+                }
+            })
+            .await;
+            return self.api_result(result);
+        }
+        let simulation = &self.settings.simulation;
+        self.env.clock.sleep(Duration::from_micros(simulation.order_latency_us)).await;
+        if self.env.rng.next_f64() < simulation.reject_rate {
+            return Err(format!("{} 拒絕訂單", self.name));
+        }
+        if self.env.rng.next_f64() < simulation.partial_fill_rate {
+            let ratio = simulation.min_fill_ratio + (1.0 - simulation.min_fill_ratio) * self.env.rng.next_f64();
+            return Ok(ratio.max(f64::MIN_POSITIVE));
+        }
+        Ok(1.0)
+    }
+    
+    // post-only 限價單：掛單 time_in_force_ms 後撤掉未成交部分，返回以 maker 成交的比例。
+    // 模擬時按 maker_fill_rate（掛得越靠近對手價越高）在有效期內全部成交，否則到期時隨機成交一部分
+    pub(crate) async fn submit_post_only(
+        &self,
+        symbol: &str,
+        side: &str,
+        quantity: f64,
+        order: maker::PostOnlyOrder,
+        client_order_id: &str,
+    ) -> Result<f64, String> {
+        if let Some(api) = &self.api {
+            let recv_window_ms = self.settings.recv_window_ms;
+            let result = retry::with_retry(&self.settings.retry.order, &self.env, &self.name, "post_only", |_| async move {
+                match api
+                    .place_order(&self.base_url(), symbol, side, quantity, Some(order.price), client_order_id, self.clock.now_ms(), recv_window_ms)
+                    .await
+                {
+                    // 上一次請求已掛上，稍後以查詢結果為準
+                    Err(error) if ExchangeApi::is_duplicate_order(&error) => Ok(0.0),
+                    result => result,
+                }
+            })
+            .await;
+            if self.api_result(result)? >= 1.0 {
+                return Ok(1.0);
+            }
+            self.env.clock.sleep(Duration::from_millis(order.time_in_force_ms)).await;
+            self.cancel_order(symbol, client_order_id).await?;
+            // 撤單後再查，撤單前最後時刻的成交也計入
+            let filled = self.order_status(symbol, client_order_id).await?.unwrap_or(0.0);
+            return Ok((filled / quantity).clamp(0.0, 1.0));
+        }
+        let simulation = &self.settings.simulation;
+        self.env.clock.sleep(Duration::from_micros(simulation.order_latency_us)).await;
+        let fill_rate = simulation.maker_fill_rate + (1.0 - simulation.maker_fill_rate) * order.inside_spread;
+        if self.env.rng.next_f64() < fill_rate {
+            return Ok(1.0);
+        }
+        self.env.clock.sleep(Duration::from_millis(order.time_in_force_ms)).await;
+        Ok(self.env.rng.next_f64())
+    }
+    
+    // 按 client_order_id 查詢訂單已成交數量，交易所沒有該訂單時返回 None；
+    // 模擬連接器沒有交易所側的訂單記錄，一律返回 None
+    pub(crate) async fn order_status(&self, symbol: &str, client_order_id: &str) -> Result<Option<f64>, String> {
+        self.scheduler.acquire(rate_limit::RequestKind::MarketData, 1).await?;
+        let Some(api) = &self.api else { return Ok(None) };
+        let result = retry::with_retry(&self.settings.retry.market_data, &self.env, &self.name, "order_status", |_| async move {
+            api.query_order(&self.base_url(), symbol, client_order_id, self.clock.now_ms(), self.settings.recv_window_ms).await
+        })
+        .await;
+        self.api_result(result)
+    }
+    
+    // 按 client_order_id 撤單，訂單不存在或已完結時返回 false；模擬連接器的訂單即時完結，一律返回 false
+    pub(crate) async fn cancel_order(&self, symbol: &str, client_order_id: &str) -> Result<bool, String> {
+        self.scheduler.acquire(rate_limit::RequestKind::Order, self.settings.rate_limit.order_weight).await?;
+        let Some(api) = &self.api else { return Ok(false) };
+        let result = retry::with_retry(&self.settings.retry.cancel, &self.env, &self.name, "cancel", |_| async move {
+            api.cancel_order(&self.base_url(), symbol, client_order_id, self.clock.now_ms(), self.settings.recv_window_ms).await
+        })
+        .await;
+        self.api_result(result)
+    }
+    
+    pub(crate) async fn fetch_funding_rate(&self, base_url: &str, symbol: &str) -> Result<f64, String> {
+        self.scheduler
+            .acquire(rate_limit::RequestKind::MarketData, self.scheduler.market_data_weight())
+            .await?;
+        if let Some(api) = &self.api {
+            let result = retry::with_retry(&self.settings.retry.market_data, &self.env, &self.name, "funding_rate", |_| {
+                api.funding_rate(base_url, symbol)
+            })
+            .await;
+            let (rate, headers) = self.api_result(result)?;
+            self.scheduler.observe_headers(&headers);
+            return Ok(rate);
+        }
+        // 模擬請求延遲：多數請求數百微秒，少數出現數十毫秒的長尾
+        let simulation = &self.settings.simulation;
+        let latency = if self.env.rng.next_f64() < simulation.slow_request_rate {
+            Duration::from_millis(uniform(self.env.rng.next_u64(), simulation.slow_request_ms))
+        } else {
+            Duration::from_micros(uniform(self.env.rng.next_u64(), simulation.request_latency_us))
+        };
+        self.env.clock.sleep(latency).await;
+        debug!(exchange = %self.name, %base_url, latency_us = latency.as_micros() as u64, "資金費率響應");
+        // 模擬響應頭：交易所回報窗口內已用權重
+        let mut headers = reqwest::header::HeaderMap::new();
+        if let Some(name) = &self.settings.rate_limit.used_weight_header {
+            let snapshot = self.scheduler.snapshot();
+            let used = (snapshot.weight_capacity - snapshot.weight_available).ceil();
+            if let (Ok(name), Ok(value)) = (
+                reqwest::header::HeaderName::from_bytes(name.as_bytes()),
+                reqwest::header::HeaderValue::from_str(&used.to_string()),
+            ) {
+                headers.insert(name, value);
+            }
+        }
+        self.scheduler.observe_headers(&headers);
+        
+        // 模擬獲取資金費率
+        match config::venue(&self.name) {
+            "binance" => Ok(0.0001 + (self.env.rng.next_f64() * 0.0002)),
+            "bybit" => Ok(0.0002 + (self.env.rng.next_f64() * 0.0002)),
+            "okx" => Ok(0.0003 + (self.env.rng.next_f64() * 0.0002)),
+            _ => Err(format!("不支持的交易所: {}", self.name)),
+        }
+    }
+}
+
+// [min, max] 上的均勻整數
+fn uniform(sample: u64, (min, max): (u64, u64)) -> u64 {
+    min + sample % (max - min + 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::environment::FixedVars;
+    use crate::mock_exchange::{MockExchange, Scenario};
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn testnet_requires_environment_credentials() {
+        let mock = MockExchange::start("127.0.0.1:0", Scenario::default()).await.unwrap();
+        let mut settings = config::ExchangeConfig {
+            environment: config::TradingEnvironment::Testnet,
+            ..Default::default()
+        };
+        assert_eq!(settings.resolved_endpoint("binance").unwrap().rest_url, "https://testnet.binancefuture.com");
+        assert_eq!(settings.credential_envs("okx")[2], "OKX_TESTNET_PASSPHRASE");
+
+        let envs = ["E2E_OKX_TESTNET_KEY", "E2E_OKX_TESTNET_SECRET", "E2E_OKX_TESTNET_PASSPHRASE"];
+        settings.endpoint = Some(mock.endpoint());
+        [settings.api_key_env, settings.secret_key_env, settings.passphrase_env] = envs.map(|var| Some(var.to_string()));
+        let vars = FixedVars::new(envs[..2].iter().map(|var| (*var, "demo")));
+        let provider = secrets::EnvSecrets(Arc::new(vars));
+        let error = secrets::fetch(&provider, provider.0.as_ref(), "okx", &settings).await.unwrap_err();
+        assert!(error.contains("passphrase") && error.contains("E2E_OKX_TESTNET_PASSPHRASE"), "{}", error);
+
+        let provider = secrets::EnvSecrets(Arc::new(FixedVars::new(envs.map(|var| (var, "demo")))));
+        let credentials = secrets::fetch(&provider, provider.0.as_ref(), "okx", &settings).await.unwrap();
+        assert_eq!(credentials.passphrase, "demo");
+        let connector = ExchangeConnector::new("okx", "https://www.okx.com", settings, None, Environment::system()).unwrap();
+        connector.set_credentials(credentials);
+        assert_eq!(connector.submit_order("BTCUSDT", "buy", 1.0, "e2e-1").await.unwrap(), 1.0);
+    }
+}
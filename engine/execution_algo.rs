@@ -0,0 +1,361 @@
+use super::config::ExecutionAlgoConfig;
+use super::{gas, ArbitrageRequest, ExecutionFailure, ExecutionOutcome, ExecutionEngine};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::Mutex;
+use std::time::Duration;
+use tracing::{info, warn};
+
+// 保留最近結束的算法執行數量
+const RECENT_LIMIT: usize = 100;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ExecutionAlgo {
+    // 在 duration_minutes 內等分為 slices 片，按固定間隔下單
+    Twap { duration_minutes: f64, slices: u32 },
+    // 每片名義金額不超過 display_size，上一片成交後間隔 refill_ms 補單
+    Iceberg { display_size: f64 },
+}
+
+impl ExecutionAlgo {
+    pub fn validate(&self, amount: f64, config: &ExecutionAlgoConfig) -> Result<(), String> {
+        match *self {
+            ExecutionAlgo::Twap { duration_minutes, slices } => {
+                if slices == 0 || duration_minutes <= 0.0 {
+                    return Err("TWAP 的 slices 與 duration_minutes 必須大於 0".to_string());
+                }
+                if duration_minutes > config.max_duration_minutes {
+                    return Err(format!("TWAP 時長不能超過 {} 分鐘", config.max_duration_minutes));
+                }
+            }
+            ExecutionAlgo::Iceberg { display_size } => {
+                if display_size <= 0.0 {
+                    return Err("冰山單的 display_size 必須大於 0".to_string());
+                }
+                if (amount / display_size).ceil() > config.max_slices as f64 {
+                    return Err(format!("冰山單切片數不能超過 {}", config.max_slices));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // 各片名義金額與片間間隔
+    fn schedule(&self, amount: f64, refill: Duration) -> (Vec<f64>, Duration) {
+        match *self {
+            ExecutionAlgo::Twap { duration_minutes, slices } => (
+                vec![amount / slices as f64; slices as usize],
+                Duration::from_secs_f64(duration_minutes * 60.0 / slices as f64),
+            ),
+            ExecutionAlgo::Iceberg { display_size } => {
+                let mut sizes = Vec::new();
+                let mut remaining = amount;
+                while remaining > 0.0 {
+                    sizes.push(remaining.min(display_size));
+                    remaining -= display_size;
+                }
+                (sizes, refill)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SliceReport {
+    pub index: usize,
+    pub notional: f64,
+    pub rate_diff: f64,
+    pub status: String,
+    pub realized_slippage_bps: Option<f64>,
+    pub profit: Option<f64>,
+    pub error: Option<String>,
+    pub executed_at_ms: i64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AlgoProgress {
+    pub execution_id: String,
+    pub symbol: String,
+    pub algo: ExecutionAlgo,
+    pub target_notional: f64,
+    pub filled_notional: f64,
+    pub planned_slices: usize,
+    // running / completed / aborted
+    pub state: String,
+    pub abort_reason: Option<String>,
+    pub slices: Vec<SliceReport>,
+}
+
+pub struct AlgoMonitor {
+    config: ExecutionAlgoConfig,
+    running: Mutex<BTreeMap<String, AlgoProgress>>,
+    recent: Mutex<VecDeque<AlgoProgress>>,
+}
+
+impl AlgoMonitor {
+    pub fn new(config: ExecutionAlgoConfig) -> Self {
+        Self {
+            config,
+            running: Mutex::new(BTreeMap::new()),
+            recent: Mutex::new(VecDeque::new()),
+        }
+    }
+
+    pub fn config(&self) -> &ExecutionAlgoConfig {
+        &self.config
+    }
+
+    // 運行中的在前，其後為最近結束的（新的在前）
+    pub fn snapshot(&self) -> Vec<AlgoProgress> {
+        let running = self.running.lock().unwrap();
+        let recent = self.recent.lock().unwrap();
+        running.values().cloned().chain(recent.iter().rev().cloned()).collect()
+    }
+
+    fn update(&self, execution_id: &str, apply: impl FnOnce(&mut AlgoProgress)) {
+        if let Some(progress) = self.running.lock().unwrap().get_mut(execution_id) {
+            apply(progress);
+        }
+    }
+
+    fn finish(&self, execution_id: &str, abort_reason: Option<String>) {
+        let Some(mut progress) = self.running.lock().unwrap().remove(execution_id) else { return };
+        progress.state = if abort_reason.is_some() { "aborted" } else { "completed" }.to_string();
+        progress.abort_reason = abort_reason;
+        let mut recent = self.recent.lock().unwrap();
+        recent.push_back(progress);
+        if recent.len() > RECENT_LIMIT {
+            recent.pop_front();
+        }
+    }
+}
+
+// 逐片執行並匯總；返回匯總結果與實際成交的名義金額。一片都未成交即中止時返回錯誤
+pub async fn run(
+    engine: &ExecutionEngine,
+    execution_id: &str,
+    request: &ArbitrageRequest,
+    algo: &ExecutionAlgo,
+    rate_diff: f64,
+    gas_quote: &gas::GasQuote,
+    margin_short: bool,
+) -> Result<(ExecutionOutcome, f64), ExecutionFailure> {
+    let monitor = &engine.algos;
+    let config = &monitor.config;
+    let (sizes, interval) = algo.schedule(request.amount, Duration::from_millis(config.iceberg_refill_ms));
+    monitor.running.lock().unwrap().insert(
+        execution_id.to_string(),
+        AlgoProgress {
+            execution_id: execution_id.to_string(),
+            symbol: request.symbol.clone(),
+            algo: algo.clone(),
+            target_notional: request.amount,
+            filled_notional: 0.0,
+            planned_slices: sizes.len(),
+            state: "running".to_string(),
+            abort_reason: None,
+            slices: Vec::new(),
+        },
+    );
+    info!(slices = sizes.len(), interval_ms = interval.as_millis() as u64, "開始算法執行");
+
+    let mut total: Option<ExecutionOutcome> = None;
+    // 單腿成交後已反向平倉的腿
+    let mut unwound = Vec::new();
+    let mut filled_notional = 0.0;
+    let mut consecutive_failures = 0;
+    let mut abort_reason = None;
+    for (index, notional) in sizes.into_iter().enumerate() {
+        // 第一片沿用執行前的費率檢查，之後每片重新獲取費率
+        let current_diff = if index == 0 {
+            rate_diff
+        } else {
+            engine.env.clock.sleep(interval).await;
+            match engine.current_rate_diff(request).await {
+                Ok(diff) => diff,
+                Err(error) => {
+                    abort_reason = Some(format!("第 {} 片獲取費率失敗: {}", index + 1, error));
+                    break;
+                }
+            }
+        };
+        if current_diff.signum() != rate_diff.signum() || current_diff.abs() < config.min_rate_diff {
+            abort_reason = Some(format!("第 {} 片前費率差 {:.6} 已低於閾值或反向", index + 1, current_diff));
+            break;
+        }
+
+        let slice_request = ArbitrageRequest {
+            amount: notional,
+            ..request.clone()
+        };
+        let result = engine.execute_flash_loan_arbitrage(execution_id, &slice_request, current_diff, gas_quote, margin_short, None).await;
+        let mut report = SliceReport {
+            index,
+            notional,
+            rate_diff: current_diff,
+            status: "filled".to_string(),
+            realized_slippage_bps: None,
+            profit: None,
+            error: None,
+            executed_at_ms: engine.env.now_ms(),
+        };
+        match result {
+            Ok(mut outcome) => {
+                consecutive_failures = 0;
+                let filled = notional * outcome.fill_ratio();
+                filled_notional += filled;
+                if filled < notional {
+                    report.status = "partially_filled".to_string();
+                }
+                report.realized_slippage_bps = Some(outcome.realized_slippage_bps);
+                report.profit = Some(outcome.profit);
+                for order in &mut outcome.orders {
+                    order.leg = format!("{}#{}", order.leg, index + 1);
+                }
+                let slippage = outcome.realized_slippage_bps;
+                total = Some(match total.take() {
+                    None => outcome,
+                    Some(total) => merge(total, outcome, filled_notional - filled, filled),
+                });
+                if slippage > config.max_slice_slippage_bps {
+                    abort_reason = Some(format!("第 {} 片滑點 {:.2} bps 超過上限", index + 1, slippage));
+                }
+            }
+            Err(ExecutionFailure { error, mut orders }) => {
+                consecutive_failures += 1;
+                report.status = "failed".to_string();
+                report.error = Some(error.clone());
+                for order in &mut orders {
+                    order.leg = format!("{}#{}", order.leg, index + 1);
+                }
+                unwound.extend(orders);
+                if consecutive_failures > config.max_failed_slices {
+                    abort_reason = Some(format!("連續 {} 片執行失敗: {}", consecutive_failures, error));
+                }
+            }
+        }
+        monitor.update(execution_id, |progress| {
+            progress.filled_notional = filled_notional;
+            progress.slices.push(report);
+        });
+        if abort_reason.is_some() {
+            break;
+        }
+    }
+
+    if let Some(reason) = &abort_reason {
+        warn!(%reason, filled_notional, target = request.amount, "算法執行中止");
+    }
+    monitor.finish(execution_id, abort_reason.clone());
+    match total {
+        Some(mut outcome) => {
+            // 反向平倉的手續費計入匯總結果
+            let unwound_fees: f64 = unwound.iter().map(|order| order.fee).sum();
+            outcome.profit -= unwound_fees;
+            outcome.fees += unwound_fees;
+            outcome.unwound = unwound;
+            Ok((outcome, filled_notional))
+        }
+        None => Err(ExecutionFailure {
+            error: abort_reason.unwrap_or_else(|| "算法執行沒有成交".to_string()),
+            orders: unwound,
+        }),
+    }
+}
+
+// 滑點按名義金額加權
+fn merge(total: ExecutionOutcome, slice: ExecutionOutcome, total_notional: f64, slice_notional: f64) -> ExecutionOutcome {
+    let weight = |a: f64, b: f64| (a * total_notional + b * slice_notional) / (total_notional + slice_notional);
+    let sum = |a: Option<f64>, b: Option<f64>| match (a, b) {
+        (None, None) => None,
+        (a, b) => Some(a.unwrap_or(0.0) + b.unwrap_or(0.0)),
+    };
+    ExecutionOutcome {
+        profit: total.profit + slice.profit,
+        fees: total.fees + slice.fees,
+        orders: total.orders.into_iter().chain(slice.orders).collect(),
+        expected_slippage_bps: weight(total.expected_slippage_bps, slice.expected_slippage_bps),
+        realized_slippage_bps: weight(total.realized_slippage_bps, slice.realized_slippage_bps),
+        gas_used: match (total.gas_used, slice.gas_used) {
+            (None, None) => None,
+            (a, b) => Some(a.unwrap_or(0) + b.unwrap_or(0)),
+        },
+        gas_cost_eth: sum(total.gas_cost_eth, slice.gas_cost_eth),
+        // 多片鏈上執行時只保留最後一片的結算證明
+        settlement_proof: slice.settlement_proof.or(total.settlement_proof),
+        unwound: total.unwound.into_iter().chain(slice.unwound).collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bench::START_MS;
+    use crate::deterministic_sim::{build, connect_sessions, funded_config, request};
+    use crate::{config, events, Environment, StrategyType};
+    use std::sync::Arc;
+
+    // TWAP 按固定間隔等分下單、冰山單按顯示數量切片並在補單間隔後續下；每片前重新檢查費率，
+    // 行情過期時中止並保留已成交的片。子訂單腿名帶片序號，超出上限的算法參數在執行前拒絕
+    #[tokio::test]
+    async fn execution_algos_slice_on_schedule_and_abort_on_stale_rates() {
+        let mut config = funded_config();
+        config.quotes = config::QuoteConfig { max_age_ms: 20 * 60_000, on_stale: config::StalePolicy::Refuse };
+        let (engine, path) = build("execution-algo", config, Environment::simulated(START_MS, 1));
+        let engine = Arc::new(engine);
+        connect_sessions(&engine).await;
+        let started_ms = engine.env.now_ms();
+        engine.quotes.record_predicted_rate("binance", "BTCUSDT", 0.0060, started_ms);
+        engine.quotes.record_predicted_rate("bybit", "BTCUSDT", 0.0010, started_ms);
+        let algo_request = |amount: f64, algo: ExecutionAlgo| ArbitrageRequest { execution_algo: Some(algo), ..request(amount) };
+
+        // 60 分鐘 4 片：第 3 片時行情已 30 分鐘未更新，中止後返回前兩片的成交
+        let twap = ExecutionAlgo::Twap { duration_minutes: 60.0, slices: 4 };
+        let response = engine.execute_funding_rate_arbitrage(algo_request(20_000.0, twap)).await;
+        assert_eq!(response.status, "success", "{:?}", response.error_message);
+        let progress = engine.algos.snapshot().remove(0);
+        assert_eq!((progress.state.as_str(), progress.planned_slices, progress.slices.len()), ("aborted", 4, 2));
+        assert!(progress.abort_reason.unwrap().starts_with("第 3 片獲取費率失敗"));
+        assert!(progress.slices.iter().all(|slice| slice.notional == 5_000.0 && slice.rate_diff == 0.005));
+        let gap = progress.slices[1].executed_at_ms - progress.slices[0].executed_at_ms;
+        assert!((15 * 60_000..16 * 60_000).contains(&gap), "{}", gap);
+        let execution_id = response.execution_id.unwrap();
+        let legs: Vec<String> = engine
+            .events
+            .read(0, 1_000)
+            .into_iter()
+            .filter_map(|envelope| match envelope.event {
+                events::EngineEvent::OrderPlaced { execution_id: id, leg, .. } if id == execution_id => Some(leg),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(legs, ["short#1", "long#1", "short#2", "long#2"]);
+
+        // 冰山單 10k 按 3k 顯示數量切為 3k × 3 + 1k，片間隔為補單間隔
+        let now_ms = engine.env.now_ms();
+        engine.quotes.record_predicted_rate("binance", "BTCUSDT", 0.0060, now_ms);
+        engine.quotes.record_predicted_rate("bybit", "BTCUSDT", 0.0010, now_ms);
+        let iceberg = ExecutionAlgo::Iceberg { display_size: 3_000.0 };
+        let response = engine.execute_funding_rate_arbitrage(algo_request(10_000.0, iceberg)).await;
+        assert_eq!(response.status, "success", "{:?}", response.error_message);
+        let progress = engine.algos.snapshot().remove(0);
+        assert_eq!(progress.state, "completed");
+        let sizes: Vec<f64> = progress.slices.iter().map(|slice| slice.notional).collect();
+        assert_eq!(sizes, [3_000.0, 3_000.0, 3_000.0, 1_000.0]);
+        assert!(progress.slices.windows(2).all(|pair| pair[1].executed_at_ms - pair[0].executed_at_ms >= 500));
+
+        let rejected = [
+            algo_request(10_000.0, ExecutionAlgo::Twap { duration_minutes: 300.0, slices: 10 }),
+            algo_request(10_000.0, ExecutionAlgo::Iceberg { display_size: 10.0 }),
+            ArbitrageRequest { strategy_type: StrategyType::SpotArbitrage, ..algo_request(10_000.0, ExecutionAlgo::Iceberg { display_size: 3_000.0 }) },
+        ];
+        let errors: Vec<String> = futures::future::join_all(rejected.map(|request| engine.execute_funding_rate_arbitrage(request)))
+            .await
+            .into_iter()
+            .map(|response| response.error_message.unwrap())
+            .collect();
+        assert_eq!(errors, ["TWAP 時長不能超過 240 分鐘", "冰山單切片數不能超過 500", "執行算法僅支持資金費率策略"]);
+        let _ = std::fs::remove_file(path);
+    }
+}
@@ -0,0 +1,477 @@
+use super::config::{self, Severity};
+use super::flash_loan::SettlementProof;
+use super::lending::{self, LendingOp};
+use super::rebalance::{self, PlannedTransfer, TransferKind};
+use super::{events, ArbitrageRequest, ChildOrder, ExecutionEngine};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use tracing::{debug, info, warn};
+
+// 一次執行的計劃：步驟按依賴關係組成有向無環圖。執行器逐層推進，同層互不依賴的步驟並發執行；
+// 任一步失敗時不再推進，已完成的步驟按完成的逆序執行各自的撤銷動作
+#[derive(Debug, Clone, Default)]
+pub(crate) struct ExecutionPlan {
+    steps: Vec<Step>,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct Step {
+    pub(crate) id: String,
+    pub(crate) action: Action,
+    // 必須先完成的步驟 id
+    pub(crate) depends_on: Vec<String>,
+    // 後續步驟失敗時撤銷本步的動作，按本步的成交比例縮放；None 表示無需撤銷（如原子結算的閃電貸）
+    pub(crate) unwind: Option<Action>,
+}
+
+/// 計劃中的一個動作；自定義策略以它描述各步驟（見 [`crate::strategy::PlannedStep`]）。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Action {
+    // 交易所市價單，side 為 buy 或 sell
+    Order {
+        exchange: String,
+        symbol: String,
+        side: String,
+        quantity: f64,
+    },
+    // 連接器之間劃轉資產：同一交易所的賬戶之間內部劃轉，跨交易所提幣到白名單地址
+    Transfer {
+        asset: String,
+        from: String,
+        to: String,
+        amount: f64,
+    },
+    // 鏈上閃電貸套利，借還在同一筆交易內原子完成
+    FlashLoan {
+        #[serde(default)]
+        chain: Option<String>,
+        symbol: String,
+        amount: f64,
+        min_profit: f64,
+    },
+    // DEX 兌換，按報價扣除滑點容忍度設置最少成交量
+    Swap {
+        #[serde(default)]
+        chain: Option<String>,
+        venue: String,
+        asset_in: String,
+        asset_out: String,
+        amount_in: f64,
+    },
+    // 借貸市場操作：存取抵押品、借款還款、存款取款
+    Lend {
+        market: String,
+        op: LendingOp,
+        amount: f64,
+    },
+}
+
+impl Action {
+    pub fn order(exchange: &str, symbol: &str, side: &str, quantity: f64) -> Self {
+        Action::Order {
+            exchange: exchange.to_string(),
+            symbol: symbol.to_string(),
+            side: side.to_string(),
+            quantity,
+        }
+    }
+
+    // 反向的交易所單、劃轉或借貸操作；兌換與閃電貸的反向動作取決於成交結果，需顯式給出
+    pub fn reversed(&self) -> Option<Action> {
+        match self {
+            Action::Order { exchange, symbol, side, quantity } => {
+                Some(Action::order(exchange, symbol, if side == "buy" { "sell" } else { "buy" }, *quantity))
+            }
+            Action::Transfer { asset, from, to, amount } => Some(Action::Transfer {
+                asset: asset.clone(),
+                from: to.clone(),
+                to: from.clone(),
+                amount: *amount,
+            }),
+            Action::Lend { market, op, amount } => Some(Action::Lend {
+                market: market.clone(),
+                op: op.reversed(),
+                amount: *amount,
+            }),
+            Action::FlashLoan { .. } | Action::Swap { .. } => None,
+        }
+    }
+
+    fn scaled(&self, ratio: f64) -> Action {
+        let mut action = self.clone();
+        match &mut action {
+            Action::Order { quantity, .. } => *quantity *= ratio,
+            Action::Transfer { amount, .. } | Action::FlashLoan { amount, .. } | Action::Lend { amount, .. } => *amount *= ratio,
+            Action::Swap { amount_in, .. } => *amount_in *= ratio,
+        }
+        action
+    }
+}
+
+// 一個步驟的執行結果；order 為該步在響應與事件日誌中的記帳表示
+pub(crate) struct StepFill {
+    pub(crate) order: ChildOrder,
+    // 相對計劃數量的成交比例
+    pub(crate) ratio: f64,
+    // 閃電貸結算到收益地址的淨收益
+    pub(crate) settled_profit: f64,
+    pub(crate) gas_used: Option<u64>,
+    pub(crate) gas_cost_eth: Option<f64>,
+    pub(crate) settlement_proof: Option<SettlementProof>,
+}
+
+pub(crate) struct PlanOutcome {
+    // 按完成順序
+    pub(crate) fills: Vec<StepFill>,
+}
+
+impl PlanOutcome {
+    pub(crate) fn orders(&self) -> Vec<ChildOrder> {
+        self.fills.iter().map(|fill| fill.order.clone()).collect()
+    }
+
+    pub(crate) fn fill_ratio(&self) -> f64 {
+        self.fills.iter().map(|fill| fill.ratio).fold(1.0, f64::min)
+    }
+
+    pub(crate) fn fees(&self) -> f64 {
+        self.fills.iter().map(|fill| fill.order.fee).sum()
+    }
+
+    pub(crate) fn settled_profit(&self) -> f64 {
+        self.fills.iter().map(|fill| fill.settled_profit).sum()
+    }
+
+    // 有鏈上步驟時為各步之和，否則為 None
+    pub(crate) fn gas(&self) -> (Option<u64>, Option<f64>) {
+        let onchain = self.fills.iter().filter(|fill| fill.gas_used.is_some());
+        onchain.fold((None, None), |(used, cost), fill| {
+            (
+                Some(used.unwrap_or(0) + fill.gas_used.unwrap_or(0)),
+                Some(cost.unwrap_or(0.0) + fill.gas_cost_eth.unwrap_or(0.0)),
+            )
+        })
+    }
+
+    pub(crate) fn settlement_proof(&mut self) -> Option<SettlementProof> {
+        self.fills.iter_mut().rev().find_map(|fill| fill.settlement_proof.take())
+    }
+}
+
+impl ExecutionPlan {
+    // 追加一個步驟，depends_on 中的步驟須已加入
+    pub(crate) fn step(mut self, id: &str, action: Action, depends_on: &[&str]) -> Self {
+        self.steps.push(Step {
+            id: id.to_string(),
+            action,
+            depends_on: depends_on.iter().map(|id| id.to_string()).collect(),
+            unwind: None,
+        });
+        self
+    }
+
+    // 為最近追加的步驟設置撤銷動作
+    pub(crate) fn unwind(mut self, action: Action) -> Self {
+        if let Some(step) = self.steps.last_mut() {
+            step.unwind = Some(action);
+        }
+        self
+    }
+
+    pub(crate) fn steps(&self) -> &[Step] {
+        &self.steps
+    }
+
+    // 各鏈上步驟按預估 gas 單位與當前費率估算的 gas 費之和（USDT）；所在鏈未配置執行器的步驟執行時會直接失敗，不計
+    pub(crate) fn estimated_gas_usdt(&self, engine: &ExecutionEngine, request: &ArbitrageRequest) -> Result<f64, String> {
+        let mut total = 0.0;
+        for step in &self.steps {
+            let onchain = match &step.action {
+                Action::FlashLoan { chain, .. } => {
+                    Some(engine.chain(chain.as_deref())?).filter(|chain| chain.flash_loan.is_some()).map(|chain| (chain, "flash_loan"))
+                }
+                Action::Swap { chain, .. } => Some(engine.chain(chain.as_deref())?).filter(|chain| chain.dex.is_some()).map(|chain| (chain, "swap")),
+                Action::Lend { market, .. } => lending::onchain_chain(engine, market)?.map(|chain| (chain, "lend")),
+                Action::Order { .. } | Action::Transfer { .. } => None,
+            };
+            if let Some((chain, kind)) = onchain {
+                total += chain.estimated_gas_usdt(kind, &chain.gas_optimizer.quote(request.priority))?;
+            }
+        }
+        Ok(total)
+    }
+
+    // 按依賴分層：每層的步驟只依賴之前各層；id 重複、依賴不存在或有環時返回錯誤
+    pub(crate) fn layers(&self) -> Result<Vec<Vec<usize>>, String> {
+        if self.steps.is_empty() {
+            return Err("執行計劃為空".to_string());
+        }
+        let mut index = BTreeMap::new();
+        for (i, step) in self.steps.iter().enumerate() {
+            if step.id.trim().is_empty() {
+                return Err("執行計劃的步驟 id 不能為空".to_string());
+            }
+            if index.insert(step.id.as_str(), i).is_some() {
+                return Err(format!("執行計劃的步驟 id {} 重複", step.id));
+            }
+        }
+        let mut depth = vec![None::<usize>; self.steps.len()];
+        let mut remaining = self.steps.len();
+        while remaining > 0 {
+            let before = remaining;
+            for (i, step) in self.steps.iter().enumerate() {
+                if depth[i].is_some() {
+                    continue;
+                }
+                let mut level = 0;
+                let mut ready = true;
+                for dependency in &step.depends_on {
+                    let Some(&j) = index.get(dependency.as_str()) else {
+                        return Err(format!("步驟 {} 依賴不存在的步驟 {}", step.id, dependency));
+                    };
+                    match depth[j] {
+                        Some(d) => level = level.max(d + 1),
+                        None => ready = false,
+                    }
+                }
+                if ready {
+                    depth[i] = Some(level);
+                    remaining -= 1;
+                }
+            }
+            if remaining == before {
+                return Err("執行計劃的步驟依賴有環".to_string());
+            }
+        }
+        let mut layers: Vec<Vec<usize>> = Vec::new();
+        for (i, d) in depth.into_iter().enumerate() {
+            let d = d.unwrap_or_default();
+            if layers.len() <= d {
+                layers.resize(d + 1, Vec::new());
+            }
+            layers[d].push(i);
+        }
+        Ok(layers)
+    }
+}
+
+/// 逐層執行計劃，每完成一步調用 `on_step`；失敗時撤銷已完成的步驟，撤銷也失敗時發出嚴重告警。
+pub(crate) async fn run(
+    engine: &ExecutionEngine,
+    execution_id: &str,
+    request: &ArbitrageRequest,
+    plan: &ExecutionPlan,
+    on_step: impl Fn(&ChildOrder),
+) -> Result<PlanOutcome, String> {
+    let layers = plan.layers()?;
+    let mut done: Vec<(usize, StepFill)> = Vec::new();
+    for layer in layers {
+        let results = futures::future::join_all(layer.iter().map(|&i| {
+            let step = &plan.steps[i];
+            perform(engine, execution_id, request, &step.id, &step.action)
+        }))
+        .await;
+        let mut failed = None;
+        for (i, result) in layer.into_iter().zip(results) {
+            match result {
+                Ok(fill) => {
+                    debug!(step = %plan.steps[i].id, ratio = fill.ratio, "執行計劃步驟完成");
+                    on_step(&fill.order);
+                    done.push((i, fill));
+                }
+                Err(error) => {
+                    failed.get_or_insert(format!("{} 步驟失敗: {}", plan.steps[i].id, error));
+                }
+            }
+        }
+        if let Some(error) = failed {
+            return Err(unwind(engine, execution_id, request, plan, &done, error).await);
+        }
+    }
+    info!(steps = done.len(), "執行計劃完成");
+    Ok(PlanOutcome {
+        fills: done.into_iter().map(|(_, fill)| fill).collect(),
+    })
+}
+
+// 按完成的逆序撤銷；返回附帶撤銷結果的錯誤信息
+async fn unwind(
+    engine: &ExecutionEngine,
+    execution_id: &str,
+    request: &ArbitrageRequest,
+    plan: &ExecutionPlan,
+    done: &[(usize, StepFill)],
+    error: String,
+) -> String {
+    let mut unwound = Vec::new();
+    let mut stranded = Vec::new();
+    for (i, fill) in done.iter().rev() {
+        let step = &plan.steps[*i];
+        let Some(action) = &step.unwind else { continue };
+        let id = format!("unwind_{}", step.id);
+        match perform(engine, execution_id, request, &id, &action.scaled(fill.ratio)).await {
+            Ok(_) => unwound.push(step.id.as_str()),
+            Err(e) => {
+                warn!(step = %step.id, error = %e, "撤銷執行計劃步驟失敗");
+                stranded.push(format!("{}（{}）", step.id, e));
+            }
+        }
+    }
+    if stranded.is_empty() {
+        if !unwound.is_empty() {
+            warn!(%error, unwound = unwound.len(), "執行計劃失敗，已撤銷已完成的步驟");
+        }
+        return format!("{}，已撤銷 {} 步", error, unwound.len());
+    }
+    let message = format!("{} {}，未能撤銷: {}", execution_id, error, stranded.join("、"));
+    engine.alert(Severity::Critical, "執行計劃撤銷失敗", message.clone());
+    message
+}
+
+async fn perform(engine: &ExecutionEngine, execution_id: &str, request: &ArbitrageRequest, id: &str, action: &Action) -> Result<StepFill, String> {
+    let filled = |exchange: &str, symbol: &str, side: &str, quantity: f64, fee: f64| ChildOrder {
+        leg: id.to_string(),
+        exchange: exchange.to_string(),
+        symbol: symbol.to_string(),
+        side: side.to_string(),
+        quantity,
+        filled_quantity: quantity,
+        fee,
+        status: "filled".to_string(),
+    };
+    let offchain = |order: ChildOrder| StepFill {
+        order,
+        ratio: 1.0,
+        settled_profit: 0.0,
+        gas_used: None,
+        gas_cost_eth: None,
+        settlement_proof: None,
+    };
+    match action {
+        Action::Order { exchange, symbol, side, quantity } => {
+            if !engine.exchanges.contains_key(exchange) {
+                return Err(format!("未配置交易所 {}", exchange));
+            }
+            engine.acquire_orders(&[exchange]).await?;
+            let ratio = engine.submit_journaled(execution_id, request, id, exchange, symbol, side, *quantity, None).await?;
+            let filled_quantity = quantity * ratio;
+            Ok(StepFill {
+                ratio,
+                ..offchain(ChildOrder {
+                    filled_quantity,
+                    status: if ratio < 1.0 { "partially_filled" } else { "filled" }.to_string(),
+                    ..filled(exchange, symbol, side, *quantity, filled_quantity * engine.taker_fee(exchange))
+                })
+            })
+        }
+        Action::Transfer { asset, from, to, amount } => {
+            let kind = if config::venue(from) == config::venue(to) { TransferKind::Internal } else { TransferKind::Withdrawal };
+            let transfer = PlannedTransfer {
+                asset: asset.clone(),
+                from: from.clone(),
+                to: to.clone(),
+                amount: *amount,
+                kind,
+            };
+            if let Some(error) = rebalance::execute(engine, &transfer).await.error_message {
+                return Err(error);
+            }
+            engine.events.append(events::EngineEvent::Transfer {
+                asset: asset.clone(),
+                from_exchange: from.clone(),
+                to_exchange: to.clone(),
+                amount: *amount,
+            });
+            Ok(offchain(filled(from, asset, "transfer", *amount, 0.0)))
+        }
+        Action::FlashLoan { chain, symbol, amount, min_profit } => {
+            let chain = engine.chain(chain.as_deref())?;
+            let flash_loan = chain.flash_loan.as_ref().ok_or_else(|| format!("鏈 {} 未配置閃電貸", chain.name))?;
+            let quote = chain.gas_optimizer.quote(request.priority);
+            // 合約保證的 min_profit 扣除實時估算的 gas 後須達到鏈的淨收益門檻
+            let profitable = engine.profit_gate(chain, symbol, *min_profit, 0.0, &quote);
+            let receipt = flash_loan
+                .execute(*amount, symbol, *min_profit, &chain.gas_optimizer, &quote, &profitable)
+                .await
+                .map_err(|error| engine.record_burned_gas(execution_id, request, chain, error))?;
+            Ok(StepFill {
+                order: filled(receipt.provider, symbol, "borrow", *amount, receipt.premium),
+                ratio: 1.0,
+                settled_profit: receipt.profit,
+                gas_used: Some(receipt.gas_used),
+                gas_cost_eth: Some(chain.to_eth(receipt.gas_cost)),
+                settlement_proof: Some(receipt.proof),
+            })
+        }
+        Action::Swap { chain, venue, asset_in, asset_out, amount_in } => {
+            let chain = engine.chain(chain.as_deref())?;
+            let dex = chain.dex.as_ref().ok_or_else(|| format!("鏈 {} 未配置 DEX", chain.name))?;
+            let quoted_out = dex.quote(venue, asset_in, asset_out, *amount_in).await?;
+            let quote = chain.gas_optimizer.quote(request.priority);
+            let fill = dex
+                .swap(venue, asset_in, asset_out, *amount_in, quoted_out, &chain.gas_optimizer, &quote)
+                .await
+                .map_err(|error| engine.record_burned_gas(execution_id, request, chain, error))?;
+            Ok(StepFill {
+                order: filled(venue, &format!("{}/{}", asset_in, asset_out), "swap", *amount_in, 0.0),
+                ratio: 1.0,
+                settled_profit: 0.0,
+                gas_used: Some(fill.gas_used),
+                gas_cost_eth: Some(chain.to_eth(fill.gas_cost)),
+                settlement_proof: None,
+            })
+        }
+        Action::Lend { market, op, amount } => {
+            let (gas_used, gas_cost_eth) = lending::perform(engine, execution_id, request, market, *op, *amount).await?;
+            Ok(StepFill {
+                gas_used,
+                gas_cost_eth,
+                ..offchain(filled(market, &engine.lending.asset(market, *op), op.name(), *amount, 0.0))
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::deterministic_sim::{engine, request, PairStrategy};
+    use crate::{strategy, StrategyType};
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn execution_plans_run_by_dependency_and_unwind_on_failure() {
+        let order = |side: &str| Action::order("binance", "BTCUSDT", side, 1.0);
+        let cyclic = ExecutionPlan::default().step("a", order("buy"), &["b"]).step("b", order("sell"), &["a"]);
+        assert!(cyclic.layers().is_err());
+        let diamond = ExecutionPlan::default()
+            .step("a", order("buy"), &[])
+            .step("b", order("sell"), &["a"])
+            .step("c", order("sell"), &["a"])
+            .step("d", order("buy"), &["b", "c"]);
+        assert_eq!(diamond.layers().unwrap(), [vec![0], vec![1, 2], vec![3]]);
+
+        let simulation = config::SimulatedExchangeConfig {
+            reject_rate: 0.0,
+            partial_fill_rate: 0.0,
+            ..Default::default()
+        };
+        let (engine, path) = engine("plan-unwind", 1, simulation);
+        let pair = Arc::new(PairStrategy {
+            fills: Default::default(),
+            restock: true,
+        });
+        engine.register_strategy(Arc::clone(&pair) as Arc<dyn strategy::Strategy>).unwrap();
+        let request = ArbitrageRequest {
+            strategy_type: StrategyType::Plugin,
+            plugin: Some("pair".to_string()),
+            ..request(1_000.0)
+        };
+        // 兩腿並發成交後劃轉因目標不在提幣白名單中失敗，兩腿以反向單撤銷
+        let error = strategy::execute(&engine, "plan-1", &request).await.err().unwrap();
+        assert!(error.contains("restock 步驟失敗") && error.contains("已撤銷 2 步"), "{}", error);
+        assert_eq!(*pair.fills.lock().unwrap(), ["long", "short"]);
+        let _ = std::fs::remove_file(path);
+    }
+}
@@ -0,0 +1,566 @@
+use super::config::{ExecutionQueueConfig, PreemptionMode};
+use super::{protocol, ArbitrageRequest, ArbitrageResponse, ExecutionEngine};
+use serde::Serialize;
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, BinaryHeap};
+use std::future::Future;
+use std::sync::atomic::{AtomicU8, Ordering as AtomicOrdering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::{oneshot, Notify};
+use tracing::{info, warn, Instrument};
+
+// 沒有新請求或完成通知時，按此間隔檢查等待超時
+const EXPIRY_CHECK_INTERVAL: Duration = Duration::from_millis(100);
+
+const RUNNING: u8 = 0;
+const COMMITTED: u8 = 1;
+const PREEMPTED: u8 = 2;
+
+pub(crate) const PREEMPTED_MESSAGE: &str = "已被更高優先級的請求搶佔";
+
+// 隊列派發的執行與搶佔方之間的約定：下單前的階段在檢查點查詢是否被搶佔，
+// 首次下單前提交，提交後不再可搶佔
+#[derive(Debug, Default)]
+pub struct Preemption {
+    state: AtomicU8,
+    notify: Notify,
+}
+
+impl Preemption {
+    // 尚未提交時標記為被搶佔並喚醒等待中的階段
+    fn preempt(&self) -> bool {
+        let preempted = self
+            .state
+            .compare_exchange(RUNNING, PREEMPTED, AtomicOrdering::AcqRel, AtomicOrdering::Acquire)
+            .is_ok();
+        if preempted {
+            self.notify.notify_waiters();
+        }
+        preempted
+    }
+
+    fn is_preempted(&self) -> bool {
+        self.state.load(AtomicOrdering::Acquire) == PREEMPTED
+    }
+
+    async fn preempted(&self) {
+        loop {
+            let notified = self.notify.notified();
+            if self.is_preempted() {
+                return;
+            }
+            notified.await;
+        }
+    }
+}
+
+// 請求是否已被搶佔；不經隊列派發的請求始終為否
+pub(crate) fn preempted(request: &ArbitrageRequest) -> bool {
+    request.preemption.as_ref().is_some_and(|preemption| preemption.is_preempted())
+}
+
+// 即將下單：提交後不再可搶佔；已被搶佔時返回錯誤，調用方放棄執行
+pub(crate) fn commit(request: &ArbitrageRequest) -> Result<(), String> {
+    let Some(preemption) = &request.preemption else {
+        return Ok(());
+    };
+    match preemption.state.compare_exchange(RUNNING, COMMITTED, AtomicOrdering::AcqRel, AtomicOrdering::Acquire) {
+        Ok(_) | Err(COMMITTED) => Ok(()),
+        Err(_) => Err(PREEMPTED_MESSAGE.to_string()),
+    }
+}
+
+// 下單前的等待階段（如獲取費率）與搶佔競爭，被搶佔時放棄該階段
+pub(crate) async fn preemptible<T>(request: &ArbitrageRequest, stage: impl Future<Output = Result<T, String>>) -> Result<T, String> {
+    let Some(preemption) = &request.preemption else {
+        return stage.await;
+    };
+    tokio::select! {
+        result = stage => result,
+        _ = preemption.preempted() => Err(PREEMPTED_MESSAGE.to_string()),
+    }
+}
+
+struct Pending {
+    priority: i32,
+    // 每單位名義金額的預期淨收益
+    expected_edge: f64,
+    sequence: u64,
+    enqueued_at: Instant,
+    request: ArbitrageRequest,
+    respond: oneshot::Sender<ArbitrageResponse>,
+}
+
+impl Pending {
+    fn venues(&self) -> Vec<String> {
+        let mut venues = vec![self.request.primary_exchange.clone()];
+        if !self.request.secondary_exchange.is_empty() && self.request.secondary_exchange != self.request.primary_exchange {
+            venues.push(self.request.secondary_exchange.clone());
+        }
+        venues
+    }
+}
+
+// 優先級高者先，其次預期收益高者，最後先到者
+impl Ord for Pending {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.priority
+            .cmp(&other.priority)
+            .then_with(|| self.expected_edge.total_cmp(&other.expected_edge))
+            .then_with(|| other.sequence.cmp(&self.sequence))
+    }
+}
+
+impl PartialOrd for Pending {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Pending {
+    fn eq(&self, other: &Self) -> bool {
+        self.sequence == other.sequence
+    }
+}
+
+impl Eq for Pending {}
+
+// 已派發的執行，供搶佔時挑選
+struct Running {
+    sequence: u64,
+    priority: i32,
+    venues: Vec<String>,
+    preemption: Arc<Preemption>,
+}
+
+#[derive(Default)]
+struct State {
+    pending: BinaryHeap<Pending>,
+    running: BTreeMap<String, usize>,
+    executions: Vec<Running>,
+    sequence: u64,
+    dispatched: u64,
+    shed: u64,
+    rejected: u64,
+    expired: u64,
+    preempted: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PendingView {
+    pub strategy_id: String,
+    pub symbol: String,
+    pub primary_exchange: String,
+    pub secondary_exchange: String,
+    pub priority: i32,
+    pub expected_edge: f64,
+    pub waited_ms: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct QueueSnapshot {
+    pub enabled: bool,
+    pub max_pending: usize,
+    pub max_concurrent_per_exchange: usize,
+    // 按出隊順序
+    pub pending: Vec<PendingView>,
+    pub running: BTreeMap<String, usize>,
+    pub dispatched: u64,
+    pub shed: u64,
+    pub rejected: u64,
+    pub expired: u64,
+    // 被更高優先級請求搶佔的執行數
+    pub preempted: u64,
+}
+
+pub struct ExecutionQueue {
+    config: ExecutionQueueConfig,
+    state: Mutex<State>,
+    notify: Notify,
+}
+
+impl ExecutionQueue {
+    pub fn new(config: ExecutionQueueConfig) -> Self {
+        Self {
+            config,
+            state: Mutex::new(State::default()),
+            notify: Notify::new(),
+        }
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.config.enabled
+    }
+
+    // 入隊並返回等待結果的接收端；隊列已滿且新請求排序最低時直接拒絕
+    pub fn push(
+        &self,
+        request: ArbitrageRequest,
+        expected_edge: f64,
+    ) -> Result<oneshot::Receiver<ArbitrageResponse>, String> {
+        let (respond, receiver) = oneshot::channel();
+        let mut state = self.state.lock().unwrap();
+        state.sequence += 1;
+        let pending = Pending {
+            priority: request.priority,
+            expected_edge,
+            sequence: state.sequence,
+            enqueued_at: Instant::now(),
+            request,
+            respond,
+        };
+        if state.pending.len() >= self.config.max_pending {
+            let lowest = state.pending.iter().min().map(|lowest| (lowest.sequence, pending > *lowest));
+            match lowest {
+                Some((sequence, true)) => {
+                    let mut kept = BinaryHeap::with_capacity(state.pending.len());
+                    for queued in std::mem::take(&mut state.pending) {
+                        if queued.sequence == sequence {
+                            warn!(strategy_id = %queued.request.strategy_id, priority = queued.priority, "執行隊列已滿，擠出排序最低的請求");
+                            let _ = queued
+                                .respond
+                                .send(ArbitrageResponse::error("執行隊列已滿，被更高優先級的請求擠出"));
+                        } else {
+                            kept.push(queued);
+                        }
+                    }
+                    state.pending = kept;
+                    state.shed += 1;
+                }
+                _ => {
+                    state.rejected += 1;
+                    warn!(strategy_id = %pending.request.strategy_id, priority = pending.priority, "執行隊列已滿，拒絕請求");
+                    return Err("執行隊列已滿，請求優先級過低".to_string());
+                }
+            }
+        }
+        state.pending.push(pending);
+        drop(state);
+        self.notify.notify_one();
+        Ok(receiver)
+    }
+
+    // 丟棄等待超時的請求，取出排序最高且所涉交易所均有空閒並發額度的請求；
+    // 啟用搶佔時為排序最高的受阻請求讓低優先級執行讓出額度
+    fn next_ready(&self, now_ms: i64) -> Option<Pending> {
+        let mut state = self.state.lock().unwrap();
+        let max_wait = Duration::from_millis(self.config.max_wait_ms);
+        let mut blocked = Vec::new();
+        let mut ready = None;
+        while let Some(pending) = state.pending.pop() {
+            if pending.request.deadline_ms.is_some_and(|deadline| deadline <= now_ms) {
+                state.expired += 1;
+                warn!(strategy_id = %pending.request.strategy_id, "請求在執行隊列中超過截止時間");
+                let _ = pending.respond.send(ArbitrageResponse::error("請求在執行隊列中超過截止時間"));
+                continue;
+            }
+            if pending.enqueued_at.elapsed() > max_wait {
+                state.expired += 1;
+                warn!(strategy_id = %pending.request.strategy_id, priority = pending.priority, "請求在執行隊列中等待超時");
+                let _ = pending.respond.send(ArbitrageResponse::error("請求在執行隊列中等待超時"));
+                continue;
+            }
+            let available = pending.venues().iter().all(|venue| {
+                state.running.get(venue).copied().unwrap_or_default() < self.config.max_concurrent_per_exchange
+            });
+            if available {
+                ready = Some(pending);
+                break;
+            }
+            if blocked.is_empty() && self.config.preemption.enabled {
+                self.preempt_for(&mut state, &pending);
+            }
+            blocked.push(pending);
+        }
+        state.pending.extend(blocked);
+        if let Some(pending) = &mut ready {
+            for venue in pending.venues() {
+                *state.running.entry(venue).or_default() += 1;
+            }
+            state.dispatched += 1;
+            if self.config.preemption.enabled {
+                let preemption = Arc::new(Preemption::default());
+                pending.request.preemption = Some(Arc::clone(&preemption));
+                state.executions.push(Running {
+                    sequence: pending.sequence,
+                    priority: pending.priority,
+                    venues: pending.venues(),
+                    preemption,
+                });
+            }
+        }
+        ready
+    }
+
+    // 在受阻請求每個額度已滿的交易所上挑選一個優先級足夠低、尚未下單的執行（優先級最低者，同級取最晚派發者）；
+    // 已在讓出中的執行計入將空出的額度，任一交易所找不到可搶佔的執行時不搶佔
+    fn preempt_for(&self, state: &mut State, pending: &Pending) {
+        let max_priority = pending.priority.saturating_sub(self.config.preemption.min_priority_gap);
+        let mut victims: Vec<usize> = Vec::new();
+        for venue in pending.venues() {
+            let involved = |index: usize| state.executions[index].venues.contains(&venue);
+            let yielding = (0..state.executions.len())
+                .filter(|&index| involved(index) && (state.executions[index].preemption.is_preempted() || victims.contains(&index)))
+                .count();
+            let running = state.running.get(&venue).copied().unwrap_or_default();
+            if running.saturating_sub(yielding) < self.config.max_concurrent_per_exchange {
+                continue;
+            }
+            let victim = (0..state.executions.len())
+                .filter(|&index| {
+                    let execution = &state.executions[index];
+                    involved(index)
+                        && execution.priority <= max_priority
+                        && execution.preemption.state.load(AtomicOrdering::Acquire) == RUNNING
+                        && !victims.contains(&index)
+                })
+                .min_by_key(|&index| (state.executions[index].priority, std::cmp::Reverse(state.executions[index].sequence)));
+            match victim {
+                Some(index) => victims.push(index),
+                None => return,
+            }
+        }
+        for index in victims {
+            let execution = &state.executions[index];
+            if execution.preemption.preempt() {
+                warn!(
+                    strategy_id = %pending.request.strategy_id,
+                    priority = pending.priority,
+                    preempted_priority = execution.priority,
+                    venues = ?execution.venues,
+                    "交易所並發額度已滿，搶佔低優先級執行"
+                );
+                state.preempted += 1;
+            }
+        }
+    }
+
+    fn finish(&self, sequence: u64, venues: &[String]) {
+        let mut state = self.state.lock().unwrap();
+        for venue in venues {
+            if let Some(running) = state.running.get_mut(venue) {
+                *running -= 1;
+                if *running == 0 {
+                    state.running.remove(venue);
+                }
+            }
+        }
+        state.executions.retain(|execution| execution.sequence != sequence);
+        drop(state);
+        self.notify.notify_one();
+    }
+
+    // 被搶佔後重新排隊：保留原序號，等待時間重新計算，不受 max_pending 限制
+    fn requeue(&self, mut pending: Pending) {
+        pending.request.preemption = None;
+        pending.enqueued_at = Instant::now();
+        self.state.lock().unwrap().pending.push(pending);
+        self.notify.notify_one();
+    }
+
+    pub fn snapshot(&self) -> QueueSnapshot {
+        let state = self.state.lock().unwrap();
+        let mut pending: Vec<&Pending> = state.pending.iter().collect();
+        pending.sort_by(|a, b| b.cmp(a));
+        QueueSnapshot {
+            enabled: self.config.enabled,
+            max_pending: self.config.max_pending,
+            max_concurrent_per_exchange: self.config.max_concurrent_per_exchange,
+            pending: pending
+                .into_iter()
+                .map(|pending| PendingView {
+                    strategy_id: pending.request.strategy_id.clone(),
+                    symbol: pending.request.symbol.clone(),
+                    primary_exchange: pending.request.primary_exchange.clone(),
+                    secondary_exchange: pending.request.secondary_exchange.clone(),
+                    priority: pending.priority,
+                    expected_edge: pending.expected_edge,
+                    waited_ms: pending.enqueued_at.elapsed().as_millis() as u64,
+                })
+                .collect(),
+            running: state.running.clone(),
+            dispatched: state.dispatched,
+            shed: state.shed,
+            rejected: state.rejected,
+            expired: state.expired,
+            preempted: state.preempted,
+        }
+    }
+}
+
+// 執行結束（包括 panic）時歸還交易所並發額度
+struct Slot<'a> {
+    queue: &'a ExecutionQueue,
+    sequence: u64,
+    venues: Vec<String>,
+}
+
+impl Drop for Slot<'_> {
+    fn drop(&mut self) {
+        self.queue.finish(self.sequence, &self.venues);
+    }
+}
+
+pub fn spawn(engine: Arc<ExecutionEngine>) {
+    if !engine.queue.enabled() {
+        info!("執行隊列未啟用，請求直接執行");
+        return;
+    }
+    tokio::spawn(async move {
+        loop {
+            while let Some(pending) = engine.queue.next_ready(engine.env.now_ms()) {
+                let engine = Arc::clone(&engine);
+                tokio::spawn(
+                    async move {
+                        let slot = Slot {
+                            queue: &engine.queue,
+                            sequence: pending.sequence,
+                            venues: pending.venues(),
+                        };
+                        let Pending { priority, expected_edge, sequence, enqueued_at, request, respond } = pending;
+                        let requeue = engine.queue.config.preemption.mode == PreemptionMode::Requeue && request.preemption.is_some();
+                        let retry = requeue.then(|| request.clone());
+                        let response = engine.execute_funding_rate_arbitrage(request).await;
+                        match retry {
+                            Some(request) if response.status == "preempted" => {
+                                // 先歸還額度，再推送 preempted 進度並重新排隊
+                                drop(slot);
+                                if let (Some(sink), Some(execution_id)) = (&request.progress, &response.execution_id) {
+                                    let _ = sink.send(protocol::ExecutionProgress::stage(execution_id, "preempted", engine.env.now_ms()));
+                                }
+                                engine.queue.requeue(Pending { priority, expected_edge, sequence, enqueued_at, request, respond });
+                            }
+                            _ => {
+                                let _ = respond.send(response);
+                            }
+                        }
+                    }
+                    .in_current_span(),
+                );
+            }
+            let _ = tokio::time::timeout(EXPIRY_CHECK_INTERVAL, engine.queue.notify.notified()).await;
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bench::START_MS;
+    use crate::deterministic_sim::{build, connect_sessions, funded_config, request};
+    use crate::{config, rate_limit, Environment};
+
+    // 按優先級、預期收益出隊且每個交易所同時只執行一筆；隊列已滿時擠出排序最低者或拒絕更低的新請求，過截止時間的出隊時丟棄
+    #[tokio::test]
+    async fn execution_queue_orders_by_priority_and_sheds_when_saturated() {
+        let mut config = funded_config();
+        config.execution_queue.max_pending = 3;
+        config.execution_queue.max_concurrent_per_exchange = 1;
+        let (engine, path) = build("queue-priority", config, Environment::simulated(START_MS, 1));
+        let engine = Arc::new(engine);
+        let (sink, mut progress) = tokio::sync::mpsc::unbounded_channel();
+        let queued = |priority: i32, deadline_ms: Option<i64>| ArbitrageRequest {
+            priority,
+            deadline_ms,
+            progress: Some(sink.clone()),
+            ..request(1_000.0)
+        };
+        let order = |engine: &ExecutionEngine| -> Vec<(i32, f64)> {
+            engine.queue.snapshot().pending.iter().map(|pending| (pending.priority, pending.expected_edge)).collect()
+        };
+
+        // 調度器啟動前入隊，只排序不執行
+        let low = engine.queue.push(queued(1, None), 5.0).unwrap();
+        let thin = engine.queue.push(queued(5, None), 1.0).unwrap();
+        let rich = engine.queue.push(queued(5, None), 9.0).unwrap();
+        assert_eq!(order(&engine), [(5, 9.0), (5, 1.0), (1, 5.0)]);
+        assert!(engine.queue.push(queued(0, None), 50.0).unwrap_err().contains("優先級過低"));
+        let expiring = engine.queue.push(queued(7, Some(START_MS + 1_000)), 0.0).unwrap();
+        let shed = low.await.unwrap();
+        assert!(shed.error_message.unwrap().contains("擠出"));
+        assert_eq!(order(&engine), [(7, 0.0), (5, 9.0), (5, 1.0)]);
+        let snapshot = engine.queue.snapshot();
+        assert_eq!((snapshot.shed, snapshot.rejected), (1, 1));
+
+        engine.env.clock.sleep(Duration::from_secs(2)).await;
+        connect_sessions(&engine).await;
+        spawn(Arc::clone(&engine));
+        let expired = expiring.await.unwrap();
+        assert!(expired.error_message.unwrap().contains("截止時間"));
+        let rich = rich.await.unwrap();
+        let thin = thin.await.unwrap();
+        assert_eq!(rich.status, "success", "{:?}", rich.error_message);
+        assert_eq!(thin.status, "success", "{:?}", thin.error_message);
+        // 並發上限為 1，結算順序即出隊順序
+        let mut settled = Vec::new();
+        while let Ok(event) = progress.try_recv() {
+            if event.stage == "settled" {
+                settled.push(event.execution_id);
+            }
+        }
+        assert_eq!(settled, [rich.execution_id.unwrap(), thin.execution_id.unwrap()]);
+        let snapshot = engine.queue.snapshot();
+        assert_eq!((snapshot.dispatched, snapshot.expired), (2, 1));
+        assert!(snapshot.pending.is_empty() && snapshot.running.is_empty());
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[tokio::test]
+    async fn higher_priority_preempts_executions_waiting_for_exchange_budget() {
+        for mode in [config::PreemptionMode::Abort, config::PreemptionMode::Requeue] {
+            let mut config = config::EngineConfig::default();
+            for exchange in ["binance", "bybit"] {
+                let settings = config.exchanges.entry(exchange.to_string()).or_default();
+                settings.simulation.reject_rate = 0.0;
+                // 權重每 200ms 恢復 1，耗盡後下單前的槓桿查詢需排隊等待
+                settings.rate_limit.weight_limit = 10;
+                settings.rate_limit.weight_window_secs = 2;
+                settings.rate_limit.reserved_weight_fraction = 0.0;
+                settings.rate_limit.max_market_data_wait_ms = 2_000;
+            }
+            config.execution_queue.max_concurrent_per_exchange = 1;
+            config.execution_queue.preemption.enabled = true;
+            config.execution_queue.preemption.mode = mode;
+            let (engine, path) = build(&format!("preemption-{:?}", mode), config, Environment::simulated(START_MS, 1));
+            let engine = Arc::new(engine);
+            spawn(Arc::clone(&engine));
+            connect_sessions(&engine).await;
+            for _ in 0..10 {
+                engine.exchanges["binance"].scheduler.acquire(rate_limit::RequestKind::MarketData, 1).await.unwrap();
+            }
+
+            let (sink, mut progress) = tokio::sync::mpsc::unbounded_channel();
+            let low = ArbitrageRequest {
+                priority: 1,
+                progress: Some(sink),
+                ..request(1_000.0)
+            };
+            let low = tokio::spawn({
+                let engine = Arc::clone(&engine);
+                async move { engine.execute(low).await }
+            });
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            let high = engine.execute(ArbitrageRequest { priority: 9, ..request(1_000.0) }).await;
+            assert_ne!(high.status, "preempted");
+            let low = low.await.unwrap();
+            let mut stages = Vec::new();
+            while let Ok(event) = progress.try_recv() {
+                stages.push(event.stage);
+            }
+            match mode {
+                config::PreemptionMode::Abort => {
+                    assert_eq!(low.status, "preempted", "{:?}", low.error_message);
+                    assert!(stages.is_empty());
+                }
+                // 推送 preempted 後重新排隊，高優先級執行完成後再執行
+                config::PreemptionMode::Requeue => {
+                    assert_ne!(low.status, "preempted");
+                    assert_eq!(stages.first().map(String::as_str), Some("preempted"));
+                }
+            }
+            assert_eq!(engine.queue.snapshot().preempted, 1);
+            let _ = std::fs::remove_file(path);
+        }
+    }
+}
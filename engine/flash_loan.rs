@@ -0,0 +1,700 @@
+use super::config::FlashLoanConfig;
+use super::gas::{GasOptimizer, GasQuote, ProfitGate};
+use super::tx_simulation::{self, OnchainError};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::str::FromStr;
+use std::sync::Arc;
+use tracing::{debug, info, warn};
+use web3::ethabi::{Contract, Event, RawLog, Token};
+use super::wallet::{WalletManager, WalletStatus};
+use super::rpc::RpcTransport;
+use web3::types::{
+    Address, BlockId, BlockNumber, Bytes, CallRequest, Log, TransactionParameters, U256,
+};
+use web3::Web3;
+
+// 只保留用到的合約接口片段
+const AAVE_POOL_ABI: &str = r#"[
+    {"type":"function","name":"flashLoanSimple","stateMutability":"nonpayable","outputs":[],"inputs":[
+        {"name":"receiverAddress","type":"address"},
+        {"name":"asset","type":"address"},
+        {"name":"amount","type":"uint256"},
+        {"name":"params","type":"bytes"},
+        {"name":"referralCode","type":"uint16"}]},
+    {"type":"event","name":"FlashLoan","anonymous":false,"inputs":[
+        {"name":"target","type":"address","indexed":true},
+        {"name":"initiator","type":"address","indexed":false},
+        {"name":"asset","type":"address","indexed":true},
+        {"name":"amount","type":"uint256","indexed":false},
+        {"name":"interestRateMode","type":"uint8","indexed":false},
+        {"name":"premium","type":"uint256","indexed":false},
+        {"name":"referralCode","type":"uint16","indexed":true}]}
+]"#;
+const BALANCER_VAULT_ABI: &str = r#"[
+    {"type":"function","name":"flashLoan","stateMutability":"nonpayable","outputs":[],"inputs":[
+        {"name":"recipient","type":"address"},
+        {"name":"tokens","type":"address[]"},
+        {"name":"amounts","type":"uint256[]"},
+        {"name":"userData","type":"bytes"}]},
+    {"type":"event","name":"FlashLoan","anonymous":false,"inputs":[
+        {"name":"recipient","type":"address","indexed":true},
+        {"name":"token","type":"address","indexed":true},
+        {"name":"amount","type":"uint256","indexed":false},
+        {"name":"feeAmount","type":"uint256","indexed":false}]}
+]"#;
+const DYDX_SOLO_ABI: &str = r#"[
+    {"type":"function","name":"operate","stateMutability":"nonpayable","outputs":[],"inputs":[
+        {"name":"accounts","type":"tuple[]","components":[
+            {"name":"owner","type":"address"},
+            {"name":"number","type":"uint256"}]},
+        {"name":"actions","type":"tuple[]","components":[
+            {"name":"actionType","type":"uint8"},
+            {"name":"accountId","type":"uint256"},
+            {"name":"amount","type":"tuple","components":[
+                {"name":"sign","type":"bool"},
+                {"name":"denomination","type":"uint8"},
+                {"name":"ref","type":"uint8"},
+                {"name":"value","type":"uint256"}]},
+            {"name":"primaryMarketId","type":"uint256"},
+            {"name":"secondaryMarketId","type":"uint256"},
+            {"name":"otherAddress","type":"address"},
+            {"name":"otherAccountId","type":"uint256"},
+            {"name":"data","type":"bytes"}]}]}
+]"#;
+const ERC20_ABI: &str = r#"[
+    {"type":"function","name":"balanceOf","stateMutability":"view","inputs":[
+        {"name":"account","type":"address"}],"outputs":[{"name":"","type":"uint256"}]},
+    {"type":"event","name":"Transfer","anonymous":false,"inputs":[
+        {"name":"from","type":"address","indexed":true},
+        {"name":"to","type":"address","indexed":true},
+        {"name":"value","type":"uint256","indexed":false}]}
+]"#;
+// dYdX 不收手續費，但歸還時需多存入 2 wei
+const DYDX_REPAY_WEI: u64 = 2;
+
+pub type ProviderFuture<'a, T> = Pin<Box<dyn Future<Output = Result<T, String>> + Send + 'a>>;
+
+pub trait FlashLoanProvider: Send + Sync {
+    fn name(&self) -> &'static str;
+    // 發起閃電貸時調用的合約
+    fn address(&self) -> Address;
+    fn fee_bps(&self) -> f64;
+    // 當前可借出的資產數量（最小單位）
+    fn max_loanable<'a>(&'a self, web3: &'a Web3<RpcTransport>, asset: Address) -> ProviderFuture<'a, U256>;
+    // 構造發起閃電貸的 calldata；params 原樣轉交接收合約的回調
+    fn build_tx(&self, receiver: Address, asset: Address, amount: U256, params: Vec<u8>) -> Result<Vec<u8>, String>;
+    // 用於解碼回執日誌的合約接口
+    fn abi(&self) -> &Contract;
+    // 從解碼後的回執日誌讀取實際手續費；讀不到時按 fee_bps 估算
+    fn fee_from_logs(&self, _logs: &[DecodedLog]) -> Option<U256> {
+        None
+    }
+}
+
+struct AaveV3 {
+    pool: Address,
+    // aToken 持有出借池中的全部可借資產
+    a_token: Address,
+    fee_bps: f64,
+    referral_code: u16,
+    abi: Contract,
+}
+
+impl FlashLoanProvider for AaveV3 {
+    fn name(&self) -> &'static str {
+        "aave_v3"
+    }
+
+    fn address(&self) -> Address {
+        self.pool
+    }
+
+    fn fee_bps(&self) -> f64 {
+        self.fee_bps
+    }
+
+    fn max_loanable<'a>(&'a self, web3: &'a Web3<RpcTransport>, asset: Address) -> ProviderFuture<'a, U256> {
+        Box::pin(token_balance(web3, asset, self.a_token, None))
+    }
+
+    fn build_tx(&self, receiver: Address, asset: Address, amount: U256, params: Vec<u8>) -> Result<Vec<u8>, String> {
+        encode_call(
+            &self.abi,
+            "flashLoanSimple",
+            &[
+                Token::Address(receiver),
+                Token::Address(asset),
+                Token::Uint(amount),
+                Token::Bytes(params),
+                Token::Uint(self.referral_code.into()),
+            ],
+        )
+    }
+
+    fn abi(&self) -> &Contract {
+        &self.abi
+    }
+
+    fn fee_from_logs(&self, logs: &[DecodedLog]) -> Option<U256> {
+        logged_fee(logs, self.pool, "premium")
+    }
+}
+
+struct Balancer {
+    vault: Address,
+    abi: Contract,
+}
+
+impl FlashLoanProvider for Balancer {
+    fn name(&self) -> &'static str {
+        "balancer"
+    }
+
+    fn address(&self) -> Address {
+        self.vault
+    }
+
+    fn fee_bps(&self) -> f64 {
+        0.0
+    }
+
+    fn max_loanable<'a>(&'a self, web3: &'a Web3<RpcTransport>, asset: Address) -> ProviderFuture<'a, U256> {
+        Box::pin(token_balance(web3, asset, self.vault, None))
+    }
+
+    fn build_tx(&self, receiver: Address, asset: Address, amount: U256, params: Vec<u8>) -> Result<Vec<u8>, String> {
+        encode_call(
+            &self.abi,
+            "flashLoan",
+            &[
+                Token::Address(receiver),
+                Token::Array(vec![Token::Address(asset)]),
+                Token::Array(vec![Token::Uint(amount)]),
+                Token::Bytes(params),
+            ],
+        )
+    }
+
+    fn abi(&self) -> &Contract {
+        &self.abi
+    }
+
+    fn fee_from_logs(&self, logs: &[DecodedLog]) -> Option<U256> {
+        logged_fee(logs, self.vault, "feeAmount")
+    }
+}
+
+// dYdX Solo 通過 operate 的 Withdraw → Call → Deposit 三個動作實現閃電貸；
+// 賬戶屬於接收合約，簽名錢包需先被接收合約設為 operator
+struct Dydx {
+    solo: Address,
+    market_id: u64,
+    abi: Contract,
+}
+
+impl Dydx {
+    const ACTION_DEPOSIT: u8 = 0;
+    const ACTION_WITHDRAW: u8 = 1;
+    const ACTION_CALL: u8 = 8;
+
+    fn action(action_type: u8, sign: bool, value: U256, market_id: u64, other: Address, data: Vec<u8>) -> Token {
+        Token::Tuple(vec![
+            Token::Uint(action_type.into()),
+            Token::Uint(U256::zero()),
+            // 以 Wei 計價的增量金額
+            Token::Tuple(vec![
+                Token::Bool(sign),
+                Token::Uint(U256::zero()),
+                Token::Uint(U256::zero()),
+                Token::Uint(value),
+            ]),
+            Token::Uint(market_id.into()),
+            Token::Uint(U256::zero()),
+            Token::Address(other),
+            Token::Uint(U256::zero()),
+            Token::Bytes(data),
+        ])
+    }
+}
+
+impl FlashLoanProvider for Dydx {
+    fn name(&self) -> &'static str {
+        "dydx"
+    }
+
+    fn address(&self) -> Address {
+        self.solo
+    }
+
+    fn fee_bps(&self) -> f64 {
+        0.0
+    }
+
+    fn max_loanable<'a>(&'a self, web3: &'a Web3<RpcTransport>, asset: Address) -> ProviderFuture<'a, U256> {
+        Box::pin(token_balance(web3, asset, self.solo, None))
+    }
+
+    fn build_tx(&self, receiver: Address, _asset: Address, amount: U256, params: Vec<u8>) -> Result<Vec<u8>, String> {
+        let account = Token::Tuple(vec![Token::Address(receiver), Token::Uint(1.into())]);
+        let actions = vec![
+            Self::action(Self::ACTION_WITHDRAW, false, amount, self.market_id, receiver, Vec::new()),
+            Self::action(Self::ACTION_CALL, false, U256::zero(), 0, receiver, params),
+            Self::action(
+                Self::ACTION_DEPOSIT,
+                true,
+                amount + DYDX_REPAY_WEI,
+                self.market_id,
+                receiver,
+                Vec::new(),
+            ),
+        ];
+        encode_call(&self.abi, "operate", &[Token::Array(vec![account]), Token::Array(actions)])
+    }
+
+    fn abi(&self) -> &Contract {
+        &self.abi
+    }
+
+    fn fee_from_logs(&self, _logs: &[DecodedLog]) -> Option<U256> {
+        Some(DYDX_REPAY_WEI.into())
+    }
+}
+
+pub struct FlashLoanReceipt {
+    pub provider: &'static str,
+    pub tx_hash: String,
+    pub gas_used: u64,
+    // gas_used × 實際成交 gas 價格（以該鏈原生代幣計）
+    pub gas_cost: f64,
+    // 閃電貸來源收取的手續費（以借入資產計）
+    pub premium: f64,
+    // 收益地址在交易所在區塊前後的餘額變化（以借入資產計）
+    pub profit: f64,
+    pub proof: SettlementProof,
+}
+
+// 可獨立核驗的結算證明：審計方可用 tx_hash 與區塊頭向任意節點比對回執與日誌
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SettlementProof {
+    pub chain_id: u64,
+    #[serde(default)]
+    pub provider: String,
+    pub tx_hash: String,
+    pub receipt: serde_json::Value,
+    pub block_header: serde_json::Value,
+    pub decoded_logs: Vec<DecodedLog>,
+    pub profit_recipient: String,
+    // 收益地址在交易前後區塊的借入資產餘額（最小單位）
+    pub balance_before: String,
+    pub balance_after: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DecodedLog {
+    pub log_index: Option<u64>,
+    pub address: String,
+    pub event: String,
+    pub params: BTreeMap<String, String>,
+}
+
+pub fn load_abi(json: &str) -> Contract {
+    Contract::load(json.as_bytes()).expect("內置合約 ABI 無效")
+}
+
+pub fn encode_call(abi: &Contract, function: &str, tokens: &[Token]) -> Result<Vec<u8>, String> {
+    abi.function(function)
+        .and_then(|function| function.encode_input(tokens))
+        .map_err(|e| format!("構造 {} 調用失敗: {}", function, e))
+}
+
+fn logged_fee(logs: &[DecodedLog], emitter: Address, param: &str) -> Option<U256> {
+    let emitter = format!("{:?}", emitter);
+    logs.iter()
+        .find(|log| log.event == "FlashLoan" && log.address == emitter)
+        .and_then(|log| log.params.get(param))
+        .and_then(|fee| U256::from_dec_str(fee).ok())
+}
+
+// holder 在指定區塊（缺省為最新）持有的 ERC20 餘額
+pub async fn token_balance(web3: &Web3<RpcTransport>, asset: Address, holder: Address, block: Option<u64>) -> Result<U256, String> {
+    let erc20 = load_abi(ERC20_ABI);
+    let balance_of = erc20.function("balanceOf").map_err(|e| e.to_string())?;
+    let data = balance_of
+        .encode_input(&[Token::Address(holder)])
+        .map_err(|e| e.to_string())?;
+    let output = web3
+        .eth()
+        .call(
+            CallRequest {
+                to: Some(asset),
+                data: Some(Bytes(data)),
+                ..CallRequest::default()
+            },
+            block.map(|block| BlockId::Number(BlockNumber::Number(block.into()))),
+        )
+        .await
+        .map_err(|e| format!("查詢 {:?} 餘額失敗: {}", holder, e))?;
+    balance_of
+        .decode_output(&output.0)
+        .map_err(|e| e.to_string())?
+        .into_iter()
+        .next()
+        .and_then(Token::into_uint)
+        .ok_or_else(|| "balanceOf 返回值無效".to_string())
+}
+
+// 地址按 0x 十六進制、整數按十進制輸出，便於與區塊瀏覽器對照
+fn token_string(token: Token) -> String {
+    match token {
+        Token::Address(address) => format!("{:?}", address),
+        Token::Uint(value) | Token::Int(value) => value.to_string(),
+        other => other.to_string(),
+    }
+}
+
+fn address(field: &str, value: &str) -> Result<Address, String> {
+    Address::from_str(value).map_err(|e| format!("flash_loan.{} 不是有效地址: {}", field, e))
+}
+
+pub struct FlashLoanExecutor {
+    web3: Web3<RpcTransport>,
+    wallets: Arc<WalletManager>,
+    // 按配置順序排列，手續費相同時靠前者優先
+    providers: Vec<Box<dyn FlashLoanProvider>>,
+    erc20: Contract,
+    // 同時實現 Aave executeOperation、Balancer receiveFlashLoan 與 dYdX callFunction 的套利合約
+    receiver: Address,
+    asset: Address,
+    profit_recipient: Address,
+    config: FlashLoanConfig,
+}
+
+impl FlashLoanExecutor {
+    // 未配置 RPC 或接收合約時返回 None，引擎繼續使用模擬執行
+    // 熱錢包與 DEX 執行共用，由調用方加載
+    pub fn connect(config: &FlashLoanConfig, wallets: Option<Arc<WalletManager>>) -> Result<Option<Self>, String> {
+        let (Some(wallets), Some(receiver)) = (wallets, &config.receiver_address) else {
+            return Ok(None);
+        };
+        let web3 = wallets.web3().clone();
+
+        let mut providers: Vec<Box<dyn FlashLoanProvider>> = Vec::new();
+        for name in &config.providers {
+            match name.as_str() {
+                "aave" => providers.push(Box::new(AaveV3 {
+                    pool: address("aave.pool_address", &config.aave.pool_address)?,
+                    a_token: address("aave.a_token_address", &config.aave.a_token_address)?,
+                    fee_bps: config.aave.fee_bps,
+                    referral_code: config.aave.referral_code,
+                    abi: load_abi(AAVE_POOL_ABI),
+                })),
+                "balancer" => providers.push(Box::new(Balancer {
+                    vault: address("balancer.vault_address", &config.balancer.vault_address)?,
+                    abi: load_abi(BALANCER_VAULT_ABI),
+                })),
+                "dydx" => providers.push(Box::new(Dydx {
+                    solo: address("dydx.solo_margin_address", &config.dydx.solo_margin_address)?,
+                    market_id: config.dydx.market_id,
+                    abi: load_abi(DYDX_SOLO_ABI),
+                })),
+                other => return Err(format!("未知的閃電貸來源: {}", other)),
+            }
+        }
+
+        let receiver = address("receiver_address", receiver)?;
+        let executor = Self {
+            web3,
+            wallets,
+            providers,
+            erc20: load_abi(ERC20_ABI),
+            receiver,
+            asset: address("asset_address", &config.asset_address)?,
+            profit_recipient: match &config.profit_recipient {
+                Some(recipient) => address("profit_recipient", recipient)?,
+                None => receiver,
+            },
+            config: config.clone(),
+        };
+        info!(
+            providers = ?config.providers,
+            receiver = ?receiver,
+            wallets = ?executor.wallets.addresses(),
+            chain_id = config.chain_id,
+            "鏈上閃電貸已配置"
+        );
+        Ok(Some(executor))
+    }
+
+    pub async fn wallet_status(&self) -> Vec<WalletStatus> {
+        self.wallets.status().await
+    }
+
+    pub fn profit_recipient(&self) -> Address {
+        self.profit_recipient
+    }
+
+    // 最便宜來源的手續費，用於執行前估算成本
+    pub fn min_fee_bps(&self) -> f64 {
+        self.providers.iter().map(|provider| provider.fee_bps()).fold(f64::INFINITY, f64::min)
+    }
+
+    // 按手續費從低到高嘗試，選擇第一個流動性足夠的來源
+    async fn select(&self, amount: U256) -> Result<&dyn FlashLoanProvider, String> {
+        let mut ranked: Vec<&dyn FlashLoanProvider> = self.providers.iter().map(|p| p.as_ref()).collect();
+        ranked.sort_by(|a, b| a.fee_bps().total_cmp(&b.fee_bps()));
+        for provider in ranked {
+            match provider.max_loanable(self.wallets.verified_web3(), self.asset).await {
+                Ok(available) if available >= amount => return Ok(provider),
+                Ok(available) => {
+                    debug!(provider = provider.name(), available = %available, "閃電貸來源流動性不足");
+                }
+                Err(error) => warn!(provider = provider.name(), %error, "查詢閃電貸流動性失敗"),
+            }
+        }
+        Err(format!("沒有閃電貸來源可借出 {}", self.to_asset(amount)))
+    }
+
+    // 借入 amount（資產單位）；接收合約的回調收到 abi.encode(symbol, minProfit)，
+    // 收益不足 min_profit 時應回滾
+    pub async fn execute(
+        &self,
+        amount: f64,
+        symbol: &str,
+        min_profit: f64,
+        gas: &GasOptimizer,
+        quote: &GasQuote,
+        profitable: &ProfitGate<'_>,
+    ) -> Result<FlashLoanReceipt, OnchainError> {
+        let amount_units = self.to_units(amount);
+        let provider = self.select(amount_units).await?;
+        let params = web3::ethabi::encode(&[
+            Token::String(symbol.to_string()),
+            Token::Uint(self.to_units(min_profit.max(0.0))),
+        ]);
+        let calldata = provider.build_tx(self.receiver, self.asset, amount_units, params)?;
+        debug!(provider = provider.name(), fee_bps = provider.fee_bps(), "已選擇閃電貸來源");
+
+        // estimateGas 會在合約回滾（例如無利可圖）時直接失敗，不必上鏈
+        let wallet = self.wallets.next();
+        let estimated = self
+            .wallets
+            .verified_web3()
+            .eth()
+            .estimate_gas(
+                CallRequest {
+                    from: Some(wallet.address()),
+                    to: Some(provider.address()),
+                    data: Some(Bytes(calldata.clone())),
+                    ..CallRequest::default()
+                },
+                None,
+            )
+            .await
+            .map_err(|e| format!("閃電貸 gas 估算失敗: {}", e))?;
+        let estimated = u64::try_from(estimated).map_err(|_| format!("閃電貸 gas 估算超出範圍: {}", estimated))?;
+        let gas_limit = (estimated as f64 * self.config.gas_buffer) as u64;
+        gas.check_budget(quote, gas_limit).map_err(|e| format!("閃電貸{}", e))?;
+        debug!(estimated, gas_limit, urgency = %quote.urgency, "閃電貸 gas 估算");
+        // 合約在收益低於 min_profit 時回滾；按實時估算的 gas 費扣除後仍須達到淨收益門檻，模擬得到實際消耗時再查一次
+        profitable(estimated).map_err(|e| format!("閃電貸{}", e))?;
+
+        // nonce 由錢包管理器分配，以 EIP-1559（type 2）交易按報價出價
+        let hash = self
+            .wallets
+            .submit_checked(
+                wallet,
+                TransactionParameters {
+                    to: Some(provider.address()),
+                    gas: gas_limit.into(),
+                    data: Bytes(calldata),
+                    transaction_type: Some(2.into()),
+                    max_fee_per_gas: Some(quote.max_fee_per_gas.into()),
+                    max_priority_fee_per_gas: Some(quote.max_priority_fee_per_gas.into()),
+                    ..TransactionParameters::default()
+                },
+                profitable,
+            )
+            .await?;
+        let tx_hash = format!("{:?}", hash);
+        debug!(%tx_hash, wallet = wallet.name(), "閃電貸交易已發送");
+        let receipt = self
+            .wallets
+            .confirm(hash, self.config.confirmations, "閃電貸交易")
+            .await?;
+        if receipt.status != Some(1.into()) {
+            let revert = tx_simulation::inspect_revert(&self.web3, &receipt, quote.max_fee_per_gas).await;
+            return Err(OnchainError::reverted("閃電貸交易", revert));
+        }
+        let block = receipt
+            .block_number
+            .ok_or_else(|| format!("閃電貸回執缺少區塊號: {}", tx_hash))?
+            .as_u64();
+
+        let decoded_logs = self.decode_logs(&receipt.logs);
+        let premium = provider.fee_from_logs(&decoded_logs).unwrap_or_else(|| {
+            self.to_units(amount * provider.fee_bps() / 10_000.0)
+        });
+
+        let before = token_balance(&self.web3, self.asset, self.profit_recipient, Some(block.saturating_sub(1))).await?;
+        let after = token_balance(&self.web3, self.asset, self.profit_recipient, Some(block)).await?;
+        let profit = self.to_asset(after) - self.to_asset(before);
+        let gas_used = receipt.gas_used.unwrap_or_default().as_u64();
+        let gas_price = receipt
+            .effective_gas_price
+            .map(|price| price.as_u64())
+            .unwrap_or(quote.max_fee_per_gas);
+        let gas_cost = gas_used as f64 * gas_price as f64 / 1e18;
+        let block_header = self
+            .web3
+            .eth()
+            .block(BlockId::Number(BlockNumber::Number(block.into())))
+            .await
+            .map_err(|e| format!("獲取區塊頭失敗 {}: {}", block, e))?
+            .ok_or_else(|| format!("區塊 {} 不存在", block))?;
+
+        info!(provider = provider.name(), %tx_hash, block, gas_used, profit, "閃電貸交易已確認");
+        let proof = SettlementProof {
+            chain_id: self.config.chain_id,
+            provider: provider.name().to_string(),
+            tx_hash: tx_hash.clone(),
+            receipt: serde_json::json!(receipt),
+            block_header: serde_json::json!(block_header),
+            decoded_logs,
+            profit_recipient: format!("{:?}", self.profit_recipient),
+            balance_before: before.to_string(),
+            balance_after: after.to_string(),
+        };
+        Ok(FlashLoanReceipt {
+            provider: provider.name(),
+            tx_hash,
+            gas_used,
+            gas_cost,
+            premium: self.to_asset(premium),
+            profit,
+            proof,
+        })
+    }
+
+    // 用各來源與 ERC20 的事件定義解碼回執日誌，無法識別的日誌保留在原始回執中
+    fn decode_logs(&self, logs: &[Log]) -> Vec<DecodedLog> {
+        let events: Vec<&Event> = self
+            .providers
+            .iter()
+            .flat_map(|provider| provider.abi().events())
+            .chain(self.erc20.events())
+            .collect();
+        logs.iter()
+            .filter_map(|log| {
+                let topic = log.topics.first()?;
+                let event = events.iter().find(|event| event.signature() == *topic)?;
+                let parsed = event
+                    .parse_log(RawLog {
+                        topics: log.topics.clone(),
+                        data: log.data.0.clone(),
+                    })
+                    .ok()?;
+                Some(DecodedLog {
+                    log_index: log.log_index.map(|index| index.as_u64()),
+                    address: format!("{:?}", log.address),
+                    event: event.name.clone(),
+                    params: parsed
+                        .params
+                        .into_iter()
+                        .map(|param| (param.name, token_string(param.value)))
+                        .collect(),
+                })
+            })
+            .collect()
+    }
+
+    fn to_units(&self, amount: f64) -> U256 {
+        U256::from((amount * 10f64.powi(self.config.asset_decimals as i32)).round() as u128)
+    }
+
+    fn to_asset(&self, units: U256) -> f64 {
+        units.low_u128() as f64 / 10f64.powi(self.config.asset_decimals as i32)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bench::START_MS;
+    use crate::deterministic_sim::{build, connect_sessions, flash_loan_config, flash_loan_node, request, wallet_env};
+    use crate::{config, EngineCommand};
+    use std::collections::HashMap;
+
+    // 鏈上閃電貸成交後，回執、區塊頭、解碼日誌與收益地址前後餘額作為結算證明寫入事件日誌，按執行 ID 查詢
+    #[tokio::test]
+    async fn flash_loan_settlement_proof_is_journaled_and_served_by_execution_id() {
+        use web3::types::Address;
+        let recipient = Address::from_low_u64_be(0xf1);
+        let vault: Address = config::BalancerConfig::default().vault_address.parse().unwrap();
+        let url = flash_loan_node(Arc::new(std::sync::Mutex::new([(vault, u64::MAX)].into())), recipient).await;
+        let (engine, path) = build("settlement-proof", flash_loan_config(url), wallet_env());
+        let engine = Arc::new(engine);
+        connect_sessions(&engine).await;
+        engine.quotes.record_predicted_rate("binance", "BTCUSDT", 0.0060, START_MS);
+        engine.quotes.record_predicted_rate("bybit", "BTCUSDT", 0.0010, START_MS);
+
+        let response = engine.execute_funding_rate_arbitrage(request(10_000.0)).await;
+        assert_eq!(response.status, "success", "{:?}", response.error_message);
+        assert_eq!((response.profit, response.gas_used), (Some(20.0), Some(200_000)));
+        let execution_id = response.execution_id.unwrap();
+
+        let proof = engine.events.settlement_proof(&execution_id).unwrap();
+        assert_eq!((proof.chain_id, proof.provider.as_str()), (1, "balancer"));
+        assert_eq!(proof.tx_hash, format!("{:?}", web3::types::H256::from_low_u64_be(0x51)));
+        assert_eq!(proof.receipt["transactionHash"].as_str(), Some(proof.tx_hash.as_str()));
+        assert_eq!(proof.block_header["number"], "0x10");
+        assert_eq!(proof.profit_recipient, format!("{:?}", recipient));
+        assert_eq!((proof.balance_before.as_str(), proof.balance_after.as_str()), ("1000000000", "1020000000"));
+        let transfer = &proof.decoded_logs[0];
+        assert_eq!((transfer.event.as_str(), transfer.log_index), ("Transfer", Some(0)));
+        assert_eq!(transfer.params["to"], format!("{:?}", recipient));
+        assert_eq!(transfer.params["value"], "20000000");
+
+        let served = engine.handle_command(EngineCommand::GetSettlementProof { execution_id }).await;
+        assert_eq!(served.data.unwrap()["tx_hash"].as_str(), Some(proof.tx_hash.as_str()));
+        let missing = engine.handle_command(EngineCommand::GetSettlementProof { execution_id: "unknown".to_string() }).await;
+        assert_eq!(missing.status, "error");
+        let _ = std::fs::remove_file(path);
+    }
+
+    // 按手續費從低到高選擇流動性足夠的來源：Balancer 與 dYdX 免手續費（dYdX 歸還時多付 2 wei），Aave 收 5 bps
+    #[tokio::test]
+    async fn flash_loans_pick_the_cheapest_provider_with_enough_liquidity() {
+        use web3::types::Address;
+        let address = |value: String| value.parse::<Address>().unwrap();
+        let defaults = config::FlashLoanConfig::default();
+        let (vault, solo, a_token) = (
+            address(defaults.balancer.vault_address),
+            address(defaults.dydx.solo_margin_address),
+            address(defaults.aave.a_token_address),
+        );
+        let liquidity = Arc::new(std::sync::Mutex::new(HashMap::new()));
+        let url = flash_loan_node(Arc::clone(&liquidity), Address::from_low_u64_be(0xf1)).await;
+        let (engine, path) = build("flash-providers", flash_loan_config(url), wallet_env());
+        let quote = engine.default_chain().gas_optimizer.quote(5);
+        // 借入 10000 USDC（6 位小數）
+        let needed = 10_000_000_000u64;
+        let pick = async |balances: [u64; 3]| {
+            *liquidity.lock().unwrap() = [(vault, balances[0]), (solo, balances[1]), (a_token, balances[2])].into();
+            engine
+                .execute_flash_loan_arbitrage("providers", &request(10_000.0), 0.005, &quote, false, None)
+                .await
+                .map(|outcome| (outcome.orders[0].exchange.clone(), outcome.fees))
+                .map_err(|failure| failure.error)
+        };
+
+        assert_eq!(pick([needed, needed, needed]).await.unwrap(), ("balancer".to_string(), 0.0));
+        assert_eq!(pick([needed - 1, needed, needed]).await.unwrap(), ("dydx".to_string(), 0.000002));
+        assert_eq!(pick([0, 0, needed]).await.unwrap(), ("aave_v3".to_string(), 5.0));
+        let error = pick([needed - 1, needed - 1, needed - 1]).await.err().unwrap();
+        assert!(error.contains("沒有閃電貸來源可借出 10000"), "{}", error);
+        let _ = std::fs::remove_file(path);
+    }
+}
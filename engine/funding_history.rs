@@ -0,0 +1,419 @@
+use super::price_guard::PriceGuard;
+use super::ExecutionEngine;
+use dashmap::DashMap;
+use serde::Serialize;
+use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{debug, warn};
+
+pub const SAMPLE_INTERVAL: Duration = Duration::from_secs(60);
+// 每個交易所/交易對保留 24 小時的分鐘樣本
+const WINDOW_SIZE: usize = 24 * 60;
+const DEFAULT_SYMBOLS: [&str; 2] = ["BTCUSDT", "ETHUSDT"];
+// 請求登記的交易對閒置超過 24 小時後停止採集並丟棄其樣本；默認交易對不過期
+const IDLE_EXPIRY_MS: i64 = 24 * 3_600_000;
+// 跟蹤的交易對上限（含默認交易對），超出時淘汰最久未請求的一個
+const MAX_TRACKED_SYMBOLS: usize = 256;
+
+#[derive(Debug, Clone)]
+pub struct FundingSample {
+    pub exchange: String,
+    pub symbol: String,
+    pub sampled_at_ms: i64,
+    pub funding_rate: f64,
+    pub predicted_rate: f64,
+}
+
+// 資金費日曆中的一項：下一次結算時間與最近一次採樣的費率，尚未採樣時費率為 None
+#[derive(Debug, Clone, Serialize)]
+pub struct CalendarEntry {
+    pub exchange: String,
+    pub symbol: String,
+    pub funding_interval_hours: u64,
+    pub next_funding_ms: i64,
+    pub current_rate: Option<f64>,
+    pub predicted_rate: Option<f64>,
+    pub sampled_at_ms: Option<i64>,
+}
+
+// 某交易對在兩個交易所間的價差統計（spread = 交易所 A 費率 - 交易所 B 費率）
+#[derive(Debug, Clone, Serialize)]
+pub struct SpreadStats {
+    pub symbol: String,
+    pub exchange_a: String,
+    pub exchange_b: String,
+    pub samples: usize,
+    pub latest_spread: f64,
+    pub latest_predicted_spread: f64,
+    pub mean_spread: f64,
+    pub volatility: f64,
+    pub z_score: f64,
+}
+
+// 每次請求都會登記交易對、採集任務與掃描器並發讀寫樣本，均按鍵分片加鎖
+pub struct FundingHistory {
+    // 交易對 -> 最近一次請求時間；默認交易對為 None
+    symbols: DashMap<String, Option<i64>>,
+    // (exchange, symbol) -> 按時間排列的樣本
+    samples: DashMap<(String, String), VecDeque<FundingSample>>,
+}
+
+impl FundingHistory {
+    pub fn new() -> Self {
+        Self {
+            symbols: DEFAULT_SYMBOLS.iter().map(|s| (s.to_string(), None)).collect(),
+            samples: DashMap::new(),
+        }
+    }
+
+    // 請求中出現的交易對自動納入採集；已跟蹤時只刷新請求時間
+    pub fn track(&self, symbol: &str, now_ms: i64) {
+        if let Some(mut requested) = self.symbols.get_mut(symbol) {
+            if requested.is_some() {
+                *requested = Some(now_ms);
+            }
+            return;
+        }
+        self.symbols.insert(symbol.to_string(), Some(now_ms));
+        while self.symbols.len() > MAX_TRACKED_SYMBOLS {
+            let oldest = self
+                .symbols
+                .iter()
+                .filter_map(|entry| entry.value().map(|requested| (requested, entry.key().clone())))
+                .min();
+            let Some((_, oldest)) = oldest else { break };
+            self.forget(&oldest);
+        }
+    }
+
+    // 停止採集閒置的交易對並丟棄其樣本窗口，返回被移除的交易對
+    pub fn expire_idle(&self, now_ms: i64) -> Vec<String> {
+        let idle: Vec<String> = self
+            .symbols
+            .iter()
+            .filter(|entry| entry.value().is_some_and(|requested| now_ms - requested > IDLE_EXPIRY_MS))
+            .map(|entry| entry.key().clone())
+            .collect();
+        for symbol in &idle {
+            self.forget(symbol);
+        }
+        idle
+    }
+
+    fn forget(&self, symbol: &str) {
+        self.symbols.remove(symbol);
+        self.samples.retain(|(_, sym), _| sym != symbol);
+    }
+
+    pub fn symbols(&self) -> Vec<String> {
+        let mut symbols: Vec<String> = self.symbols.iter().map(|entry| entry.key().clone()).collect();
+        symbols.sort();
+        symbols
+    }
+
+    // 每個交易所/交易對最新的一筆樣本
+    pub fn latest(&self) -> Vec<FundingSample> {
+        self.samples.iter().filter_map(|w| w.back().cloned()).collect()
+    }
+
+    pub fn push(&self, sample: FundingSample) {
+        let mut window = self
+            .samples
+            .entry((sample.exchange.clone(), sample.symbol.clone()))
+            .or_default();
+        if window.len() == WINDOW_SIZE {
+            window.pop_front();
+        }
+        window.push_back(sample);
+    }
+
+    // 所有跟蹤交易對在各交易所（只含有永續合約的組合）的下一次結算，按結算時間先後排列；
+    // venues 為交易所名、結算間隔與校正後的交易所當前時間
+    pub fn calendar(
+        &self,
+        venues: &[(&str, u64, i64)],
+        has_perp: impl Fn(&str, &str) -> bool,
+    ) -> Vec<CalendarEntry> {
+        let mut entries = Vec::new();
+        for symbol in self.symbols() {
+            for (exchange, interval_hours, now_ms) in venues {
+                if !has_perp(exchange, &symbol) {
+                    continue;
+                }
+                let latest = self
+                    .samples
+                    .get(&(exchange.to_string(), symbol.clone()))
+                    .and_then(|window| window.back().cloned());
+                entries.push(CalendarEntry {
+                    exchange: exchange.to_string(),
+                    symbol: symbol.clone(),
+                    funding_interval_hours: *interval_hours,
+                    next_funding_ms: next_funding_ms(*now_ms, *interval_hours),
+                    current_rate: latest.as_ref().map(|s| s.funding_rate),
+                    predicted_rate: latest.as_ref().map(|s| s.predicted_rate),
+                    sampled_at_ms: latest.map(|s| s.sampled_at_ms),
+                });
+            }
+        }
+        entries.sort_by(|a, b| {
+            (a.next_funding_ms, &a.exchange, &a.symbol).cmp(&(b.next_funding_ms, &b.exchange, &b.symbol))
+        });
+        entries
+    }
+
+    // 返回各交易所兩兩組合的價差統計，按 |z-score| 由高到低排序
+    pub fn spread_stats(&self, symbol: Option<&str>) -> Vec<SpreadStats> {
+        // 先複製窗口再計算，不在統計期間佔用分片鎖
+        let mut by_symbol: HashMap<String, Vec<(String, VecDeque<FundingSample>)>> = HashMap::new();
+        for item in self.samples.iter() {
+            let ((exchange, sym), window) = item.pair();
+            if symbol.is_some_and(|s| s != sym) {
+                continue;
+            }
+            by_symbol.entry(sym.clone()).or_default().push((exchange.clone(), window.clone()));
+        }
+
+        let mut stats = Vec::new();
+        for (sym, mut venues) in by_symbol {
+            venues.sort_by(|a, b| a.0.cmp(&b.0));
+            for (i, (exchange_a, a)) in venues.iter().enumerate() {
+                for (exchange_b, b) in &venues[i + 1..] {
+                    if let Some(s) = pair_stats(&sym, exchange_a, a, exchange_b, b) {
+                        stats.push(s);
+                    }
+                }
+            }
+        }
+        stats.sort_by(|a, b| b.z_score.abs().total_cmp(&a.z_score.abs()));
+        stats
+    }
+}
+
+pub fn next_funding_ms(now_ms: i64, interval_hours: u64) -> i64 {
+    let interval_ms = interval_hours as i64 * 3_600_000;
+    (now_ms / interval_ms + 1) * interval_ms
+}
+
+// 以採樣時間對齊兩個序列後計算均值、標準差與最新樣本的 z-score
+fn pair_stats(
+    symbol: &str,
+    exchange_a: &str,
+    a: &VecDeque<FundingSample>,
+    exchange_b: &str,
+    b: &VecDeque<FundingSample>,
+) -> Option<SpreadStats> {
+    let b_by_time: HashMap<i64, &FundingSample> = b.iter().map(|s| (s.sampled_at_ms, s)).collect();
+    let aligned: Vec<(&FundingSample, &FundingSample)> = a
+        .iter()
+        .filter_map(|sa| b_by_time.get(&sa.sampled_at_ms).map(|sb| (sa, *sb)))
+        .collect();
+    let (latest_a, latest_b) = *aligned.last()?;
+
+    let spreads: Vec<f64> = aligned.iter().map(|(sa, sb)| sa.funding_rate - sb.funding_rate).collect();
+    let n = spreads.len() as f64;
+    let mean = spreads.iter().sum::<f64>() / n;
+    let variance = spreads.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / n;
+    let volatility = variance.sqrt();
+    let latest_spread = latest_a.funding_rate - latest_b.funding_rate;
+    let z_score = if volatility > 0.0 { (latest_spread - mean) / volatility } else { 0.0 };
+
+    Some(SpreadStats {
+        symbol: symbol.to_string(),
+        exchange_a: exchange_a.to_string(),
+        exchange_b: exchange_b.to_string(),
+        samples: spreads.len(),
+        latest_spread,
+        latest_predicted_spread: latest_a.predicted_rate - latest_b.predicted_rate,
+        mean_spread: mean,
+        volatility,
+        z_score,
+    })
+}
+
+// 後台採集任務：每分鐘對所有交易所與跟蹤的交易對採樣一次
+pub fn spawn_collector(engine: Arc<ExecutionEngine>) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(SAMPLE_INTERVAL);
+        loop {
+            interval.tick().await;
+            collect_once(&engine).await;
+        }
+    });
+}
+
+async fn collect_once(engine: &ExecutionEngine) {
+    // 同一輪樣本使用相同時間戳，便於跨交易所對齊
+    let sampled_at_ms = engine.env.now_ms();
+    let exchanges = engine.venues();
+
+    let expired = engine.funding_history.expire_idle(sampled_at_ms);
+    if !expired.is_empty() {
+        debug!(?expired, "閒置交易對停止資金費率採樣");
+    }
+    for symbol in engine.funding_history.symbols() {
+        // 同時採樣現貨參考價，用於波動率定倉的實現波動率
+        if let Some(price) = PriceGuard::reference_price(engine, &symbol) {
+            engine.sizing.record_price(&symbol, sampled_at_ms, price);
+        }
+        for exchange in &exchanges {
+            if !engine.margin.has_perp(exchange, &symbol) {
+                continue;
+            }
+            let rates = tokio::try_join!(
+                engine.get_funding_rate(exchange, &symbol),
+                engine.get_predicted_funding_rate(exchange, &symbol),
+            );
+            let (funding_rate, predicted_rate) = match rates {
+                Ok(rates) => rates,
+                Err(error) => {
+                    warn!(%exchange, %symbol, %error, "資金費率採樣失敗");
+                    continue;
+                }
+            };
+            let sample = FundingSample {
+                exchange: exchange.to_string(),
+                symbol: symbol.clone(),
+                sampled_at_ms,
+                funding_rate,
+                predicted_rate,
+            };
+            if let Some(history) = &engine.history {
+                if let Err(e) = history.insert_funding_sample(&sample).await {
+                    warn!(error = %e, "寫入資金費率樣本失敗");
+                }
+            }
+            engine.funding_history.push(sample);
+        }
+    }
+    debug!(sampled_at_ms, "資金費率採樣完成");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bench::START_MS;
+    use crate::deterministic_sim::{build, engine, funded_config};
+    use crate::{config, EngineCommand, Environment};
+
+    // 價差統計只使用同一採樣時間的樣本，按 |z-score| 排序並可按交易對過濾
+    #[tokio::test]
+    async fn funding_spread_stats_align_samples_across_exchanges() {
+        let (engine, path) = engine("spread-stats", 1, config::SimulatedExchangeConfig::default());
+        let sample = |exchange: &str, symbol: &str, minute: i64, funding_rate: f64| FundingSample {
+            exchange: exchange.to_string(),
+            symbol: symbol.to_string(),
+            sampled_at_ms: START_MS + minute * 60_000,
+            funding_rate,
+            predicted_rate: funding_rate * 2.0,
+        };
+        for (minute, binance) in [(0, 0.0003), (1, 0.0003), (2, 0.0006)] {
+            engine.funding_history.push(sample("binance", "BTCUSDT", minute, binance));
+            engine.funding_history.push(sample("bybit", "BTCUSDT", minute, 0.0001));
+            engine.funding_history.push(sample("binance", "ETHUSDT", minute, 0.0002 + minute as f64 * 0.0001));
+            engine.funding_history.push(sample("bybit", "ETHUSDT", minute, 0.0001));
+        }
+        // okx 的樣本與其他交易所時間不重疊，不組成價差
+        engine.funding_history.push(sample("okx", "BTCUSDT", 3, 0.0009));
+
+        let response = engine.handle_command(EngineCommand::GetSpreadStats { symbol: None }).await;
+        let stats: Vec<serde_json::Value> = serde_json::from_value(response.data.unwrap()).unwrap();
+        assert_eq!(stats.len(), 2);
+        let btc = &stats[0];
+        assert_eq!((btc["symbol"].as_str(), btc["exchange_a"].as_str(), btc["exchange_b"].as_str()), (Some("BTCUSDT"), Some("binance"), Some("bybit")));
+        assert_eq!(btc["samples"], 3);
+        let close = |value: &serde_json::Value, expected: f64| (value.as_f64().unwrap() - expected).abs() < 1e-9;
+        assert!(close(&btc["latest_spread"], 0.0005) && close(&btc["latest_predicted_spread"], 0.001) && close(&btc["mean_spread"], 0.0003));
+        assert!((btc["z_score"].as_f64().unwrap() - 0.0002 / 2e-8f64.sqrt()).abs() < 1e-6);
+        // ETH 價差線性增長，z-score 較低排在後面
+        assert!(stats[1]["z_score"].as_f64().unwrap() < btc["z_score"].as_f64().unwrap());
+
+        let eth = engine.funding_history.spread_stats(Some("ETHUSDT"));
+        assert_eq!((eth.len(), eth[0].symbol.as_str()), (1, "ETHUSDT"));
+        assert_eq!(engine.funding_history.latest().len(), 5);
+        let _ = std::fs::remove_file(path);
+    }
+
+    // 資金費日曆列出每個跟蹤交易對在各交易所的下一次結算與最新費率，按結算時間排列；
+    // 沒有永續合約的組合不列出，尚未採樣的組合費率為空
+    #[tokio::test]
+    async fn funding_calendar_lists_next_settlements_across_venues() {
+        let mut config = funded_config();
+        config.exchanges.get_mut("okx").unwrap().funding_interval_hours = 1;
+        config.margin.missing_perps.insert("okx".to_string(), vec!["ETHUSDT".to_string()]);
+        let (engine, path) = build("funding-calendar", config, Environment::simulated(START_MS, 1));
+        let sample = |exchange: &str, symbol: &str, funding_rate: f64| FundingSample {
+            exchange: exchange.to_string(),
+            symbol: symbol.to_string(),
+            sampled_at_ms: START_MS - 60_000,
+            funding_rate,
+            predicted_rate: funding_rate * 2.0,
+        };
+        for (exchange, symbol, rate) in [("binance", "BTCUSDT", 0.0003), ("bybit", "BTCUSDT", 0.0001), ("okx", "BTCUSDT", 0.0002), ("binance", "ETHUSDT", 0.0004)] {
+            engine.funding_history.push(sample(exchange, symbol, rate));
+        }
+
+        let response = engine.handle_command(EngineCommand::GetFundingCalendar).await;
+        let data = response.data.unwrap();
+        assert_eq!(data["generated_at_ms"], START_MS);
+        let entries: Vec<(String, String, i64, Option<f64>)> = data["entries"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|entry| {
+                (
+                    entry["exchange"].as_str().unwrap().to_string(),
+                    entry["symbol"].as_str().unwrap().to_string(),
+                    entry["next_funding_ms"].as_i64().unwrap(),
+                    entry["predicted_rate"].as_f64(),
+                )
+            })
+            .collect();
+        let hourly = next_funding_ms(START_MS, 1);
+        let eight_hourly = next_funding_ms(START_MS, 8);
+        assert!(START_MS < hourly && hourly < eight_hourly);
+        let entry = |exchange: &str, symbol: &str, next: i64, predicted: Option<f64>| (exchange.to_string(), symbol.to_string(), next, predicted);
+        assert_eq!(
+            entries,
+            [
+                entry("okx", "BTCUSDT", hourly, Some(0.0004)),
+                entry("binance", "BTCUSDT", eight_hourly, Some(0.0006)),
+                entry("binance", "ETHUSDT", eight_hourly, Some(0.0008)),
+                entry("bybit", "BTCUSDT", eight_hourly, Some(0.0002)),
+                entry("bybit", "ETHUSDT", eight_hourly, None),
+            ]
+        );
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[tokio::test]
+    async fn funding_history_expires_idle_symbols_and_caps_tracking() {
+        let (engine, path) = engine("funding-expiry", 1, config::SimulatedExchangeConfig::default());
+        let history = &engine.funding_history;
+        history.track("SOLUSDT", START_MS);
+        history.track("BTCUSDT", START_MS);
+        history.push(FundingSample {
+            exchange: "binance".to_string(),
+            symbol: "SOLUSDT".to_string(),
+            sampled_at_ms: START_MS,
+            funding_rate: 0.0001,
+            predicted_rate: 0.0001,
+        });
+        // 閒置未滿 24 小時仍在採集
+        assert!(history.expire_idle(START_MS + 23 * 3_600_000).is_empty());
+        assert_eq!(history.expire_idle(START_MS + 25 * 3_600_000), vec!["SOLUSDT".to_string()]);
+        assert_eq!(history.symbols(), vec!["BTCUSDT".to_string(), "ETHUSDT".to_string()]);
+        assert!(history.latest().is_empty());
+
+        // 超出上限時淘汰最久未請求的交易對，默認交易對保留
+        for n in 0..300 {
+            history.track(&format!("X{}USDT", n), START_MS + n);
+        }
+        let symbols = history.symbols();
+        assert_eq!(symbols.len(), 256);
+        assert!(symbols.contains(&"BTCUSDT".to_string()) && symbols.contains(&"X299USDT".to_string()));
+        assert!(!symbols.contains(&"X45USDT".to_string()));
+        assert!(symbols.contains(&"X46USDT".to_string()));
+        let _ = std::fs::remove_file(path);
+    }
+}
@@ -0,0 +1,197 @@
+use super::config::{FundingModelConfig, PredictionSource};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Mutex, RwLock};
+
+// 交易所返回的溢價指數；impact_bid / impact_ask 為按 impact_notional 吃單的成交均價
+#[derive(Debug, Clone, Serialize)]
+pub struct PremiumIndex {
+    pub index_price: f64,
+    pub impact_bid: f64,
+    pub impact_ask: f64,
+    pub sampled_at_ms: i64,
+}
+
+impl PremiumIndex {
+    // (max(0, impact_bid - index) - max(0, index - impact_ask)) / index
+    pub fn premium(&self) -> f64 {
+        ((self.impact_bid - self.index_price).max(0.0) - (self.index_price - self.impact_ask).max(0.0)) / self.index_price
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Prediction {
+    pub exchange: String,
+    pub symbol: String,
+    pub source: PredictionSource,
+    pub predicted_rate: f64,
+    // 溢價指數模型的中間量；交易所公佈預測費率時為 None
+    pub premium_average: Option<f64>,
+    pub interest_rate: Option<f64>,
+    pub samples: usize,
+    pub interval_start_ms: Option<i64>,
+    pub computed_at_ms: i64,
+}
+
+// 當前結算週期內的溢價樣本：第 i 個樣本權重為 i，越接近結算權重越高
+struct Accumulator {
+    interval_start_ms: i64,
+    samples: usize,
+    weighted_sum: f64,
+    total_weight: f64,
+}
+
+pub struct FundingModel {
+    accumulators: Mutex<HashMap<(String, String), Accumulator>>,
+    latest: RwLock<BTreeMap<String, Prediction>>,
+}
+
+impl FundingModel {
+    pub fn new() -> Self {
+        Self {
+            accumulators: Mutex::new(HashMap::new()),
+            latest: RwLock::new(BTreeMap::new()),
+        }
+    }
+
+    pub fn snapshot(&self) -> Vec<Prediction> {
+        self.latest.read().unwrap().values().cloned().collect()
+    }
+
+    pub fn latest_rate(&self, exchange: &str, symbol: &str) -> Option<f64> {
+        self.latest
+            .read()
+            .unwrap()
+            .get(&format!("{}:{}", exchange, symbol))
+            .map(|prediction| prediction.predicted_rate)
+    }
+
+    fn record(&self, prediction: Prediction) -> f64 {
+        let rate = prediction.predicted_rate;
+        self.latest
+            .write()
+            .unwrap()
+            .insert(format!("{}:{}", prediction.exchange, prediction.symbol), prediction);
+        rate
+    }
+
+    // 交易所直接公佈的預測費率
+    pub fn published(&self, exchange: &str, symbol: &str, rate: f64, now_ms: i64) -> f64 {
+        self.record(Prediction {
+            exchange: exchange.to_string(),
+            symbol: symbol.to_string(),
+            source: PredictionSource::Exchange,
+            predicted_rate: rate,
+            premium_average: None,
+            interest_rate: None,
+            samples: 0,
+            interval_start_ms: None,
+            computed_at_ms: now_ms,
+        })
+    }
+
+    // 計入一個溢價樣本後按 F = P + clamp(I - P, ±premium_clamp) 預測，並限制在 ±rate_cap 內；
+    // 跨過結算時間後從新週期重新累積
+    pub fn observe(
+        &self,
+        exchange: &str,
+        symbol: &str,
+        interval_hours: u64,
+        index: &PremiumIndex,
+        config: &FundingModelConfig,
+    ) -> f64 {
+        let interval_ms = interval_hours as i64 * 3_600_000;
+        let interval_start_ms = index.sampled_at_ms / interval_ms * interval_ms;
+        let (premium_average, samples) = {
+            let mut accumulators = self.accumulators.lock().unwrap();
+            let accumulator = accumulators
+                .entry((exchange.to_string(), symbol.to_string()))
+                .or_insert(Accumulator {
+                    interval_start_ms,
+                    samples: 0,
+                    weighted_sum: 0.0,
+                    total_weight: 0.0,
+                });
+            if accumulator.interval_start_ms != interval_start_ms {
+                *accumulator = Accumulator {
+                    interval_start_ms,
+                    samples: 0,
+                    weighted_sum: 0.0,
+                    total_weight: 0.0,
+                };
+            }
+            accumulator.samples += 1;
+            let weight = accumulator.samples as f64;
+            accumulator.weighted_sum += weight * index.premium();
+            accumulator.total_weight += weight;
+            (accumulator.weighted_sum / accumulator.total_weight, accumulator.samples)
+        };
+        let interest = (config.interest_rate - premium_average).clamp(-config.premium_clamp, config.premium_clamp);
+        self.record(Prediction {
+            exchange: exchange.to_string(),
+            symbol: symbol.to_string(),
+            source: PredictionSource::PremiumIndex,
+            predicted_rate: (premium_average + interest).clamp(-config.rate_cap, config.rate_cap),
+            premium_average: Some(premium_average),
+            interest_rate: Some(config.interest_rate),
+            samples,
+            interval_start_ms: Some(interval_start_ms),
+            computed_at_ms: index.sampled_at_ms,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bench::START_MS;
+    use crate::deterministic_sim::{build, funded_config, request};
+    use crate::{config, EngineCommand, Environment};
+
+    // 溢價指數模型按樣本序號加權平均溢價，預測費率 F = P + clamp(I - P, ±0.05%) 並限制在 ±0.75% 內，
+    // 跨過結算時間後重新累積；配置為交易所來源時直接使用公佈的預測費率。入場與排隊排序讀取最新預測
+    #[tokio::test]
+    async fn predicted_funding_follows_premium_index_or_published_rates() {
+        let model = FundingModel::new();
+        let settings = config::FundingModelConfig::default();
+        let index = |premium: f64, sampled_at_ms: i64| PremiumIndex {
+            index_price: 50_000.0,
+            impact_bid: 50_000.0 * (1.0 + premium),
+            impact_ask: 50_000.0 * (1.0 + premium),
+            sampled_at_ms,
+        };
+        let interval_ms = 8 * 3_600_000;
+        let start_ms = START_MS / interval_ms * interval_ms;
+        // 溢價 0.1%：利率分量 -0.09% 截斷為 -0.05%
+        let rate = model.observe("binance", "BTCUSDT", 8, &index(0.001, start_ms + 1_000), &settings);
+        assert!((rate - 0.0005).abs() < 1e-12);
+        // 第二個樣本權重 2：P = (0.1% - 2 × 0.02%) / 3 = 0.02%，F = P + (I - P) = I
+        let rate = model.observe("binance", "BTCUSDT", 8, &index(-0.0002, start_ms + 2_000), &settings);
+        assert!((rate - settings.interest_rate).abs() < 1e-12);
+        let prediction = &model.snapshot()[0];
+        assert_eq!((prediction.samples, prediction.interval_start_ms), (2, Some(start_ms)));
+        assert!((prediction.premium_average.unwrap() - 0.0002).abs() < 1e-12);
+        // 新週期重新累積；極端溢價按上限截斷
+        let rate = model.observe("binance", "BTCUSDT", 8, &index(0.02, start_ms + interval_ms), &settings);
+        assert_eq!(rate, settings.rate_cap);
+        assert_eq!(model.snapshot()[0].samples, 1);
+
+        let mut config = funded_config();
+        config.exchanges.get_mut("bybit").unwrap().funding_model.source = config::PredictionSource::Exchange;
+        let (engine, path) = build("predicted-funding", config, Environment::simulated(START_MS, 1));
+        let binance = engine.get_predicted_funding_rate("binance", "BTCUSDT").await.unwrap();
+        let bybit = engine.get_predicted_funding_rate("bybit", "BTCUSDT").await.unwrap();
+        let predictions = engine.handle_command(EngineCommand::GetPredictedFunding).await.data.unwrap();
+        assert_eq!(predictions[0]["exchange"], "binance");
+        assert_eq!(predictions[0]["source"], "premium_index");
+        assert_eq!(predictions[0]["samples"], 1);
+        assert_eq!(predictions[1]["exchange"], "bybit");
+        assert_eq!(predictions[1]["source"], "exchange");
+        assert!(predictions[1]["premium_average"].is_null());
+        assert_eq!(engine.fresh_predicted_rate("bybit", "BTCUSDT").await.unwrap(), bybit);
+        let edge = (binance - bybit).abs() - engine.taker_fee("binance") - engine.taker_fee("bybit");
+        assert!((engine.expected_edge(&request(1_000.0)) - edge).abs() < 1e-12);
+        assert!(engine.get_predicted_funding_rate("binance", "NOPEUSDT").await.is_err());
+        let _ = std::fs::remove_file(path);
+    }
+}
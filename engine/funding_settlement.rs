@@ -0,0 +1,408 @@
+use super::config::FundingExitConfig;
+use super::funding_history::next_funding_ms;
+use super::{events, market_data, ChildOrder, ExecutionEngine};
+use serde::Serialize;
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tracing::{debug, info, warn};
+
+// 交易所返回的一筆資金費：position 為結算時的名義持倉，amount 為正表示收到
+#[derive(Debug, Clone, Serialize)]
+pub struct FundingPayment {
+    pub symbol: String,
+    pub funding_time_ms: i64,
+    pub rate: f64,
+    pub position: f64,
+    pub amount: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PairLeg {
+    pub exchange: String,
+    // 現貨槓桿空頭腿不收付資金費，而是支付借幣利息
+    pub perp: bool,
+    pub next_funding_ms: Option<i64>,
+    pub funding: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FundingPair {
+    pub symbol: String,
+    pub short: PairLeg,
+    pub long: PairLeg,
+    pub notional: f64,
+    pub opened_at_ms: i64,
+    pub execution_ids: Vec<String>,
+    pub payments: usize,
+    pub funding_pnl: f64,
+    // 預測的下一期費率差（空頭腿費率 - 多頭腿費率），為正時持有收益
+    pub predicted_spread: Option<f64>,
+    pub adverse_checks: u32,
+    pub closed_at_ms: Option<i64>,
+    pub close_reason: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TrackerSnapshot {
+    pub open: Vec<FundingPair>,
+    pub closed: Vec<FundingPair>,
+    pub last_payment_ms: BTreeMap<String, i64>,
+}
+
+#[derive(Default)]
+struct State {
+    // symbol:short:long -> 持倉對
+    open: BTreeMap<String, FundingPair>,
+    closed: VecDeque<FundingPair>,
+    // 每個交易所已處理到的資金費時間
+    last_payment_ms: BTreeMap<String, i64>,
+}
+
+pub struct FundingTracker {
+    pub config: FundingExitConfig,
+    state: Mutex<State>,
+}
+
+impl FundingTracker {
+    pub fn new(config: FundingExitConfig) -> Self {
+        Self {
+            config,
+            state: Mutex::new(State::default()),
+        }
+    }
+
+    pub fn snapshot(&self) -> TrackerSnapshot {
+        let state = self.state.lock().unwrap();
+        TrackerSnapshot {
+            open: state.open.values().cloned().collect(),
+            closed: state.closed.iter().cloned().collect(),
+            last_payment_ms: state.last_payment_ms.clone(),
+        }
+    }
+
+    // 資金費率執行成交後登記持倉對；同一方向的持倉對合併
+    pub fn open(&self, execution_id: &str, symbol: &str, orders: &[ChildOrder], now_ms: i64) {
+        let filled = |legs: &[&str]| {
+            let orders: Vec<&ChildOrder> = orders.iter().filter(|order| legs.contains(&order.leg.as_str())).collect();
+            let quantity: f64 = orders.iter().map(|order| order.filled_quantity).sum();
+            orders.first().map(|order| (order.exchange.clone(), order.leg != "margin_short", quantity))
+        };
+        let (Some((short_exchange, short_perp, short_quantity)), Some((long_exchange, _, long_quantity))) =
+            (filled(&["short", "margin_short"]), filled(&["long"]))
+        else {
+            return;
+        };
+        let notional = short_quantity.min(long_quantity);
+        if notional <= 0.0 {
+            return;
+        }
+        let key = format!("{}:{}:{}", symbol, short_exchange, long_exchange);
+        let leg = |exchange: String, perp: bool| PairLeg {
+            exchange,
+            perp,
+            next_funding_ms: None,
+            funding: 0.0,
+        };
+        let mut state = self.state.lock().unwrap();
+        let pair = state.open.entry(key).or_insert_with(|| FundingPair {
+            symbol: symbol.to_string(),
+            short: leg(short_exchange, short_perp),
+            long: leg(long_exchange, true),
+            notional: 0.0,
+            opened_at_ms: now_ms,
+            execution_ids: Vec::new(),
+            payments: 0,
+            funding_pnl: 0.0,
+            predicted_spread: None,
+            adverse_checks: 0,
+            closed_at_ms: None,
+            close_reason: None,
+        });
+        pair.notional += notional;
+        pair.execution_ids.push(execution_id.to_string());
+    }
+
+    // 把某交易所的資金費按該交易對各持倉對在該交易所的名義金額分攤
+    fn record(&self, exchange: &str, payment: &FundingPayment) {
+        let mut state = self.state.lock().unwrap();
+        let last = state.last_payment_ms.entry(exchange.to_string()).or_default();
+        *last = (*last).max(payment.funding_time_ms);
+        let total: f64 = state
+            .open
+            .values()
+            .filter(|pair| pair.symbol == payment.symbol)
+            .flat_map(|pair| [&pair.short, &pair.long].map(|leg| (leg, pair.notional)))
+            .filter(|(leg, _)| leg.perp && leg.exchange == exchange)
+            .map(|(_, notional)| notional)
+            .sum();
+        if total <= 0.0 {
+            return;
+        }
+        for pair in state.open.values_mut().filter(|pair| pair.symbol == payment.symbol) {
+            let share = payment.amount * pair.notional / total;
+            let mut matched = false;
+            for leg in [&mut pair.short, &mut pair.long] {
+                if leg.perp && leg.exchange == exchange {
+                    leg.funding += share;
+                    matched = true;
+                }
+            }
+            if matched {
+                pair.funding_pnl += share;
+                pair.payments += 1;
+            }
+        }
+    }
+
+    fn close(&self, key: &str, reason: String, now_ms: i64) {
+        let mut state = self.state.lock().unwrap();
+        if let Some(mut pair) = state.open.remove(key) {
+            pair.closed_at_ms = Some(now_ms);
+            pair.close_reason = Some(reason);
+            if state.closed.len() == self.config.closed_history {
+                state.closed.pop_front();
+            }
+            state.closed.push_back(pair);
+        }
+    }
+}
+
+pub fn spawn(engine: Arc<ExecutionEngine>) {
+    if !engine.funding_pairs.config.enabled {
+        info!("資金費結算跟蹤未啟用");
+        return;
+    }
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(engine.funding_pairs.config.check_interval_secs));
+        loop {
+            interval.tick().await;
+            collect_payments(&engine).await;
+            check_exits(&engine).await;
+        }
+    });
+}
+
+async fn collect_payments(engine: &ExecutionEngine) {
+    let tracker = &engine.funding_pairs;
+    for (exchange, connector) in &engine.exchanges {
+        if engine.routing.is_remote(exchange) || !engine.sessions.is_available(exchange) {
+            continue;
+        }
+        let since_ms = tracker.state.lock().unwrap().last_payment_ms.get(exchange).copied().unwrap_or_default();
+        let payments = match connector.fetch_funding_payments(since_ms).await {
+            Ok(payments) => payments,
+            Err(error) => {
+                warn!(%exchange, %error, "拉取資金費記錄失敗");
+                continue;
+            }
+        };
+        for payment in payments {
+            info!(%exchange, symbol = %payment.symbol, rate = payment.rate, amount = payment.amount, "資金費已結算");
+            tracker.record(exchange, &payment);
+            engine.events.append(events::EngineEvent::FundingPayment {
+                exchange: exchange.clone(),
+                symbol: payment.symbol,
+                funding_time_ms: payment.funding_time_ms,
+                rate: payment.rate,
+                position: payment.position,
+                amount: payment.amount,
+            });
+        }
+    }
+}
+
+// 空頭腿預期收到的費率減去多頭腿預期支付的費率；現貨槓桿腿按負借幣利率計
+async fn predicted_spread(engine: &ExecutionEngine, pair: &FundingPair) -> Result<f64, String> {
+    let (base, _) = market_data::split_symbol(&pair.symbol).ok_or_else(|| format!("無法解析交易對: {}", pair.symbol))?;
+    let short = if pair.short.perp {
+        engine.get_predicted_funding_rate(&pair.short.exchange, &pair.symbol).await?
+    } else {
+        -engine.margin.borrow_rate(&pair.short.exchange, &base)?
+    };
+    let long = engine.get_predicted_funding_rate(&pair.long.exchange, &pair.symbol).await?;
+    Ok(short - long)
+}
+
+async fn check_exits(engine: &ExecutionEngine) {
+    let tracker = &engine.funding_pairs;
+    let pairs: Vec<(String, FundingPair)> =
+        tracker.state.lock().unwrap().open.iter().map(|(key, pair)| (key.clone(), pair.clone())).collect();
+    for (key, pair) in pairs {
+        let spread = match predicted_spread(engine, &pair).await {
+            Ok(spread) => spread,
+            Err(error) => {
+                warn!(pair = %key, %error, "預測費率差計算失敗");
+                continue;
+            }
+        };
+        let adverse_checks = {
+            let mut state = tracker.state.lock().unwrap();
+            let Some(pair) = state.open.get_mut(&key) else { continue };
+            pair.predicted_spread = Some(spread);
+            for leg in [&mut pair.short, &mut pair.long] {
+                leg.next_funding_ms = engine
+                    .exchanges
+                    .get(&leg.exchange)
+                    .filter(|_| leg.perp)
+                    .map(|connector| next_funding_ms(connector.clock.now_ms(), connector.settings.funding_interval_hours));
+            }
+            pair.adverse_checks = if spread < tracker.config.exit_spread { pair.adverse_checks + 1 } else { 0 };
+            pair.adverse_checks
+        };
+        debug!(pair = %key, spread, adverse_checks, "持倉對預測費率差");
+        if adverse_checks < tracker.config.confirmations {
+            continue;
+        }
+        match close_pair(engine, &pair).await {
+            Ok(()) => {
+                info!(pair = %key, spread, funding_pnl = pair.funding_pnl, "預測費率差轉為不利，已平倉");
+                tracker.close(
+                    &key,
+                    format!("predicted spread {:.6} below {:.6}", spread, tracker.config.exit_spread),
+                    engine.env.now_ms(),
+                );
+            }
+            Err(error) => warn!(pair = %key, %error, "平倉失敗，下一輪重試"),
+        }
+    }
+}
+
+// 同時平掉兩條腿；持倉已被其他流程（如強平監控）縮減時按交易所實際持倉平
+async fn close_pair(engine: &ExecutionEngine, pair: &FundingPair) -> Result<(), String> {
+    engine.acquire_orders(&[&pair.short.exchange, &pair.long.exchange]).await?;
+    let execution_id = format!("exit-{}", engine.env.uuid());
+    for (leg, side) in [(&pair.short, "buy"), (&pair.long, "sell")] {
+        let connector = engine.exchanges.get(&leg.exchange).ok_or_else(|| format!("未知交易所: {}", leg.exchange))?;
+        let quantity = pair.notional.min(connector.position(&pair.symbol).abs());
+        if quantity <= 0.0 {
+            continue;
+        }
+        let order = ChildOrder {
+            leg: format!("exit_{}", if side == "buy" { "short" } else { "long" }),
+            exchange: leg.exchange.clone(),
+            symbol: pair.symbol.clone(),
+            side: side.to_string(),
+            quantity,
+            filled_quantity: quantity,
+            fee: quantity * engine.taker_fee(&leg.exchange),
+            status: "filled".to_string(),
+        };
+        let leverage = leg.perp.then(|| connector.current_leverage(&pair.symbol).unwrap_or(1.0));
+        connector.simulate_fill(&execution_id, &order, leverage);
+        if !leg.perp {
+            let (base, _) = market_data::split_symbol(&pair.symbol).unwrap_or_default();
+            if let Ok(borrowed) = engine.margin.borrow_quantity(engine.env.rng.as_ref(), &leg.exchange, &pair.symbol, quantity) {
+                engine.margin.release(&leg.exchange, &base, borrowed);
+            }
+        }
+        engine.events.append(events::EngineEvent::OrderFilled {
+            execution_id: execution_id.clone(),
+            strategy_id: "funding_exit".to_string(),
+            leg: order.leg.clone(),
+            exchange: order.exchange.clone(),
+            symbol: order.symbol.clone(),
+            side: order.side.clone(),
+            filled_quantity: order.filled_quantity,
+            fee: order.fee,
+        });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bench::START_MS;
+    use crate::deterministic_sim::{build, connect_sessions, funded_config};
+    use crate::{config, Environment};
+    use std::path::PathBuf;
+
+    // 資金費率成交後登記持倉對（bybit 空、binance 多），結算時間到後拉取兩條腿的資金費入賬並寫入事件；
+    // 預測費率差連續兩次低於 exit_spread 時平掉兩條腿並記錄原因
+    #[tokio::test]
+    async fn funding_pairs_collect_payments_and_exit_on_adverse_predicted_spread() {
+        async fn opened(name: &str, exit_spread: f64) -> (Arc<ExecutionEngine>, PathBuf) {
+            let mut config = funded_config();
+            config.funding_exit = config::FundingExitConfig {
+                check_interval_secs: 1,
+                exit_spread,
+                ..Default::default()
+            };
+            let (engine, path) = build(name, config, Environment::simulated(START_MS, 1));
+            let engine = Arc::new(engine);
+            connect_sessions(&engine).await;
+            let orders: Vec<ChildOrder> = [("short", "bybit", "sell"), ("long", "binance", "buy")]
+                .into_iter()
+                .map(|(leg, exchange, side)| ChildOrder {
+                    leg: leg.to_string(),
+                    exchange: exchange.to_string(),
+                    symbol: "BTCUSDT".to_string(),
+                    side: side.to_string(),
+                    quantity: 10_000.0,
+                    filled_quantity: 10_000.0,
+                    fee: 0.0,
+                    status: "filled".to_string(),
+                })
+                .collect();
+            for order in &orders {
+                engine.exchanges[&order.exchange].simulate_fill("e1", order, Some(2.0));
+            }
+            engine.funding_pairs.open("e1", "BTCUSDT", &orders, engine.env.now_ms());
+            (engine, path)
+        }
+        async fn wait_for(engine: &ExecutionEngine, done: impl Fn(&TrackerSnapshot) -> bool) -> TrackerSnapshot {
+            for _ in 0..250 {
+                let snapshot = engine.funding_pairs.snapshot();
+                if done(&snapshot) {
+                    return snapshot;
+                }
+                tokio::time::sleep(Duration::from_millis(20)).await;
+            }
+            panic!("持倉對狀態未達預期: {:?}", engine.funding_pairs.snapshot());
+        }
+
+        let (engine, path) = opened("funding-pairs", -1.0).await;
+        spawn(Arc::clone(&engine));
+        let snapshot = wait_for(&engine, |snapshot| snapshot.open.first().is_some_and(|pair| pair.predicted_spread.is_some())).await;
+        let pair = &snapshot.open[0];
+        assert_eq!((pair.short.exchange.as_str(), pair.long.exchange.as_str(), pair.notional), ("bybit", "binance", 10_000.0));
+        assert!(pair.short.next_funding_ms.is_some_and(|next| next > engine.env.now_ms()));
+        assert_eq!(pair.payments, 0);
+
+        // 跨過下一次結算
+        engine.env.clock.sleep(Duration::from_secs(8 * 3_600)).await;
+        let snapshot = wait_for(&engine, |snapshot| snapshot.open[0].payments >= 2).await;
+        let payments: Vec<(String, f64, f64, f64)> = engine
+            .events
+            .read(0, usize::MAX)
+            .into_iter()
+            .filter_map(|record| match record.event {
+                events::EngineEvent::FundingPayment { exchange, rate, position, amount, .. } => Some((exchange, rate, position, amount)),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(payments.len(), 2);
+        for (exchange, rate, position, amount) in &payments {
+            assert_eq!(*position, if exchange == "bybit" { -10_000.0 } else { 10_000.0 });
+            assert_eq!(*amount, -position * rate);
+        }
+        let pair = &snapshot.open[0];
+        assert!((pair.funding_pnl - payments.iter().map(|payment| payment.3).sum::<f64>()).abs() < 1e-9);
+        assert!((pair.short.funding + pair.long.funding - pair.funding_pnl).abs() < 1e-9);
+        assert!(snapshot.last_payment_ms.contains_key("binance") && snapshot.last_payment_ms.contains_key("bybit"));
+        let _ = std::fs::remove_file(path);
+
+        let (engine, path) = opened("funding-exit", 1.0).await;
+        spawn(Arc::clone(&engine));
+        let snapshot = wait_for(&engine, |snapshot| !snapshot.closed.is_empty()).await;
+        assert!(snapshot.open.is_empty());
+        let closed = &snapshot.closed[0];
+        assert_eq!(closed.adverse_checks, 2);
+        assert!(closed.close_reason.as_deref().unwrap().starts_with("predicted spread"));
+        for exchange in ["binance", "bybit"] {
+            assert_eq!(engine.exchanges[exchange].position("BTCUSDT"), 0.0);
+        }
+        let _ = std::fs::remove_file(path);
+    }
+}
@@ -0,0 +1,430 @@
+use super::config::{GasConfig, UrgencyTier};
+use super::rpc::RpcTransport;
+use super::ExecutionEngine;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tracing::{debug, info, warn};
+use web3::types::{BlockNumber, U256};
+use web3::Web3;
+
+const GWEI: f64 = 1e9;
+const WEI_PER_ETH: f64 = 1e18;
+const DAY_MS: i64 = 86_400_000;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct GasQuote {
+    pub urgency: String,
+    pub base_fee_per_gas: u64,
+    pub max_priority_fee_per_gas: u64,
+    pub max_fee_per_gas: u64,
+    // 基於最近一次 feeHistory；false 表示未配置 RPC 或數據過期，使用靜態報價
+    pub live: bool,
+}
+
+/// 鏈上交易發送前的淨收益檢查：參數為 gas 單位（實時估算或模擬得到的實際消耗），收益不足時返回拒絕原因。
+pub type ProfitGate<'a> = dyn Fn(u64) -> Result<(), String> + Send + Sync + 'a;
+
+// 最近一次 feeHistory：下一區塊的基礎費與各檔位小費百分位（跨區塊取中位數），單位 wei
+struct FeeSnapshot {
+    next_base_fee: u64,
+    priority_fees: Vec<u64>,
+    fetched_at: Instant,
+}
+
+pub struct GasOptimizer {
+    config: GasConfig,
+    web3: Option<Web3<RpcTransport>>,
+    latest: Mutex<Option<FeeSnapshot>>,
+}
+
+impl GasOptimizer {
+    // 沒有 RPC 時只提供靜態報價
+    pub fn connect(config: GasConfig, rpc: Option<RpcTransport>) -> Self {
+        Self {
+            config,
+            web3: rpc.map(Web3::new),
+            latest: Mutex::new(None),
+        }
+    }
+
+    pub fn max_gas_limit(&self) -> u64 {
+        self.config.max_gas_limit
+    }
+
+    // 未配置的鏈上操作不計 gas
+    pub fn estimated_gas(&self, kind: &str) -> u64 {
+        self.config.estimated_gas.get(kind).copied().unwrap_or_default()
+    }
+
+    pub fn min_net_profit_usdt(&self) -> f64 {
+        self.config.min_net_profit_usdt
+    }
+
+    // 按當前基礎費加小費估算 gas 單位的花費（原生代幣）；max_fee_per_gas 中的基礎費餘量不會被收取，不計入
+    pub fn expected_cost(&self, quote: &GasQuote, gas: u64) -> f64 {
+        gas as f64 * (quote.base_fee_per_gas + quote.max_priority_fee_per_gas) as f64 / WEI_PER_ETH
+    }
+
+    // 取 min_priority 不超過請求優先級的最高檔位，低於所有檔位時使用最低檔
+    fn tier(&self, priority: i32) -> (usize, &UrgencyTier) {
+        self.config
+            .urgency_tiers
+            .iter()
+            .enumerate()
+            .rev()
+            .find(|(_, tier)| tier.min_priority <= priority)
+            .unwrap_or((0, &self.config.urgency_tiers[0]))
+    }
+
+    pub fn quote(&self, priority: i32) -> GasQuote {
+        let (index, tier) = self.tier(priority);
+        let stale_after = Duration::from_millis(self.config.stale_after_ms);
+        let latest = self.latest.lock().unwrap();
+        match latest.as_ref().filter(|snapshot| snapshot.fetched_at.elapsed() <= stale_after) {
+            Some(snapshot) => {
+                let (base_fee, priority_fee) = (snapshot.next_base_fee, snapshot.priority_fees[index]);
+                GasQuote {
+                    urgency: tier.name.clone(),
+                    base_fee_per_gas: base_fee,
+                    max_priority_fee_per_gas: priority_fee,
+                    // 預留基礎費上漲空間，未用完的部分不會被收取
+                    max_fee_per_gas: ((base_fee as f64 * tier.base_fee_multiplier) as u64).saturating_add(priority_fee),
+                    live: true,
+                }
+            }
+            None => {
+                let priority_fee = (self.config.fallback_priority_fee_gwei * GWEI) as u64;
+                let max_fee = (self.config.fallback_max_fee_gwei * GWEI) as u64;
+                GasQuote {
+                    urgency: tier.name.clone(),
+                    base_fee_per_gas: max_fee.saturating_sub(priority_fee),
+                    max_priority_fee_per_gas: priority_fee,
+                    max_fee_per_gas: max_fee,
+                    live: false,
+                }
+            }
+        }
+    }
+
+    // gas 上限與單次執行最高花費（gas_limit × max_fee_per_gas）檢查
+    pub fn check_budget(&self, quote: &GasQuote, gas_limit: u64) -> Result<(), String> {
+        if gas_limit > self.config.max_gas_limit {
+            return Err(format!("gas 估算 {} 超過上限 {}", gas_limit, self.config.max_gas_limit));
+        }
+        let max_spend = gas_limit as f64 * quote.max_fee_per_gas as f64 / WEI_PER_ETH;
+        if max_spend > self.config.max_fee_per_execution_eth {
+            return Err(format!(
+                "單次執行最高 gas 花費 {:.6} ETH 超過預算 {} ETH",
+                max_spend, self.config.max_fee_per_execution_eth
+            ));
+        }
+        Ok(())
+    }
+
+    async fn poll_once(&self, web3: &Web3<RpcTransport>) -> Result<(), String> {
+        let percentiles: Vec<f64> = self.config.urgency_tiers.iter().map(|tier| tier.reward_percentile).collect();
+        let history = web3
+            .eth()
+            .fee_history(self.config.fee_history_blocks.into(), BlockNumber::Latest, Some(percentiles))
+            .await
+            .map_err(|e| format!("eth_feeHistory 失敗: {}", e))?;
+        // base_fee_per_gas 的最後一項即下一區塊的基礎費
+        let next_base_fee = *history
+            .base_fee_per_gas
+            .last()
+            .ok_or("eth_feeHistory 未返回基礎費")?;
+        let rewards = history.reward.unwrap_or_default();
+        let priority_fees = (0..self.config.urgency_tiers.len())
+            .map(|index| {
+                let mut fees: Vec<U256> = rewards.iter().filter_map(|block| block.get(index).copied()).collect();
+                fees.sort();
+                fees.get(fees.len() / 2).copied().unwrap_or_default()
+            })
+            .collect::<Vec<_>>();
+        // 超出 u64 的費率來自異常節點，丟棄整份快照，沿用上一份或靜態報價
+        let wei = |fee: U256| u64::try_from(fee).map_err(|_| format!("eth_feeHistory 返回的費率超出範圍: {}", fee));
+        let snapshot = FeeSnapshot {
+            next_base_fee: wei(next_base_fee)?,
+            priority_fees: priority_fees.iter().copied().map(wei).collect::<Result<_, _>>()?,
+            fetched_at: Instant::now(),
+        };
+        debug!(next_base_fee = %next_base_fee, priority_fees = ?priority_fees, "gas 費率已更新");
+        *self.latest.lock().unwrap() = Some(snapshot);
+        Ok(())
+    }
+}
+
+// UTC 當日零點（毫秒），每日預算以此為界重置
+pub fn utc_day_start_ms() -> i64 {
+    let now_ms = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as i64)
+        .unwrap_or_default();
+    now_ms - now_ms.rem_euclid(DAY_MS)
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BudgetStatus {
+    pub strategy: String,
+    pub day_start_ms: i64,
+    pub spent_eth: f64,
+    pub budget_eth: Option<f64>,
+    pub exhausted: bool,
+}
+
+struct DailySpend {
+    day_start_ms: i64,
+    spent_eth: f64,
+}
+
+// 按策略類型累計當日鏈上 gas 花費
+pub struct GasBudget {
+    budgets: HashMap<String, f64>,
+    spent: Mutex<HashMap<String, DailySpend>>,
+}
+
+impl GasBudget {
+    // today 為啟動時從事件日誌恢復的當日花費
+    pub fn new(budgets: HashMap<String, f64>, today: HashMap<String, f64>) -> Self {
+        let day_start_ms = utc_day_start_ms();
+        let spent = today
+            .into_iter()
+            .map(|(strategy, spent_eth)| (strategy, DailySpend { day_start_ms, spent_eth }))
+            .collect();
+        Self {
+            budgets,
+            spent: Mutex::new(spent),
+        }
+    }
+
+    fn spent_today(spent: &mut HashMap<String, DailySpend>, strategy: &str) -> f64 {
+        let day_start_ms = utc_day_start_ms();
+        match spent.get_mut(strategy) {
+            Some(entry) if entry.day_start_ms == day_start_ms => entry.spent_eth,
+            Some(entry) => {
+                *entry = DailySpend { day_start_ms, spent_eth: 0.0 };
+                0.0
+            }
+            None => 0.0,
+        }
+    }
+
+    pub fn allows(&self, strategy: &str) -> bool {
+        let Some(budget) = self.budgets.get(strategy) else {
+            return true;
+        };
+        Self::spent_today(&mut self.spent.lock().unwrap(), strategy) < *budget
+    }
+
+    pub fn record(&self, strategy: &str, cost_eth: f64) {
+        let mut spent = self.spent.lock().unwrap();
+        let before = Self::spent_today(&mut spent, strategy);
+        let after = before + cost_eth;
+        spent.insert(
+            strategy.to_string(),
+            DailySpend {
+                day_start_ms: utc_day_start_ms(),
+                spent_eth: after,
+            },
+        );
+        if let Some(budget) = self.budgets.get(strategy) {
+            if before < *budget && after >= *budget {
+                warn!(strategy, spent_eth = after, budget_eth = budget, "當日 gas 預算已用盡，後續機會改為純交易所執行");
+            }
+        }
+    }
+
+    pub fn snapshot(&self) -> Vec<BudgetStatus> {
+        let mut spent = self.spent.lock().unwrap();
+        let mut strategies: Vec<String> = self.budgets.keys().chain(spent.keys()).cloned().collect();
+        strategies.sort();
+        strategies.dedup();
+        strategies
+            .into_iter()
+            .map(|strategy| {
+                let spent_eth = Self::spent_today(&mut spent, &strategy);
+                let budget_eth = self.budgets.get(&strategy).copied();
+                BudgetStatus {
+                    day_start_ms: utc_day_start_ms(),
+                    spent_eth,
+                    budget_eth,
+                    exhausted: budget_eth.is_some_and(|budget| spent_eth >= budget),
+                    strategy,
+                }
+            })
+            .collect()
+    }
+}
+
+// 每條鏈各自輪詢費率
+pub fn spawn(engine: Arc<ExecutionEngine>) {
+    for name in engine.chains.keys() {
+        let Some(web3) = engine.chains[name].gas_optimizer.web3.clone() else {
+            info!(chain = %name, "未配置 gas RPC，使用靜態 gas 報價");
+            continue;
+        };
+        let engine = Arc::clone(&engine);
+        let name = name.clone();
+        tokio::spawn(async move {
+            let gas_optimizer = &engine.chains[&name].gas_optimizer;
+            let mut interval = tokio::time::interval(Duration::from_millis(gas_optimizer.config.poll_interval_ms));
+            loop {
+                interval.tick().await;
+                if let Err(error) = gas_optimizer.poll_once(&web3).await {
+                    warn!(%error, chain = %name, "更新 gas 費率失敗");
+                }
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bench::START_MS;
+    use crate::config::RpcConfig;
+    use crate::deterministic_sim::{build, connect_sessions, flash_loan_config, flash_loan_node, request, wallet_env};
+    use crate::mock_exchange_e2e::rpc_node;
+    use crate::{config, events, Environment};
+    use serde_json::json;
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    // 基礎費超出 u64 時丟棄整份快照並返回錯誤，報價保持靜態，恢復正常後重新使用實時費率
+    #[tokio::test]
+    async fn out_of_range_fee_history_is_rejected() {
+        let overflow = Arc::new(AtomicBool::new(true));
+        let node = Arc::clone(&overflow);
+        let url = rpc_node(Arc::new(move |method, _| {
+            let base_fee = match node.load(Ordering::SeqCst) {
+                true => format!("{:#x}", U256::from(u64::MAX) + 1),
+                false => "0x6fc23ac00".to_string(),
+            };
+            Ok(match method {
+                "eth_feeHistory" => json!({
+                    "oldestBlock": "0x1",
+                    "baseFeePerGas": ["0x1", base_fee],
+                    "gasUsedRatio": [0.5],
+                    "reward": [["0x1", "0x2", "0x3"]],
+                }),
+                other => return Err(format!("unexpected {}", other)),
+            })
+        }))
+        .await;
+        let rpc = RpcTransport::connect("gas", &url, &RpcConfig::default()).unwrap();
+        let optimizer = GasOptimizer::connect(GasConfig::default(), Some(rpc));
+        let web3 = optimizer.web3.clone().unwrap();
+
+        let error = optimizer.poll_once(&web3).await.unwrap_err();
+        assert_eq!(error, "eth_feeHistory 返回的費率超出範圍: 18446744073709551616");
+        assert!(!optimizer.quote(5).live);
+
+        overflow.store(false, Ordering::SeqCst);
+        optimizer.poll_once(&web3).await.unwrap();
+        let quote = optimizer.quote(5);
+        assert!(quote.live);
+        assert_eq!((quote.base_fee_per_gas, quote.max_priority_fee_per_gas), (30_000_000_000, 2));
+    }
+
+    // eth_feeHistory 三個區塊的小費百分位逐檔取中位數：低/中/高檔 4、5、6 gwei，下一區塊基礎費 30 gwei；
+    // 輪詢前使用靜態報價，之後按 priority 所在檔位出價，gas 上限與單次執行花費上限分別檢查
+    #[tokio::test]
+    async fn gas_quotes_follow_fee_history_percentiles_by_urgency_tier() {
+        let gwei = |value: u64| format!("{:#x}", value * 1_000_000_000);
+        let url = rpc_node(Arc::new(move |method, params| {
+            Ok(match method {
+                "eth_feeHistory" => {
+                    assert_eq!(params[2], serde_json::json!([10.0, 50.0, 90.0]));
+                    serde_json::json!({
+                        "oldestBlock": "0x1",
+                        "baseFeePerGas": [gwei(20), gwei(24), gwei(27), gwei(30)],
+                        "gasUsedRatio": [0.9, 0.8, 0.7],
+                        "reward": [[gwei(7), gwei(2), gwei(9)], [gwei(1), gwei(8), gwei(3)], [gwei(4), gwei(5), gwei(6)]],
+                    })
+                }
+                other => return Err(format!("unexpected {}", other)),
+            })
+        }))
+        .await;
+        let mut config = config::EngineConfig::default();
+        config.gas.rpc_url = Some(url);
+        config.gas.poll_interval_ms = 20;
+        let (engine, path) = build("mock-gas", config, Environment::system());
+        let engine = Arc::new(engine);
+        let optimizer = &engine.default_chain().gas_optimizer;
+        let fallback = optimizer.quote(5);
+        assert!(!fallback.live);
+        assert_eq!((fallback.max_fee_per_gas, fallback.max_priority_fee_per_gas), (20_000_000_000, 1_500_000_000));
+
+        spawn(Arc::clone(&engine));
+        for _ in 0..250 {
+            if optimizer.quote(5).live {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        let tiers: Vec<_> = [0, 5, 9]
+            .into_iter()
+            .map(|priority| {
+                let quote = optimizer.quote(priority);
+                (quote.urgency, quote.base_fee_per_gas / 1_000_000_000, quote.max_priority_fee_per_gas / 1_000_000_000, quote.max_fee_per_gas)
+            })
+            .collect();
+        assert_eq!(tiers, [
+            ("low".to_string(), 30, 4, 37_750_000_000),
+            ("normal".to_string(), 30, 5, 50_000_000_000),
+            ("high".to_string(), 30, 6, 66_000_000_000),
+        ]);
+        // 實際收取基礎費加小費，不含 max_fee 中預留的餘量
+        assert!((optimizer.expected_cost(&optimizer.quote(5), 100_000) - 0.0035).abs() < 1e-12);
+
+        let high = optimizer.quote(9);
+        assert!(optimizer.check_budget(&high, 6_000_000).unwrap_err().contains("超過上限 5000000"));
+        // 1M gas × 66 gwei = 0.066 ETH，超過 0.05 ETH 的單次預算
+        assert!(optimizer.check_budget(&high, 1_000_000).unwrap_err().contains("超過預算 0.05 ETH"));
+        optimizer.check_budget(&high, 500_000).unwrap();
+        let _ = std::fs::remove_file(path);
+    }
+
+    // 每筆閃電貸消耗 200k gas × 1 gwei = 0.0002 ETH；第二筆後達到 0.0003 ETH 的當日預算，之後只走交易所腿。
+    // 重啟時從事件日誌中的 gas 花費恢復預算狀態
+    #[tokio::test]
+    async fn daily_gas_budget_routes_to_exchange_legs_once_exhausted() {
+        use web3::types::Address;
+        let vault: Address = config::BalancerConfig::default().vault_address.parse().unwrap();
+        let url = flash_loan_node(Arc::new(std::sync::Mutex::new([(vault, u64::MAX)].into())), Address::from_low_u64_be(0xf1)).await;
+        let mut config = flash_loan_config(url);
+        config.gas.daily_budget_eth = [("funding_rate".to_string(), 0.0003)].into();
+        let (engine, path) = build("gas-budget", config.clone(), wallet_env());
+        let engine = Arc::new(engine);
+        connect_sessions(&engine).await;
+        engine.quotes.record_predicted_rate("binance", "BTCUSDT", 0.0060, START_MS);
+        engine.quotes.record_predicted_rate("bybit", "BTCUSDT", 0.0010, START_MS);
+
+        let mut legs = Vec::new();
+        for _ in 0..3 {
+            let response = engine.execute_funding_rate_arbitrage(request(10_000.0)).await;
+            assert_eq!(response.status, "success", "{:?}", response.error_message);
+            let execution_id = response.execution_id.unwrap();
+            let placed: Vec<String> = engine
+                .events
+                .read(0, 1_000)
+                .into_iter()
+                .filter_map(|envelope| match envelope.event {
+                    events::EngineEvent::OrderPlaced { execution_id: id, leg, .. } if id == execution_id => Some(leg),
+                    _ => None,
+                })
+                .collect();
+            legs.push(placed);
+        }
+        assert_eq!(legs, [vec!["flash_loan"], vec!["flash_loan"], vec!["short", "long"]]);
+        let budget = engine.gas_budget.snapshot().into_iter().find(|status| status.strategy == "funding_rate").unwrap();
+        assert!(budget.exhausted && (budget.spent_eth - 0.0004).abs() < 1e-12, "{:?}", budget);
+        assert!(!engine.gas_budget.allows("funding_rate") && engine.gas_budget.allows("spot_arbitrage"));
+
+        let restored = GasBudget::new(config.gas.daily_budget_eth, engine.events.gas_spent_since(0));
+        assert!(!restored.allows("funding_rate"));
+        let _ = std::fs::remove_file(path);
+    }
+}
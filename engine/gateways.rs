@@ -0,0 +1,221 @@
+use super::config::GatewayConfig;
+use super::exchange_api::ApiError;
+use super::{rate_limit, ExchangeConnector, ExecutionEngine};
+use serde::Serialize;
+use std::sync::atomic::Ordering;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use tracing::{debug, info, warn};
+
+#[derive(Debug, Clone, Serialize)]
+pub struct GatewayStatus {
+    pub url: String,
+    pub healthy: bool,
+    // 最近一次探測的往返延遲，未探測成功前為 None
+    pub round_trip_ms: Option<f64>,
+    pub probed_at_ms: Option<i64>,
+    pub consecutive_failures: u32,
+    pub last_error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct GatewaySnapshot {
+    pub active: String,
+    pub endpoints: Vec<GatewayStatus>,
+}
+
+/// 一個連接器的候選 REST 端點：定期探測各端點延遲，選用最快的健康端點；
+/// 當前端點連續失敗時立即切換，所有端點都不健康時保留當前端點。
+pub struct GatewaySelector {
+    failover_after: u32,
+    state: RwLock<(usize, Vec<GatewayStatus>)>,
+}
+
+impl GatewaySelector {
+    // 第一個為默認端點，其後為 gateways.rest_urls 中的候選端點
+    pub fn new(base_url: &str, config: &GatewayConfig) -> Self {
+        let mut urls = vec![base_url.to_string()];
+        for url in &config.rest_urls {
+            if !urls.contains(url) {
+                urls.push(url.clone());
+            }
+        }
+        let endpoints = urls
+            .into_iter()
+            .map(|url| GatewayStatus {
+                url,
+                healthy: true,
+                round_trip_ms: None,
+                probed_at_ms: None,
+                consecutive_failures: 0,
+                last_error: None,
+            })
+            .collect();
+        Self {
+            failover_after: config.failover_after,
+            state: RwLock::new((0, endpoints)),
+        }
+    }
+
+    pub fn current(&self) -> String {
+        let state = self.state.read().unwrap();
+        state.1[state.0].url.clone()
+    }
+
+    pub fn urls(&self) -> Vec<String> {
+        self.state.read().unwrap().1.iter().map(|endpoint| endpoint.url.clone()).collect()
+    }
+
+    pub fn record_probe(&self, url: &str, result: Result<f64, String>, now_ms: i64) {
+        let mut state = self.state.write().unwrap();
+        let Some(endpoint) = state.1.iter_mut().find(|endpoint| endpoint.url == url) else { return };
+        endpoint.probed_at_ms = Some(now_ms);
+        match result {
+            Ok(round_trip_ms) => {
+                endpoint.round_trip_ms = Some(round_trip_ms);
+                endpoint.healthy = true;
+                endpoint.consecutive_failures = 0;
+                endpoint.last_error = None;
+            }
+            Err(error) => self.fail(endpoint, error),
+        }
+    }
+
+    // 請求經重試後仍以瞬時錯誤失敗時記入當前端點；達到 failover_after 後立即切換
+    pub fn record_failure(&self, error: &str) -> Option<(String, String)> {
+        let mut state = self.state.write().unwrap();
+        let active = state.0;
+        self.fail(&mut state.1[active], error.to_string());
+        if state.1[active].healthy {
+            return None;
+        }
+        Self::select(&mut state)
+    }
+
+    fn fail(&self, endpoint: &mut GatewayStatus, error: String) {
+        endpoint.consecutive_failures += 1;
+        endpoint.last_error = Some(error);
+        if endpoint.consecutive_failures >= self.failover_after {
+            endpoint.healthy = false;
+        }
+    }
+
+    // 健康端點中往返延遲最短者；未探測成功的端點排在最後，同等條件下保留當前端點
+    fn select(state: &mut (usize, Vec<GatewayStatus>)) -> Option<(String, String)> {
+        let (active, endpoints) = state;
+        let rank = |endpoint: &GatewayStatus| endpoint.round_trip_ms.unwrap_or(f64::INFINITY);
+        let best = endpoints
+            .iter()
+            .enumerate()
+            .filter(|(_, endpoint)| endpoint.healthy)
+            .min_by(|(a, x), (b, y)| rank(x).total_cmp(&rank(y)).then_with(|| (*b == *active).cmp(&(*a == *active))))
+            .map(|(index, _)| index)?;
+        if best == *active {
+            return None;
+        }
+        let previous = std::mem::replace(active, best);
+        Some((endpoints[previous].url.clone(), endpoints[best].url.clone()))
+    }
+
+    // 探測一輪後重新選擇，返回切換前後的端點
+    pub fn reselect(&self) -> Option<(String, String)> {
+        Self::select(&mut self.state.write().unwrap())
+    }
+
+    pub fn snapshot(&self) -> GatewaySnapshot {
+        let state = self.state.read().unwrap();
+        GatewaySnapshot {
+            active: state.1[state.0].url.clone(),
+            endpoints: state.1.clone(),
+        }
+    }
+}
+
+pub fn spawn(engine: Arc<ExecutionEngine>) {
+    for (exchange, connector) in engine.exchanges.iter() {
+        // 只有一個端點或進程內模擬時無需探測
+        if connector.api.is_none() || connector.gateways.urls().len() < 2 {
+            continue;
+        }
+        let engine = Arc::clone(&engine);
+        let exchange = exchange.clone();
+        tokio::spawn(async move {
+            let Some(connector) = engine.exchanges.get(&exchange) else { return };
+            let interval = Duration::from_secs(connector.settings.gateways.probe_interval_secs);
+            while !engine.shutting_down.load(Ordering::Relaxed) {
+                probe(connector).await;
+                tokio::time::sleep(interval).await;
+            }
+        });
+    }
+}
+
+// 以服務器時間請求測量各端點往返延遲，再選出最快的健康端點
+pub(crate) async fn probe(connector: &ExchangeConnector) {
+    let Some(api) = &connector.api else { return };
+    for url in connector.gateways.urls() {
+        if connector.scheduler.acquire(rate_limit::RequestKind::MarketData, 1).await.is_err() {
+            return;
+        }
+        let started_us = connector.env.clock.now_us();
+        let result = match api.server_time(&url).await {
+            Ok(_) => Ok((connector.env.clock.now_us() - started_us) as f64 / 1_000.0),
+            Err(ApiError::Throttled(retry_after)) => Err(format!("限頻（429），{} 秒後重試", retry_after.as_secs())),
+            Err(ApiError::Transient(error) | ApiError::Failed(error)) => Err(error),
+        };
+        if let Err(error) = &result {
+            debug!(exchange = %connector.name, %url, %error, "端點探測失敗");
+        }
+        connector.gateways.record_probe(&url, result, connector.env.now_ms());
+    }
+    if let Some((from, to)) = connector.gateways.reselect() {
+        info!(exchange = %connector.name, %from, %to, "切換到延遲最低的健康端點");
+    }
+    if connector.gateways.snapshot().endpoints.iter().all(|endpoint| !endpoint.healthy) {
+        warn!(exchange = %connector.name, "所有 REST 端點探測失敗，保留當前端點");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::deterministic_sim::build;
+    use crate::mock_exchange::{MockExchange, Scenario};
+    use crate::{config, Environment};
+
+    #[tokio::test]
+    async fn gateways_probe_and_fail_over_to_healthy_endpoint() {
+        let mut scenario = Scenario::default();
+        scenario.funding_rates.entry("binance".to_string()).or_default().insert("BTCUSDT".to_string(), 0.0001);
+        scenario.funding_rates.entry("bybit".to_string()).or_default().insert("BTCUSDT".to_string(), 0.0002);
+        let mock = MockExchange::start("127.0.0.1:0", scenario).await.unwrap();
+        // 默認端點不可連通，候選端點為模擬交易所
+        let dead = config::EndpointConfig {
+            rest_url: "http://127.0.0.1:9".to_string(),
+            ..mock.endpoint()
+        };
+        let mut config = config::EngineConfig::default();
+        for exchange in ["binance", "bybit"] {
+            let settings = config.exchanges.entry(exchange.to_string()).or_default();
+            settings.endpoint = Some(dead.clone());
+            settings.gateways.rest_urls = vec![mock.endpoint().rest_url];
+        }
+        let (engine, path) = build("mock-gateways", config, Environment::system());
+
+        // 探測後選用可連通的端點
+        let binance = &engine.exchanges["binance"];
+        probe(binance).await;
+        assert_eq!(binance.base_url(), mock.endpoint().rest_url);
+        let snapshot = binance.gateways.snapshot();
+        assert!(snapshot.endpoints[0].round_trip_ms.is_none() && snapshot.endpoints[1].round_trip_ms.is_some());
+        assert_eq!(engine.get_funding_rate("binance", "BTCUSDT").await.unwrap(), 0.0001);
+
+        // 未探測時請求連續失敗 failover_after 次後切換
+        for _ in 0..2 {
+            assert!(engine.get_funding_rate("bybit", "BTCUSDT").await.is_err());
+        }
+        assert_eq!(engine.exchanges["bybit"].base_url(), mock.endpoint().rest_url);
+        assert_eq!(engine.get_funding_rate("bybit", "BTCUSDT").await.unwrap(), 0.0002);
+        let _ = std::fs::remove_file(path);
+    }
+}
@@ -0,0 +1,156 @@
+use super::risk::Halt;
+use super::session::Health;
+use super::ExecutionEngine;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::sync::atomic::Ordering;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ListenerStatus {
+    // 客戶端 TCP 協議的監聽地址，綁定前與關閉後為 None
+    pub address: Option<String>,
+    pub accepting: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ExchangeStatus {
+    pub health: Health,
+    // 由其他區域實例執行，連接狀態由對方判斷
+    pub remote: bool,
+    // 最近一次資金費率採樣距今；尚未採樣時為 None
+    pub market_data_age_ms: Option<i64>,
+    pub stale: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RiskStatus {
+    pub kill_switch: bool,
+    pub shutting_down: bool,
+    pub halts: Vec<Halt>,
+    pub open_executions: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct HealthReport {
+    pub ready: bool,
+    // 未就緒的原因，就緒時為空
+    pub reasons: Vec<String>,
+    pub listener: ListenerStatus,
+    pub exchanges: BTreeMap<String, ExchangeStatus>,
+    pub risk: RiskStatus,
+}
+
+/// 引擎能否接收流量：監聽中、本區域交易所連接就緒且行情未過期、未緊急停止、未關閉且沒有引擎整體的交易暫停。
+/// 單個策略的暫停只報告，不影響就緒。
+pub fn report(engine: &ExecutionEngine, max_market_data_age_ms: i64) -> HealthReport {
+    let now_ms = engine.env.now_ms();
+    let mut reasons = Vec::new();
+
+    let kill_switch = engine.kill_switch.load(Ordering::Relaxed);
+    let shutting_down = engine.shutting_down.load(Ordering::Relaxed);
+    let address = engine.listen_address.read().unwrap().clone();
+    let accepting = address.is_some() && !shutting_down;
+    if !accepting {
+        reasons.push("客戶端監聽未就緒".to_string());
+    }
+
+    let mut sampled: BTreeMap<String, i64> = BTreeMap::new();
+    for sample in engine.funding_history.latest() {
+        let latest = sampled.entry(sample.exchange).or_insert(sample.sampled_at_ms);
+        *latest = (*latest).max(sample.sampled_at_ms);
+    }
+    let exchanges = engine
+        .sessions
+        .snapshot()
+        .into_iter()
+        .map(|(exchange, session)| {
+            let remote = engine.routing.is_remote(&exchange);
+            let market_data_age_ms = sampled.get(&exchange).map(|sampled_at_ms| now_ms - sampled_at_ms);
+            let stale = market_data_age_ms.is_some_and(|age| age > max_market_data_age_ms);
+            if !remote && session.health != Health::Connected {
+                reasons.push(format!("交易所 {} 連接未就緒", exchange));
+            }
+            if stale {
+                reasons.push(format!("交易所 {} 行情已過期", exchange));
+            }
+            let status = ExchangeStatus {
+                health: session.health,
+                remote,
+                market_data_age_ms,
+                stale,
+            };
+            (exchange, status)
+        })
+        .collect();
+
+    let halts = engine.risk.halts(now_ms);
+    if kill_switch {
+        reasons.push("緊急停止已啟用".to_string());
+    }
+    if shutting_down {
+        reasons.push("引擎正在關閉".to_string());
+    }
+    if halts.iter().any(|halt| halt.strategy_id.is_none()) {
+        reasons.push("引擎整體交易已暫停".to_string());
+    }
+    HealthReport {
+        ready: reasons.is_empty(),
+        reasons,
+        listener: ListenerStatus { address, accepting },
+        exchanges,
+        risk: RiskStatus {
+            kill_switch,
+            shutting_down,
+            halts,
+            open_executions: engine.open_executions.len(),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::mock_exchange::Scenario;
+    use crate::mock_exchange_e2e::engine;
+    use crate::{funding_history, session};
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn readiness_tracks_listener_sessions_market_data_and_risk() {
+        let (_mock, engine, path) = engine("readiness", Scenario::default()).await;
+        let engine = Arc::new(engine);
+        let report = super::report(&engine, 60_000);
+        assert!(!report.ready);
+        assert!(report.reasons.iter().any(|reason| reason.contains("監聽")));
+
+        session::spawn(Arc::clone(&engine));
+        *engine.listen_address.write().unwrap() = Some("127.0.0.1:8080".to_string());
+        let mut report = super::report(&engine, 60_000);
+        for _ in 0..250 {
+            if report.ready {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+            report = super::report(&engine, 60_000);
+        }
+        assert!(report.ready, "{:?}", report.reasons);
+
+        // 行情過期
+        let sample = |sampled_at_ms: i64| funding_history::FundingSample {
+            exchange: "okx".to_string(),
+            symbol: "BTCUSDT".to_string(),
+            sampled_at_ms,
+            funding_rate: 0.0001,
+            predicted_rate: 0.0001,
+        };
+        engine.funding_history.push(sample(engine.env.now_ms() - 120_000));
+        let report = super::report(&engine, 60_000);
+        assert_eq!(report.reasons, ["交易所 okx 行情已過期"]);
+        assert!(report.exchanges["okx"].stale && !report.exchanges["binance"].stale);
+        engine.funding_history.push(sample(engine.env.now_ms()));
+        assert!(super::report(&engine, 60_000).ready);
+
+        engine.kill_switch.store(true, std::sync::atomic::Ordering::Relaxed);
+        assert_eq!(super::report(&engine, 60_000).reasons, ["緊急停止已啟用"]);
+        let _ = std::fs::remove_file(path);
+    }
+}
@@ -0,0 +1,86 @@
+use std::future::Future;
+use std::time::Duration;
+use tracing::debug;
+
+pub async fn hedged<T, E, P, B, F>(delay: Duration, primary: P, backup: F) -> Result<T, E>
+where
+    P: Future<Output = Result<T, E>>,
+    B: Future<Output = Result<T, E>>,
+    F: FnOnce() -> B,
+{
+    tokio::pin!(primary);
+    tokio::select! {
+        result = &mut primary => {
+            return match result {
+                Ok(value) => Ok(value),
+                // 主請求快速失敗時立即改用備用端點
+                Err(_) => backup().await,
+            };
+        }
+        _ = tokio::time::sleep(delay) => {}
+    }
+
+    debug!(delay_us = delay.as_micros() as u64, "主請求超時未返回，發出對沖請求");
+    let backup = backup();
+    tokio::pin!(backup);
+    // 任一方先成功即返回；先返回的一方失敗則等待另一方
+    tokio::select! {
+        result = &mut primary => match result {
+            Ok(value) => Ok(value),
+            Err(_) => backup.await,
+        },
+        result = &mut backup => match result {
+            Ok(value) => Ok(value),
+            Err(_) => primary.await,
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::deterministic_sim::build;
+    use crate::mock_exchange::{MockExchange, Scenario};
+    use crate::{config, Environment};
+    use std::time::Instant;
+
+    // 主端點接受連接但不應答：超過對沖延遲後向備用端點發出同一請求並採用其結果；主端點正常時不請求備用端點
+    #[tokio::test]
+    async fn hedged_rate_queries_fall_back_to_the_backup_endpoint_after_the_delay() {
+        let silent = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let silent_url = format!("http://{}", silent.local_addr().unwrap());
+        tokio::spawn(async move {
+            let mut held = Vec::new();
+            while let Ok((stream, _)) = silent.accept().await {
+                held.push(stream);
+            }
+        });
+        let mut rates = Scenario::default();
+        rates.funding_rates.entry("bybit".to_string()).or_default().insert("BTCUSDT".to_string(), 0.0002);
+        let healthy = MockExchange::start("127.0.0.1:0", rates).await.unwrap();
+        let mut rates = Scenario::default();
+        for (exchange, rate) in [("binance", 0.0007), ("bybit", 0.0009)] {
+            rates.funding_rates.entry(exchange.to_string()).or_default().insert("BTCUSDT".to_string(), rate);
+        }
+        let backup = MockExchange::start("127.0.0.1:0", rates).await.unwrap();
+
+        let mut config = config::EngineConfig::default();
+        for (exchange, primary) in [("binance", silent_url), ("bybit", healthy.endpoint().rest_url)] {
+            let settings = config.exchanges.entry(exchange.to_string()).or_default();
+            settings.endpoint = Some(config::EndpointConfig { rest_url: primary, timeout_ms: 10_000, ..healthy.endpoint() });
+            settings.backup_base_url = Some(backup.endpoint().rest_url);
+            settings.hedge_delay_ms = Some(50);
+        }
+        let (engine, path) = build("mock-hedged", config, Environment::system());
+
+        let started = Instant::now();
+        assert_eq!(engine.get_funding_rate("binance", "BTCUSDT").await.unwrap(), 0.0007);
+        assert!(started.elapsed() < Duration::from_secs(5), "{:?}", started.elapsed());
+        assert_eq!(backup.requests(), ["GET /fapi/v1/premiumIndex"]);
+
+        assert_eq!(engine.get_funding_rate("bybit", "BTCUSDT").await.unwrap(), 0.0002);
+        assert_eq!(healthy.requests(), ["GET /v5/market/tickers"]);
+        assert_eq!(backup.requests().len(), 1);
+        let _ = std::fs::remove_file(path);
+    }
+}
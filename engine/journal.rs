@@ -0,0 +1,377 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Write};
+use std::sync::Mutex;
+use tracing::{error, info, warn};
+
+use super::config::Severity;
+use super::engine::{perp_leg, ExecutionEngine};
+use super::order_ids::OrderTag;
+use super::{events, ArbitrageRequest, ArbitrageResponse, ChildOrder, StrategyType};
+
+pub const DEFAULT_JOURNAL_PATH: &str = "execution_journal.jsonl";
+
+// 執行狀態轉換；下單前先寫入並落盤，崩潰後據此找回已送出但未確認的訂單
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum Entry {
+    Started {
+        execution_id: String,
+        request: Box<ArbitrageRequest>,
+        at_ms: i64,
+    },
+    LegSubmitted {
+        execution_id: String,
+        leg: JournalLeg,
+    },
+    LegAcked {
+        execution_id: String,
+        client_order_id: String,
+        filled_quantity: f64,
+    },
+    Finished {
+        execution_id: String,
+    },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JournalLeg {
+    pub leg: String,
+    pub exchange: String,
+    pub symbol: String,
+    pub side: String,
+    pub quantity: f64,
+    pub client_order_id: String,
+    // 交易所確認的成交數量；崩潰前未收到確認時為 None，恢復時向交易所查詢
+    pub filled_quantity: Option<f64>,
+}
+
+impl JournalLeg {
+    // 按成交數量記為子訂單，手續費按吃單費率
+    pub(crate) fn order(&self, filled: f64, taker_fee: f64) -> ChildOrder {
+        ChildOrder {
+            leg: self.leg.clone(),
+            exchange: self.exchange.clone(),
+            symbol: self.symbol.clone(),
+            side: self.side.clone(),
+            quantity: self.quantity,
+            filled_quantity: filled,
+            fee: filled * taker_fee,
+            status: if filled <= 0.0 {
+                "rejected"
+            } else if filled < self.quantity {
+                "partially_filled"
+            } else {
+                "filled"
+            }
+            .to_string(),
+        }
+    }
+}
+
+// 已開始但未結束的執行
+#[derive(Debug, Clone, Serialize)]
+pub struct InFlight {
+    pub execution_id: String,
+    pub request: ArbitrageRequest,
+    pub started_at_ms: i64,
+    pub legs: Vec<JournalLeg>,
+}
+
+impl InFlight {
+    fn apply(in_flight: &mut BTreeMap<String, InFlight>, entry: Entry) {
+        match entry {
+            Entry::Started { execution_id, request, at_ms } => {
+                in_flight.insert(
+                    execution_id.clone(),
+                    InFlight {
+                        execution_id,
+                        request: *request,
+                        started_at_ms: at_ms,
+                        legs: Vec::new(),
+                    },
+                );
+            }
+            Entry::LegSubmitted { execution_id, leg } => {
+                if let Some(execution) = in_flight.get_mut(&execution_id) {
+                    execution.legs.push(leg);
+                }
+            }
+            Entry::LegAcked { execution_id, client_order_id, filled_quantity } => {
+                let leg = in_flight
+                    .get_mut(&execution_id)
+                    .and_then(|execution| execution.legs.iter_mut().find(|leg| leg.client_order_id == client_order_id));
+                if let Some(leg) = leg {
+                    leg.filled_quantity = Some(filled_quantity);
+                }
+            }
+            Entry::Finished { execution_id } => {
+                in_flight.remove(&execution_id);
+            }
+        }
+    }
+}
+
+struct Inner {
+    file: File,
+    in_flight: BTreeMap<String, InFlight>,
+}
+
+/// 執行預寫日誌：只保留未結束的執行，全部結束後截斷文件。
+pub struct Journal {
+    inner: Mutex<Inner>,
+    // 本次啟動時的恢復結果
+    recoveries: Mutex<Vec<Recovery>>,
+}
+
+impl Journal {
+    // 打開（或創建）日誌並回放，得到上次退出時仍在執行中的請求
+    pub fn open(path: &str) -> io::Result<Self> {
+        let mut in_flight = BTreeMap::new();
+        if let Ok(file) = File::open(path) {
+            for (index, line) in BufReader::new(file).lines().enumerate() {
+                let line = line?;
+                if line.trim().is_empty() {
+                    continue;
+                }
+                // 崩潰時最後一行可能只寫了一半，該條目之前的狀態仍然有效
+                match serde_json::from_str(&line) {
+                    Ok(entry) => InFlight::apply(&mut in_flight, entry),
+                    Err(e) => warn!(path, line = index + 1, error = %e, "忽略無法解析的執行日誌條目"),
+                }
+            }
+        }
+        info!(path, in_flight = in_flight.len(), "執行日誌回放完成");
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(Self {
+            inner: Mutex::new(Inner { file, in_flight }),
+            recoveries: Mutex::new(Vec::new()),
+        })
+    }
+
+    pub fn started(&self, execution_id: &str, request: &ArbitrageRequest, at_ms: i64) {
+        self.write(
+            Entry::Started {
+                execution_id: execution_id.to_string(),
+                request: Box::new(request.clone()),
+                at_ms,
+            },
+            false,
+        );
+    }
+
+    // 下單前調用，寫入後才送出訂單；未經 started 登記的執行不記錄
+    pub fn leg_submitted(&self, execution_id: &str, leg: JournalLeg) {
+        if !self.inner.lock().unwrap().in_flight.contains_key(execution_id) {
+            return;
+        }
+        self.write(
+            Entry::LegSubmitted {
+                execution_id: execution_id.to_string(),
+                leg,
+            },
+            true,
+        );
+    }
+
+    pub fn leg_acked(&self, execution_id: &str, client_order_id: &str, filled_quantity: f64) {
+        if !self.inner.lock().unwrap().in_flight.contains_key(execution_id) {
+            return;
+        }
+        self.write(
+            Entry::LegAcked {
+                execution_id: execution_id.to_string(),
+                client_order_id: client_order_id.to_string(),
+                filled_quantity,
+            },
+            false,
+        );
+    }
+
+    pub fn finished(&self, execution_id: &str) {
+        self.write(Entry::Finished { execution_id: execution_id.to_string() }, false);
+    }
+
+    pub fn in_flight(&self) -> Vec<InFlight> {
+        self.inner.lock().unwrap().in_flight.values().cloned().collect()
+    }
+
+    pub fn execution(&self, execution_id: &str) -> Option<InFlight> {
+        self.inner.lock().unwrap().in_flight.get(execution_id).cloned()
+    }
+
+    pub fn recoveries(&self) -> Vec<Recovery> {
+        self.recoveries.lock().unwrap().clone()
+    }
+
+    // 下單條目同步到磁盤；其餘條目丟失時只會多查詢一次交易所
+    fn write(&self, entry: Entry, sync: bool) {
+        let mut inner = self.inner.lock().unwrap();
+        let line = serde_json::to_string(&entry).unwrap();
+        InFlight::apply(&mut inner.in_flight, entry);
+        let result = if inner.in_flight.is_empty() {
+            inner.file.set_len(0)
+        } else {
+            writeln!(inner.file, "{}", line).and_then(|_| if sync { inner.file.sync_data() } else { Ok(()) })
+        };
+        if let Err(e) = result {
+            error!(error = %e, "寫入執行日誌失敗");
+        }
+    }
+}
+
+/// 恢復結果，供日誌與 get_journal 查看。
+#[derive(Debug, Clone, Serialize)]
+pub struct Recovery {
+    pub execution_id: String,
+    // resumed：兩側成交一致，按已開倉的對沖繼續持有；unwound：反向平掉已成交的腿；
+    // abandoned：沒有成交；failed：查詢失敗，留待下次重啟
+    pub outcome: String,
+    pub orders: Vec<ChildOrder>,
+    pub error: Option<String>,
+}
+
+// 啟動時處理上次退出時仍在執行中的請求：未確認的腿向交易所按 client_order_id 查詢成交，
+// 兩側成交數量一致時恢復對沖持倉，否則反向平掉已成交的腿
+pub(crate) async fn recover(engine: &ExecutionEngine) -> Vec<Recovery> {
+    let mut recoveries = Vec::new();
+    for execution in engine.journal.in_flight() {
+        let recovery = recover_one(engine, &execution).await;
+        match &recovery.error {
+            Some(error) => {
+                error!(execution_id = %execution.execution_id, %error, "執行恢復失敗，保留在執行日誌中");
+                let message = format!("{} {}：{}", execution.execution_id, execution.request.symbol, error);
+                engine.alert(Severity::Critical, "崩潰前的執行無法恢復", message);
+            }
+            None => {
+                warn!(execution_id = %execution.execution_id, outcome = %recovery.outcome, "已恢復崩潰前的執行");
+                engine.journal.finished(&execution.execution_id);
+            }
+        }
+        recoveries.push(recovery);
+    }
+    *engine.journal.recoveries.lock().unwrap() = recoveries.clone();
+    recoveries
+}
+
+async fn recover_one(engine: &ExecutionEngine, execution: &InFlight) -> Recovery {
+    let request = &execution.request;
+    let mut orders = Vec::new();
+    for leg in &execution.legs {
+        // 恢復後交易所推送的成交仍能歸屬到這次執行
+        engine.order_ids.register(
+            &leg.client_order_id,
+            OrderTag {
+                strategy_id: request.strategy_id.clone(),
+                execution_id: execution.execution_id.clone(),
+                leg: leg.leg.clone(),
+            },
+        );
+        let filled = match leg.filled_quantity {
+            Some(filled) => filled,
+            None => {
+                let Some(connector) = engine.exchanges.get(&leg.exchange) else { continue };
+                // 先撤銷可能仍在途的訂單，避免查詢之後才成交
+                if let Err(error) = connector.cancel_order(&leg.symbol, &leg.client_order_id).await {
+                    warn!(exchange = %leg.exchange, client_order_id = %leg.client_order_id, %error, "恢復前撤單失敗");
+                }
+                match connector.order_status(&leg.symbol, &leg.client_order_id).await {
+                    Ok(filled) => filled.unwrap_or(0.0),
+                    Err(error) => {
+                        return Recovery {
+                            execution_id: execution.execution_id.clone(),
+                            outcome: "failed".to_string(),
+                            orders: Vec::new(),
+                            error: Some(format!("查詢 {} 訂單 {} 失敗: {}", leg.exchange, leg.client_order_id, error)),
+                        };
+                    }
+                }
+            }
+        };
+        orders.push(leg.order(filled, engine.taker_fee(&leg.exchange)));
+    }
+    let side_total = |side: &str| orders.iter().filter(|order| order.side == side).map(|order| order.filled_quantity).sum::<f64>();
+    let (sold, bought) = (side_total("sell"), side_total("buy"));
+    let leverage = engine.leverage_setting(request);
+    let (outcome, fees, recorded) = if sold <= 0.0 && bought <= 0.0 {
+        ("abandoned", 0.0, Vec::new())
+    } else if sold > 0.0 && (sold - bought).abs() <= f64::EPSILON * sold.max(bought) {
+        if request.strategy_type == StrategyType::FundingRate {
+            engine.funding_pairs.open(&execution.execution_id, &request.symbol, &orders, engine.env.now_ms());
+        }
+        for order in &orders {
+            let perp = perp_leg(request, &order.leg);
+            engine.exchanges[&order.exchange].simulate_fill(&execution.execution_id, order, perp.then_some(leverage.leverage));
+        }
+        ("resumed", orders.iter().map(|order| order.fee).sum(), orders)
+    } else {
+        let unwound = engine.unwind_local_legs(&execution.execution_id, request, &orders, leverage);
+        ("unwound", unwound.iter().map(|order| order.fee).sum(), unwound)
+    };
+    let response = ArbitrageResponse {
+        execution_id: Some(execution.execution_id.clone()),
+        status: outcome.to_string(),
+        error_message: match outcome {
+            "unwound" => Some("執行在引擎重啟前中斷，已反向平倉".to_string()),
+            "abandoned" => Some("執行在引擎重啟前中斷，沒有成交".to_string()),
+            _ => None,
+        },
+        ..ArbitrageResponse::error("")
+    };
+    engine.record_execution_events(&execution.execution_id, request, &response, fees, &recorded);
+    engine.record_realized_pnl(&request.strategy_id, events::settled_pnl(&response.status, 0.0, fees));
+    Recovery {
+        execution_id: execution.execution_id.clone(),
+        outcome: outcome.to_string(),
+        orders: recorded,
+        error: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::deterministic_sim::request;
+    use crate::mock_exchange::Scenario;
+    use crate::mock_exchange_e2e::engine;
+
+    #[tokio::test]
+    async fn journal_recovers_in_flight_executions_after_crash() {
+        let (mock, engine, path) = engine("journal", Scenario::default()).await;
+        let leg = |leg: &str, exchange: &str, side: &str, client_order_id: &str| JournalLeg {
+            leg: leg.to_string(),
+            exchange: exchange.to_string(),
+            symbol: "BTCUSDT".to_string(),
+            side: side.to_string(),
+            quantity: 10.0,
+            client_order_id: client_order_id.to_string(),
+            filled_quantity: None,
+        };
+        // 崩潰前：e1 只有空頭腿送達交易所，e2 兩條腿都已成交但未收到確認
+        engine.journal.started("e1", &request(10.0), 0);
+        engine.journal.leg_submitted("e1", leg("short", "binance", "sell", "c1"));
+        engine.journal.leg_submitted("e1", leg("long", "bybit", "buy", "c2"));
+        engine.exchanges["binance"].submit_order("BTCUSDT", "sell", 10.0, "c1").await.unwrap();
+        engine.journal.started("e2", &request(10.0), 0);
+        engine.journal.leg_submitted("e2", leg("short", "okx", "sell", "c3"));
+        engine.journal.leg_submitted("e2", leg("long", "bybit", "buy", "c4"));
+        engine.exchanges["okx"].submit_order("BTCUSDT", "sell", 10.0, "c3").await.unwrap();
+        engine.exchanges["bybit"].submit_order("BTCUSDT", "buy", 10.0, "c4").await.unwrap();
+
+        let journal_path = path.with_extension("journal");
+        let replayed = Journal::open(journal_path.to_str().unwrap()).unwrap().in_flight();
+        assert_eq!(replayed.iter().map(|execution| execution.legs.len()).collect::<Vec<_>>(), [2, 2]);
+
+        let recoveries = recover(&engine).await;
+        let outcomes: Vec<_> = recoveries.iter().map(|recovery| (recovery.execution_id.as_str(), recovery.outcome.as_str())).collect();
+        assert_eq!(outcomes, [("e1", "unwound"), ("e2", "resumed")]);
+        let legs: Vec<_> = recoveries[0].orders.iter().map(|order| (order.leg.as_str(), order.filled_quantity)).collect();
+        assert_eq!(legs, [("short", 10.0), ("unwind_short", 10.0)]);
+        assert!(mock.requests().iter().any(|request| request == "GET /fapi/v1/order"));
+        assert!(engine.journal.in_flight().is_empty());
+        assert_eq!(std::fs::metadata(&journal_path).unwrap().len(), 0);
+        let _ = std::fs::remove_file(journal_path);
+        let _ = std::fs::remove_file(path);
+    }
+}
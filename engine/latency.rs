@@ -0,0 +1,218 @@
+use dashmap::DashMap;
+use hdrhistogram::Histogram;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::Mutex;
+use std::time::Instant;
+
+// 可記錄 1µs ~ 60s，三位有效數字
+const MAX_MICROS: u64 = 60_000_000;
+const SIGNIFICANT_DIGITS: u8 = 3;
+const QUANTILES: [f64; 4] = [0.5, 0.9, 0.99, 0.999];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StageTiming {
+    pub stage: String,
+    pub micros: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Timings {
+    // 按發生順序
+    pub stages: Vec<StageTiming>,
+    pub total_micros: u64,
+}
+
+impl Timings {
+    // 連接層在引擎計時之前的階段
+    pub fn prepend(&mut self, stage: &str, micros: u64) {
+        self.stages.insert(
+            0,
+            StageTiming {
+                stage: stage.to_string(),
+                micros,
+            },
+        );
+        self.total_micros += micros;
+    }
+}
+
+// 每次 mark 記錄距上一次 mark 的耗時；跨 await 共享，內部加鎖
+pub struct StageTimer {
+    started: Instant,
+    state: Mutex<(Instant, Vec<StageTiming>)>,
+}
+
+impl StageTimer {
+    pub fn start() -> Self {
+        let now = Instant::now();
+        Self {
+            started: now,
+            state: Mutex::new((now, Vec::new())),
+        }
+    }
+
+    pub fn mark(&self, stage: &str) {
+        let now = Instant::now();
+        let mut state = self.state.lock().unwrap();
+        let micros = now.duration_since(state.0).as_micros() as u64;
+        state.0 = now;
+        state.1.push(StageTiming {
+            stage: stage.to_string(),
+            micros,
+        });
+    }
+
+    pub fn finish(self) -> Timings {
+        let total_micros = self.started.elapsed().as_micros() as u64;
+        Timings {
+            stages: self.state.into_inner().unwrap().1,
+            total_micros,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct StageSummary {
+    pub count: u64,
+    pub mean: f64,
+    pub min: u64,
+    pub p50: u64,
+    pub p90: u64,
+    pub p99: u64,
+    pub p999: u64,
+    pub max: u64,
+}
+
+pub(crate) fn histogram() -> Histogram<u64> {
+    Histogram::new_with_bounds(1, MAX_MICROS, SIGNIFICANT_DIGITS).expect("直方圖範圍有效")
+}
+
+pub(crate) fn summarize(histogram: &Histogram<u64>) -> StageSummary {
+    StageSummary {
+        count: histogram.len(),
+        mean: histogram.mean(),
+        min: histogram.min(),
+        p50: histogram.value_at_quantile(0.5),
+        p90: histogram.value_at_quantile(0.9),
+        p99: histogram.value_at_quantile(0.99),
+        p999: histogram.value_at_quantile(0.999),
+        max: histogram.max(),
+    }
+}
+
+pub struct LatencyRecorder {
+    // 每個請求的每個階段都會寫入，按階段分片加鎖，不同階段並發記錄互不阻塞
+    histograms: DashMap<String, Histogram<u64>>,
+}
+
+impl LatencyRecorder {
+    pub fn new() -> Self {
+        Self {
+            histograms: DashMap::new(),
+        }
+    }
+
+    pub fn record(&self, stage: &str, micros: u64) {
+        // 已有的階段不分配鍵
+        if let Some(mut histogram) = self.histograms.get_mut(stage) {
+            histogram.saturating_record(micros.max(1));
+            return;
+        }
+        let mut histogram = self.histograms.entry(stage.to_string()).or_insert_with(histogram);
+        histogram.saturating_record(micros.max(1));
+    }
+
+    // total 只統計引擎內耗時，不含連接層解析
+    pub fn record_timings(&self, timings: &Timings) {
+        for timing in &timings.stages {
+            self.record(&timing.stage, timing.micros);
+        }
+        self.record("total", timings.total_micros);
+    }
+
+    pub fn snapshot(&self) -> BTreeMap<String, StageSummary> {
+        self.histograms
+            .iter()
+            .map(|item| (item.key().clone(), summarize(item.value())))
+            .collect()
+    }
+
+    // Prometheus summary；_sum 由均值乘次數近似
+    pub fn prometheus(&self) -> String {
+        let mut output = String::new();
+        output.push_str("# HELP arb_stage_latency_microseconds Execution stage latency in microseconds\n");
+        output.push_str("# TYPE arb_stage_latency_microseconds summary\n");
+        let mut stages: Vec<String> = self.histograms.iter().map(|item| item.key().clone()).collect();
+        stages.sort();
+        for stage in stages {
+            let Some(histogram) = self.histograms.get(&stage) else { continue };
+            for quantile in QUANTILES {
+                let _ = writeln!(
+                    output,
+                    "arb_stage_latency_microseconds{{stage=\"{}\",quantile=\"{}\"}} {}",
+                    stage,
+                    quantile,
+                    histogram.value_at_quantile(quantile)
+                );
+            }
+            let _ = writeln!(
+                output,
+                "arb_stage_latency_microseconds_sum{{stage=\"{}\"}} {}",
+                stage,
+                histogram.mean() * histogram.len() as f64
+            );
+            let _ = writeln!(output, "arb_stage_latency_microseconds_count{{stage=\"{}\"}} {}", stage, histogram.len());
+        }
+        output
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bench::START_MS;
+    use crate::deterministic_sim::{build, connect_sessions, funded_config, request};
+    use crate::{execution_queue, server, ArbitrageResponse, EngineCommand, Environment};
+    use std::sync::Arc;
+
+    // 響應帶有按階段的微秒耗時，連接層解析計入其中；各階段匯總為 HDR 直方圖並以 Prometheus 格式導出
+    #[tokio::test]
+    async fn executions_report_stage_timings_and_aggregate_histograms() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+        let (engine, path) = build("stage-timings", funded_config(), Environment::simulated(START_MS, 1));
+        let engine = Arc::new(engine);
+        connect_sessions(&engine).await;
+        execution_queue::spawn(Arc::clone(&engine));
+        engine.quotes.record_predicted_rate("binance", "BTCUSDT", 0.0060, START_MS);
+        engine.quotes.record_predicted_rate("bybit", "BTCUSDT", 0.0010, START_MS);
+        let (mut client, socket) = tokio::io::duplex(64 * 1024);
+        let serving = Arc::clone(&engine);
+        tokio::spawn(async move { server::handle_connection(socket, &serving).await });
+        let mut buffer = vec![0; 64 * 1024];
+        let mut responses = Vec::new();
+        for _ in 0..3 {
+            client.write_all(serde_json::to_string(&request(1_000.0)).unwrap().as_bytes()).await.unwrap();
+            let n = client.read(&mut buffer).await.unwrap();
+            responses.push(serde_json::from_slice::<ArbitrageResponse>(&buffer[..n]).unwrap());
+        }
+        for response in &responses {
+            assert_eq!(response.status, "success", "{:?}", response.error_message);
+            let timings = response.timings.as_ref().unwrap();
+            let stages: Vec<&str> = timings.stages.iter().map(|timing| timing.stage.as_str()).collect();
+            assert_eq!(stages, ["request_parse", "risk_check", "rate_lookup", "leg1_ack", "leg2_ack", "fill_confirmation", "settle"]);
+            // 各階段之間的記賬與日誌也計入總耗時
+            assert!(timings.stages.iter().map(|timing| timing.micros).sum::<u64>() <= timings.total_micros);
+        }
+        let latency = engine.handle_command(EngineCommand::GetLatency).await.data.unwrap();
+        for stage in ["request_parse", "leg1_ack", "settle", "total"] {
+            assert_eq!(latency[stage]["count"], 3, "{}", stage);
+            assert!(latency[stage]["min"].as_u64().unwrap() <= latency[stage]["p99"].as_u64().unwrap());
+        }
+        let metrics = engine.latency.prometheus();
+        assert!(metrics.contains("# TYPE arb_stage_latency_microseconds summary"));
+        assert!(metrics.contains("arb_stage_latency_microseconds_count{stage=\"leg2_ack\"} 3"));
+        assert!(metrics.contains("arb_stage_latency_microseconds{stage=\"total\",quantile=\"0.99\"}"));
+        let _ = std::fs::remove_file(path);
+    }
+}
@@ -0,0 +1,792 @@
+use super::chain::ChainStack;
+use super::config::{LendingConfig, LendingMarketConfig, LendingProtocol, Severity, TokenConfig};
+use super::dex::{self, to_units};
+use super::environment::Rng;
+use super::execution_plan::{self, Action, ExecutionPlan};
+use super::flash_loan::{encode_call, load_abi};
+use super::market_data;
+use super::tx_simulation::{self, OnchainError};
+use super::wallet::WalletManager;
+use super::{ArbitrageRequest, ExecutionEngine, ExecutionOutcome};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tracing::{debug, info, warn};
+use web3::ethabi::Token;
+use web3::types::{Address, Bytes, CallRequest, TransactionParameters, H256, U256};
+
+const SECONDS_PER_YEAR: f64 = 31_536_000.0;
+const MS_PER_YEAR: f64 = SECONDS_PER_YEAR * 1000.0;
+const WAD: f64 = 1e18;
+// 資金費期現部署要求借出穩定幣
+const STABLECOINS: [&str; 3] = ["USDT", "USDC", "DAI"];
+
+const COMET_ABI: &str = r#"[
+    {"type":"function","name":"getUtilization","stateMutability":"view","inputs":[],"outputs":[{"name":"","type":"uint256"}]},
+    {"type":"function","name":"getSupplyRate","stateMutability":"view","inputs":[{"name":"utilization","type":"uint256"}],"outputs":[{"name":"","type":"uint64"}]},
+    {"type":"function","name":"getBorrowRate","stateMutability":"view","inputs":[{"name":"utilization","type":"uint256"}],"outputs":[{"name":"","type":"uint64"}]},
+    {"type":"function","name":"supply","stateMutability":"nonpayable","inputs":[{"name":"asset","type":"address"},{"name":"amount","type":"uint256"}],"outputs":[]},
+    {"type":"function","name":"withdraw","stateMutability":"nonpayable","inputs":[{"name":"asset","type":"address"},{"name":"amount","type":"uint256"}],"outputs":[]}
+]"#;
+const MORPHO_ABI: &str = r#"[
+    {"type":"function","name":"idToMarketParams","stateMutability":"view","inputs":[{"name":"id","type":"bytes32"}],"outputs":[
+        {"name":"loanToken","type":"address"},{"name":"collateralToken","type":"address"},{"name":"oracle","type":"address"},
+        {"name":"irm","type":"address"},{"name":"lltv","type":"uint256"}]},
+    {"type":"function","name":"market","stateMutability":"view","inputs":[{"name":"id","type":"bytes32"}],"outputs":[
+        {"name":"totalSupplyAssets","type":"uint128"},{"name":"totalSupplyShares","type":"uint128"},
+        {"name":"totalBorrowAssets","type":"uint128"},{"name":"totalBorrowShares","type":"uint128"},
+        {"name":"lastUpdate","type":"uint128"},{"name":"fee","type":"uint128"}]},
+    {"type":"function","name":"supply","stateMutability":"nonpayable","inputs":[
+        {"name":"marketParams","type":"tuple","components":[
+            {"name":"loanToken","type":"address"},{"name":"collateralToken","type":"address"},{"name":"oracle","type":"address"},
+            {"name":"irm","type":"address"},{"name":"lltv","type":"uint256"}]},
+        {"name":"assets","type":"uint256"},{"name":"shares","type":"uint256"},{"name":"onBehalf","type":"address"},{"name":"data","type":"bytes"}],
+     "outputs":[{"name":"","type":"uint256"},{"name":"","type":"uint256"}]},
+    {"type":"function","name":"withdraw","stateMutability":"nonpayable","inputs":[
+        {"name":"marketParams","type":"tuple","components":[
+            {"name":"loanToken","type":"address"},{"name":"collateralToken","type":"address"},{"name":"oracle","type":"address"},
+            {"name":"irm","type":"address"},{"name":"lltv","type":"uint256"}]},
+        {"name":"assets","type":"uint256"},{"name":"shares","type":"uint256"},{"name":"onBehalf","type":"address"},{"name":"receiver","type":"address"}],
+     "outputs":[{"name":"","type":"uint256"},{"name":"","type":"uint256"}]},
+    {"type":"function","name":"borrow","stateMutability":"nonpayable","inputs":[
+        {"name":"marketParams","type":"tuple","components":[
+            {"name":"loanToken","type":"address"},{"name":"collateralToken","type":"address"},{"name":"oracle","type":"address"},
+            {"name":"irm","type":"address"},{"name":"lltv","type":"uint256"}]},
+        {"name":"assets","type":"uint256"},{"name":"shares","type":"uint256"},{"name":"onBehalf","type":"address"},{"name":"receiver","type":"address"}],
+     "outputs":[{"name":"","type":"uint256"},{"name":"","type":"uint256"}]},
+    {"type":"function","name":"repay","stateMutability":"nonpayable","inputs":[
+        {"name":"marketParams","type":"tuple","components":[
+            {"name":"loanToken","type":"address"},{"name":"collateralToken","type":"address"},{"name":"oracle","type":"address"},
+            {"name":"irm","type":"address"},{"name":"lltv","type":"uint256"}]},
+        {"name":"assets","type":"uint256"},{"name":"shares","type":"uint256"},{"name":"onBehalf","type":"address"},{"name":"data","type":"bytes"}],
+     "outputs":[{"name":"","type":"uint256"},{"name":"","type":"uint256"}]},
+    {"type":"function","name":"supplyCollateral","stateMutability":"nonpayable","inputs":[
+        {"name":"marketParams","type":"tuple","components":[
+            {"name":"loanToken","type":"address"},{"name":"collateralToken","type":"address"},{"name":"oracle","type":"address"},
+            {"name":"irm","type":"address"},{"name":"lltv","type":"uint256"}]},
+        {"name":"assets","type":"uint256"},{"name":"onBehalf","type":"address"},{"name":"data","type":"bytes"}],
+     "outputs":[]},
+    {"type":"function","name":"withdrawCollateral","stateMutability":"nonpayable","inputs":[
+        {"name":"marketParams","type":"tuple","components":[
+            {"name":"loanToken","type":"address"},{"name":"collateralToken","type":"address"},{"name":"oracle","type":"address"},
+            {"name":"irm","type":"address"},{"name":"lltv","type":"uint256"}]},
+        {"name":"assets","type":"uint256"},{"name":"onBehalf","type":"address"},{"name":"receiver","type":"address"}],
+     "outputs":[]}
+]"#;
+const MORPHO_IRM_ABI: &str = r#"[
+    {"type":"function","name":"borrowRateView","stateMutability":"view","inputs":[
+        {"name":"marketParams","type":"tuple","components":[
+            {"name":"loanToken","type":"address"},{"name":"collateralToken","type":"address"},{"name":"oracle","type":"address"},
+            {"name":"irm","type":"address"},{"name":"lltv","type":"uint256"}]},
+        {"name":"market","type":"tuple","components":[
+            {"name":"totalSupplyAssets","type":"uint128"},{"name":"totalSupplyShares","type":"uint128"},
+            {"name":"totalBorrowAssets","type":"uint128"},{"name":"totalBorrowShares","type":"uint128"},
+            {"name":"lastUpdate","type":"uint128"},{"name":"fee","type":"uint128"}]}],
+     "outputs":[{"name":"","type":"uint256"}]}
+]"#;
+
+/// 貨幣市場上的一步操作；抵押品只進出抵押賬戶，不計息。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LendingOp {
+    SupplyCollateral,
+    WithdrawCollateral,
+    Borrow,
+    Repay,
+    Supply,
+    Withdraw,
+}
+
+impl LendingOp {
+    pub fn reversed(self) -> Self {
+        match self {
+            LendingOp::SupplyCollateral => LendingOp::WithdrawCollateral,
+            LendingOp::WithdrawCollateral => LendingOp::SupplyCollateral,
+            LendingOp::Borrow => LendingOp::Repay,
+            LendingOp::Repay => LendingOp::Borrow,
+            LendingOp::Supply => LendingOp::Withdraw,
+            LendingOp::Withdraw => LendingOp::Supply,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            LendingOp::SupplyCollateral => "supply_collateral",
+            LendingOp::WithdrawCollateral => "withdraw_collateral",
+            LendingOp::Borrow => "borrow",
+            LendingOp::Repay => "repay",
+            LendingOp::Supply => "supply",
+            LendingOp::Withdraw => "withdraw",
+        }
+    }
+
+    fn collateral(self) -> bool {
+        matches!(self, LendingOp::SupplyCollateral | LendingOp::WithdrawCollateral)
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MarketRates {
+    pub supply_apr: f64,
+    pub borrow_apr: f64,
+    pub utilization: f64,
+    pub sampled_at_ms: i64,
+    // false 表示按配置的利率模型模擬
+    pub onchain: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LendingPosition {
+    pub execution_id: String,
+    pub strategy_id: String,
+    pub borrow_market: String,
+    // 存款市場，或資金費期現頭寸所在的交易所
+    pub deploy_venue: String,
+    pub symbol: String,
+    pub collateral: f64,
+    // 借入的本金與含應計利息的債務
+    pub principal: f64,
+    pub debt: f64,
+    // 期現頭寸的現貨數量，存入貨幣市場時為 0
+    pub carry_quantity: f64,
+    // 存款利息或資金費累計收入
+    pub earned: f64,
+    pub borrow_apr: f64,
+    pub yield_apr: f64,
+    pub spread_apr: f64,
+    pub health_factor: f64,
+    pub opened_at_ms: i64,
+    pub updated_at_ms: i64,
+    #[serde(skip)]
+    request: ArbitrageRequest,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LendingOpportunity {
+    pub borrow_market: String,
+    pub deploy_venue: String,
+    pub symbol: String,
+    pub borrow_apr: f64,
+    pub yield_apr: f64,
+    pub spread_apr: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LendingSnapshot {
+    pub rates: BTreeMap<String, MarketRates>,
+    pub positions: Vec<LendingPosition>,
+    // 按利差從高到低
+    pub opportunities: Vec<LendingOpportunity>,
+}
+
+#[derive(Default)]
+struct State {
+    rates: BTreeMap<String, MarketRates>,
+    // 模擬市場當前的利用率
+    utilization: BTreeMap<String, f64>,
+    // 開倉的 execution_id -> 頭寸
+    positions: BTreeMap<String, LendingPosition>,
+    opportunities: Vec<LendingOpportunity>,
+}
+
+pub struct LendingBook {
+    config: LendingConfig,
+    state: Mutex<State>,
+}
+
+impl LendingBook {
+    pub fn new(config: LendingConfig) -> Self {
+        Self {
+            config,
+            state: Mutex::new(State::default()),
+        }
+    }
+
+    pub fn has_market(&self, name: &str) -> bool {
+        self.config.markets.contains_key(name)
+    }
+
+    fn market(&self, name: &str) -> Result<&LendingMarketConfig, String> {
+        self.config.markets.get(name).ok_or_else(|| format!("未配置借貸市場 {}", name))
+    }
+
+    // 操作涉及的資產：抵押品操作為抵押資產，其餘為借出資產
+    pub(crate) fn asset(&self, name: &str, op: LendingOp) -> String {
+        self.config
+            .markets
+            .get(name)
+            .map(|market| if op.collateral() { market.collateral.clone() } else { market.asset.clone() })
+            .unwrap_or_default()
+    }
+
+    pub fn snapshot(&self) -> LendingSnapshot {
+        let state = self.state.lock().unwrap();
+        LendingSnapshot {
+            rates: state.rates.clone(),
+            positions: state.positions.values().cloned().collect(),
+            opportunities: state.opportunities.clone(),
+        }
+    }
+
+    // 讀取最新利率：鏈上市場調用合約，否則按利率模型模擬一步
+    async fn refresh(&self, engine: &ExecutionEngine, name: &str) -> Result<MarketRates, String> {
+        let market = self.market(name)?;
+        let now = engine.env.now_ms();
+        let rates = match onchain(engine, market)? {
+            Some((chain, _)) => read_rates(chain, market, now).await?,
+            None => self.simulate(engine.env.rng.as_ref(), name, market, now),
+        };
+        self.state.lock().unwrap().rates.insert(name.to_string(), rates.clone());
+        Ok(rates)
+    }
+
+    fn simulate(&self, rng: &dyn Rng, name: &str, market: &LendingMarketConfig, now: i64) -> MarketRates {
+        let model = &market.simulation;
+        let mut state = self.state.lock().unwrap();
+        let utilization = state.utilization.entry(name.to_string()).or_insert(model.utilization);
+        // 向配置的利用率回歸，疊加 ±2.5% 的隨機擾動
+        *utilization += 0.2 * (model.utilization - *utilization) + (rng.next_f64() - 0.5) * 0.05;
+        *utilization = utilization.clamp(0.0, 0.99);
+        let u = *utilization;
+        let borrow_apr = model.base_apr
+            + model.slope_apr * u.min(model.kink) / model.kink
+            + model.jump_apr * (u - model.kink).max(0.0) / (1.0 - model.kink);
+        MarketRates {
+            supply_apr: borrow_apr * u * (1.0 - model.reserve_factor),
+            borrow_apr,
+            utilization: u,
+            sampled_at_ms: now,
+            onchain: false,
+        }
+    }
+
+    // 按最新利率與資金費重算機會：同一資產的市場兩兩組合，以及穩定幣市場對各交易所的資金費
+    fn rank(&self, engine: &ExecutionEngine) {
+        let mut state = self.state.lock().unwrap();
+        let mut opportunities = Vec::new();
+        for (borrow_name, borrow) in &state.rates {
+            let market = &self.config.markets[borrow_name];
+            let mut push = |deploy_venue: &str, symbol: String, yield_apr: f64| {
+                let spread_apr = yield_apr - borrow.borrow_apr;
+                if spread_apr >= self.config.min_spread_apr {
+                    opportunities.push(LendingOpportunity {
+                        borrow_market: borrow_name.clone(),
+                        deploy_venue: deploy_venue.to_string(),
+                        symbol,
+                        borrow_apr: borrow.borrow_apr,
+                        yield_apr,
+                        spread_apr,
+                    });
+                }
+            };
+            for (deploy_name, deploy) in &state.rates {
+                if deploy_name != borrow_name && self.config.markets[deploy_name].asset == market.asset {
+                    push(deploy_name, format!("{}{}", market.collateral, market.asset), deploy.supply_apr);
+                }
+            }
+            if STABLECOINS.contains(&market.asset.as_str()) {
+                for sample in engine.funding_history.latest() {
+                    push(&sample.exchange, sample.symbol.clone(), funding_apr(engine, &sample.exchange, sample.predicted_rate));
+                }
+            }
+        }
+        opportunities.sort_by(|a, b| b.spread_apr.total_cmp(&a.spread_apr));
+        state.opportunities = opportunities;
+    }
+}
+
+// 鏈上市場所在的鏈，模擬市場返回 None
+pub(crate) fn onchain_chain<'a>(engine: &'a ExecutionEngine, name: &str) -> Result<Option<&'a ChainStack>, String> {
+    Ok(onchain(engine, engine.lending.market(name)?)?.map(|(chain, _)| chain))
+}
+
+// 鏈上市場需要所在鏈配置了熱錢包且市場配置了合約地址；否則返回 None 按模擬執行
+fn onchain<'a>(engine: &'a ExecutionEngine, market: &LendingMarketConfig) -> Result<Option<(&'a ChainStack, &'a Arc<WalletManager>)>, String> {
+    let chain = engine.chain(market.chain.as_deref())?;
+    Ok(chain.wallets.as_ref().filter(|_| !market.address.is_empty()).map(|wallets| (chain, wallets)))
+}
+
+fn parse_address(value: &str) -> Result<Address, String> {
+    Address::from_str(value).map_err(|e| format!("{} 不是有效地址: {}", value, e))
+}
+
+fn per_second_to_apr(rate: U256) -> f64 {
+    rate.low_u128() as f64 / WAD * SECONDS_PER_YEAR
+}
+
+// 按交易所的結算間隔把單期資金費換算為年化
+fn funding_apr(engine: &ExecutionEngine, exchange: &str, rate: f64) -> f64 {
+    let hours = engine.config.exchanges.get(exchange).map(|c| c.funding_interval_hours).unwrap_or(8).max(1);
+    rate * (365 * 24 / hours) as f64
+}
+
+// 抵押資產以借出資產計價的價格：優先使用預言機，沒有未過期喂價時退回交易所中間價
+fn collateral_price(engine: &ExecutionEngine, name: &str, market: &LendingMarketConfig) -> Result<f64, String> {
+    if let Some(price) = engine.oracle.pair_price(&market.collateral, &market.asset, engine.env.now_ms()) {
+        return Ok(price);
+    }
+    market_data::simulated_spot_book(engine.env.rng.as_ref(), name, &market.collateral, &market.asset)?
+        .mid()
+        .ok_or_else(|| format!("{} 抵押品價格不可用", name))
+}
+
+async fn read_rates(chain: &ChainStack, market: &LendingMarketConfig, now: i64) -> Result<MarketRates, String> {
+    let web3 = chain.wallets.as_ref().ok_or("未配置熱錢包")?.web3();
+    let address = parse_address(&market.address)?;
+    let (utilization, supply_apr, borrow_apr) = match market.protocol {
+        LendingProtocol::CompoundV3 => {
+            let abi = load_abi(COMET_ABI);
+            let utilization = dex::call_uint(web3, &abi, address, "getUtilization", &[], None).await?;
+            let supply = dex::call_uint(web3, &abi, address, "getSupplyRate", &[Token::Uint(utilization)], None).await?;
+            let borrow = dex::call_uint(web3, &abi, address, "getBorrowRate", &[Token::Uint(utilization)], None).await?;
+            (utilization.low_u128() as f64 / WAD, per_second_to_apr(supply), per_second_to_apr(borrow))
+        }
+        LendingProtocol::MorphoBlue => {
+            let abi = load_abi(MORPHO_ABI);
+            let id = Token::FixedBytes(market_id(market)?.as_bytes().to_vec());
+            let params = dex::call(web3, &abi, address, "idToMarketParams", std::slice::from_ref(&id), None).await?;
+            let irm = params.get(3).cloned().and_then(Token::into_address).ok_or("idToMarketParams 缺少 irm")?;
+            let state = dex::call(web3, &abi, address, "market", &[id], None).await?;
+            let amount = |i: usize| state.get(i).cloned().and_then(Token::into_uint).map(|v| v.low_u128() as f64).unwrap_or_default();
+            let (total_supply, total_borrow, fee) = (amount(0), amount(2), amount(5));
+            let rate = dex::call_uint(
+                web3,
+                &load_abi(MORPHO_IRM_ABI),
+                irm,
+                "borrowRateView",
+                &[Token::Tuple(params), Token::Tuple(state)],
+                None,
+            )
+            .await?;
+            let utilization = if total_supply > 0.0 { total_borrow / total_supply } else { 0.0 };
+            let borrow_apr = per_second_to_apr(rate);
+            (utilization, borrow_apr * utilization * (1.0 - fee / WAD), borrow_apr)
+        }
+    };
+    Ok(MarketRates {
+        supply_apr,
+        borrow_apr,
+        utilization,
+        sampled_at_ms: now,
+        onchain: true,
+    })
+}
+
+fn market_id(market: &LendingMarketConfig) -> Result<H256, String> {
+    H256::from_str(&market.market_id).map_err(|e| format!("market_id {} 無效: {}", market.market_id, e))
+}
+
+fn token(market: &LendingMarketConfig, op: LendingOp) -> Result<&TokenConfig, String> {
+    let (token, asset) = if op.collateral() {
+        (&market.collateral_token, &market.collateral)
+    } else {
+        (&market.asset_token, &market.asset)
+    };
+    token.as_ref().ok_or_else(|| format!("鏈上借貸市場未配置 {} 的代幣地址", asset))
+}
+
+/// 在借貸市場執行一步操作，返回鏈上消耗的 gas 與以 ETH 計的 gas 費；模擬市場直接成功。
+/// 代幣授權需事先完成；交易回滾時燒掉的 gas 計入該執行的已實現虧損。
+pub(crate) async fn perform(
+    engine: &ExecutionEngine,
+    execution_id: &str,
+    request: &ArbitrageRequest,
+    name: &str,
+    op: LendingOp,
+    amount: f64,
+) -> Result<(Option<u64>, Option<f64>), String> {
+    let book = &engine.lending;
+    let market = book.market(name)?;
+    let Some((chain, wallets)) = onchain(engine, market)? else {
+        debug!(market = name, op = op.name(), amount, "模擬借貸操作");
+        return Ok((None, None));
+    };
+    let wallet = wallets.primary().address();
+    let contract = parse_address(&market.address)?;
+    let token = token(market, op)?;
+    let units = Token::Uint(to_units(amount, token.decimals));
+    let data = match market.protocol {
+        LendingProtocol::CompoundV3 => {
+            // Comet 的借款即提取基礎資產，還款即存入基礎資產
+            let function = match op {
+                LendingOp::SupplyCollateral | LendingOp::Supply | LendingOp::Repay => "supply",
+                LendingOp::WithdrawCollateral | LendingOp::Withdraw | LendingOp::Borrow => "withdraw",
+            };
+            encode_call(&load_abi(COMET_ABI), function, &[Token::Address(parse_address(&token.address)?), units])?
+        }
+        LendingProtocol::MorphoBlue => {
+            let abi = load_abi(MORPHO_ABI);
+            let id = Token::FixedBytes(market_id(market)?.as_bytes().to_vec());
+            let params = Token::Tuple(dex::call(wallets.web3(), &abi, contract, "idToMarketParams", &[id], None).await?);
+            let (zero, me, empty) = (Token::Uint(U256::zero()), Token::Address(wallet), Token::Bytes(Vec::new()));
+            let (function, tokens) = match op {
+                LendingOp::SupplyCollateral => ("supplyCollateral", vec![params, units, me, empty]),
+                LendingOp::WithdrawCollateral => ("withdrawCollateral", vec![params, units, me.clone(), me]),
+                LendingOp::Supply => ("supply", vec![params, units, zero, me, empty]),
+                LendingOp::Repay => ("repay", vec![params, units, zero, me, empty]),
+                LendingOp::Withdraw => ("withdraw", vec![params, units, zero, me.clone(), me]),
+                LendingOp::Borrow => ("borrow", vec![params, units, zero, me.clone(), me]),
+            };
+            encode_call(&abi, function, &tokens)?
+        }
+    };
+    let (gas_used, gas_cost) = send(&book.config, chain, wallets, contract, data, request.priority, name, op)
+        .await
+        .map_err(|error| engine.record_burned_gas(execution_id, request, chain, error))?;
+    Ok((Some(gas_used), Some(chain.to_eth(gas_cost))))
+}
+
+// 估算 gas、檢查預算後以固定熱錢包發送並等待確認；返回消耗的 gas 與以原生代幣計的 gas 費
+#[allow(clippy::too_many_arguments)]
+async fn send(
+    config: &LendingConfig,
+    chain: &ChainStack,
+    wallets: &WalletManager,
+    to: Address,
+    data: Vec<u8>,
+    priority: i32,
+    name: &str,
+    op: LendingOp,
+) -> Result<(u64, f64), OnchainError> {
+    let wallet = wallets.primary();
+    let estimated = wallets
+        .verified_web3()
+        .eth()
+        .estimate_gas(
+            CallRequest {
+                from: Some(wallet.address()),
+                to: Some(to),
+                data: Some(Bytes(data.clone())),
+                ..CallRequest::default()
+            },
+            None,
+        )
+        .await
+        .map_err(|e| format!("{} {} gas 估算失敗: {}", name, op.name(), e))?;
+    let estimated = u64::try_from(estimated).map_err(|_| format!("{} {} gas 估算超出範圍: {}", name, op.name(), estimated))?;
+    let gas_limit = (estimated as f64 * config.gas_buffer) as u64;
+    let quote = chain.gas_optimizer.quote(priority);
+    chain.gas_optimizer.check_budget(&quote, gas_limit).map_err(|e| format!("{} {}{}", name, op.name(), e))?;
+    let hash = wallets
+        .submit(
+            wallet,
+            TransactionParameters {
+                to: Some(to),
+                gas: gas_limit.into(),
+                data: Bytes(data),
+                transaction_type: Some(2.into()),
+                max_fee_per_gas: Some(quote.max_fee_per_gas.into()),
+                max_priority_fee_per_gas: Some(quote.max_priority_fee_per_gas.into()),
+                ..TransactionParameters::default()
+            },
+        )
+        .await?;
+    let tx_hash = format!("{:?}", hash);
+    let receipt = wallets
+        .confirm(hash, config.confirmations, &format!("{} {}", name, op.name()))
+        .await?;
+    if receipt.status != Some(1.into()) {
+        let revert = tx_simulation::inspect_revert(wallets.web3(), &receipt, quote.max_fee_per_gas).await;
+        return Err(OnchainError::reverted(&format!("{} {} ", name, op.name()), revert));
+    }
+    let gas_used = receipt.gas_used.unwrap_or_default().as_u64();
+    let gas_price = receipt.effective_gas_price.map(|price| price.as_u64()).unwrap_or(quote.max_fee_per_gas);
+    info!(market = name, op = op.name(), %tx_hash, gas_used, "借貸操作已確認");
+    Ok((gas_used, gas_used as f64 * gas_price as f64 / 1e18))
+}
+
+// 開倉：在 primary 市場存入抵押品並借出，再存入 secondary 市場，或在 secondary 交易所建立
+// 現貨多頭 + 永續空頭的期現頭寸收取資金費。借入的資金尚未產生收益，開倉收益只計成本，
+// 利差收入在監控平倉時計入已實現盈虧
+pub(crate) async fn execute(engine: &ExecutionEngine, execution_id: &str, request: &ArbitrageRequest) -> Result<ExecutionOutcome, String> {
+    let book = &engine.lending;
+    let config = &book.config;
+    let borrow_name = &request.primary_exchange;
+    let deploy_venue = &request.secondary_exchange;
+    let borrow = book.market(borrow_name)?;
+    match config.markets.get(deploy_venue) {
+        Some(deploy) if deploy.asset != borrow.asset => {
+            return Err(format!("{} 借出 {}，{} 存入 {}，資產不一致", borrow_name, borrow.asset, deploy_venue, deploy.asset));
+        }
+        Some(_) if request.symbol != format!("{}{}", borrow.collateral, borrow.asset) => {
+            return Err(format!("借貸市場之間的利差頭寸交易對應為 {}{}", borrow.collateral, borrow.asset));
+        }
+        None if !STABLECOINS.contains(&borrow.asset.as_str()) => {
+            return Err(format!("資金費期現部署需要借出穩定幣，{} 借出 {}", borrow_name, borrow.asset));
+        }
+        _ => {}
+    }
+    let borrow_apr = book.refresh(engine, borrow_name).await?.borrow_apr;
+
+    let mut carry_quantity = 0.0;
+    let yield_apr = match config.markets.get(deploy_venue) {
+        Some(_) => book.refresh(engine, deploy_venue).await?.supply_apr,
+        None => {
+            let (base, quote) =
+                market_data::split_symbol(&request.symbol).ok_or_else(|| format!("無法解析交易對: {}", request.symbol))?;
+            let price = market_data::simulated_spot_book(engine.env.rng.as_ref(), deploy_venue, &base, &quote)?
+                .mid()
+                .ok_or("訂單簿為空")?;
+            carry_quantity = request.amount / price;
+            let predicted = engine.get_predicted_funding_rate(deploy_venue, &request.symbol).await?;
+            funding_apr(engine, deploy_venue, predicted)
+        }
+    };
+    let spread_apr = yield_apr - borrow_apr;
+    if spread_apr < config.min_spread_apr {
+        return Err(format!("年化利差 {:.4} 低於開倉閾值 {:.4}", spread_apr, config.min_spread_apr));
+    }
+
+    let price = collateral_price(engine, borrow_name, borrow)?;
+    let collateral = request.amount * config.target_health_factor / (borrow.liquidation_threshold * price);
+    let lend = |market: &str, op, amount| Action::Lend {
+        market: market.to_string(),
+        op,
+        amount,
+    };
+    let mut plan = ExecutionPlan::default()
+        .step("post_collateral", lend(borrow_name, LendingOp::SupplyCollateral, collateral), &[])
+        .unwind(lend(borrow_name, LendingOp::WithdrawCollateral, collateral))
+        .step("borrow", lend(borrow_name, LendingOp::Borrow, request.amount), &["post_collateral"])
+        .unwind(lend(borrow_name, LendingOp::Repay, request.amount));
+    plan = if carry_quantity > 0.0 {
+        let spot = Action::order(deploy_venue, &request.symbol, "buy", carry_quantity);
+        let perp = Action::order(deploy_venue, &request.symbol, "sell", carry_quantity);
+        plan.step("carry_spot", spot.clone(), &["borrow"])
+            .unwind(spot.reversed().unwrap_or(spot))
+            .step("carry_perp", perp.clone(), &["borrow"])
+            .unwind(perp.reversed().unwrap_or(perp))
+    } else {
+        plan.step("supply", lend(deploy_venue, LendingOp::Supply, request.amount), &["borrow"])
+            .unwind(lend(deploy_venue, LendingOp::Withdraw, request.amount))
+    };
+    let outcome = execution_plan::run(engine, execution_id, request, &plan, |_| {}).await?;
+    let fees = outcome.fees();
+    let (gas_used, gas_cost_eth) = outcome.gas();
+
+    let now = engine.env.now_ms();
+    let position = LendingPosition {
+        execution_id: execution_id.to_string(),
+        strategy_id: request.strategy_id.clone(),
+        borrow_market: borrow_name.clone(),
+        deploy_venue: deploy_venue.clone(),
+        symbol: request.symbol.clone(),
+        collateral,
+        principal: request.amount,
+        debt: request.amount,
+        carry_quantity: carry_quantity * outcome.fill_ratio(),
+        earned: 0.0,
+        borrow_apr,
+        yield_apr,
+        spread_apr,
+        health_factor: config.target_health_factor,
+        opened_at_ms: now,
+        updated_at_ms: now,
+        request: request.clone(),
+    };
+    book.state.lock().unwrap().positions.insert(execution_id.to_string(), position);
+    let expected_carry = spread_apr * request.amount * config.holding_days / 365.0;
+    info!(borrow = %borrow_name, deploy = %deploy_venue, spread_apr, collateral, expected_carry, "借貸利差頭寸已開倉");
+    let expected_slippage_bps = engine.sizing.expected_slippage_bps(request.amount);
+    Ok(ExecutionOutcome {
+        profit: -fees,
+        fees,
+        orders: outcome.orders(),
+        expected_slippage_bps,
+        realized_slippage_bps: expected_slippage_bps,
+        gas_used,
+        gas_cost_eth,
+        settlement_proof: None,
+        unwound: Vec::new(),
+    })
+}
+
+/// 刷新各市場利率與機會，按最新利率計提頭寸的利息與收益；健康因子跌破下限或利差收斂時平倉。
+pub(crate) async fn monitor(engine: &ExecutionEngine) {
+    let book = &engine.lending;
+    let config = &book.config;
+    for name in config.markets.keys() {
+        if let Err(e) = book.refresh(engine, name).await {
+            warn!(market = %name, error = %e, "借貸市場利率刷新失敗");
+        }
+    }
+    book.rank(engine);
+
+    let now = engine.env.now_ms();
+    let positions: Vec<LendingPosition> = book.state.lock().unwrap().positions.values().cloned().collect();
+    for mut position in positions {
+        let borrow = &config.markets[&position.borrow_market];
+        let rates = book.state.lock().unwrap().rates.clone();
+        if let Some(rates) = rates.get(&position.borrow_market) {
+            position.borrow_apr = rates.borrow_apr;
+        }
+        match rates.get(&position.deploy_venue) {
+            Some(rates) => position.yield_apr = rates.supply_apr,
+            None => match engine.get_predicted_funding_rate(&position.deploy_venue, &position.symbol).await {
+                Ok(rate) => position.yield_apr = funding_apr(engine, &position.deploy_venue, rate),
+                Err(e) => warn!(execution_id = %position.execution_id, error = %e, "資金費率不可用，沿用上次的收益率"),
+            },
+        }
+        // 上次計提以來按上次的利率計息
+        let years = (now - position.updated_at_ms).max(0) as f64 / MS_PER_YEAR;
+        position.debt *= 1.0 + position.borrow_apr * years;
+        position.earned += position.principal * position.yield_apr * years;
+        position.updated_at_ms = now;
+        position.spread_apr = position.yield_apr - position.borrow_apr;
+        match collateral_price(engine, &position.borrow_market, borrow) {
+            Ok(price) => position.health_factor = position.collateral * price * borrow.liquidation_threshold / position.debt,
+            Err(e) => warn!(execution_id = %position.execution_id, error = %e, "無法計算健康因子"),
+        }
+        book.state.lock().unwrap().positions.insert(position.execution_id.clone(), position.clone());
+
+        let reason = if position.health_factor < config.min_health_factor {
+            let message = format!(
+                "{} 在 {} 的健康因子 {:.3} 低於 {:.3}，自動平倉",
+                position.execution_id, position.borrow_market, position.health_factor, config.min_health_factor
+            );
+            engine.alert(Severity::Critical, "借貸頭寸健康因子過低", message);
+            "health_factor"
+        } else if position.spread_apr < config.exit_spread_apr {
+            "spread_converged"
+        } else {
+            continue;
+        };
+        if let Err(e) = close(engine, &position, reason).await {
+            let message = format!("{} 平倉失敗（{}）: {}", position.execution_id, reason, e);
+            engine.alert(Severity::Critical, "借貸頭寸平倉失敗", message);
+        }
+    }
+}
+
+// 平倉：收回部署的資金，還清債務後取回抵押品，利差收入減利息與成本計入已實現盈虧
+async fn close(engine: &ExecutionEngine, position: &LendingPosition, reason: &str) -> Result<(), String> {
+    let book = &engine.lending;
+    let borrow = &position.borrow_market;
+    let lend = |market: &str, op, amount| Action::Lend {
+        market: market.to_string(),
+        op,
+        amount,
+    };
+    let plan = if position.carry_quantity > 0.0 {
+        ExecutionPlan::default()
+            .step("close_spot", Action::order(&position.deploy_venue, &position.symbol, "sell", position.carry_quantity), &[])
+            .step("close_perp", Action::order(&position.deploy_venue, &position.symbol, "buy", position.carry_quantity), &[])
+            .step("repay", lend(borrow, LendingOp::Repay, position.debt), &["close_spot", "close_perp"])
+    } else {
+        ExecutionPlan::default()
+            .step("withdraw", lend(&position.deploy_venue, LendingOp::Withdraw, position.principal + position.earned), &[])
+            .step("repay", lend(borrow, LendingOp::Repay, position.debt), &["withdraw"])
+    };
+    let plan = plan.step("withdraw_collateral", lend(borrow, LendingOp::WithdrawCollateral, position.collateral), &["repay"]);
+    let execution_id = format!("{}-close", position.execution_id);
+    let outcome = execution_plan::run(engine, &execution_id, &position.request, &plan, |_| {}).await?;
+    let (_, gas_cost_eth) = outcome.gas();
+    if let Some(cost_eth) = gas_cost_eth {
+        engine.gas_budget.record(position.request.strategy_type.name(), cost_eth);
+    }
+    book.state.lock().unwrap().positions.remove(&position.execution_id);
+    let pnl = position.earned - (position.debt - position.principal) - outcome.fees();
+    engine.record_realized_pnl(&position.strategy_id, pnl);
+    info!(execution_id = %position.execution_id, reason, pnl, health_factor = position.health_factor, "借貸利差頭寸已平倉");
+    Ok(())
+}
+
+pub fn spawn(engine: Arc<ExecutionEngine>) {
+    let config = &engine.lending.config;
+    if !config.enabled || config.markets.is_empty() {
+        info!("借貸利差監控未啟用");
+        return;
+    }
+    let interval_secs = config.monitor_interval_secs;
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(interval_secs));
+        loop {
+            interval.tick().await;
+            monitor(&engine).await;
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bench::START_MS;
+    use crate::deterministic_sim::{build, request};
+    use crate::{config, validation, Environment, StrategyType};
+
+    #[tokio::test]
+    async fn lending_positions_open_on_cheaper_market_and_unwind_on_low_health_factor() {
+        let mut config = config::EngineConfig::default();
+        for exchange in ["binance", "bybit", "okx"] {
+            config.exchanges.entry(exchange.to_string()).or_default().simulation = config::SimulatedExchangeConfig {
+                reject_rate: 0.0,
+                partial_fill_rate: 0.0,
+                ..Default::default()
+            };
+        }
+        let market = |protocol, market_id: &str, utilization| config::LendingMarketConfig {
+            protocol,
+            chain: None,
+            asset: "USDC".to_string(),
+            collateral: "ETH".to_string(),
+            address: String::new(),
+            market_id: market_id.to_string(),
+            asset_token: None,
+            collateral_token: None,
+            liquidation_threshold: 0.83,
+            simulation: config::SimulatedRateModel {
+                utilization,
+                ..Default::default()
+            },
+        };
+        config.lending.markets.insert("compound_usdc".to_string(), market(config::LendingProtocol::CompoundV3, "", 0.5));
+        config.lending.markets.insert("morpho_usdc".to_string(), market(config::LendingProtocol::MorphoBlue, "0x01", 0.95));
+        assert!(config.validate().is_ok());
+        let (engine, path) = build("lending-1", config, Environment::simulated(START_MS, 1));
+
+        // 在利用率低的 Compound 借款，存入利用率越過拐點的 Morpho
+        let pair = ArbitrageRequest {
+            strategy_type: StrategyType::LendingRate,
+            symbol: "ETHUSDC".to_string(),
+            primary_exchange: "compound_usdc".to_string(),
+            secondary_exchange: "morpho_usdc".to_string(),
+            ..request(10_000.0)
+        };
+        assert!(validation::validate(&engine, &pair).is_empty());
+        let outcome = execute(&engine, "lend-1", &pair).await.unwrap();
+        let legs: Vec<&str> = outcome.orders.iter().map(|order| order.leg.as_str()).collect();
+        assert_eq!(legs, ["post_collateral", "borrow", "supply"]);
+        assert_eq!(outcome.orders[0].symbol, "ETH");
+
+        // 借出的穩定幣投入 OKX 的期現頭寸收取資金費
+        let carry = ArbitrageRequest {
+            strategy_type: StrategyType::LendingRate,
+            primary_exchange: "compound_usdc".to_string(),
+            secondary_exchange: "okx".to_string(),
+            ..request(10_000.0)
+        };
+        let outcome = execute(&engine, "lend-2", &carry).await.unwrap();
+        let legs: Vec<&str> = outcome.orders.iter().map(|order| order.leg.as_str()).collect();
+        assert_eq!(legs, ["post_collateral", "borrow", "carry_spot", "carry_perp"]);
+        let mismatched = ArbitrageRequest {
+            symbol: "BTCUSDC".to_string(),
+            ..pair.clone()
+        };
+        assert!(execute(&engine, "lend-3", &mismatched).await.is_err());
+
+        monitor(&engine).await;
+        let snapshot = engine.lending.snapshot();
+        assert_eq!(snapshot.positions.len(), 2);
+        assert!(snapshot.positions.iter().all(|position| position.health_factor > 1.7));
+        let best = &snapshot.opportunities[0];
+        assert_eq!((best.borrow_market.as_str(), best.deploy_venue.as_str()), ("compound_usdc", "morpho_usdc"));
+
+        // 長期計息後債務增長，健康因子跌破下限，兩個頭寸都自動平倉
+        engine.env.clock.sleep(Duration::from_secs(20 * 365 * 86_400)).await;
+        monitor(&engine).await;
+        assert!(engine.lending.snapshot().positions.is_empty());
+        let _ = std::fs::remove_file(path);
+    }
+}
@@ -0,0 +1,243 @@
+//! 資金費率套利執行引擎。
+//!
+//! 二進制 `funding_rate_arbitrage_engine` 只是 [`server::serve`] 的薄封裝；嵌入其他程序時直接建立
+//! [`ExecutionEngine`] 並調用 [`ExecutionEngine::execute`]：
+//!
+//! ```no_run
+//! use arbitrage_engine::{config::EngineConfig, ArbitrageRequest, ExecutionEngine};
+//! use std::time::Duration;
+//!
+//! # async fn example() -> Result<(), String> {
+//! let engine = ExecutionEngine::start(EngineConfig::load()?, None).await?;
+//! let request: ArbitrageRequest = serde_json::from_value(serde_json::json!({
+//!     "strategy_id": "fr-1",
+//!     "symbol": "BTCUSDT",
+//!     "primary_exchange": "binance",
+//!     "secondary_exchange": "bybit",
+//!     "amount": 1000.0,
+//!     "priority": 5,
+//!     "timestamp": "2024-01-01T00:00:00Z"
+//! }))
+//! .map_err(|e| e.to_string())?;
+//! let response = engine.execute(request).await;
+//! println!("{} {:?}", response.status, response.profit);
+//! engine.shutdown(Duration::from_secs(5)).await;
+//! # Ok(())
+//! # }
+//! ```
+
+// 協議類型：套利請求與響應、控制指令，以及版本兼容層與線路編碼
+pub mod protocol;
+// 執行引擎：持有各子系統狀態，負責單次執行流程與控制指令
+pub mod engine;
+// 交易所連接器（模擬）：行情、賬戶與簽名請求
+pub mod exchanges;
+// 交易所 REST/WebSocket 接口：按 Binance、Bybit、OKX 各自的路徑、簽名與響應格式收發請求，
+// 配置了 endpoint 的連接器經此訪問交易所（或模擬交易所）
+mod exchange_api;
+// 交易所 REST 請求的重試：瞬時錯誤（5xx、超時、連接失敗）按操作類別的策略指數退避並加隨機抖動，
+// 重試下單前按 client_order_id 查詢，避免重複提交
+mod retry;
+// 確定性的 client_order_id：由策略、執行與腿生成，重試與交易所側去重共用同一 ID，用戶數據流的成交據此歸屬到執行
+mod order_ids;
+// 交易對命名：請求中的交易對統一為內部格式（BTCUSDT），連接器按各交易所的原生代碼收發（如 OKX 的 BTC-USDT-SWAP），
+// 個別交易對可按交易所配置覆蓋
+mod symbols;
+// 交易所憑證來源：環境變量、Vault 或 AWS Secrets Manager，運行時讀取並按輪換刷新，不寫入磁盤
+mod secrets;
+// 運維告警：執行失敗後的反向平倉、當日限額暫停、緊急停止、交易所斷線等事件按嚴重級別推送到
+// Telegram、Discord、Slack 或通用 webhook
+mod alerts;
+// 事件總線：按日誌序號把請求、成交、結算與已實現盈虧發布到 Kafka（REST Proxy）、NATS 或 Redis Streams，
+// 確認後推進 cursor，代理不可用時從斷點重發
+mod event_bus;
+// 模擬交易所：本地端口上按 Binance、Bybit、OKX 的路徑與響應格式提供資金費率、下單、listen key 與私有 WebSocket，
+// 場景可腳本化（拒單、429 限頻、斷線），供端到端測試驅動連接器；也可經 `mock-exchange` 子命令單獨運行
+pub mod mock_exchange;
+// 注入引擎的時鐘與隨機數：生產使用系統時間，集成測試以模擬時鐘與固定種子重現延遲、拒單與部分成交
+pub mod environment;
+// TCP 服務：監聽（及可選的同機 Unix 域套接字）、TLS 握手、連接上的消息處理與優雅退出
+pub mod server;
+// 執行記帳：常規請求同步寫入，快速通道請求交由後台隊列
+mod bookkeeping;
+// 交易與執行歷史存儲（sqlx，支持 SQLite 與 Postgres）
+mod storage;
+// 資金費率歷史採集與價差統計
+mod funding_history;
+// 引擎配置：從 ARB_ENGINE_CONFIG 指定的 JSON 文件加載，缺省字段使用默認值
+pub mod config;
+// 配置熱加載：監視配置文件或經 reload_config 指令重新讀取，校驗後替換風控限額、閾值、手續費與策略開關
+mod config_reload;
+// 交易所限頻：每個連接器一個請求調度器，請求權重與下單數各用一個令牌桶；
+// 權重按響應頭回報的已用量校準，下單可動用全部餘量，行情請求只能用保留份額以外的部分，超時未獲配額即丟棄
+mod rate_limit;
+// 用戶數據流：接收各交易所私有 WebSocket 推送的訂單、持倉與餘額更新，維護交易所側的訂單表與賬戶狀態，
+// 成交寫入事件日誌供 PnL 投影使用；連接建立或消息積壓丟失後以 REST 快照對賬
+mod user_stream;
+// 下單前保證金檢查：以用戶數據流維護的交易所餘額為緩存，扣除在途執行已預留的保證金，
+// 可用保證金不足時按比例縮減下單金額，縮減後仍低於最小比例則拒絕
+mod balance;
+// 強平距離監控：定時拉取各交易所永續持倉的標記價與強平價，距離低於告警閾值時記錄告警，
+// 低於降風險閾值時為逐倉追加保證金，或按比例同時縮減該腿與另一交易所的對沖腿
+mod liquidation;
+// 資金費結算跟蹤：登記資金費率套利開出的對沖持倉對，定時從交易所拉取各結算時點實際收付的資金費，
+// 按名義金額分攤到持倉對並寫入事件日誌；預測的下一期費率差連續數次對我們不利時平掉整對持倉
+mod funding_settlement;
+// 預測資金費率：按溢價指數模型（結算週期內溢價指數的加權平均加上截斷後的利率分量）
+// 或交易所自身公佈的預測費率計算下一期費率，入場判斷與平倉判斷都以此為準
+mod funding_model;
+// HTTP 管理接口：與 TCP 協議共用命令處理，便於 curl 與運維工具調試；
+// 除 /health、/healthz、/readyz 外均需 Authorization: Bearer <token>，token 從 token_env 指定的環境變量讀取
+mod admin_api;
+// 存活與就緒檢查：客戶端監聽、交易所連接、行情新鮮度與風控狀態，供編排系統與負載均衡判斷是否導入流量
+mod health;
+// 客戶端監聽 TLS：證書/私鑰從 PEM 文件加載，配置客戶端 CA 後校驗客戶端證書；
+// 區域實例間轉發也使用本實例證書做雙向 TLS，並以同一 CA 校驗對端
+mod tls;
+// 客戶端認證：連接建立後先以 authenticate 握手，簽名為 HMAC-SHA256(密鑰, "client|timestamp_ms|nonce")，
+// 密鑰不在線路上傳輸；nonce 在時鐘偏差窗口內不可重用。握手後每條消息按所需權限範圍校驗
+mod client_auth;
+// 請求校驗：排隊前按字段約束檢查金額、交易對、交易所與各可選參數，返回逐字段的違規項
+mod validation;
+// 執行隊列：待執行請求按優先級、預期收益、到達順序排序；每個交易所限制並發執行數，
+// 隊列已滿時擠出排序最低的請求（新請求排序更低則直接拒絕），等待超時的請求以錯誤返回；
+// 可選搶佔：額度佔滿時讓尚未下單的低優先級執行中止或重新排隊
+mod execution_queue;
+// 執行計劃：按依賴組成有向無環圖的步驟（交易所單、劃轉、閃電貸、DEX 兌換、借貸操作）及各自的撤銷動作，
+// 執行器逐層並發推進，失敗時按完成的逆序撤銷
+mod execution_plan;
+// 自定義策略：實現 Strategy trait 並經 ExecutionEngine::register_strategy 註冊，掃描器每輪調用其機會評估，
+// strategy_type 為 plugin 的請求按其計劃經同一執行管線（排隊、風控、事件與記帳）執行
+pub mod strategy;
+// 延遲統計：單次執行逐階段微秒計時並附在響應中，各階段耗時匯總為 HDR 直方圖，
+// 通過 get_latency 指令與管理接口 /metrics（Prometheus 文本格式）導出
+mod latency;
+// 時鐘同步：每個交易所一個常駐任務，定期以服務器時間接口多次採樣，取往返最短的一次估計交易所時鐘相對本機的偏差；
+// 簽名請求的時間戳與資金費結算時間計算使用校正後的交易所時間。簽名請求因 recvWindow 被拒時立即重新同步
+mod time_sync;
+// 交易所會話監督：每個交易所一個常駐任務維持行情/用戶數據連接，斷線後按指數退避加抖動重連，
+// 重連成功後重放訂閱；有 listen key 的交易所（Binance 用戶數據流）定期續期。連接未就緒的交易所暫停執行
+mod session;
+// 按交易對根據近期成交滑點自動校準最大名義金額
+mod sizing;
+// 事件溯源：引擎狀態變化以追加日誌記錄，持倉/PnL 投影可隨時由日誌重建
+mod events;
+// 執行預寫日誌：下單前記錄執行的狀態轉換並落盤，重啟時按 client_order_id 向交易所查詢崩潰前未確認的訂單，
+// 兩側成交一致時恢復對沖持倉，否則反向平掉已成交的腿
+mod journal;
+// 狀態對賬：定時以交易所 REST 快照核對本地持倉、餘額與未完結訂單，報告經管理接口查看，可選按交易所自動修正
+mod reconciliation;
+// 定時執行：按指定時間或資金費結算前的秒數登記請求，到點後跳過執行隊列直接執行
+mod scheduler;
+// 庫存再平衡：按目標佔比監控各交易所與子賬戶的資產餘額，經內部劃轉或提幣到白名單地址恢復分佈
+mod rebalance;
+// 跨交易所劃轉成本模型：按資產與網絡的提幣費、最小提幣量與典型耗時估算劃轉成本，供掃描器與訂單路由評估跨交易所機會
+mod transfer_cost;
+// 價格合理性檢查：以各交易所的獨立報價交叉驗證交易對價格與穩定幣錨定，偏離超過閾值時拒絕執行
+mod price_guard;
+// 預言機喂價：讀取 Chainlink 聚合器與 Pyth Hermes 的美元價格，作為交易所報價的獨立參照與鏈上抵押品估值來源
+mod oracle;
+// 掛單優先執行：資金費率腿先以 post-only 限價單在價差內掛單，有效期內未成交部分改為吃單，並統計 maker 成交比例
+mod maker;
+// 行情新鮮度：記錄各交易所/交易對費率的接收時間，執行時緩存過期則強制經 REST 刷新或拒絕執行
+mod quotes;
+// 下單確認延遲模型：按交易所統計吃單確認延遲，估算機會在預計成交時剩餘的收益，供掃描器過濾邊際機會
+mod ack_latency;
+// 多端點接入：按探測延遲為每個交易所選用最快的健康 REST 端點，連續失敗時自動切換
+mod gateways;
+// REST 連接池：每個交易所共用保活連接，啟動時與空閒期間定期預熱，避免空閒後首筆訂單重新握手
+mod connection_pool;
+// 運行時隔離：執行與下單在綁定 CPU 核的單線程運行時上運行，行情與管理等後台任務在另一個運行時上運行
+mod runtime;
+// 套利機會掃描器：比較緩存的資金費率，按淨收益排序後推送或自動執行
+mod scanner;
+// 行情數據：現貨訂單簿（模擬）與逐檔成交計算
+pub mod market_data;
+// 三角套利：同一交易所的三個現貨交易對構成閉環，按深度計算淨收益並執行
+mod triangular;
+// 競爭流量偵測：根據價差消失速度與訂單被搶先的頻率估算交易對擁擠度
+mod crowding;
+// 期現（cash-and-carry）套利：買入現貨、做空永續收取資金費，基差收斂後平倉
+mod basis;
+// 借貸利差套利：在 Compound v3 / Morpho Blue 較便宜的市場抵押借款，存入收益更高的市場或投入資金費期現頭寸；
+// 後台監控健康因子與利差，利率收斂或健康因子過低時自動平倉
+mod lending;
+// 智能訂單路由：數量超過單一交易所最優檔深度時，按含手續費的邊際價格把數量分配到多個交易所與價位，
+// 生成多筆子訂單並行執行
+mod order_router;
+// 大額資金費率頭寸的執行算法：TWAP 在給定時長內等分下單，冰山單每次只顯示固定名義金額；
+// 每片下單前重新檢查費率差，滑點超限、費率差消失或連續失敗時中止，已成交部分保留
+mod execution_algo;
+// 回測：按時間回放錄製的資金費率、訂單簿與成交，經掃描器排序入場、校準器定額、
+// 訂單簿逐檔成交與資金費平倉規則模擬持倉對，輸出 PnL 曲線、勝率與滑點統計
+pub mod backtest;
+// 跨交易所現貨價差套利：考慮提幣費、轉賬耗時與兩邊可用庫存
+mod spot_arbitrage;
+// 多鏈執行棧：每條鏈各自的熱錢包（nonce 按鏈獨立）、閃電貸、DEX 與 gas 預言機
+mod chain;
+// DEX 現貨腿：經 Quoter / StableSwap 報價，構造帶最少成交量與截止時間的精確輸入兌換並上鏈
+mod dex;
+// 代幣授權：緩存各熱錢包對 DEX 路由（或經 Permit2）的授權額度，兌換時不等待授權，由後台一次性授權或補足
+mod approvals;
+// 待確認交易監控：識別發往已啟用 DEX 的大額兌換，DEX 腿下單前據此判斷報價是否已過時
+mod mempool;
+// EIP-1559 gas 預言機：輪詢 eth_feeHistory，按請求優先級映射的緊急程度報價，並限制單次執行與每日的 gas 花費
+mod gas;
+// 對沖請求：主請求超過延遲仍未返回時發出備用請求，取第一個成功的結果
+mod hedging;
+// 閃電貸：Aave / Balancer / dYdX 來源實現同一接口，按手續費與流動性選擇來源，
+// 簽名發送後從回執解析實際收益並生成結算證明
+mod flash_loan;
+// 現貨槓桿：永續合約缺失時借幣賣出構建空頭腿，跟踪各交易所可借額度
+mod margin;
+// 交易前風險預覽：把擬下單的各條腿疊加到事件投影的持倉上，比較前後的敞口、集中度、借幣使用率與 VaR
+pub mod risk;
+// 多區域路由：按交易所把腿轉交離它最近的引擎實例，轉發指令以共享密鑰做 HMAC-SHA256 簽名，
+// 並以簽發時間與 leg_id 拒絕過期或重放的指令
+mod routing;
+// 私有 bundle 提交：對接下來若干區塊向 Flashbots 中繼提交已簽名交易，請求以身份私鑰簽名（X-Flashbots-Signature）；
+// 目標區塊內均未被打包時以同一筆簽名交易公開發送，nonce 不變
+mod bundle_submitter;
+// 多節點 RPC：按健康評分（成功率與延遲）選用節點，不可達、超時或限流時切換並暫停連續失敗的節點；
+// 簽名前的餘額、gas 估算與 nonce 讀取可要求多個節點結果一致
+mod rpc;
+// 上鏈前交易模擬：經 eth_call、debug_traceCall、本地分叉節點或 Tenderly 按當前狀態模擬，回滾時不簽名發送
+mod tx_simulation;
+// 新區塊與結算事件訂閱：經 WebSocket 訂閱 newHeads 與轉入熱錢包的轉賬日誌，新區塊到達時確認回執；
+// 已確認交易按區塊哈希核對重組，被重組出鏈時重新核驗、告警並重新同步 nonce
+mod block_watcher;
+// 熱錢包與 nonce 管理：私鑰來自加密 keystore 或環境變量，nonce 按地址在本地遞增，
+// 同一地址的簽名與發送串行執行以避免 nonce 衝突
+mod wallet;
+// 共享內存傳輸：同機策略進程經內存映射文件上的請求/響應環形緩衝收發固定佈局的記錄，提交路徑無系統調用
+pub mod shared_memory;
+// 回環壓測：`funding_rate_arbitrage_engine load-test [參數 JSON 文件]` 以固定速率經多個連接驅動 TCP 或 Unix 域套接字監聽，
+// 報告吞吐與從計劃發送時間算起的延遲分佈
+pub mod load_test;
+// 基準支持（benches/）：不啟動後台任務的模擬引擎、經連接處理路徑的消息分派與機會評估輸入
+#[doc(hidden)]
+pub mod bench;
+// 運維 REPL：`funding_rate_arbitrage_engine repl [地址]` 連接運行中的引擎，命令支持 Tab 補全
+pub mod repl;
+// Python 綁定（python feature）：以 PyO3 導出 ExecutionEngine，策略層可在進程內同步或以 asyncio 調用
+#[cfg(feature = "python")]
+mod python;
+// 協議兼容性測試：tests/golden/protocol 下每個版本目錄保存該版本客戶端的請求與響應樣本，
+// 確保滾動升級時舊客戶端的消息仍可解析、舊客戶端讀取的字段不被刪除或改變類型
+#[cfg(test)]
+mod protocol_compat;
+// 確定性模擬測試：以模擬時鐘與固定種子啟動引擎，驗證同一種子結果可重現，以及拒單與部分成交的處理
+#[cfg(test)]
+mod deterministic_sim;
+// 模擬交易所端到端測試：連接器經 REST/WebSocket 訪問模擬交易所，覆蓋三家交易所的接口格式、拒單、部分成交、限頻與斷線
+#[cfg(test)]
+mod mock_exchange_e2e;
+
+pub use engine::{ExecutionEngine, LogHandle};
+pub use environment::Environment;
+pub use protocol::{
+    ArbitrageRequest, ArbitrageResponse, BatchItem, BatchRequest, BatchResponse, ChildOrder, ClientMessage, CommandResponse, EngineCommand, ExecutionProgress, FieldViolation,
+    LeverageSetting, MarginMode, MarketContext, StrategyType,
+};
+pub(crate) use engine::{ExecutionFailure, ExecutionOutcome};
+pub(crate) use exchanges::ExchangeConnector;
@@ -0,0 +1,387 @@
+use super::config::{DeriskAction, LiquidationConfig, Severity};
+use super::{events, ChildOrder, MarginMode, ExecutionEngine};
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Duration;
+use tracing::{debug, info, warn};
+
+// 保留的最近告警數
+const ALERT_HISTORY: usize = 200;
+
+// 交易所返回的永續持倉風險；quantity 為按開倉價計的名義金額，正數為多頭
+#[derive(Debug, Clone, Serialize)]
+pub struct PositionRisk {
+    pub symbol: String,
+    pub quantity: f64,
+    pub entry_price: f64,
+    pub mark_price: f64,
+    pub leverage: f64,
+    pub margin_mode: MarginMode,
+    // 該持倉佔用的保證金（全倉時不含賬戶其餘可用餘額）
+    pub margin: f64,
+    pub maintenance_margin_rate: f64,
+    pub liquidation_price: f64,
+}
+
+impl PositionRisk {
+    // 基礎資產數量
+    pub fn size(&self) -> f64 {
+        self.quantity.abs() / self.entry_price
+    }
+
+    // 標記價到強平價的相對距離
+    pub fn distance(&self) -> f64 {
+        (self.mark_price - self.liquidation_price).abs() / self.mark_price
+    }
+
+    // 權益等於維持保證金時的價格：多頭 margin + size·(P − entry) = mmr·size·P，空頭對稱
+    pub fn liquidation_price(quantity: f64, entry_price: f64, margin: f64, maintenance_margin_rate: f64) -> f64 {
+        let size = quantity.abs() / entry_price;
+        if quantity > 0.0 {
+            ((entry_price * size - margin) / (size * (1.0 - maintenance_margin_rate))).max(0.0)
+        } else {
+            (entry_price * size + margin) / (size * (1.0 + maintenance_margin_rate))
+        }
+    }
+
+    // 把強平價推到距標記價 distance 之外所需的持倉保證金
+    pub fn margin_for_distance(&self, distance: f64) -> f64 {
+        let size = self.size();
+        if self.quantity > 0.0 {
+            size * (self.entry_price - self.mark_price * (1.0 - distance) * (1.0 - self.maintenance_margin_rate))
+        } else {
+            size * (self.mark_price * (1.0 + distance) * (1.0 + self.maintenance_margin_rate) - self.entry_price)
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RiskLevel {
+    Safe,
+    Alert,
+    Critical,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LegRisk {
+    pub exchange: String,
+    #[serde(flatten)]
+    pub position: PositionRisk,
+    pub distance: f64,
+    pub level: RiskLevel,
+    pub checked_at_ms: i64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Alert {
+    pub at_ms: i64,
+    pub exchange: String,
+    pub symbol: String,
+    pub distance: f64,
+    pub level: RiskLevel,
+    // 已採取的處理：alert / add_margin / reduce，處理失敗時附錯誤
+    pub action: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MonitorSnapshot {
+    pub legs: Vec<LegRisk>,
+    pub alerts: Vec<Alert>,
+}
+
+pub struct LiquidationMonitor {
+    // 可熱加載；enabled 與 interval_secs 只在啟動時讀取
+    config: RwLock<LiquidationConfig>,
+    legs: Mutex<BTreeMap<String, LegRisk>>,
+    alerts: Mutex<VecDeque<Alert>>,
+}
+
+impl LiquidationMonitor {
+    pub fn new(config: LiquidationConfig) -> Self {
+        Self {
+            config: RwLock::new(config),
+            legs: Mutex::new(BTreeMap::new()),
+            alerts: Mutex::new(VecDeque::new()),
+        }
+    }
+
+    pub fn config(&self) -> LiquidationConfig {
+        self.config.read().unwrap().clone()
+    }
+
+    pub fn reconfigure(&self, config: LiquidationConfig) {
+        *self.config.write().unwrap() = config;
+    }
+
+    pub fn snapshot(&self) -> MonitorSnapshot {
+        MonitorSnapshot {
+            legs: self.legs.lock().unwrap().values().cloned().collect(),
+            alerts: self.alerts.lock().unwrap().iter().cloned().collect(),
+        }
+    }
+
+    fn level(&self, distance: f64) -> RiskLevel {
+        let config = self.config.read().unwrap();
+        if distance < config.derisk_distance {
+            RiskLevel::Critical
+        } else if distance < config.alert_distance {
+            RiskLevel::Alert
+        } else {
+            RiskLevel::Safe
+        }
+    }
+
+    fn alert(&self, leg: &LegRisk, action: String) {
+        warn!(
+            exchange = %leg.exchange,
+            symbol = %leg.position.symbol,
+            distance = leg.distance,
+            liquidation_price = leg.position.liquidation_price,
+            mark_price = leg.position.mark_price,
+            %action,
+            "持倉接近強平價"
+        );
+        let mut alerts = self.alerts.lock().unwrap();
+        if alerts.len() == ALERT_HISTORY {
+            alerts.pop_front();
+        }
+        alerts.push_back(Alert {
+            at_ms: leg.checked_at_ms,
+            exchange: leg.exchange.clone(),
+            symbol: leg.position.symbol.clone(),
+            distance: leg.distance,
+            level: leg.level,
+            action,
+        });
+    }
+}
+
+pub fn spawn(engine: Arc<ExecutionEngine>) {
+    let config = engine.liquidation.config();
+    if !config.enabled {
+        info!("強平距離監控未啟用");
+        return;
+    }
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(config.interval_secs));
+        loop {
+            interval.tick().await;
+            check(&engine).await;
+        }
+    });
+}
+
+async fn check(engine: &ExecutionEngine) {
+    let monitor = &engine.liquidation;
+    let checked_at_ms = engine.env.now_ms();
+    let mut legs = BTreeMap::new();
+    for (exchange, connector) in &engine.exchanges {
+        if engine.routing.is_remote(exchange) || !engine.sessions.is_available(exchange) {
+            continue;
+        }
+        match connector.fetch_position_risk().await {
+            Ok(positions) => {
+                for position in positions {
+                    let distance = position.distance();
+                    legs.insert(
+                        format!("{}:{}", exchange, position.symbol),
+                        LegRisk {
+                            exchange: exchange.clone(),
+                            level: monitor.level(distance),
+                            position,
+                            distance,
+                            checked_at_ms,
+                        },
+                    );
+                }
+            }
+            // 拉取失敗時保留上一輪結果
+            Err(error) => {
+                warn!(%exchange, %error, "拉取持倉風險失敗");
+                let previous = monitor.legs.lock().unwrap();
+                legs.extend(
+                    previous
+                        .iter()
+                        .filter(|(_, leg)| leg.exchange == *exchange)
+                        .map(|(key, leg)| (key.clone(), leg.clone())),
+                );
+            }
+        }
+    }
+    debug!(legs = legs.len(), "強平距離檢查完成");
+    *monitor.legs.lock().unwrap() = legs.clone();
+
+    // 作為對沖腿已被縮減的持倉本輪不再單獨處理
+    let mut handled = BTreeSet::new();
+    for (key, leg) in legs.iter().filter(|(_, leg)| leg.checked_at_ms == checked_at_ms) {
+        match leg.level {
+            RiskLevel::Safe => {}
+            RiskLevel::Alert => monitor.alert(leg, "alert".to_string()),
+            RiskLevel::Critical if handled.contains(key) => monitor.alert(leg, "hedge_reduced".to_string()),
+            RiskLevel::Critical => {
+                let action = match derisk(engine, leg, &legs, &mut handled).await {
+                    Ok(action) => action,
+                    Err(error) => format!("failed: {}", error),
+                };
+                let message = format!("{} {} 距強平 {:.2}%：{}", leg.exchange, leg.position.symbol, leg.distance * 100.0, action);
+                engine.alert(Severity::Critical, "持倉接近強平價，已降風險", message);
+                monitor.alert(leg, action);
+            }
+        }
+    }
+}
+
+async fn derisk(
+    engine: &ExecutionEngine,
+    leg: &LegRisk,
+    legs: &BTreeMap<String, LegRisk>,
+    handled: &mut BTreeSet<String>,
+) -> Result<String, String> {
+    let config = engine.liquidation.config();
+    let position = &leg.position;
+    let connector = &engine.exchanges[&leg.exchange];
+    match config.action {
+        DeriskAction::AlertOnly => return Ok("alert".to_string()),
+        // 全倉持倉的保證金就是賬戶餘額，無法單獨追加，改為縮減
+        DeriskAction::AddMargin if position.margin_mode == MarginMode::Isolated => {
+            let amount = position.margin_for_distance(config.target_distance) - position.margin;
+            match connector.add_isolated_margin(&position.symbol, amount).await {
+                Ok(()) => return Ok(format!("add_margin {:.2}", amount)),
+                Err(error) => warn!(exchange = %leg.exchange, %error, "追加保證金失敗，改為縮減持倉"),
+            }
+        }
+        DeriskAction::AddMargin | DeriskAction::Reduce => {}
+    }
+
+    // 同一交易對在其他交易所的反向持倉作為對沖腿一併縮減，保持兩邊名義金額相等；
+    // 縮減後剩餘不足 min_position_notional 時直接平倉
+    let mut quantity = position.quantity.abs() * config.reduce_fraction;
+    if position.quantity.abs() - quantity < config.min_position_notional {
+        quantity = position.quantity.abs();
+    }
+    let hedge = legs
+        .values()
+        .filter(|other| other.exchange != leg.exchange && other.position.symbol == position.symbol)
+        .filter(|other| other.position.quantity * position.quantity < 0.0)
+        .max_by(|a, b| a.position.quantity.abs().total_cmp(&b.position.quantity.abs()));
+    let mut reductions = vec![(leg, quantity)];
+    if let Some(hedge) = hedge {
+        reductions.push((hedge, quantity.min(hedge.position.quantity.abs())));
+    }
+    let exchanges: Vec<&str> = reductions.iter().map(|(leg, _)| leg.exchange.as_str()).collect();
+    engine.acquire_orders(&exchanges).await?;
+    handled.extend(reductions.iter().map(|(leg, _)| format!("{}:{}", leg.exchange, leg.position.symbol)));
+
+    let execution_id = format!("derisk-{}", engine.env.uuid());
+    for (index, (leg, quantity)) in reductions.iter().enumerate() {
+        let order = ChildOrder {
+            leg: if index == 0 { "derisk" } else { "derisk_hedge" }.to_string(),
+            exchange: leg.exchange.clone(),
+            symbol: leg.position.symbol.clone(),
+            side: if leg.position.quantity > 0.0 { "sell" } else { "buy" }.to_string(),
+            quantity: *quantity,
+            filled_quantity: *quantity,
+            fee: quantity * engine.taker_fee(&leg.exchange),
+            status: "filled".to_string(),
+        };
+        engine.exchanges[&leg.exchange].simulate_fill(&execution_id, &order, Some(leg.position.leverage));
+        engine.events.append(events::EngineEvent::OrderFilled {
+            execution_id: execution_id.clone(),
+            strategy_id: "liquidation_monitor".to_string(),
+            leg: order.leg.clone(),
+            exchange: order.exchange.clone(),
+            symbol: order.symbol.clone(),
+            side: order.side.clone(),
+            filled_quantity: order.filled_quantity,
+            fee: order.fee,
+        });
+    }
+    info!(%execution_id, exchange = %leg.exchange, symbol = %position.symbol, quantity, hedged = hedge.is_some(), "已縮減接近強平的持倉");
+    Ok(format!("reduce {:.2}", quantity))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bench::START_MS;
+    use crate::deterministic_sim::{build, connect_sessions, funded_config};
+    use crate::{config, Environment, LeverageSetting};
+    use std::path::PathBuf;
+
+    // binance 逐倉 20 倍多頭距強平不足 5%，bybit 逐倉 5 倍空頭約 19%：前者低於降風險閾值，後者只告警。
+    // 縮減時對沖腿按相同名義金額一併縮減；追加保證金時把距離推回目標值
+    #[tokio::test]
+    async fn liquidation_monitor_alerts_and_derisks_legs_near_liquidation() {
+        async fn monitored(name: &str, action: config::DeriskAction) -> (Arc<ExecutionEngine>, PathBuf) {
+            let mut config = funded_config();
+            config.liquidation = config::LiquidationConfig {
+                interval_secs: 3_600,
+                alert_distance: 0.25,
+                action,
+                ..Default::default()
+            };
+            let (engine, path) = build(name, config, Environment::simulated(START_MS, 1));
+            let engine = Arc::new(engine);
+            connect_sessions(&engine).await;
+            for (exchange, side, leverage) in [("binance", "buy", 20.0), ("bybit", "sell", 5.0)] {
+                let setting = LeverageSetting {
+                    leverage,
+                    margin_mode: MarginMode::Isolated,
+                };
+                engine.exchanges[exchange].set_leverage("BTCUSDT", setting).await.unwrap();
+                let order = ChildOrder {
+                    leg: side.to_string(),
+                    exchange: exchange.to_string(),
+                    symbol: "BTCUSDT".to_string(),
+                    side: side.to_string(),
+                    quantity: 10_000.0,
+                    filled_quantity: 10_000.0,
+                    fee: 0.0,
+                    status: "filled".to_string(),
+                };
+                engine.exchanges[exchange].simulate_fill("open", &order, Some(leverage));
+            }
+            spawn(Arc::clone(&engine));
+            for _ in 0..250 {
+                if engine.liquidation.snapshot().alerts.len() >= 2 {
+                    break;
+                }
+                tokio::time::sleep(Duration::from_millis(20)).await;
+            }
+            (engine, path)
+        }
+
+        let (engine, path) = monitored("liquidation-reduce", config::DeriskAction::Reduce).await;
+        let snapshot = engine.liquidation.snapshot();
+        let levels: Vec<_> = snapshot.legs.iter().map(|leg| (leg.exchange.as_str(), leg.level)).collect();
+        assert_eq!(levels, [("binance", RiskLevel::Critical), ("bybit", RiskLevel::Alert)]);
+        assert!(snapshot.legs[0].distance < 0.05 && snapshot.legs[1].distance > 0.15);
+        let actions: Vec<_> = snapshot.alerts.iter().map(|alert| (alert.exchange.as_str(), alert.action.as_str())).collect();
+        assert_eq!(actions, [("binance", "reduce 2500.00"), ("bybit", "alert")]);
+        let positions = |engine: &ExecutionEngine| {
+            ["binance", "bybit"].map(|exchange| engine.exchanges[exchange].account.lock().unwrap().positions["BTCUSDT"])
+        };
+        assert_eq!(positions(&engine), [7_500.0, -7_500.0]);
+        let derisked: Vec<_> = engine
+            .events
+            .read(0, usize::MAX)
+            .into_iter()
+            .filter_map(|record| match record.event {
+                events::EngineEvent::OrderFilled { strategy_id, leg, side, .. } if strategy_id == "liquidation_monitor" => Some((leg, side)),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(derisked, [("derisk".to_string(), "sell".to_string()), ("derisk_hedge".to_string(), "buy".to_string())]);
+        let _ = std::fs::remove_file(path);
+
+        let (engine, path) = monitored("liquidation-margin", config::DeriskAction::AddMargin).await;
+        let alert = &engine.liquidation.snapshot().alerts[0];
+        assert!(alert.action.starts_with("add_margin"), "{}", alert.action);
+        assert_eq!(positions(&engine), [10_000.0, -10_000.0]);
+        let risk = engine.exchanges["binance"].fetch_position_risk().await.unwrap();
+        assert!((risk[0].distance() - 0.15).abs() < 0.01, "{}", risk[0].distance());
+        let _ = std::fs::remove_file(path);
+    }
+}
@@ -0,0 +1,233 @@
+use super::latency::{self, StageSummary};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio::time::{Instant, MissedTickBehavior};
+
+// 連接引擎的流：TCP 或 Unix 域套接字
+trait Stream: AsyncRead + AsyncWrite + Unpin + Send {}
+
+impl<S: AsyncRead + AsyncWrite + Unpin + Send> Stream for S {}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LoadTestParams {
+    // host:port，或 unix:<套接字路徑> 經 Unix 域套接字連接
+    pub address: String,
+    pub connections: usize,
+    // 全部連接合計的目標請求速率（每秒），按連接平均分配
+    pub rate_per_sec: f64,
+    pub duration_secs: u64,
+    // 每次發送的消息，缺省為 get_latency 指令；監聽需未啟用客戶端認證
+    pub message: Value,
+    pub timeout_ms: u64,
+}
+
+impl Default for LoadTestParams {
+    fn default() -> Self {
+        Self {
+            address: "127.0.0.1:8080".to_string(),
+            connections: 4,
+            rate_per_sec: 200.0,
+            duration_secs: 10,
+            message: serde_json::json!({ "command": "get_latency" }),
+            timeout_ms: 5_000,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LoadTestReport {
+    pub address: String,
+    pub connections: usize,
+    pub target_rate_per_sec: f64,
+    pub achieved_rate_per_sec: f64,
+    pub elapsed_secs: f64,
+    pub sent: u64,
+    pub succeeded: u64,
+    // 響應 status 為 error
+    pub failed: u64,
+    // 超時或連接錯誤；連接出錯後該連接停止發送
+    pub errors: u64,
+    // 從計劃發送時間到收到響應的延遲（微秒），發送落後於計劃時的排隊時間也計入
+    pub latency_us: StageSummary,
+}
+
+#[derive(Default)]
+struct ConnectionResult {
+    sent: u64,
+    succeeded: u64,
+    failed: u64,
+    errors: u64,
+}
+
+/// 以固定速率驅動引擎的 TCP 或 Unix 域套接字監聽：每個連接按計劃時間點發送消息並等待響應（JSON 模式下
+/// 每個連接同時只有一條在途消息），持續 `duration_secs` 後匯總吞吐與延遲分佈。
+pub async fn run(params: &LoadTestParams) -> Result<LoadTestReport, String> {
+    if params.connections == 0 || params.rate_per_sec <= 0.0 || params.duration_secs == 0 {
+        return Err("connections、rate_per_sec 與 duration_secs 需大於 0".to_string());
+    }
+    let payload = serde_json::to_vec(&params.message).map_err(|e| e.to_string())?;
+    let period = Duration::from_secs_f64(params.connections as f64 / params.rate_per_sec);
+    let timeout = Duration::from_millis(params.timeout_ms);
+    let mut streams = Vec::with_capacity(params.connections);
+    for _ in 0..params.connections {
+        let stream = connect(&params.address).await.map_err(|e| format!("連接 {} 失敗: {}", params.address, e))?;
+        streams.push(stream);
+    }
+
+    let started = Instant::now();
+    let deadline = started + Duration::from_secs(params.duration_secs);
+    let tasks: Vec<_> = streams
+        .into_iter()
+        .enumerate()
+        .map(|(index, stream)| {
+            let payload = payload.clone();
+            // 各連接的發送時間點錯開，合計速率均勻
+            let first = started + period.mul_f64(index as f64 / params.connections as f64);
+            tokio::spawn(drive(stream, payload, first, period, deadline, timeout))
+        })
+        .collect();
+
+    let mut histogram = latency::histogram();
+    let mut total = ConnectionResult::default();
+    for task in tasks {
+        let (result, latencies) = task.await.map_err(|e| e.to_string())?;
+        total.sent += result.sent;
+        total.succeeded += result.succeeded;
+        total.failed += result.failed;
+        total.errors += result.errors;
+        histogram.add(&latencies).map_err(|e| e.to_string())?;
+    }
+    let elapsed_secs = started.elapsed().as_secs_f64();
+    Ok(LoadTestReport {
+        address: params.address.clone(),
+        connections: params.connections,
+        target_rate_per_sec: params.rate_per_sec,
+        achieved_rate_per_sec: (total.succeeded + total.failed) as f64 / elapsed_secs,
+        elapsed_secs,
+        sent: total.sent,
+        succeeded: total.succeeded,
+        failed: total.failed,
+        errors: total.errors,
+        latency_us: latency::summarize(&histogram),
+    })
+}
+
+async fn connect(address: &str) -> std::io::Result<Box<dyn Stream>> {
+    #[cfg(unix)]
+    if let Some(path) = address.strip_prefix("unix:") {
+        return Ok(Box::new(tokio::net::UnixStream::connect(path).await?));
+    }
+    let stream = TcpStream::connect(address).await?;
+    stream.set_nodelay(true)?;
+    Ok(Box::new(stream))
+}
+
+async fn drive(
+    mut stream: Box<dyn Stream>,
+    payload: Vec<u8>,
+    first: Instant,
+    period: Duration,
+    deadline: Instant,
+    timeout: Duration,
+) -> (ConnectionResult, hdrhistogram::Histogram<u64>) {
+    let mut result = ConnectionResult::default();
+    let mut histogram = latency::histogram();
+    let mut pending = Vec::new();
+    let mut schedule = tokio::time::interval_at(first, period);
+    // 響應慢於計劃時連續補發，延遲從計劃時間算起，避免協調遺漏低估尾延遲
+    schedule.set_missed_tick_behavior(MissedTickBehavior::Burst);
+    loop {
+        let scheduled = schedule.tick().await;
+        if scheduled >= deadline {
+            break;
+        }
+        result.sent += 1;
+        let exchange = async {
+            stream.write_all(&payload).await.map_err(|e| e.to_string())?;
+            read_response(&mut stream, &mut pending).await
+        };
+        match tokio::time::timeout(timeout, exchange).await {
+            Ok(Ok(response)) => {
+                histogram.saturating_record(scheduled.elapsed().as_micros() as u64);
+                if response["status"] == "error" {
+                    result.failed += 1;
+                } else {
+                    result.succeeded += 1;
+                }
+            }
+            Ok(Err(_)) | Err(_) => {
+                result.errors += 1;
+                break;
+            }
+        }
+    }
+    (result, histogram)
+}
+
+// 讀取下一條響應；心跳等推送消息帶 event 字段，跳過
+async fn read_response(stream: &mut Box<dyn Stream>, pending: &mut Vec<u8>) -> Result<Value, String> {
+    let mut chunk = [0; 4096];
+    loop {
+        let mut values = serde_json::Deserializer::from_slice(pending).into_iter::<Value>();
+        match values.next() {
+            Some(Ok(value)) => {
+                let consumed = values.byte_offset();
+                pending.drain(..consumed);
+                if value.get("event").is_none() {
+                    return Ok(value);
+                }
+                continue;
+            }
+            Some(Err(e)) if !e.is_eof() => return Err(format!("響應不是合法 JSON: {}", e)),
+            _ => {}
+        }
+        let n = stream.read(&mut chunk).await.map_err(|e| e.to_string())?;
+        if n == 0 {
+            return Err("連接已關閉".to_string());
+        }
+        pending.extend_from_slice(&chunk[..n]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::deterministic_sim::build;
+    use crate::{config, server, Environment};
+
+    #[tokio::test]
+    async fn load_test_drives_listener_at_target_rate() {
+        let (engine, path) = build("load-test", config::EngineConfig::default(), Environment::system());
+        let engine = std::sync::Arc::new(engine);
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        let server = tokio::spawn({
+            let engine = std::sync::Arc::clone(&engine);
+            async move {
+                while let Ok((socket, _)) = listener.accept().await {
+                    let engine = std::sync::Arc::clone(&engine);
+                    tokio::spawn(async move { server::handle_connection(socket, &engine).await });
+                }
+            }
+        });
+        let params = LoadTestParams {
+            address,
+            connections: 2,
+            rate_per_sec: 100.0,
+            duration_secs: 1,
+            ..Default::default()
+        };
+        let report = run(&params).await.unwrap();
+        // 兩個連接各按 50/s 發送一秒
+        assert!((90..=102).contains(&report.sent), "{:?}", report);
+        assert_eq!(report.succeeded, report.sent);
+        assert_eq!(report.errors, 0);
+        assert_eq!(report.latency_us.count, report.sent);
+        server.abort();
+        let _ = std::fs::remove_file(path);
+    }
+}
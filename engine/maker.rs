@@ -0,0 +1,189 @@
+use super::config::MakerConfig;
+use super::market_data::OrderBook;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::Mutex;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MakerLegs {
+    #[default]
+    Both,
+    Short,
+    Long,
+}
+
+// 掛單優先：指定的腿先以 post-only 限價單在價差內掛單，有效期內未成交的部分撤單後改為吃單
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MakerFirst {
+    #[serde(default)]
+    pub legs: MakerLegs,
+    // 缺省為 maker.default_time_in_force_ms
+    #[serde(default)]
+    pub time_in_force_ms: Option<u64>,
+    // 掛單價從本方最優價向對手價移動的價差比例，缺省為 maker.default_inside_spread
+    #[serde(default)]
+    pub inside_spread: Option<f64>,
+}
+
+// 一筆 post-only 單的價格與掛單參數
+#[derive(Debug, Clone, Copy)]
+pub struct PostOnlyOrder {
+    pub price: f64,
+    pub inside_spread: f64,
+    pub time_in_force_ms: u64,
+}
+
+impl MakerFirst {
+    pub fn validate(&self, config: &MakerConfig) -> Result<(), String> {
+        if self.time_in_force_ms.is_some_and(|ms| ms == 0 || ms > config.max_time_in_force_ms) {
+            return Err(format!("掛單有效期必須大於 0 且不超過 {} 毫秒", config.max_time_in_force_ms));
+        }
+        if self.inside_spread.is_some_and(|inside| !(0.0..1.0).contains(&inside)) {
+            return Err("inside_spread 必須在 [0, 1) 內，否則會吃單".to_string());
+        }
+        Ok(())
+    }
+
+    // 現貨槓桿空頭腿（margin_short）也按 short 處理
+    pub fn applies(&self, leg: &str) -> bool {
+        match self.legs {
+            MakerLegs::Both => true,
+            MakerLegs::Short => leg.ends_with("short"),
+            MakerLegs::Long => leg == "long",
+        }
+    }
+
+    // 賣單從最優賣價、買單從最優買價向對手價移動 inside_spread 的價差，仍留在本方不吃單
+    pub fn order(&self, config: &MakerConfig, book: &OrderBook, side: &str) -> Result<PostOnlyOrder, String> {
+        let (bid, ask) = match (book.bids.first(), book.asks.first()) {
+            (Some(bid), Some(ask)) => (bid.price, ask.price),
+            _ => return Err("訂單簿為空，無法掛單".to_string()),
+        };
+        let inside_spread = self.inside_spread.unwrap_or(config.default_inside_spread);
+        let spread = ask - bid;
+        let price = if side == "sell" { ask - spread * inside_spread } else { bid + spread * inside_spread };
+        Ok(PostOnlyOrder {
+            price,
+            inside_spread,
+            time_in_force_ms: self.time_in_force_ms.unwrap_or(config.default_time_in_force_ms),
+        })
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct MakerFillStats {
+    pub orders: u64,
+    // 掛單優先的腿中以 maker 與回退吃單成交的名義金額
+    pub maker_notional: f64,
+    pub taker_notional: f64,
+    pub maker_fill_ratio: f64,
+}
+
+/// 各交易所掛單優先執行的成交統計，經 get_maker_stats 指令與 /metrics 導出。
+pub struct MakerStats {
+    config: MakerConfig,
+    stats: Mutex<BTreeMap<String, MakerFillStats>>,
+}
+
+impl MakerStats {
+    pub fn new(config: MakerConfig) -> Self {
+        Self {
+            config,
+            stats: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    pub fn config(&self) -> &MakerConfig {
+        &self.config
+    }
+
+    pub fn record(&self, exchange: &str, maker_notional: f64, taker_notional: f64) {
+        let mut stats = self.stats.lock().unwrap();
+        let entry = stats.entry(exchange.to_string()).or_default();
+        entry.orders += 1;
+        entry.maker_notional += maker_notional;
+        entry.taker_notional += taker_notional;
+        let total = entry.maker_notional + entry.taker_notional;
+        entry.maker_fill_ratio = if total > 0.0 { entry.maker_notional / total } else { 0.0 };
+    }
+
+    pub fn snapshot(&self) -> BTreeMap<String, MakerFillStats> {
+        self.stats.lock().unwrap().clone()
+    }
+
+    pub fn prometheus(&self) -> String {
+        let mut output = String::new();
+        output.push_str("# HELP arb_maker_fill_ratio Share of maker-first notional filled as maker\n");
+        output.push_str("# TYPE arb_maker_fill_ratio gauge\n");
+        let stats = self.stats.lock().unwrap();
+        for (exchange, stats) in stats.iter() {
+            let _ = writeln!(output, "arb_maker_fill_ratio{{exchange=\"{}\"}} {}", exchange, stats.maker_fill_ratio);
+        }
+        output.push_str("# HELP arb_maker_first_notional_total Maker-first notional by fill type\n");
+        output.push_str("# TYPE arb_maker_first_notional_total counter\n");
+        for (exchange, stats) in stats.iter() {
+            for (fill, notional) in [("maker", stats.maker_notional), ("taker", stats.taker_notional)] {
+                let _ = writeln!(output, "arb_maker_first_notional_total{{exchange=\"{}\",fill=\"{}\"}} {}", exchange, fill, notional);
+            }
+        }
+        output
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::deterministic_sim::{engine, request};
+    use crate::{config, ArbitrageRequest};
+
+    #[tokio::test]
+    async fn maker_first_posts_then_falls_back_to_taker() {
+        let simulation = config::SimulatedExchangeConfig {
+            reject_rate: 0.0,
+            maker_fill_rate: 1.0,
+            ..Default::default()
+        };
+        // 掛單在有效期內從不全部成交的對照
+        let fallback_simulation = config::SimulatedExchangeConfig {
+            maker_fill_rate: 0.0,
+            ..simulation.clone()
+        };
+        let (fallback, fallback_path) = engine("maker_fallback", 1, fallback_simulation);
+        let (engine, path) = engine("maker_first", 1, simulation);
+        let gas_quote = engine.default_chain().gas_optimizer.quote(5);
+        let maker_first = |legs: &str| ArbitrageRequest {
+            maker_first: Some(serde_json::from_value(serde_json::json!({"legs": legs, "time_in_force_ms": 500})).unwrap()),
+            ..request(1_000.0)
+        };
+
+        // 全部以 maker 成交，兩腿按 maker 費率計費
+        let outcome = engine.execute_flash_loan_arbitrage("sim", &maker_first("both"), 0.0005, &gas_quote, false, None).await.unwrap();
+        assert_eq!(outcome.fill_ratio(), 1.0);
+        let maker_fees = 1_000.0 * (engine.maker_fee("binance") + engine.maker_fee("bybit"));
+        assert!((outcome.fees - maker_fees).abs() < 1e-9);
+        let stats = engine.maker.snapshot();
+        assert_eq!(stats["binance"].maker_fill_ratio, 1.0);
+        assert_eq!(stats["bybit"].maker_fill_ratio, 1.0);
+
+        // 只有空頭腿（binance）掛單；多頭腿直接吃單，不計入統計
+        let outcome = engine.execute_flash_loan_arbitrage("sim", &maker_first("short"), 0.0005, &gas_quote, false, None).await.unwrap();
+        assert_eq!(outcome.orders[1].fee, 1_000.0 * engine.taker_fee("bybit"));
+        let stats = engine.maker.snapshot();
+        assert_eq!((stats["binance"].orders, stats["bybit"].orders), (2, 1));
+        assert!(engine.maker.prometheus().contains("arb_maker_fill_ratio{exchange=\"binance\"} 1"));
+
+        let invalid: MakerFirst = serde_json::from_value(serde_json::json!({"inside_spread": 1.0})).unwrap();
+        assert!(invalid.validate(engine.maker.config()).is_err());
+
+        // 未成交部分撤單後吃單，兩腿仍全部成交
+        let outcome = fallback.execute_flash_loan_arbitrage("sim", &maker_first("both"), 0.0005, &gas_quote, false, None).await.unwrap();
+        assert_eq!(outcome.fill_ratio(), 1.0);
+        let stats = fallback.maker.snapshot();
+        assert!(stats["binance"].maker_fill_ratio < 1.0 && stats["binance"].taker_notional > 0.0);
+        for path in [path, fallback_path] {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+}
@@ -0,0 +1,155 @@
+use super::config::{self, MarginConfig};
+use super::environment::Rng;
+use super::market_data;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Mutex;
+use tracing::info;
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct BorrowLine {
+    pub limit: f64,
+    pub borrowed: f64,
+}
+
+pub struct MarginDesk {
+    config: MarginConfig,
+    // (exchange, asset) -> 額度與已借數量
+    borrows: Mutex<HashMap<(String, String), BorrowLine>>,
+}
+
+impl MarginDesk {
+    pub fn new(config: MarginConfig) -> Self {
+        let borrows = config
+            .borrow_limits
+            .iter()
+            .flat_map(|(exchange, assets)| {
+                assets.iter().map(move |(asset, limit)| {
+                    let line = BorrowLine {
+                        limit: *limit,
+                        borrowed: 0.0,
+                    };
+                    ((exchange.clone(), asset.to_uppercase()), line)
+                })
+            })
+            .collect();
+        Self {
+            config,
+            borrows: Mutex::new(borrows),
+        }
+    }
+
+    pub fn has_perp(&self, exchange: &str, symbol: &str) -> bool {
+        !self
+            .config
+            .missing_perps
+            .get(config::venue(exchange))
+            .is_some_and(|symbols| symbols.iter().any(|s| s.eq_ignore_ascii_case(symbol)))
+    }
+
+    pub fn borrow_rate(&self, exchange: &str, asset: &str) -> Result<f64, String> {
+        self.config
+            .borrow_rates
+            .get(config::venue(exchange))
+            .and_then(|assets| assets.get(asset))
+            .copied()
+            .ok_or_else(|| format!("{} 不支持借入 {}", exchange, asset))
+    }
+
+    // 按現貨中間價把名義金額換算成需要借入的基礎資產數量
+    pub fn borrow_quantity(&self, rng: &dyn Rng, exchange: &str, symbol: &str, notional: f64) -> Result<f64, String> {
+        let (base, quote) =
+            market_data::split_symbol(symbol).ok_or_else(|| format!("無法解析交易對: {}", symbol))?;
+        let mid = market_data::simulated_spot_book(rng, exchange, &base, &quote)?
+            .mid()
+            .ok_or_else(|| format!("{} 現貨訂單簿為空", symbol))?;
+        Ok(notional / mid)
+    }
+
+    pub fn reserve(&self, exchange: &str, asset: &str, quantity: f64) -> Result<(), String> {
+        let mut borrows = self.borrows.lock().unwrap();
+        let line = borrows
+            .get_mut(&(exchange.to_string(), asset.to_string()))
+            .ok_or_else(|| format!("{} 沒有 {} 借幣額度", exchange, asset))?;
+        let available = line.limit - line.borrowed;
+        if quantity > available {
+            return Err(format!(
+                "{} 可借 {} 不足：需要 {:.6}，剩餘 {:.6}",
+                exchange, asset, quantity, available
+            ));
+        }
+        line.borrowed += quantity;
+        info!(%exchange, %asset, quantity, borrowed = line.borrowed, "已預留借幣額度");
+        Ok(())
+    }
+
+    pub fn release(&self, exchange: &str, asset: &str, quantity: f64) {
+        if let Some(line) = self.borrows.lock().unwrap().get_mut(&(exchange.to_string(), asset.to_string())) {
+            line.borrowed = (line.borrowed - quantity).max(0.0);
+        }
+    }
+
+    pub fn snapshot(&self) -> BTreeMap<String, BorrowLine> {
+        self.borrows
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|((exchange, asset), line)| (format!("{}:{}", exchange, asset), line.clone()))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bench::START_MS;
+    use crate::deterministic_sim::{build, connect_sessions, funded_config, request};
+    use crate::{events, ArbitrageRequest, Environment};
+    use std::sync::Arc;
+
+    // binance 沒有 BTCUSDT 永續：該側按負借幣利率參與比較，只能借幣賣出現貨承擔空頭腿，成交後借幣額度保持佔用
+    #[tokio::test]
+    async fn spot_margin_short_replaces_missing_perp_within_borrow_limit() {
+        let mut config = funded_config();
+        config.margin.missing_perps.insert("binance".to_string(), vec!["BTCUSDT".to_string()]);
+        config.margin.borrow_rates.insert("binance".to_string(), [("BTC".to_string(), 0.0001)].into());
+        config.margin.borrow_limits.insert("binance".to_string(), [("BTC".to_string(), 0.03)].into());
+        let (engine, path) = build("margin-short", config, Environment::simulated(START_MS, 1));
+        let engine = Arc::new(engine);
+        connect_sessions(&engine).await;
+        let borrowed = || engine.margin.snapshot()["binance:BTC"].borrowed;
+
+        // bybit 費率更高，空頭腿應在 bybit，binance 無法以現貨槓桿做多
+        engine.quotes.record_predicted_rate("bybit", "BTCUSDT", 0.0010, START_MS);
+        let response = engine.execute_funding_rate_arbitrage(request(1_000.0)).await;
+        let error = response.error_message.unwrap();
+        assert!(error.contains("現貨槓桿只能承擔空頭腿"), "{}", error);
+        assert_eq!(borrowed(), 0.0);
+
+        // 借幣利息 0.0001 對比 bybit 的 -0.0010，費率差 0.0009，binance 借幣賣出
+        engine.quotes.record_predicted_rate("bybit", "BTCUSDT", -0.0010, START_MS);
+        let response = engine.execute_funding_rate_arbitrage(ArbitrageRequest { include_market_context: true, ..request(1_000.0) }).await;
+        assert_eq!(response.status, "success", "{:?}", response.error_message);
+        let context = response.market_context.unwrap();
+        assert_eq!((context.primary_rate, context.borrow_rate), (None, Some(0.0001)));
+        assert!((context.rate_diff.unwrap() - 0.0009).abs() < 1e-12);
+        let orders: Vec<_> = engine
+            .events
+            .read(0, 1_000)
+            .into_iter()
+            .filter_map(|envelope| match envelope.event {
+                events::EngineEvent::OrderPlaced { leg, exchange, side, .. } => Some((leg, exchange, side)),
+                _ => None,
+            })
+            .collect();
+        assert!(orders.contains(&("margin_short".to_string(), "binance".to_string(), "sell".to_string())), "{:?}", orders);
+        // 1000 USDT 按約 60000 的中間價借入約 0.0167 BTC
+        assert!((borrowed() - 1_000.0 / 60_000.0).abs() < 1e-4, "{}", borrowed());
+
+        // 剩餘額度不足第二筆
+        let response = engine.execute_funding_rate_arbitrage(request(1_000.0)).await;
+        let error = response.error_message.unwrap();
+        assert!(error.contains("可借 BTC 不足"), "{}", error);
+        assert!((borrowed() - 1_000.0 / 60_000.0).abs() < 1e-4);
+        let _ = std::fs::remove_file(path);
+    }
+}
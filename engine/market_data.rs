@@ -0,0 +1,171 @@
+use crate::environment::Rng;
+use serde::Serialize;
+
+// 各資產的參考 USDT 價格，用於生成模擬訂單簿
+const REFERENCE_PRICES: [(&str, f64); 7] = [
+    ("USDT", 1.0),
+    ("USDC", 1.0),
+    ("DAI", 1.0),
+    ("BTC", 60_000.0),
+    ("ETH", 3_000.0),
+    ("BNB", 550.0),
+    ("SOL", 150.0),
+];
+// 報價資產後綴，用於拆分 "BTCUSDT" 形式的交易對
+const QUOTE_ASSETS: [&str; 3] = ["USDT", "USDC", "BTC"];
+const BOOK_LEVELS: usize = 5;
+// 每檔掛單的名義金額（USDT），逐檔遞增
+const LEVEL_NOTIONAL: f64 = 25_000.0;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Level {
+    pub price: f64,
+    pub quantity: f64,
+}
+
+// 最優價在前；數量以基礎資產計
+#[derive(Debug, Clone, Serialize)]
+pub struct OrderBook {
+    pub bids: Vec<Level>,
+    pub asks: Vec<Level>,
+}
+
+// 逐檔成交結果；complete 為 false 表示深度不足
+#[derive(Debug, Clone)]
+pub struct Fill {
+    pub base_quantity: f64,
+    pub quote_quantity: f64,
+    pub top_price: f64,
+    pub complete: bool,
+}
+
+impl Fill {
+    pub fn average_price(&self) -> f64 {
+        if self.base_quantity > 0.0 {
+            self.quote_quantity / self.base_quantity
+        } else {
+            self.top_price
+        }
+    }
+
+    // 成交均價相對最優價的滑點（基點）
+    pub fn slippage_bps(&self) -> f64 {
+        ((self.average_price() - self.top_price) / self.top_price).abs() * 10_000.0
+    }
+}
+
+impl OrderBook {
+    pub fn mid(&self) -> Option<f64> {
+        Some((self.bids.first()?.price + self.asks.first()?.price) / 2.0)
+    }
+
+    // 用報價資產買入基礎資產，逐檔吃賣盤
+    pub fn buy_with_quote(&self, quote_amount: f64) -> Fill {
+        let mut remaining = quote_amount;
+        let mut base = 0.0;
+        for level in &self.asks {
+            let take = remaining.min(level.price * level.quantity);
+            base += take / level.price;
+            remaining -= take;
+            if remaining <= 0.0 {
+                break;
+            }
+        }
+        Fill {
+            base_quantity: base,
+            quote_quantity: quote_amount - remaining,
+            top_price: self.asks.first().map(|l| l.price).unwrap_or_default(),
+            complete: remaining <= 0.0,
+        }
+    }
+
+    // 買入指定數量的基礎資產，逐檔吃賣盤
+    pub fn buy_base(&self, base_amount: f64) -> Fill {
+        let mut remaining = base_amount;
+        let mut quote = 0.0;
+        for level in &self.asks {
+            let take = remaining.min(level.quantity);
+            quote += take * level.price;
+            remaining -= take;
+            if remaining <= 0.0 {
+                break;
+            }
+        }
+        Fill {
+            base_quantity: base_amount - remaining,
+            quote_quantity: quote,
+            top_price: self.asks.first().map(|l| l.price).unwrap_or_default(),
+            complete: remaining <= 0.0,
+        }
+    }
+
+    // 賣出基礎資產換取報價資產，逐檔吃買盤
+    pub fn sell_base(&self, base_amount: f64) -> Fill {
+        let mut remaining = base_amount;
+        let mut quote = 0.0;
+        for level in &self.bids {
+            let take = remaining.min(level.quantity);
+            quote += take * level.price;
+            remaining -= take;
+            if remaining <= 0.0 {
+                break;
+            }
+        }
+        Fill {
+            base_quantity: base_amount - remaining,
+            quote_quantity: quote,
+            top_price: self.bids.first().map(|l| l.price).unwrap_or_default(),
+            complete: remaining <= 0.0,
+        }
+    }
+}
+
+// 將 "BTC/USDT" 或 "BTCUSDT" 拆分為 (基礎資產, 報價資產)
+pub fn split_symbol(symbol: &str) -> Option<(String, String)> {
+    let symbol = symbol.to_uppercase();
+    if let Some((base, quote)) = symbol.split_once('/') {
+        return Some((base.to_string(), quote.to_string()));
+    }
+    QUOTE_ASSETS.iter().find_map(|quote| {
+        symbol
+            .strip_suffix(quote)
+            .filter(|base| !base.is_empty())
+            .map(|base| (base.to_string(), quote.to_string()))
+    })
+}
+
+pub fn reference_price(asset: &str) -> Option<f64> {
+    REFERENCE_PRICES.iter().find(|(a, _)| *a == asset).map(|(_, p)| *p)
+}
+
+// 模擬現貨訂單簿：中間價在參考價附近 ±0.2% 隨機偏移，價差 1 個基點
+pub fn simulated_spot_book(rng: &dyn Rng, _exchange: &str, base: &str, quote: &str) -> Result<OrderBook, String> {
+    simulated_book(base, quote, (rng.next_f64() - 0.5) * 0.004)
+}
+
+// 模擬永續合約訂單簿：相對參考價帶 0 ~ 0.3% 的溢價
+pub fn simulated_perp_book(rng: &dyn Rng, _exchange: &str, base: &str, quote: &str) -> Result<OrderBook, String> {
+    simulated_book(base, quote, rng.next_f64() * 0.003)
+}
+
+// 模擬指定溢價的永續合約訂單簿，用於溢價指數
+pub fn simulated_premium_book(base: &str, quote: &str, premium: f64) -> Result<OrderBook, String> {
+    simulated_book(base, quote, premium)
+}
+
+fn simulated_book(base: &str, quote: &str, offset: f64) -> Result<OrderBook, String> {
+    let base_price = reference_price(base).ok_or_else(|| format!("未知資產: {}", base))?;
+    let quote_price = reference_price(quote).ok_or_else(|| format!("未知資產: {}", quote))?;
+    let mid = base_price / quote_price * (1.0 + offset);
+    let half_spread = mid * 0.00005;
+    let tick = mid * 0.0001;
+
+    let level = |i: usize, price: f64| Level {
+        price,
+        quantity: LEVEL_NOTIONAL * (i + 1) as f64 / base_price,
+    };
+    Ok(OrderBook {
+        bids: (0..BOOK_LEVELS).map(|i| level(i, mid - half_spread - tick * i as f64)).collect(),
+        asks: (0..BOOK_LEVELS).map(|i| level(i, mid + half_spread + tick * i as f64)).collect(),
+    })
+}
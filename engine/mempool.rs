@@ -0,0 +1,298 @@
+use super::config::MempoolConfig;
+use super::dex::{DexExecutor, PoolKey};
+use super::market_data;
+use super::ExecutionEngine;
+use futures::StreamExt;
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tracing::{debug, info, warn};
+use web3::api::SubscriptionId;
+use web3::transports::WebSocket;
+use web3::types::{Transaction, TransactionId, H256};
+use web3::{DuplexTransport, Transport, Web3};
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PendingSwap {
+    pub hash: H256,
+    pub venue: &'static str,
+    pub pool: PoolKey,
+    pub asset: String,
+    pub amount: f64,
+    pub usd: f64,
+    #[serde(skip)]
+    seen_at: Instant,
+}
+
+pub struct MempoolMonitor {
+    config: MempoolConfig,
+    // 按觀察時間排序，超出衝突窗口的記錄在讀寫時清理
+    pending: Mutex<VecDeque<PendingSwap>>,
+}
+
+impl MempoolMonitor {
+    pub fn new(config: MempoolConfig) -> Self {
+        Self {
+            config,
+            pending: Mutex::new(VecDeque::new()),
+        }
+    }
+
+    fn prune(&self, pending: &mut VecDeque<PendingSwap>) {
+        let window = Duration::from_secs(self.config.conflict_window_secs);
+        while pending.front().is_some_and(|swap| swap.seen_at.elapsed() > window) {
+            pending.pop_front();
+        }
+    }
+
+    fn inspect(&self, dex: &DexExecutor, tx: &Transaction) {
+        let Some(to) = tx.to else { return };
+        let Some((venue, pool, asset, amount)) = dex.decode_pending(to, &tx.input.0) else {
+            return;
+        };
+        let usd = amount * market_data::reference_price(&asset).unwrap_or(0.0);
+        if usd < self.config.min_swap_usd {
+            return;
+        }
+        let mut pending = self.pending.lock().unwrap();
+        self.prune(&mut pending);
+        if pending.iter().any(|swap| swap.hash == tx.hash) {
+            return;
+        }
+        debug!(tx_hash = ?tx.hash, %venue, %asset, amount, usd, "發現待確認的大額兌換");
+        pending.push_back(PendingSwap {
+            hash: tx.hash,
+            venue,
+            pool,
+            asset,
+            amount,
+            usd,
+            seen_at: Instant::now(),
+        });
+    }
+
+    // 衝突窗口內經過同一池子的待確認兌換
+    pub fn conflicts(&self, venue: &str, pool: PoolKey) -> Vec<PendingSwap> {
+        let mut pending = self.pending.lock().unwrap();
+        self.prune(&mut pending);
+        pending.iter().filter(|swap| swap.venue == venue && swap.pool == pool).cloned().collect()
+    }
+
+    pub fn snapshot(&self) -> Vec<PendingSwap> {
+        let mut pending = self.pending.lock().unwrap();
+        self.prune(&mut pending);
+        pending.iter().cloned().collect()
+    }
+}
+
+pub fn spawn(engine: Arc<ExecutionEngine>) {
+    let Some(url) = engine.mempool.config.ws_url.clone() else {
+        info!("未配置 mempool.ws_url，不監控待確認交易");
+        return;
+    };
+    if engine.default_chain().dex.is_none() {
+        info!("默認鏈未啟用 DEX 腿，不監控待確認交易");
+        return;
+    }
+    tokio::spawn(async move {
+        let reconnect = Duration::from_secs(engine.mempool.config.reconnect_secs);
+        loop {
+            if let Err(error) = watch(&engine, &url).await {
+                warn!(%error, "待確認交易訂閱中斷，稍後重連");
+            }
+            tokio::time::sleep(reconnect).await;
+        }
+    });
+}
+
+async fn watch(engine: &ExecutionEngine, url: &str) -> Result<(), String> {
+    let Some(dex) = engine.default_chain().dex.as_ref() else { return Ok(()) };
+    let transport = WebSocket::new(url).await.map_err(|e| format!("連接 {} 失敗: {}", url, e))?;
+    let mut params = vec![serde_json::json!("newPendingTransactions")];
+    if engine.mempool.config.full_transactions {
+        params.push(serde_json::json!(true));
+    }
+    let id = transport
+        .execute("eth_subscribe", params)
+        .await
+        .map_err(|e| format!("eth_subscribe 失敗: {}", e))?;
+    let id: String = serde_json::from_value(id).map_err(|e| format!("無效的訂閱 ID: {}", e))?;
+    let mut stream = transport
+        .subscribe(SubscriptionId::from(id))
+        .map_err(|e| format!("註冊訂閱失敗: {}", e))?;
+    let web3 = Web3::new(transport.clone());
+    info!(%url, "已訂閱待確認交易");
+    while let Some(value) = stream.next().await {
+        // 完整交易對象直接解析；只推送哈希的節點需要逐筆查詢
+        let tx = match serde_json::from_value::<Transaction>(value.clone()) {
+            Ok(tx) => tx,
+            Err(_) => {
+                let Ok(hash) = serde_json::from_value::<H256>(value) else { continue };
+                match web3.eth().transaction(TransactionId::Hash(hash)).await {
+                    Ok(Some(tx)) => tx,
+                    // 查詢前已被打包或丟棄
+                    _ => continue,
+                }
+            }
+        };
+        engine.mempool.inspect(dex, &tx);
+    }
+    Err("訂閱流已關閉".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::deterministic_sim::{build, flash_loan_config, request, wallet_env};
+    use crate::{config, mock_exchange_e2e, spot_arbitrage, ArbitrageRequest, StrategyType};
+
+    // 內存池只記錄經過已配置池子且超過金額門檻的兌換（小額、其他手續費檔位的不計）；DEX 腿的目標池子有衝突時按 pending 狀態重新報價，
+    // 跌破滑點下限放棄且不動庫存，仍在容忍範圍內則按新報價成交
+    #[tokio::test]
+    async fn mempool_conflicts_abort_or_reprice_dex_legs() {
+        use web3::ethabi::Token;
+        use web3::types::{Address, Transaction, H256, U256};
+        let dex_config = config::DexConfig::default();
+        let token = |asset: &str| dex_config.tokens[asset].address.parse::<Address>().unwrap();
+        let (weth, usdt) = (token("ETH"), token("USDT"));
+        let quoter: Address = dex_config.uniswap_v3.quoter_address.parse().unwrap();
+        let router: Address = dex_config.uniswap_v3.router_address.parse().unwrap();
+        let eth = |amount: f64| U256::from((amount * 1e18) as u128);
+
+        // 他人賣出 ETH 的 exactInputSingle
+        let swap = |hash: u64, fee: u32, amount: f64| {
+            let selector = &web3::signing::keccak256(b"exactInputSingle((address,address,uint24,address,uint256,uint256,uint256,uint160))")[..4];
+            let params = Token::Tuple(vec![
+                Token::Address(weth),
+                Token::Address(usdt),
+                Token::Uint(fee.into()),
+                Token::Address(Address::from_low_u64_be(0xbeef)),
+                Token::Uint(u64::MAX.into()),
+                Token::Uint(eth(amount)),
+                Token::Uint(U256::zero()),
+                Token::Uint(U256::zero()),
+            ]);
+            Transaction {
+                hash: H256::from_low_u64_be(hash),
+                to: Some(router),
+                input: [selector, &web3::ethabi::encode(&[params])].concat().into(),
+                ..Default::default()
+            }
+        };
+        let large = swap(1, 500, 50.0);
+        let queried = swap(4, 500, 40.0);
+        let pushed = vec![
+            serde_json::json!(large),
+            serde_json::json!(swap(2, 500, 10.0)),
+            serde_json::json!(swap(3, 3000, 50.0)),
+            serde_json::json!(queried.hash),
+        ];
+        let ws_url = pending_tx_node(pushed, vec![queried]).await;
+
+        // 最新狀態可按 2970 買入 ETH；pending 狀態下的輸出比例可調
+        let latest_out = 10_000.0 / 2_970.0;
+        let pending_ratio = Arc::new(std::sync::Mutex::new(0.95));
+        let ratio = Arc::clone(&pending_ratio);
+        let url = mock_exchange_e2e::rpc_node(Arc::new(move |method, params| {
+            let to = params.first().and_then(|call| call["to"].as_str()).and_then(|to| to.parse::<Address>().ok());
+            let data = params.first().and_then(|call| call["data"].as_str()).unwrap_or_default().to_string();
+            let block = params.get(1).and_then(|block| block.as_str()).unwrap_or_default();
+            let pending_out = latest_out * *ratio.lock().unwrap();
+            Ok(match method {
+                "eth_call" if to == Some(quoter) => {
+                    let out = if block == "pending" { pending_out } else { latest_out };
+                    mock_exchange_e2e::abi_hex(&[Token::Uint(eth(out)), Token::Uint(U256::zero()), Token::Uint(U256::one()), Token::Uint(U256::zero())])
+                }
+                "eth_call" if data.starts_with("0xdd62ed3e") => mock_exchange_e2e::abi_hex(&[Token::Uint(U256::MAX)]),
+                "eth_call" if to == Some(weth) && block == "0xf" => mock_exchange_e2e::abi_hex(&[Token::Uint(U256::zero())]),
+                "eth_call" if to == Some(weth) => mock_exchange_e2e::abi_hex(&[Token::Uint(eth(pending_out))]),
+                // 發送前的兌換模擬
+                "eth_call" => serde_json::json!("0x"),
+                "eth_estimateGas" => serde_json::json!("0x30d40"),
+                "eth_getTransactionCount" => serde_json::json!("0x0"),
+                "eth_blockNumber" => serde_json::json!("0x10"),
+                "eth_sendRawTransaction" => serde_json::json!(format!("{:?}", H256::from_low_u64_be(0x52))),
+                "eth_getTransactionReceipt" => mock_exchange_e2e::mined_receipt(&params[0]),
+                other => return Err(format!("unexpected {}", other)),
+            })
+        }))
+        .await;
+        let mut config = flash_loan_config(url);
+        config.mempool.ws_url = Some(ws_url);
+        config.order_router.enabled = false;
+        config.spot_arbitrage.min_net_edge_bps = -1_000.0;
+        config.spot_arbitrage.inventory.insert("uniswap_v3".to_string(), [("USDT".to_string(), 100_000.0)].into());
+        config.spot_arbitrage.inventory.get_mut("binance").unwrap().insert("ETH".to_string(), 10.0);
+        let (engine, path) = build("mempool", config, wallet_env());
+        let engine = Arc::new(engine);
+        spawn(Arc::clone(&engine));
+        for _ in 0..250 {
+            if engine.mempool.snapshot().len() >= 2 {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        let seen: Vec<(H256, f64)> = engine.mempool.snapshot().iter().map(|swap| (swap.hash, swap.amount)).collect();
+        assert_eq!(seen, [(H256::from_low_u64_be(1), 50.0), (H256::from_low_u64_be(4), 40.0)]);
+
+        let spot = ArbitrageRequest {
+            strategy_type: StrategyType::SpotArbitrage,
+            symbol: "ETHUSDT".to_string(),
+            primary_exchange: "uniswap_v3".to_string(),
+            secondary_exchange: "binance".to_string(),
+            ..request(10_000.0)
+        };
+        let before = engine.spot_arbitrage.inventory();
+        let error = spot_arbitrage::execute(&engine, "mempool-1", &spot).await.err().unwrap();
+        assert!(error.contains("待確認交易") && error.contains("放棄 DEX 腿"), "{}", error);
+        assert_eq!(engine.spot_arbitrage.inventory(), before);
+
+        // 跌幅在 30 bps 滑點容忍度內：按 pending 報價成交
+        *pending_ratio.lock().unwrap() = 0.999;
+        let outcome = spot_arbitrage::execute(&engine, "mempool-2", &spot).await.unwrap();
+        let (dex_leg, cex_leg) = (&outcome.orders[0], &outcome.orders[1]);
+        assert_eq!((dex_leg.exchange.as_str(), cex_leg.exchange.as_str()), ("uniswap_v3", "binance"));
+        assert!((dex_leg.filled_quantity - latest_out * 0.999).abs() < 1e-9);
+        assert!((cex_leg.filled_quantity - dex_leg.filled_quantity).abs() < 1e-9);
+        let _ = std::fs::remove_file(path);
+    }
+
+    // 本地 WebSocket 節點：應答 eth_subscribe 後推送待確認交易（完整對象或只有哈希），哈希按 eth_getTransactionByHash 查詢
+    async fn pending_tx_node(pushed: Vec<serde_json::Value>, by_hash: Vec<web3::types::Transaction>) -> String {
+        use axum::extract::ws::{Message, WebSocketUpgrade};
+        let app = axum::Router::new().route(
+            "/",
+            axum::routing::get(move |upgrade: WebSocketUpgrade| {
+                let (pushed, by_hash) = (pushed.clone(), by_hash.clone());
+                async move {
+                    upgrade.on_upgrade(move |mut socket| async move {
+                        let reply = |id: &serde_json::Value, result: serde_json::Value| {
+                            Message::Text(serde_json::json!({"jsonrpc": "2.0", "id": id, "result": result}).to_string())
+                        };
+                        let Some(Ok(Message::Text(subscribe))) = socket.recv().await else { return };
+                        let subscribe: serde_json::Value = serde_json::from_str(&subscribe).unwrap();
+                        assert_eq!(subscribe["params"], serde_json::json!(["newPendingTransactions", true]));
+                        let _ = socket.send(reply(&subscribe["id"], serde_json::json!("0x1"))).await;
+                        // 等客戶端登記訂閱後再推送
+                        tokio::time::sleep(Duration::from_millis(200)).await;
+                        for result in pushed {
+                            let notification = serde_json::json!({"jsonrpc": "2.0", "method": "eth_subscription", "params": {"subscription": "0x1", "result": result}});
+                            let _ = socket.send(Message::Text(notification.to_string())).await;
+                        }
+                        while let Some(Ok(Message::Text(request))) = socket.recv().await {
+                            let request: serde_json::Value = serde_json::from_str(&request).unwrap();
+                            let hash: web3::types::H256 = serde_json::from_value(request["params"][0].clone()).unwrap();
+                            let tx = by_hash.iter().find(|tx| tx.hash == hash);
+                            let _ = socket.send(reply(&request["id"], serde_json::json!(tx))).await;
+                        }
+                    })
+                }
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await });
+        format!("ws://{}/", address)
+    }
+}
@@ -0,0 +1,579 @@
+use crate::config::EndpointConfig;
+use axum::extract::ws::{Message, WebSocket, WebSocketUpgrade};
+use axum::extract::{Path, Query, Request, State};
+use axum::http::{header, HeaderValue, StatusCode};
+use axum::middleware::{self, Next};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::sync::broadcast;
+use tracing::{info, warn};
+
+// 同一交易所上 client_order_id 重複，各交易所處理器轉換為各自的錯誤碼
+const DUPLICATE_CLIENT_ORDER_ID: &str = "duplicate client order id";
+
+/// 可腳本化的場景；運行中可經 [`MockExchange::script`] 修改，計數類字段每觸發一次減一。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Scenario {
+    // 交易所 -> 交易對 -> 當期資金費率；未列出的使用 default_funding_rate
+    pub funding_rates: BTreeMap<String, BTreeMap<String, f64>>,
+    pub default_funding_rate: f64,
+    // 接下來的 N 筆訂單被拒
+    pub reject_orders: u32,
+    // 接下來的 N 個 REST 請求返回 429
+    pub throttle_requests: u32,
+    pub retry_after_secs: u64,
+    // 接下來的 N 個 REST 請求返回 503
+    pub server_errors: u32,
+    // 接下來的 N 筆訂單成交後返回 503，模擬響應在途中丟失
+    pub lost_order_responses: u32,
+    // 市價單的成交比例
+    pub fill_ratio: f64,
+    // 每個私有 WebSocket 連接收到 N 條客戶端消息後由服務端斷開
+    pub disconnect_after_messages: Option<u32>,
+    // 服務器時間相對本機的偏差
+    pub clock_skew_ms: i64,
+}
+
+impl Default for Scenario {
+    fn default() -> Self {
+        Self {
+            funding_rates: BTreeMap::new(),
+            default_funding_rate: 0.0001,
+            reject_orders: 0,
+            throttle_requests: 0,
+            retry_after_secs: 1,
+            server_errors: 0,
+            lost_order_responses: 0,
+            fill_ratio: 1.0,
+            disconnect_after_messages: None,
+            clock_skew_ms: 0,
+        }
+    }
+}
+
+/// 模擬交易所收到的一筆訂單。
+#[derive(Debug, Clone, Serialize)]
+pub struct MockOrder {
+    pub exchange: String,
+    pub order_id: u64,
+    pub client_order_id: Option<String>,
+    pub symbol: String,
+    pub side: String,
+    pub quantity: f64,
+    pub filled_quantity: f64,
+}
+
+struct Shared {
+    scenario: Mutex<Scenario>,
+    // "METHOD path"，按到達順序
+    requests: Mutex<Vec<String>>,
+    orders: Mutex<Vec<MockOrder>>,
+    listen_keys: Mutex<HashSet<String>>,
+    next_id: AtomicU64,
+    // (交易所, 推送內容)：成交後推送到該交易所的全部私有連接
+    updates: broadcast::Sender<(String, String)>,
+    disconnect: broadcast::Sender<()>,
+}
+
+type ApiState = Arc<Shared>;
+
+/// 運行中的模擬交易所；drop 時停止服務。
+pub struct MockExchange {
+    addr: SocketAddr,
+    shared: Arc<Shared>,
+    server: tokio::task::JoinHandle<()>,
+}
+
+impl MockExchange {
+    /// 在 `addr`（如 `127.0.0.1:0`）上啟動；需在 tokio 運行時中調用。
+    pub async fn start(addr: &str, scenario: Scenario) -> Result<Self, String> {
+        let shared = Arc::new(Shared {
+            scenario: Mutex::new(scenario),
+            requests: Mutex::new(Vec::new()),
+            orders: Mutex::new(Vec::new()),
+            listen_keys: Mutex::new(HashSet::new()),
+            next_id: AtomicU64::new(1),
+            updates: broadcast::channel(1024).0,
+            disconnect: broadcast::channel(16).0,
+        });
+        let app = Router::new()
+            .route("/fapi/v1/premiumIndex", get(binance_premium_index))
+            .route("/fapi/v1/time", get(binance_time))
+            .route("/fapi/v1/order", post(binance_order).get(binance_order_query).delete(binance_cancel))
+            .route(
+                "/fapi/v1/listenKey",
+                post(binance_listen_key).put(binance_listen_key).delete(binance_listen_key),
+            )
+            .route("/ws/:listen_key", get(binance_stream))
+            .route("/v5/market/tickers", get(bybit_tickers))
+            .route("/v5/market/time", get(bybit_time))
+            .route("/v5/order/create", post(bybit_order))
+            .route("/v5/order/realtime", get(bybit_order_query))
+            .route("/v5/order/cancel", post(bybit_cancel))
+            .route("/v5/private", get(bybit_stream))
+            .route("/api/v5/public/funding-rate", get(okx_funding_rate))
+            .route("/api/v5/public/time", get(okx_time))
+            .route("/api/v5/trade/order", post(okx_order).get(okx_order_query))
+            .route("/api/v5/trade/cancel-order", post(okx_cancel))
+            .route("/ws/v5/private", get(okx_stream))
+            .layer(middleware::from_fn_with_state(Arc::clone(&shared), record_and_throttle))
+            .with_state(Arc::clone(&shared));
+        let listener = tokio::net::TcpListener::bind(addr)
+            .await
+            .map_err(|e| format!("模擬交易所監聽 {} 失敗: {}", addr, e))?;
+        let addr = listener.local_addr().map_err(|e| e.to_string())?;
+        let server = tokio::spawn(async move {
+            if let Err(e) = axum::serve(listener, app).await {
+                warn!(error = %e, "模擬交易所已停止");
+            }
+        });
+        info!(%addr, "模擬交易所已啟動");
+        Ok(Self { addr, shared, server })
+    }
+
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// 指向本模擬交易所的連接器端點配置，填入 `exchanges.<name>.endpoint`。
+    pub fn endpoint(&self) -> EndpointConfig {
+        EndpointConfig {
+            rest_url: format!("http://{}", self.addr),
+            ws_url: format!("ws://{}", self.addr),
+            ..EndpointConfig::default()
+        }
+    }
+
+    pub fn script(&self, update: impl FnOnce(&mut Scenario)) {
+        update(&mut self.shared.scenario.lock().unwrap());
+    }
+
+    /// 立即斷開全部私有 WebSocket 連接。
+    pub fn disconnect_streams(&self) {
+        let _ = self.shared.disconnect.send(());
+    }
+
+    pub fn requests(&self) -> Vec<String> {
+        self.shared.requests.lock().unwrap().clone()
+    }
+
+    pub fn orders(&self) -> Vec<MockOrder> {
+        self.shared.orders.lock().unwrap().clone()
+    }
+}
+
+impl Drop for MockExchange {
+    fn drop(&mut self) {
+        self.server.abort();
+    }
+}
+
+// 記錄請求；場景要求限頻或服務端錯誤時 REST 請求直接返回 429 / 503（WebSocket 握手不受影響）
+async fn record_and_throttle(State(shared): State<ApiState>, request: Request, next: Next) -> Response {
+    shared.requests.lock().unwrap().push(format!("{} {}", request.method(), request.uri().path()));
+    if !request.headers().contains_key(header::UPGRADE) {
+        let mut scenario = shared.scenario.lock().unwrap();
+        if scenario.throttle_requests > 0 {
+            scenario.throttle_requests -= 1;
+            let mut response = (StatusCode::TOO_MANY_REQUESTS, Json(json!({"code": -1003, "msg": "Too many requests"})))
+                .into_response();
+            response.headers_mut().insert(header::RETRY_AFTER, HeaderValue::from(scenario.retry_after_secs));
+            return response;
+        }
+        if scenario.server_errors > 0 {
+            scenario.server_errors -= 1;
+            return StatusCode::SERVICE_UNAVAILABLE.into_response();
+        }
+    }
+    next.run(request).await
+}
+
+impl Shared {
+    fn now_ms(&self) -> i64 {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_millis() as i64).unwrap_or_default();
+        now + self.scenario.lock().unwrap().clock_skew_ms
+    }
+
+    fn funding_rate(&self, exchange: &str, symbol: &str) -> f64 {
+        let scenario = self.scenario.lock().unwrap();
+        scenario
+            .funding_rates
+            .get(exchange)
+            .and_then(|rates| rates.get(symbol))
+            .copied()
+            .unwrap_or(scenario.default_funding_rate)
+    }
+
+    // 按場景拒單或按成交比例成交，並向該交易所的私有連接推送訂單更新
+    fn place(&self, exchange: &str, symbol: &str, side: &str, quantity: f64, client_order_id: &str) -> Result<MockOrder, String> {
+        let fill_ratio = {
+            let mut scenario = self.scenario.lock().unwrap();
+            if scenario.reject_orders > 0 {
+                scenario.reject_orders -= 1;
+                return Err("order rejected by mock scenario".to_string());
+            }
+            scenario.fill_ratio
+        };
+        if quantity.is_nan() || quantity <= 0.0 {
+            return Err("invalid quantity".to_string());
+        }
+        if self.order(exchange, "", client_order_id).is_some() {
+            return Err(DUPLICATE_CLIENT_ORDER_ID.to_string());
+        }
+        let order = MockOrder {
+            exchange: exchange.to_string(),
+            order_id: self.next_id.fetch_add(1, Ordering::Relaxed),
+            client_order_id: Some(client_order_id.to_string()).filter(|id| !id.is_empty()),
+            symbol: symbol.to_string(),
+            side: side.to_lowercase(),
+            quantity,
+            filled_quantity: quantity * fill_ratio.clamp(0.0, 1.0),
+        };
+        self.orders.lock().unwrap().push(order.clone());
+        let partial = order.filled_quantity < order.quantity;
+        let update = match exchange {
+            "binance" => json!({
+                "e": "ORDER_TRADE_UPDATE",
+                "E": self.now_ms(),
+                "o": {
+                    "s": order.symbol, "S": order.side.to_uppercase(), "i": order.order_id,
+                    "X": if partial { "PARTIALLY_FILLED" } else { "FILLED" },
+                    "q": order.quantity.to_string(), "z": order.filled_quantity.to_string(),
+                },
+            }),
+            "bybit" => json!({"topic": "order", "data": [bybit_order_view(&order)]}),
+            _ => json!({"arg": {"channel": "orders", "instType": "SWAP"}, "data": [okx_order_view(&order)]}),
+        };
+        let _ = self.updates.send((exchange.to_string(), update.to_string()));
+        Ok(order)
+    }
+
+    // 場景要求丟失下單響應時返回 true；訂單已記錄並成交
+    fn lose_response(&self) -> bool {
+        let mut scenario = self.scenario.lock().unwrap();
+        if scenario.lost_order_responses == 0 {
+            return false;
+        }
+        scenario.lost_order_responses -= 1;
+        true
+    }
+
+    // 按交易所訂單號或客戶端訂單號查詢
+    fn order(&self, exchange: &str, order_id: &str, client_order_id: &str) -> Option<MockOrder> {
+        self.orders
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|order| {
+                order.exchange == exchange
+                    && (order.order_id.to_string() == order_id
+                        || (!client_order_id.is_empty() && order.client_order_id.as_deref() == Some(client_order_id)))
+            })
+            .cloned()
+    }
+}
+
+fn param<'a>(params: &'a HashMap<String, String>, name: &str) -> &'a str {
+    params.get(name).map(String::as_str).unwrap_or_default()
+}
+
+fn quantity(value: &Value) -> f64 {
+    value.as_str().and_then(|text| text.parse().ok()).or_else(|| value.as_f64()).unwrap_or(f64::NAN)
+}
+
+// ---- Binance USDⓈ-M 合約 ----
+
+async fn binance_premium_index(State(shared): State<ApiState>, Query(params): Query<HashMap<String, String>>) -> Response {
+    let symbol = param(&params, "symbol");
+    Json(json!({
+        "symbol": symbol,
+        "lastFundingRate": shared.funding_rate("binance", symbol).to_string(),
+        "time": shared.now_ms(),
+    }))
+    .into_response()
+}
+
+async fn binance_time(State(shared): State<ApiState>) -> Response {
+    Json(json!({"serverTime": shared.now_ms()})).into_response()
+}
+
+async fn binance_order(State(shared): State<ApiState>, Query(params): Query<HashMap<String, String>>) -> Response {
+    let quantity = param(&params, "quantity").parse().unwrap_or(f64::NAN);
+    let client_order_id = param(&params, "newClientOrderId");
+    match shared.place("binance", param(&params, "symbol"), param(&params, "side"), quantity, client_order_id) {
+        Ok(_) if shared.lose_response() => StatusCode::SERVICE_UNAVAILABLE.into_response(),
+        Ok(order) => Json(binance_order_view(&order)).into_response(),
+        Err(msg) if msg == DUPLICATE_CLIENT_ORDER_ID => {
+            (StatusCode::BAD_REQUEST, Json(json!({"code": -4116, "msg": "ClientOrderId is duplicated."}))).into_response()
+        }
+        Err(msg) => (StatusCode::BAD_REQUEST, Json(json!({"code": -2010, "msg": msg}))).into_response(),
+    }
+}
+
+fn binance_order_view(order: &MockOrder) -> Value {
+    json!({
+        "orderId": order.order_id,
+        "clientOrderId": order.client_order_id,
+        "symbol": order.symbol,
+        "status": if order.filled_quantity < order.quantity { "PARTIALLY_FILLED" } else { "FILLED" },
+        "origQty": order.quantity.to_string(),
+        "executedQty": order.filled_quantity.to_string(),
+    })
+}
+
+async fn binance_order_query(State(shared): State<ApiState>, Query(params): Query<HashMap<String, String>>) -> Response {
+    match shared.order("binance", param(&params, "orderId"), param(&params, "origClientOrderId")) {
+        Some(order) => Json(binance_order_view(&order)).into_response(),
+        None => (StatusCode::BAD_REQUEST, Json(json!({"code": -2013, "msg": "Order does not exist."}))).into_response(),
+    }
+}
+
+// 市價單即時完結，撤單總是失敗
+async fn binance_cancel() -> Response {
+    (StatusCode::BAD_REQUEST, Json(json!({"code": -2011, "msg": "Unknown order sent."}))).into_response()
+}
+
+async fn binance_listen_key(
+    State(shared): State<ApiState>,
+    method: axum::http::Method,
+    Query(params): Query<HashMap<String, String>>,
+) -> Response {
+    let mut listen_keys = shared.listen_keys.lock().unwrap();
+    let listen_key = param(&params, "listenKey").to_string();
+    match method {
+        axum::http::Method::POST => {
+            let listen_key = format!("mock{}", shared.next_id.fetch_add(1, Ordering::Relaxed));
+            listen_keys.insert(listen_key.clone());
+            Json(json!({"listenKey": listen_key})).into_response()
+        }
+        _ if !listen_keys.contains(&listen_key) => {
+            (StatusCode::BAD_REQUEST, Json(json!({"code": -1125, "msg": "This listenKey does not exist."}))).into_response()
+        }
+        axum::http::Method::DELETE => {
+            listen_keys.remove(&listen_key);
+            Json(json!({})).into_response()
+        }
+        _ => Json(json!({"listenKey": listen_key})).into_response(),
+    }
+}
+
+async fn binance_stream(
+    State(shared): State<ApiState>,
+    Path(listen_key): Path<String>,
+    upgrade: WebSocketUpgrade,
+) -> Response {
+    if !shared.listen_keys.lock().unwrap().contains(&listen_key) {
+        return (StatusCode::BAD_REQUEST, "unknown listen key").into_response();
+    }
+    upgrade.on_upgrade(move |socket| stream(shared, socket, "binance"))
+}
+
+// ---- Bybit v5 linear ----
+
+async fn bybit_tickers(State(shared): State<ApiState>, Query(params): Query<HashMap<String, String>>) -> Response {
+    let symbol = param(&params, "symbol");
+    Json(json!({
+        "retCode": 0,
+        "retMsg": "OK",
+        "result": {"category": "linear", "list": [{
+            "symbol": symbol,
+            "fundingRate": shared.funding_rate("bybit", symbol).to_string(),
+        }]},
+    }))
+    .into_response()
+}
+
+async fn bybit_time(State(shared): State<ApiState>) -> Response {
+    let now_ms = shared.now_ms();
+    Json(json!({
+        "retCode": 0,
+        "retMsg": "OK",
+        "result": {"timeSecond": (now_ms / 1_000).to_string(), "timeNano": (now_ms as i128 * 1_000_000).to_string()},
+    }))
+    .into_response()
+}
+
+fn bybit_order_view(order: &MockOrder) -> Value {
+    json!({
+        "orderId": order.order_id.to_string(),
+        "orderLinkId": order.client_order_id.clone().unwrap_or_default(),
+        "symbol": order.symbol,
+        "side": if order.side == "buy" { "Buy" } else { "Sell" },
+        "orderStatus": if order.filled_quantity < order.quantity { "PartiallyFilled" } else { "Filled" },
+        "qty": order.quantity.to_string(),
+        "cumExecQty": order.filled_quantity.to_string(),
+    })
+}
+
+async fn bybit_order(State(shared): State<ApiState>, Json(body): Json<Value>) -> Response {
+    let side = body["side"].as_str().unwrap_or_default();
+    let client_order_id = body["orderLinkId"].as_str().unwrap_or_default();
+    match shared.place("bybit", body["symbol"].as_str().unwrap_or_default(), side, quantity(&body["qty"]), client_order_id) {
+        Ok(_) if shared.lose_response() => StatusCode::SERVICE_UNAVAILABLE.into_response(),
+        Ok(order) => Json(json!({
+            "retCode": 0,
+            "retMsg": "OK",
+            "result": {"orderId": order.order_id.to_string()},
+        }))
+        .into_response(),
+        Err(msg) if msg == DUPLICATE_CLIENT_ORDER_ID => {
+            Json(json!({"retCode": 110072, "retMsg": "OrderLinkedID is duplicate", "result": {}})).into_response()
+        }
+        Err(msg) => Json(json!({"retCode": 110007, "retMsg": msg, "result": {}})).into_response(),
+    }
+}
+
+async fn bybit_order_query(State(shared): State<ApiState>, Query(params): Query<HashMap<String, String>>) -> Response {
+    let list: Vec<Value> = shared.order("bybit", param(&params, "orderId"), param(&params, "orderLinkId")).iter().map(bybit_order_view).collect();
+    Json(json!({"retCode": 0, "retMsg": "OK", "result": {"list": list}})).into_response()
+}
+
+async fn bybit_cancel() -> Response {
+    Json(json!({"retCode": 110001, "retMsg": "order not exists or too late to cancel", "result": {}})).into_response()
+}
+
+async fn bybit_stream(State(shared): State<ApiState>, upgrade: WebSocketUpgrade) -> Response {
+    upgrade.on_upgrade(move |socket| stream(shared, socket, "bybit"))
+}
+
+// ---- OKX v5 永續 ----
+
+async fn okx_funding_rate(State(shared): State<ApiState>, Query(params): Query<HashMap<String, String>>) -> Response {
+    let inst_id = param(&params, "instId");
+    let symbol = inst_id.trim_end_matches("-SWAP").replace('-', "");
+    Json(json!({
+        "code": "0",
+        "msg": "",
+        "data": [{"instId": inst_id, "fundingRate": shared.funding_rate("okx", &symbol).to_string()}],
+    }))
+    .into_response()
+}
+
+async fn okx_time(State(shared): State<ApiState>) -> Response {
+    Json(json!({"code": "0", "msg": "", "data": [{"ts": shared.now_ms().to_string()}]})).into_response()
+}
+
+fn okx_order_view(order: &MockOrder) -> Value {
+    json!({
+        "ordId": order.order_id.to_string(),
+        "clOrdId": order.client_order_id.clone().unwrap_or_default(),
+        "instId": format!("{}-SWAP", okx_pair(&order.symbol)),
+        "side": order.side,
+        "state": if order.filled_quantity < order.quantity { "partially_filled" } else { "filled" },
+        "sz": order.quantity.to_string(),
+        "accFillSz": order.filled_quantity.to_string(),
+    })
+}
+
+// 內部統一記錄無分隔符的交易對，如 BTCUSDT -> BTC-USDT
+fn okx_pair(symbol: &str) -> String {
+    crate::market_data::split_symbol(symbol)
+        .map(|(base, quote)| format!("{}-{}", base, quote))
+        .unwrap_or_else(|| symbol.to_string())
+}
+
+async fn okx_order(State(shared): State<ApiState>, Json(body): Json<Value>) -> Response {
+    let symbol = body["instId"].as_str().unwrap_or_default().trim_end_matches("-SWAP").replace('-', "");
+    let side = body["side"].as_str().unwrap_or_default();
+    match shared.place("okx", &symbol, side, quantity(&body["sz"]), body["clOrdId"].as_str().unwrap_or_default()) {
+        Ok(_) if shared.lose_response() => StatusCode::SERVICE_UNAVAILABLE.into_response(),
+        Ok(order) => Json(json!({
+            "code": "0",
+            "msg": "",
+            "data": [{"ordId": order.order_id.to_string(), "sCode": "0", "sMsg": ""}],
+        }))
+        .into_response(),
+        Err(msg) => Json(json!({
+            "code": "1",
+            "msg": "Operation failed.",
+            "data": [{"ordId": "", "sCode": if msg == DUPLICATE_CLIENT_ORDER_ID { "51016" } else { "51008" }, "sMsg": msg}],
+        }))
+        .into_response(),
+    }
+}
+
+async fn okx_order_query(State(shared): State<ApiState>, Query(params): Query<HashMap<String, String>>) -> Response {
+    match shared.order("okx", param(&params, "ordId"), param(&params, "clOrdId")) {
+        Some(order) => Json(json!({"code": "0", "msg": "", "data": [okx_order_view(&order)]})).into_response(),
+        None => Json(json!({
+            "code": "51603",
+            "msg": "",
+            "data": [{"sCode": "51603", "sMsg": "Order does not exist"}],
+        }))
+        .into_response(),
+    }
+}
+
+async fn okx_cancel(State(shared): State<ApiState>, Json(body): Json<Value>) -> Response {
+    let code = match shared.order("okx", "", body["clOrdId"].as_str().unwrap_or_default()) {
+        Some(_) => "51402",
+        None => "51400",
+    };
+    Json(json!({"code": "1", "msg": "", "data": [{"clOrdId": body["clOrdId"], "sCode": code, "sMsg": "Cancellation failed"}]}))
+        .into_response()
+}
+
+async fn okx_stream(State(shared): State<ApiState>, upgrade: WebSocketUpgrade) -> Response {
+    upgrade.on_upgrade(move |socket| stream(shared, socket, "okx"))
+}
+
+// ---- 私有數據流 ----
+
+// 響應登錄、訂閱與應用層心跳，轉發本交易所的訂單推送；按場景或 disconnect_streams 斷開
+async fn stream(shared: Arc<Shared>, mut socket: WebSocket, exchange: &'static str) {
+    let mut updates = shared.updates.subscribe();
+    let mut disconnect = shared.disconnect.subscribe();
+    let mut received = 0u32;
+    loop {
+        tokio::select! {
+            message = socket.recv() => {
+                let Some(Ok(message)) = message else { return };
+                received += 1;
+                let reply = match (&message, exchange) {
+                    (Message::Text(text), "okx") if text == "ping" => Some("pong".to_string()),
+                    (Message::Text(text), _) => serde_json::from_str::<Value>(text).ok().map(|request| {
+                        match (exchange, request["op"].as_str().unwrap_or_default()) {
+                            ("bybit", "ping") => json!({"op": "pong"}).to_string(),
+                            ("bybit", op) => json!({"op": op, "success": true}).to_string(),
+                            (_, "login") => json!({"event": "login", "code": "0", "msg": ""}).to_string(),
+                            (_, op) => json!({"event": op, "arg": request["args"][0]}).to_string(),
+                        }
+                    }),
+                    _ => None,
+                };
+                if let Some(reply) = reply {
+                    if socket.send(Message::Text(reply)).await.is_err() {
+                        return;
+                    }
+                }
+                let limit = shared.scenario.lock().unwrap().disconnect_after_messages;
+                if limit.is_some_and(|limit| received >= limit) {
+                    let _ = socket.send(Message::Close(None)).await;
+                    return;
+                }
+            }
+            update = updates.recv() => match update {
+                Ok((venue, payload)) if venue == exchange => {
+                    if socket.send(Message::Text(payload)).await.is_err() {
+                        return;
+                    }
+                }
+                Ok(_) | Err(broadcast::error::RecvError::Lagged(_)) => {}
+                Err(broadcast::error::RecvError::Closed) => return,
+            },
+            _ = disconnect.recv() => {
+                let _ = socket.send(Message::Close(None)).await;
+                return;
+            }
+        }
+    }
+}
@@ -0,0 +1,153 @@
+use super::deterministic_sim::{build, request};
+use super::environment::FixedVars;
+use super::mock_exchange::{MockExchange, Scenario};
+use super::*;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+pub(crate) const EXCHANGES: [&str; 3] = ["binance", "bybit", "okx"];
+
+// 三個連接器都指向同一個模擬交易所
+pub(crate) async fn engine(name: &str, scenario: Scenario) -> (MockExchange, ExecutionEngine, PathBuf) {
+    engine_with(name, scenario, Environment::system()).await
+}
+
+pub(crate) async fn engine_with(name: &str, scenario: Scenario, env: Environment) -> (MockExchange, ExecutionEngine, PathBuf) {
+    let mock = MockExchange::start("127.0.0.1:0", scenario).await.unwrap();
+    let mut config = config::EngineConfig::default();
+    for exchange in EXCHANGES {
+        let settings = config.exchanges.entry(exchange.to_string()).or_default();
+        settings.endpoint = Some(mock.endpoint());
+        settings.listen_key_keepalive_secs = (exchange == "binance").then_some(1800);
+    }
+    let (engine, path) = build(&format!("mock-{}", name), config, env);
+    (mock, engine, path)
+}
+
+#[tokio::test]
+async fn funding_rates_use_each_exchange_dialect() {
+    let mut scenario = Scenario::default();
+    for (exchange, rate) in EXCHANGES.iter().zip([0.0001, -0.0002, 0.0003]) {
+        scenario.funding_rates.entry(exchange.to_string()).or_default().insert("BTCUSDT".to_string(), rate);
+    }
+    let (mock, engine, path) = engine("funding", scenario).await;
+    for (exchange, rate) in EXCHANGES.iter().zip([0.0001, -0.0002, 0.0003]) {
+        assert_eq!(engine.get_funding_rate(exchange, "BTCUSDT").await.unwrap(), rate);
+    }
+    let requests = mock.requests();
+    for path in ["GET /fapi/v1/premiumIndex", "GET /v5/market/tickers", "GET /api/v5/public/funding-rate"] {
+        assert!(requests.iter().any(|request| request == path), "缺少請求 {}", path);
+    }
+    let _ = std::fs::remove_file(path);
+}
+
+#[tokio::test]
+async fn scripted_rejections_and_partial_fills() {
+    let (mock, engine, path) = engine("orders", Scenario { reject_orders: 1, ..Scenario::default() }).await;
+    let gas_quote = engine.default_chain().gas_optimizer.quote(5);
+    let rejected = engine.execute_flash_loan_arbitrage("sim", &request(10.0), 0.0005, &gas_quote, false, None).await;
+    assert!(rejected.err().unwrap().error.contains("rejected by mock scenario"));
+
+    mock.script(|scenario| scenario.fill_ratio = 0.5);
+    for secondary in ["bybit", "okx"] {
+        let request = ArbitrageRequest {
+            secondary_exchange: secondary.to_string(),
+            ..request(10.0)
+        };
+        let outcome = engine.execute_flash_loan_arbitrage("sim", &request, 0.0005, &gas_quote, false, None).await.unwrap();
+        assert_eq!(outcome.fill_ratio(), 0.5);
+        assert!(outcome.orders.iter().all(|order| order.status == "partially_filled"));
+    }
+    let orders = mock.orders();
+    assert!(orders.iter().any(|order| order.exchange == "okx" && order.side == "buy" && order.filled_quantity == 5.0));
+    assert!(orders.iter().any(|order| order.exchange == "bybit" && order.side == "buy"));
+    let _ = std::fs::remove_file(path);
+}
+
+#[tokio::test]
+async fn throttling_pauses_the_scheduler() {
+    let (_mock, engine, path) = engine("throttle", Scenario { throttle_requests: 1, ..Scenario::default() }).await;
+    let error = engine.get_funding_rate("bybit", "BTCUSDT").await.unwrap_err();
+    assert!(error.contains("429"), "{}", error);
+    assert_eq!(engine.exchanges["bybit"].scheduler.snapshot().throttled, 1);
+    // 其他交易所的配額不受影響
+    assert!(engine.get_funding_rate("okx", "BTCUSDT").await.is_ok());
+    let _ = std::fs::remove_file(path);
+}
+
+#[tokio::test]
+async fn private_streams_detect_disconnects() {
+    let scenario = Scenario {
+        disconnect_after_messages: Some(3),
+        ..Scenario::default()
+    };
+    let (mock, engine, path) = engine("streams", scenario).await;
+    let binance = &engine.exchanges["binance"];
+    let listen_key = binance.create_listen_key().await.unwrap();
+    binance.keepalive_listen_key(&listen_key).await.unwrap();
+    binance.open_stream(Some(&listen_key)).await.unwrap();
+    binance.ping().await.unwrap();
+    mock.disconnect_streams();
+    assert!(binance.ping().await.is_err());
+
+    // 登錄與訂閱各一條消息，第一次心跳後服務端按場景斷開
+    for exchange in ["bybit", "okx"] {
+        let connector = &engine.exchanges[exchange];
+        connector.open_stream(None).await.unwrap();
+        connector.ping().await.unwrap();
+        assert!(connector.ping().await.is_err(), "{} 應檢測到斷線", exchange);
+    }
+    binance.close_listen_key(&listen_key).await.unwrap();
+    assert!(binance.keepalive_listen_key(&listen_key).await.is_err());
+    let _ = std::fs::remove_file(path);
+}
+
+pub(crate) type RpcHandler = Arc<dyn Fn(&str, &[serde_json::Value]) -> Result<serde_json::Value, String> + Send + Sync>;
+
+// 本地 JSON-RPC 節點：按方法名與參數應答，handler 返回 Err 時以 JSON-RPC 錯誤（code 3）應答
+pub(crate) async fn rpc_node(handler: RpcHandler) -> String {
+    use axum::routing::post;
+    let app = axum::Router::new().route(
+        "/",
+        post(move |axum::Json(body): axum::Json<serde_json::Value>| async move {
+            let params = body["params"].as_array().cloned().unwrap_or_default();
+            let reply = match handler(body["method"].as_str().unwrap_or_default(), &params) {
+                Ok(result) => serde_json::json!({"jsonrpc": "2.0", "id": body["id"], "result": result}),
+                Err(message) => serde_json::json!({"jsonrpc": "2.0", "id": body["id"], "error": {"code": 3, "message": message}}),
+            };
+            axum::Json(reply)
+        }),
+    );
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await });
+    format!("http://{}/", address)
+}
+
+pub(crate) fn abi_hex(tokens: &[web3::ethabi::Token]) -> serde_json::Value {
+    serde_json::json!(format!("0x{}", hex::encode(web3::ethabi::encode(tokens))))
+}
+
+// 已上鏈且成功的回執
+pub(crate) fn mined_receipt(hash: &serde_json::Value) -> serde_json::Value {
+    serde_json::json!(web3::types::TransactionReceipt {
+        transaction_hash: serde_json::from_value(hash.clone()).unwrap(),
+        block_number: Some(16.into()),
+        block_hash: Some(web3::types::H256::from_low_u64_be(16)),
+        status: Some(1.into()),
+        gas_used: Some(50_000.into()),
+        effective_gas_price: Some(1_000_000_000u64.into()),
+        ..Default::default()
+    })
+}
+
+pub(crate) fn hot_wallets(url: &str) -> wallet::WalletManager {
+    let rpc = rpc::RpcTransport::connect("e2e", url, &config::RpcConfig::default()).unwrap();
+    let config = config::WalletConfig {
+        name: "hot".to_string(),
+        private_key_env: Some("HOT_KEY".to_string()),
+        ..Default::default()
+    };
+    let vars = FixedVars::new([("HOT_KEY", "4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318")]);
+    wallet::WalletManager::load(&[config], &rpc, 1, &vars).unwrap()
+}
@@ -0,0 +1,283 @@
+use super::config::{OracleConfig, OracleFeedConfig, OracleSource};
+use super::dex;
+use super::flash_loan::load_abi;
+use super::ExecutionEngine;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tracing::{debug, info, warn};
+use web3::ethabi::Token;
+use web3::types::{Address, U256};
+
+const AGGREGATOR_ABI: &str = r#"[
+    {"type":"function","name":"decimals","stateMutability":"view","inputs":[],"outputs":[{"name":"","type":"uint8"}]},
+    {"type":"function","name":"latestRoundData","stateMutability":"view","inputs":[],"outputs":[{"name":"roundId","type":"uint80"},{"name":"answer","type":"int256"},{"name":"startedAt","type":"uint256"},{"name":"updatedAt","type":"uint256"},{"name":"answeredInRound","type":"uint80"}]}
+]"#;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct OraclePrice {
+    pub asset: String,
+    pub source: OracleSource,
+    pub price_usd: f64,
+    // 喂價自身的更新時間（秒）
+    pub published_at: i64,
+    pub fetched_at_ms: i64,
+}
+
+/// 預言機喂價：後台按間隔刷新 Chainlink 與 Pyth 喂價，查詢時只使用未過期的讀數。
+pub struct OracleFeeds {
+    config: OracleConfig,
+    client: reqwest::Client,
+    // 喂價在配置中的序號 -> 最新讀數
+    latest: Mutex<BTreeMap<usize, OraclePrice>>,
+}
+
+impl OracleFeeds {
+    pub fn new(config: OracleConfig) -> Self {
+        Self {
+            config,
+            client: reqwest::Client::new(),
+            latest: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    pub fn max_deviation_bps(&self) -> f64 {
+        self.config.max_deviation_bps
+    }
+
+    pub fn snapshot(&self) -> Vec<OraclePrice> {
+        self.latest.lock().unwrap().values().cloned().collect()
+    }
+
+    // 資產的美元價格：同一資產多個未過期喂價時取中位數
+    pub fn price_usd(&self, asset: &str, now_ms: i64) -> Option<f64> {
+        let oldest = now_ms / 1000 - self.config.max_staleness_secs as i64;
+        let mut prices: Vec<f64> = self
+            .latest
+            .lock()
+            .unwrap()
+            .values()
+            .filter(|price| price.asset == asset && price.published_at >= oldest)
+            .map(|price| price.price_usd)
+            .collect();
+        if prices.is_empty() {
+            return None;
+        }
+        prices.sort_by(|a, b| a.total_cmp(b));
+        let middle = prices.len() / 2;
+        Some(if prices.len().is_multiple_of(2) { (prices[middle - 1] + prices[middle]) / 2.0 } else { prices[middle] })
+    }
+
+    // base 以 quote 計的價格；兩個資產都需要未過期的喂價，穩定幣同樣需要配置（如 USDT/USD）
+    pub fn pair_price(&self, base: &str, quote: &str, now_ms: i64) -> Option<f64> {
+        Some(self.price_usd(base, now_ms)? / self.price_usd(quote, now_ms)?)
+    }
+
+    pub(crate) fn record(&self, index: usize, price: OraclePrice) {
+        self.latest.lock().unwrap().insert(index, price);
+    }
+
+    // 讀取失敗時保留上一次讀數直至過期；喂價數據無效時立即停用上一次讀數
+    fn update_chainlink(&self, index: usize, feed: &OracleFeedConfig, result: Result<(f64, i64), FeedError>, now_ms: i64) {
+        match result {
+            Ok((price_usd, published_at)) => self.record(index, reading(feed, price_usd, published_at, now_ms)),
+            Err(FeedError::Unavailable(error)) => warn!(asset = %feed.asset, %error, "讀取 Chainlink 喂價失敗"),
+            Err(FeedError::Invalid(error)) => {
+                self.latest.lock().unwrap().remove(&index);
+                warn!(asset = %feed.asset, %error, "Chainlink 喂價無效，已停用該喂價的讀數");
+            }
+        }
+    }
+
+    pub(crate) async fn refresh(&self, engine: &ExecutionEngine) {
+        let now_ms = engine.env.now_ms();
+        let mut pyth = Vec::new();
+        for (index, feed) in self.config.feeds.iter().enumerate() {
+            match feed.source {
+                OracleSource::Chainlink => self.update_chainlink(index, feed, read_chainlink(engine, feed).await, now_ms),
+                OracleSource::Pyth => pyth.push((index, feed)),
+            }
+        }
+        if pyth.is_empty() {
+            return;
+        }
+        match self.read_pyth(&pyth).await {
+            Ok(prices) => {
+                for (index, feed) in pyth {
+                    match prices.get(&normalize_id(&feed.price_id)) {
+                        Some(&(price_usd, published_at)) => self.record(index, reading(feed, price_usd, published_at, now_ms)),
+                        None => warn!(asset = %feed.asset, price_id = %feed.price_id, "Hermes 未返回 Pyth 喂價"),
+                    }
+                }
+            }
+            Err(error) => warn!(%error, "讀取 Pyth 喂價失敗"),
+        }
+    }
+
+    // 一次請求取得全部 Pyth 喂價：價格 ID -> (美元價格, 發布時間)
+    async fn read_pyth(&self, feeds: &[(usize, &OracleFeedConfig)]) -> Result<BTreeMap<String, (f64, i64)>, String> {
+        let ids: Vec<(&str, &str)> = feeds.iter().map(|(_, feed)| ("ids[]", feed.price_id.as_str())).collect();
+        let response: Value = self
+            .client
+            .get(format!("{}/v2/updates/price/latest", self.config.hermes_url.trim_end_matches('/')))
+            .query(&ids)
+            .query(&[("parsed", "true")])
+            .send()
+            .await
+            .map_err(|e| format!("連接 Hermes 失敗: {}", e))?
+            .json()
+            .await
+            .map_err(|e| format!("Hermes 響應無效: {}", e))?;
+        let parsed = response["parsed"].as_array().ok_or("Hermes 響應缺少 parsed")?;
+        Ok(parsed
+            .iter()
+            .filter_map(|update| {
+                let price = &update["price"];
+                // 價格與精度分開給出：price × 10^expo
+                let mantissa = price["price"].as_str()?.parse::<f64>().ok()?;
+                let expo = price["expo"].as_i64()?;
+                let published_at = price["publish_time"].as_i64()?;
+                Some((normalize_id(update["id"].as_str()?), (mantissa * 10f64.powi(expo as i32), published_at)))
+            })
+            .filter(|(_, (price, _))| *price > 0.0)
+            .collect())
+    }
+}
+
+fn reading(feed: &OracleFeedConfig, price_usd: f64, published_at: i64, now_ms: i64) -> OraclePrice {
+    debug!(asset = %feed.asset, source = ?feed.source, price_usd, published_at, "預言機喂價已更新");
+    OraclePrice {
+        asset: feed.asset.clone(),
+        source: feed.source,
+        price_usd,
+        published_at,
+        fetched_at_ms: now_ms,
+    }
+}
+
+fn normalize_id(id: &str) -> String {
+    id.trim_start_matches("0x").to_lowercase()
+}
+
+#[derive(Debug)]
+enum FeedError {
+    // RPC 等讀取失敗
+    Unavailable(String),
+    // 聚合器返回的數據超出範圍或格式錯誤
+    Invalid(String),
+}
+
+impl From<String> for FeedError {
+    fn from(error: String) -> Self {
+        Self::Unavailable(error)
+    }
+}
+
+async fn read_chainlink(engine: &ExecutionEngine, feed: &OracleFeedConfig) -> Result<(f64, i64), FeedError> {
+    let chain = engine.chain(feed.chain.as_deref())?;
+    let web3 = chain.wallets.as_ref().ok_or_else(|| format!("鏈 {} 未配置 RPC", chain.name))?.web3();
+    let abi = load_abi(AGGREGATOR_ABI);
+    let aggregator = Address::from_str(&feed.address).map_err(|e| format!("聚合器地址無效 {}: {}", feed.address, e))?;
+    let decimals = dex::call_uint(web3, &abi, aggregator, "decimals", &[], None).await?;
+    let round = dex::call(web3, &abi, aggregator, "latestRoundData", &[], None).await?;
+    chainlink_reading(decimals, &round)
+}
+
+// latestRoundData 的 answer 為有符號數，非正數視為喂價異常；精度與更新時間超出範圍同樣視為無效
+fn chainlink_reading(decimals: U256, round: &[Token]) -> Result<(f64, i64), FeedError> {
+    let decimals = u8::try_from(decimals).map_err(|_| FeedError::Invalid(format!("喂價精度超出範圍: {}", decimals)))?;
+    let (Some(Token::Int(answer)), Some(Token::Uint(updated_at))) = (round.get(1).cloned(), round.get(3).cloned()) else {
+        return Err(FeedError::Invalid("latestRoundData 返回值格式錯誤".to_string()));
+    };
+    if answer.is_zero() || answer.bit(255) || answer > U256::from(u128::MAX) {
+        return Err(FeedError::Invalid(format!("喂價 answer 異常: {}", answer)));
+    }
+    let updated_at = i64::try_from(updated_at).map_err(|_| FeedError::Invalid(format!("喂價更新時間超出範圍: {}", updated_at)))?;
+    Ok((answer.as_u128() as f64 / 10f64.powi(decimals.into()), updated_at))
+}
+
+pub fn spawn(engine: Arc<ExecutionEngine>) {
+    if engine.oracle.config.feeds.is_empty() {
+        return;
+    }
+    info!(feeds = engine.oracle.config.feeds.len(), "預言機喂價刷新已啟動");
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_millis(engine.oracle.config.refresh_interval_ms));
+        loop {
+            interval.tick().await;
+            engine.oracle.refresh(&engine).await;
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bench::START_MS;
+    use crate::deterministic_sim::build;
+    use crate::{config, Environment};
+
+    fn round(answer: U256, updated_at: U256) -> Vec<Token> {
+        vec![Token::Uint(1.into()), Token::Int(answer), Token::Uint(updated_at), Token::Uint(updated_at), Token::Uint(1.into())]
+    }
+
+    // 精度或更新時間超出範圍時喂價無效，已記錄的讀數隨即停用；讀取失敗則保留讀數直至過期
+    #[test]
+    fn out_of_range_rounds_invalidate_the_feed() {
+        let valid = round(U256::from(6_000_000_000_000u64), U256::from(1_700_000_000u64));
+        assert_eq!(chainlink_reading(8.into(), &valid).unwrap(), (60_000.0, 1_700_000_000));
+        let error = chainlink_reading(U256::from(256), &valid).unwrap_err();
+        assert!(matches!(&error, FeedError::Invalid(message) if message == "喂價精度超出範圍: 256"), "{:?}", error);
+        let late = round(U256::from(6_000_000_000_000u64), U256::from(u64::MAX));
+        let error = chainlink_reading(8.into(), &late).unwrap_err();
+        assert!(matches!(&error, FeedError::Invalid(message) if message.starts_with("喂價更新時間超出範圍")), "{:?}", error);
+
+        let feeds = OracleFeeds::new(OracleConfig::default());
+        let feed = OracleFeedConfig {
+            asset: "BTC".to_string(),
+            source: OracleSource::Chainlink,
+            chain: None,
+            address: String::new(),
+            price_id: String::new(),
+        };
+        let now_ms = 1_700_000_000_000;
+        feeds.update_chainlink(0, &feed, chainlink_reading(8.into(), &valid), now_ms);
+        feeds.update_chainlink(0, &feed, Err(FeedError::Unavailable("連接超時".to_string())), now_ms);
+        assert_eq!(feeds.price_usd("BTC", now_ms), Some(60_000.0));
+        feeds.update_chainlink(0, &feed, chainlink_reading(8.into(), &late), now_ms);
+        assert_eq!(feeds.price_usd("BTC", now_ms), None);
+        assert!(feeds.snapshot().is_empty());
+    }
+
+    #[tokio::test]
+    async fn price_guard_refuses_prices_diverging_from_fresh_oracle_feeds() {
+        let (engine, path) = build("oracle", config::EngineConfig::default(), Environment::simulated(START_MS, 1));
+        let now_secs = START_MS / 1000;
+        let feed = |asset: &str, price_usd: f64, published_at: i64| OraclePrice {
+            asset: asset.to_string(),
+            source: config::OracleSource::Pyth,
+            price_usd,
+            published_at,
+            fetched_at_ms: START_MS,
+        };
+        engine.oracle.record(0, feed("USDT", 1.0, now_secs));
+        engine.oracle.record(1, feed("BTC", 60_000.0, now_secs));
+        let check = engine.price_guard.check(&engine, "BTCUSDT").unwrap();
+        assert!(check.passed, "{:?}", check.reasons);
+        assert_eq!(check.oracle_price, Some(60_000.0));
+
+        // 交易所報價整體偏離預言機 10%，各交易所之間一致也拒絕
+        engine.oracle.record(1, feed("BTC", 66_000.0, now_secs));
+        let error = engine.price_guard.verify(&engine, "BTCUSDT").unwrap_err();
+        assert!(error.contains("偏離預言機價格"), "{}", error);
+
+        // 過期喂價不再使用
+        engine.oracle.record(1, feed("BTC", 66_000.0, now_secs - 7_200));
+        let check = engine.price_guard.check(&engine, "BTCUSDT").unwrap();
+        assert!(check.passed && check.oracle_price.is_none(), "{:?}", check.reasons);
+        let _ = std::fs::remove_file(path);
+    }
+}
@@ -0,0 +1,148 @@
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use crossbeam_queue::SegQueue;
+use dashmap::mapref::entry::Entry;
+use dashmap::DashMap;
+
+// OKX clOrdId 最長 32 位且只允許字母數字，Binance / Bybit 上限 36 位，三家統一按 OKX 的限制生成
+const ID_LEN: usize = 32;
+// 保留歸屬關係的最近訂單數
+const CAPACITY: usize = 100_000;
+
+// client_order_id 對應的策略、執行與腿
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct OrderTag {
+    pub strategy_id: String,
+    pub execution_id: String,
+    pub leg: String,
+}
+
+/// 由 strategy_id、execution_id 與腿名確定性地生成 client_order_id（SHA-256 的前 32 位十六進制）。
+/// 同一筆訂單的每次重試都帶同一 ID，交易所按 ID 去重，不會重複下單。
+pub fn client_order_id(strategy_id: &str, execution_id: &str, leg: &str) -> String {
+    let digest = Sha256::digest(format!("{}\n{}\n{}", strategy_id, execution_id, leg).as_bytes());
+    hex::encode(digest)[..ID_LEN].to_string()
+}
+
+/// 已下發訂單的 client_order_id -> 執行歸屬。用戶數據流的成交按此關聯到執行；
+/// 分片執行中同一條腿多次下單時依次加上 #1、#2 … 區分，ID 仍由請求內容確定。
+/// 歸屬表按鍵分片加鎖，登記順序放在無鎖隊列中，各連接並發下單互不阻塞。
+pub struct OrderIds {
+    tags: DashMap<String, OrderTag>,
+    // 登記順序，超出容量時淘汰最早的
+    order: SegQueue<String>,
+}
+
+impl OrderIds {
+    pub fn new() -> Self {
+        Self {
+            tags: DashMap::new(),
+            order: SegQueue::new(),
+        }
+    }
+
+    // 為一筆新訂單分配 ID：該執行的這條腿第 n 次下單時以 "腿名#n" 參與計算
+    pub fn assign(&self, strategy_id: &str, execution_id: &str, leg: &str) -> String {
+        let tag = OrderTag {
+            strategy_id: strategy_id.to_string(),
+            execution_id: execution_id.to_string(),
+            leg: leg.to_string(),
+        };
+        for n in 0.. {
+            let id = match n {
+                0 => client_order_id(strategy_id, execution_id, leg),
+                n => client_order_id(strategy_id, execution_id, &format!("{}#{}", leg, n)),
+            };
+            // 佔位與檢查在同一分片鎖內完成，並發分配不會得到同一 ID
+            if let Entry::Vacant(entry) = self.tags.entry(id.clone()) {
+                entry.insert(tag);
+                self.record(id.clone());
+                return id;
+            }
+        }
+        unreachable!("序號空間足夠")
+    }
+
+    // 登記已有的 ID（如從執行預寫日誌恢復的腿）
+    pub fn register(&self, client_order_id: &str, tag: OrderTag) {
+        if let Entry::Vacant(entry) = self.tags.entry(client_order_id.to_string()) {
+            entry.insert(tag);
+            self.record(client_order_id.to_string());
+        }
+    }
+
+    pub fn lookup(&self, client_order_id: &str) -> Option<OrderTag> {
+        self.tags.get(client_order_id).map(|tag| tag.clone())
+    }
+
+    fn record(&self, id: String) {
+        self.order.push(id);
+        while self.order.len() > CAPACITY {
+            if let Some(evicted) = self.order.pop() {
+                self.tags.remove(&evicted);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::deterministic_sim::request;
+    use crate::mock_exchange::Scenario;
+    use crate::mock_exchange_e2e::{engine, EXCHANGES};
+    use crate::{events, user_stream};
+
+    #[tokio::test]
+    async fn client_order_ids_are_deterministic_and_deduplicated() {
+        let (mock, engine, path) = engine("client-ids", Scenario::default()).await;
+        let gas_quote = engine.default_chain().gas_optimizer.quote(5);
+        engine.execute_flash_loan_arbitrage("e1", &request(10.0), 0.0005, &gas_quote, false, None).await.unwrap();
+        // 同一執行再下一片：腿名相同，ID 按序號區分
+        engine.execute_flash_loan_arbitrage("e1", &request(10.0), 0.0005, &gas_quote, false, None).await.unwrap();
+        let ids: Vec<String> = mock.orders().into_iter().filter_map(|order| order.client_order_id).collect();
+        assert_eq!(
+            ids,
+            [
+                client_order_id("sim", "e1", "short"),
+                client_order_id("sim", "e1", "long"),
+                client_order_id("sim", "e1", "short#1"),
+                client_order_id("sim", "e1", "long#1"),
+            ]
+        );
+        assert!(ids.iter().all(|id| id.len() == 32 && id.chars().all(|c| c.is_ascii_alphanumeric())));
+        let tag = engine.order_ids.lookup(&ids[2]).unwrap();
+        assert_eq!((tag.strategy_id.as_str(), tag.execution_id.as_str(), tag.leg.as_str()), ("sim", "e1", "short"));
+
+        // 交易所按 client_order_id 去重：重複提交沿用已有訂單的成交
+        mock.script(|scenario| scenario.fill_ratio = 0.5);
+        for exchange in EXCHANGES {
+            let id = client_order_id("sim", "e2", exchange);
+            assert_eq!(engine.exchanges[exchange].submit_order("BTCUSDT", "buy", 4.0, &id).await.unwrap(), 0.5);
+            assert_eq!(engine.exchanges[exchange].submit_order("BTCUSDT", "buy", 4.0, &id).await.unwrap(), 0.5);
+        }
+        assert_eq!(mock.orders().len(), 7);
+
+        // 用戶數據流的成交按 ID 歸屬到執行
+        user_stream::ingest(
+            &engine,
+            "binance",
+            user_stream::UserEvent::Order {
+                order_id: "1".to_string(),
+                client_order_id: ids[0].clone(),
+                symbol: "BTCUSDT".to_string(),
+                side: "sell".to_string(),
+                status: "FILLED".to_string(),
+                quantity: 10.0,
+                filled_quantity: 10.0,
+                last_fill_quantity: 10.0,
+                last_fill_price: 50_000.0,
+                fee: 0.0,
+                event_time_ms: 0,
+            },
+        );
+        let last = engine.events.read(engine.events.last_sequence(), 1).pop().unwrap();
+        assert!(matches!(last.event, events::EngineEvent::ExchangeFill { execution_id: Some(ref id), .. } if id == "e1"));
+        let _ = std::fs::remove_file(path);
+    }
+}
@@ -0,0 +1,300 @@
+use super::config::OrderRouterConfig;
+use super::market_data::OrderBook;
+use super::ChildOrder;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Side {
+    Buy,
+    Sell,
+}
+
+impl Side {
+    pub fn name(self) -> &'static str {
+        match self {
+            Side::Buy => "buy",
+            Side::Sell => "sell",
+        }
+    }
+}
+
+// 候選交易所；capacity 為可動用庫存：買入時以報價資產計（含手續費），賣出時以基礎資產計。
+// transfer_cost 為在此成交後回補庫存的每單位劃轉成本（報價資產計），只影響分配順序，不計入 fees
+pub struct Venue {
+    pub exchange: String,
+    pub book: OrderBook,
+    pub fee_rate: f64,
+    pub capacity: f64,
+    pub transfer_cost: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RouteChild {
+    pub exchange: String,
+    pub quantity: f64,
+    pub quote_quantity: f64,
+    pub average_price: f64,
+    pub fee: f64,
+    // 吃到的價位數
+    pub levels: usize,
+}
+
+// 多子訂單執行計劃；expected_cost 為含手續費的總支出（買入）或總收入的相反數（賣出）
+#[derive(Debug, Clone, Serialize)]
+pub struct RoutePlan {
+    pub side: Side,
+    pub requested_quantity: f64,
+    pub filled_quantity: f64,
+    pub quote_quantity: f64,
+    pub fees: f64,
+    pub expected_cost: f64,
+    // 所有候選交易所中的最優價，用於計算滑點
+    pub best_price: f64,
+    pub complete: bool,
+    pub children: Vec<RouteChild>,
+}
+
+impl RoutePlan {
+    pub fn average_price(&self) -> f64 {
+        if self.filled_quantity > 0.0 {
+            self.quote_quantity / self.filled_quantity
+        } else {
+            self.best_price
+        }
+    }
+
+    pub fn slippage_bps(&self) -> f64 {
+        if self.best_price <= 0.0 {
+            return 0.0;
+        }
+        ((self.average_price() - self.best_price) / self.best_price).abs() * 10_000.0
+    }
+}
+
+pub struct OrderRouter {
+    config: OrderRouterConfig,
+}
+
+impl OrderRouter {
+    pub fn new(config: OrderRouterConfig) -> Self {
+        Self { config }
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.config.enabled
+    }
+
+    // 逐檔按含手續費價格從優到劣分配，分段線性成本下貪心即為最小總成本；
+    // 交易所數超過上限或子訂單名義金額過小時剔除分配最少的交易所後重新分配
+    pub fn plan(&self, side: Side, quantity: f64, venues: &[Venue]) -> RoutePlan {
+        let mut candidates: Vec<&Venue> = venues.iter().collect();
+        loop {
+            let plan = allocate(side, quantity, &candidates);
+            let smallest = plan
+                .children
+                .iter()
+                .min_by(|a, b| a.quote_quantity.total_cmp(&b.quote_quantity))
+                .filter(|child| {
+                    plan.children.len() > 1
+                        && (plan.children.len() > self.config.max_venues
+                            || child.quote_quantity < self.config.min_child_notional)
+                });
+            match smallest {
+                Some(child) => candidates.retain(|venue| venue.exchange != child.exchange),
+                None => return plan,
+            }
+        }
+    }
+}
+
+fn allocate(side: Side, quantity: f64, venues: &[&Venue]) -> RoutePlan {
+    // (含手續費與劃轉成本的價格, 交易所序號, 價格, 數量)
+    let mut levels: Vec<(f64, usize, f64, f64)> = Vec::new();
+    for (index, venue) in venues.iter().enumerate() {
+        let (book_side, sign) = match side {
+            Side::Buy => (&venue.book.asks, 1.0),
+            Side::Sell => (&venue.book.bids, -1.0),
+        };
+        for level in book_side {
+            levels.push((level.price * (1.0 + sign * venue.fee_rate) + sign * venue.transfer_cost, index, level.price, level.quantity));
+        }
+    }
+    match side {
+        Side::Buy => levels.sort_by(|a, b| a.0.total_cmp(&b.0)),
+        Side::Sell => levels.sort_by(|a, b| b.0.total_cmp(&a.0)),
+    }
+    let best_price = levels.first().map(|level| level.2).unwrap_or_default();
+
+    let mut remaining = quantity;
+    let mut capacity: Vec<f64> = venues.iter().map(|venue| venue.capacity).collect();
+    let mut fills: BTreeMap<usize, RouteChild> = BTreeMap::new();
+    for (effective_price, index, price, level_quantity) in levels {
+        if remaining <= 0.0 {
+            break;
+        }
+        let affordable = match side {
+            Side::Buy => capacity[index] / effective_price,
+            Side::Sell => capacity[index],
+        };
+        let take = remaining.min(level_quantity).min(affordable);
+        if take <= 0.0 {
+            continue;
+        }
+        let fee = take * price * venues[index].fee_rate;
+        capacity[index] -= match side {
+            Side::Buy => take * effective_price,
+            Side::Sell => take,
+        };
+        remaining -= take;
+        let child = fills.entry(index).or_insert_with(|| RouteChild {
+            exchange: venues[index].exchange.clone(),
+            quantity: 0.0,
+            quote_quantity: 0.0,
+            average_price: 0.0,
+            fee: 0.0,
+            levels: 0,
+        });
+        child.quantity += take;
+        child.quote_quantity += take * price;
+        child.fee += fee;
+        child.levels += 1;
+    }
+
+    let mut children: Vec<RouteChild> = fills.into_values().collect();
+    for child in &mut children {
+        child.average_price = child.quote_quantity / child.quantity;
+    }
+    children.sort_by(|a, b| b.quantity.total_cmp(&a.quantity));
+    let quote_quantity: f64 = children.iter().map(|child| child.quote_quantity).sum();
+    let fees: f64 = children.iter().map(|child| child.fee).sum();
+    RoutePlan {
+        side,
+        requested_quantity: quantity,
+        filled_quantity: quantity - remaining.max(0.0),
+        quote_quantity,
+        fees,
+        expected_cost: match side {
+            Side::Buy => quote_quantity + fees,
+            Side::Sell => fees - quote_quantity,
+        },
+        best_price,
+        complete: remaining <= quantity * 1e-9,
+        children,
+    }
+}
+
+// 各子訂單並行提交，全部回報後返回
+pub async fn execute(plan: &RoutePlan, leg: &str, symbol: &str) -> Vec<ChildOrder> {
+    let submissions = plan.children.iter().map(|child| async move {
+        // 模擬執行延遲（微秒級）
+        tokio::time::sleep(tokio::time::Duration::from_micros(100)).await;
+        ChildOrder {
+            leg: leg.to_string(),
+            exchange: child.exchange.clone(),
+            symbol: symbol.to_string(),
+            side: plan.side.name().to_string(),
+            quantity: child.quantity,
+            filled_quantity: child.quantity,
+            fee: child.fee,
+            status: "filled".to_string(),
+        }
+    });
+    futures::future::join_all(submissions).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bench::START_MS;
+    use crate::deterministic_sim::{build, funded_config, request};
+    use crate::{config, spot_arbitrage, ArbitrageRequest, Environment, ExecutionEngine, ExecutionOutcome, StrategyType};
+
+    // 智能訂單路由逐檔按含手續費與劃轉成本的價格分配，受各交易所可用庫存約束；
+    // 超過交易所數上限或子訂單過小時剔除分配最少的交易所。指定交易所庫存不足時只有開啟路由才能成交全部數量
+    #[tokio::test]
+    async fn order_router_splits_by_effective_price_within_capacity() {
+        use crate::market_data::{Level, OrderBook};
+        let book = |asks: &[(f64, f64)], bids: &[(f64, f64)]| OrderBook {
+            asks: asks.iter().map(|&(price, quantity)| Level { price, quantity }).collect(),
+            bids: bids.iter().map(|&(price, quantity)| Level { price, quantity }).collect(),
+        };
+        let venue = |exchange: &str, book: OrderBook, fee_rate: f64, capacity: f64, transfer_cost: f64| Venue {
+            exchange: exchange.to_string(),
+            book,
+            fee_rate,
+            capacity,
+            transfer_cost,
+        };
+        // 含手續費的買價：a 100.1 / 101.101，b 100.5，c 100.4004
+        let venues = || {
+            vec![
+                venue("a", book(&[(100.0, 1.0), (101.0, 5.0)], &[(99.0, 1.0), (98.0, 5.0)]), 0.001, 1e9, 0.0),
+                venue("b", book(&[(100.5, 2.0)], &[(98.9, 2.0)]), 0.0, 1e9, 0.0),
+                venue("c", book(&[(100.2, 1.0)], &[(99.2, 1.0)]), 0.002, 1e9, 0.0),
+            ]
+        };
+        let router = |max_venues: usize, min_child_notional: f64| {
+            OrderRouter::new(config::OrderRouterConfig { enabled: true, max_venues, min_child_notional })
+        };
+        let children = |plan: &RoutePlan| -> Vec<(String, f64)> {
+            plan.children.iter().map(|child| (child.exchange.clone(), child.quantity)).collect()
+        };
+
+        let plan = router(3, 0.0).plan(Side::Buy, 3.0, &venues());
+        let mut split = children(&plan);
+        split.sort_by(|a, b| a.0.cmp(&b.0));
+        assert_eq!(split, [("a".to_string(), 1.0), ("b".to_string(), 1.0), ("c".to_string(), 1.0)]);
+        assert!(plan.complete && plan.best_price == 100.0);
+        assert!((plan.fees - (0.1 + 0.2004)).abs() < 1e-9);
+        assert!((plan.expected_cost - (300.7 + 0.3004)).abs() < 1e-9);
+        assert!((plan.slippage_bps() - (300.7 / 3.0 - 100.0) * 100.0).abs() < 1e-6);
+
+        // 賣出按扣除手續費後的買價從高到低：c 99.0016、a 98.901、b 98.9
+        let plan = router(3, 0.0).plan(Side::Sell, 2.0, &venues());
+        assert_eq!(children(&plan), [("a".to_string(), 1.0), ("c".to_string(), 1.0)]);
+        assert!((plan.expected_cost - (0.099 + 0.1984 - 198.2)).abs() < 1e-9);
+
+        // a 只有 50 報價資產的庫存；c 的劃轉成本使其排在 b 之後
+        let mut constrained = venues();
+        constrained[0].capacity = 50.0;
+        constrained[2].transfer_cost = 0.5;
+        let plan = router(3, 0.0).plan(Side::Buy, 2.0, &constrained);
+        assert_eq!(children(&plan), [("b".to_string(), 2.0 - 50.0 / 100.1), ("a".to_string(), 50.0 / 100.1)]);
+
+        // 子訂單低於 150 時逐個剔除分配最少的交易所，剩下一個交易所時深度不足也不再剔除
+        let plan = router(3, 150.0).plan(Side::Buy, 3.0, &venues());
+        assert_eq!(children(&plan), [("b".to_string(), 2.0)]);
+        assert!(!plan.complete && plan.filled_quantity == 2.0);
+        assert_eq!(router(2, 0.0).plan(Side::Buy, 3.0, &venues()).children.len(), 2);
+
+        // 兩個指定交易所各只有 20k USDT：關閉路由時買入腿受庫存限制，開啟後其餘買入量路由到 okx
+        let mut config = funded_config();
+        config.spot_arbitrage.min_net_edge_bps = -1_000.0;
+        for (exchange, usdt) in [("binance", 20_000.0), ("bybit", 20_000.0), ("okx", 1e6)] {
+            config.spot_arbitrage.inventory.insert(exchange.to_string(), [("USDT".to_string(), usdt), ("BTC".to_string(), 10.0)].into());
+        }
+        let spot = ArbitrageRequest { strategy_type: StrategyType::SpotArbitrage, ..request(60_000.0) };
+        config.order_router.enabled = false;
+        let (engine, path) = build("order-router", config.clone(), Environment::simulated(START_MS, 1));
+        let filled = |outcome: &ExecutionOutcome, side: &str| {
+            outcome.orders.iter().filter(|order| order.side == side).map(|order| order.filled_quantity).sum::<f64>()
+        };
+        let outcome = spot_arbitrage::execute(&engine, "route-1", &spot).await.unwrap();
+        assert_eq!(outcome.orders.len(), 2);
+        assert!(filled(&outcome, "buy") < 20_000.0 / 59_000.0);
+
+        config.order_router.enabled = true;
+        let engine = ExecutionEngine {
+            order_router: OrderRouter::new(config.order_router),
+            spot_arbitrage: spot_arbitrage::SpotArbitrage::new(config.spot_arbitrage),
+            ..engine
+        };
+        let outcome = spot_arbitrage::execute(&engine, "route-2", &spot).await.unwrap();
+        assert!(outcome.orders.iter().any(|order| order.side == "buy" && order.exchange == "okx"));
+        assert!(filled(&outcome, "buy") > 60_000.0 / 61_000.0);
+        assert!((filled(&outcome, "buy") - filled(&outcome, "sell")).abs() < 1e-9);
+        let _ = std::fs::remove_file(path);
+    }
+}
@@ -0,0 +1,193 @@
+use super::config::{PriceGuardConfig, Severity};
+use super::{market_data, ExecutionEngine};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashSet};
+use std::sync::Mutex;
+use tracing::warn;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PriceCheck {
+    pub symbol: String,
+    // 交易所 -> 中間價
+    pub sources: BTreeMap<String, f64>,
+    pub median: f64,
+    // 各來源相對中位數的最大偏離（基點）
+    pub max_divergence_bps: f64,
+    // 穩定幣 -> 以其他穩定幣計價相對 1 的偏離（基點）
+    pub stablecoins: BTreeMap<String, f64>,
+    // 預言機價格及交易所報價中位數相對它的偏離（基點）；沒有未過期喂價時為 None
+    pub oracle_price: Option<f64>,
+    pub oracle_divergence_bps: Option<f64>,
+    pub passed: bool,
+    pub reasons: Vec<String>,
+}
+
+/// 價格合理性檢查：穩定幣脫錨或某一來源報價異常時價差看起來極好，執行前以各交易所的獨立報價交叉驗證，
+/// 偏離超過閾值則拒絕。首次觸發時告警，恢復後再次觸發重新告警。
+pub struct PriceGuard {
+    config: PriceGuardConfig,
+    // 當前處於異常狀態的交易對
+    tripped: Mutex<HashSet<String>>,
+}
+
+fn median_of(values: &mut [f64]) -> Option<f64> {
+    if values.is_empty() {
+        return None;
+    }
+    values.sort_by(|a, b| a.total_cmp(b));
+    let middle = values.len() / 2;
+    Some(if values.len().is_multiple_of(2) { (values[middle - 1] + values[middle]) / 2.0 } else { values[middle] })
+}
+
+impl PriceGuard {
+    pub fn new(config: PriceGuardConfig) -> Self {
+        Self {
+            config,
+            tripped: Mutex::new(HashSet::new()),
+        }
+    }
+
+    // 各交易所現貨訂單簿的中間價；沒有該交易對的交易所不計入
+    fn sources(engine: &ExecutionEngine, base: &str, quote: &str) -> BTreeMap<String, f64> {
+        engine
+            .venues()
+            .into_iter()
+            .filter_map(|venue| {
+                let book = market_data::simulated_spot_book(engine.env.rng.as_ref(), venue, base, quote).ok()?;
+                Some((venue.clone(), book.mid()?))
+            })
+            .collect()
+    }
+
+    // 各交易所中間價的中位數，供實現波動率採樣；沒有報價來源時為 None
+    pub(crate) fn reference_price(engine: &ExecutionEngine, symbol: &str) -> Option<f64> {
+        let (base, quote) = market_data::split_symbol(symbol)?;
+        median_of(&mut Self::sources(engine, &base, &quote).into_values().collect::<Vec<_>>())
+    }
+
+    pub fn check(&self, engine: &ExecutionEngine, symbol: &str) -> Result<PriceCheck, String> {
+        let (base, quote) = market_data::split_symbol(symbol).ok_or_else(|| format!("無法解析交易對: {}", symbol))?;
+        let sources = Self::sources(engine, &base, &quote);
+        let mut reasons = Vec::new();
+        let median = median_of(&mut sources.values().copied().collect::<Vec<_>>()).unwrap_or_default();
+        let max_divergence_bps = sources
+            .values()
+            .map(|price| ((price - median) / median).abs() * 10_000.0)
+            .fold(0.0, f64::max);
+        if sources.len() < self.config.min_sources {
+            reasons.push(format!("{} 只有 {} 個獨立報價來源，無法交叉驗證", symbol, sources.len()));
+        } else if max_divergence_bps > self.config.max_divergence_bps {
+            reasons.push(format!(
+                "{} 各交易所報價偏離中位數 {:.1} bps，超過 {:.1} bps",
+                symbol, max_divergence_bps, self.config.max_divergence_bps
+            ));
+        }
+
+        // 穩定幣以其他穩定幣計價，取各交易所、各參照穩定幣的中位數，單一參照脫錨不影響判斷
+        let mut stablecoins = BTreeMap::new();
+        for asset in [&base, &quote].into_iter().filter(|asset| self.config.stablecoins.contains(asset)) {
+            let mut prices: Vec<f64> = self
+                .config
+                .stablecoins
+                .iter()
+                .filter(|reference| *reference != asset)
+                .flat_map(|reference| Self::sources(engine, asset, reference).into_values())
+                .collect();
+            let Some(price) = median_of(&mut prices) else { continue };
+            let deviation_bps = (price - 1.0).abs() * 10_000.0;
+            stablecoins.insert(asset.clone(), deviation_bps);
+            if deviation_bps > self.config.max_stablecoin_deviation_bps {
+                reasons.push(format!(
+                    "穩定幣 {} 偏離 {:.1} bps，超過 {:.1} bps",
+                    asset, deviation_bps, self.config.max_stablecoin_deviation_bps
+                ));
+            }
+        }
+
+        // 預言機不依賴交易所報價，各交易所同時偏離時仍能發現
+        let oracle_price = engine.oracle.pair_price(&base, &quote, engine.env.now_ms());
+        let oracle_divergence_bps = oracle_price
+            .filter(|_| !sources.is_empty())
+            .map(|price| ((median - price) / price).abs() * 10_000.0);
+        if let Some(divergence_bps) = oracle_divergence_bps.filter(|bps| *bps > engine.oracle.max_deviation_bps()) {
+            reasons.push(format!(
+                "{} 交易所報價偏離預言機價格 {:.1} bps，超過 {:.1} bps",
+                symbol,
+                divergence_bps,
+                engine.oracle.max_deviation_bps()
+            ));
+        }
+
+        let passed = reasons.is_empty();
+        let newly_tripped = {
+            let mut tripped = self.tripped.lock().unwrap();
+            if passed {
+                tripped.remove(symbol);
+                false
+            } else {
+                tripped.insert(symbol.to_string())
+            }
+        };
+        if newly_tripped {
+            engine.alert(Severity::Critical, "價格異常，暫停相關交易", reasons.join("；"));
+        }
+        Ok(PriceCheck {
+            symbol: symbol.to_string(),
+            sources,
+            median,
+            max_divergence_bps,
+            stablecoins,
+            oracle_price,
+            oracle_divergence_bps,
+            passed,
+            reasons,
+        })
+    }
+
+    // 執行前檢查；未啟用時直接通過
+    pub(crate) fn verify(&self, engine: &ExecutionEngine, symbol: &str) -> Result<(), String> {
+        if !self.config.enabled {
+            return Ok(());
+        }
+        let check = self.check(engine, symbol)?;
+        if !check.passed {
+            warn!(%symbol, reasons = ?check.reasons, "價格合理性檢查未通過");
+            return Err(format!("價格合理性檢查未通過: {}", check.reasons.join("；")));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bench::START_MS;
+    use crate::deterministic_sim::build;
+    use crate::{config, Environment};
+
+    #[tokio::test]
+    async fn price_guard_refuses_divergent_sources_and_depegged_stablecoins() {
+        let (engine, path) = build("price_guard", config::EngineConfig::default(), Environment::simulated(START_MS, 1));
+        let check = engine.price_guard.check(&engine, "BTCUSDT").unwrap();
+        assert!(check.passed, "{:?}", check.reasons);
+        assert!(check.sources.len() >= 2);
+        assert!(check.stablecoins.contains_key("USDT"));
+        assert!(engine.price_guard.verify(&engine, "USDCUSDT").is_ok());
+
+        // 模擬報價各交易所偏離數個基點，收緊閾值後拒絕執行
+        let mut config = config::EngineConfig::default();
+        config.price_guard.max_divergence_bps = 0.001;
+        config.price_guard.max_stablecoin_deviation_bps = 0.001;
+        let (strict, strict_path) = build("price_guard_strict", config, Environment::simulated(START_MS, 1));
+        let error = strict.price_guard.verify(&strict, "BTCUSDT").unwrap_err();
+        assert!(error.starts_with("價格合理性檢查未通過"), "{}", error);
+        assert!(error.contains("穩定幣 USDT"), "{}", error);
+
+        let mut config = config::EngineConfig::default();
+        config.price_guard.min_sources = 10;
+        let (sparse, sparse_path) = build("price_guard_sparse", config, Environment::simulated(START_MS, 1));
+        assert!(!sparse.price_guard.check(&sparse, "ETHUSDT").unwrap().passed);
+        for path in [path, strict_path, sparse_path] {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+}
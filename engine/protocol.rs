@@ -0,0 +1,567 @@
+use crate::{backtest, client_auth, execution_algo, execution_queue, latency, maker, order_router, routing, sizing, storage};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+pub mod wire;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArbitrageRequest {
+    pub strategy_id: String,
+    pub symbol: String,
+    pub primary_exchange: String,
+    pub secondary_exchange: String,
+    pub amount: f64,
+    pub priority: i32,
+    pub timestamp: String,
+    // 快速通道：客戶端已自行驗證機會，跳過費率差重驗並將記帳延後至後台隊列
+    #[serde(default)]
+    pub fast_path: bool,
+    // 在響應中附帶執行時使用的市場快照（費率、Gas 等），默認關閉以控制響應大小
+    #[serde(default)]
+    pub include_market_context: bool,
+    // 策略類型，缺省為跨交易所資金費率套利
+    #[serde(default)]
+    pub strategy_type: StrategyType,
+    // 三角套利的三個現貨交易對（如 ["BTC/USDT", "ETH/BTC", "ETH/USDT"]），在 primary_exchange 上執行
+    #[serde(default)]
+    pub triangle: Option<Vec<String>>,
+    // strategy_type 為 plugin 時選擇的已註冊自定義策略名
+    #[serde(default)]
+    pub plugin: Option<String>,
+    // 鏈上腿所在的鏈（如 arbitrum），缺省為以太坊主網
+    #[serde(default)]
+    pub chain: Option<String>,
+    // 兩側交易所使用的子賬戶（exchanges.<交易所>.accounts 中的名稱，main 為主賬戶）；
+    // 缺省時按 strategy_id 所屬的子賬戶，未歸屬任何子賬戶時用主賬戶
+    #[serde(default)]
+    pub account: Option<String>,
+    // 大額資金費率頭寸的分片執行算法（TWAP / 冰山單），缺省一次性下單
+    #[serde(default)]
+    pub execution_algo: Option<execution_algo::ExecutionAlgo>,
+    // 定倉方式：缺省按 amount 固定金額；volatility 按風險預算與近期實現波動率計算，amount 為上限
+    #[serde(default)]
+    pub sizing: Option<sizing::SizingMode>,
+    // 資金費率腿先以 post-only 限價單掛單，有效期內未成交的部分改為吃單；缺省直接吃單
+    #[serde(default)]
+    pub maker_first: Option<Box<maker::MakerFirst>>,
+    // 資金費率套利兩條永續腿的槓桿與保證金模式，缺省使用 pre_trade 配置；下單前在兩邊交易所設置並確認生效
+    #[serde(default)]
+    pub leverage: Option<f64>,
+    #[serde(default)]
+    pub margin_mode: Option<MarginMode>,
+    // 執行截止時間（Unix 毫秒）；過期的請求不再執行，各階段超時不超過剩餘時間
+    #[serde(default)]
+    pub deadline_ms: Option<i64>,
+    // 定時執行：到 execute_at_ms（Unix 毫秒）時執行，或在兩側交易所中先結算的資金費時間之前
+    // execute_before_funding_secs 秒執行；指定後立即返回 scheduled 響應
+    #[serde(default)]
+    pub execute_at_ms: Option<i64>,
+    #[serde(default)]
+    pub execute_before_funding_secs: Option<u64>,
+    // 在最終響應前通過同一連接逐階段推送執行進度（execution_progress 事件）
+    #[serde(default)]
+    pub stream_progress: bool,
+    // 連接層為 stream_progress 請求接入的進度通道，不參與序列化
+    #[serde(skip)]
+    pub progress: Option<ProgressSink>,
+    // 執行隊列派發時接入的搶佔句柄，不參與序列化
+    #[serde(skip)]
+    pub preemption: Option<std::sync::Arc<execution_queue::Preemption>>,
+}
+
+pub type ProgressSink = tokio::sync::mpsc::UnboundedSender<ExecutionProgress>;
+
+// 執行進度：risk_approved、leg1_submitted、leg1_filled、leg2_submitted、leg2_filled、settled；
+// 下單前被搶佔並重新排隊時推送 preempted
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExecutionProgress {
+    pub execution_id: String,
+    pub stage: String,
+    pub at_ms: i64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exchange: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub filled_quantity: Option<f64>,
+    // settled 階段的最終狀態
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status: Option<String>,
+}
+
+impl ExecutionProgress {
+    pub fn stage(execution_id: &str, stage: &str, at_ms: i64) -> Self {
+        Self {
+            execution_id: execution_id.to_string(),
+            stage: stage.to_string(),
+            at_ms,
+            exchange: None,
+            filled_quantity: None,
+            status: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MarginMode {
+    #[default]
+    Cross,
+    Isolated,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct LeverageSetting {
+    pub leverage: f64,
+    pub margin_mode: MarginMode,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StrategyType {
+    #[default]
+    FundingRate,
+    Triangular,
+    // 期現套利：primary_exchange 買入現貨，secondary_exchange 做空永續合約（可為同一交易所）
+    CashAndCarry,
+    // 跨交易所現貨價差套利：在較便宜的一方買入、較貴的一方賣出
+    SpotArbitrage,
+    // 經 ExecutionEngine::register_strategy 註冊的自定義策略，由請求的 plugin 字段選擇
+    Plugin,
+    // 借貸利差套利：primary_exchange 為借款的借貸市場，secondary_exchange 為存款的借貸市場或收取資金費的交易所
+    LendingRate,
+}
+
+impl StrategyType {
+    pub const ALL: [StrategyType; 6] = [
+        StrategyType::FundingRate,
+        StrategyType::Triangular,
+        StrategyType::CashAndCarry,
+        StrategyType::SpotArbitrage,
+        StrategyType::Plugin,
+        StrategyType::LendingRate,
+    ];
+    
+    pub fn name(self) -> &'static str {
+        match self {
+            StrategyType::FundingRate => "funding_rate",
+            StrategyType::Triangular => "triangular",
+            StrategyType::CashAndCarry => "cash_and_carry",
+            StrategyType::SpotArbitrage => "spot_arbitrage",
+            StrategyType::Plugin => "plugin",
+            StrategyType::LendingRate => "lending_rate",
+        }
+    }
+}
+
+// 客戶端消息：套利請求，或以 `command` 標記的控制指令
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+pub enum ClientMessage {
+    Execute(Box<ArbitrageRequest>),
+    Batch(BatchRequest),
+    Command(EngineCommand),
+}
+
+// 批量套利請求，例如 {"requests":[{...},{...}]}；各項並發執行，響應按請求順序逐項返回
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BatchRequest {
+    pub requests: Vec<ArbitrageRequest>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "command", rename_all = "snake_case")]
+pub enum EngineCommand {
+    // 連接上的認證握手，例如 {"command":"authenticate","client":"ops","timestamp_ms":0,"nonce":"...","signature":"..."}
+    Authenticate(client_auth::AuthRequest),
+    // 協商本連接的線路編碼，例如 {"command":"set_encoding","encoding":"msgpack"}；
+    // 響應仍按原編碼返回，之後的請求與響應改用新編碼
+    SetEncoding { encoding: wire::Encoding },
+    // 客戶端發起的心跳，返回服務端時間，例如 {"command":"ping"}
+    Ping,
+    // 回應服務端推送的 {"event":"ping","data":{"at_ms":...}}，例如 {"command":"pong","at_ms":1700000000000}；不返回響應
+    Pong { at_ms: Option<i64> },
+    // 運行時調整日誌級別，例如 {"command":"set_log_level","filter":"debug"}
+    SetLogLevel { filter: String },
+    // 查詢歷史執行記錄，可按策略、交易對、時間範圍過濾
+    GetHistory(storage::HistoryFilter),
+    // 查詢各交易對的資金費率價差滾動統計，按價差強度排序
+    GetSpreadStats { symbol: Option<String> },
+    // 查詢所有跟蹤交易對在各交易所的下一次資金費結算時間與預測費率
+    GetFundingCalendar,
+    // 查詢各交易對當前校準後的最大名義金額
+    GetSizingLimits,
+    // 查詢持倉/PnL 投影，可指定時間點或事件序號回放到歷史狀態
+    GetPositions { at_ms: Option<i64>, at_sequence: Option<u64> },
+    // 按序號讀取事件日誌，用於審計
+    GetEvents { from_sequence: Option<u64>, limit: Option<usize> },
+    // 查詢掃描器最近一輪排序後的套利機會
+    GetOpportunities,
+    // 在當前連接上訂閱掃描器推送的套利機會
+    SubscribeOpportunities,
+    // 查詢各交易對的擁擠度指標
+    GetCrowding,
+    // 查詢期現基差跟蹤與未平倉的期現頭寸
+    GetBasis,
+    // 查詢各交易所限頻配額餘量與被丟棄、排隊的請求數
+    GetRateLimits,
+    // 查詢各交易所連接狀態、重連次數、訂閱與 listen key
+    GetSessions,
+    // 查詢用戶數據流維護的交易所側持倉、餘額與最近訂單
+    GetAccounts { exchange: Option<String> },
+    // 查詢各交易所可用保證金與在途執行預留的保證金
+    GetMarginAvailability,
+    // 查詢永續持倉的強平距離與最近的告警、降風險處理
+    GetLiquidationRisk,
+    // 查詢資金費率套利持倉對的下一次結算、已收付資金費與平倉記錄
+    GetFundingPairs,
+    // 查詢各交易所/交易對最近一次的預測費率及溢價指數模型中間量
+    GetPredictedFunding,
+    // 查詢啟動時加載的配置、當前生效的配置及運行時修改
+    GetConfig,
+    // 重新讀取配置文件並熱加載風控限額、閾值、手續費與策略開關；含需重啟的變更時整體拒絕
+    ReloadConfig,
+    // 查詢正在執行中的請求
+    GetOpenExecutions,
+    // 查詢執行隊列中等待的請求、各交易所並發數及擠出/拒絕/超時計數
+    GetExecutionQueue,
+    // 查詢各執行階段耗時分佈（微秒）
+    GetLatency,
+    // 查詢各交易所掛單優先執行的 maker 成交比例
+    GetMakerStats,
+    // 查詢各交易所/交易對緩存費率的接收時間與是否過期
+    GetQuotes,
+    // 查詢各交易所下單確認延遲的統計與機會評估使用的預期延遲
+    GetVenueLatency,
+    // 查詢各交易所候選 REST 端點的探測延遲、健康狀態與當前選用的端點
+    GetGateways,
+    // 查詢各交易所時鐘偏差與同步狀態
+    GetClockSync,
+    // 啟用或解除緊急停止
+    SetKillSwitch { engaged: bool },
+    // 查詢各交易所現貨庫存
+    GetInventory,
+    // 查詢現貨槓桿可借額度與已借數量
+    GetMargin,
+    // 導出某次執行鏈上腿的結算證明（交易哈希、回執、解碼日誌、區塊頭）
+    GetSettlementProof { execution_id: String },
+    // 查詢熱錢包地址與本地跟踪的下一個 nonce
+    GetWallets { chain: Option<String> },
+    // 查詢各策略當日鏈上 gas 花費與預算
+    GetGasBudget,
+    // 查詢各 strategy_id 的資金分配、佔用與當日盈虧
+    GetCapital,
+    // 查詢事件總線的發布進度與最近一次錯誤
+    GetEventBus,
+    // 查詢執行預寫日誌中未結束的執行與本次啟動的恢復結果
+    GetJournal,
+    // 把交易對解析為內部格式及各交易所的原生代碼
+    GetSymbol { symbol: String },
+    // 查詢最近一次狀態對賬報告
+    GetReconciliation,
+    // 立即對賬；auto_correct 為空時按配置
+    Reconcile { auto_correct: Option<bool> },
+    // 查詢待觸發與最近觸發的定時請求
+    GetScheduled,
+    // 取消尚未觸發的定時請求
+    CancelScheduled { execution_id: String },
+    // 以各交易所報價交叉驗證交易對價格與穩定幣錨定
+    GetPriceCheck { symbol: String },
+    // 查詢預言機喂價的最新讀數（美元）與更新時間
+    GetOraclePrices,
+    // 查詢 DEX 代幣授權額度與待處理的授權
+    GetApprovals { chain: Option<String> },
+    // 查詢各 RPC 節點的健康評分、延遲與最近錯誤
+    GetRpcProviders { chain: Option<String> },
+    // 查詢新區塊訂閱狀態、重組次數與監控中的已確認交易
+    GetBlockSubscription { chain: Option<String> },
+    // 查詢當日再平衡劃轉量與最近的劃轉記錄
+    GetRebalance,
+    // 立即按目標佔比再平衡各賬戶的庫存；dry_run 時只返回劃轉計劃
+    Rebalance {
+        #[serde(default)]
+        dry_run: bool,
+    },
+    // 查詢當日限額觸發的交易暫停
+    GetTradingHalts,
+    // 解除交易暫停，strategy_id 為空時解除引擎整體暫停；當日不再因限額暫停
+    ResumeTrading { strategy_id: Option<String> },
+    // 查詢衝突窗口內觀察到的待確認大額兌換
+    GetMempool,
+    // 預覽一筆擬執行請求對敞口、集中度、借幣額度使用率與 VaR 的影響，不下單
+    PreviewRisk { request: Box<ArbitrageRequest> },
+    // 其他區域實例轉發的單條腿，需攜帶有效簽名
+    ExecuteLeg(routing::LegEnvelope),
+    // 查詢運行中與最近結束的 TWAP / 冰山單執行及每片成交情況
+    GetAlgoExecutions,
+    // 查詢各策略類型是否啟用
+    GetStrategies,
+    // 查詢已註冊的自定義策略及其最近一輪掃描發布的機會
+    GetStrategyPlugins,
+    // 查詢借貸市場利率、借貸利差頭寸（債務、收益、健康因子）與當前機會
+    GetLending,
+    // 啟用或停用某類策略，停用後該類請求直接拒絕
+    SetStrategyEnabled { strategy: StrategyType, enabled: bool },
+    // 按當前訂單簿生成現貨訂單的拆單計劃，不下單；未指定交易所時考慮所有交易所
+    PlanOrder {
+        symbol: String,
+        side: order_router::Side,
+        quantity: f64,
+        exchanges: Option<Vec<String>>,
+    },
+    // 以當前費率、手續費與 gas 報價估算一個機會的收益，不下單
+    SimulateOpportunity {
+        symbol: String,
+        primary_exchange: String,
+        secondary_exchange: String,
+        amount: Option<f64>,
+        priority: Option<i32>,
+    },
+    // 回放錄製的資金費率、訂單簿與成交數據，返回 PnL 曲線、勝率與滑點統計；
+    // 未指定 data_path 時回放歷史存儲中的資金費率樣本
+    RunBacktest(backtest::BacktestParams),
+    // 登記交易所間的資金劃轉
+    RecordTransfer {
+        asset: String,
+        from_exchange: String,
+        to_exchange: String,
+        amount: f64,
+    },
+}
+
+// 控制指令的響應
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CommandResponse {
+    #[serde(default = "current_version")]
+    pub version: u32,
+    pub status: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub data: Option<serde_json::Value>,
+    pub error_message: Option<String>,
+}
+
+impl CommandResponse {
+    pub fn ok(data: Option<serde_json::Value>) -> Self {
+        Self {
+            version: CURRENT_VERSION,
+            status: "success".to_string(),
+            data,
+            error_message: None,
+        }
+    }
+    
+    pub fn error(message: impl Into<String>) -> Self {
+        Self {
+            version: CURRENT_VERSION,
+            status: "error".to_string(),
+            data: None,
+            error_message: Some(message.into()),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ArbitrageResponse {
+    #[serde(default = "current_version")]
+    pub version: u32,
+    #[serde(default)]
+    pub execution_id: Option<String>,
+    pub status: String,
+    pub profit: Option<f64>,
+    pub execution_time: String,
+    pub gas_used: Option<u64>,
+    pub error_message: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub market_context: Option<MarketContext>,
+    // 逐階段微秒耗時，僅實際進入執行流程的請求有
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timings: Option<latency::Timings>,
+    // 定時請求的觸發時間（Unix 毫秒），僅 scheduled 響應有
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scheduled_for_ms: Option<i64>,
+    // 請求指定 sizing 時實際採用的金額與依據
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sizing: Option<sizing::SizingDecision>,
+    // 請求校驗未通過時的逐字段違規項
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub violations: Vec<FieldViolation>,
+}
+
+// 請求中違反約束的一個字段
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FieldViolation {
+    pub field: String,
+    pub message: String,
+}
+
+// 執行決策時引擎看到的市場狀態
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MarketContext {
+    pub captured_at_ms: i64,
+    // 入場判斷使用的預測下一期費率
+    pub primary_rate: Option<f64>,
+    pub secondary_rate: Option<f64>,
+    pub rate_diff: Option<f64>,
+    // 無永續合約一側以現貨槓桿做空時的借幣利率（每個資金費週期）
+    pub borrow_rate: Option<f64>,
+    pub max_notional: f64,
+    pub expected_slippage_bps: Option<f64>,
+    pub gas_price: u64,
+    pub max_gas_limit: u64,
+}
+
+impl ArbitrageResponse {
+    pub fn error(message: impl Into<String>) -> Self {
+        Self {
+            version: CURRENT_VERSION,
+            execution_id: None,
+            status: "error".to_string(),
+            profit: None,
+            execution_time: "0ms".to_string(),
+            gas_used: None,
+            error_message: Some(message.into()),
+            market_context: None,
+            timings: None,
+            scheduled_for_ms: None,
+            sizing: None,
+            violations: Vec::new(),
+        }
+    }
+
+    // 下單前被更高優先級的請求搶佔
+    pub fn preempted(message: impl Into<String>) -> Self {
+        Self {
+            status: "preempted".to_string(),
+            ..Self::error(message)
+        }
+    }
+
+    // 校驗未通過：error_message 列出全部違規項，violations 供客戶端按字段處理
+    pub fn invalid(violations: Vec<FieldViolation>) -> Self {
+        let summary: Vec<String> = violations.iter().map(|v| format!("{}: {}", v.field, v.message)).collect();
+        Self {
+            violations,
+            ..Self::error(format!("請求校驗失敗: {}", summary.join("; ")))
+        }
+    }
+}
+
+// 批量請求的響應：全部成功為 success，部分成功為 partial，全部失敗為 error
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BatchResponse {
+    #[serde(default = "current_version")]
+    pub version: u32,
+    pub status: String,
+    pub succeeded: usize,
+    pub failed: usize,
+    // 成功項的預期收益合計
+    pub expected_profit: f64,
+    pub results: Vec<BatchItem>,
+    pub error_message: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BatchItem {
+    pub symbol: String,
+    #[serde(flatten)]
+    pub response: ArbitrageResponse,
+}
+
+impl BatchResponse {
+    pub fn from_results(results: Vec<BatchItem>) -> Self {
+        let succeeded = results.iter().filter(|item| item.response.status == "success").count();
+        let failed = results.len() - succeeded;
+        let expected_profit = results
+            .iter()
+            .filter(|item| item.response.status == "success")
+            .filter_map(|item| item.response.profit)
+            .sum();
+        let status = match (succeeded, failed) {
+            (_, 0) => "success",
+            (0, _) => "error",
+            _ => "partial",
+        };
+        Self {
+            version: CURRENT_VERSION,
+            status: status.to_string(),
+            succeeded,
+            failed,
+            expected_profit,
+            results,
+            error_message: None,
+        }
+    }
+
+    pub fn error(message: impl Into<String>) -> Self {
+        Self {
+            version: CURRENT_VERSION,
+            status: "error".to_string(),
+            succeeded: 0,
+            failed: 0,
+            expected_profit: 0.0,
+            results: Vec::new(),
+            error_message: Some(message.into()),
+        }
+    }
+}
+
+// 單筆子訂單（每條腿一筆）及其成交情況
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChildOrder {
+    pub leg: String,
+    pub exchange: String,
+    pub symbol: String,
+    pub side: String,
+    pub quantity: f64,
+    pub filled_quantity: f64,
+    pub fee: f64,
+    pub status: String,
+}
+
+pub const CURRENT_VERSION: u32 = 2;
+pub const MIN_SUPPORTED_VERSION: u32 = 1;
+
+pub fn current_version() -> u32 {
+    CURRENT_VERSION
+}
+
+pub fn parse(message: Value) -> Result<ClientMessage, String> {
+    let Value::Object(mut fields) = message else {
+        return Err("解析失敗: 消息必須是對象".to_string());
+    };
+    let version = match fields.remove("version") {
+        None => 1,
+        Some(version) => version
+            .as_u64()
+            .and_then(|version| u32::try_from(version).ok())
+            .ok_or_else(|| format!("協議版本必須是正整數: {}", version))?,
+    };
+    if !(MIN_SUPPORTED_VERSION..=CURRENT_VERSION).contains(&version) {
+        return Err(format!(
+            "不支持的協議版本 {}，引擎支持 {} ~ {}",
+            version, MIN_SUPPORTED_VERSION, CURRENT_VERSION
+        ));
+    }
+    if version < 2 {
+        upgrade_v1(&mut fields)?;
+    }
+    serde_json::from_value(Value::Object(fields)).map_err(|e| format!("解析失敗: {}", e))
+}
+
+// v1 → v2：v1 套利請求以 type 標記策略（如 "funding_rate_arbitrage"），v2 統一使用 strategy_type
+fn upgrade_v1(fields: &mut Map<String, Value>) -> Result<(), String> {
+    if fields.contains_key("command") {
+        return Ok(());
+    }
+    let Some(kind) = fields.remove("type") else { return Ok(()) };
+    if fields.contains_key("strategy_type") {
+        return Ok(());
+    }
+    let strategy_type = match kind.as_str() {
+        Some("funding_rate_arbitrage" | "funding_rate") => "funding_rate",
+        Some("triangular_arbitrage" | "triangular") => "triangular",
+        Some("cash_and_carry_arbitrage" | "cash_and_carry") => "cash_and_carry",
+        Some("spot_arbitrage") => "spot_arbitrage",
+        _ => return Err(format!("v1 請求類型 {} 無法對應到策略", kind)),
+    };
+    fields.insert("strategy_type".to_string(), Value::from(strategy_type));
+    Ok(())
+}
@@ -0,0 +1,93 @@
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+pub const MAGIC: [u8; 2] = *b"AB";
+pub const FRAME_VERSION: u8 = 1;
+pub const HEADER_LEN: usize = 8;
+// 單幀負載上限，超過視為幀錯位；JSON 模式下同為單條消息的上限
+const MAX_FRAME_LEN: usize = 1 << 20;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Encoding {
+    #[default]
+    Json,
+    Msgpack,
+}
+
+impl Encoding {
+    fn code(self) -> u8 {
+        match self {
+            Encoding::Json => 0,
+            Encoding::Msgpack => 1,
+        }
+    }
+}
+
+pub fn encode<T: Serialize>(encoding: Encoding, value: &T) -> Vec<u8> {
+    let mut output = Vec::new();
+    encode_into(encoding, value, &mut output);
+    output
+}
+
+// 清空 output 後寫入編碼結果；連接處理複用同一緩衝，避免每條消息分配
+pub fn encode_into<T: Serialize>(encoding: Encoding, value: &T, output: &mut Vec<u8>) {
+    output.clear();
+    match encoding {
+        Encoding::Json => serde_json::to_writer(&mut *output, value).unwrap(),
+        Encoding::Msgpack => {
+            output.extend_from_slice(&MAGIC);
+            output.push(FRAME_VERSION);
+            output.push(encoding.code());
+            output.extend_from_slice(&[0; 4]);
+            // 按字段名編碼，可選字段缺省時客戶端仍能按名匹配；寫完負載後回填長度
+            rmp_serde::encode::write_named(output, value).unwrap();
+            let len = (output.len() - HEADER_LEN) as u32;
+            output[4..HEADER_LEN].copy_from_slice(&len.to_be_bytes());
+        }
+    }
+}
+
+// 直接從讀緩衝解析，不複製負載；JSON 消息必須是合法 UTF-8
+pub fn decode<T: DeserializeOwned>(encoding: Encoding, payload: &[u8]) -> Result<T, String> {
+    match encoding {
+        Encoding::Json => serde_json::from_slice(payload).map_err(|e| e.to_string()),
+        Encoding::Msgpack => rmp_serde::from_slice(payload).map_err(|e| e.to_string()),
+    }
+}
+
+// 讀緩衝開頭一個完整幀的總長度（含幀頭），數據不足時返回 None；幀頭無效時連接已無法重新對齊
+pub fn frame_len(buffer: &[u8]) -> Result<Option<usize>, String> {
+    if buffer.len() < HEADER_LEN {
+        return Ok(None);
+    }
+    if buffer[..2] != MAGIC {
+        return Err("幀頭魔數錯誤".to_string());
+    }
+    if buffer[2] != FRAME_VERSION {
+        return Err(format!("不支持的幀格式版本 {}，當前為 {}", buffer[2], FRAME_VERSION));
+    }
+    if buffer[3] != Encoding::Msgpack.code() {
+        return Err(format!("幀編碼 {} 與協商的 msgpack 不一致", buffer[3]));
+    }
+    let len = u32::from_be_bytes([buffer[4], buffer[5], buffer[6], buffer[7]]) as usize;
+    if len > MAX_FRAME_LEN {
+        return Err(format!("幀長度 {} 超過上限 {}", len, MAX_FRAME_LEN));
+    }
+    if buffer.len() < HEADER_LEN + len {
+        return Ok(None);
+    }
+    Ok(Some(HEADER_LEN + len))
+}
+
+// JSON 模式下讀緩衝開頭一條完整消息的長度（含前導空白），數據不足時返回 None；
+// 消息不是合法 JSON 或超過長度上限時返回錯誤
+pub fn json_message_len(buffer: &[u8]) -> Result<Option<usize>, String> {
+    let mut stream = serde_json::Deserializer::from_slice(buffer).into_iter::<serde::de::IgnoredAny>();
+    match stream.next() {
+        Some(Ok(_)) => Ok(Some(stream.byte_offset())),
+        Some(Err(_)) | None if buffer.len() > MAX_FRAME_LEN => Err(format!("消息長度超過上限 {}", MAX_FRAME_LEN)),
+        Some(Err(e)) if !e.is_eof() => Err(format!("解析失敗: {}", e)),
+        _ => Ok(None),
+    }
+}
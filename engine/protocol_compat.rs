@@ -0,0 +1,174 @@
+use super::*;
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+
+// 當前協議版本；協議變化時新增版本目錄並更新此常量，已發布版本的樣本不可修改
+const CURRENT_VERSION: &str = "v2";
+
+fn golden_root() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/golden/protocol")
+}
+
+fn versions() -> Vec<String> {
+    let mut versions: Vec<String> = std::fs::read_dir(golden_root())
+        .expect("缺少協議樣本目錄")
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.path().is_dir())
+        .map(|entry| entry.file_name().to_string_lossy().into_owned())
+        .collect();
+    versions.sort();
+    versions
+}
+
+fn samples(version: &str, kind: &str) -> Vec<(String, Value)> {
+    let dir = golden_root().join(version).join(kind);
+    let mut files: Vec<PathBuf> = std::fs::read_dir(&dir)
+        .unwrap_or_else(|e| panic!("讀取 {} 失敗: {}", dir.display(), e))
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+        .collect();
+    files.sort();
+    files
+        .into_iter()
+        .map(|path| {
+            let name = path.file_stem().unwrap().to_string_lossy().into_owned();
+            let raw = std::fs::read_to_string(&path).unwrap();
+            let value = serde_json::from_str(&raw)
+                .unwrap_or_else(|e| panic!("{}/{}/{} 不是合法 JSON: {}", version, kind, name, e));
+            (name, value)
+        })
+        .collect()
+}
+
+fn json_type(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "bool",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+// 舊樣本中的每個字段必須仍然存在；非空字段的 JSON 類型不得改變（可選字段允許為 null）
+fn assert_superset(golden: &Value, current: &Value, path: &str) {
+    match (golden, current) {
+        (Value::Object(old), Value::Object(new)) => {
+            for (key, old_value) in old {
+                let field = format!("{}.{}", path, key);
+                let new_value = new
+                    .get(key)
+                    .unwrap_or_else(|| panic!("{} 在當前協議中被移除", field));
+                assert_superset(old_value, new_value, &field);
+            }
+        }
+        (Value::Null, _) | (_, Value::Null) => {}
+        (old, new) => assert_eq!(
+            json_type(old),
+            json_type(new),
+            "{} 的類型從 {} 變為 {}",
+            path,
+            json_type(old),
+            json_type(new)
+        ),
+    }
+}
+
+fn reserialize_response(name: &str, golden: &Value) -> Value {
+    if name.starts_with("execute_") {
+        let response: ArbitrageResponse = serde_json::from_value(golden.clone())
+            .unwrap_or_else(|e| panic!("{} 無法解析為 ArbitrageResponse: {}", name, e));
+        serde_json::to_value(response).unwrap()
+    } else if name.starts_with("command_") {
+        let response: CommandResponse = serde_json::from_value(golden.clone())
+            .unwrap_or_else(|e| panic!("{} 無法解析為 CommandResponse: {}", name, e));
+        serde_json::to_value(response).unwrap()
+    } else if name.starts_with("batch_") {
+        let response: BatchResponse = serde_json::from_value(golden.clone())
+            .unwrap_or_else(|e| panic!("{} 無法解析為 BatchResponse: {}", name, e));
+        serde_json::to_value(response).unwrap()
+    } else {
+        panic!("未知的響應樣本前綴: {}", name)
+    }
+}
+
+#[test]
+fn current_version_has_samples() {
+    assert!(versions().iter().any(|v| v == CURRENT_VERSION), "缺少 {} 樣本目錄", CURRENT_VERSION);
+}
+
+#[test]
+fn requests_from_all_versions_still_parse() {
+    for version in versions() {
+        for (name, golden) in samples(&version, "requests") {
+            let message = protocol::parse(golden.clone())
+                .unwrap_or_else(|e| panic!("{}/{} 無法解析: {}", version, name, e));
+            match message {
+                ClientMessage::Execute(request) => {
+                    assert!(name.starts_with("execute_"), "{}/{} 被解析為套利請求", version, name);
+                    // 重新序列化後再次解析應得到相同的請求
+                    let first = serde_json::to_value(&request).unwrap();
+                    let again: ArbitrageRequest = serde_json::from_value(first.clone()).unwrap();
+                    assert_eq!(first, serde_json::to_value(&again).unwrap(), "{}/{} 往返不一致", version, name);
+                }
+                ClientMessage::Batch(batch) => {
+                    assert!(name.starts_with("batch_"), "{}/{} 被解析為批量請求", version, name);
+                    assert!(!batch.requests.is_empty(), "{}/{} 批量請求為空", version, name);
+                }
+                ClientMessage::Command(_) => {
+                    assert!(name.starts_with("command_"), "{}/{} 被解析為控制指令", version, name);
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn unversioned_requests_are_upgraded_from_v1() {
+    for (name, golden) in samples("v0", "requests") {
+        let Ok(ClientMessage::Execute(request)) = protocol::parse(golden) else {
+            panic!("v0/{} 無法解析為套利請求", name)
+        };
+        assert_eq!(request.strategy_type, StrategyType::FundingRate, "v0/{} 的 type 未升級為 strategy_type", name);
+    }
+    let unknown = serde_json::json!({
+        "type": "unknown_strategy",
+        "strategy_id": "s",
+        "symbol": "BTCUSDT",
+        "primary_exchange": "binance",
+        "secondary_exchange": "bybit",
+        "amount": 1000.0,
+        "priority": 1,
+        "timestamp": "2024-06-10T08:00:00.000000"
+    });
+    assert!(protocol::parse(unknown).is_err(), "無法對應的 v1 type 應被拒絕");
+}
+
+#[test]
+fn unsupported_versions_are_rejected() {
+    for version in [serde_json::json!(0), serde_json::json!(protocol::CURRENT_VERSION + 1), serde_json::json!("2")] {
+        let error = protocol::parse(serde_json::json!({"version": version, "command": "get_latency"}))
+            .err()
+            .unwrap_or_else(|| panic!("版本 {} 應被拒絕", version));
+        assert!(error.contains("版本"), "版本 {} 的錯誤信息不明確: {}", version, error);
+    }
+}
+
+#[test]
+fn responses_keep_fields_read_by_older_clients() {
+    for version in versions() {
+        for (name, golden) in samples(&version, "responses") {
+            let current = reserialize_response(&name, &golden);
+            assert_superset(&golden, &current, &format!("{}/{}", version, name));
+        }
+    }
+}
+
+#[test]
+fn current_responses_match_golden_exactly() {
+    for (name, golden) in samples(CURRENT_VERSION, "responses") {
+        let current = reserialize_response(&name, &golden);
+        assert_eq!(golden, current, "{}/{} 的序列化結果與樣本不一致", CURRENT_VERSION, name);
+    }
+}
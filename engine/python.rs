@@ -0,0 +1,235 @@
+use crate::{config::EngineConfig, ArbitrageRequest, CommandResponse, EngineCommand, Environment, ExecutionEngine, LogHandle};
+use pyo3::exceptions::{PyRuntimeError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::PyString;
+use serde::{de::DeserializeOwned, Serialize};
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+use tracing_subscriber::{fmt, prelude::*, reload, EnvFilter};
+
+// 進程內只安裝一次 tracing；宿主進程已安裝其他 subscriber 時不接管日誌，set_log_level 不可用
+static LOG_HANDLE: OnceLock<Option<LogHandle>> = OnceLock::new();
+
+fn log_handle() -> Option<LogHandle> {
+    LOG_HANDLE
+        .get_or_init(|| {
+            let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"));
+            let (filter, handle) = reload::Layer::new(filter);
+            tracing_subscriber::registry()
+                .with(filter)
+                .with(fmt::layer())
+                .try_init()
+                .ok()
+                .map(|_| handle)
+        })
+        .clone()
+}
+
+// Python 對象（dict 或 JSON 字符串）經 JSON 轉為 Rust 類型
+fn from_py<T: DeserializeOwned>(value: &Bound<'_, PyAny>) -> PyResult<T> {
+    let json = match value.downcast::<PyString>() {
+        Ok(text) => text.to_str()?.to_string(),
+        Err(_) => value.py().import("json")?.call_method1("dumps", (value,))?.extract()?,
+    };
+    serde_json::from_str(&json).map_err(|e| PyValueError::new_err(e.to_string()))
+}
+
+fn to_py<T: Serialize>(py: Python<'_>, value: &T) -> PyResult<PyObject> {
+    let json = serde_json::to_string(value).map_err(|e| PyValueError::new_err(e.to_string()))?;
+    Ok(py.import("json")?.call_method1("loads", (json,))?.unbind())
+}
+
+// 指令成功時返回 data，失敗時拋出 RuntimeError
+fn command_result(py: Python<'_>, response: CommandResponse) -> PyResult<PyObject> {
+    match response.status.as_str() {
+        "success" => to_py(py, &response.data),
+        _ => Err(PyRuntimeError::new_err(response.error_message.unwrap_or(response.status))),
+    }
+}
+
+fn load_config(config: Option<&Bound<'_, PyAny>>) -> PyResult<EngineConfig> {
+    let config = match config {
+        Some(config) => from_py::<EngineConfig>(config)?,
+        None => return EngineConfig::load().map_err(PyValueError::new_err),
+    };
+    config.validate().map_err(PyValueError::new_err)?;
+    Ok(config)
+}
+
+async fn start(config: EngineConfig, env: Environment) -> PyResult<PyExecutionEngine> {
+    ExecutionEngine::start_with(config, log_handle(), env)
+        .await
+        .map(|engine| PyExecutionEngine { engine })
+        .map_err(PyRuntimeError::new_err)
+}
+
+/// 進程內的套利執行引擎。
+///
+/// 後台任務運行在 pyo3-async-runtimes 管理的 tokio 運行時上。同步方法在等待期間釋放 GIL，
+/// `*_async` 方法返回可在 asyncio 事件循環中 await 的對象。請求與返回值為 dict，字段與 TCP 協議一致。
+#[pyclass(name = "ExecutionEngine", module = "arbitrage_engine", frozen)]
+pub struct PyExecutionEngine {
+    engine: Arc<ExecutionEngine>,
+}
+
+#[pymethods]
+impl PyExecutionEngine {
+    /// 按配置建立並啟動引擎；`config` 缺省時與二進制相同，從 `ARB_ENGINE_CONFIG` 加載。
+    #[new]
+    #[pyo3(signature = (config=None))]
+    fn new(py: Python<'_>, config: Option<&Bound<'_, PyAny>>) -> PyResult<Self> {
+        let config = load_config(config)?;
+        py.allow_threads(|| pyo3_async_runtimes::tokio::get_runtime().block_on(start(config, Environment::default())))
+    }
+
+    /// `ExecutionEngine(config)` 的異步版本。
+    #[staticmethod]
+    #[pyo3(signature = (config=None))]
+    fn start_async<'py>(py: Python<'py>, config: Option<&Bound<'py, PyAny>>) -> PyResult<Bound<'py, PyAny>> {
+        let config = load_config(config)?;
+        pyo3_async_runtimes::tokio::future_into_py(py, start(config, Environment::default()))
+    }
+
+    /// 提交一筆套利請求並返回響應；執行失敗以 `status == "error"` 的響應表示，請求無法解析時拋出 ValueError。
+    fn execute(&self, py: Python<'_>, request: &Bound<'_, PyAny>) -> PyResult<PyObject> {
+        let request: ArbitrageRequest = from_py(request)?;
+        let engine = Arc::clone(&self.engine);
+        let response = py.allow_threads(|| pyo3_async_runtimes::tokio::get_runtime().block_on(engine.execute(request)));
+        to_py(py, &response)
+    }
+
+    fn execute_async<'py>(&self, py: Python<'py>, request: &Bound<'py, PyAny>) -> PyResult<Bound<'py, PyAny>> {
+        let request: ArbitrageRequest = from_py(request)?;
+        let engine = Arc::clone(&self.engine);
+        pyo3_async_runtimes::tokio::future_into_py(py, async move {
+            let response = engine.execute(request).await;
+            Python::with_gil(|py| to_py(py, &response))
+        })
+    }
+
+    /// 事件投影的持倉與 PnL；指定 `at_ms` 或 `at_sequence` 時重建該時點的投影。
+    #[pyo3(signature = (at_ms=None, at_sequence=None))]
+    fn get_positions(&self, py: Python<'_>, at_ms: Option<i64>, at_sequence: Option<u64>) -> PyResult<PyObject> {
+        self.command(py, EngineCommand::GetPositions { at_ms, at_sequence })
+    }
+
+    #[pyo3(signature = (at_ms=None, at_sequence=None))]
+    fn get_positions_async<'py>(
+        &self,
+        py: Python<'py>,
+        at_ms: Option<i64>,
+        at_sequence: Option<u64>,
+    ) -> PyResult<Bound<'py, PyAny>> {
+        self.command_async(py, EngineCommand::GetPositions { at_ms, at_sequence })
+    }
+
+    /// 執行一條控制指令（如 `{"command": "get_latency"}`），返回指令的 data。
+    fn handle_command(&self, py: Python<'_>, command: &Bound<'_, PyAny>) -> PyResult<PyObject> {
+        self.command(py, from_py(command)?)
+    }
+
+    fn handle_command_async<'py>(&self, py: Python<'py>, command: &Bound<'py, PyAny>) -> PyResult<Bound<'py, PyAny>> {
+        self.command_async(py, from_py(command)?)
+    }
+
+    /// 停止接收新執行，限時等待執行中的請求結束後落盤並關閉交易所會話。
+    #[pyo3(signature = (drain_timeout_secs=30.0))]
+    fn shutdown(&self, py: Python<'_>, drain_timeout_secs: f64) {
+        let engine = Arc::clone(&self.engine);
+        let timeout = Duration::from_secs_f64(drain_timeout_secs.max(0.0));
+        py.allow_threads(|| pyo3_async_runtimes::tokio::get_runtime().block_on(engine.shutdown(timeout)));
+    }
+
+    #[pyo3(signature = (drain_timeout_secs=30.0))]
+    fn shutdown_async<'py>(&self, py: Python<'py>, drain_timeout_secs: f64) -> PyResult<Bound<'py, PyAny>> {
+        let engine = Arc::clone(&self.engine);
+        let timeout = Duration::from_secs_f64(drain_timeout_secs.max(0.0));
+        pyo3_async_runtimes::tokio::future_into_py(py, async move {
+            engine.shutdown(timeout).await;
+            Ok(())
+        })
+    }
+}
+
+impl PyExecutionEngine {
+    fn command(&self, py: Python<'_>, command: EngineCommand) -> PyResult<PyObject> {
+        let engine = Arc::clone(&self.engine);
+        let response =
+            py.allow_threads(|| pyo3_async_runtimes::tokio::get_runtime().block_on(engine.handle_command(command)));
+        command_result(py, response)
+    }
+
+    fn command_async<'py>(&self, py: Python<'py>, command: EngineCommand) -> PyResult<Bound<'py, PyAny>> {
+        let engine = Arc::clone(&self.engine);
+        pyo3_async_runtimes::tokio::future_into_py(py, async move {
+            let response = engine.handle_command(command).await;
+            Python::with_gil(|py| command_result(py, response))
+        })
+    }
+}
+
+#[pymodule]
+fn arbitrage_engine(module: &Bound<'_, PyModule>) -> PyResult<()> {
+    module.add_class::<PyExecutionEngine>()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pyo3::types::IntoPyDict;
+
+    // Python 綁定：經解釋器調用同步與 asyncio 方法，請求與返回值為 dict；無法解析的請求與指令拋出 ValueError
+    #[test]
+    fn python_bindings_drive_the_engine_in_process() {
+        let dir = std::env::temp_dir().join(format!("arb-python-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let env = Environment::system().with_vars([
+            ("ARB_EVENT_LOG", dir.join("events.jsonl").display().to_string()),
+            ("ARB_EXECUTION_JOURNAL", dir.join("journal.jsonl").display().to_string()),
+            ("ARB_DATABASE_URL", format!("sqlite://{}?mode=rwc", dir.join("history.db").display())),
+        ]);
+        let mut config = EngineConfig::default();
+        for exchange in ["binance", "bybit"] {
+            config.exchanges.entry(exchange.to_string()).or_default();
+        }
+        let engine = pyo3_async_runtimes::tokio::get_runtime()
+            .block_on(start(config, env))
+            .unwrap_or_else(|error| panic!("{}", error));
+        pyo3::prepare_freethreaded_python();
+        let script = c"
+import asyncio
+
+request = {
+    'strategy_id': 'py', 'symbol': 'BTCUSDT', 'primary_exchange': 'binance',
+    'secondary_exchange': 'bybit', 'amount': 100.0, 'priority': 5, 'timestamp': '0',
+}
+response = engine.execute(request)
+assert response['status'] in ('success', 'error'), response
+assert response['execution_id'], response
+assert 'positions' in engine.get_positions(), engine.get_positions()
+assert isinstance(engine.handle_command({'command': 'get_latency'}), dict)
+for call in (lambda: engine.execute({'amount': 'x'}), lambda: engine.handle_command({'command': 'no_such_command'})):
+    try:
+        call()
+        raise AssertionError('expected ValueError')
+    except ValueError:
+        pass
+
+async def main():
+    response = await engine.execute_async(dict(request, strategy_id='py-async'))
+    assert response['execution_id'], response
+    assert 'positions' in await engine.get_positions_async()
+    assert isinstance(await engine.handle_command_async({'command': 'get_latency'}), dict)
+    await engine.shutdown_async(1.0)
+
+asyncio.run(main())
+engine.shutdown(0.0)
+";
+        Python::with_gil(|py| -> PyResult<()> {
+            let globals = [("engine", Bound::new(py, engine)?)].into_py_dict(py)?;
+            py.run(script, Some(&globals), None)
+        })
+        .unwrap_or_else(|error| panic!("{}", error));
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
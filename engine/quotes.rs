@@ -0,0 +1,128 @@
+use super::config::{QuoteConfig, StalePolicy};
+use dashmap::DashMap;
+use serde::Serialize;
+
+#[derive(Debug, Clone, Default)]
+struct Entry {
+    funding_rate: Option<(f64, i64)>,
+    predicted_rate: Option<(f64, i64)>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct QuoteStatus {
+    pub exchange: String,
+    pub symbol: String,
+    pub funding_rate: Option<f64>,
+    pub predicted_rate: Option<f64>,
+    // 最近一次收到任一費率的時間與距今
+    pub received_at_ms: i64,
+    pub age_ms: i64,
+    // 預測費率缺失或超過 max_age_ms
+    pub stale: bool,
+}
+
+/// 各交易所/交易對最近收到的費率及接收時間。執行時只使用未過期的預測費率，
+/// 推送中斷導致緩存過期時按配置經 REST 刷新或拒絕執行，避免按數分鐘前的行情下單。
+pub struct QuoteCache {
+    config: QuoteConfig,
+    // "exchange:symbol" -> 費率與接收時間；行情寫入與執行讀取按鍵分片加鎖
+    entries: DashMap<String, Entry>,
+}
+
+impl QuoteCache {
+    pub fn new(config: QuoteConfig) -> Self {
+        Self {
+            config,
+            entries: DashMap::new(),
+        }
+    }
+
+    pub fn policy(&self) -> StalePolicy {
+        self.config.on_stale
+    }
+
+    pub fn record_funding_rate(&self, exchange: &str, symbol: &str, rate: f64, received_at_ms: i64) {
+        self.entries.entry(format!("{}:{}", exchange, symbol)).or_default().funding_rate = Some((rate, received_at_ms));
+    }
+
+    pub fn record_predicted_rate(&self, exchange: &str, symbol: &str, rate: f64, received_at_ms: i64) {
+        self.entries.entry(format!("{}:{}", exchange, symbol)).or_default().predicted_rate = Some((rate, received_at_ms));
+    }
+
+    // 未過期的預測費率；缺失或過期時返回原因
+    pub fn predicted_rate(&self, exchange: &str, symbol: &str, now_ms: i64) -> Result<f64, String> {
+        let Some((rate, received_at_ms)) = self.entries.get(&format!("{}:{}", exchange, symbol)).and_then(|entry| entry.predicted_rate) else {
+            return Err(format!("{} {} 尚無預測費率行情", exchange, symbol));
+        };
+        let age_ms = now_ms - received_at_ms;
+        if age_ms > self.config.max_age_ms as i64 {
+            return Err(format!("{} {} 預測費率已 {}ms 未更新，超過 {}ms", exchange, symbol, age_ms, self.config.max_age_ms));
+        }
+        Ok(rate)
+    }
+
+    pub fn snapshot(&self, now_ms: i64) -> Vec<QuoteStatus> {
+        let mut quotes: Vec<QuoteStatus> = self
+            .entries
+            .iter()
+            .map(|item| {
+                let (key, entry) = item.pair();
+                let (exchange, symbol) = key.split_once(':').unwrap_or((key, ""));
+                let received_at_ms = [entry.funding_rate, entry.predicted_rate]
+                    .into_iter()
+                    .flatten()
+                    .map(|(_, at_ms)| at_ms)
+                    .max()
+                    .unwrap_or_default();
+                QuoteStatus {
+                    exchange: exchange.to_string(),
+                    symbol: symbol.to_string(),
+                    funding_rate: entry.funding_rate.map(|(rate, _)| rate),
+                    predicted_rate: entry.predicted_rate.map(|(rate, _)| rate),
+                    received_at_ms,
+                    age_ms: now_ms - received_at_ms,
+                    stale: entry
+                        .predicted_rate
+                        .is_none_or(|(_, at_ms)| now_ms - at_ms > self.config.max_age_ms as i64),
+                }
+            })
+            .collect();
+        quotes.sort_by(|a, b| (&a.exchange, &a.symbol).cmp(&(&b.exchange, &b.symbol)));
+        quotes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bench::START_MS;
+    use crate::deterministic_sim::build;
+    use crate::{config, Environment};
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn stale_quotes_refresh_or_refuse_execution() {
+        let mut config = config::EngineConfig::default();
+        config.quotes.on_stale = config::StalePolicy::Refuse;
+        let (engine, path) = build("stale_quotes", config, Environment::simulated(START_MS, 1));
+        let error = engine.fresh_predicted_rate("binance", "BTCUSDT").await.unwrap_err();
+        assert!(error.contains("尚無預測費率"), "{}", error);
+
+        // 收到的費率在有效期內直接使用，不再請求交易所
+        let rate = engine.get_predicted_funding_rate("binance", "BTCUSDT").await.unwrap();
+        engine.env.clock.sleep(Duration::from_millis(1_000)).await;
+        assert_eq!(engine.fresh_predicted_rate("binance", "BTCUSDT").await.unwrap(), rate);
+
+        engine.env.clock.sleep(Duration::from_millis(5_000)).await;
+        let error = engine.fresh_predicted_rate("binance", "BTCUSDT").await.unwrap_err();
+        assert!(error.starts_with("行情過期"), "{}", error);
+        assert!(engine.quotes.snapshot(engine.env.now_ms()).iter().any(|quote| quote.exchange == "binance" && quote.stale));
+
+        // 默認策略下過期時經 REST 刷新
+        let (refresh, refresh_path) = build("stale_quotes_refresh", config::EngineConfig::default(), Environment::simulated(START_MS, 1));
+        assert!(refresh.fresh_predicted_rate("bybit", "BTCUSDT").await.is_ok());
+        assert!(refresh.quotes.predicted_rate("bybit", "BTCUSDT", refresh.env.now_ms()).is_ok());
+        for path in [path, refresh_path] {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+}
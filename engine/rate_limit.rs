@@ -0,0 +1,268 @@
+use super::config::RateLimitConfig;
+use reqwest::header::HeaderMap;
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tracing::{debug, warn};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RequestKind {
+    Order,
+    MarketData,
+}
+
+struct Bucket {
+    capacity: f64,
+    refill_per_sec: f64,
+    tokens: f64,
+    updated: Instant,
+}
+
+impl Bucket {
+    fn new(capacity: f64, window: Duration) -> Self {
+        Self {
+            capacity,
+            refill_per_sec: capacity / window.as_secs_f64(),
+            tokens: capacity,
+            updated: Instant::now(),
+        }
+    }
+
+    fn refill(&mut self, now: Instant) {
+        let elapsed = now.duration_since(self.updated).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.refill_per_sec).min(self.capacity);
+        self.updated = now;
+    }
+
+    // 餘量達到 floor + amount 所需的等待時間
+    fn wait_for(&self, amount: f64, floor: f64) -> Duration {
+        let missing = floor + amount - self.tokens;
+        if missing <= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(missing / self.refill_per_sec)
+        }
+    }
+}
+
+struct State {
+    weight: Bucket,
+    orders: Bucket,
+    shed: u64,
+    delayed: u64,
+    throttled: u64,
+    // 交易所最近一次回報的窗口內已用權重
+    reported_weight: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SchedulerSnapshot {
+    pub weight_available: f64,
+    pub weight_capacity: f64,
+    pub orders_available: f64,
+    pub orders_capacity: f64,
+    pub reported_weight: Option<f64>,
+    pub shed: u64,
+    pub delayed: u64,
+    // 交易所返回 429 的次數
+    pub throttled: u64,
+}
+
+pub struct RequestScheduler {
+    exchange: String,
+    config: RateLimitConfig,
+    state: Mutex<State>,
+}
+
+impl RequestScheduler {
+    pub fn new(exchange: &str, config: RateLimitConfig) -> Self {
+        let state = State {
+            weight: Bucket::new(config.weight_limit as f64, Duration::from_secs(config.weight_window_secs)),
+            orders: Bucket::new(config.order_limit as f64, Duration::from_secs(config.order_window_secs)),
+            shed: 0,
+            delayed: 0,
+            throttled: 0,
+            reported_weight: None,
+        };
+        Self {
+            exchange: exchange.to_string(),
+            config,
+            state: Mutex::new(state),
+        }
+    }
+
+    pub fn market_data_weight(&self) -> u32 {
+        self.config.market_data_weight
+    }
+
+    // 獲取配額，必要時排隊等待；下單最多等待 max_order_wait_ms，行情請求最多等待 max_market_data_wait_ms
+    pub async fn acquire(&self, kind: RequestKind, weight: u32) -> Result<(), String> {
+        let weight = weight as f64;
+        let (max_wait, floor) = match kind {
+            RequestKind::Order => (Duration::from_millis(self.config.max_order_wait_ms), 0.0),
+            RequestKind::MarketData => (
+                Duration::from_millis(self.config.max_market_data_wait_ms),
+                self.config.reserved_weight_fraction * self.config.weight_limit as f64,
+            ),
+        };
+        let started = Instant::now();
+        let mut delayed = false;
+        loop {
+            let wait = {
+                let mut state = self.state.lock().unwrap();
+                let now = Instant::now();
+                state.weight.refill(now);
+                state.orders.refill(now);
+                let wait = match kind {
+                    RequestKind::Order => state.weight.wait_for(weight, floor).max(state.orders.wait_for(1.0, 0.0)),
+                    RequestKind::MarketData => state.weight.wait_for(weight, floor),
+                };
+                if wait.is_zero() {
+                    state.weight.tokens -= weight;
+                    if kind == RequestKind::Order {
+                        state.orders.tokens -= 1.0;
+                    }
+                    if delayed {
+                        state.delayed += 1;
+                    }
+                    return Ok(());
+                }
+                if started.elapsed() + wait > max_wait {
+                    state.shed += 1;
+                    warn!(exchange = %self.exchange, ?kind, wait_ms = wait.as_millis() as u64, "限頻配額不足，請求已丟棄");
+                    return Err(format!("{} 限頻配額不足，請求已丟棄", self.exchange));
+                }
+                wait
+            };
+            delayed = true;
+            debug!(exchange = %self.exchange, ?kind, wait_ms = wait.as_millis() as u64, "等待限頻配額");
+            tokio::time::sleep(wait.max(Duration::from_millis(1))).await;
+        }
+    }
+
+    // 按交易所響應頭回報的已用權重校準本地餘量，只向更保守的方向調整
+    pub fn observe_headers(&self, headers: &HeaderMap) {
+        let Some(used) = self
+            .config
+            .used_weight_header
+            .as_deref()
+            .and_then(|name| headers.get(name))
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.parse::<f64>().ok())
+        else {
+            return;
+        };
+        let mut state = self.state.lock().unwrap();
+        state.weight.refill(Instant::now());
+        state.weight.tokens = state.weight.tokens.min(state.weight.capacity - used);
+        state.reported_weight = Some(used);
+    }
+
+    // 交易所返回 429：清空權重餘量並透支到 Retry-After 之後才恢復，期間的請求排隊或被丟棄
+    pub fn throttled(&self, retry_after: Duration) {
+        let mut state = self.state.lock().unwrap();
+        state.weight.refill(Instant::now());
+        state.weight.tokens = -state.weight.refill_per_sec * retry_after.as_secs_f64();
+        state.throttled += 1;
+        warn!(exchange = %self.exchange, retry_after_ms = retry_after.as_millis() as u64, "交易所返回 429，暫停請求");
+    }
+
+    pub fn snapshot(&self) -> SchedulerSnapshot {
+        let mut state = self.state.lock().unwrap();
+        let now = Instant::now();
+        state.weight.refill(now);
+        state.orders.refill(now);
+        SchedulerSnapshot {
+            weight_available: state.weight.tokens,
+            weight_capacity: state.weight.capacity,
+            orders_available: state.orders.tokens,
+            orders_capacity: state.orders.capacity,
+            reported_weight: state.reported_weight,
+            shed: state.shed,
+            delayed: state.delayed,
+            throttled: state.throttled,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bench::START_MS;
+    use crate::deterministic_sim::{build, funded_config};
+    use crate::{config, EngineCommand, Environment};
+
+    // 令牌桶：行情請求不能動用為下單保留的權重，下單另受訂單數上限約束；配額不足時在等待上限內排隊，否則丟棄。
+    // 響應頭回報的已用權重只向更保守的方向校準餘量，429 後透支到 Retry-After 之後
+    #[tokio::test]
+    async fn request_scheduler_reserves_weight_for_orders_and_follows_exchange_reports() {
+        use {RequestKind, RequestScheduler};
+        let config = config::RateLimitConfig {
+            weight_limit: 10,
+            weight_window_secs: 100,
+            order_limit: 2,
+            order_window_secs: 100,
+            reserved_weight_fraction: 0.3,
+            max_order_wait_ms: 0,
+            max_market_data_wait_ms: 0,
+            used_weight_header: Some("x-mbx-used-weight-1m".to_string()),
+            ..Default::default()
+        };
+        let scheduler = RequestScheduler::new("binance", config.clone());
+        for _ in 0..7 {
+            scheduler.acquire(RequestKind::MarketData, 1).await.unwrap();
+        }
+        assert!(scheduler.acquire(RequestKind::MarketData, 1).await.unwrap_err().contains("限頻配額不足"));
+        scheduler.acquire(RequestKind::Order, 1).await.unwrap();
+        scheduler.acquire(RequestKind::Order, 1).await.unwrap();
+        // 權重仍有餘量，但訂單數已用完
+        assert!(scheduler.acquire(RequestKind::Order, 1).await.is_err());
+        let snapshot = scheduler.snapshot();
+        assert_eq!((snapshot.shed, snapshot.delayed), (2, 0));
+        assert!(snapshot.orders_available < 1.0 && snapshot.weight_available >= 1.0);
+
+        let headers = |used: &str| {
+            let mut headers = reqwest::header::HeaderMap::new();
+            headers.insert("x-mbx-used-weight-1m", used.parse().unwrap());
+            headers
+        };
+        let scheduler = RequestScheduler::new("binance", config.clone());
+        scheduler.observe_headers(&headers("8"));
+        let snapshot = scheduler.snapshot();
+        assert_eq!(snapshot.reported_weight, Some(8.0));
+        assert!(snapshot.weight_available <= 2.01);
+        scheduler.observe_headers(&headers("1"));
+        assert!(scheduler.snapshot().weight_available <= 2.01);
+
+        // 429：權重透支 Retry-After × 每秒恢復量
+        let scheduler = RequestScheduler::new("binance", config::RateLimitConfig { max_order_wait_ms: 5_000, ..config });
+        scheduler.throttled(Duration::from_secs(10));
+        let snapshot = scheduler.snapshot();
+        assert_eq!(snapshot.throttled, 1);
+        assert!((snapshot.weight_available + 1.0).abs() < 0.01, "{}", snapshot.weight_available);
+        assert!(scheduler.acquire(RequestKind::MarketData, 1).await.is_err());
+
+        // 配額在等待上限內恢復時下單排隊後放行
+        let scheduler = RequestScheduler::new(
+            "bybit",
+            config::RateLimitConfig { weight_limit: 2, weight_window_secs: 1, max_order_wait_ms: 2_000, ..Default::default() },
+        );
+        scheduler.acquire(RequestKind::Order, 2).await.unwrap();
+        let started = Instant::now();
+        scheduler.acquire(RequestKind::Order, 2).await.unwrap();
+        assert!(started.elapsed() >= Duration::from_millis(900));
+        assert_eq!(scheduler.snapshot().delayed, 1);
+
+        // 模擬連接器在資金費率響應中回報已用權重，並經 get_rate_limits 匯總
+        let mut config = funded_config();
+        config.exchanges.get_mut("binance").unwrap().rate_limit.used_weight_header = Some("x-mbx-used-weight-1m".to_string());
+        let (engine, path) = build("rate-limit", config, Environment::simulated(START_MS, 1));
+        engine.get_funding_rate("binance", "BTCUSDT").await.unwrap();
+        let response = engine.handle_command(EngineCommand::GetRateLimits).await;
+        let limits = response.data.unwrap();
+        assert_eq!(limits["binance"]["reported_weight"], 1.0);
+        assert!(limits["bybit"]["reported_weight"].is_null());
+        let _ = std::fs::remove_file(path);
+    }
+}
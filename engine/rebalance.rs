@@ -0,0 +1,347 @@
+use super::config::{self, RebalanceConfig, Severity};
+use super::{events, risk, ExecutionEngine};
+use serde::Serialize;
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tracing::{info, warn};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TransferKind {
+    // 同一交易所的賬戶之間
+    Internal,
+    // 跨交易所提幣到白名單地址
+    Withdrawal,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PlannedTransfer {
+    pub asset: String,
+    // 連接器鍵，如 binance 或 binance@sub1
+    pub from: String,
+    pub to: String,
+    pub amount: f64,
+    pub kind: TransferKind,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TransferRecord {
+    #[serde(flatten)]
+    pub transfer: PlannedTransfer,
+    pub executed_at_ms: i64,
+    pub status: String,
+    pub withdrawal_id: Option<String>,
+    pub error_message: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RebalanceReport {
+    pub dry_run: bool,
+    pub planned: Vec<PlannedTransfer>,
+    // 因缺少餘額數據、白名單地址或達到每日上限而未劃轉的原因
+    pub skipped: Vec<String>,
+    pub executed: Vec<TransferRecord>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RebalanceSnapshot {
+    pub enabled: bool,
+    pub transferred_today: BTreeMap<String, f64>,
+    pub history: Vec<TransferRecord>,
+}
+
+#[derive(Default)]
+struct State {
+    day_start_ms: i64,
+    // 資產 -> 當日已劃轉量
+    transferred: BTreeMap<String, f64>,
+    history: VecDeque<TransferRecord>,
+}
+
+/// 庫存再平衡：資金費率套利使穩定幣逐漸集中到一側交易所，按配置的目標佔比把富餘賬戶的餘額劃回不足的賬戶。
+/// 同一交易所內優先走內部劃轉，跨交易所只提幣到白名單地址；單筆與每日劃轉量受限。
+pub struct Rebalancer {
+    config: RebalanceConfig,
+    state: Mutex<State>,
+    // 定時任務與 rebalance 指令不並發劃轉
+    running: AtomicBool,
+}
+
+impl Rebalancer {
+    pub fn new(config: RebalanceConfig) -> Self {
+        Self {
+            config,
+            state: Mutex::new(State::default()),
+            running: AtomicBool::new(false),
+        }
+    }
+
+    pub fn config(&self) -> &RebalanceConfig {
+        &self.config
+    }
+
+    pub fn snapshot(&self, now_ms: i64) -> RebalanceSnapshot {
+        let mut state = self.state.lock().unwrap();
+        Self::roll_day(&mut state, now_ms);
+        RebalanceSnapshot {
+            enabled: self.config.enabled,
+            transferred_today: state.transferred.clone(),
+            history: state.history.iter().cloned().collect(),
+        }
+    }
+
+    fn roll_day(state: &mut State, now_ms: i64) {
+        let day_start_ms = risk::day_start_ms(now_ms);
+        if state.day_start_ms != day_start_ms {
+            state.day_start_ms = day_start_ms;
+            state.transferred.clear();
+        }
+    }
+
+    /// 按各賬戶的可用餘額（資產 -> 連接器鍵 -> 可用量）計算劃轉計劃。偏離目標佔比不超過 tolerance 的資產不動；
+    /// 否則把富餘方劃到目標佔比，先配對同一交易所的賬戶，再跨交易所配對。
+    pub fn plan(&self, balances: &BTreeMap<String, BTreeMap<String, f64>>, now_ms: i64) -> (Vec<PlannedTransfer>, Vec<String>) {
+        let mut state = self.state.lock().unwrap();
+        Self::roll_day(&mut state, now_ms);
+        let mut planned = Vec::new();
+        let mut skipped = Vec::new();
+        for (asset, targets) in &self.config.targets {
+            let Some(free) = targets
+                .keys()
+                .map(|key| balances.get(asset).and_then(|by_key| by_key.get(key)).map(|free| (key, *free)))
+                .collect::<Option<BTreeMap<&String, f64>>>()
+            else {
+                skipped.push(format!("{} 尚無全部賬戶的餘額數據", asset));
+                continue;
+            };
+            let total: f64 = free.values().sum();
+            if total <= 0.0 {
+                continue;
+            }
+            // 正為富餘，負為不足
+            let mut deviations: Vec<(&String, f64)> = targets.iter().map(|(key, weight)| (key, free[key] - weight * total)).collect();
+            if deviations.iter().all(|(_, deviation)| deviation.abs() / total <= self.config.tolerance) {
+                continue;
+            }
+            let mut remaining = self.config.daily_limit - state.transferred.get(asset).copied().unwrap_or_default();
+            deviations.sort_by(|a, b| b.1.abs().total_cmp(&a.1.abs()));
+            for same_venue in [true, false] {
+                for deficit in 0..deviations.len() {
+                    for surplus in 0..deviations.len() {
+                        let (to, need) = deviations[deficit];
+                        let (from, excess) = deviations[surplus];
+                        if need >= 0.0 || excess <= 0.0 || (config::venue(from) == config::venue(to)) != same_venue {
+                            continue;
+                        }
+                        let amount = excess.min(-need).min(self.config.max_transfer).min(remaining);
+                        if amount < self.config.min_transfer {
+                            if remaining < self.config.min_transfer {
+                                skipped.push(format!("{} 已達當日劃轉上限 {}", asset, self.config.daily_limit));
+                            }
+                            continue;
+                        }
+                        let kind = if same_venue { TransferKind::Internal } else { TransferKind::Withdrawal };
+                        if kind == TransferKind::Withdrawal && self.address(config::venue(to), asset).is_none() {
+                            skipped.push(format!("{} 未配置 {} 的白名單充值地址，不從 {} 提幣", config::venue(to), asset, from));
+                            continue;
+                        }
+                        deviations[deficit].1 += amount;
+                        deviations[surplus].1 -= amount;
+                        remaining -= amount;
+                        planned.push(PlannedTransfer {
+                            asset: asset.clone(),
+                            from: from.clone(),
+                            to: to.clone(),
+                            amount,
+                            kind,
+                        });
+                    }
+                }
+            }
+        }
+        skipped.dedup();
+        (planned, skipped)
+    }
+
+    fn address(&self, venue: &str, asset: &str) -> Option<&config::WithdrawalAddress> {
+        self.config.withdrawal_addresses.get(venue).and_then(|addresses| addresses.get(asset))
+    }
+
+    fn record(&self, record: TransferRecord) {
+        let mut state = self.state.lock().unwrap();
+        if record.status == "completed" {
+            *state.transferred.entry(record.transfer.asset.clone()).or_default() += record.transfer.amount;
+        }
+        state.history.push_back(record);
+        while state.history.len() > self.config.history {
+            state.history.pop_front();
+        }
+    }
+}
+
+// 從用戶數據流維護的賬戶狀態讀取 targets 中各賬戶的可用餘額；其他區域實例執行的交易所不參與
+fn balances(engine: &ExecutionEngine) -> BTreeMap<String, BTreeMap<String, f64>> {
+    let mut balances: BTreeMap<String, BTreeMap<String, f64>> = BTreeMap::new();
+    for (asset, targets) in &engine.rebalancer.config.targets {
+        for key in targets.keys() {
+            if engine.routing.is_remote(key) {
+                continue;
+            }
+            let free = engine.user_streams.account(key).and_then(|account| account.balances.get(asset).map(|balance| balance.free));
+            if let Some(free) = free {
+                balances.entry(asset.clone()).or_default().insert(key.clone(), free);
+            }
+        }
+    }
+    balances
+}
+
+/// 計算並執行一輪再平衡；`dry_run` 時只返回計劃。每筆劃轉完成後以 Transfer 事件寫入事件日誌，失敗時告警。
+pub(crate) async fn run(engine: &ExecutionEngine, dry_run: bool) -> Result<RebalanceReport, String> {
+    let rebalancer = &engine.rebalancer;
+    if rebalancer.running.swap(true, Ordering::AcqRel) {
+        return Err("再平衡正在進行".to_string());
+    }
+    let (planned, skipped) = rebalancer.plan(&balances(engine), engine.env.now_ms());
+    let mut executed = Vec::new();
+    if !dry_run {
+        for transfer in &planned {
+            let record = execute(engine, transfer).await;
+            match &record.error_message {
+                Some(error) => {
+                    warn!(asset = %transfer.asset, from = %transfer.from, to = %transfer.to, amount = transfer.amount, %error, "再平衡劃轉失敗");
+                    engine.alert(
+                        Severity::Warning,
+                        "再平衡劃轉失敗",
+                        format!("{} {:.2} {} -> {}: {}", transfer.asset, transfer.amount, transfer.from, transfer.to, error),
+                    );
+                }
+                None => {
+                    info!(asset = %transfer.asset, from = %transfer.from, to = %transfer.to, amount = transfer.amount, kind = ?transfer.kind, "再平衡劃轉完成");
+                    engine.events.append(events::EngineEvent::Transfer {
+                        asset: transfer.asset.clone(),
+                        from_exchange: transfer.from.clone(),
+                        to_exchange: transfer.to.clone(),
+                        amount: transfer.amount,
+                    });
+                }
+            }
+            rebalancer.record(record.clone());
+            executed.push(record);
+        }
+    }
+    rebalancer.running.store(false, Ordering::Release);
+    Ok(RebalanceReport {
+        dry_run,
+        planned,
+        skipped,
+        executed,
+    })
+}
+
+pub(crate) async fn execute(engine: &ExecutionEngine, transfer: &PlannedTransfer) -> TransferRecord {
+    let result = async {
+        let connector = |key: &str| engine.exchanges.get(key).ok_or_else(|| format!("不支持的交易所: {}", key));
+        let (from, to) = (connector(&transfer.from)?, connector(&transfer.to)?);
+        let withdrawal_id = match transfer.kind {
+            TransferKind::Internal => {
+                let account = transfer.to.split_once('@').map_or(config::MAIN_ACCOUNT, |(_, account)| account);
+                from.internal_transfer(&transfer.asset, transfer.amount, account).await?;
+                None
+            }
+            TransferKind::Withdrawal => {
+                let address = engine
+                    .rebalancer
+                    .address(config::venue(&transfer.to), &transfer.asset)
+                    .ok_or("目標不在提幣白名單中")?;
+                Some(from.withdraw(&transfer.asset, transfer.amount, address).await?)
+            }
+        };
+        to.credit(&transfer.asset, transfer.amount)?;
+        Ok::<_, String>(withdrawal_id)
+    }
+    .await;
+    let (status, withdrawal_id, error_message) = match result {
+        Ok(withdrawal_id) => ("completed", withdrawal_id, None),
+        Err(e) => ("failed", None, Some(e)),
+    };
+    TransferRecord {
+        transfer: transfer.clone(),
+        executed_at_ms: engine.env.now_ms(),
+        status: status.to_string(),
+        withdrawal_id,
+        error_message,
+    }
+}
+
+pub fn spawn(engine: Arc<ExecutionEngine>) {
+    let config = engine.rebalancer.config().clone();
+    if !config.enabled || config.targets.is_empty() {
+        info!("庫存再平衡未啟用");
+        return;
+    }
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(config.interval_secs));
+        // 啟動時用戶數據流尚未同步餘額，跳過首個立即觸發的 tick
+        interval.tick().await;
+        loop {
+            interval.tick().await;
+            if let Err(e) = run(&engine, false).await {
+                warn!(error = %e, "再平衡未執行");
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bench::START_MS;
+    use crate::deterministic_sim::build;
+    use crate::Environment;
+
+    #[tokio::test]
+    async fn rebalancer_restores_targets_within_whitelist() {
+        let mut config = config::EngineConfig::default();
+        config.exchanges.entry("binance".to_string()).or_default().accounts.insert("iso".to_string(), Default::default());
+        for (key, usdt) in [("binance", 9_000.0), ("binance@iso", 0.0), ("bybit", 1_000.0)] {
+            config.spot_arbitrage.inventory.insert(key.to_string(), [("USDT".to_string(), usdt)].into());
+        }
+        config.rebalance.targets.insert(
+            "USDT".to_string(),
+            [("binance".to_string(), 0.5), ("binance@iso".to_string(), 0.25), ("bybit".to_string(), 0.25)].into(),
+        );
+        let (engine, path) = build("rebalance", config.clone(), Environment::simulated(START_MS, 1));
+        for key in ["binance", "binance@iso", "bybit"] {
+            let snapshot = engine.exchanges[key].fetch_account_snapshot().await.unwrap();
+            engine.user_streams.reconcile(key, snapshot);
+        }
+
+        // bybit 沒有白名單地址，只做同一交易所內的劃轉
+        let report = run(&engine, true).await.unwrap();
+        assert_eq!(report.planned.len(), 1);
+        assert_eq!((report.planned[0].to.as_str(), report.planned[0].amount), ("binance@iso", 2_500.0));
+        assert!(report.skipped[0].contains("白名單"));
+        let _ = std::fs::remove_file(path);
+
+        config.rebalance.withdrawal_addresses.insert(
+            "bybit".to_string(),
+            [("USDT".to_string(), config::WithdrawalAddress { address: "0xbybit".to_string(), network: "ERC20".to_string() })].into(),
+        );
+        let (engine, path) = build("rebalance_whitelisted", config, Environment::simulated(START_MS, 1));
+        for key in ["binance", "binance@iso", "bybit"] {
+            let snapshot = engine.exchanges[key].fetch_account_snapshot().await.unwrap();
+            engine.user_streams.reconcile(key, snapshot);
+        }
+        let report = run(&engine, false).await.unwrap();
+        let kinds: Vec<_> = report.executed.iter().map(|record| (record.transfer.kind, record.status.as_str())).collect();
+        assert_eq!(kinds, [(TransferKind::Internal, "completed"), (TransferKind::Withdrawal, "completed")]);
+        let free = |key: &str| engine.exchanges[key].account.lock().unwrap().balances["USDT"].free;
+        assert_eq!((free("binance"), free("binance@iso"), free("bybit")), (5_000.0, 2_500.0, 2_500.0));
+        assert_eq!(engine.events.current().transfers["binance:USDT"], -4_000.0);
+        assert_eq!(engine.rebalancer.snapshot(engine.env.now_ms()).transferred_today["USDT"], 4_000.0);
+        let _ = std::fs::remove_file(path);
+    }
+}
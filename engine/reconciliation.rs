@@ -0,0 +1,241 @@
+use super::config::{ReconciliationConfig, Severity};
+use super::events::EngineEvent;
+use super::ExecutionEngine;
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tracing::{info, warn};
+
+// 不再變化的訂單狀態，不必向交易所核對
+const TERMINAL_STATUSES: [&str; 5] = ["filled", "canceled", "cancelled", "rejected", "expired"];
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Discrepancy {
+    pub exchange: String,
+    // position / balance / order
+    pub kind: &'static str,
+    // 持倉為交易對，餘額為 "資產:free" / "資產:locked"，訂單為交易所訂單號
+    pub key: String,
+    pub local: f64,
+    // 交易所沒有該訂單時為 None
+    pub remote: Option<f64>,
+    pub corrected: bool,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ReconciliationReport {
+    pub started_at_ms: i64,
+    pub finished_at_ms: i64,
+    pub auto_correct: bool,
+    pub discrepancies: Vec<Discrepancy>,
+    // 拉取快照失敗的交易所
+    pub errors: BTreeMap<String, String>,
+}
+
+pub struct Reconciler {
+    config: ReconciliationConfig,
+    last: Mutex<Option<ReconciliationReport>>,
+}
+
+impl Reconciler {
+    pub fn new(config: ReconciliationConfig) -> Self {
+        Self {
+            config,
+            last: Mutex::new(None),
+        }
+    }
+
+    pub fn config(&self) -> &ReconciliationConfig {
+        &self.config
+    }
+
+    pub fn last_report(&self) -> Option<ReconciliationReport> {
+        self.last.lock().unwrap().clone()
+    }
+}
+
+pub fn spawn(engine: Arc<ExecutionEngine>) {
+    let config = engine.reconciler.config().clone();
+    if !config.enabled {
+        info!("狀態對賬未啟用");
+        return;
+    }
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(config.interval_secs));
+        // 首次對賬等一個週期，讓會話與用戶數據流先建立
+        interval.tick().await;
+        loop {
+            interval.tick().await;
+            run(&engine, config.auto_correct).await;
+        }
+    });
+}
+
+// 逐個本地執行的交易所拉取 REST 賬戶快照核對；auto_correct 時持倉以修正事件寫入事件日誌，餘額以快照覆蓋
+pub(crate) async fn run(engine: &ExecutionEngine, auto_correct: bool) -> ReconciliationReport {
+    let config = engine.reconciler.config();
+    let mut report = ReconciliationReport {
+        started_at_ms: engine.env.now_ms(),
+        auto_correct,
+        ..ReconciliationReport::default()
+    };
+    let mut exchanges: Vec<&String> = engine.exchanges.keys().filter(|exchange| !engine.routing.is_remote(exchange)).collect();
+    exchanges.sort();
+    for exchange in exchanges {
+        let connector = &engine.exchanges[exchange];
+        let snapshot = match connector.fetch_account_snapshot().await {
+            Ok(snapshot) => snapshot,
+            Err(error) => {
+                warn!(%exchange, %error, "對賬拉取賬戶快照失敗");
+                report.errors.insert(exchange.clone(), error);
+                continue;
+            }
+        };
+
+        // 持倉：事件日誌投影 vs 交易所
+        let projection = engine.events.current();
+        let prefix = format!("{}:", exchange);
+        let local: BTreeMap<&str, f64> = projection
+            .positions
+            .iter()
+            .filter_map(|(key, quantity)| key.strip_prefix(&prefix).map(|symbol| (symbol, *quantity)))
+            .collect();
+        let symbols: BTreeSet<&str> = local.keys().copied().chain(snapshot.positions.keys().map(String::as_str)).collect();
+        for symbol in symbols {
+            let local = local.get(symbol).copied().unwrap_or_default();
+            let remote = snapshot.positions.get(symbol).copied().unwrap_or_default();
+            if (local - remote).abs() <= config.position_tolerance {
+                continue;
+            }
+            if auto_correct {
+                engine.events.append(EngineEvent::PositionAdjusted {
+                    exchange: exchange.clone(),
+                    symbol: symbol.to_string(),
+                    quantity: remote - local,
+                    reason: "reconciliation".to_string(),
+                });
+            }
+            report.discrepancies.push(Discrepancy {
+                exchange: exchange.clone(),
+                kind: "position",
+                key: symbol.to_string(),
+                local,
+                remote: Some(remote),
+                corrected: auto_correct,
+            });
+        }
+
+        // 餘額：用戶數據流 vs 交易所
+        let account = engine.user_streams.account(exchange).unwrap_or_default();
+        let assets: BTreeSet<&String> = account.balances.keys().chain(snapshot.balances.keys()).collect();
+        let mut balance_diffs = Vec::new();
+        for asset in assets {
+            let local = account.balances.get(asset).copied().unwrap_or_default();
+            let remote = snapshot.balances.get(asset).copied().unwrap_or_default();
+            for (field, local, remote) in [("free", local.free, remote.free), ("locked", local.locked, remote.locked)] {
+                if (local - remote).abs() > config.balance_tolerance {
+                    balance_diffs.push(Discrepancy {
+                        exchange: exchange.clone(),
+                        kind: "balance",
+                        key: format!("{}:{}", asset, field),
+                        local,
+                        remote: Some(remote),
+                        corrected: auto_correct,
+                    });
+                }
+            }
+        }
+        if auto_correct && !balance_diffs.is_empty() {
+            engine.user_streams.reconcile(exchange, snapshot);
+        }
+        report.discrepancies.extend(balance_diffs);
+
+        // 未完結訂單：按 client_order_id 查詢交易所的已成交數量
+        let open = account
+            .orders
+            .iter()
+            .filter(|order| !order.client_order_id.is_empty() && !TERMINAL_STATUSES.contains(&order.status.to_lowercase().as_str()));
+        for order in open {
+            let remote = match connector.order_status(&order.symbol, &order.client_order_id).await {
+                Ok(remote) => remote,
+                Err(error) => {
+                    report.errors.insert(exchange.clone(), error);
+                    break;
+                }
+            };
+            if remote.is_some_and(|filled| (filled - order.filled_quantity).abs() <= config.position_tolerance) {
+                continue;
+            }
+            report.discrepancies.push(Discrepancy {
+                exchange: exchange.clone(),
+                kind: "order",
+                key: order.order_id.clone(),
+                local: order.filled_quantity,
+                remote,
+                corrected: false,
+            });
+        }
+    }
+    report.finished_at_ms = engine.env.now_ms();
+
+    if !report.discrepancies.is_empty() {
+        warn!(discrepancies = report.discrepancies.len(), auto_correct, "對賬發現本地狀態與交易所不一致");
+        let summary: Vec<String> = report
+            .discrepancies
+            .iter()
+            .take(10)
+            .map(|diff| format!("{} {} {}: 本地 {} / 交易所 {:?}", diff.exchange, diff.kind, diff.key, diff.local, diff.remote))
+            .collect();
+        let title = if auto_correct { "對賬發現差異，已按交易所修正" } else { "對賬發現差異" };
+        engine.alert(Severity::Warning, title, summary.join("\n"));
+    }
+    *engine.reconciler.last.lock().unwrap() = Some(report.clone());
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bench::START_MS;
+    use crate::deterministic_sim::{build, request};
+    use crate::{config, ArbitrageResponse, ChildOrder, EngineCommand, Environment};
+
+    #[tokio::test]
+    async fn reconciliation_flags_and_corrects_drift() {
+        let (engine, path) = build("reconcile", config::EngineConfig::default(), Environment::simulated(START_MS, 1));
+        // 交易所側成交後，本地事件日誌漏記一條腿、多記一條腿
+        let fill = |exchange: &str, side: &str| ChildOrder {
+            leg: "long".to_string(),
+            exchange: exchange.to_string(),
+            symbol: "BTCUSDT".to_string(),
+            side: side.to_string(),
+            quantity: 2.0,
+            filled_quantity: 2.0,
+            fee: 0.0,
+            status: "filled".to_string(),
+        };
+        engine.exchanges["binance"].simulate_fill("e1", &fill("binance", "buy"), None);
+        let response = ArbitrageResponse::error("");
+        engine.record_execution_events("e2", &request(2.0), &response, 0.0, &[fill("bybit", "sell")]);
+
+        let report = run(&engine, false).await;
+        let positions: Vec<_> = report
+            .discrepancies
+            .iter()
+            .filter(|diff| diff.kind == "position")
+            .map(|diff| (diff.exchange.as_str(), diff.local, diff.remote))
+            .collect();
+        assert_eq!(positions, [("binance", 0.0, Some(2.0)), ("bybit", -2.0, Some(0.0))]);
+        assert!(report.discrepancies.iter().all(|diff| !diff.corrected));
+
+        let corrected = engine.handle_command(EngineCommand::Reconcile { auto_correct: Some(true) }).await;
+        assert_eq!(corrected.status, "success");
+        let positions = engine.events.current().positions;
+        assert_eq!((positions["binance:BTCUSDT"], positions["bybit:BTCUSDT"]), (2.0, 0.0));
+        assert!(run(&engine, false).await.discrepancies.is_empty());
+        let last = engine.handle_command(EngineCommand::GetReconciliation).await;
+        assert_eq!(last.data.unwrap()["discrepancies"], serde_json::json!([]));
+        let _ = std::fs::remove_file(path);
+    }
+}
@@ -0,0 +1,447 @@
+use rustyline::completion::{Completer, Pair};
+use rustyline::error::ReadlineError;
+use rustyline::highlight::Highlighter;
+use rustyline::hint::Hinter;
+use rustyline::history::DefaultHistory;
+use rustyline::validate::Validator;
+use rustyline::{Context, Editor, Helper};
+use serde_json::{json, Value};
+use std::io::{Read, Write};
+use std::net::TcpStream;
+use tokio_rustls::rustls::{ClientConnection, StreamOwned};
+
+pub const DEFAULT_ADDRESS: &str = "127.0.0.1:8080";
+
+// 命令名與用法
+pub(crate) const COMMANDS: &[(&str, &str)] = &[
+    ("positions", "positions [at_sequence]        持倉/PnL 投影"),
+    ("history", "history [symbol] [limit]        歷史執行記錄"),
+    ("events", "events [from_sequence] [limit]  事件日誌"),
+    ("opportunities", "opportunities                   掃描器最新機會"),
+    ("spreads", "spreads [symbol]                資金費率價差統計"),
+    ("calendar", "calendar                        各交易所下一次資金費結算與預測費率"),
+    ("sizing", "sizing                          校準後的最大名義金額"),
+    ("crowding", "crowding                        擁擠度指標"),
+    ("basis", "basis                           期現基差與頭寸"),
+    ("inventory", "inventory                       現貨庫存"),
+    ("limits", "limits                          各交易所限頻配額"),
+    ("sessions", "sessions                        各交易所連接狀態"),
+    ("accounts", "accounts [exchange]             交易所側持倉、餘額與最近訂單"),
+    ("collateral", "collateral                      各交易所可用保證金"),
+    ("liquidation", "liquidation                     永續持倉強平距離與告警"),
+    ("pairs", "pairs                           資金費率套利持倉對與資金費收付"),
+    ("predicted", "predicted                       預測的下一期資金費率"),
+    ("config", "config                          當前配置"),
+    ("reload", "reload                          重新加載配置文件"),
+    ("open", "open                            執行中的請求"),
+    ("queue", "queue                           執行隊列"),
+    ("latency", "latency                         各執行階段耗時分佈"),
+    ("maker", "maker                           各交易所掛單優先的 maker 成交比例"),
+    ("quotes", "quotes                          緩存費率的接收時間與是否過期"),
+    ("acks", "acks                            各交易所下單確認延遲"),
+    ("gateways", "gateways                        各交易所 REST 端點延遲與當前端點"),
+    ("clock", "clock                           交易所時鐘偏差"),
+    ("kill", "kill <on|off>                   啟用或解除緊急停止"),
+    ("margin", "margin                          現貨槓桿借幣額度"),
+    ("wallets", "wallets                         熱錢包與 nonce"),
+    ("gas", "gas                             當日 gas 花費與預算"),
+    ("capital", "capital                         各 strategy_id 的資金分配與佔用"),
+    ("bus", "bus                             事件總線發布進度"),
+    ("journal", "journal                         未結束的執行與重啟恢復結果"),
+    ("symbol", "symbol <symbol>                 交易對的內部格式與各交易所原生代碼"),
+    ("reconciliation", "reconciliation                  最近一次狀態對賬報告"),
+    ("reconcile", "reconcile [fix]                 立即對賬，fix 時按交易所修正"),
+    ("scheduled", "scheduled                       待觸發與最近觸發的定時請求"),
+    ("unschedule", "unschedule <execution_id>       取消尚未觸發的定時請求"),
+    ("prices", "prices <symbol>                 各交易所報價與穩定幣錨定檢查"),
+    ("oracle", "oracle                          預言機喂價與更新時間"),
+    ("approvals", "approvals                       DEX 代幣授權額度"),
+    ("rpc", "rpc                             RPC 節點健康狀況"),
+    ("heads", "heads                           新區塊訂閱與重組監控"),
+    ("transfers", "transfers                       當日再平衡劃轉量與最近的劃轉記錄"),
+    ("rebalance", "rebalance [run]                 庫存再平衡計劃，run 時執行劃轉"),
+    ("halts", "halts                           當日限額觸發的交易暫停"),
+    ("resume", "resume [strategy_id]            解除交易暫停，省略時解除引擎整體暫停"),
+    ("mempool", "mempool                         待確認的大額 DEX 兌換"),
+    ("algos", "algos                           TWAP / 冰山單執行進度"),
+    ("proof", "proof <execution_id>            鏈上結算證明"),
+    ("strategies", "strategies                      策略開關狀態"),
+    ("plugins", "plugins                         自定義策略及其最近發布的機會"),
+    ("lending", "lending                         借貸市場利率、頭寸與健康因子"),
+    ("enable", "enable <strategy>               啟用策略"),
+    ("disable", "disable <strategy>              停用策略"),
+    ("simulate", "simulate <symbol> <primary> <secondary> [amount] [priority]  估算機會收益"),
+    ("preview", "preview <strategy> <symbol> <primary> <secondary> <amount>  預覽執行後的風險變化"),
+    ("route", "route <symbol> <buy|sell> <quantity> [exchange...]  預覽拆單計劃"),
+    ("backtest", "backtest [data_path] [amount]   回放錄製數據回測（缺省回放已存儲的資金費率）"),
+    ("log", "log <filter>                    調整日誌級別"),
+    ("raw", "raw <json>                      直接發送 JSON"),
+    ("help", "help                            顯示幫助"),
+    ("quit", "quit                            退出"),
+];
+const STRATEGIES: &[&str] = &["funding_rate", "triangular", "cash_and_carry", "spot_arbitrage"];
+const EXCHANGES: &[&str] = &["binance", "bybit", "okx"];
+
+pub(crate) struct ReplHelper;
+
+impl Completer for ReplHelper {
+    type Candidate = Pair;
+
+    fn complete(&self, line: &str, pos: usize, _ctx: &Context<'_>) -> rustyline::Result<(usize, Vec<Pair>)> {
+        let start = line[..pos].rfind(char::is_whitespace).map(|i| i + 1).unwrap_or(0);
+        let prefix = &line[start..pos];
+        let words: Vec<&str> = line[..start].split_whitespace().collect();
+        let candidates: Vec<&str> = match (words.first().copied(), words.len()) {
+            (None, _) => COMMANDS.iter().map(|(name, _)| *name).collect(),
+            (Some("enable" | "disable"), 1) => STRATEGIES.to_vec(),
+            (Some("simulate"), 2 | 3) => EXCHANGES.to_vec(),
+            (Some("preview"), 1) => STRATEGIES.to_vec(),
+            (Some("preview"), 3 | 4) => EXCHANGES.to_vec(),
+            (Some("route"), 2) => vec!["buy", "sell"],
+            (Some("route"), n) if n > 3 => EXCHANGES.to_vec(),
+            _ => Vec::new(),
+        };
+        let pairs = candidates
+            .into_iter()
+            .filter(|candidate| candidate.starts_with(prefix))
+            .map(|candidate| Pair {
+                display: candidate.to_string(),
+                replacement: format!("{} ", candidate),
+            })
+            .collect();
+        Ok((start, pairs))
+    }
+}
+
+impl Hinter for ReplHelper {
+    type Hint = String;
+}
+
+impl Highlighter for ReplHelper {}
+
+impl Validator for ReplHelper {}
+
+impl Helper for ReplHelper {}
+
+trait Stream: Read + Write {}
+
+impl<T: Read + Write> Stream for T {}
+
+// 設置 ARB_REPL_CA 時以 TLS 連接，另設 ARB_REPL_CERT/ARB_REPL_KEY 時出示客戶端證書
+fn connect(address: &str) -> Result<Box<dyn Stream>, String> {
+    let stream = TcpStream::connect(address).map_err(|e| format!("連接 {} 失敗: {}", address, e))?;
+    let Ok(ca_path) = std::env::var("ARB_REPL_CA") else {
+        return Ok(Box::new(stream));
+    };
+    let cert = std::env::var("ARB_REPL_CERT").ok();
+    let key = std::env::var("ARB_REPL_KEY").ok();
+    let identity = cert.as_deref().zip(key.as_deref());
+    let config = super::tls::client_config(&ca_path, identity)?;
+    let connection = ClientConnection::new(config, super::tls::server_name(address)?)
+        .map_err(|e| format!("TLS 初始化失敗: {}", e))?;
+    Ok(Box::new(StreamOwned::new(connection, stream)))
+}
+
+// 設置 ARB_REPL_CLIENT/ARB_REPL_SECRET 時連接後先完成認證握手
+fn authenticate<S: Read + Write>(stream: &mut S) -> Result<(), String> {
+    let (Ok(client), Ok(secret)) = (std::env::var("ARB_REPL_CLIENT"), std::env::var("ARB_REPL_SECRET")) else {
+        return Ok(());
+    };
+    let timestamp_ms = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_millis() as i64)
+        .unwrap_or_default();
+    let nonce = uuid::Uuid::new_v4().to_string();
+    let signature = super::client_auth::sign(secret.as_bytes(), &client, timestamp_ms, &nonce);
+    let response = send(
+        stream,
+        &json!({
+            "command": "authenticate",
+            "client": client,
+            "timestamp_ms": timestamp_ms,
+            "nonce": nonce,
+            "signature": signature,
+        }),
+    )?;
+    if response["status"] != "success" {
+        return Err(format!("認證失敗: {}", response["error_message"]));
+    }
+    println!("已認證為 {}，權限 {}", client, response["data"]["scopes"]);
+    Ok(())
+}
+
+pub fn run(address: &str) -> Result<(), String> {
+    let mut stream = connect(address)?;
+    authenticate(&mut stream)?;
+    let mut editor: Editor<ReplHelper, DefaultHistory> = Editor::new().map_err(|e| e.to_string())?;
+    editor.set_helper(Some(ReplHelper));
+    println!("已連接 {}，輸入 help 查看命令，Tab 補全", address);
+
+    loop {
+        let line = match editor.readline("arb> ") {
+            Ok(line) => line,
+            Err(ReadlineError::Interrupted) => continue,
+            Err(ReadlineError::Eof) => break,
+            Err(e) => return Err(e.to_string()),
+        };
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        let _ = editor.add_history_entry(line);
+        match line {
+            "quit" | "exit" => break,
+            "help" => {
+                for (_, usage) in COMMANDS {
+                    println!("  {}", usage);
+                }
+                continue;
+            }
+            _ => {}
+        }
+        let message = match parse(line) {
+            Ok(message) => message,
+            Err(e) => {
+                println!("{}", e);
+                continue;
+            }
+        };
+        match send(&mut stream, &message) {
+            Ok(response) => println!("{}", serde_json::to_string_pretty(&response).unwrap()),
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(())
+}
+
+// 把運維命令翻譯為引擎協議消息
+pub(crate) fn parse(line: &str) -> Result<Value, String> {
+    let (command, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+    let args: Vec<&str> = rest.split_whitespace().collect();
+    let number = |index: usize, name: &str| -> Result<Option<f64>, String> {
+        args.get(index)
+            .map(|arg| arg.parse::<f64>().map_err(|_| format!("{} 必須是數字: {}", name, arg)))
+            .transpose()
+    };
+    let required = |index: usize, name: &str| -> Result<&str, String> {
+        args.get(index).copied().ok_or_else(|| format!("缺少參數 {}", name))
+    };
+    Ok(match command {
+        "positions" => json!({"command": "get_positions", "at_sequence": number(0, "at_sequence")?.map(|n| n as u64)}),
+        "history" => {
+            // 只有一個數字參數時視為 limit
+            let (symbol, limit) = match args.as_slice() {
+                [only] if only.parse::<i64>().is_ok() => (None, number(0, "limit")?),
+                _ => (args.first().copied(), number(1, "limit")?),
+            };
+            json!({"command": "get_history", "symbol": symbol, "limit": limit.map(|n| n as i64)})
+        }
+        "events" => json!({
+            "command": "get_events",
+            "from_sequence": number(0, "from_sequence")?.map(|n| n as u64),
+            "limit": number(1, "limit")?.map(|n| n as usize),
+        }),
+        "opportunities" => json!({"command": "get_opportunities"}),
+        "spreads" => json!({"command": "get_spread_stats", "symbol": args.first()}),
+        "sizing" => json!({"command": "get_sizing_limits"}),
+        "crowding" => json!({"command": "get_crowding"}),
+        "basis" => json!({"command": "get_basis"}),
+        "inventory" => json!({"command": "get_inventory"}),
+        "limits" => json!({"command": "get_rate_limits"}),
+        "sessions" => json!({"command": "get_sessions"}),
+        "accounts" => json!({"command": "get_accounts", "exchange": args.first()}),
+        "collateral" => json!({"command": "get_margin_availability"}),
+        "liquidation" => json!({"command": "get_liquidation_risk"}),
+        "pairs" => json!({"command": "get_funding_pairs"}),
+        "predicted" => json!({"command": "get_predicted_funding"}),
+        "config" => json!({"command": "get_config"}),
+        "reload" => json!({"command": "reload_config"}),
+        "open" => json!({"command": "get_open_executions"}),
+        "queue" => json!({"command": "get_execution_queue"}),
+        "latency" => json!({"command": "get_latency"}),
+        "maker" => json!({"command": "get_maker_stats"}),
+        "quotes" => json!({"command": "get_quotes"}),
+        "acks" => json!({"command": "get_venue_latency"}),
+        "gateways" => json!({"command": "get_gateways"}),
+        "clock" => json!({"command": "get_clock_sync"}),
+        "kill" => match required(0, "on|off")? {
+            "on" => json!({"command": "set_kill_switch", "engaged": true}),
+            "off" => json!({"command": "set_kill_switch", "engaged": false}),
+            other => return Err(format!("未知參數: {}", other)),
+        },
+        "margin" => json!({"command": "get_margin"}),
+        "wallets" => json!({"command": "get_wallets"}),
+        "calendar" => json!({"command": "get_funding_calendar"}),
+        "gas" => json!({"command": "get_gas_budget"}),
+        "capital" => json!({"command": "get_capital"}),
+        "bus" => json!({"command": "get_event_bus"}),
+        "journal" => json!({"command": "get_journal"}),
+        "symbol" => json!({"command": "get_symbol", "symbol": required(0, "symbol")?}),
+        "reconciliation" => json!({"command": "get_reconciliation"}),
+        "reconcile" => match args.first().copied() {
+            None => json!({"command": "reconcile"}),
+            Some("fix") => json!({"command": "reconcile", "auto_correct": true}),
+            Some(other) => return Err(format!("未知參數: {}", other)),
+        },
+        "scheduled" => json!({"command": "get_scheduled"}),
+        "unschedule" => json!({"command": "cancel_scheduled", "execution_id": required(0, "execution_id")?}),
+        "prices" => json!({"command": "get_price_check", "symbol": required(0, "symbol")?}),
+        "oracle" => json!({"command": "get_oracle_prices"}),
+        "approvals" => json!({"command": "get_approvals"}),
+        "rpc" => json!({"command": "get_rpc_providers"}),
+        "heads" => json!({"command": "get_block_subscription"}),
+        "transfers" => json!({"command": "get_rebalance"}),
+        "rebalance" => match args.first().copied() {
+            None => json!({"command": "rebalance", "dry_run": true}),
+            Some("run") => json!({"command": "rebalance"}),
+            Some(other) => return Err(format!("未知參數: {}", other)),
+        },
+        "halts" => json!({"command": "get_trading_halts"}),
+        "resume" => json!({"command": "resume_trading", "strategy_id": args.first()}),
+        "mempool" => json!({"command": "get_mempool"}),
+        "algos" => json!({"command": "get_algo_executions"}),
+        "proof" => json!({"command": "get_settlement_proof", "execution_id": required(0, "execution_id")?}),
+        "strategies" => json!({"command": "get_strategies"}),
+        "plugins" => json!({"command": "get_strategy_plugins"}),
+        "lending" => json!({"command": "get_lending"}),
+        "enable" | "disable" => json!({
+            "command": "set_strategy_enabled",
+            "strategy": required(0, "strategy")?,
+            "enabled": command == "enable",
+        }),
+        "simulate" => json!({
+            "command": "simulate_opportunity",
+            "symbol": required(0, "symbol")?,
+            "primary_exchange": required(1, "primary")?,
+            "secondary_exchange": required(2, "secondary")?,
+            "amount": number(3, "amount")?,
+            "priority": number(4, "priority")?.map(|n| n as i32),
+        }),
+        "preview" => json!({
+            "command": "preview_risk",
+            "request": {
+                "strategy_id": "repl",
+                "strategy_type": required(0, "strategy")?,
+                "symbol": required(1, "symbol")?,
+                "primary_exchange": required(2, "primary")?,
+                "secondary_exchange": required(3, "secondary")?,
+                "amount": number(4, "amount")?.ok_or("缺少參數 amount")?,
+                "priority": 0,
+                "timestamp": "0",
+            },
+        }),
+        "route" => json!({
+            "command": "plan_order",
+            "symbol": required(0, "symbol")?,
+            "side": required(1, "side")?,
+            "quantity": number(2, "quantity")?.ok_or("缺少參數 quantity")?,
+            "exchanges": (args.len() > 3).then(|| &args[3..]),
+        }),
+        "backtest" => {
+            // 只有一個數字參數時視為 amount
+            let (data_path, amount) = match args.as_slice() {
+                [only] if only.parse::<f64>().is_ok() => (None, number(0, "amount")?),
+                _ => (args.first().copied(), number(1, "amount")?),
+            };
+            json!({"command": "run_backtest", "data_path": data_path, "amount": amount})
+        }
+        "log" => json!({"command": "set_log_level", "filter": required(0, "filter")?}),
+        "raw" => serde_json::from_str(rest).map_err(|e| format!("無效的 JSON: {}", e))?,
+        other => return Err(format!("未知命令 {}，輸入 help 查看命令", other)),
+    })
+}
+
+// 引擎響應沒有分隔符，持續讀取直到得到完整的 JSON
+pub(crate) fn send<S: Read + Write>(stream: &mut S, message: &Value) -> Result<Value, String> {
+    stream
+        .write_all(message.to_string().as_bytes())
+        .map_err(|e| format!("發送失敗: {}", e))?;
+    let mut buffer = Vec::new();
+    let mut chunk = [0; 8192];
+    loop {
+        let n = stream.read(&mut chunk).map_err(|e| format!("讀取失敗: {}", e))?;
+        if n == 0 {
+            return Err("引擎已關閉連接".to_string());
+        }
+        buffer.extend_from_slice(&chunk[..n]);
+        match serde_json::from_slice(&buffer) {
+            Ok(response) => return Ok(response),
+            Err(e) if e.is_eof() => continue,
+            Err(e) => return Err(format!("無法解析響應: {}", e)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock_exchange_e2e::engine;
+    use crate::mock_exchange::Scenario;
+    use crate::{server, EngineCommand, StrategyType};
+    use std::sync::Arc;
+
+    // 每個 REPL 命令都翻譯為引擎能解析的協議消息；經 TCP 連接發送後按響應操作引擎狀態，Tab 補全命令與參數
+    #[tokio::test]
+    async fn repl_commands_translate_to_protocol_messages_and_drive_the_engine() {
+        use rustyline::completion::Completer;
+        let arguments = |command: &str| match command {
+            "kill" => " on",
+            "symbol" | "prices" => " BTCUSDT",
+            "unschedule" | "proof" => " exec-1",
+            "enable" | "disable" => " triangular",
+            "simulate" => " BTCUSDT binance bybit 1000 5",
+            "preview" => " funding_rate BTCUSDT binance bybit 1000",
+            "route" => " BTCUSDT buy 0.1 binance okx",
+            "log" => " info",
+            "raw" => r#" {"command":"get_latency"}"#,
+            _ => "",
+        };
+        for (command, _) in COMMANDS.iter().filter(|(command, _)| !matches!(*command, "help" | "quit")) {
+            let message = parse(&format!("{}{}", command, arguments(command))).unwrap();
+            serde_json::from_value::<EngineCommand>(message.clone()).unwrap_or_else(|e| panic!("{}: {} {}", command, message, e));
+        }
+        assert!(parse("kill maybe").unwrap_err().contains("未知參數"));
+        assert!(parse("simulate BTCUSDT binance").unwrap_err().contains("缺少參數 secondary"));
+        assert!(parse("route BTCUSDT buy many").unwrap_err().contains("quantity 必須是數字"));
+        assert!(parse("frobnicate").unwrap_err().contains("未知命令"));
+
+        let history = rustyline::history::DefaultHistory::new();
+        let context = rustyline::Context::new(&history);
+        let complete = |line: &str| {
+            let (start, pairs) = ReplHelper.complete(line, line.len(), &context).unwrap();
+            (start, pairs.into_iter().map(|pair| pair.replacement).collect::<Vec<_>>())
+        };
+        assert_eq!(complete("cro"), (0, vec!["crowding ".to_string()]));
+        assert_eq!(complete("disable ca"), (8, vec!["cash_and_carry ".to_string()]));
+        assert_eq!(complete("simulate BTCUSDT b"), (17, vec!["binance ".to_string(), "bybit ".to_string()]));
+        assert_eq!(complete("route BTCUSDT s"), (14, vec!["sell ".to_string()]));
+
+        let (_mock, engine, path) = engine("repl", Scenario::default()).await;
+        let engine = Arc::new(engine);
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let serving = Arc::clone(&engine);
+        tokio::spawn(async move {
+            while let Ok((socket, _)) = listener.accept().await {
+                let engine = Arc::clone(&serving);
+                tokio::spawn(async move { server::handle_connection(socket, &engine).await });
+            }
+        });
+        let responses = tokio::task::spawn_blocking(move || {
+            let mut stream = std::net::TcpStream::connect(address).unwrap();
+            ["disable triangular", "strategies", "kill on", "kill off", "proof exec-1"]
+                .into_iter()
+                .map(|line| send(&mut stream, &parse(line).unwrap()).unwrap())
+                .collect::<Vec<_>>()
+        })
+        .await
+        .unwrap();
+        let statuses: Vec<_> = responses.iter().map(|response| response["status"].as_str().unwrap()).collect();
+        assert_eq!(statuses, ["success", "success", "success", "success", "error"]);
+        assert_eq!(responses[1]["data"]["triangular"], false);
+        assert_eq!(responses[1]["data"]["funding_rate"], true);
+        assert!(!engine.disabled_strategies.read().unwrap().contains(&StrategyType::FundingRate));
+        assert!(engine.disabled_strategies.read().unwrap().contains(&StrategyType::Triangular));
+        let _ = std::fs::remove_file(path);
+    }
+}
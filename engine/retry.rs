@@ -0,0 +1,73 @@
+use super::config::RetryPolicy;
+use super::environment::{Environment, Rng};
+use super::exchange_api::ApiError;
+use std::future::Future;
+use std::time::Duration;
+use tracing::warn;
+
+// 第 attempt 次重試（從 1 起）前的等待：指數增長到上限，再乘以 1 ± jitter 的隨機因子
+pub fn backoff(policy: &RetryPolicy, attempt: u32, rng: &dyn Rng) -> Duration {
+    let base = (policy.base_delay_ms as f64 * 2f64.powi(attempt.saturating_sub(1).min(30) as i32)).min(policy.max_delay_ms as f64);
+    let jitter = 1.0 + policy.jitter * (rng.next_f64() * 2.0 - 1.0);
+    Duration::from_millis((base * jitter).max(0.0) as u64)
+}
+
+/// 執行 `call`，遇到瞬時錯誤時按策略退避後重試；`call` 收到當前是第幾次嘗試（從 1 起），
+/// 據此在重試下單前先查詢上一次請求是否已到達交易所。業務拒絕、限頻與用盡次數後的錯誤原樣返回。
+pub(crate) async fn with_retry<T, F, Fut>(
+    policy: &RetryPolicy,
+    env: &Environment,
+    exchange: &str,
+    operation: &str,
+    mut call: F,
+) -> Result<T, ApiError>
+where
+    F: FnMut(u32) -> Fut,
+    Fut: Future<Output = Result<T, ApiError>>,
+{
+    let mut attempt = 1;
+    loop {
+        match call(attempt).await {
+            Err(ApiError::Transient(error)) if attempt < policy.max_attempts => {
+                let delay = backoff(policy, attempt, env.rng.as_ref());
+                warn!(%exchange, operation, attempt, delay_ms = delay.as_millis() as u64, %error, "請求遇到瞬時錯誤，退避後重試");
+                env.clock.sleep(delay).await;
+                attempt += 1;
+            }
+            result => return result,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::deterministic_sim::request;
+    use crate::mock_exchange::Scenario;
+    use crate::mock_exchange_e2e::engine;
+    use crate::ArbitrageRequest;
+
+    #[tokio::test]
+    async fn transient_errors_retry_without_duplicate_orders() {
+        let (mock, engine, path) = engine("retry", Scenario { server_errors: 1, ..Scenario::default() }).await;
+        assert!(engine.get_funding_rate("binance", "BTCUSDT").await.is_ok());
+
+        // 下單響應丟失：重試前按 client_order_id 查到已成交的訂單，不再重複下單
+        let gas_quote = engine.default_chain().gas_optimizer.quote(5);
+        for secondary in ["bybit", "okx"] {
+            mock.script(|scenario| scenario.lost_order_responses = 2);
+            let request = ArbitrageRequest {
+                secondary_exchange: secondary.to_string(),
+                ..request(10.0)
+            };
+            let outcome = engine.execute_flash_loan_arbitrage("sim", &request, 0.0005, &gas_quote, false, None).await.unwrap();
+            assert_eq!(outcome.fill_ratio(), 1.0);
+        }
+        assert_eq!(mock.orders().len(), 4);
+
+        // 次數用盡後返回錯誤
+        mock.script(|scenario| scenario.server_errors = 3);
+        let error = engine.get_funding_rate("okx", "BTCUSDT").await.unwrap_err();
+        assert!(error.contains("503"), "{}", error);
+        let _ = std::fs::remove_file(path);
+    }
+}
@@ -0,0 +1,606 @@
+use super::config::{PnlLimits, RiskConfig, StrategyAllocation};
+use super::events::{settled_pnl, EngineEvent, EventEnvelope};
+use super::margin::BorrowLine;
+use super::market_data;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Mutex, RwLock};
+use tracing::warn;
+
+const DAY_MS: i64 = 86_400_000;
+
+// 擬下單的一條腿：正數為多頭名義金額，負數為空頭
+pub struct Leg {
+    pub exchange: String,
+    pub symbol: String,
+    pub notional: f64,
+}
+
+// 現貨槓桿空頭腿需要預留的借幣數量
+pub struct Borrow {
+    pub exchange: String,
+    pub asset: String,
+    pub quantity: f64,
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct Delta {
+    pub before: f64,
+    pub after: f64,
+    pub delta: f64,
+}
+
+impl Delta {
+    fn new(before: f64, after: f64) -> Self {
+        Self {
+            before,
+            after,
+            delta: after - before,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct RiskPreview {
+    // 受影響的 exchange:symbol 頭寸
+    pub positions: BTreeMap<String, Delta>,
+    // 按資產匯總的淨敞口
+    pub net_exposure: BTreeMap<String, Delta>,
+    pub gross_exposure: Delta,
+    // 最大單一資產 / 單一交易所佔總敞口的比例
+    pub asset_concentration: Delta,
+    pub exchange_concentration: Delta,
+    // 預留借幣所在額度的使用率；不需要借幣時為 None
+    pub margin_utilization: Option<Delta>,
+    pub value_at_risk: Delta,
+    // 執行後超出的限額
+    pub breaches: Vec<String>,
+}
+
+// 當日已實現盈虧與高點，在 UTC 零點重置
+#[derive(Default)]
+struct DailyPnl {
+    day_start_ms: i64,
+    pnl: f64,
+    peak: f64,
+}
+
+impl DailyPnl {
+    fn roll(&mut self, now_ms: i64) {
+        let day_start_ms = day_start_ms(now_ms);
+        if self.day_start_ms != day_start_ms {
+            *self = Self {
+                day_start_ms,
+                ..Self::default()
+            };
+        }
+    }
+
+    fn add(&mut self, pnl: f64, now_ms: i64) {
+        self.roll(now_ms);
+        self.pnl += pnl;
+        self.peak = self.peak.max(self.pnl);
+    }
+
+    // 觸發的第一項限額
+    fn breach(&self, limits: &PnlLimits) -> Option<String> {
+        if let Some(limit) = limits.max_daily_loss.filter(|limit| -self.pnl >= *limit) {
+            return Some(format!("當日虧損 {:.2} 達到限額 {}", -self.pnl, limit));
+        }
+        let drawdown = self.peak - self.pnl;
+        limits
+            .max_drawdown
+            .filter(|limit| drawdown >= *limit)
+            .map(|limit| format!("當日回撤 {:.2} 達到限額 {}", drawdown, limit))
+    }
+}
+
+// 單個 strategy_id 的資金佔用與當日盈虧
+#[derive(Default)]
+struct StrategyUsage {
+    open_notional: f64,
+    open_executions: usize,
+    daily: DailyPnl,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AllocationStatus {
+    pub strategy_id: String,
+    pub allocation: StrategyAllocation,
+    pub open_notional: f64,
+    pub open_executions: usize,
+    pub day_start_ms: i64,
+    pub pnl_today: f64,
+    pub peak_today: f64,
+    // 當日虧損已達預算
+    pub exhausted: bool,
+}
+
+// 當日限額觸發的交易暫停；strategy_id 為 None 表示整個引擎
+#[derive(Debug, Clone, Serialize)]
+pub struct Halt {
+    pub strategy_id: Option<String>,
+    pub reason: String,
+    pub halted_at_ms: i64,
+}
+
+#[derive(Default)]
+struct Ledger {
+    strategies: HashMap<String, StrategyUsage>,
+    global: DailyPnl,
+    halts: HashMap<Option<String>, Halt>,
+    // 管理員解除暫停的日期（UTC 零點），當日不再因限額暫停
+    overridden: HashMap<Option<String>, i64>,
+}
+
+impl Ledger {
+    fn halt(&self, scope: &Option<String>, now_ms: i64) -> Option<&Halt> {
+        self.halts.get(scope).filter(|halt| day_start_ms(halt.halted_at_ms) == day_start_ms(now_ms))
+    }
+
+    fn check(&mut self, scope: Option<String>, reason: Option<String>, now_ms: i64) -> Option<Halt> {
+        let reason = reason?;
+        let overridden = self.overridden.get(&scope) == Some(&day_start_ms(now_ms));
+        if overridden || self.halt(&scope, now_ms).is_some() {
+            return None;
+        }
+        let halt = Halt {
+            strategy_id: scope.clone(),
+            reason,
+            halted_at_ms: now_ms,
+        };
+        self.halts.insert(scope, halt.clone());
+        Some(halt)
+    }
+}
+
+pub struct RiskModel {
+    // 限額可熱加載
+    config: RwLock<RiskConfig>,
+    ledger: Mutex<Ledger>,
+}
+
+impl RiskModel {
+    // today 為事件日誌中當日的事件，據此恢復各 strategy_id 的盈虧與交易暫停狀態
+    pub fn new(config: RiskConfig, today: &[EventEnvelope]) -> Self {
+        let mut ledger = Ledger::default();
+        for envelope in today {
+            let at_ms = envelope.recorded_at_ms;
+            match &envelope.event {
+                EngineEvent::ExecutionSettled { strategy_id, status, pnl, fees, .. } => {
+                    let pnl = settled_pnl(status, *pnl, *fees);
+                    ledger.global.add(pnl, at_ms);
+                    ledger.strategies.entry(strategy_id.clone()).or_default().daily.add(pnl, at_ms);
+                }
+                EngineEvent::TradingHalted { strategy_id, reason } => {
+                    let halt = Halt {
+                        strategy_id: strategy_id.clone(),
+                        reason: reason.clone(),
+                        halted_at_ms: at_ms,
+                    };
+                    ledger.halts.insert(strategy_id.clone(), halt);
+                }
+                EngineEvent::TradingResumed { strategy_id } => {
+                    ledger.halts.remove(strategy_id);
+                    ledger.overridden.insert(strategy_id.clone(), day_start_ms(at_ms));
+                }
+                _ => {}
+            }
+        }
+        Self {
+            config: RwLock::new(config),
+            ledger: Mutex::new(ledger),
+        }
+    }
+
+    // 按 strategy_id 的分配額度佔用資金，超出任一額度時拒絕而不縮減金額
+    pub fn reserve_capital(&self, strategy_id: &str, amount: f64, now_ms: i64) -> Result<(), String> {
+        let config = self.config.read().unwrap();
+        let allocation = config.capital.allocation(strategy_id);
+        let mut ledger = self.ledger.lock().unwrap();
+        let usage = ledger.strategies.entry(strategy_id.to_string()).or_default();
+        usage.daily.roll(now_ms);
+        let pnl_today = usage.daily.pnl;
+        if let Some(budget) = allocation.daily_loss_budget.filter(|budget| -pnl_today >= *budget) {
+            return Err(format!("策略 {} 當日虧損 {:.2} 已達預算 {}", strategy_id, -pnl_today, budget));
+        }
+        if let Some(limit) = allocation.max_concurrent.filter(|limit| usage.open_executions >= *limit) {
+            return Err(format!("策略 {} 執行中請求已達上限 {}", strategy_id, limit));
+        }
+        if let Some(limit) = allocation.max_notional.filter(|limit| usage.open_notional + amount > *limit) {
+            return Err(format!(
+                "策略 {} 佔用名義金額 {:.2} 加上本次 {:.2} 超過分配上限 {}",
+                strategy_id, usage.open_notional, amount, limit
+            ));
+        }
+        usage.open_notional += amount;
+        usage.open_executions += 1;
+        Ok(())
+    }
+
+    pub fn release_capital(&self, strategy_id: &str, amount: f64) {
+        if let Some(usage) = self.ledger.lock().unwrap().strategies.get_mut(strategy_id) {
+            usage.open_notional = (usage.open_notional - amount).max(0.0);
+            usage.open_executions = usage.open_executions.saturating_sub(1);
+        }
+    }
+
+    // 計入已實現盈虧，返回本次新觸發的交易暫停
+    pub fn record_pnl(&self, strategy_id: &str, pnl: f64, now_ms: i64) -> Vec<Halt> {
+        let config = self.config.read().unwrap();
+        let mut ledger = self.ledger.lock().unwrap();
+        ledger.global.add(pnl, now_ms);
+        let daily = &mut ledger.strategies.entry(strategy_id.to_string()).or_default().daily;
+        let before = daily.pnl;
+        daily.add(pnl, now_ms);
+        let pnl_today = daily.pnl;
+        let budget = config.capital.allocation(strategy_id).daily_loss_budget;
+        if let Some(budget) = budget.filter(|budget| -before < *budget && -pnl_today >= *budget) {
+            warn!(strategy_id, pnl_today, budget, "當日虧損預算已用盡，後續執行將被拒絕");
+        }
+        let strategy_breach = config
+            .daily_limits
+            .strategies
+            .get(strategy_id)
+            .and_then(|limits| ledger.strategies[strategy_id].daily.breach(limits));
+        let global_breach = ledger.global.breach(&config.daily_limits.global);
+        [(Some(strategy_id.to_string()), strategy_breach), (None, global_breach)]
+            .into_iter()
+            .filter_map(|(scope, reason)| ledger.check(scope, reason, now_ms))
+            .collect()
+    }
+
+    // 當日生效的暫停，引擎整體暫停優先
+    pub fn halted(&self, strategy_id: &str, now_ms: i64) -> Option<Halt> {
+        let ledger = self.ledger.lock().unwrap();
+        ledger
+            .halt(&None, now_ms)
+            .or_else(|| ledger.halt(&Some(strategy_id.to_string()), now_ms))
+            .cloned()
+    }
+
+    // 管理員解除暫停；未處於暫停狀態時返回 None
+    pub fn resume(&self, strategy_id: Option<String>, now_ms: i64) -> Option<Halt> {
+        let mut ledger = self.ledger.lock().unwrap();
+        let halt = ledger.halt(&strategy_id, now_ms).cloned();
+        ledger.halts.remove(&strategy_id);
+        if halt.is_some() {
+            ledger.overridden.insert(strategy_id, day_start_ms(now_ms));
+        }
+        halt
+    }
+
+    pub fn halts(&self, now_ms: i64) -> Vec<Halt> {
+        let ledger = self.ledger.lock().unwrap();
+        let mut halts: Vec<Halt> = ledger
+            .halts
+            .keys()
+            .filter_map(|scope| ledger.halt(scope, now_ms).cloned())
+            .collect();
+        halts.sort_by(|a, b| a.strategy_id.cmp(&b.strategy_id));
+        halts
+    }
+
+    // 已配置額度或有資金佔用記錄的 strategy_id，按名稱排序
+    pub fn allocations(&self, now_ms: i64) -> Vec<AllocationStatus> {
+        let config = self.config.read().unwrap();
+        let mut ledger = self.ledger.lock().unwrap();
+        let mut strategies: Vec<String> = config.capital.strategies.keys().chain(ledger.strategies.keys()).cloned().collect();
+        strategies.sort();
+        strategies.dedup();
+        strategies
+            .into_iter()
+            .map(|strategy_id| {
+                let allocation = config.capital.allocation(&strategy_id).clone();
+                let usage = ledger.strategies.entry(strategy_id.clone()).or_default();
+                usage.daily.roll(now_ms);
+                AllocationStatus {
+                    exhausted: allocation.daily_loss_budget.is_some_and(|budget| -usage.daily.pnl >= budget),
+                    allocation,
+                    open_notional: usage.open_notional,
+                    open_executions: usage.open_executions,
+                    day_start_ms: usage.daily.day_start_ms,
+                    pnl_today: usage.daily.pnl,
+                    peak_today: usage.daily.peak,
+                    strategy_id,
+                }
+            })
+            .collect()
+    }
+
+    // 配置的日波動率，未配置的資產使用默認值
+    pub fn daily_volatility(&self, asset: &str) -> f64 {
+        volatility(&self.config.read().unwrap(), asset)
+    }
+
+    pub fn reconfigure(&self, config: RiskConfig) {
+        *self.config.write().unwrap() = config;
+    }
+
+    pub fn preview(
+        &self,
+        positions: &BTreeMap<String, f64>,
+        legs: &[Leg],
+        borrow: Option<&Borrow>,
+        margin: &BTreeMap<String, BorrowLine>,
+    ) -> RiskPreview {
+        // 同一次預覽內使用同一份限額
+        let config = self.config.read().unwrap().clone();
+        let mut after = positions.clone();
+        for leg in legs {
+            *after.entry(format!("{}:{}", leg.exchange, leg.symbol)).or_default() += leg.notional;
+        }
+        let touched = legs
+            .iter()
+            .map(|leg| {
+                let key = format!("{}:{}", leg.exchange, leg.symbol);
+                let delta = Delta::new(positions.get(&key).copied().unwrap_or_default(), after[&key]);
+                (key, delta)
+            })
+            .collect();
+        let (net_before, net_after) = (net_by_asset(positions), net_by_asset(&after));
+        let net_exposure = net_before
+            .keys()
+            .chain(net_after.keys())
+            .map(|asset| {
+                let value = |net: &BTreeMap<String, f64>| net.get(asset).copied().unwrap_or_default();
+                (asset.clone(), Delta::new(value(&net_before), value(&net_after)))
+            })
+            .collect();
+        let gross = |positions: &BTreeMap<String, f64>| positions.values().map(|v| v.abs()).sum::<f64>();
+        let margin_utilization = borrow.map(|borrow| {
+            let line = margin.get(&format!("{}:{}", borrow.exchange, borrow.asset)).cloned().unwrap_or_default();
+            let utilization = |borrowed: f64| if line.limit > 0.0 { borrowed / line.limit } else { f64::INFINITY };
+            Delta::new(utilization(line.borrowed), utilization(line.borrowed + borrow.quantity))
+        });
+        let preview = RiskPreview {
+            positions: touched,
+            net_exposure,
+            gross_exposure: Delta::new(gross(positions), gross(&after)),
+            asset_concentration: Delta::new(
+                concentration(positions, asset_of),
+                concentration(&after, asset_of),
+            ),
+            exchange_concentration: Delta::new(
+                concentration(positions, exchange_of),
+                concentration(&after, exchange_of),
+            ),
+            margin_utilization,
+            value_at_risk: Delta::new(value_at_risk(&config, &net_before), value_at_risk(&config, &net_after)),
+            breaches: Vec::new(),
+        };
+        RiskPreview {
+            breaches: breaches(&config, &preview),
+            ..preview
+        }
+    }
+}
+
+fn breaches(config: &RiskConfig, preview: &RiskPreview) -> Vec<String> {
+    let mut breaches = Vec::new();
+    if let Some(limit) = config.max_asset_concentration {
+        if preview.asset_concentration.after > limit {
+            breaches.push(format!(
+                "單一資產集中度 {:.1}% 超過上限 {:.1}%",
+                preview.asset_concentration.after * 100.0,
+                limit * 100.0
+            ));
+        }
+    }
+    if let (Some(limit), Some(utilization)) = (config.max_margin_utilization, preview.margin_utilization) {
+        if utilization.after > limit {
+            breaches.push(format!(
+                "借幣額度使用率 {:.1}% 超過上限 {:.1}%",
+                utilization.after * 100.0,
+                limit * 100.0
+            ));
+        }
+    }
+    if let Some(limit) = config.max_var_usd {
+        if preview.value_at_risk.after > limit {
+            breaches.push(format!("VaR {:.2} 超過上限 {:.2}", preview.value_at_risk.after, limit));
+        }
+    }
+    breaches
+}
+
+fn volatility(config: &RiskConfig, asset: &str) -> f64 {
+    config
+        .daily_volatility
+        .get(asset)
+        .copied()
+        .unwrap_or(config.default_daily_volatility)
+}
+
+// 參數法 VaR：各資產淨敞口 × 日波動率，資產間按統一相關係數合成
+fn value_at_risk(config: &RiskConfig, net: &BTreeMap<String, f64>) -> f64 {
+    let risks: Vec<f64> = net.iter().map(|(asset, exposure)| exposure * volatility(config, asset)).collect();
+    let mut variance = 0.0;
+    for (i, a) in risks.iter().enumerate() {
+        for (j, b) in risks.iter().enumerate() {
+            variance += a * b * if i == j { 1.0 } else { config.correlation };
+        }
+    }
+    config.var_z_score * variance.max(0.0).sqrt() * config.horizon_days.sqrt()
+}
+
+// UTC 當日零點（毫秒）
+pub fn day_start_ms(now_ms: i64) -> i64 {
+    now_ms - now_ms.rem_euclid(DAY_MS)
+}
+
+fn exchange_of(key: &str) -> String {
+    key.split_once(':').map_or(key, |(exchange, _)| exchange).to_string()
+}
+
+fn asset_of(key: &str) -> String {
+    let symbol = key.split_once(':').map_or(key, |(_, symbol)| symbol);
+    market_data::split_symbol(symbol).map_or_else(|| symbol.to_string(), |(base, _)| base)
+}
+
+fn net_by_asset(positions: &BTreeMap<String, f64>) -> BTreeMap<String, f64> {
+    let mut net = BTreeMap::new();
+    for (key, position) in positions {
+        *net.entry(asset_of(key)).or_default() += position;
+    }
+    net
+}
+
+// 按分組匯總絕對敞口，返回最大一組的佔比
+fn concentration(positions: &BTreeMap<String, f64>, group: impl Fn(&str) -> String) -> f64 {
+    let mut groups: BTreeMap<String, f64> = BTreeMap::new();
+    for (key, position) in positions {
+        *groups.entry(group(key)).or_default() += position.abs();
+    }
+    let total: f64 = groups.values().sum();
+    if total <= 0.0 {
+        return 0.0;
+    }
+    groups.values().fold(0.0_f64, |max, value| max.max(*value)) / total
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bench::START_MS;
+    use crate::deterministic_sim::{build, connect_sessions, funded_config, request};
+    use crate::{config, events, margin, ArbitrageRequest, EngineCommand, Environment};
+    use std::sync::Arc;
+
+    // 風險預覽按執行方向推斷各條腿疊加到當前持倉上，不下單也不寫事件：對沖腿不改變淨敞口與 VaR，
+    // 集中度按資產分組；未對沖敞口的 VaR 與借幣額度使用率超過限額時列入 breaches
+    #[tokio::test]
+    async fn risk_preview_reports_deltas_without_executing() {
+        let mut config = funded_config();
+        config.risk.max_asset_concentration = Some(0.9);
+        config.risk.max_var_usd = Some(1_000.0);
+        let (engine, path) = build("risk-preview", config, Environment::simulated(START_MS, 1));
+        let engine = Arc::new(engine);
+        connect_sessions(&engine).await;
+        for symbol in ["BTCUSDT", "ETHUSDT"] {
+            engine.quotes.record_predicted_rate("binance", symbol, 0.0060, START_MS);
+            engine.quotes.record_predicted_rate("bybit", symbol, 0.0010, START_MS);
+        }
+        let response = engine.execute_funding_rate_arbitrage(request(10_000.0)).await;
+        assert_eq!(response.status, "success", "{:?}", response.error_message);
+        let positions = engine.events.current().positions;
+        let events = engine.events.read(0, 10_000).len();
+
+        // 費率較高的 binance 做空
+        let preview = engine.preview_risk(&request(5_000.0)).await.unwrap();
+        let short = &preview.positions["binance:BTCUSDT"];
+        let long = &preview.positions["bybit:BTCUSDT"];
+        assert_eq!((short.delta, long.delta), (-5_000.0, 5_000.0));
+        assert_eq!(short.before, positions["binance:BTCUSDT"]);
+        assert!(preview.net_exposure["BTC"].delta.abs() < 1e-9);
+        assert!((preview.gross_exposure.delta - 10_000.0).abs() < 1e-9);
+        assert!(preview.value_at_risk.delta.abs() < 1e-6);
+        assert_eq!(preview.asset_concentration.after, 1.0);
+        assert!(preview.margin_utilization.is_none());
+        assert!(preview.breaches.iter().any(|breach| breach.starts_with("單一資產集中度")), "{:?}", preview.breaches);
+
+        // 分散到 ETH 後集中度下降
+        let eth = ArbitrageRequest { symbol: "ETHUSDT".to_string(), ..request(10_000.0) };
+        let preview = engine.preview_risk(&eth).await.unwrap();
+        assert!(preview.asset_concentration.delta < -0.4 && preview.asset_concentration.after < 0.9);
+        assert!(preview.breaches.is_empty(), "{:?}", preview.breaches);
+        assert_eq!(engine.events.current().positions, positions);
+        assert_eq!(engine.events.read(0, 10_000).len(), events);
+
+        // 未對沖的 100k BTC 多頭：VaR = 2.33 × 100k × 3.5%；借幣 1 BTC 使額度使用率由 25% 升至 75%
+        let leg = Leg { exchange: "binance".to_string(), symbol: "BTCUSDT".to_string(), notional: 100_000.0 };
+        let borrow = Borrow { exchange: "bybit".to_string(), asset: "BTC".to_string(), quantity: 1.0 };
+        let lines = [("bybit:BTC".to_string(), margin::BorrowLine { limit: 2.0, borrowed: 0.5 })].into();
+        let preview = engine.risk.preview(&Default::default(), &[leg], Some(&borrow), &lines);
+        assert!((preview.value_at_risk.after - 2.33 * 100_000.0 * 0.035).abs() < 1e-6);
+        let utilization = preview.margin_utilization.unwrap();
+        assert_eq!((utilization.before, utilization.after), (0.25, 0.75));
+        assert_eq!(preview.breaches.len(), 2, "{:?}", preview.breaches);
+        assert!(preview.breaches[1].starts_with("VaR"));
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[tokio::test]
+    async fn capital_allocation_rejects_over_budget() {
+        let mut config = config::EngineConfig::default();
+        let allocation = config::StrategyAllocation {
+            max_notional: Some(1_500.0),
+            max_concurrent: Some(2),
+            daily_loss_budget: Some(1.0),
+        };
+        config.risk.capital.strategies.insert("sim".to_string(), allocation);
+        let (engine, path) = build("capital", config.clone(), Environment::simulated(START_MS, 1));
+        let risk = &engine.risk;
+        risk.reserve_capital("sim", 1_000.0, START_MS).unwrap();
+        let error = risk.reserve_capital("sim", 1_000.0, START_MS).unwrap_err();
+        assert!(error.contains("分配上限"), "{}", error);
+        risk.reserve_capital("sim", 500.0, START_MS).unwrap();
+        let error = risk.reserve_capital("sim", 0.0, START_MS).unwrap_err();
+        assert!(error.contains("執行中請求"), "{}", error);
+        risk.release_capital("sim", 1_000.0);
+        risk.release_capital("sim", 500.0);
+        // 未配置的 strategy_id 使用不限額的默認分配
+        assert!(risk.reserve_capital("other", 1e9, START_MS).is_ok());
+
+        // 失敗執行的平倉手續費計入虧損，重啟後從事件日誌恢復當日盈虧
+        for (status, pnl, fees) in [("success", 0.5, 0.1), ("error", 0.0, 2.0)] {
+            engine.events.append(events::EngineEvent::ExecutionSettled {
+                execution_id: status.to_string(),
+                strategy_id: "sim".to_string(),
+                symbol: "BTCUSDT".to_string(),
+                status: status.to_string(),
+                pnl,
+                fees,
+            });
+        }
+        let restored = RiskModel::new(config.risk, &engine.events.since(day_start_ms(START_MS)));
+        let error = restored.reserve_capital("sim", 100.0, START_MS).unwrap_err();
+        assert!(error.contains("已達預算"), "{}", error);
+        assert!(restored.allocations(START_MS).iter().any(|status| status.strategy_id == "sim" && status.exhausted));
+        // 次日虧損預算重置
+        assert!(restored.reserve_capital("sim", 100.0, START_MS + 86_400_000).is_ok());
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[tokio::test]
+    async fn daily_limits_halt_until_override_or_next_day() {
+        let mut config = config::EngineConfig::default();
+        let strategy_limits = config::PnlLimits {
+            max_daily_loss: Some(0.5),
+            max_drawdown: None,
+        };
+        config.risk.daily_limits.strategies.insert("sim".to_string(), strategy_limits);
+        config.risk.daily_limits.global.max_drawdown = Some(3.0);
+        let (engine, path) = build("halts", config.clone(), Environment::simulated(START_MS, 1));
+        engine.record_realized_pnl("sim", -1.0);
+        let error = engine.execute_funding_rate_arbitrage(request(1_000.0)).await.error_message.unwrap();
+        assert!(error.contains("交易已暫停") && error.contains("當日虧損"), "{}", error);
+        // 其他 strategy_id 不受該策略的暫停影響
+        let other = ArbitrageRequest {
+            strategy_id: "other".to_string(),
+            ..request(1_000.0)
+        };
+        let error = engine.execute_funding_rate_arbitrage(other.clone()).await.error_message.unwrap();
+        assert!(!error.contains("交易已暫停"), "{}", error);
+
+        // 合計盈虧從高點 4 回落到 1，觸發引擎整體暫停
+        engine.record_realized_pnl("other", 5.0);
+        engine.record_realized_pnl("other", -3.0);
+        let error = engine.execute_funding_rate_arbitrage(other).await.error_message.unwrap();
+        assert!(error.contains("當日回撤"), "{}", error);
+        let command = |strategy_id: Option<&str>| EngineCommand::ResumeTrading { strategy_id: strategy_id.map(str::to_string) };
+        assert_eq!(engine.handle_command(command(None)).await.status, "success");
+        assert_eq!(engine.handle_command(command(None)).await.status, "error");
+        // 解除後當日不再因同一限額暫停
+        engine.record_realized_pnl("other", -1.0);
+        assert_eq!(engine.risk.halts(engine.env.now_ms()).len(), 1);
+
+        // 暫停狀態與解除記錄從事件日誌恢復，次日自動解除
+        let restored = RiskModel::new(config.risk, &engine.events.since(day_start_ms(START_MS)));
+        let now_ms = engine.env.now_ms();
+        assert!(restored.halted("other", now_ms).is_none());
+        assert!(restored.halted("sim", now_ms).is_some_and(|halt| halt.strategy_id.as_deref() == Some("sim")));
+        assert!(restored.halts(now_ms + 86_400_000).is_empty());
+        let _ = std::fs::remove_file(path);
+    }
+}
@@ -0,0 +1,262 @@
+use super::config::{self, PeerConfig, RoutingConfig};
+use super::environment::Vars;
+use super::{ChildOrder, CommandResponse};
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio_rustls::TlsConnector;
+use tracing::{debug, info};
+
+type HmacSha256 = Hmac<Sha256>;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LegEnvelope {
+    pub leg_id: String,
+    // 發起實例所在區域
+    pub origin: String,
+    pub issued_at_ms: i64,
+    // JSON 編碼的 ChildOrder；簽名覆蓋原始字符串，避免浮點數重新序列化後不一致
+    pub order: String,
+    pub signature: String,
+}
+
+pub struct LegRouter {
+    config: RoutingConfig,
+    secret: Option<Vec<u8>>,
+    // 時鐘偏差窗口內已處理的 leg_id -> 簽發時間
+    seen: Mutex<HashMap<String, i64>>,
+    tls: Option<TlsConnector>,
+}
+
+fn now_ms() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as i64)
+        .unwrap_or_default()
+}
+
+impl LegRouter {
+    pub fn new(config: RoutingConfig, tls: Option<TlsConnector>, vars: &dyn Vars) -> Result<Self, String> {
+        let secret = vars.var(&config.secret_env).map(String::into_bytes);
+        if secret.is_none() && !config.peers.is_empty() {
+            return Err(format!("配置了 routing.peers 但未設置環境變量 {}", config.secret_env));
+        }
+        for peer in &config.peers {
+            info!(peer = %peer.name, region = %peer.region, address = %peer.address, venues = ?peer.venues, "已配置區域實例");
+        }
+        Ok(Self {
+            config,
+            secret,
+            seen: Mutex::new(HashMap::new()),
+            tls,
+        })
+    }
+
+    // 子賬戶隨所屬交易所路由
+    fn peer(&self, venue: &str) -> Option<&PeerConfig> {
+        let venue = config::venue(venue);
+        self.config.peers.iter().find(|peer| peer.venues.iter().any(|v| v == venue))
+    }
+
+    pub fn is_remote(&self, venue: &str) -> bool {
+        self.peer(venue).is_some()
+    }
+
+    fn mac(&self, leg_id: &str, origin: &str, issued_at_ms: i64, order: &str) -> Result<HmacSha256, String> {
+        let secret = self.secret.as_ref().ok_or("未配置實例間共享密鑰")?;
+        let mut mac = HmacSha256::new_from_slice(secret).map_err(|e| e.to_string())?;
+        mac.update(format!("{}|{}|{}|{}", leg_id, origin, issued_at_ms, order).as_bytes());
+        Ok(mac)
+    }
+
+    // 校驗通過後返回轉發的腿
+    pub fn verify(&self, envelope: &LegEnvelope) -> Result<ChildOrder, String> {
+        let now = now_ms();
+        let skew = self.config.max_clock_skew_ms;
+        if now.abs_diff(envelope.issued_at_ms) > skew {
+            return Err(format!("轉發指令已過期或時鐘偏差過大: {}ms", now.saturating_sub(envelope.issued_at_ms)));
+        }
+        let signature = hex::decode(&envelope.signature).map_err(|_| "簽名格式無效".to_string())?;
+        self.mac(&envelope.leg_id, &envelope.origin, envelope.issued_at_ms, &envelope.order)?
+            .verify_slice(&signature)
+            .map_err(|_| "簽名校驗失敗".to_string())?;
+        let mut seen = self.seen.lock().unwrap();
+        seen.retain(|_, issued_at_ms| now.abs_diff(*issued_at_ms) <= skew);
+        if seen.insert(envelope.leg_id.clone(), envelope.issued_at_ms).is_some() {
+            return Err(format!("重複的轉發指令: {}", envelope.leg_id));
+        }
+        serde_json::from_str(&envelope.order).map_err(|e| format!("轉發的腿無效: {}", e))
+    }
+
+    // 本區域的腿原樣返回，其餘並發轉交對應實例；任一轉發失敗即整體失敗
+    pub async fn dispatch(&self, orders: Vec<ChildOrder>) -> Result<Vec<ChildOrder>, String> {
+        if self.config.peers.is_empty() {
+            return Ok(orders);
+        }
+        let legs = orders.into_iter().map(|order| async move {
+            match self.peer(&order.exchange) {
+                Some(peer) => self.forward(peer, order).await,
+                None => Ok(order),
+            }
+        });
+        futures::future::join_all(legs).await.into_iter().collect()
+    }
+
+    async fn forward(&self, peer: &PeerConfig, order: ChildOrder) -> Result<ChildOrder, String> {
+        let leg_id = uuid::Uuid::new_v4().to_string();
+        let issued_at_ms = now_ms();
+        let order = serde_json::to_string(&order).map_err(|e| e.to_string())?;
+        let signature = hex::encode(
+            self.mac(&leg_id, &self.config.region, issued_at_ms, &order)?
+                .finalize()
+                .into_bytes(),
+        );
+        let mut message = serde_json::json!(LegEnvelope {
+            leg_id: leg_id.clone(),
+            origin: self.config.region.clone(),
+            issued_at_ms,
+            order,
+            signature,
+        });
+        message["command"] = "execute_leg".into();
+        let timeout = Duration::from_millis(self.config.timeout_ms);
+        let response = tokio::time::timeout(timeout, request(&peer.address, &message, self.tls.as_ref()))
+            .await
+            .map_err(|_| format!("區域實例 {} 響應超時", peer.name))?
+            .map_err(|e| format!("區域實例 {} 通信失敗: {}", peer.name, e))?;
+        debug!(peer = %peer.name, %leg_id, status = %response.status, "轉發的腿已返回");
+        if response.status != "success" {
+            return Err(format!(
+                "區域實例 {} 執行失敗: {}",
+                peer.name,
+                response.error_message.unwrap_or_default()
+            ));
+        }
+        serde_json::from_value(response.data.unwrap_or_default())
+            .map_err(|e| format!("區域實例 {} 成交回報無效: {}", peer.name, e))
+    }
+}
+
+// 響應沒有分隔符，讀到可完整解析的 JSON 為止
+async fn request(
+    address: &str,
+    message: &serde_json::Value,
+    tls: Option<&TlsConnector>,
+) -> Result<CommandResponse, String> {
+    let stream = TcpStream::connect(address).await.map_err(|e| e.to_string())?;
+    match tls {
+        Some(connector) => {
+            let stream = connector
+                .connect(super::tls::server_name(address)?, stream)
+                .await
+                .map_err(|e| format!("TLS 握手失敗: {}", e))?;
+            exchange(stream, message).await
+        }
+        None => exchange(stream, message).await,
+    }
+}
+
+async fn exchange<S: AsyncRead + AsyncWrite + Unpin>(
+    mut stream: S,
+    message: &serde_json::Value,
+) -> Result<CommandResponse, String> {
+    stream
+        .write_all(message.to_string().as_bytes())
+        .await
+        .map_err(|e| e.to_string())?;
+    let mut buffer = Vec::new();
+    let mut chunk = [0; 4096];
+    loop {
+        let n = stream.read(&mut chunk).await.map_err(|e| e.to_string())?;
+        if n == 0 {
+            return Err("連接已關閉".to_string());
+        }
+        buffer.extend_from_slice(&chunk[..n]);
+        if let Ok(response) = serde_json::from_slice(&buffer) {
+            return Ok(response);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::environment::FixedVars;
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn routed_legs_are_signed_and_verified_once_by_the_peer() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+        let vars = FixedVars::new([("E2E_PEER_SECRET", "e2e-peer")]);
+        let routing = |region: &str, peers: Vec<config::PeerConfig>| config::RoutingConfig {
+            region: region.to_string(),
+            peers,
+            secret_env: "E2E_PEER_SECRET".to_string(),
+            ..config::RoutingConfig::default()
+        };
+        let tokyo = Arc::new(LegRouter::new(routing("tokyo", Vec::new()), None, &vars).unwrap());
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        // 對端實例：校驗簽名後按全部成交回報，並記下收到的指令供重放
+        let received = Arc::new(std::sync::Mutex::new(None));
+        tokio::spawn({
+            let (tokyo, received) = (Arc::clone(&tokyo), Arc::clone(&received));
+            async move {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                let mut buffer = vec![0; 8192];
+                let n = stream.read(&mut buffer).await.unwrap();
+                let envelope: LegEnvelope = serde_json::from_slice(&buffer[..n]).unwrap();
+                let response = match tokyo.verify(&envelope) {
+                    Ok(mut order) => {
+                        order.filled_quantity = order.quantity;
+                        order.status = "filled".to_string();
+                        CommandResponse::ok(Some(serde_json::json!(order)))
+                    }
+                    Err(error) => CommandResponse::error(error),
+                };
+                *received.lock().unwrap() = Some(envelope);
+                stream.write_all(serde_json::to_string(&response).unwrap().as_bytes()).await.unwrap();
+            }
+        });
+        let peer = config::PeerConfig {
+            name: "tokyo-1".to_string(),
+            region: "tokyo".to_string(),
+            address,
+            venues: vec!["bybit".to_string()],
+        };
+        let london = LegRouter::new(routing("london", vec![peer]), None, &vars).unwrap();
+        let leg = |exchange: &str| ChildOrder {
+            leg: "short".to_string(),
+            exchange: exchange.to_string(),
+            symbol: "BTCUSDT".to_string(),
+            side: "sell".to_string(),
+            quantity: 0.5,
+            filled_quantity: 0.0,
+            fee: 0.0,
+            status: "new".to_string(),
+        };
+        assert!(london.is_remote("bybit") && !london.is_remote("binance"));
+        let legs = london.dispatch(vec![leg("binance"), leg("bybit")]).await.unwrap();
+        assert_eq!((legs[0].status.as_str(), legs[0].filled_quantity), ("new", 0.0));
+        assert_eq!((legs[1].status.as_str(), legs[1].filled_quantity), ("filled", 0.5));
+
+        let envelope = received.lock().unwrap().clone().unwrap();
+        assert_eq!(envelope.origin, "london");
+        assert!(tokyo.verify(&envelope).unwrap_err().contains("重複"));
+        let tampered = LegEnvelope {
+            leg_id: "tampered".to_string(),
+            order: envelope.order.replace("0.5", "5.0"),
+            ..envelope.clone()
+        };
+        assert_eq!(tokyo.verify(&tampered).unwrap_err(), "簽名校驗失敗");
+        for issued_at_ms in [envelope.issued_at_ms - 60_000, i64::MIN, i64::MAX] {
+            let stale = LegEnvelope { leg_id: "stale".to_string(), issued_at_ms, ..envelope.clone() };
+            assert!(tokyo.verify(&stale).unwrap_err().contains("時鐘偏差"));
+        }
+    }
+}
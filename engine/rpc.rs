@@ -0,0 +1,384 @@
+use super::config::RpcConfig;
+use futures::future::{join_all, BoxFuture};
+use jsonrpc_core::{Call, Params};
+use serde::Serialize;
+use serde_json::Value;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tracing::{debug, info, warn};
+use web3::error::TransportError;
+use web3::signing::keccak256;
+use web3::transports::Http;
+use web3::{helpers, RequestId, Transport};
+
+// 節點限流的 JSON-RPC 錯誤碼（limit exceeded）
+const RATE_LIMITED: i64 = -32005;
+
+#[derive(Default)]
+struct Health {
+    successes: u64,
+    failures: u64,
+    consecutive_failures: u32,
+    // 成功請求延遲的指數移動平均
+    latency_ms: f64,
+    suspended_until: Option<Instant>,
+    last_error: Option<String>,
+}
+
+impl Health {
+    fn available(&self, now: Instant) -> bool {
+        self.suspended_until.is_none_or(|until| now >= until)
+    }
+
+    // 成功率（加一平滑）按延遲折減，越高越優先
+    fn score(&self) -> f64 {
+        let rate = (self.successes + 1) as f64 / (self.successes + self.failures + 2) as f64;
+        rate / (1.0 + self.latency_ms / 100.0)
+    }
+}
+
+struct Provider {
+    // 只含協議與主機，URL 路徑中常帶有 API key，不寫入日誌
+    host: String,
+    http: Http,
+    health: Mutex<Health>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RpcProviderStatus {
+    pub host: String,
+    pub available: bool,
+    pub score: f64,
+    pub latency_ms: f64,
+    pub successes: u64,
+    pub failures: u64,
+    pub last_error: Option<String>,
+}
+
+struct RpcPool {
+    chain: String,
+    config: RpcConfig,
+    providers: Vec<Provider>,
+    next_id: AtomicUsize,
+}
+
+/// 一條鏈的多節點 RPC 傳輸：按健康評分依次嘗試，節點不可達、超時或限流時切換到下一個；
+/// 節點正常應答的 JSON-RPC 錯誤（如回滾）直接返回，不再換節點重試。
+#[derive(Clone)]
+pub struct RpcTransport {
+    pool: Arc<RpcPool>,
+    // 為 true 時每個請求都並發發往各節點並按 quorum 交叉驗證
+    cross_check: bool,
+}
+
+impl std::fmt::Debug for RpcTransport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RpcTransport")
+            .field("chain", &self.pool.chain)
+            .field("providers", &self.pool.providers.len())
+            .field("cross_check", &self.cross_check)
+            .finish()
+    }
+}
+
+fn host(url: &str) -> String {
+    reqwest::Url::parse(url)
+        .map(|url| format!("{}://{}", url.scheme(), url.host_str().unwrap_or_default()))
+        .unwrap_or_else(|_| "<無效 URL>".to_string())
+}
+
+impl RpcTransport {
+    // primary 為 rpc_url，之後依次為 fallback_urls
+    pub fn connect(chain: &str, primary: &str, config: &RpcConfig) -> Result<Self, String> {
+        let providers = std::iter::once(primary)
+            .chain(config.fallback_urls.iter().map(String::as_str))
+            .map(|url| {
+                Ok(Provider {
+                    host: host(url),
+                    http: Http::new(url).map_err(|e| format!("RPC 節點 {} 無效: {}", host(url), e))?,
+                    health: Mutex::new(Health::default()),
+                })
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+        info!(chain, providers = providers.len(), quorum = config.quorum, "RPC 節點已配置");
+        Ok(Self {
+            pool: Arc::new(RpcPool {
+                chain: chain.to_string(),
+                config: config.clone(),
+                providers,
+                next_id: AtomicUsize::new(1),
+            }),
+            cross_check: false,
+        })
+    }
+
+    // 共用節點與健康狀況的交叉驗證版本；quorum 為 1 時與原傳輸相同
+    pub fn cross_checked(&self) -> Self {
+        Self {
+            pool: Arc::clone(&self.pool),
+            cross_check: self.pool.config.quorum > 1,
+        }
+    }
+
+    pub fn status(&self) -> Vec<RpcProviderStatus> {
+        let now = Instant::now();
+        self.pool
+            .providers
+            .iter()
+            .map(|provider| {
+                let health = provider.health.lock().unwrap();
+                RpcProviderStatus {
+                    host: provider.host.clone(),
+                    available: health.available(now),
+                    score: health.score(),
+                    latency_ms: health.latency_ms,
+                    successes: health.successes,
+                    failures: health.failures,
+                    last_error: health.last_error.clone(),
+                }
+            })
+            .collect()
+    }
+}
+
+impl Transport for RpcTransport {
+    type Out = BoxFuture<'static, web3::error::Result<Value>>;
+
+    fn prepare(&self, method: &str, params: Vec<Value>) -> (RequestId, Call) {
+        let id = self.pool.next_id.fetch_add(1, Ordering::Relaxed);
+        (id, helpers::build_request(id, method, params))
+    }
+
+    fn send(&self, id: RequestId, call: Call) -> Self::Out {
+        let pool = Arc::clone(&self.pool);
+        let cross_check = self.cross_check;
+        Box::pin(async move {
+            if cross_check {
+                pool.cross_check(id, call).await
+            } else {
+                pool.failover(id, call).await
+            }
+        })
+    }
+}
+
+// 節點不可達、超時、響應無效或限流時視為節點故障
+fn is_fault(error: &web3::Error) -> bool {
+    match error {
+        web3::Error::Rpc(error) => error.code.code() == RATE_LIMITED || error.message.to_lowercase().contains("rate limit"),
+        _ => true,
+    }
+}
+
+fn method(call: &Call) -> &str {
+    match call {
+        Call::MethodCall(call) => &call.method,
+        Call::Notification(notification) => &notification.method,
+        Call::Invalid { .. } => "",
+    }
+}
+
+// 已簽名交易的哈希，用於換節點重發時節點回覆 already known 的情形
+fn raw_transaction_hash(call: &Call) -> Option<Value> {
+    let Call::MethodCall(call) = call else { return None };
+    let Params::Array(params) = &call.params else { return None };
+    let raw = hex::decode(params.first()?.as_str()?.trim_start_matches("0x")).ok()?;
+    Some(Value::String(format!("0x{}", hex::encode(keccak256(&raw)))))
+}
+
+// 十六進制數量（餘額、gas、nonce）
+fn quantity(value: &Value) -> Option<f64> {
+    let hex = value.as_str()?.strip_prefix("0x")?;
+    u128::from_str_radix(hex, 16).ok().map(|quantity| quantity as f64)
+}
+
+// 數值結果允許偏差的方法；nonce 差 1 即會留下空洞，必須完全一致
+fn tolerates_deviation(method: &str) -> bool {
+    method != "eth_getTransactionCount"
+}
+
+// 至少 quorum 個結果一致時返回其中之一：允許偏差的數值取中位數並與之比較，其他結果要求完全相同
+fn agreed(values: &[Value], quorum: usize, max_deviation_bps: Option<f64>) -> Option<Value> {
+    let quantities = max_deviation_bps.and_then(|max_deviation_bps| {
+        let quantities = values.iter().map(|value| quantity(value).map(|q| (q, value))).collect::<Option<Vec<_>>>()?;
+        Some((quantities, max_deviation_bps))
+    });
+    if let Some((mut quantities, max_deviation_bps)) = quantities {
+        quantities.sort_by(|a, b| a.0.total_cmp(&b.0));
+        let (median, value) = quantities[quantities.len() / 2];
+        let tolerance = median * max_deviation_bps / 10_000.0;
+        let close = quantities.iter().filter(|(q, _)| (q - median).abs() <= tolerance).count();
+        return (close >= quorum).then(|| value.clone());
+    }
+    values
+        .iter()
+        .find(|value| values.iter().filter(|other| other == value).count() >= quorum)
+        .cloned()
+}
+
+impl RpcPool {
+    // 可用節點在前，各自按評分從高到低
+    fn ranked(&self) -> Vec<(bool, &Provider)> {
+        let now = Instant::now();
+        let mut ranked: Vec<(bool, f64, &Provider)> = self
+            .providers
+            .iter()
+            .map(|provider| {
+                let health = provider.health.lock().unwrap();
+                (health.available(now), health.score(), provider)
+            })
+            .collect();
+        ranked.sort_by(|a, b| b.0.cmp(&a.0).then(b.1.total_cmp(&a.1)));
+        ranked.into_iter().map(|(available, _, provider)| (available, provider)).collect()
+    }
+
+    async fn call(&self, provider: &Provider, id: RequestId, call: &Call) -> web3::error::Result<Value> {
+        let started = Instant::now();
+        let timeout = Duration::from_millis(self.config.request_timeout_ms);
+        let result = match tokio::time::timeout(timeout, provider.http.send(id, call.clone())).await {
+            Ok(result) => result,
+            Err(_) => Err(web3::Error::Transport(TransportError::Message(format!(
+                "請求超過 {}ms",
+                self.config.request_timeout_ms
+            )))),
+        };
+        let mut health = provider.health.lock().unwrap();
+        match &result {
+            Err(error) if is_fault(error) => {
+                health.failures += 1;
+                health.consecutive_failures += 1;
+                health.last_error = Some(error.to_string());
+                if health.consecutive_failures >= self.config.max_consecutive_failures {
+                    health.consecutive_failures = 0;
+                    health.suspended_until = Some(Instant::now() + Duration::from_millis(self.config.cooldown_ms));
+                    warn!(chain = %self.chain, host = %provider.host, cooldown_ms = self.config.cooldown_ms, %error, "RPC 節點連續失敗，暫停使用");
+                }
+            }
+            _ => {
+                let latency_ms = started.elapsed().as_secs_f64() * 1000.0;
+                health.latency_ms = if health.successes == 0 { latency_ms } else { health.latency_ms * 0.8 + latency_ms * 0.2 };
+                health.successes += 1;
+                health.consecutive_failures = 0;
+                health.suspended_until = None;
+            }
+        }
+        result
+    }
+
+    // 依次嘗試；所有節點都在暫停中時仍按評分嘗試，避免整條鏈不可用
+    async fn failover(&self, id: RequestId, call: Call) -> web3::error::Result<Value> {
+        let mut last_error = None;
+        for (attempt, (_, provider)) in self.ranked().into_iter().enumerate() {
+            match self.call(provider, id, &call).await {
+                Err(error) if is_fault(&error) => {
+                    debug!(chain = %self.chain, host = %provider.host, method = method(&call), %error, "RPC 節點失敗，切換到下一個");
+                    last_error = Some(error);
+                }
+                // 前一節點超時前可能已廣播了交易
+                Err(web3::Error::Rpc(error)) if attempt > 0 && error.message.to_lowercase().contains("already known") => {
+                    return raw_transaction_hash(&call).ok_or(web3::Error::Rpc(error));
+                }
+                result => return result,
+            }
+        }
+        Err(last_error.unwrap_or(web3::Error::Unreachable))
+    }
+
+    // 並發請求所有可用節點；節點正常應答的錯誤（如 gas 估算回滾）直接返回
+    async fn cross_check(&self, id: RequestId, call: Call) -> web3::error::Result<Value> {
+        let quorum = self.config.quorum;
+        let providers: Vec<&Provider> = self.ranked().into_iter().filter(|(available, _)| *available).map(|(_, provider)| provider).collect();
+        if providers.len() < quorum {
+            return Err(web3::Error::InvalidResponse(format!(
+                "鏈 {} 可用 RPC 節點 {} 個，少於交叉驗證所需的 {} 個",
+                self.chain,
+                providers.len(),
+                quorum
+            )));
+        }
+        let results = join_all(providers.iter().map(|provider| self.call(provider, id, &call))).await;
+        let mut values = Vec::new();
+        let mut answered = None;
+        for result in results {
+            match result {
+                Ok(value) => values.push(value),
+                Err(error) if !is_fault(&error) => answered = answered.or(Some(error)),
+                Err(_) => {}
+            }
+        }
+        if values.len() < quorum {
+            return Err(answered.unwrap_or_else(|| {
+                web3::Error::InvalidResponse(format!("{} 只有 {} 個 RPC 節點返回結果，少於 quorum {}", method(&call), values.len(), quorum))
+            }));
+        }
+        let max_deviation_bps = tolerates_deviation(method(&call)).then_some(self.config.max_deviation_bps);
+        agreed(&values, quorum, max_deviation_bps).ok_or_else(|| {
+            warn!(chain = %self.chain, method = method(&call), results = ?values, "RPC 節點結果不一致");
+            web3::Error::InvalidResponse(format!("{} 的 RPC 節點結果不一致，未達 quorum {}", method(&call), quorum))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config;
+
+    // 本地 JSON-RPC 節點按路徑返回不同的餘額：a 與 b 相差 1%，c 相差一倍
+    #[tokio::test]
+    async fn rpc_fails_over_and_cross_checks_critical_reads() {
+        use axum::extract::Path;
+        use axum::routing::post;
+        let app = axum::Router::new().route(
+            "/:node",
+            post(|Path(node): Path<String>, axum::Json(body): axum::Json<serde_json::Value>| async move {
+                let result = match (body["method"].as_str(), node.as_str()) {
+                    (Some("eth_blockNumber"), _) => "0x10",
+                    (_, "a") => "0x64",
+                    (_, "b") => "0x65",
+                    _ => "0xc8",
+                };
+                axum::Json(serde_json::json!({"jsonrpc": "2.0", "id": body["id"], "result": result}))
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await });
+        let node = |name: &str| format!("http://{}/{}", address, name);
+
+        // 主節點不可連通，切換到備用節點；連續失敗後暫停使用
+        let config = config::RpcConfig {
+            fallback_urls: vec![node("a"), node("b")],
+            max_consecutive_failures: 1,
+            quorum: 2,
+            ..Default::default()
+        };
+        let transport = RpcTransport::connect("e2e", "http://127.0.0.1:9", &config).unwrap();
+        let web3 = web3::Web3::new(transport.clone());
+        assert_eq!(web3.eth().block_number().await.unwrap().as_u64(), 16);
+        let status = transport.status();
+        assert!(!status[0].available && status[0].failures == 1 && status[0].last_error.is_some());
+        assert_eq!(status[1].successes, 1);
+
+        // 交叉驗證只使用可用節點，偏差在 max_deviation_bps 內視為一致
+        let verified = web3::Web3::new(transport.cross_checked());
+        let balance = verified.eth().balance(web3::types::Address::zero(), None).await.unwrap();
+        assert!(balance.as_u64() == 100 || balance.as_u64() == 101);
+        // nonce 必須完全一致
+        assert!(verified.eth().transaction_count(web3::types::Address::zero(), None).await.is_err());
+
+        // 結果不一致時拒絕返回
+        let config = config::RpcConfig {
+            fallback_urls: vec![node("c")],
+            quorum: 2,
+            ..Default::default()
+        };
+        let verified = web3::Web3::new(RpcTransport::connect("e2e", &node("a"), &config).unwrap().cross_checked());
+        assert!(verified.eth().balance(web3::types::Address::zero(), None).await.is_err());
+
+        let mut config = config::EngineConfig::default();
+        config.rpc.quorum = 2;
+        assert!(config.validate().unwrap_err().contains("rpc.quorum"));
+    }
+}
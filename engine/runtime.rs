@@ -0,0 +1,123 @@
+use super::config::RuntimeConfig;
+use std::future::Future;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, RwLock};
+use tokio::runtime::{Builder, Handle, Runtime};
+use tracing::{info, warn};
+
+// 後台運行時；未隔離時為 None，後台任務與執行共用當前運行時
+static BACKGROUND: RwLock<Option<Handle>> = RwLock::new(None);
+// 執行運行時；未隔離時為 None
+static HOT_PATH: RwLock<Option<Handle>> = RwLock::new(None);
+
+/// 按配置建立運行時並在其上運行 `future`（引擎主循環）。隔離時當前線程綁定到 hot_path_core，
+/// 以單線程運行時驅動客戶端連接、執行與下單；後台任務經 [`in_background`] 派發到獨立的多線程運行時。
+pub fn block_on<F: Future>(config: &RuntimeConfig, future: F) -> Result<F::Output, String> {
+    if !config.isolate_hot_path {
+        let runtime = Builder::new_multi_thread().enable_all().build().map_err(|e| e.to_string())?;
+        return Ok(runtime.block_on(future));
+    }
+    let background = background_runtime(config)?;
+    *BACKGROUND.write().unwrap() = Some(background.handle().clone());
+    if let Some(core) = config.hot_path_core {
+        match pin_current_thread(core) {
+            Ok(()) => info!(core, "執行運行時已綁定 CPU 核"),
+            Err(e) => warn!(core, error = %e, "執行運行時綁定 CPU 核失敗"),
+        }
+    }
+    let hot_path = Builder::new_current_thread().enable_all().build().map_err(|e| e.to_string())?;
+    *HOT_PATH.write().unwrap() = Some(hot_path.handle().clone());
+    let output = hot_path.block_on(future);
+    *HOT_PATH.write().unwrap() = None;
+    *BACKGROUND.write().unwrap() = None;
+    // 後台任務可能仍在等待，不阻塞退出
+    background.shutdown_background();
+    Ok(output)
+}
+
+pub(crate) fn background_runtime(config: &RuntimeConfig) -> Result<Runtime, String> {
+    let cores = Arc::new(config.background_cores.clone());
+    let next = Arc::new(AtomicUsize::new(0));
+    Builder::new_multi_thread()
+        .worker_threads(config.background_threads)
+        .thread_name("arb-background")
+        .on_thread_start(move || {
+            if cores.is_empty() {
+                return;
+            }
+            let core = cores[next.fetch_add(1, Ordering::Relaxed) % cores.len()];
+            if let Err(e) = pin_current_thread(core) {
+                warn!(core, error = %e, "後台線程綁定 CPU 核失敗");
+            }
+        })
+        .enable_all()
+        .build()
+        .map_err(|e| e.to_string())
+}
+
+// f 中的 tokio::spawn 派發到後台運行時；未隔離時仍在當前運行時
+pub fn in_background<R>(f: impl FnOnce() -> R) -> R {
+    let handle = BACKGROUND.read().unwrap().clone();
+    let _guard = handle.as_ref().map(Handle::enter);
+    f()
+}
+
+// 後台任務觸發的執行（調度、掃描器自動執行）派發回執行運行時
+pub fn spawn_hot_path<F>(future: F)
+where
+    F: Future<Output = ()> + Send + 'static,
+{
+    let handle = HOT_PATH.read().unwrap().clone();
+    match handle {
+        Some(handle) => {
+            handle.spawn(future);
+        }
+        None => {
+            tokio::spawn(future);
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+pub(crate) fn pin_current_thread(core: usize) -> Result<(), String> {
+    if core >= libc::CPU_SETSIZE as usize {
+        return Err(format!("CPU 核編號 {} 超出範圍", core));
+    }
+    // cpu_set_t 為純位圖，全零即空集合；只修改當前線程的親和性
+    unsafe {
+        let mut set: libc::cpu_set_t = std::mem::zeroed();
+        libc::CPU_SET(core, &mut set);
+        if libc::sched_setaffinity(0, std::mem::size_of::<libc::cpu_set_t>(), &set) != 0 {
+            return Err(std::io::Error::last_os_error().to_string());
+        }
+    }
+    Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+pub(crate) fn pin_current_thread(_core: usize) -> Result<(), String> {
+    Err("當前平台不支持綁定 CPU 核".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config;
+
+    #[test]
+    fn background_runtime_names_and_pins_worker_threads() {
+        let config = config::RuntimeConfig {
+            isolate_hot_path: true,
+            background_threads: 1,
+            background_cores: vec![0],
+            ..Default::default()
+        };
+        let background = background_runtime(&config).unwrap();
+        let name = background.block_on(async { tokio::spawn(async { std::thread::current().name().map(str::to_string) }).await.unwrap() });
+        assert_eq!(name.as_deref(), Some("arb-background"));
+        background.shutdown_background();
+        // 在獨立線程上綁定，避免影響測試線程的親和性
+        let pinned = std::thread::spawn(|| pin_current_thread(0)).join().unwrap();
+        assert_eq!(pinned.is_ok(), cfg!(target_os = "linux"));
+    }
+}
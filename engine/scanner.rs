@@ -0,0 +1,305 @@
+use super::config::ScannerConfig;
+use super::funding_history::FundingSample;
+use super::{market_data, runtime, ArbitrageRequest, ExecutionEngine, StrategyType};
+use serde::Serialize;
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
+use tokio::sync::broadcast;
+use tracing::{debug, info, Instrument};
+
+const SUBSCRIBER_BUFFER: usize = 16;
+const AUTO_EXECUTION_WINDOW: Duration = Duration::from_secs(3600);
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Opportunity {
+    pub symbol: String,
+    // 預測費率較高的一方做空收取資金費
+    pub short_exchange: String,
+    pub long_exchange: String,
+    // 最近一次結算費率及其價差，僅作參考
+    pub short_rate: f64,
+    pub long_rate: f64,
+    pub gross_edge: f64,
+    pub fee_cost: f64,
+    // 每期攤銷的保證金回補劃轉成本（資金費率單位），由掃描時按劃轉成本模型計入 net_edge
+    pub transfer_cost: f64,
+    pub net_edge: f64,
+    pub predicted_net_edge: f64,
+    pub detected_at_ms: i64,
+    // 交易對擁擠度 [0, 1] 及據此折減後的收益，排序依據後者
+    pub crowding: f64,
+    pub adjusted_edge: f64,
+    // 按兩邊交易所確認延遲估算的成交耗時，以及屆時剩餘的收益比例
+    pub expected_fill_ms: f64,
+    pub edge_survival: f64,
+}
+
+pub struct Scanner {
+    // 可熱加載；enabled 與 interval_secs 只在啟動時讀取
+    config: RwLock<ScannerConfig>,
+    latest: RwLock<Vec<Opportunity>>,
+    auto_executions: Mutex<VecDeque<Instant>>,
+    publisher: broadcast::Sender<Vec<Opportunity>>,
+}
+
+impl Scanner {
+    pub fn new(config: ScannerConfig) -> Self {
+        let (publisher, _) = broadcast::channel(SUBSCRIBER_BUFFER);
+        Self {
+            config: RwLock::new(config),
+            latest: RwLock::new(Vec::new()),
+            auto_executions: Mutex::new(VecDeque::new()),
+            publisher,
+        }
+    }
+
+    pub fn config(&self) -> ScannerConfig {
+        self.config.read().unwrap().clone()
+    }
+
+    pub fn reconfigure(&self, config: ScannerConfig) {
+        *self.config.write().unwrap() = config;
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<Vec<Opportunity>> {
+        self.publisher.subscribe()
+    }
+
+    pub fn latest(&self) -> Vec<Opportunity> {
+        self.latest.read().unwrap().clone()
+    }
+
+    // 對每個交易對的所有交易所兩兩比較，返回淨收益達標的機會（降序）
+    pub fn rank(&self, samples: &[FundingSample], taker_fee: impl Fn(&str) -> f64) -> Vec<Opportunity> {
+        let config = self.config();
+        let mut by_symbol: HashMap<&str, Vec<&FundingSample>> = HashMap::new();
+        for sample in samples {
+            by_symbol.entry(&sample.symbol).or_default().push(sample);
+        }
+
+        let mut opportunities = Vec::new();
+        for (symbol, venues) in by_symbol {
+            for (i, a) in venues.iter().enumerate() {
+                for b in &venues[i + 1..] {
+                    // 方向與門檻按預測的下一期費率判斷，已結算費率只作參考
+                    let (short, long) = if a.predicted_rate >= b.predicted_rate { (a, b) } else { (b, a) };
+                    // 兩條腿開倉與平倉的吃單費，按持有期數攤銷
+                    let fee_cost = 2.0 * (taker_fee(&short.exchange) + taker_fee(&long.exchange))
+                        / config.holding_periods as f64;
+                    let gross_edge = short.funding_rate - long.funding_rate;
+                    let net_edge = gross_edge - fee_cost;
+                    let predicted_net_edge = short.predicted_rate - long.predicted_rate - fee_cost;
+                    if predicted_net_edge < config.min_net_edge {
+                        continue;
+                    }
+                    opportunities.push(Opportunity {
+                        symbol: symbol.to_string(),
+                        short_exchange: short.exchange.clone(),
+                        long_exchange: long.exchange.clone(),
+                        short_rate: short.funding_rate,
+                        long_rate: long.funding_rate,
+                        gross_edge,
+                        fee_cost,
+                        transfer_cost: 0.0,
+                        net_edge,
+                        predicted_net_edge,
+                        detected_at_ms: short.sampled_at_ms.max(long.sampled_at_ms),
+                        crowding: 0.0,
+                        adjusted_edge: predicted_net_edge,
+                        expected_fill_ms: 0.0,
+                        edge_survival: 1.0,
+                    });
+                }
+            }
+        }
+        opportunities.sort_by(|a, b| b.predicted_net_edge.total_cmp(&a.predicted_net_edge));
+        opportunities
+    }
+
+    // 滑動一小時窗口內的自動執行次數限制
+    fn try_reserve_auto_execution(&self) -> bool {
+        let mut executions = self.auto_executions.lock().unwrap();
+        let now = Instant::now();
+        while executions.front().is_some_and(|t| now.duration_since(*t) > AUTO_EXECUTION_WINDOW) {
+            executions.pop_front();
+        }
+        if executions.len() >= self.config.read().unwrap().max_auto_executions_per_hour as usize {
+            return false;
+        }
+        executions.push_back(now);
+        true
+    }
+}
+
+pub fn spawn(engine: Arc<ExecutionEngine>) {
+    let config = engine.scanner.config();
+    if !config.enabled {
+        info!("套利機會掃描器未啟用");
+        return;
+    }
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(config.interval_secs));
+        loop {
+            interval.tick().await;
+            scan_once(&engine).await;
+        }
+    });
+}
+
+async fn scan_once(engine: &Arc<ExecutionEngine>) {
+    engine.strategies.evaluate(engine);
+    let scanner = &engine.scanner;
+    let config = scanner.config();
+    let mut opportunities = scanner.rank(&engine.funding_history.latest(), |e| engine.taker_fee(e));
+    // 收取資金費的一側保證金累積、支付的一側減少：按每個持有期把 auto_execute_amount 的保證金回補一次計成本，
+    // 兩邊交易所之間沒有可用劃轉網絡的機會不報告
+    let notional = config.auto_execute_amount;
+    opportunities.retain_mut(|opportunity| {
+        let Some((_, quote)) = market_data::split_symbol(&opportunity.symbol) else {
+            return false;
+        };
+        match engine.transfer_costs.quote(&quote, &opportunity.short_exchange, &opportunity.long_exchange, notional, 1.0) {
+            Ok(transfer) => {
+                opportunity.transfer_cost = transfer.cost / notional / config.holding_periods as f64;
+                opportunity.net_edge -= opportunity.transfer_cost;
+                opportunity.predicted_net_edge -= opportunity.transfer_cost;
+                // 預計成交時剩餘的收益不足 min_net_edge 的邊際機會捕獲不到，不報告
+                let decay = engine.ack_latency.decay(&opportunity.short_exchange, &opportunity.long_exchange);
+                opportunity.expected_fill_ms = decay.expected_fill_ms;
+                opportunity.edge_survival = decay.survival;
+                opportunity.predicted_net_edge * decay.survival >= config.min_net_edge
+            }
+            Err(error) => {
+                debug!(symbol = %opportunity.symbol, %error, "無法回補保證金，忽略機會");
+                false
+            }
+        }
+    });
+    // 先以完整結果更新價差存活時間，再按擁擠度與延遲衰減折減收益重新排序
+    engine.crowding.observe_scan(&opportunities);
+    for opportunity in &mut opportunities {
+        opportunity.crowding = engine.crowding.score(&opportunity.symbol);
+        opportunity.adjusted_edge = opportunity.predicted_net_edge * opportunity.edge_survival * (1.0 - opportunity.crowding);
+    }
+    opportunities.sort_by(|a, b| b.adjusted_edge.total_cmp(&a.adjusted_edge));
+    opportunities.truncate(config.publish_top);
+    debug!(count = opportunities.len(), "掃描完成");
+
+    *scanner.latest.write().unwrap() = opportunities.clone();
+    if !opportunities.is_empty() {
+        // 沒有訂閱者時發送失敗可以忽略
+        let _ = scanner.publisher.send(opportunities.clone());
+    }
+
+    if !config.auto_execute {
+        return;
+    }
+    let Some(best) = opportunities.first() else {
+        return;
+    };
+    if !scanner.try_reserve_auto_execution() {
+        debug!("已達自動執行次數上限");
+        return;
+    }
+    let request = ArbitrageRequest {
+        strategy_id: "scanner".to_string(),
+        symbol: best.symbol.clone(),
+        primary_exchange: best.short_exchange.clone(),
+        secondary_exchange: best.long_exchange.clone(),
+        amount: config.auto_execute_amount,
+        priority: 0,
+        timestamp: engine.env.now_ms().to_string(),
+        fast_path: false,
+        include_market_context: false,
+        strategy_type: StrategyType::FundingRate,
+        triangle: None,
+        plugin: None,
+        chain: None,
+        account: None,
+        execution_algo: None,
+        sizing: None,
+        maker_first: None,
+        leverage: None,
+        margin_mode: None,
+        deadline_ms: None,
+        execute_at_ms: None,
+        execute_before_funding_secs: None,
+        stream_progress: false,
+        progress: None,
+        preemption: None,
+    };
+    info!(symbol = %best.symbol, predicted_net_edge = best.predicted_net_edge, "掃描器自動執行套利機會");
+    let engine = Arc::clone(engine);
+    runtime::spawn_hot_path(
+        async move {
+            engine.submit(request).await;
+        }
+        .in_current_span(),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bench::START_MS;
+    use crate::deterministic_sim::{build, connect_sessions, funded_config};
+    use crate::{config, events, funding_history, Environment};
+
+    // 掃描器按預測費率決定方向、攤銷手續費後過濾，發布排序後的機會並在每小時上限內自動執行最優機會
+    #[tokio::test]
+    async fn scanner_ranks_cached_rates_and_auto_executes_within_hourly_cap() {
+        let mut config = funded_config();
+        config.scanner.interval_secs = 1;
+        config.scanner.auto_execute = true;
+        config.scanner.max_auto_executions_per_hour = 1;
+        config.execution_queue.enabled = false;
+        let (engine, path) = build("scanner", config, Environment::simulated(START_MS, 1));
+        let sample = |exchange: &str, funding_rate: f64, predicted_rate: f64| funding_history::FundingSample {
+            exchange: exchange.to_string(),
+            symbol: "BTCUSDT".to_string(),
+            sampled_at_ms: START_MS,
+            funding_rate,
+            predicted_rate,
+        };
+        let samples = [sample("binance", 0.0002, 0.0010), sample("bybit", 0.0003, 0.0001), sample("okx", 0.0001, 0.0004)];
+        let ranked = engine.scanner.rank(&samples, |_| 0.0004);
+        let pairs: Vec<_> = ranked.iter().map(|o| (o.short_exchange.as_str(), o.long_exchange.as_str())).collect();
+        assert_eq!(pairs, [("binance", "bybit"), ("binance", "okx"), ("okx", "bybit")]);
+        let fee_cost = 2.0 * 0.0008 / 21.0;
+        assert!((ranked[0].fee_cost - fee_cost).abs() < 1e-12);
+        assert!((ranked[0].predicted_net_edge - (0.0009 - fee_cost)).abs() < 1e-12);
+        // 已結算費率的價差只作參考，可以為負
+        assert!((ranked[0].net_edge - (-0.0001 - fee_cost)).abs() < 1e-12);
+        engine.scanner.reconfigure(config::ScannerConfig { min_net_edge: 0.0006, ..engine.scanner.config() });
+        assert_eq!(engine.scanner.rank(&samples, |_| 0.0004).len(), 1);
+        engine.scanner.reconfigure(config::ScannerConfig { min_net_edge: 0.00005, ..engine.scanner.config() });
+
+        let engine = Arc::new(engine);
+        connect_sessions(&engine).await;
+        for sample in samples {
+            engine.quotes.record_predicted_rate(&sample.exchange, &sample.symbol, sample.predicted_rate, START_MS);
+            engine.funding_history.push(sample);
+        }
+        let mut published = engine.scanner.subscribe();
+        spawn(Arc::clone(&engine));
+        for _ in 0..2 {
+            let opportunities = tokio::time::timeout(Duration::from_secs(5), published.recv()).await.unwrap().unwrap();
+            assert_eq!((opportunities[0].short_exchange.as_str(), opportunities[0].long_exchange.as_str()), ("binance", "bybit"));
+        }
+        assert_eq!(engine.scanner.latest()[0].symbol, "BTCUSDT");
+        let auto: Vec<_> = engine
+            .events
+            .read(0, 1_000)
+            .into_iter()
+            .filter_map(|envelope| match envelope.event {
+                events::EngineEvent::RequestReceived { strategy_id, primary_exchange, secondary_exchange, .. } if strategy_id == "scanner" => {
+                    Some((primary_exchange, secondary_exchange))
+                }
+                _ => None,
+            })
+            .collect();
+        assert_eq!(auto, [("binance".to_string(), "bybit".to_string())]);
+        let _ = std::fs::remove_file(path);
+    }
+}
@@ -0,0 +1,307 @@
+use super::config::{ExchangeConfig, SchedulerConfig};
+use super::funding_history::next_funding_ms;
+use super::{runtime, ArbitrageRequest, ArbitrageResponse, ExecutionEngine};
+use serde::Serialize;
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tracing::{info, warn, Instrument};
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Scheduled {
+    pub execution_id: String,
+    pub request: ArbitrageRequest,
+    pub scheduled_at_ms: i64,
+    pub trigger_at_ms: i64,
+    // 按 execute_before_funding_secs 對齊的資金費時間
+    pub funding_time_ms: Option<i64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ScheduledResult {
+    pub execution_id: String,
+    pub symbol: String,
+    pub trigger_at_ms: i64,
+    pub started_at_ms: i64,
+    pub funding_time_ms: Option<i64>,
+    pub status: String,
+    pub profit: Option<f64>,
+    pub error_message: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SchedulerSnapshot {
+    pub enabled: bool,
+    // 按觸發時間排序
+    pub pending: Vec<Scheduled>,
+    pub completed: Vec<ScheduledResult>,
+}
+
+#[derive(Default)]
+struct State {
+    pending: BTreeMap<String, Scheduled>,
+    completed: VecDeque<ScheduledResult>,
+}
+
+/// 定時執行：請求按 `execute_at_ms` 或 `execute_before_funding_secs` 登記，到點後跳過執行隊列直接執行，
+/// 保證在資金費結算前的指定秒數內成交。資金費時間按兩側交易所各自的結算間隔（1h/4h/8h）計算，取先結算的一方。
+pub struct Scheduler {
+    config: SchedulerConfig,
+    state: Mutex<State>,
+}
+
+impl Scheduler {
+    pub fn new(config: SchedulerConfig) -> Self {
+        Self {
+            config,
+            state: Mutex::new(State::default()),
+        }
+    }
+
+    pub fn config(&self) -> &SchedulerConfig {
+        &self.config
+    }
+
+    pub fn cancel(&self, execution_id: &str) -> Result<Scheduled, String> {
+        self.state
+            .lock()
+            .unwrap()
+            .pending
+            .remove(execution_id)
+            .ok_or_else(|| format!("沒有待執行的定時請求 {}", execution_id))
+    }
+
+    pub fn snapshot(&self) -> SchedulerSnapshot {
+        let state = self.state.lock().unwrap();
+        let mut pending: Vec<Scheduled> = state.pending.values().cloned().collect();
+        pending.sort_by_key(|scheduled| (scheduled.trigger_at_ms, scheduled.scheduled_at_ms));
+        SchedulerSnapshot {
+            enabled: self.config.enabled,
+            pending,
+            completed: state.completed.iter().cloned().collect(),
+        }
+    }
+
+    fn take_due(&self, now_ms: i64) -> Vec<Scheduled> {
+        let mut state = self.state.lock().unwrap();
+        let due: Vec<String> = state
+            .pending
+            .values()
+            .filter(|scheduled| scheduled.trigger_at_ms <= now_ms)
+            .map(|scheduled| scheduled.execution_id.clone())
+            .collect();
+        let mut due: Vec<Scheduled> = due.iter().filter_map(|id| state.pending.remove(id)).collect();
+        due.sort_by_key(|scheduled| scheduled.trigger_at_ms);
+        due
+    }
+
+    fn complete(&self, result: ScheduledResult) {
+        let mut state = self.state.lock().unwrap();
+        state.completed.push_back(result);
+        while state.completed.len() > self.config.history {
+            state.completed.pop_front();
+        }
+    }
+}
+
+// 計算觸發時間並登記，立即返回 status 為 scheduled 的響應；execution_id 即到點執行時使用的 ID
+pub(crate) fn schedule(engine: &ExecutionEngine, mut request: ArbitrageRequest) -> ArbitrageResponse {
+    let scheduler = &engine.scheduler;
+    if !scheduler.config.enabled {
+        return ArbitrageResponse::error("定時執行未啟用");
+    }
+    let now_ms = engine.env.now_ms();
+    let (trigger_at_ms, funding_time_ms) = match trigger_time(engine, &request, now_ms) {
+        Ok(trigger) => trigger,
+        Err(e) => return ArbitrageResponse::error(e),
+    };
+    if request.deadline_ms.is_some_and(|deadline| deadline <= trigger_at_ms) {
+        return ArbitrageResponse::error("觸發時間晚於執行截止時間");
+    }
+    let execution_id = engine.env.uuid().to_string();
+    // 到點時客戶端已收到 scheduled 響應，不再推送進度
+    request.progress = None;
+    {
+        let mut state = scheduler.state.lock().unwrap();
+        if state.pending.len() >= scheduler.config.max_pending {
+            return ArbitrageResponse::error(format!("定時請求已達上限 {}", scheduler.config.max_pending));
+        }
+        state.pending.insert(
+            execution_id.clone(),
+            Scheduled {
+                execution_id: execution_id.clone(),
+                request,
+                scheduled_at_ms: now_ms,
+                trigger_at_ms,
+                funding_time_ms,
+            },
+        );
+    }
+    info!(%execution_id, trigger_at_ms, ?funding_time_ms, "已登記定時執行");
+    ArbitrageResponse {
+        execution_id: Some(execution_id),
+        status: "scheduled".to_string(),
+        error_message: None,
+        scheduled_for_ms: Some(trigger_at_ms),
+        sizing: None,
+        ..ArbitrageResponse::error("")
+    }
+}
+
+// execute_at_ms 直接作為觸發時間；execute_before_funding_secs 取兩側交易所中先結算的資金費時間往前推，
+// 已來不及時順延到下一期
+fn trigger_time(engine: &ExecutionEngine, request: &ArbitrageRequest, now_ms: i64) -> Result<(i64, Option<i64>), String> {
+    match (request.execute_at_ms, request.execute_before_funding_secs) {
+        (Some(_), Some(_)) => Err("execute_at_ms 與 execute_before_funding_secs 只能指定一個".to_string()),
+        (Some(execute_at_ms), None) if execute_at_ms <= now_ms => Err("execute_at_ms 已過".to_string()),
+        (Some(execute_at_ms), None) => Ok((execute_at_ms, None)),
+        (None, Some(before_secs)) => {
+            let interval_hours = funding_interval_hours(engine, &request.primary_exchange)
+                .min(funding_interval_hours(engine, &request.secondary_exchange));
+            if before_secs == 0 || before_secs >= interval_hours * 3_600 {
+                return Err(format!("execute_before_funding_secs 必須在 1 ~ {} 之間", interval_hours * 3_600 - 1));
+            }
+            let lead_ms = before_secs as i64 * 1_000;
+            let next = [&request.primary_exchange, &request.secondary_exchange]
+                .into_iter()
+                .map(|exchange| next_funding_ms(now_ms, funding_interval_hours(engine, exchange)))
+                .min()
+                .unwrap_or_default();
+            let funding_time_ms = if next - lead_ms > now_ms {
+                next
+            } else {
+                [&request.primary_exchange, &request.secondary_exchange]
+                    .into_iter()
+                    .map(|exchange| next_funding_ms(next, funding_interval_hours(engine, exchange)))
+                    .min()
+                    .unwrap_or_default()
+            };
+            Ok((funding_time_ms - lead_ms, Some(funding_time_ms)))
+        }
+        (None, None) => Err("請求未指定執行時間".to_string()),
+    }
+}
+
+fn funding_interval_hours(engine: &ExecutionEngine, exchange: &str) -> u64 {
+    engine
+        .exchanges
+        .get(exchange)
+        .map_or_else(|| ExchangeConfig::default().funding_interval_hours, |connector| connector.settings.funding_interval_hours)
+}
+
+pub fn spawn(engine: Arc<ExecutionEngine>) {
+    let config = engine.scheduler.config().clone();
+    if !config.enabled {
+        info!("定時執行未啟用");
+        return;
+    }
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_millis(config.tick_ms));
+        loop {
+            interval.tick().await;
+            run_due(&engine);
+        }
+    });
+}
+
+// 到點的請求各自並發執行，互不等待
+pub(crate) fn run_due(engine: &Arc<ExecutionEngine>) {
+    let now_ms = engine.env.now_ms();
+    for scheduled in engine.scheduler.take_due(now_ms) {
+        let late_ms = now_ms - scheduled.trigger_at_ms;
+        if late_ms > engine.scheduler.config.max_delay_ms as i64 {
+            warn!(execution_id = %scheduled.execution_id, late_ms, "定時請求錯過觸發時間，放棄執行");
+            engine.scheduler.complete(ScheduledResult {
+                execution_id: scheduled.execution_id,
+                symbol: scheduled.request.symbol,
+                trigger_at_ms: scheduled.trigger_at_ms,
+                started_at_ms: now_ms,
+                funding_time_ms: scheduled.funding_time_ms,
+                status: "missed".to_string(),
+                profit: None,
+                error_message: Some(format!("晚於觸發時間 {}ms", late_ms)),
+            });
+            continue;
+        }
+        let engine = Arc::clone(engine);
+        runtime::spawn_hot_path(
+            async move {
+                let Scheduled { execution_id, request, trigger_at_ms, funding_time_ms, .. } = scheduled;
+                let symbol = request.symbol.clone();
+                let response = engine.execute_as(execution_id.clone(), request).await;
+                engine.scheduler.complete(ScheduledResult {
+                    execution_id,
+                    symbol,
+                    trigger_at_ms,
+                    started_at_ms: now_ms,
+                    funding_time_ms,
+                    status: response.status,
+                    profit: response.profit,
+                    error_message: response.error_message,
+                });
+            }
+            .in_current_span(),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bench::START_MS;
+    use crate::deterministic_sim::{build, request};
+    use crate::{config, events, EngineCommand, Environment};
+
+    #[tokio::test]
+    async fn scheduler_aligns_to_earliest_funding_and_triggers_when_due() {
+        let mut config = config::EngineConfig::default();
+        config.exchanges.entry("bybit".to_string()).or_default().funding_interval_hours = 1;
+        let (engine, path) = build("scheduler", config, Environment::simulated(START_MS, 1));
+        let engine = Arc::new(engine);
+
+        // bybit 每小時結算，早於 binance 的 8 小時結算
+        let before_funding = ArbitrageRequest {
+            execute_before_funding_secs: Some(30),
+            ..request(1_000.0)
+        };
+        let next_hour = (START_MS / 3_600_000 + 1) * 3_600_000;
+        let scheduled = engine.submit(before_funding.clone()).await;
+        assert_eq!(scheduled.status, "scheduled");
+        assert_eq!(scheduled.scheduled_for_ms, Some(next_hour - 30_000));
+
+        // 離結算不足 30 秒時順延到下一期
+        engine.env.clock.sleep(Duration::from_millis((next_hour - 10_000 - START_MS) as u64)).await;
+        assert_eq!(engine.submit(before_funding).await.scheduled_for_ms, Some(next_hour + 3_600_000 - 30_000));
+        let cancelled = scheduled.execution_id.unwrap();
+        let response = engine.handle_command(EngineCommand::CancelScheduled { execution_id: cancelled.clone() }).await;
+        assert_eq!(response.status, "success");
+
+        let at = ArbitrageRequest {
+            execute_at_ms: Some(engine.env.now_ms() + 1_000),
+            ..request(1_000.0)
+        };
+        let execution_id = engine.submit(at).await.execution_id.unwrap();
+        run_due(&engine);
+        assert_eq!(engine.scheduler.snapshot().pending.len(), 2);
+        engine.env.clock.sleep(Duration::from_millis(1_500)).await;
+        run_due(&engine);
+        let mut completed = Vec::new();
+        for _ in 0..100 {
+            completed = engine.scheduler.snapshot().completed;
+            if !completed.is_empty() {
+                break;
+            }
+            tokio::task::yield_now().await;
+        }
+        assert_eq!(completed.len(), 1);
+        assert_eq!(completed[0].execution_id, execution_id);
+        // 沿用登記時返回的 execution_id
+        let received = engine.events.read(0, 100).into_iter().any(|envelope| {
+            matches!(envelope.event, events::EngineEvent::RequestReceived { execution_id: id, .. } if id == execution_id)
+        });
+        assert!(received);
+        assert_eq!(engine.scheduler.snapshot().pending.len(), 1);
+        assert_eq!(engine.submit(ArbitrageRequest { execute_at_ms: Some(START_MS), ..request(1_000.0) }).await.status, "error");
+        let _ = std::fs::remove_file(path);
+    }
+}
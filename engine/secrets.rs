@@ -0,0 +1,394 @@
+use super::config::{self, AwsSecretsConfig, ExchangeConfig, SecretsBackend, SecretsConfig, TradingEnvironment, VaultConfig};
+use super::environment::Vars;
+use super::ExecutionEngine;
+use hmac::Mac;
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{info, warn};
+
+pub type SecretFuture<'a, T> = Pin<Box<dyn Future<Output = Result<T, String>> + Send + 'a>>;
+
+// 讀取密鑰服務的超時
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// 一個交易所的 API 憑證。只保存在內存中，不實現序列化，Debug 輸出不含密鑰。
+#[derive(Clone, Default, PartialEq, Eq)]
+pub struct Credentials {
+    pub api_key: String,
+    pub secret_key: String,
+    pub passphrase: String,
+    // 密鑰服務中的版本，用於識別輪換；環境變量來源為 None
+    pub version: Option<String>,
+}
+
+impl fmt::Debug for Credentials {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Credentials")
+            .field("api_key", &mask(&self.api_key))
+            .field("version", &self.version)
+            .finish_non_exhaustive()
+    }
+}
+
+// 只保留前 4 個字符
+fn mask(value: &str) -> String {
+    match value.char_indices().nth(4) {
+        Some((index, _)) => format!("{}***", &value[..index]),
+        None if value.is_empty() => String::new(),
+        None => "***".to_string(),
+    }
+}
+
+pub trait SecretsProvider: Send + Sync {
+    fn name(&self) -> &'static str;
+    // 憑證所在位置，用於錯誤信息
+    fn location(&self, exchange: &str, settings: &ExchangeConfig) -> String;
+    fn fetch<'a>(&'a self, exchange: &'a str, settings: &'a ExchangeConfig) -> SecretFuture<'a, Credentials>;
+}
+
+pub fn provider(config: &SecretsConfig, vars: &Arc<dyn Vars>) -> Result<Box<dyn SecretsProvider>, String> {
+    Ok(match config.provider {
+        SecretsBackend::Env => Box::new(EnvSecrets(Arc::clone(vars))),
+        SecretsBackend::Vault => Box::new(VaultSecrets::new(&config.vault, vars.as_ref())?),
+        SecretsBackend::AwsSecretsManager => Box::new(AwsSecretsManager::new(&config.aws, vars.as_ref())?),
+    })
+}
+
+pub struct EnvSecrets(pub Arc<dyn Vars>);
+
+impl SecretsProvider for EnvSecrets {
+    fn name(&self) -> &'static str {
+        "env"
+    }
+
+    fn location(&self, exchange: &str, settings: &ExchangeConfig) -> String {
+        format!("環境變量 {}", settings.credential_envs(exchange).join(" / "))
+    }
+
+    fn fetch<'a>(&'a self, exchange: &'a str, settings: &'a ExchangeConfig) -> SecretFuture<'a, Credentials> {
+        let credentials = env_credentials(self.0.as_ref(), exchange, settings);
+        Box::pin(async move { Ok(credentials) })
+    }
+}
+
+fn env_credentials(vars: &dyn Vars, exchange: &str, settings: &ExchangeConfig) -> Credentials {
+    let [api_key, secret_key, passphrase] = settings.credential_envs(exchange).map(|var| vars.var(&var).unwrap_or_default());
+    Credentials {
+        api_key,
+        secret_key,
+        passphrase,
+        version: None,
+    }
+}
+
+// Vault KV v2：GET /v1/<mount>/data/<path>，版本取 metadata.version
+pub struct VaultSecrets {
+    client: reqwest::Client,
+    address: String,
+    mount: String,
+    token: String,
+    namespace: Option<String>,
+}
+
+impl VaultSecrets {
+    pub fn new(config: &VaultConfig, vars: &dyn Vars) -> Result<Self, String> {
+        let token = vars
+            .var(&config.token_env)
+            .filter(|token| !token.is_empty())
+            .ok_or_else(|| format!("未設置 Vault token 環境變量 {}", config.token_env))?;
+        Ok(Self {
+            client: client()?,
+            address: config.address.trim_end_matches('/').to_string(),
+            mount: config.mount.trim_matches('/').to_string(),
+            token,
+            namespace: config.namespace.clone(),
+        })
+    }
+}
+
+impl SecretsProvider for VaultSecrets {
+    fn name(&self) -> &'static str {
+        "vault"
+    }
+
+    fn location(&self, exchange: &str, settings: &ExchangeConfig) -> String {
+        format!("Vault {}/{}", self.mount, settings.secret_path(exchange))
+    }
+
+    fn fetch<'a>(&'a self, exchange: &'a str, settings: &'a ExchangeConfig) -> SecretFuture<'a, Credentials> {
+        Box::pin(async move {
+            let url = format!("{}/v1/{}/data/{}", self.address, self.mount, settings.secret_path(exchange));
+            let mut request = self.client.get(url).header("X-Vault-Token", &self.token);
+            if let Some(namespace) = &self.namespace {
+                request = request.header("X-Vault-Namespace", namespace);
+            }
+            let body = send(request, self.location(exchange, settings)).await?;
+            let version = body["data"]["metadata"]["version"].as_u64().map(|version| version.to_string());
+            credentials(&body["data"]["data"], version)
+        })
+    }
+}
+
+// AWS Secrets Manager GetSecretValue，請求以 SigV4 簽名
+pub struct AwsSecretsManager {
+    client: reqwest::Client,
+    region: String,
+    url: reqwest::Url,
+    access_key_id: String,
+    secret_access_key: String,
+    session_token: Option<String>,
+}
+
+impl AwsSecretsManager {
+    pub fn new(config: &AwsSecretsConfig, vars: &dyn Vars) -> Result<Self, String> {
+        let env = |name: &str| vars.var(name).filter(|value| !value.is_empty());
+        let (Some(access_key_id), Some(secret_access_key)) = (env("AWS_ACCESS_KEY_ID"), env("AWS_SECRET_ACCESS_KEY")) else {
+            return Err("未設置 AWS_ACCESS_KEY_ID 或 AWS_SECRET_ACCESS_KEY".to_string());
+        };
+        let endpoint = config
+            .endpoint
+            .clone()
+            .unwrap_or_else(|| format!("https://secretsmanager.{}.amazonaws.com", config.region));
+        let url = reqwest::Url::parse(&endpoint).map_err(|e| format!("secrets.aws.endpoint {}: {}", endpoint, e))?;
+        Ok(Self {
+            client: client()?,
+            region: config.region.clone(),
+            url,
+            access_key_id,
+            secret_access_key,
+            session_token: env("AWS_SESSION_TOKEN"),
+        })
+    }
+
+    // SigV4：規範請求 -> 待簽字符串 -> 按日期、區域、服務逐級派生的密鑰簽名
+    fn authorization(&self, host: &str, amz_date: &str, body: &str) -> (String, Vec<(&'static str, String)>) {
+        let mut headers = vec![
+            ("content-type", "application/x-amz-json-1.1".to_string()),
+            ("host", host.to_string()),
+            ("x-amz-date", amz_date.to_string()),
+        ];
+        if let Some(token) = &self.session_token {
+            headers.push(("x-amz-security-token", token.clone()));
+        }
+        headers.push(("x-amz-target", "secretsmanager.GetSecretValue".to_string()));
+        let signed_headers = headers.iter().map(|(name, _)| *name).collect::<Vec<_>>().join(";");
+        let canonical_headers: String = headers.iter().map(|(name, value)| format!("{}:{}\n", name, value)).collect();
+        let canonical_request = format!(
+            "POST\n/\n\n{}\n{}\n{}",
+            canonical_headers,
+            signed_headers,
+            hex::encode(Sha256::digest(body.as_bytes()))
+        );
+        let date = &amz_date[..8];
+        let scope = format!("{}/{}/secretsmanager/aws4_request", date, self.region);
+        let string_to_sign = format!(
+            "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+            amz_date,
+            scope,
+            hex::encode(Sha256::digest(canonical_request.as_bytes()))
+        );
+        let key = [date, self.region.as_str(), "secretsmanager", "aws4_request"]
+            .iter()
+            .fold(format!("AWS4{}", self.secret_access_key).into_bytes(), |key, part| hmac(&key, part));
+        let authorization = format!(
+            "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+            self.access_key_id,
+            scope,
+            signed_headers,
+            hex::encode(hmac(&key, &string_to_sign))
+        );
+        (authorization, headers)
+    }
+}
+
+impl SecretsProvider for AwsSecretsManager {
+    fn name(&self) -> &'static str {
+        "aws_secrets_manager"
+    }
+
+    fn location(&self, exchange: &str, settings: &ExchangeConfig) -> String {
+        format!("AWS Secrets Manager {}", settings.secret_path(exchange))
+    }
+
+    fn fetch<'a>(&'a self, exchange: &'a str, settings: &'a ExchangeConfig) -> SecretFuture<'a, Credentials> {
+        Box::pin(async move {
+            let host = match self.url.port() {
+                Some(port) => format!("{}:{}", self.url.host_str().unwrap_or_default(), port),
+                None => self.url.host_str().unwrap_or_default().to_string(),
+            };
+            let body = serde_json::json!({ "SecretId": settings.secret_path(exchange) }).to_string();
+            let amz_date = chrono::Utc::now().format("%Y%m%dT%H%M%SZ").to_string();
+            let (authorization, headers) = self.authorization(&host, &amz_date, &body);
+            let mut request = self.client.post(self.url.clone()).header("authorization", authorization).body(body);
+            for (name, value) in headers.into_iter().filter(|(name, _)| *name != "host") {
+                request = request.header(name, value);
+            }
+            let response = send(request, self.location(exchange, settings)).await?;
+            let secret: Value = response["SecretString"]
+                .as_str()
+                .and_then(|secret| serde_json::from_str(secret).ok())
+                .ok_or_else(|| format!("{} 的 SecretString 不是 JSON", self.location(exchange, settings)))?;
+            credentials(&secret, response["VersionId"].as_str().map(str::to_string))
+        })
+    }
+}
+
+fn client() -> Result<reqwest::Client, String> {
+    reqwest::Client::builder()
+        .timeout(REQUEST_TIMEOUT)
+        .build()
+        .map_err(|e| e.to_string())
+}
+
+fn hmac(key: &[u8], payload: &str) -> Vec<u8> {
+    let mut mac = hmac::Hmac::<Sha256>::new_from_slice(key).expect("HMAC 接受任意長度密鑰");
+    mac.update(payload.as_bytes());
+    mac.finalize().into_bytes().to_vec()
+}
+
+// 錯誤信息只含狀態碼，不含響應正文
+async fn send(request: reqwest::RequestBuilder, location: String) -> Result<Value, String> {
+    let response = request.send().await.map_err(|e| format!("讀取 {} 失敗: {}", location, e))?;
+    if !response.status().is_success() {
+        return Err(format!("讀取 {} 失敗: {}", location, response.status()));
+    }
+    response.json().await.map_err(|e| format!("解析 {} 失敗: {}", location, e))
+}
+
+fn credentials(secret: &Value, version: Option<String>) -> Result<Credentials, String> {
+    let field = |name: &str| secret[name].as_str().unwrap_or_default().to_string();
+    Ok(Credentials {
+        api_key: field("api_key"),
+        secret_key: field("secret_key"),
+        passphrase: field("passphrase"),
+        version,
+    })
+}
+
+// 讀取一個交易所的憑證。simulated 環境（如指向 mock_exchange）只讀環境變量且可留空；testnet/live 環境
+// 缺少 API key 或密鑰（OKX 另需口令）時返回錯誤，以免用錯環境的憑證下單
+pub async fn fetch(provider: &dyn SecretsProvider, vars: &dyn Vars, exchange: &str, settings: &ExchangeConfig) -> Result<Credentials, String> {
+    if settings.environment == TradingEnvironment::Simulated {
+        return Ok(env_credentials(vars, exchange, settings));
+    }
+    let credentials = provider.fetch(exchange, settings).await?;
+    let required = [
+        ("api_key", &credentials.api_key),
+        ("secret_key", &credentials.secret_key),
+        ("passphrase", &credentials.passphrase),
+    ];
+    let count = if config::venue(exchange) == "okx" { 3 } else { 2 };
+    if let Some((field, _)) = required.iter().take(count).find(|(_, value)| value.is_empty()) {
+        return Err(format!(
+            "{} {:?} 環境的憑證缺少 {}，請檢查{}",
+            exchange,
+            settings.environment,
+            field,
+            provider.location(exchange, settings)
+        ));
+    }
+    Ok(credentials)
+}
+
+// 啟動時為每個經 REST/WebSocket 連接的交易所讀取憑證，任一失敗則拒絕啟動
+pub async fn load(engine: &ExecutionEngine, provider: &dyn SecretsProvider) -> Result<(), String> {
+    for (name, connector) in &engine.exchanges {
+        if connector.api.is_none() {
+            continue;
+        }
+        let credentials = fetch(provider, engine.env.vars.as_ref(), name, &connector.settings).await?;
+        info!(exchange = %name, provider = provider.name(), version = ?credentials.version, "已加載交易所憑證");
+        connector.set_credentials(credentials);
+    }
+    Ok(())
+}
+
+// 按 refresh_interval_secs 重新讀取密鑰服務，版本或內容變化時替換連接器的憑證；讀取失敗時沿用當前憑證。
+// 環境變量在進程內不會變化，不需要刷新
+pub fn spawn(engine: Arc<ExecutionEngine>, provider: Box<dyn SecretsProvider>, refresh_interval_secs: u64) {
+    if refresh_interval_secs == 0 || provider.name() == "env" {
+        return;
+    }
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(refresh_interval_secs));
+        interval.tick().await;
+        loop {
+            interval.tick().await;
+            for (name, connector) in &engine.exchanges {
+                if connector.api.is_none() || connector.settings.environment == TradingEnvironment::Simulated {
+                    continue;
+                }
+                match fetch(provider.as_ref(), engine.env.vars.as_ref(), name, &connector.settings).await {
+                    Ok(credentials) if credentials != *connector.credentials.read().unwrap() => {
+                        info!(exchange = %name, version = ?credentials.version, "交易所憑證已輪換");
+                        connector.set_credentials(credentials);
+                    }
+                    Ok(_) => {}
+                    Err(e) => warn!(exchange = %name, error = %e, "刷新交易所憑證失敗，沿用當前憑證"),
+                }
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::environment::FixedVars;
+
+    // 模擬 Vault KV v2：每次讀取返回當前版本，測試中修改版本模擬輪換
+    #[tokio::test]
+    async fn vault_credentials_are_versioned() {
+        use axum::routing::get;
+        let version = std::sync::Arc::new(std::sync::atomic::AtomicU64::new(1));
+        let served = std::sync::Arc::clone(&version);
+        let app = axum::Router::new().route(
+            "/v1/secret/data/arbitrage/bybit/live",
+            get(move |headers: axum::http::HeaderMap| async move {
+                if headers.get("X-Vault-Token").and_then(|token| token.to_str().ok()) != Some("e2e-token") {
+                    return Err(axum::http::StatusCode::FORBIDDEN);
+                }
+                let version = served.load(std::sync::atomic::Ordering::SeqCst);
+                Ok(axum::Json(serde_json::json!({
+                    "data": {
+                        "data": {"api_key": format!("key-v{}", version), "secret_key": "secret"},
+                        "metadata": {"version": version},
+                    }
+                })))
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await });
+
+        let vars = FixedVars::new([("E2E_VAULT_TOKEN", "e2e-token")]);
+        let vault = VaultSecrets::new(
+            &config::VaultConfig {
+                address: format!("http://{}", address),
+                token_env: "E2E_VAULT_TOKEN".to_string(),
+                ..Default::default()
+            },
+            &vars,
+        )
+        .unwrap();
+        let settings = config::ExchangeConfig {
+            environment: config::TradingEnvironment::Live,
+            ..Default::default()
+        };
+        let first = fetch(&vault, &vars, "bybit", &settings).await.unwrap();
+        assert_eq!((first.api_key.as_str(), first.version.as_deref()), ("key-v1", Some("1")));
+        assert!(!format!("{:?}", first).contains("secret"));
+
+        version.store(2, std::sync::atomic::Ordering::SeqCst);
+        let rotated = fetch(&vault, &vars, "bybit", &settings).await.unwrap();
+        assert_eq!(rotated.api_key, "key-v2");
+        assert_ne!(first, rotated);
+        // 未寫入密鑰的路徑讀取失敗
+        assert!(fetch(&vault, &vars, "okx", &settings).await.is_err());
+    }
+}
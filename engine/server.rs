@@ -0,0 +1,850 @@
+use crate::protocol::wire;
+use crate::{
+    client_auth, config, protocol, runtime, scanner, shared_memory, tls, ArbitrageResponse, BatchResponse, ClientMessage, CommandResponse,
+    EngineCommand, ExecutionEngine, ExecutionProgress, LogHandle,
+};
+use serde::Serialize;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::time::Instant;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::net::TcpListener;
+use tracing::{debug, error, info, info_span, warn, Instrument};
+
+const DEFAULT_LISTEN_ADDR: &str = "127.0.0.1:8080";
+
+/// 加載配置，按 `runtime` 配置建立運行時（可將執行與後台任務隔離到不同運行時並綁定 CPU 核）後運行 [`run`]。
+/// 不能在已有的 tokio 運行時內調用。
+pub fn serve(log_handle: Option<LogHandle>) -> Result<(), String> {
+    let config = config::EngineConfig::load().map_err(|e| format!("加載引擎配置失敗: {}", e))?;
+    let runtime_config = config.runtime.clone();
+    runtime::block_on(&runtime_config, run_with(config, log_handle))?
+}
+
+/// 加載配置並啟動引擎，在 `ARB_LISTEN_ADDR`（缺省 127.0.0.1:8080）上服務客戶端連接；配置啟用 TLS 時
+/// 握手在各連接任務內完成。啟用 `unix_socket` 或 `shared_memory` 時同時經套接字文件或共享內存服務同機客戶端。收到 SIGINT/SIGTERM 後停止接受連接，等待執行中的請求完成後返回。
+pub async fn run(log_handle: Option<LogHandle>) -> Result<(), String> {
+    let config = config::EngineConfig::load().map_err(|e| format!("加載引擎配置失敗: {}", e))?;
+    run_with(config, log_handle).await
+}
+
+async fn run_with(config: config::EngineConfig, log_handle: Option<LogHandle>) -> Result<(), String> {
+    info!("啟動 Rust 執行引擎");
+    let tls_config = config.tls.clone();
+    let unix_socket = config.unix_socket.clone();
+    let shared_memory_config = config.shared_memory.clone();
+    let drain_timeout = Duration::from_secs(config.shutdown.drain_timeout_secs);
+    let acceptor = if tls_config.enabled {
+        Some(tls::acceptor(&tls_config).map_err(|e| format!("加載 TLS 證書失敗: {}", e))?)
+    } else {
+        None
+    };
+    let engine = ExecutionEngine::start(config, log_handle).await?;
+    // 多區域部署時需監聽其他實例可達的地址
+    let listen_address = engine.env.var("ARB_LISTEN_ADDR").unwrap_or_else(|| DEFAULT_LISTEN_ADDR.to_string());
+    let listener = TcpListener::bind(&listen_address)
+        .await
+        .map_err(|e| format!("監聽 {} 失敗: {}", listen_address, e))?;
+    
+    // 同機客戶端經 Unix 域套接字連接，省去 TCP 回環開銷且不暴露端口
+    let local_listener = match unix_socket.enabled {
+        true => Some(LocalListener::bind(&unix_socket)?),
+        false => None,
+    };
+    let shared_memory = match shared_memory_config.enabled {
+        true => Some(shared_memory::Server::start(Arc::clone(&engine), &shared_memory_config)?),
+        false => None,
+    };
+    
+    *engine.listen_address.write().unwrap() = Some(listen_address.clone());
+    info!(%listen_address, tls = acceptor.is_some(), unix_socket = unix_socket.enabled.then_some(unix_socket.path.as_str()), "Rust 引擎已啟動");
+    
+    let shutdown = shutdown_signal();
+    tokio::pin!(shutdown);
+    loop {
+        let accepted = tokio::select! {
+            accepted = listener.accept() => accepted,
+            accepted = accept_local(local_listener.as_ref()) => {
+                match accepted {
+                    Ok(socket) => {
+                        info!(path = %unix_socket.path, "新的 Unix 域套接字連接");
+                        let engine_clone = Arc::clone(&engine);
+                        tokio::spawn(
+                            async move { handle_connection(socket, &engine_clone).await }
+                                .instrument(info_span!("connection", path = %unix_socket.path)),
+                        );
+                    }
+                    Err(e) => error!(error = %e, "接受 Unix 域套接字連接失敗"),
+                }
+                continue;
+            }
+            _ = &mut shutdown => break,
+        };
+        match accepted {
+            Ok((socket, addr)) => {
+                info!(%addr, "新連接");
+                let engine_clone = Arc::clone(&engine);
+                let acceptor = acceptor.clone();
+                tokio::spawn(
+                    async move {
+                        match acceptor {
+                            // 握手在連接任務內完成，慢速或惡意客戶端不阻塞 accept
+                            Some(acceptor) => match acceptor.accept(socket).await {
+                                Ok(stream) => handle_connection(stream, &engine_clone).await,
+                                Err(e) => warn!(error = %e, "TLS 握手失敗"),
+                            },
+                            None => handle_connection(socket, &engine_clone).await,
+                        }
+                    }
+                    .instrument(info_span!("connection", %addr)),
+                );
+            }
+            Err(e) => {
+                error!(error = %e, "接受連接失敗");
+            }
+        }
+    }
+    
+    // 不再接受新連接；已建立的連接上的新請求由 shutting_down 拒絕
+    drop(listener);
+    drop(local_listener);
+    drop(shared_memory);
+    *engine.listen_address.write().unwrap() = None;
+    engine.shutdown(drain_timeout).await;
+    Ok(())
+}
+
+// Unix 域套接字監聽；退出時刪除套接字文件
+#[cfg(unix)]
+pub(crate) struct LocalListener {
+    listener: tokio::net::UnixListener,
+    path: String,
+}
+
+#[cfg(unix)]
+impl LocalListener {
+    pub(crate) fn bind(config: &config::UnixSocketConfig) -> Result<Self, String> {
+        use std::os::unix::fs::{FileTypeExt, PermissionsExt};
+        let path = &config.path;
+        // 上次未正常退出遺留的套接字文件：仍有進程在監聽時不搶佔，其他類型的文件不刪除
+        if let Ok(metadata) = std::fs::symlink_metadata(path) {
+            if !metadata.file_type().is_socket() {
+                return Err(format!("{} 已存在且不是套接字文件", path));
+            }
+            if std::os::unix::net::UnixStream::connect(path).is_ok() {
+                return Err(format!("{} 上已有進程在監聽", path));
+            }
+            std::fs::remove_file(path).map_err(|e| format!("刪除遺留的套接字文件 {} 失敗: {}", path, e))?;
+        }
+        let listener = tokio::net::UnixListener::bind(path).map_err(|e| format!("監聽 {} 失敗: {}", path, e))?;
+        let listener = Self {
+            listener,
+            path: path.clone(),
+        };
+        std::fs::set_permissions(path, std::fs::Permissions::from_mode(config.permissions()?))
+            .map_err(|e| format!("設置 {} 的權限失敗: {}", path, e))?;
+        Ok(listener)
+    }
+
+    pub(crate) async fn accept(&self) -> std::io::Result<tokio::net::UnixStream> {
+        self.listener.accept().await.map(|(socket, _)| socket)
+    }
+}
+
+#[cfg(unix)]
+impl Drop for LocalListener {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+#[cfg(not(unix))]
+pub(crate) struct LocalListener;
+
+#[cfg(not(unix))]
+impl LocalListener {
+    pub(crate) fn bind(_config: &config::UnixSocketConfig) -> Result<Self, String> {
+        Err("當前平台不支持 Unix 域套接字".to_string())
+    }
+
+    pub(crate) async fn accept(&self) -> std::io::Result<tokio::io::DuplexStream> {
+        std::future::pending().await
+    }
+}
+
+// 未啟用 Unix 域套接字時永不返回
+async fn accept_local(listener: Option<&LocalListener>) -> std::io::Result<impl AsyncRead + AsyncWrite + Unpin + Send + 'static> {
+    match listener {
+        Some(listener) => listener.accept().await,
+        None => std::future::pending().await,
+    }
+}
+
+// SIGINT 或 SIGTERM
+async fn shutdown_signal() {
+    let interrupt = async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            error!(error = %e, "監聽 SIGINT 失敗");
+            std::future::pending::<()>().await;
+        }
+    };
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut signal) => {
+                signal.recv().await;
+            }
+            Err(e) => {
+                error!(error = %e, "監聽 SIGTERM 失敗");
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+    tokio::select! {
+        _ = interrupt => info!("收到 SIGINT，開始關閉"),
+        _ = terminate => info!("收到 SIGTERM，開始關閉"),
+    }
+}
+
+// 連接級狀態：認證身份、機會訂閱與協商的線路編碼
+struct Connection {
+    principal: Option<client_auth::Principal>,
+    opportunities: Option<tokio::sync::broadcast::Receiver<Vec<scanner::Opportunity>>>,
+    encoding: wire::Encoding,
+}
+
+pub(crate) async fn handle_connection<S: AsyncRead + AsyncWrite + Unpin>(mut socket: S, engine: &ExecutionEngine) {
+    let mut buffer = [0; 1024];
+    // 跨讀取拼接的未完整消息
+    let mut pending = Vec::new();
+    // 響應與推送的編碼緩衝，整個連接複用
+    let mut output = Vec::with_capacity(4096);
+    let mut connection = Connection {
+        principal: None,
+        opportunities: None,
+        encoding: wire::Encoding::Json,
+    };
+    let heartbeat_interval = Duration::from_millis(engine.config.connections.heartbeat_interval_ms);
+    let idle_timeout = Duration::from_millis(engine.config.connections.idle_timeout_ms);
+    let mut heartbeat = tokio::time::interval(heartbeat_interval);
+    heartbeat.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    heartbeat.tick().await;
+    // 最近一次收到客戶端消息（或處理完請求）的時間
+    let mut last_seen = Instant::now();
+    
+    loop {
+        // 已訂閱時同時等待客戶端請求與掃描器推送
+        let read = tokio::select! {
+            read = socket.read(&mut buffer) => read,
+            _ = heartbeat.tick() => {
+                let idle = last_seen.elapsed();
+                if idle >= idle_timeout {
+                    warn!(idle_ms = idle.as_millis() as u64, "客戶端連接空閒超時，關閉連接");
+                    break;
+                }
+                if idle >= heartbeat_interval {
+                    let ping = serde_json::json!({ "event": "ping", "data": { "at_ms": engine.env.now_ms() } });
+                    if let Err(e) = send(&mut socket, connection.encoding, &ping, &mut output).await {
+                        warn!(error = %e, "發送心跳失敗，關閉連接");
+                        break;
+                    }
+                }
+                continue;
+            }
+            pushed = recv_opportunities(&mut connection.opportunities) => {
+                let push = serde_json::json!({ "event": "opportunities", "data": pushed });
+                if let Err(e) = send(&mut socket, connection.encoding, &push, &mut output).await {
+                    error!(error = %e, "推送套利機會失敗");
+                    break;
+                }
+                continue;
+            }
+        };
+        match read {
+            Ok(0) => {
+                info!("連接關閉");
+                break;
+            }
+            Ok(n) => {
+                // JSON 按值的邊界、二進制按幀切分：一次讀取可能包含多條消息，一條消息也可能跨多次讀取；
+                // 每條消息的響應使用其到達時的編碼
+                let encoding = connection.encoding;
+                let mut framing_error = None;
+                let mut write_failed = false;
+                pending.extend_from_slice(&buffer[..n]);
+                // 已處理的消息在本次讀取結束後一次移出
+                let mut offset = 0;
+                loop {
+                    let (message_len, header_len) = match encoding {
+                        wire::Encoding::Json => (wire::json_message_len(&pending[offset..]), 0),
+                        wire::Encoding::Msgpack => (wire::frame_len(&pending[offset..]), wire::HEADER_LEN),
+                    };
+                    match message_len {
+                        Ok(Some(len)) => {
+                            let payload = &pending[offset + header_len..offset + len];
+                            offset += len;
+                            if !respond(engine, &mut connection, encoding, payload, &mut socket, &mut output).await {
+                                write_failed = true;
+                                break;
+                            }
+                            // 切換編碼後的剩餘數據不再按原編碼解析
+                            if connection.encoding != encoding {
+                                break;
+                            }
+                        }
+                        Ok(None) => break,
+                        Err(e) => {
+                            framing_error = Some(e);
+                            break;
+                        }
+                    }
+                }
+                pending.drain(..offset);
+                if write_failed {
+                    break;
+                }
+                // 處理完才計時：長時間執行期間沒有讀取，不算空閒
+                last_seen = Instant::now();
+                if connection.encoding != encoding {
+                    pending.clear();
+                }
+                match framing_error {
+                    // JSON 消息無效時無法找到下一條消息的邊界，丟棄已收到的數據，連接保持
+                    Some(e) if encoding == wire::Encoding::Json => {
+                        warn!(error = %e, "JSON 消息無效，丟棄未處理的數據");
+                        pending.clear();
+                        if send(&mut socket, encoding, &ArbitrageResponse::error(e), &mut output).await.is_err() {
+                            break;
+                        }
+                    }
+                    Some(e) => {
+                        warn!(error = %e, "二進制幀無效，關閉連接");
+                        let error_response = ArbitrageResponse::error(format!("幀無效: {}", e));
+                        let _ = send(&mut socket, encoding, &error_response, &mut output).await;
+                        break;
+                    }
+                    None => {}
+                }
+            }
+            // TLS 客戶端未發送 close_notify 直接斷開
+            Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => {
+                info!("連接關閉");
+                break;
+            }
+            Err(e) => {
+                error!(error = %e, "讀取數據失敗");
+                break;
+            }
+        }
+    }
+    if connection.opportunities.take().is_some() {
+        info!("連接結束，已取消套利機會訂閱");
+    }
+}
+
+// 處理一條客戶端消息，按 encoding 編碼的響應寫入 output；stream_progress 請求執行期間的進度直接寫入 socket
+async fn handle_message<S: AsyncWrite + Unpin>(
+    engine: &ExecutionEngine,
+    connection: &mut Connection,
+    encoding: wire::Encoding,
+    payload: &[u8],
+    socket: &mut S,
+    output: &mut Vec<u8>,
+) {
+    let parse_started = std::time::Instant::now();
+    let parsed = wire::decode::<serde_json::Value>(encoding, payload)
+        .map_err(|e| format!("解析失敗: {}", e))
+        .and_then(protocol::parse);
+    let parse_micros = parse_started.elapsed().as_micros() as u64;
+    
+    let message = match parsed {
+        Ok(message) => message,
+        Err(e) => {
+            warn!(error = %e, "解析請求失敗");
+            wire::encode_into(encoding, &ArbitrageResponse::error(e), output);
+            return;
+        }
+    };
+    let denied = engine.auth.authorize(connection.principal.as_ref(), &message).err();
+    match message {
+        ClientMessage::Execute(_) if denied.is_some() => {
+            warn!(error = ?denied, "拒絕未授權的套利請求");
+            wire::encode_into(encoding, &ArbitrageResponse::error(denied.unwrap_or_default()), output)
+        }
+        ClientMessage::Batch(_) if denied.is_some() => {
+            warn!(error = ?denied, "拒絕未授權的批量套利請求");
+            wire::encode_into(encoding, &BatchResponse::error(denied.unwrap_or_default()), output)
+        }
+        ClientMessage::Command(_) if denied.is_some() => {
+            warn!(error = ?denied, "拒絕未授權的指令");
+            wire::encode_into(encoding, &CommandResponse::error(denied.unwrap_or_default()), output)
+        }
+        ClientMessage::Command(EngineCommand::Authenticate(request)) => {
+            let response = match engine.auth.authenticate(&request) {
+                Ok(authenticated) => {
+                    info!(client = %authenticated.client, scopes = ?authenticated.scopes, "客戶端已認證");
+                    let data = serde_json::json!(authenticated);
+                    connection.principal = Some(authenticated);
+                    CommandResponse::ok(Some(data))
+                }
+                Err(e) => {
+                    warn!(client = %request.client, error = %e, "客戶端認證失敗");
+                    CommandResponse::error(e)
+                }
+            };
+            wire::encode_into(encoding, &response, output)
+        }
+        ClientMessage::Command(EngineCommand::SetEncoding { encoding: negotiated }) => {
+            info!(from = ?encoding, to = ?negotiated, "連接切換線路編碼");
+            connection.encoding = negotiated;
+            let data = serde_json::json!({ "encoding": negotiated, "frame_version": wire::FRAME_VERSION });
+            wire::encode_into(encoding, &CommandResponse::ok(Some(data)), output)
+        }
+        ClientMessage::Execute(mut request) => {
+            let mut response = if request.stream_progress {
+                let (sink, progress) = tokio::sync::mpsc::unbounded_channel();
+                request.progress = Some(sink);
+                stream_progress(socket, encoding, engine.submit(*request), progress).await
+            } else {
+                engine.submit(*request).await
+            };
+            engine.latency.record("request_parse", parse_micros);
+            if let Some(timings) = &mut response.timings {
+                timings.prepend("request_parse", parse_micros);
+            }
+            wire::encode_into(encoding, &response, output)
+        }
+        ClientMessage::Batch(batch) => {
+            engine.latency.record("request_parse", parse_micros);
+            let response = engine.submit_batch(batch).await;
+            wire::encode_into(encoding, &response, output)
+        }
+        // 只用於刷新空閒計時，不返回響應
+        ClientMessage::Command(EngineCommand::Pong { at_ms }) => {
+            debug!(?at_ms, "收到心跳回應");
+            output.clear();
+        }
+        ClientMessage::Command(EngineCommand::SubscribeOpportunities) => {
+            connection.opportunities = Some(engine.scanner.subscribe());
+            wire::encode_into(encoding, &CommandResponse::ok(None), output)
+        }
+        ClientMessage::Command(command) => {
+            let response = engine.handle_command(command).await;
+            wire::encode_into(encoding, &response, output)
+        }
+    }
+}
+
+// 在新的連接狀態上處理一條消息，進度推送丟棄；供基準測量解析到分派的耗時
+pub(crate) async fn dispatch(engine: &ExecutionEngine, encoding: wire::Encoding, payload: &[u8], output: &mut Vec<u8>) {
+    let mut connection = Connection {
+        principal: None,
+        opportunities: None,
+        encoding,
+    };
+    handle_message(engine, &mut connection, encoding, payload, &mut tokio::io::sink(), output).await
+}
+
+// 處理一條消息並寫出響應，寫出失敗時返回 false
+async fn respond<S: AsyncRead + AsyncWrite + Unpin>(
+    engine: &ExecutionEngine,
+    connection: &mut Connection,
+    encoding: wire::Encoding,
+    payload: &[u8],
+    socket: &mut S,
+    output: &mut Vec<u8>,
+) -> bool {
+    handle_message(engine, connection, encoding, payload, socket, output).await;
+    match socket.write_all(output).await {
+        Ok(()) => true,
+        Err(e) => {
+            error!(error = %e, "發送響應失敗");
+            false
+        }
+    }
+}
+
+async fn send<S: AsyncWrite + Unpin, T: Serialize>(
+    socket: &mut S,
+    encoding: wire::Encoding,
+    value: &T,
+    output: &mut Vec<u8>,
+) -> std::io::Result<()> {
+    wire::encode_into(encoding, value, output);
+    socket.write_all(output).await
+}
+
+// 執行期間轉發進度推送，最終響應之前推送完全部進度；推送失敗不中斷執行
+async fn stream_progress<S: AsyncWrite + Unpin>(
+    socket: &mut S,
+    encoding: wire::Encoding,
+    execution: impl std::future::Future<Output = ArbitrageResponse>,
+    mut progress: tokio::sync::mpsc::UnboundedReceiver<ExecutionProgress>,
+) -> ArbitrageResponse {
+    tokio::pin!(execution);
+    let mut connected = true;
+    let mut output = Vec::new();
+    let response = loop {
+        tokio::select! {
+            response = &mut execution => break response,
+            Some(event) = progress.recv() => {
+                connected = connected && push_progress(socket, encoding, &event, &mut output).await;
+            }
+        }
+    };
+    while let Ok(event) = progress.try_recv() {
+        connected = connected && push_progress(socket, encoding, &event, &mut output).await;
+    }
+    response
+}
+
+async fn push_progress<S: AsyncWrite + Unpin>(
+    socket: &mut S,
+    encoding: wire::Encoding,
+    event: &ExecutionProgress,
+    output: &mut Vec<u8>,
+) -> bool {
+    let push = serde_json::json!({ "event": "execution_progress", "data": event });
+    match send(socket, encoding, &push, output).await {
+        Ok(()) => true,
+        Err(e) => {
+            warn!(error = %e, execution_id = %event.execution_id, "推送執行進度失敗");
+            false
+        }
+    }
+}
+
+// 未訂閱時永不返回；訂閱者落後時跳過積壓的舊批次
+async fn recv_opportunities(
+    receiver: &mut Option<tokio::sync::broadcast::Receiver<Vec<scanner::Opportunity>>>,
+) -> Vec<scanner::Opportunity> {
+    use tokio::sync::broadcast::error::RecvError;
+    loop {
+        match receiver {
+            Some(rx) => match rx.recv().await {
+                Ok(batch) => return batch,
+                Err(RecvError::Lagged(skipped)) => warn!(skipped, "訂閱者處理過慢，已跳過舊的機會推送"),
+                Err(RecvError::Closed) => *receiver = None,
+            },
+            None => std::future::pending::<()>().await,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bench::START_MS;
+    use crate::deterministic_sim::{build, connect_sessions, funded_config, request};
+    use crate::mock_exchange::Scenario;
+    use crate::mock_exchange_e2e::engine;
+    use crate::{config, execution_queue, load_test, ArbitrageRequest, ArbitrageResponse, BatchResponse, CommandResponse, Environment, StrategyType};
+    use serde_json::Value;
+
+    // 讀到一條完整的 JSON 響應為止
+    async fn read_message(stream: &mut tokio::net::TcpStream, received: &mut Vec<u8>) -> serde_json::Value {
+        let mut buffer = [0; 4096];
+        loop {
+            if let Some(len) = wire::json_message_len(received).unwrap() {
+                let message = serde_json::from_slice(&received[..len]).unwrap();
+                received.drain(..len);
+                return message;
+            }
+            let n = stream.read(&mut buffer).await.unwrap();
+            assert!(n > 0, "連接已關閉");
+            received.extend_from_slice(&buffer[..n]);
+        }
+    }
+
+    // 超過單次讀取緩衝的消息跨多次讀取拼接，一次寫入的多條消息逐條響應
+    #[tokio::test]
+    async fn json_messages_are_framed_across_and_within_reads() {
+        let mut config = config::EngineConfig::default();
+        config.execution_queue.enabled = false;
+        let (engine, path) = build("server-framing", config, Environment::simulated(START_MS, 1));
+        let engine = Arc::new(engine);
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let serving = Arc::clone(&engine);
+        tokio::spawn(async move {
+            let (socket, _) = listener.accept().await.unwrap();
+            handle_connection(socket, &serving).await;
+        });
+        let mut stream = tokio::net::TcpStream::connect(address).await.unwrap();
+        let mut received = Vec::new();
+
+        let legs: Vec<ArbitrageRequest> = (1..=8).map(|leg| request(100.0 * leg as f64)).collect();
+        let batch = serde_json::to_vec(&serde_json::json!({ "version": 2, "requests": legs })).unwrap();
+        assert!(batch.len() > 1024, "{}", batch.len());
+        stream.write_all(&batch).await.unwrap();
+        let response: BatchResponse = serde_json::from_value(read_message(&mut stream, &mut received).await).unwrap();
+        assert_eq!(response.results.len(), 8, "{:?}", response.error_message);
+        assert_eq!(response.succeeded + response.failed, 8);
+
+        stream.write_all(br#"{"command":"ping"} {"command":"ping"}"#).await.unwrap();
+        for _ in 0..2 {
+            let response: CommandResponse = serde_json::from_value(read_message(&mut stream, &mut received).await).unwrap();
+            assert_eq!(response.status, "success");
+        }
+
+        // 無效的 JSON 返回錯誤並丟棄，連接仍可繼續使用
+        stream.write_all(b"{not json}").await.unwrap();
+        let response: ArbitrageResponse = serde_json::from_value(read_message(&mut stream, &mut received).await).unwrap();
+        assert!(response.error_message.unwrap().starts_with("解析失敗"));
+        stream.write_all(br#"{"command":"ping"}"#).await.unwrap();
+        let response: CommandResponse = serde_json::from_value(read_message(&mut stream, &mut received).await).unwrap();
+        assert_eq!(response.status, "success");
+        let _ = std::fs::remove_file(path);
+    }
+
+    // 協商 msgpack 的連接以帶版本幀頭的二進制幀收發執行請求，其他連接仍默認 JSON；幀格式版本不符時返回錯誤並關閉連接
+    #[tokio::test]
+    async fn msgpack_connections_execute_alongside_json_clients() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+        let (engine, path) = build("msgpack-execute", funded_config(), Environment::simulated(START_MS, 1));
+        let engine = Arc::new(engine);
+        connect_sessions(&engine).await;
+        execution_queue::spawn(Arc::clone(&engine));
+        engine.quotes.record_predicted_rate("binance", "BTCUSDT", 0.0060, START_MS);
+        engine.quotes.record_predicted_rate("bybit", "BTCUSDT", 0.0010, START_MS);
+        let connect = |engine: &Arc<ExecutionEngine>| {
+            let (client, socket) = tokio::io::duplex(64 * 1024);
+            let serving = Arc::clone(engine);
+            (client, tokio::spawn(async move { handle_connection(socket, &serving).await }))
+        };
+        async fn read_frame(client: &mut tokio::io::DuplexStream) -> ArbitrageResponse {
+            let mut received = Vec::new();
+            let mut buffer = vec![0; 64 * 1024];
+            loop {
+                if let Some(len) = wire::frame_len(&received).unwrap() {
+                    return wire::decode(wire::Encoding::Msgpack, &received[wire::HEADER_LEN..len]).unwrap();
+                }
+                let n = client.read(&mut buffer).await.unwrap();
+                assert!(n > 0, "連接已關閉");
+                received.extend_from_slice(&buffer[..n]);
+            }
+        }
+        let mut buffer = vec![0; 64 * 1024];
+        let (mut binary, binary_connection) = connect(&engine);
+        let (mut json, _) = connect(&engine);
+        binary.write_all(br#"{"command":"set_encoding","encoding":"msgpack"}"#).await.unwrap();
+        let n = binary.read(&mut buffer).await.unwrap();
+        let negotiated: serde_json::Value = serde_json::from_slice(&buffer[..n]).unwrap();
+        assert_eq!(negotiated["data"], serde_json::json!({"encoding": "msgpack", "frame_version": wire::FRAME_VERSION}));
+
+        let execute = serde_json::to_value(request(1_000.0)).unwrap();
+        binary.write_all(&wire::encode(wire::Encoding::Msgpack, &execute)).await.unwrap();
+        json.write_all(execute.to_string().as_bytes()).await.unwrap();
+        let response = read_frame(&mut binary).await;
+        assert_eq!(response.status, "success", "{:?}", response.error_message);
+        assert_eq!(response.timings.unwrap().stages[0].stage, "request_parse");
+        let n = json.read(&mut buffer).await.unwrap();
+        let response: ArbitrageResponse = serde_json::from_slice(&buffer[..n]).unwrap();
+        assert_eq!(response.status, "success", "{:?}", response.error_message);
+
+        // 未來版本的幀無法按當前格式解析
+        let mut frame = wire::encode(wire::Encoding::Msgpack, &execute);
+        frame[2] = wire::FRAME_VERSION + 1;
+        binary.write_all(&frame).await.unwrap();
+        let rejected = read_frame(&mut binary).await;
+        assert!(rejected.error_message.unwrap().contains("不支持的幀格式版本 2"));
+        assert_eq!(binary.read(&mut buffer).await.unwrap(), 0);
+        tokio::time::timeout(Duration::from_secs(1), binary_connection).await.unwrap().unwrap();
+        assert_eq!(engine.queue.snapshot().dispatched, 2);
+        let _ = std::fs::remove_file(path);
+    }
+
+    // 多個客戶端連接共享同一引擎並發執行，另一連接同時切換策略開關：每筆執行有唯一 ID，
+    // 結束後都從執行中列表移除，策略開關停在最後一次設置
+    #[tokio::test]
+    async fn concurrent_connections_share_engine_state() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+        let (engine, path) = build("connections", funded_config(), Environment::simulated(START_MS, 1));
+        let engine = Arc::new(engine);
+        connect_sessions(&engine).await;
+        execution_queue::spawn(Arc::clone(&engine));
+        async fn exchange(engine: &Arc<ExecutionEngine>, messages: Vec<serde_json::Value>) -> Vec<serde_json::Value> {
+            let (mut client, socket) = tokio::io::duplex(64 * 1024);
+            let serving = Arc::clone(engine);
+            tokio::spawn(async move { handle_connection(socket, &serving).await });
+            let mut buffer = vec![0; 64 * 1024];
+            let mut responses = Vec::new();
+            for message in messages {
+                client.write_all(message.to_string().as_bytes()).await.unwrap();
+                let n = client.read(&mut buffer).await.unwrap();
+                responses.push(serde_json::from_slice(&buffer[..n]).unwrap());
+            }
+            responses
+        }
+        let execute = serde_json::to_value(request(1_000.0)).unwrap();
+        let toggles = (0..20)
+            .map(|n| serde_json::json!({"command": "set_strategy_enabled", "strategy": "triangular", "enabled": n % 2 == 1}))
+            .collect();
+        let clients: Vec<_> = std::iter::repeat_n(vec![execute; 5], 8)
+            .chain([toggles])
+            .map(|messages| {
+                let engine = Arc::clone(&engine);
+                tokio::spawn(async move { exchange(&engine, messages).await })
+            })
+            .collect();
+        let mut responses = Vec::new();
+        for client in clients {
+            responses.extend(client.await.unwrap());
+        }
+        let (toggled, executed): (Vec<_>, Vec<_>) = responses.into_iter().partition(|response| response.get("execution_id").is_none());
+        assert!(toggled.iter().all(|response| response["status"] == "success"));
+        let ids: std::collections::HashSet<_> = executed.iter().map(|response| response["execution_id"].as_str().unwrap().to_string()).collect();
+        assert_eq!(ids.len(), 40);
+        assert!(executed.iter().any(|response| response["status"] == "success"));
+        assert!(engine.open_executions.is_empty());
+        assert!(!engine.disabled_strategies.read().unwrap().contains(&StrategyType::Triangular));
+        let strategies = engine.handle_command(EngineCommand::GetStrategies).await.data.unwrap();
+        assert_eq!(strategies["triangular"], true);
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[tokio::test]
+    async fn leg_progress_streams_to_requester() {
+        let (_mock, engine, path) = engine("progress", Scenario::default()).await;
+        let gas_quote = engine.default_chain().gas_optimizer.quote(5);
+        let (sink, mut progress) = tokio::sync::mpsc::unbounded_channel();
+        let request = ArbitrageRequest {
+            progress: Some(sink),
+            ..request(10.0)
+        };
+        engine.execute_flash_loan_arbitrage("sim", &request, 0.0005, &gas_quote, false, None).await.unwrap();
+        let mut stages = Vec::new();
+        while let Ok(event) = progress.try_recv() {
+            assert_eq!(event.execution_id, "sim");
+            stages.push((event.stage, event.exchange.unwrap(), event.filled_quantity));
+        }
+        let stage = |stage: &str, exchange: &str, filled: Option<f64>| (stage.to_string(), exchange.to_string(), filled);
+        assert_eq!(
+            stages,
+            [
+                stage("leg1_submitted", "binance", None),
+                stage("leg1_filled", "binance", Some(10.0)),
+                stage("leg2_submitted", "bybit", None),
+                stage("leg2_filled", "bybit", Some(10.0)),
+            ]
+        );
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[tokio::test]
+    async fn idle_connections_get_pings_then_close() {
+        let mut config = config::EngineConfig::default();
+        config.connections = config::ConnectionConfig {
+            heartbeat_interval_ms: 50,
+            idle_timeout_ms: 300,
+        };
+        let (engine, path) = build("heartbeat", config, Environment::system());
+        let (mut client, socket) = tokio::io::duplex(64 * 1024);
+        let connection = tokio::spawn(async move { handle_connection(socket, &engine).await });
+
+        // 每次讀取可能包含多條 JSON 消息
+        let mut buffer = vec![0; 64 * 1024];
+        let mut read = async |client: &mut tokio::io::DuplexStream| -> Vec<Value> {
+            let n = client.read(&mut buffer).await.unwrap();
+            serde_json::Deserializer::from_slice(&buffer[..n]).into_iter::<Value>().map(Result::unwrap).collect()
+        };
+        client.write_all(br#"{"command":"subscribe_opportunities"}"#).await.unwrap();
+        assert_eq!(read(&mut client).await[0]["status"], "success");
+
+        // 空閒後推送 ping；pong 不返回響應
+        let pushed = read(&mut client).await;
+        assert_eq!(pushed[0]["event"], "ping");
+        client.write_all(br#"{"command":"pong","at_ms":0}"#).await.unwrap();
+        let mut closed = false;
+        while let Ok(messages) = tokio::time::timeout(std::time::Duration::from_secs(2), read(&mut client)).await {
+            if messages.is_empty() {
+                closed = true;
+                break;
+            }
+            assert!(messages.iter().all(|message| message["event"] == "ping"), "{:?}", messages);
+        }
+        assert!(closed, "空閒連接未被關閉");
+        tokio::time::timeout(std::time::Duration::from_secs(1), connection).await.unwrap().unwrap();
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[tokio::test]
+    async fn msgpack_frames_split_across_reads_are_all_answered() {
+        let (engine, path) = build("msgpack", config::EngineConfig::default(), Environment::system());
+        let (mut client, socket) = tokio::io::duplex(64 * 1024);
+        let connection = tokio::spawn(async move { handle_connection(socket, &engine).await });
+        let mut buffer = vec![0; 64 * 1024];
+        client.write_all(br#"{"command":"set_encoding","encoding":"msgpack"}"#).await.unwrap();
+        let n = client.read(&mut buffer).await.unwrap();
+        assert_eq!(serde_json::from_slice::<Value>(&buffer[..n]).unwrap()["status"], "success");
+
+        // 兩幀連續發送，第二幀分兩次寫入
+        let mut frames = wire::encode(wire::Encoding::Msgpack, &serde_json::json!({"command": "get_latency"}));
+        frames.extend(wire::encode(wire::Encoding::Msgpack, &serde_json::json!({"command": "get_quotes"})));
+        let split = frames.len() - 3;
+        client.write_all(&frames[..split]).await.unwrap();
+        client.flush().await.unwrap();
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+        client.write_all(&frames[split..]).await.unwrap();
+
+        let mut received = Vec::new();
+        let mut responses = Vec::new();
+        while responses.len() < 2 {
+            let n = client.read(&mut buffer).await.unwrap();
+            received.extend_from_slice(&buffer[..n]);
+            while let Some(len) = wire::frame_len(&received).unwrap() {
+                responses.push(wire::decode::<Value>(wire::Encoding::Msgpack, &received[wire::HEADER_LEN..len]).unwrap());
+                received.drain(..len);
+            }
+        }
+        assert!(responses.iter().all(|response| response["status"] == "success"), "{:?}", responses);
+        drop(client);
+        tokio::time::timeout(std::time::Duration::from_secs(1), connection).await.unwrap().unwrap();
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn unix_socket_listener_serves_same_protocol() {
+        use std::os::unix::fs::PermissionsExt;
+        let (engine, path) = build("unix-socket", config::EngineConfig::default(), Environment::system());
+        let engine = std::sync::Arc::new(engine);
+        let socket_path = std::env::temp_dir().join(format!("arb-engine-{}.sock", std::process::id()));
+        let config = config::UnixSocketConfig {
+            enabled: true,
+            path: socket_path.to_string_lossy().into_owned(),
+            mode: "660".to_string(),
+        };
+        // 遺留的套接字文件無人監聽時被替換，有進程監聽時拒絕
+        drop(std::os::unix::net::UnixListener::bind(&socket_path).unwrap());
+        let listener = LocalListener::bind(&config).unwrap();
+        assert!(LocalListener::bind(&config).err().unwrap().contains("已有進程在監聽"));
+        assert_eq!(std::fs::metadata(&socket_path).unwrap().permissions().mode() & 0o777, 0o660);
+
+        let server = tokio::spawn({
+            let engine = std::sync::Arc::clone(&engine);
+            async move {
+                while let Ok(socket) = listener.accept().await {
+                    let engine = std::sync::Arc::clone(&engine);
+                    tokio::spawn(async move { handle_connection(socket, &engine).await });
+                }
+            }
+        });
+        let params = load_test::LoadTestParams {
+            address: format!("unix:{}", config.path),
+            connections: 1,
+            rate_per_sec: 50.0,
+            duration_secs: 1,
+            ..Default::default()
+        };
+        let report = load_test::run(&params).await.unwrap();
+        assert!(report.sent > 0 && report.succeeded == report.sent, "{:?}", report);
+        // 監聽關閉後刪除套接字文件
+        server.abort();
+        let _ = server.await;
+        assert!(!socket_path.exists());
+        let _ = std::fs::remove_file(path);
+    }
+}
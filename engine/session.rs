@@ -0,0 +1,336 @@
+use super::config::{SessionConfig, Severity};
+use super::environment::Rng;
+use super::user_stream::{self, UserEvent};
+use super::ExecutionEngine;
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tokio::sync::broadcast;
+use tracing::{info, warn};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Health {
+    Connecting,
+    Connected,
+    Reconnecting,
+    // 連續重連失敗達到閾值
+    Degraded,
+    // 引擎關閉，不再重連
+    Closed,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SessionSnapshot {
+    pub health: Health,
+    pub consecutive_failures: u32,
+    pub reconnects: u64,
+    pub connected_since_ms: Option<i64>,
+    pub last_disconnect_ms: Option<i64>,
+    pub last_error: Option<String>,
+    pub subscriptions: BTreeSet<String>,
+    pub listen_key: Option<String>,
+    pub listen_key_renewed_ms: Option<i64>,
+}
+
+pub struct SessionSupervisor {
+    config: SessionConfig,
+    // 健康狀態在每次執行與掃描時讀取，只在連接狀態變化時寫入
+    sessions: BTreeMap<String, RwLock<SessionSnapshot>>,
+}
+
+fn now_ms() -> i64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_millis() as i64).unwrap_or_default()
+}
+
+impl SessionSupervisor {
+    pub fn new<'a>(config: SessionConfig, exchanges: impl IntoIterator<Item = &'a str>) -> Self {
+        let sessions = exchanges
+            .into_iter()
+            .map(|exchange| {
+                let state = SessionSnapshot {
+                    health: Health::Connecting,
+                    consecutive_failures: 0,
+                    reconnects: 0,
+                    connected_since_ms: None,
+                    last_disconnect_ms: None,
+                    last_error: None,
+                    subscriptions: BTreeSet::new(),
+                    listen_key: None,
+                    listen_key_renewed_ms: None,
+                };
+                (exchange.to_string(), RwLock::new(state))
+            })
+            .collect();
+        Self { config, sessions }
+    }
+
+    // 未受監督的交易所（如 DEX）視為可用
+    pub fn health(&self, exchange: &str) -> Option<Health> {
+        self.sessions.get(exchange).map(|state| state.read().unwrap().health)
+    }
+
+    pub fn is_available(&self, exchange: &str) -> bool {
+        self.health(exchange).is_none_or(|health| health == Health::Connected)
+    }
+
+    // 記錄訂閱主題，重連後按記錄重放；返回是否需要立即發送（新主題且已連接）
+    pub fn subscribe(&self, exchange: &str, topic: &str) -> bool {
+        let Some(state) = self.sessions.get(exchange) else { return false };
+        let mut state = state.write().unwrap();
+        state.subscriptions.insert(topic.to_string()) && state.health == Health::Connected
+    }
+
+    pub fn snapshot(&self) -> BTreeMap<String, SessionSnapshot> {
+        self.sessions
+            .iter()
+            .map(|(exchange, state)| (exchange.clone(), state.read().unwrap().clone()))
+            .collect()
+    }
+
+    fn update(&self, exchange: &str, apply: impl FnOnce(&mut SessionSnapshot)) {
+        if let Some(state) = self.sessions.get(exchange) {
+            let mut state = state.write().unwrap();
+            if state.health != Health::Closed {
+                apply(&mut state);
+            }
+        }
+    }
+
+    // 第 attempt 次重連前的等待：指數增長到上限，再乘以 1 ± jitter 的隨機因子
+    fn backoff(&self, attempt: u32, rng: &dyn Rng) -> Duration {
+        let base = (self.config.initial_backoff_ms as f64 * 2f64.powi(attempt.min(30) as i32))
+            .min(self.config.max_backoff_ms as f64);
+        let jitter = 1.0 + self.config.jitter * (rng.next_f64() * 2.0 - 1.0);
+        Duration::from_millis((base * jitter).max(0.0) as u64)
+    }
+}
+
+// 關閉時註銷 listen key 並標記會話已關閉，之後監督任務的狀態更新被忽略
+pub async fn close(engine: &ExecutionEngine) {
+    for (exchange, state) in &engine.sessions.sessions {
+        let listen_key = {
+            let mut state = state.write().unwrap();
+            state.health = Health::Closed;
+            state.listen_key.take()
+        };
+        if let (Some(key), Some(connector)) = (listen_key, engine.exchanges.get(exchange)) {
+            if let Err(error) = connector.close_listen_key(&key).await {
+                warn!(%exchange, %error, "註銷 listen key 失敗");
+            }
+        }
+        info!(%exchange, "交易所會話已關閉");
+    }
+}
+
+pub fn spawn(engine: Arc<ExecutionEngine>) {
+    for exchange in engine.sessions.sessions.keys() {
+        let engine = Arc::clone(&engine);
+        let exchange = exchange.clone();
+        tokio::spawn(async move { supervise(&engine, &exchange).await });
+    }
+}
+
+async fn supervise(engine: &ExecutionEngine, exchange: &str) {
+    let supervisor = &engine.sessions;
+    let Some(connector) = engine.exchanges.get(exchange) else { return };
+    let keepalive = connector.settings.listen_key_keepalive_secs.map(Duration::from_secs);
+    let mut attempt = 0;
+    loop {
+        let result = async {
+            // Binance 的私有數據流以 listen key 作連接路徑，先申請再連接
+            let listen_key = match keepalive {
+                Some(_) => Some(connector.create_listen_key().await?),
+                None => None,
+            };
+            connector.open_stream(listen_key.as_deref()).await?;
+            Ok::<_, String>(listen_key)
+        }
+        .await;
+        let listen_key = match result {
+            Ok(listen_key) => listen_key,
+            Err(error) => {
+                attempt += 1;
+                let delay = supervisor.backoff(attempt - 1, connector.env.rng.as_ref());
+                supervisor.update(exchange, |state| {
+                    state.consecutive_failures = attempt;
+                    state.health = if attempt >= supervisor.config.degraded_after_failures {
+                        Health::Degraded
+                    } else {
+                        Health::Reconnecting
+                    };
+                    state.last_error = Some(error.clone());
+                });
+                warn!(%exchange, %error, attempt, delay_ms = delay.as_millis() as u64, "交易所連接失敗，退避後重連");
+                tokio::time::sleep(delay).await;
+                continue;
+            }
+        };
+
+        // 先訂閱用戶數據流再拉取 REST 快照對賬，避免兩者之間的推送丟失
+        let updates = connector.user_events.subscribe();
+        if let Err(error) = user_stream::reconcile(engine, exchange).await {
+            warn!(%exchange, %error, "REST 快照對賬失敗");
+        }
+        // 重放斷線前的訂閱，完成後才恢復執行
+        let topics = supervisor.sessions[exchange].read().unwrap().subscriptions.clone();
+        for topic in &topics {
+            connector.send_subscribe(topic).await;
+        }
+        let reconnected = attempt > 0 || supervisor.sessions[exchange].read().unwrap().connected_since_ms.is_some();
+        attempt = 0;
+        supervisor.update(exchange, |state| {
+            state.health = Health::Connected;
+            state.consecutive_failures = 0;
+            state.connected_since_ms = Some(now_ms());
+            if reconnected {
+                state.reconnects += 1;
+            }
+            state.listen_key_renewed_ms = listen_key.as_ref().map(|_| now_ms());
+            state.listen_key = listen_key.clone();
+        });
+        info!(%exchange, replayed = topics.len(), "交易所連接就緒");
+
+        let error = run(engine, exchange, listen_key, keepalive, updates).await;
+        supervisor.update(exchange, |state| {
+            state.health = Health::Reconnecting;
+            state.last_disconnect_ms = Some(now_ms());
+            state.last_error = Some(error.clone());
+        });
+        warn!(%exchange, %error, "交易所連接斷開，暫停該交易所執行");
+        engine.alert(Severity::Warning, "交易所連接斷開", format!("{}：{}", exchange, error));
+        tokio::time::sleep(supervisor.backoff(0, connector.env.rng.as_ref())).await;
+    }
+}
+
+// 連接期間定期心跳並續期 listen key，返回斷線原因
+async fn run(
+    engine: &ExecutionEngine,
+    exchange: &str,
+    mut listen_key: Option<String>,
+    keepalive: Option<Duration>,
+    mut updates: broadcast::Receiver<UserEvent>,
+) -> String {
+    let supervisor = &engine.sessions;
+    let connector = &engine.exchanges[exchange];
+    let mut heartbeat = tokio::time::interval(Duration::from_secs(supervisor.config.heartbeat_secs));
+    heartbeat.tick().await;
+    let mut renewed = Instant::now();
+    loop {
+        tokio::select! {
+            _ = heartbeat.tick() => {}
+            update = updates.recv() => {
+                match update {
+                    Ok(event) => user_stream::ingest(engine, exchange, event),
+                    // 積壓過多被丟棄的推送無法補回，以 REST 快照修正
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        warn!(%exchange, skipped, "用戶數據流積壓，重新對賬");
+                        if let Err(error) = user_stream::reconcile(engine, exchange).await {
+                            return format!("對賬失敗: {}", error);
+                        }
+                    }
+                    Err(broadcast::error::RecvError::Closed) => return "用戶數據流已關閉".to_string(),
+                }
+                continue;
+            }
+        }
+        if let Err(error) = connector.ping().await {
+            return error;
+        }
+        let (Some(key), Some(interval)) = (listen_key.as_ref(), keepalive) else { continue };
+        if renewed.elapsed() < interval {
+            continue;
+        }
+        // 續期失敗時 listen key 可能已過期，重新申請
+        let result = match connector.keepalive_listen_key(key).await {
+            Ok(()) => Ok(key.clone()),
+            Err(error) => {
+                warn!(%exchange, %error, "listen key 續期失敗，重新申請");
+                connector.create_listen_key().await
+            }
+        };
+        match result {
+            Ok(key) => {
+                renewed = Instant::now();
+                supervisor.update(exchange, |state| {
+                    state.listen_key = Some(key.clone());
+                    state.listen_key_renewed_ms = Some(now_ms());
+                });
+                listen_key = Some(key);
+            }
+            Err(error) => return format!("無法維持用戶數據流: {}", error),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::deterministic_sim::{build, request};
+    use crate::mock_exchange::{MockExchange, Scenario};
+    use crate::mock_exchange_e2e::EXCHANGES;
+    use crate::{config, Environment};
+
+    // 斷線後按退避重連並重放訂閱，listen key 按間隔續期；連續重連失敗降級期間暫停執行
+    #[tokio::test]
+    async fn sessions_reconnect_with_backoff_and_replay_subscriptions() {
+        async fn wait_for(engine: &ExecutionEngine, exchange: &str, done: impl Fn(&SessionSnapshot) -> bool) -> SessionSnapshot {
+            for _ in 0..500 {
+                let snapshot = engine.sessions.snapshot().remove(exchange).unwrap();
+                if done(&snapshot) {
+                    return snapshot;
+                }
+                tokio::time::sleep(Duration::from_millis(20)).await;
+            }
+            panic!("{} 會話狀態未達預期: {:?}", exchange, engine.sessions.snapshot()[exchange]);
+        }
+        let mock = MockExchange::start("127.0.0.1:0", Scenario::default()).await.unwrap();
+        let mut config = config::EngineConfig::default();
+        config.sessions = config::SessionConfig {
+            initial_backoff_ms: 20,
+            max_backoff_ms: 100,
+            jitter: 0.2,
+            heartbeat_secs: 1,
+            degraded_after_failures: 2,
+        };
+        for exchange in EXCHANGES {
+            let settings = config.exchanges.entry(exchange.to_string()).or_default();
+            settings.endpoint = Some(mock.endpoint());
+            settings.listen_key_keepalive_secs = (exchange == "binance").then_some(1);
+        }
+        let (engine, path) = build("mock-sessions", config, Environment::system());
+        let engine = Arc::new(engine);
+        spawn(Arc::clone(&engine));
+        let connected = |snapshot: &SessionSnapshot| snapshot.health == Health::Connected;
+        let first = wait_for(&engine, "binance", connected).await;
+        wait_for(&engine, "bybit", connected).await;
+        assert!(engine.sessions.subscribe("bybit", "orderbook.50.BTCUSDT"));
+        assert!(!engine.sessions.subscribe("bybit", "orderbook.50.BTCUSDT"), "重複訂閱不再發送");
+
+        // listen key 按間隔續期
+        let renewed = wait_for(&engine, "binance", |snapshot| snapshot.listen_key_renewed_ms > first.listen_key_renewed_ms).await;
+        assert_eq!(renewed.listen_key, first.listen_key);
+        assert!(mock.requests().iter().any(|request| request == "PUT /fapi/v1/listenKey"));
+
+        // REST 持續 503 時 binance 申請不到 listen key，連續失敗後降級並暫停執行
+        mock.script(|scenario| scenario.server_errors = u32::MAX);
+        mock.disconnect_streams();
+        let degraded = wait_for(&engine, "binance", |snapshot| snapshot.health == Health::Degraded).await;
+        assert!(degraded.consecutive_failures >= 2 && degraded.last_error.is_some());
+        let response = engine.execute_funding_rate_arbitrage(request(10.0)).await;
+        assert!(response.error_message.unwrap().contains("binance 連接未就緒"));
+
+        // 恢復後重連，重放斷線前的訂閱並換用新的 listen key
+        mock.script(|scenario| scenario.server_errors = 0);
+        let recovered = wait_for(&engine, "binance", |snapshot| connected(snapshot) && snapshot.reconnects >= 1).await;
+        assert_eq!(recovered.consecutive_failures, 0);
+        assert_ne!(recovered.listen_key, first.listen_key);
+        let bybit = wait_for(&engine, "bybit", |snapshot| connected(snapshot) && snapshot.reconnects >= 1).await;
+        assert!(bybit.subscriptions.contains("orderbook.50.BTCUSDT"));
+
+        close(&engine).await;
+        assert_eq!(engine.sessions.health("binance"), Some(Health::Closed));
+        let _ = std::fs::remove_file(path);
+    }
+}
@@ -0,0 +1,427 @@
+use super::protocol::wire;
+use super::{config::SharedMemoryConfig, server, ExecutionEngine};
+use memmap2::MmapMut;
+use serde::Serialize;
+use serde_json::Value;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{mpsc, Arc};
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+
+const MAGIC: [u8; 8] = *b"ARBRING1";
+const VERSION: u32 = 1;
+const HEADER_SIZE: usize = 320;
+const RECORD_HEADER: usize = 16;
+
+#[derive(Clone, Copy)]
+enum Ring {
+    Request,
+    Response,
+}
+
+// 映射區域；序號只經原子類型訪問，槽位只由當前持有該槽位的一方讀寫
+struct Region {
+    // 保持映射存活，訪問經 base 指針
+    _map: MmapMut,
+    base: *mut u8,
+    slots: u64,
+    slot_size: usize,
+}
+
+// 映射在 Region 存活期間有效，並發訪問的約束見 Client 的佈局說明
+unsafe impl Send for Region {}
+unsafe impl Sync for Region {}
+
+impl Region {
+    fn new(mut map: MmapMut) -> Result<Self, String> {
+        if map.len() < HEADER_SIZE || map[..8] != MAGIC {
+            return Err("不是共享內存傳輸文件".to_string());
+        }
+        let field = |offset: usize| u32::from_le_bytes(map[offset..offset + 4].try_into().unwrap());
+        if field(8) != VERSION {
+            return Err(format!("共享內存傳輸版本不兼容: {}", field(8)));
+        }
+        let (slots, slot_size) = (field(12) as usize, field(16) as usize);
+        if slots == 0 || slot_size <= RECORD_HEADER || map.len() < HEADER_SIZE + 2 * slots * slot_size {
+            return Err("共享內存傳輸文件大小與槽位配置不符".to_string());
+        }
+        Ok(Self {
+            base: map.as_mut_ptr(),
+            _map: map,
+            slots: slots as u64,
+            slot_size,
+        })
+    }
+
+    fn counter(&self, offset: usize) -> &AtomicU64 {
+        // 偏移均為 64 字節對齊，映射起點按頁對齊
+        unsafe { &*(self.base.add(offset) as *const AtomicU64) }
+    }
+
+    fn tail(&self, ring: Ring) -> &AtomicU64 {
+        self.counter(match ring {
+            Ring::Request => 64,
+            Ring::Response => 192,
+        })
+    }
+
+    fn head(&self, ring: Ring) -> &AtomicU64 {
+        self.counter(match ring {
+            Ring::Request => 128,
+            Ring::Response => 256,
+        })
+    }
+
+    fn slot(&self, ring: Ring, sequence: u64) -> *mut u8 {
+        let index = (sequence % self.slots) as usize
+            + match ring {
+                Ring::Request => 0,
+                Ring::Response => self.slots as usize,
+            };
+        unsafe { self.base.add(HEADER_SIZE + index * self.slot_size) }
+    }
+
+    fn capacity(&self) -> usize {
+        self.slot_size - RECORD_HEADER
+    }
+
+    // 生產者寫入一條記錄；環已滿時返回 false。每個環只能有一個生產者
+    fn push(&self, ring: Ring, id: u64, payload: &[u8]) -> Result<bool, String> {
+        if payload.len() > self.capacity() {
+            return Err(format!("消息 {} 字節，超過槽位容量 {} 字節", payload.len(), self.capacity()));
+        }
+        let tail = self.tail(ring).load(Ordering::Relaxed);
+        let head = self.head(ring).load(Ordering::Acquire);
+        // 讀取序號由對端推進，超過寫入序號說明對端寫壞了序號
+        let unread = tail.checked_sub(head).ok_or_else(|| format!("共享內存序號異常: 讀取序號 {} 超過寫入序號 {}", head, tail))?;
+        if unread >= self.slots {
+            return Ok(false);
+        }
+        let slot = self.slot(ring, tail);
+        unsafe {
+            std::ptr::copy_nonoverlapping(id.to_le_bytes().as_ptr(), slot, 8);
+            std::ptr::copy_nonoverlapping((payload.len() as u32).to_le_bytes().as_ptr(), slot.add(8), 4);
+            std::ptr::copy_nonoverlapping(payload.as_ptr(), slot.add(RECORD_HEADER), payload.len());
+        }
+        self.tail(ring).store(tail + 1, Ordering::Release);
+        Ok(true)
+    }
+
+    // 消費者取出一條記錄，負載複製到 payload；環為空時返回 None。每個環只能有一個消費者
+    fn pop(&self, ring: Ring, payload: &mut Vec<u8>) -> Option<u64> {
+        let head = self.head(ring).load(Ordering::Relaxed);
+        if head == self.tail(ring).load(Ordering::Acquire) {
+            return None;
+        }
+        let slot = self.slot(ring, head);
+        let mut id = [0; 8];
+        let mut len = [0; 4];
+        unsafe {
+            std::ptr::copy_nonoverlapping(slot, id.as_mut_ptr(), 8);
+            std::ptr::copy_nonoverlapping(slot.add(8), len.as_mut_ptr(), 4);
+            // 長度來自對端，不超過槽位容量
+            let len = (u32::from_le_bytes(len) as usize).min(self.capacity());
+            payload.clear();
+            payload.extend_from_slice(std::slice::from_raw_parts(slot.add(RECORD_HEADER), len));
+        }
+        self.head(ring).store(head + 1, Ordering::Release);
+        Some(u64::from_le_bytes(id))
+    }
+}
+
+fn map(path: &str, create: Option<&SharedMemoryConfig>) -> Result<MmapMut, String> {
+    let mut options = std::fs::OpenOptions::new();
+    options.read(true).write(true);
+    if create.is_some() {
+        options.create(true).truncate(true);
+    }
+    let file = options.open(path).map_err(|e| format!("打開 {} 失敗: {}", path, e))?;
+    if let Some(config) = create {
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            file.set_permissions(std::fs::Permissions::from_mode(config.permissions()?))
+                .map_err(|e| format!("設置 {} 的權限失敗: {}", path, e))?;
+        }
+        let len = HEADER_SIZE + 2 * config.slots as usize * config.slot_size as usize;
+        file.set_len(len as u64).map_err(|e| format!("設置 {} 的大小失敗: {}", path, e))?;
+    }
+    // 映射文件由引擎建立，權限限制為策略進程可訪問
+    unsafe { MmapMut::map_mut(&file) }.map_err(|e| format!("映射 {} 失敗: {}", path, e))
+}
+
+// 建立（覆蓋）映射文件並寫入文件頭
+fn create(config: &SharedMemoryConfig) -> Result<Region, String> {
+    let mut map = map(&config.path, Some(config))?;
+    map[..8].copy_from_slice(&MAGIC);
+    map[8..12].copy_from_slice(&VERSION.to_le_bytes());
+    map[12..16].copy_from_slice(&config.slots.to_le_bytes());
+    map[16..20].copy_from_slice(&config.slot_size.to_le_bytes());
+    Region::new(map)
+}
+
+/// 引擎端的共享內存傳輸：輪詢線程收取請求並派發到當前運行時處理，響應寫回響應環。
+/// 釋放時停止輪詢並刪除映射文件。
+pub struct Server {
+    stop: Arc<AtomicBool>,
+    path: String,
+}
+
+impl Server {
+    // 建立（覆蓋）映射文件並啟動輪詢線程；需在 tokio 運行時中調用，請求在該運行時上處理
+    pub(crate) fn start(engine: Arc<ExecutionEngine>, config: &SharedMemoryConfig) -> Result<Self, String> {
+        let region = Arc::new(create(config)?);
+        let stop = Arc::new(AtomicBool::new(false));
+        let runtime = tokio::runtime::Handle::current();
+        let (spin_iterations, idle_sleep) = (config.spin_iterations, Duration::from_micros(config.idle_sleep_us));
+        let (responses, pending) = mpsc::channel();
+        let (responder_region, responder_stop) = (Arc::clone(&region), Arc::clone(&stop));
+        std::thread::Builder::new()
+            .name("arb-shm-responder".to_string())
+            .spawn(move || respond(&responder_region, &responder_stop, pending, spin_iterations, idle_sleep))
+            .map_err(|e| format!("啟動共享內存響應線程失敗: {}", e))?;
+        let poller_stop = Arc::clone(&stop);
+        std::thread::Builder::new()
+            .name("arb-shm-poller".to_string())
+            .spawn(move || {
+                let mut payload = Vec::new();
+                let mut idle = 0u32;
+                while !poller_stop.load(Ordering::Relaxed) {
+                    let Some(id) = region.pop(Ring::Request, &mut payload) else {
+                        idle = idle.saturating_add(1);
+                        if idle < spin_iterations {
+                            std::hint::spin_loop();
+                        } else {
+                            std::thread::sleep(idle_sleep);
+                        }
+                        continue;
+                    };
+                    idle = 0;
+                    let (engine, responses) = (Arc::clone(&engine), responses.clone());
+                    let request = std::mem::take(&mut payload);
+                    runtime.spawn(async move {
+                        let mut output = Vec::new();
+                        server::dispatch(&engine, wire::Encoding::Msgpack, &request, &mut output).await;
+                        // pong 等不返回響應的消息
+                        if output.is_empty() {
+                            return;
+                        }
+                        output.drain(..wire::HEADER_LEN);
+                        // 響應線程只在服務停止後退出，此後的響應直接丟棄
+                        let _ = responses.send((id, output));
+                    });
+                }
+            })
+            .map_err(|e| format!("啟動共享內存輪詢線程失敗: {}", e))?;
+        info!(path = %config.path, slots = config.slots, slot_size = config.slot_size, "共享內存傳輸已啟動");
+        Ok(Self {
+            stop,
+            path: config.path.clone(),
+        })
+    }
+}
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+// 響應環的唯一生產者：處理任務經通道交付響應，環已滿時在此線程等待客戶端取走，不佔用運行時的工作線程。
+// 響應超過槽位容量時改為返回錯誤；輪詢線程和處理任務都結束後退出
+fn respond(region: &Region, stop: &AtomicBool, pending: mpsc::Receiver<(u64, Vec<u8>)>, spin_iterations: u32, idle_sleep: Duration) {
+    for (id, mut payload) in pending {
+        if payload.len() > region.capacity() {
+            warn!(id, len = payload.len(), "響應超過共享內存槽位容量");
+            let error = serde_json::json!({ "status": "error", "error": format!("響應 {} 字節，超過槽位容量", payload.len()) });
+            payload = wire::encode(wire::Encoding::Msgpack, &error);
+            payload.drain(..wire::HEADER_LEN);
+        }
+        let mut idle = 0u32;
+        while !stop.load(Ordering::Relaxed) {
+            match region.push(Ring::Response, id, &payload) {
+                Ok(true) => break,
+                Ok(false) => {
+                    idle = idle.saturating_add(1);
+                    if idle < spin_iterations {
+                        std::hint::spin_loop();
+                    } else {
+                        std::thread::sleep(idle_sleep);
+                    }
+                }
+                Err(e) => {
+                    warn!(id, error = %e, "寫入共享內存響應失敗");
+                    break;
+                }
+            }
+        }
+    }
+}
+
+/// 共享內存傳輸的客戶端：映射引擎建立的文件，提交請求並輪詢響應，提交與收取都不經系統調用。
+/// 每個引擎同時只能有一個客戶端；序號保存在共享內存中，客戶端重新打開後從當前位置繼續。
+///
+/// 文件佈局（整數均為小端）：
+///
+/// | 偏移 | 內容 |
+/// |---|---|
+/// | 0 | 魔數 `ARBRING1` |
+/// | 8 | 版本 u32（1）、槽位數 u32、槽位大小 u32 |
+/// | 64 / 128 | 請求環的寫入序號（客戶端遞增）/ 讀取序號（引擎遞增），u64 |
+/// | 192 / 256 | 響應環的寫入序號（引擎遞增）/ 讀取序號（客戶端遞增），u64 |
+/// | 320 | 請求環槽位，其後為響應環槽位，各 `slots` 個 |
+///
+/// 序號單調遞增，第 n 條記錄位於 `n % slots` 號槽位；寫入序號減讀取序號即環中未讀的記錄數。生產者寫完槽位後
+/// 以 release 語義推進寫入序號，消費者以 acquire 語義讀取寫入序號後讀槽位，讀完再推進讀取序號。
+/// 每個槽位是一條記錄：請求 ID u64（響應沿用請求的 ID）、負載長度 u32、保留 u32，之後為 MessagePack 編碼的
+/// 消息（與 msgpack 線路編碼相同，不含幀頭）。
+pub struct Client {
+    region: Region,
+    payload: Vec<u8>,
+    encoded: Vec<u8>,
+}
+
+impl Client {
+    pub fn open(path: &str) -> Result<Self, String> {
+        Ok(Self {
+            region: Region::new(map(path, None)?)?,
+            payload: Vec::new(),
+            encoded: Vec::new(),
+        })
+    }
+
+    // 以 MessagePack 編碼寫入請求環；環已滿時返回錯誤
+    pub fn submit<T: Serialize>(&mut self, id: u64, message: &T) -> Result<(), String> {
+        wire::encode_into(wire::Encoding::Msgpack, message, &mut self.encoded);
+        match self.region.push(Ring::Request, id, &self.encoded[wire::HEADER_LEN..])? {
+            true => Ok(()),
+            false => Err("請求環已滿".to_string()),
+        }
+    }
+
+    pub fn try_recv(&mut self) -> Result<Option<(u64, Value)>, String> {
+        let Some(id) = self.region.pop(Ring::Response, &mut self.payload) else { return Ok(None) };
+        Ok(Some((id, wire::decode(wire::Encoding::Msgpack, &self.payload)?)))
+    }
+
+    // 自旋等待下一條響應
+    pub fn recv(&mut self, timeout: Duration) -> Result<(u64, Value), String> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            if let Some(response) = self.try_recv()? {
+                return Ok(response);
+            }
+            if Instant::now() >= deadline {
+                return Err("等待響應超時".to_string());
+            }
+            std::hint::spin_loop();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::deterministic_sim::build;
+    use crate::{config, Environment};
+
+    fn ring_config(name: &str, slots: u32) -> SharedMemoryConfig {
+        let path = std::env::temp_dir().join(format!("arb-engine-{}-{}.ring", name, std::process::id()));
+        SharedMemoryConfig {
+            enabled: true,
+            path: path.to_string_lossy().into_owned(),
+            slots,
+            slot_size: 1024,
+            ..Default::default()
+        }
+    }
+
+    // 響應環已滿時響應在響應線程等待，單線程運行時仍可調度其他任務
+    #[tokio::test]
+    async fn full_response_ring_does_not_block_the_runtime() {
+        let (engine, path) = build("shm-full-ring", config::EngineConfig::default(), Environment::system());
+        let config = ring_config("full", 2);
+        let server = Server::start(Arc::new(engine), &config).unwrap();
+        let mut client = Client::open(&config.path).unwrap();
+        for id in 0..4u64 {
+            client.submit(id, &serde_json::json!({ "command": "get_latency" })).unwrap();
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        // 兩條響應佔滿響應環，其餘兩條等待客戶端取走
+        assert_eq!(client.region.tail(Ring::Response).load(Ordering::Acquire), 2);
+        let mut ids = Vec::new();
+        let deadline = Instant::now() + Duration::from_secs(2);
+        while ids.len() < 4 {
+            match client.try_recv().unwrap() {
+                Some((id, response)) => {
+                    assert_eq!(response["status"], "success", "{}", response);
+                    ids.push(id);
+                }
+                None => {
+                    assert!(Instant::now() < deadline, "等待響應超時");
+                    tokio::time::sleep(Duration::from_millis(1)).await;
+                }
+            }
+        }
+        ids.sort_unstable();
+        assert_eq!(ids, vec![0, 1, 2, 3]);
+        drop(server);
+        let _ = std::fs::remove_file(path);
+    }
+
+    // 對端寫壞讀取序號時寫入返回錯誤，不因序號相減下溢而誤判環的狀態
+    #[test]
+    fn read_sequence_past_write_sequence_is_a_protocol_error() {
+        let config = ring_config("corrupt", 2);
+        let region = create(&config).unwrap();
+        region.head(Ring::Response).store(5, Ordering::Release);
+        let error = region.push(Ring::Response, 1, b"x").unwrap_err();
+        assert!(error.contains("讀取序號 5 超過寫入序號 0"), "{}", error);
+        let _ = std::fs::remove_file(&config.path);
+    }
+
+    #[tokio::test]
+    async fn shared_memory_ring_round_trips_requests() {
+        let (engine, path) = build("shared-memory", config::EngineConfig::default(), Environment::system());
+        let ring_path = std::env::temp_dir().join(format!("arb-engine-{}.ring", std::process::id()));
+        let config = config::SharedMemoryConfig {
+            enabled: true,
+            path: ring_path.to_string_lossy().into_owned(),
+            slots: 4,
+            slot_size: 1024,
+            ..Default::default()
+        };
+        let server = Server::start(std::sync::Arc::new(engine), &config).unwrap();
+        let mut client = Client::open(&config.path).unwrap();
+
+        // 超過槽位數的請求在環繞後仍按 ID 一一返回
+        let mut responses = std::collections::BTreeMap::new();
+        for id in 0..6u64 {
+            let message = match id {
+                3 => serde_json::json!({ "command": "no_such_command" }),
+                _ => serde_json::json!({ "command": "get_latency" }),
+            };
+            client.submit(id, &message).unwrap();
+            let deadline = std::time::Instant::now() + std::time::Duration::from_secs(2);
+            while responses.len() <= id as usize {
+                match client.try_recv().unwrap() {
+                    Some((id, response)) => {
+                        responses.insert(id, response);
+                    }
+                    None => {
+                        assert!(std::time::Instant::now() < deadline, "等待響應超時");
+                        tokio::time::sleep(std::time::Duration::from_millis(1)).await;
+                    }
+                }
+            }
+        }
+        assert_eq!(responses.keys().copied().collect::<Vec<_>>(), (0..6).collect::<Vec<_>>());
+        assert!(responses.iter().all(|(id, response)| (response["status"] == "error") == (*id == 3)), "{:?}", responses);
+        // 超過槽位容量的請求在客戶端即被拒絕
+        assert!(client.submit(9, &"x".repeat(2048)).unwrap_err().contains("超過槽位容量"));
+        drop(server);
+        assert!(!ring_path.exists());
+        let _ = std::fs::remove_file(path);
+    }
+}
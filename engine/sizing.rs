@@ -0,0 +1,312 @@
+use super::config::SizingConfig;
+use super::market_data::{self, OrderBook};
+use super::protocol::{ArbitrageRequest, StrategyType};
+use super::ExecutionEngine;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+use tracing::info;
+
+const DAY_MS: f64 = 86_400_000.0;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SymbolLimit {
+    pub max_notional: f64,
+    // 連續優於模型的成交筆數
+    pub better_streak: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "mode", rename_all = "snake_case")]
+pub enum SizingMode {
+    // 按風險預算（缺省為 sizing.volatility.risk_budget）與近期實現波動率計算金額
+    Volatility {
+        #[serde(default)]
+        risk_budget: Option<f64>,
+    },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SizingDecision {
+    pub requested_amount: f64,
+    pub applied_amount: f64,
+    pub risk_budget: f64,
+    pub daily_volatility: f64,
+    // realized 為近期價格樣本的實現波動率，configured 為樣本不足時的 risk.daily_volatility
+    pub volatility_source: String,
+    pub samples: usize,
+    // 風險預算與訂單簿深度各自允許的金額
+    pub risk_notional: f64,
+    pub depth_notional: f64,
+    pub reason: String,
+}
+
+pub struct NotionalCalibrator {
+    config: SizingConfig,
+    limits: Mutex<HashMap<String, SymbolLimit>>,
+    // 交易對 -> 按時間排列的 (採樣時間, 價格)，用於實現波動率
+    prices: Mutex<HashMap<String, VecDeque<(i64, f64)>>>,
+}
+
+impl NotionalCalibrator {
+    pub fn new(config: SizingConfig) -> Self {
+        Self {
+            config,
+            limits: Mutex::new(HashMap::new()),
+            prices: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn initial(&self) -> SymbolLimit {
+        SymbolLimit {
+            max_notional: self
+                .config
+                .initial_notional
+                .clamp(self.config.min_notional, self.config.max_notional),
+            better_streak: 0,
+        }
+    }
+
+    pub fn max_notional(&self, symbol: &str) -> f64 {
+        self.limits
+            .lock()
+            .unwrap()
+            .get(symbol)
+            .map(|l| l.max_notional)
+            .unwrap_or_else(|| self.initial().max_notional)
+    }
+
+    pub fn expected_slippage_bps(&self, notional: f64) -> f64 {
+        self.config.slippage_model_bps * (notional / self.config.model_reference_notional).sqrt()
+    }
+
+    // 滑點超出模型則乘性收縮；持續優於模型則緩慢放大
+    pub fn observe(&self, symbol: &str, expected_bps: f64, realized_bps: f64) {
+        let mut limits = self.limits.lock().unwrap();
+        let limit = limits.entry(symbol.to_string()).or_insert_with(|| self.initial());
+        let before = limit.max_notional;
+
+        if realized_bps > expected_bps * (1.0 + self.config.shrink_tolerance) {
+            limit.max_notional = (limit.max_notional * self.config.shrink_factor).max(self.config.min_notional);
+            limit.better_streak = 0;
+        } else if realized_bps < expected_bps {
+            limit.better_streak += 1;
+            if limit.better_streak >= self.config.grow_after_fills {
+                limit.max_notional = (limit.max_notional * self.config.growth_factor).min(self.config.max_notional);
+                limit.better_streak = 0;
+            }
+        } else {
+            limit.better_streak = 0;
+        }
+
+        if limit.max_notional != before {
+            info!(
+                %symbol,
+                expected_bps,
+                realized_bps,
+                from = before,
+                to = limit.max_notional,
+                "最大名義金額已重新校準"
+            );
+        }
+    }
+
+    pub fn snapshot(&self) -> HashMap<String, SymbolLimit> {
+        self.limits.lock().unwrap().clone()
+    }
+
+    pub fn record_price(&self, symbol: &str, sampled_at_ms: i64, price: f64) {
+        let mut prices = self.prices.lock().unwrap();
+        let window = prices.entry(symbol.to_string()).or_default();
+        if window.len() >= self.config.volatility.lookback {
+            window.pop_front();
+        }
+        window.push_back((sampled_at_ms, price));
+    }
+
+    // 對數收益率的標準差按平均採樣間隔換算為日波動率，並返回樣本數；樣本不足時為 None
+    pub fn realized_volatility(&self, symbol: &str) -> Option<(f64, usize)> {
+        let prices = self.prices.lock().unwrap();
+        let window = prices.get(symbol)?;
+        if window.len() < self.config.volatility.min_samples {
+            return None;
+        }
+        let returns: Vec<f64> = window.iter().zip(window.iter().skip(1)).map(|((_, a), (_, b))| (b / a).ln()).collect();
+        let (first, last) = (window.front()?.0, window.back()?.0);
+        let interval_ms = (last - first) as f64 / returns.len() as f64;
+        if interval_ms <= 0.0 {
+            return None;
+        }
+        let n = returns.len() as f64;
+        let mean = returns.iter().sum::<f64>() / n;
+        let variance = returns.iter().map(|r| (r - mean).powi(2)).sum::<f64>() / n;
+        Some(((variance * DAY_MS / interval_ms).sqrt(), window.len()))
+    }
+}
+
+// 訂單簿前若干檔中買賣兩側較淺一側的名義金額
+fn depth_notional(book: &OrderBook) -> f64 {
+    let side = |levels: &[market_data::Level]| levels.iter().map(|level| level.price * level.quantity).sum::<f64>();
+    side(&book.bids).min(side(&book.asks))
+}
+
+/// 按請求的定倉方式計算下單金額：風險預算除以日波動率（樣本不足時用配置值）與持有期，
+/// 再以兩側交易所中較淺訂單簿深度的 max_depth_fraction 封頂，不超過請求的 amount。
+pub(crate) fn size(engine: &ExecutionEngine, request: &ArbitrageRequest, mode: &SizingMode) -> Result<SizingDecision, String> {
+    let config = &engine.sizing.config.volatility;
+    let SizingMode::Volatility { risk_budget } = mode;
+    let risk_budget = risk_budget.unwrap_or(config.risk_budget);
+    if risk_budget.is_nan() || risk_budget <= 0.0 {
+        return Err("risk_budget 必須大於 0".to_string());
+    }
+    let (base, quote) = market_data::split_symbol(&request.symbol).ok_or_else(|| format!("無法解析交易對: {}", request.symbol))?;
+    let (daily_volatility, volatility_source, samples) = match engine.sizing.realized_volatility(&request.symbol) {
+        Some((volatility, samples)) => (volatility, "realized", samples),
+        None => (engine.risk.daily_volatility(&base), "configured", 0),
+    };
+    // 價格樣本完全不變時波動率為 0，風險預算不構成限制
+    let risk_notional = if daily_volatility > 0.0 {
+        risk_budget / (daily_volatility * config.horizon_days.sqrt())
+    } else {
+        request.amount
+    };
+
+    let mut depth: Option<f64> = None;
+    for venue in [&request.primary_exchange, &request.secondary_exchange].into_iter().filter(|venue| !venue.is_empty()) {
+        let rng = engine.env.rng.as_ref();
+        let book = if request.strategy_type == StrategyType::FundingRate {
+            market_data::simulated_perp_book(rng, venue, &base, &quote)?
+        } else {
+            market_data::simulated_spot_book(rng, venue, &base, &quote)?
+        };
+        depth = Some(depth.map_or(depth_notional(&book), |depth| depth.min(depth_notional(&book))));
+    }
+    let depth_notional = depth.map_or(request.amount, |depth| depth * config.max_depth_fraction);
+
+    let applied_amount = request.amount.min(risk_notional).min(depth_notional);
+    let reason = if applied_amount == request.amount {
+        format!("請求金額 {:.2} 在風險預算與深度允許範圍內", request.amount)
+    } else if applied_amount == risk_notional {
+        format!(
+            "風險預算 {:.2} / 日波動率 {:.2}%（{}）限制為 {:.2}",
+            risk_budget,
+            daily_volatility * 100.0,
+            volatility_source,
+            risk_notional
+        )
+    } else {
+        format!("訂單簿深度的 {:.0}% 限制為 {:.2}", config.max_depth_fraction * 100.0, depth_notional)
+    };
+    if applied_amount < engine.sizing.config.min_notional {
+        return Err(format!("定倉金額 {:.2} 低於最小名義金額 {:.2}：{}", applied_amount, engine.sizing.config.min_notional, reason));
+    }
+    Ok(SizingDecision {
+        requested_amount: request.amount,
+        applied_amount,
+        risk_budget,
+        daily_volatility,
+        volatility_source: volatility_source.to_string(),
+        samples,
+        risk_notional,
+        depth_notional,
+        reason,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bench::START_MS;
+    use crate::deterministic_sim::{build, connect_sessions, funded_config, request};
+    use crate::{config, events, EngineCommand, Environment};
+    use std::sync::Arc;
+
+    // 滑點超出模型時收縮單交易對的名義上限，連續優於模型後放大，均不越過硬性上下限；請求金額按上限縮減
+    #[tokio::test]
+    async fn max_notional_calibrates_from_fill_slippage_and_caps_requests() {
+        let mut config = funded_config();
+        config.sizing = config::SizingConfig {
+            min_notional: 1_000.0,
+            max_notional: 8_000.0,
+            initial_notional: 4_000.0,
+            shrink_factor: 0.5,
+            growth_factor: 2.0,
+            grow_after_fills: 2,
+            ..config::SizingConfig::default()
+        };
+        let (engine, path) = build("calibration", config, Environment::simulated(START_MS, 1));
+        let sizing = &engine.sizing;
+        assert_eq!(sizing.max_notional("BTCUSDT"), 4_000.0);
+        sizing.observe("BTCUSDT", 2.0, 3.0);
+        assert_eq!(sizing.max_notional("BTCUSDT"), 2_000.0);
+        // 容忍範圍內的滑點不收縮，且中斷優於模型的連續計數
+        sizing.observe("BTCUSDT", 2.0, 1.0);
+        sizing.observe("BTCUSDT", 2.0, 2.3);
+        sizing.observe("BTCUSDT", 2.0, 1.0);
+        assert_eq!(sizing.max_notional("BTCUSDT"), 2_000.0);
+        sizing.observe("BTCUSDT", 2.0, 1.0);
+        assert_eq!(sizing.max_notional("BTCUSDT"), 4_000.0);
+        for _ in 0..4 {
+            sizing.observe("BTCUSDT", 2.0, 1.0);
+        }
+        assert_eq!(sizing.max_notional("BTCUSDT"), 8_000.0);
+        for _ in 0..5 {
+            sizing.observe("BTCUSDT", 2.0, 10.0);
+        }
+        assert_eq!(sizing.max_notional("BTCUSDT"), 1_000.0);
+        assert_eq!(sizing.max_notional("ETHUSDT"), 4_000.0);
+        let limits = engine.handle_command(EngineCommand::GetSizingLimits).await.data.unwrap();
+        assert_eq!(limits["BTCUSDT"]["max_notional"], 1_000.0);
+
+        let engine = Arc::new(engine);
+        connect_sessions(&engine).await;
+        engine.quotes.record_predicted_rate("binance", "BTCUSDT", 0.0005, START_MS);
+        engine.quotes.record_predicted_rate("bybit", "BTCUSDT", 0.0001, START_MS);
+        let response = engine.execute_funding_rate_arbitrage(request(5_000.0)).await;
+        assert_eq!(response.status, "success", "{:?}", response.error_message);
+        let placed: Vec<f64> = engine
+            .events
+            .read(0, 100)
+            .into_iter()
+            .filter_map(|envelope| match envelope.event {
+                events::EngineEvent::OrderPlaced { execution_id, quantity, .. } if Some(&execution_id) == response.execution_id.as_ref() => Some(quantity),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(placed, [1_000.0, 1_000.0]);
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[tokio::test]
+    async fn volatility_sizing_caps_amount_by_risk_budget_and_depth() {
+        let (engine, path) = build("volatility_sizing", config::EngineConfig::default(), Environment::simulated(START_MS, 1));
+        let mode = SizingMode::Volatility { risk_budget: Some(1_000.0) };
+
+        // 樣本不足時使用配置的 BTC 日波動率 3.5%：1000 / 0.035 ≈ 28571
+        let decision = size(&engine, &request(100_000.0), &mode).unwrap();
+        assert_eq!(decision.volatility_source, "configured");
+        assert!((decision.applied_amount - 1_000.0 / 0.035).abs() < 1e-6, "{:?}", decision);
+
+        // 每分鐘 ±0.1% 交替波動，日波動率約 0.1% × √1440 ≈ 3.8%
+        for i in 0..60 {
+            let price = if i % 2 == 0 { 60_000.0 } else { 60_060.0 };
+            engine.sizing.record_price("BTCUSDT", START_MS + i * 60_000, price);
+        }
+        let decision = size(&engine, &request(100_000.0), &mode).unwrap();
+        assert_eq!(decision.volatility_source, "realized");
+        assert_eq!(decision.samples, 60);
+        assert!((decision.daily_volatility - 0.038).abs() < 0.001, "{:?}", decision);
+        assert_eq!(decision.applied_amount, decision.risk_notional);
+
+        // 預算寬鬆時受深度限制，不超過請求金額
+        let loose = SizingMode::Volatility { risk_budget: Some(1_000_000.0) };
+        let decision = size(&engine, &request(100_000.0), &loose).unwrap();
+        assert_eq!(decision.applied_amount, decision.depth_notional);
+        assert!(decision.applied_amount < 100_000.0);
+        assert_eq!(size(&engine, &request(500.0), &loose).unwrap().applied_amount, 500.0);
+
+        let tiny = SizingMode::Volatility { risk_budget: Some(1.0) };
+        assert!(size(&engine, &request(100_000.0), &tiny).unwrap_err().contains("低於最小名義金額"));
+        let _ = std::fs::remove_file(path);
+    }
+}
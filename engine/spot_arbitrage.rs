@@ -0,0 +1,463 @@
+use super::chain::ChainStack;
+use super::config::{self, SpotArbitrageConfig};
+use super::dex::DexExecutor;
+use super::market_data::{self, OrderBook};
+use super::order_router::{self, Side, Venue};
+use super::{ArbitrageRequest, ChildOrder, ExecutionOutcome, ExecutionEngine};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Mutex;
+use tracing::{debug, info, warn};
+
+pub struct SpotArbitrage {
+    config: SpotArbitrageConfig,
+    // (exchange, asset) -> 可用數量
+    inventory: Mutex<HashMap<(String, String), f64>>,
+}
+
+impl SpotArbitrage {
+    pub fn new(config: SpotArbitrageConfig) -> Self {
+        let inventory = config
+            .inventory
+            .iter()
+            .flat_map(|(exchange, assets)| {
+                assets
+                    .iter()
+                    .map(move |(asset, amount)| ((exchange.clone(), asset.to_uppercase()), *amount))
+            })
+            .collect();
+        Self {
+            config,
+            inventory: Mutex::new(inventory),
+        }
+    }
+
+    pub fn inventory(&self) -> BTreeMap<String, f64> {
+        self.inventory
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|((exchange, asset), amount)| (format!("{}:{}", exchange, asset), *amount))
+            .collect()
+    }
+
+    fn available(&self, exchange: &str, asset: &str) -> f64 {
+        self.inventory
+            .lock()
+            .unwrap()
+            .get(&(exchange.to_string(), asset.to_string()))
+            .copied()
+            .unwrap_or(0.0)
+    }
+}
+
+// 任一方為已啟用的 DEX 時走 CEX–DEX 路徑，否則兩邊都是交易所
+pub async fn execute(engine: &ExecutionEngine, execution_id: &str, request: &ArbitrageRequest) -> Result<ExecutionOutcome, String> {
+    let chain = engine.chain(request.chain.as_deref())?;
+    let dex = chain.dex.as_ref();
+    let is_dex = |venue: &str| dex.is_some_and(|dex| dex.has_venue(venue));
+    for venue in [&request.primary_exchange, &request.secondary_exchange] {
+        if !engine.exchanges.contains_key(venue) && !is_dex(venue) {
+            return Err(format!("不支持的交易所: {}", venue));
+        }
+    }
+    if request.primary_exchange == request.secondary_exchange {
+        return Err("現貨套利需要兩個不同的交易所".to_string());
+    }
+    match (dex, is_dex(&request.primary_exchange), is_dex(&request.secondary_exchange)) {
+        (Some(_), true, true) => Err("暫不支持兩個 DEX 之間的套利".to_string()),
+        (Some(dex), true, false) => {
+            execute_with_dex(engine, execution_id, request, chain, dex, &request.primary_exchange, &request.secondary_exchange).await
+        }
+        (Some(dex), false, true) => {
+            execute_with_dex(engine, execution_id, request, chain, dex, &request.secondary_exchange, &request.primary_exchange).await
+        }
+        _ => execute_on_exchanges(engine, request).await,
+    }
+}
+
+// 買方交易所用報價資產買入，賣方交易所賣出預先持有的基礎資產；之後需要把基礎資產轉回平衡庫存。
+// 任一腿數量超過指定交易所最優檔深度時由智能訂單路由拆到其他交易所，兩腿的子訂單並行執行
+async fn execute_on_exchanges(engine: &ExecutionEngine, request: &ArbitrageRequest) -> Result<ExecutionOutcome, String> {
+    let (base, quote) =
+        market_data::split_symbol(&request.symbol).ok_or_else(|| format!("無法解析交易對: {}", request.symbol))?;
+    let primary_book = market_data::simulated_spot_book(engine.env.rng.as_ref(), &request.primary_exchange, &base, &quote)?;
+    let secondary_book = market_data::simulated_spot_book(engine.env.rng.as_ref(), &request.secondary_exchange, &base, &quote)?;
+
+    let (buy_exchange, buy_book, sell_exchange, sell_book) = if primary_book.asks[0].price <= secondary_book.asks[0].price
+    {
+        (&request.primary_exchange, primary_book, &request.secondary_exchange, secondary_book)
+    } else {
+        (&request.secondary_exchange, secondary_book, &request.primary_exchange, primary_book)
+    };
+
+    let spot = &engine.spot_arbitrage;
+    // 規模受兩邊庫存約束：買方的報價資產、賣方的基礎資產
+    let quote_available = spot.available(buy_exchange, &quote);
+    let base_available = spot.available(sell_exchange, &base);
+    if request.amount.min(quote_available).min(base_available * sell_book.bids[0].price) <= 0.0 {
+        return Err(format!(
+            "庫存不足：{} 上 {} 可用 {:.4}，{} 上 {} 可用 {:.6}",
+            buy_exchange, quote, quote_available, sell_exchange, base, base_available
+        ));
+    }
+
+    // 買入腿：以請求金額折算數量，先只考慮指定交易所，超過其最優檔深度時加入其他交易所
+    let router = &engine.order_router;
+    let quantity = request.amount / buy_book.asks[0].price;
+    // 基礎資產的回補以賣方交易所為中轉：各買入地劃到賣方交易所，再分到其他賣出地；
+    // 路由按整筆數量攤到每單位的劃轉成本比較交易所，沒有可用網絡的交易所不參與
+    let unit_transfer_cost = |from: &str, to: &str| {
+        engine
+            .transfer_costs
+            .quote(&base, from, to, quantity, buy_book.asks[0].price)
+            .map(|quote| quote.cost / quantity)
+    };
+    let venue = |exchange: &str, book: &OrderBook, asset: &str, transfer_cost: f64| Venue {
+        exchange: exchange.to_string(),
+        book: book.clone(),
+        fee_rate: engine.taker_fee(exchange),
+        capacity: spot.available(exchange, asset),
+        transfer_cost,
+    };
+    let others = |excluded: &[&str], asset: &str, side: Side| -> Result<Vec<Venue>, String> {
+        let mut venues = Vec::new();
+        for name in engine.venues().into_iter().filter(|name| !excluded.contains(&name.as_str())) {
+            let transfer_cost = match side {
+                Side::Buy => unit_transfer_cost(name, sell_exchange),
+                Side::Sell => unit_transfer_cost(sell_exchange, name),
+            };
+            match transfer_cost {
+                Ok(transfer_cost) => {
+                    let book = market_data::simulated_spot_book(engine.env.rng.as_ref(), name, &base, &quote)?;
+                    venues.push(venue(name, &book, asset, transfer_cost));
+                }
+                Err(error) => debug!(exchange = %name, %error, "無法回補庫存，不參與路由"),
+            }
+        }
+        Ok(venues)
+    };
+    let mut buy_venues = vec![venue(buy_exchange, &buy_book, &quote, unit_transfer_cost(buy_exchange, sell_exchange)?)];
+    if router.enabled() && quantity > buy_book.asks[0].quantity {
+        buy_venues.extend(others(&[buy_exchange, sell_exchange], &quote, Side::Buy)?);
+    }
+    let mut buy_plan = router.plan(Side::Buy, quantity, &buy_venues);
+
+    // 賣出腿不使用買入腿已用到的交易所；可賣數量不足時按可賣數量重做買入計劃
+    let mut sell_venues = vec![venue(sell_exchange, &sell_book, &base, 0.0)];
+    if router.enabled() && buy_plan.filled_quantity > sell_book.bids[0].quantity {
+        let used: Vec<&str> = buy_plan.children.iter().map(|child| child.exchange.as_str()).collect();
+        sell_venues.extend(others(&[used.as_slice(), &[sell_exchange.as_str()]].concat(), &base, Side::Sell)?);
+    }
+    let mut sell_plan = router.plan(Side::Sell, buy_plan.filled_quantity, &sell_venues);
+    if sell_plan.filled_quantity < buy_plan.filled_quantity {
+        buy_plan = router.plan(Side::Buy, sell_plan.filled_quantity, &buy_venues);
+        sell_plan = router.plan(Side::Sell, buy_plan.filled_quantity, &sell_venues);
+    }
+    if buy_plan.filled_quantity <= 0.0 || sell_plan.filled_quantity < buy_plan.filled_quantity * (1.0 - 1e-9) {
+        return Err("訂單簿深度不足".to_string());
+    }
+    let buy_fee = buy_plan.fees;
+    let sell_fee = sell_plan.fees;
+    // 按實際成交數量計算回補成本，任一段低於最小提幣量時放棄
+    let price = buy_plan.average_price();
+    let transfers = buy_plan
+        .children
+        .iter()
+        .map(|child| (child.exchange.as_str(), sell_exchange.as_str(), child.quantity))
+        .chain(
+            sell_plan
+                .children
+                .iter()
+                .filter(|child| child.exchange != *sell_exchange)
+                .map(|child| (sell_exchange.as_str(), child.exchange.as_str(), child.quantity)),
+        );
+    let mut transfer_cost = 0.0;
+    for (from, to, quantity) in transfers {
+        transfer_cost += engine.transfer_costs.quote(&base, from, to, quantity, price)?.cost;
+    }
+    let gross = sell_plan.quote_quantity - buy_plan.quote_quantity;
+    let net = gross - buy_fee - sell_fee - transfer_cost;
+    let net_edge_bps = net / buy_plan.quote_quantity * 10_000.0;
+    debug!(
+        %buy_exchange,
+        %sell_exchange,
+        buy_venues = buy_plan.children.len(),
+        sell_venues = sell_plan.children.len(),
+        gross,
+        transfer_cost,
+        net_edge_bps,
+        "現貨價差評估"
+    );
+    if net_edge_bps < spot.config.min_net_edge_bps {
+        return Err(format!("扣除手續費與轉賬成本後收益 {:.2} bps 低於閾值", net_edge_bps));
+    }
+
+    let venues: Vec<&str> = buy_plan
+        .children
+        .iter()
+        .chain(&sell_plan.children)
+        .map(|child| child.exchange.as_str())
+        .collect();
+    engine.acquire_orders(&venues).await?;
+    let (buy_orders, sell_orders) = tokio::join!(
+        order_router::execute(&buy_plan, "buy", &request.symbol),
+        order_router::execute(&sell_plan, "sell", &request.symbol),
+    );
+    {
+        let mut inventory = spot.inventory.lock().unwrap();
+        let mut adjust = |exchange: &str, asset: &str, delta: f64| {
+            *inventory.entry((exchange.to_string(), asset.to_string())).or_default() += delta;
+        };
+        for child in &buy_plan.children {
+            adjust(&child.exchange, &quote, -(child.quote_quantity + child.fee));
+            adjust(&child.exchange, &base, child.quantity);
+        }
+        for child in &sell_plan.children {
+            adjust(&child.exchange, &base, -child.quantity);
+            adjust(&child.exchange, &quote, child.quote_quantity - child.fee);
+        }
+    }
+    info!(
+        %buy_exchange,
+        %sell_exchange,
+        quantity = buy_plan.filled_quantity,
+        child_orders = buy_orders.len() + sell_orders.len(),
+        net,
+        "現貨價差套利完成"
+    );
+
+    Ok(ExecutionOutcome {
+        profit: net,
+        fees: buy_fee + sell_fee,
+        orders: buy_orders.into_iter().chain(sell_orders).collect(),
+        expected_slippage_bps: engine.sizing.expected_slippage_bps(buy_plan.quote_quantity),
+        realized_slippage_bps: (buy_plan.slippage_bps() + sell_plan.slippage_bps()) / 2.0,
+        gas_used: None,
+        gas_cost_eth: None,
+        settlement_proof: None,
+        unwound: Vec::new(),
+    })
+}
+
+// 與交易所現貨對沖的 DEX 腿：先以請求金額探測 DEX 買入價決定方向，DEX 腿先上鏈，確認後再成交交易所腿
+async fn execute_with_dex(
+    engine: &ExecutionEngine,
+    execution_id: &str,
+    request: &ArbitrageRequest,
+    chain: &ChainStack,
+    dex: &DexExecutor,
+    dex_venue: &str,
+    cex_venue: &str,
+) -> Result<ExecutionOutcome, String> {
+    let strategy = request.strategy_type.name();
+    if !engine.gas_budget.allows(strategy) {
+        return Err("當日 gas 預算已用盡，DEX 腿暫停".to_string());
+    }
+    let (base, quote) =
+        market_data::split_symbol(&request.symbol).ok_or_else(|| format!("無法解析交易對: {}", request.symbol))?;
+    let book = market_data::simulated_spot_book(engine.env.rng.as_ref(), cex_venue, &base, &quote)?;
+
+    let probe_base = dex.quote(dex_venue, &quote, &base, request.amount).await?;
+    if probe_base <= 0.0 {
+        return Err(format!("{} 沒有 {} 流動性", dex_venue, request.symbol));
+    }
+    let buy_on_dex = request.amount / probe_base < book.bids[0].price;
+    let (buy_venue, sell_venue) = if buy_on_dex { (dex_venue, cex_venue) } else { (cex_venue, dex_venue) };
+
+    let spot = &engine.spot_arbitrage;
+    let quote_available = spot.available(buy_venue, &quote);
+    let base_available = spot.available(sell_venue, &base);
+    let notional = request
+        .amount
+        .min(quote_available)
+        .min(base_available * book.bids[0].price);
+    if notional <= 0.0 {
+        return Err(format!(
+            "庫存不足：{} 上 {} 可用 {:.4}，{} 上 {} 可用 {:.6}",
+            buy_venue, quote, quote_available, sell_venue, base, base_available
+        ));
+    }
+
+    // DEX 腿的輸入與報價輸出；交易所腿按訂單簿估算
+    let (dex_in, mut dex_out, mut cex_fill) = if buy_on_dex {
+        let base_out = if notional == request.amount {
+            probe_base
+        } else {
+            dex.quote(dex_venue, &quote, &base, notional).await?
+        };
+        (notional, base_out, book.sell_base(base_out))
+    } else {
+        let buy = book.buy_with_quote(notional);
+        let quote_out = dex.quote(dex_venue, &base, &quote, buy.base_quantity).await?;
+        (buy.base_quantity, quote_out, buy)
+    };
+    // 目標池子有待確認的大額兌換時按 pending 狀態重新報價：跌破滑點下限則放棄，否則按新報價評估收益
+    let (asset_in, asset_out) = if buy_on_dex { (&quote, &base) } else { (&base, &quote) };
+    // 內存池只監控默認鏈
+    let conflicts = if chain.name == config::DEFAULT_CHAIN {
+        engine.mempool.conflicts(dex_venue, dex.pool(dex_venue, asset_in, asset_out)?)
+    } else {
+        Vec::new()
+    };
+    if let Some(conflict) = conflicts.first() {
+        let repriced = dex.quote_pending(dex_venue, asset_in, asset_out, dex_in).await?;
+        warn!(tx_hash = ?conflict.hash, conflicts = conflicts.len(), dex_out, repriced, "DEX 目標池子有待確認的大額兌換");
+        if !dex.within_slippage(dex_out, repriced) {
+            return Err(format!(
+                "待確認交易 {:?} 將使 {} 報價由 {:.6} 降至 {:.6}，放棄 DEX 腿",
+                conflict.hash, dex_venue, dex_out, repriced
+            ));
+        }
+        dex_out = repriced;
+        if buy_on_dex {
+            cex_fill = book.sell_base(dex_out);
+        }
+    }
+    if !cex_fill.complete {
+        return Err("訂單簿深度不足".to_string());
+    }
+    let cex_fee_rate = engine.taker_fee(cex_venue);
+    let (cost, proceeds, base_quantity) = if buy_on_dex {
+        (dex_in, cex_fill.quote_quantity, dex_out)
+    } else {
+        (cex_fill.quote_quantity, dex_out, dex_in)
+    };
+    let transfer_cost = engine
+        .transfer_costs
+        .quote(&base, buy_venue, sell_venue, base_quantity, cost / base_quantity)?
+        .cost;
+    let gas_quote = chain.gas_optimizer.quote(request.priority);
+    let gas_usdt = chain.estimated_gas_usdt("swap", &gas_quote)?;
+    let cex_fee = cex_fill.quote_quantity * cex_fee_rate;
+    let gas_cost = engine.check_profit_after_gas(chain, &request.symbol, proceeds - cost - transfer_cost, cex_fee, gas_usdt)?;
+    let net = proceeds - cost - cex_fee - transfer_cost - gas_cost;
+    let net_edge_bps = net / cost * 10_000.0;
+    debug!(%buy_venue, %sell_venue, dex_in, dex_out, transfer_cost, gas_cost, net_edge_bps, "CEX–DEX 價差評估");
+    if net_edge_bps < spot.config.min_net_edge_bps {
+        return Err(format!("扣除手續費、轉賬成本與 gas 後收益 {:.2} bps 低於閾值", net_edge_bps));
+    }
+
+    let fill = dex
+        .swap(dex_venue, asset_in, asset_out, dex_in, dex_out, &chain.gas_optimizer, &gas_quote)
+        .await
+        .map_err(|error| engine.record_burned_gas(execution_id, request, chain, error))?;
+    let dex_slippage_bps = ((dex_out - fill.amount_out) / dex_out).abs() * 10_000.0;
+
+    // DEX 實際成交後按實際數量成交交易所腿；DEX 腿已上鏈，配額不足也只能等待
+    engine.acquire_orders(&[cex_venue]).await?;
+    tokio::time::sleep(tokio::time::Duration::from_micros(100)).await;
+    let cex_fill = if buy_on_dex { book.sell_base(fill.amount_out) } else { cex_fill };
+    let cex_fee = cex_fill.quote_quantity * cex_fee_rate;
+    let dex_fee = cost * dex.fee_bps(dex_venue) / 10_000.0;
+    let (cost, proceeds) = if buy_on_dex {
+        (dex_in, cex_fill.quote_quantity)
+    } else {
+        (cex_fill.quote_quantity, fill.amount_out)
+    };
+    let net = proceeds - cost - cex_fee - transfer_cost;
+    {
+        let mut inventory = spot.inventory.lock().unwrap();
+        let mut adjust = |venue: &str, asset: &str, delta: f64| {
+            *inventory.entry((venue.to_string(), asset.to_string())).or_default() += delta;
+        };
+        if buy_on_dex {
+            adjust(dex_venue, &quote, -dex_in);
+            adjust(dex_venue, &base, fill.amount_out);
+            adjust(cex_venue, &base, -cex_fill.base_quantity);
+            adjust(cex_venue, &quote, cex_fill.quote_quantity - cex_fee);
+        } else {
+            adjust(dex_venue, &base, -dex_in);
+            adjust(dex_venue, &quote, fill.amount_out);
+            adjust(cex_venue, &quote, -(cex_fill.quote_quantity + cex_fee));
+            adjust(cex_venue, &base, cex_fill.base_quantity);
+        }
+    }
+    info!(%buy_venue, %sell_venue, tx_hash = %fill.tx_hash, net, "CEX–DEX 套利完成");
+
+    let order = |leg: &str, venue: &str, side: &str, quantity: f64, fee: f64| ChildOrder {
+        leg: leg.to_string(),
+        exchange: venue.to_string(),
+        symbol: request.symbol.clone(),
+        side: side.to_string(),
+        quantity,
+        filled_quantity: quantity,
+        fee,
+        status: "filled".to_string(),
+    };
+    let dex_base = if buy_on_dex { fill.amount_out } else { dex_in };
+    let orders = if buy_on_dex {
+        vec![
+            order("buy", dex_venue, "buy", dex_base, dex_fee),
+            order("sell", cex_venue, "sell", cex_fill.base_quantity, cex_fee),
+        ]
+    } else {
+        vec![
+            order("buy", cex_venue, "buy", cex_fill.base_quantity, cex_fee),
+            order("sell", dex_venue, "sell", dex_base, dex_fee),
+        ]
+    };
+    Ok(ExecutionOutcome {
+        profit: net,
+        fees: cex_fee + dex_fee,
+        orders,
+        expected_slippage_bps: engine.sizing.expected_slippage_bps(notional),
+        realized_slippage_bps: (cex_fill.slippage_bps() + dex_slippage_bps) / 2.0,
+        gas_used: Some(fill.gas_used),
+        gas_cost_eth: Some(chain.to_eth(fill.gas_cost)),
+        settlement_proof: None,
+        unwound: Vec::new(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bench::START_MS;
+    use crate::deterministic_sim::{build, funded_config, request};
+    use crate::{Environment, StrategyType};
+
+    // 賣方交易所沒有基礎資產時拒絕；有庫存時在較便宜的一方買入、另一方賣出，利潤為兩邊庫存變化扣除 BTC 回補成本
+    #[tokio::test]
+    async fn spot_arbitrage_checks_inventory_and_nets_transfer_cost() {
+        let mut config = funded_config();
+        config.order_router.enabled = false;
+        config.spot_arbitrage.min_net_edge_bps = -1_000.0;
+        let (engine, path) = build("spot-arbitrage", config.clone(), Environment::simulated(START_MS, 1));
+        let spot = ArbitrageRequest { strategy_type: StrategyType::SpotArbitrage, ..request(1_000.0) };
+        let error = execute(&engine, "spot-1", &spot).await.err().unwrap();
+        assert!(error.starts_with("庫存不足") && error.contains("BTC 可用 0.000000"), "{}", error);
+
+        for exchange in ["binance", "bybit"] {
+            config.spot_arbitrage.inventory.get_mut(exchange).unwrap().insert("BTC".to_string(), 1.0);
+        }
+        let engine = ExecutionEngine { spot_arbitrage: SpotArbitrage::new(config.spot_arbitrage.clone()), ..engine };
+        let before = engine.spot_arbitrage.inventory();
+        let outcome = execute(&engine, "spot-2", &spot).await.unwrap();
+        let (buy, sell) = (&outcome.orders[0], &outcome.orders[1]);
+        assert_eq!((outcome.orders.len(), buy.side.as_str(), sell.side.as_str()), (2, "buy", "sell"));
+        assert_ne!(buy.exchange, sell.exchange);
+        assert!((buy.filled_quantity - sell.filled_quantity).abs() < 1e-12);
+
+        let after = engine.spot_arbitrage.inventory();
+        let delta = |exchange: &str, asset: &str| {
+            let key = format!("{}:{}", exchange, asset);
+            after[&key] - before[&key]
+        };
+        assert!((delta(&buy.exchange, "BTC") - buy.filled_quantity).abs() < 1e-12);
+        assert!((delta(&sell.exchange, "BTC") + sell.filled_quantity).abs() < 1e-12);
+        let buy_price = (-delta(&buy.exchange, "USDT") - buy.fee) / buy.filled_quantity;
+        let transfer = engine.transfer_costs.quote("BTC", &buy.exchange, &sell.exchange, buy.filled_quantity, buy_price).unwrap();
+        let usdt = delta(&buy.exchange, "USDT") + delta(&sell.exchange, "USDT");
+        assert!((outcome.profit - (usdt - transfer.cost)).abs() < 1e-6, "{} {}", outcome.profit, usdt - transfer.cost);
+        assert!((outcome.fees - (buy.fee + sell.fee)).abs() < 1e-9);
+
+        // 扣除手續費與回補成本後收益低於閾值時不下單、不動庫存
+        config.spot_arbitrage.min_net_edge_bps = 1_000.0;
+        let engine = ExecutionEngine { spot_arbitrage: SpotArbitrage::new(config.spot_arbitrage), ..engine };
+        let before = engine.spot_arbitrage.inventory();
+        let error = execute(&engine, "spot-3", &spot).await.err().unwrap();
+        assert!(error.contains("低於閾值"), "{}", error);
+        assert_eq!(engine.spot_arbitrage.inventory(), before);
+        let _ = std::fs::remove_file(path);
+    }
+}
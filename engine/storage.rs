@@ -0,0 +1,364 @@
+use super::bookkeeping::ExecutionRecord;
+use super::funding_history::FundingSample;
+use super::ChildOrder;
+use serde::{Deserialize, Serialize};
+use sqlx::any::{AnyPoolOptions, AnyRow};
+use sqlx::{AnyPool, Row};
+
+pub const DEFAULT_DATABASE_URL: &str = "sqlite://execution_history.db?mode=rwc";
+const DEFAULT_HISTORY_LIMIT: i64 = 100;
+const MAX_HISTORY_LIMIT: i64 = 1000;
+
+// 兩種數據庫通用的建表語句（佔位符統一使用 $n，布爾值以整數存儲）
+const SCHEMA: [&str; 4] = [
+    "CREATE TABLE IF NOT EXISTS executions (
+        execution_id TEXT PRIMARY KEY,
+        strategy_id TEXT NOT NULL,
+        symbol TEXT NOT NULL,
+        primary_exchange TEXT NOT NULL,
+        secondary_exchange TEXT NOT NULL,
+        amount DOUBLE PRECISION NOT NULL,
+        priority BIGINT NOT NULL,
+        requested_at TEXT NOT NULL,
+        completed_at_ms BIGINT NOT NULL,
+        status TEXT NOT NULL,
+        profit DOUBLE PRECISION,
+        fees DOUBLE PRECISION NOT NULL,
+        execution_time TEXT NOT NULL,
+        error_message TEXT,
+        fast_path BIGINT NOT NULL
+    )",
+    "CREATE TABLE IF NOT EXISTS child_orders (
+        execution_id TEXT NOT NULL,
+        leg TEXT NOT NULL,
+        exchange TEXT NOT NULL,
+        symbol TEXT NOT NULL,
+        side TEXT NOT NULL,
+        quantity DOUBLE PRECISION NOT NULL,
+        filled_quantity DOUBLE PRECISION NOT NULL,
+        fee DOUBLE PRECISION NOT NULL,
+        status TEXT NOT NULL,
+        PRIMARY KEY (execution_id, leg)
+    )",
+    "CREATE INDEX IF NOT EXISTS executions_by_time ON executions (completed_at_ms)",
+    "CREATE TABLE IF NOT EXISTS funding_rates (
+        exchange TEXT NOT NULL,
+        symbol TEXT NOT NULL,
+        sampled_at_ms BIGINT NOT NULL,
+        funding_rate DOUBLE PRECISION NOT NULL,
+        predicted_rate DOUBLE PRECISION NOT NULL,
+        PRIMARY KEY (exchange, symbol, sampled_at_ms)
+    )",
+];
+
+#[derive(Debug, Default, Deserialize)]
+pub struct HistoryFilter {
+    pub strategy_id: Option<String>,
+    pub symbol: Option<String>,
+    pub from_ms: Option<i64>,
+    pub to_ms: Option<i64>,
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct HistoryEntry {
+    pub execution_id: String,
+    pub strategy_id: String,
+    pub symbol: String,
+    pub primary_exchange: String,
+    pub secondary_exchange: String,
+    pub amount: f64,
+    pub priority: i64,
+    pub requested_at: String,
+    pub completed_at_ms: i64,
+    pub status: String,
+    pub profit: Option<f64>,
+    pub fees: f64,
+    pub execution_time: String,
+    pub error_message: Option<String>,
+    pub fast_path: bool,
+    pub orders: Vec<ChildOrder>,
+}
+
+pub struct HistoryStore {
+    pool: AnyPool,
+}
+
+impl HistoryStore {
+    pub async fn close(&self) {
+        self.pool.close().await;
+    }
+
+    pub async fn connect(url: &str) -> Result<Self, sqlx::Error> {
+        sqlx::any::install_default_drivers();
+        let pool = AnyPoolOptions::new().max_connections(4).connect(url).await?;
+        for statement in SCHEMA {
+            sqlx::query(statement).execute(&pool).await?;
+        }
+        Ok(Self { pool })
+    }
+
+    pub async fn insert(&self, record: &ExecutionRecord) -> Result<(), sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+        sqlx::query(
+            "INSERT INTO executions (execution_id, strategy_id, symbol, primary_exchange,
+                secondary_exchange, amount, priority, requested_at, completed_at_ms, status,
+                profit, fees, execution_time, error_message, fast_path)
+             VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15)",
+        )
+        .bind(&record.execution_id)
+        .bind(&record.strategy_id)
+        .bind(&record.symbol)
+        .bind(&record.primary_exchange)
+        .bind(&record.secondary_exchange)
+        .bind(record.amount)
+        .bind(record.priority as i64)
+        .bind(&record.requested_at)
+        .bind(record.completed_at_ms)
+        .bind(&record.status)
+        .bind(record.profit)
+        .bind(record.fees)
+        .bind(&record.execution_time)
+        .bind(&record.error_message)
+        .bind(record.fast_path as i64)
+        .execute(&mut *tx)
+        .await?;
+
+        for order in &record.orders {
+            sqlx::query(
+                "INSERT INTO child_orders (execution_id, leg, exchange, symbol, side, quantity,
+                    filled_quantity, fee, status)
+                 VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)",
+            )
+            .bind(&record.execution_id)
+            .bind(&order.leg)
+            .bind(&order.exchange)
+            .bind(&order.symbol)
+            .bind(&order.side)
+            .bind(order.quantity)
+            .bind(order.filled_quantity)
+            .bind(order.fee)
+            .bind(&order.status)
+            .execute(&mut *tx)
+            .await?;
+        }
+        tx.commit().await
+    }
+
+    pub async fn query(&self, filter: &HistoryFilter) -> Result<Vec<HistoryEntry>, sqlx::Error> {
+        let mut sql = String::from(
+            "SELECT execution_id, strategy_id, symbol, primary_exchange, secondary_exchange,
+                amount, priority, requested_at, completed_at_ms, status, profit, fees,
+                execution_time, error_message, fast_path
+             FROM executions WHERE 1 = 1",
+        );
+        let mut placeholder = 0;
+        let mut next = || {
+            placeholder += 1;
+            placeholder
+        };
+        if filter.strategy_id.is_some() {
+            sql.push_str(&format!(" AND strategy_id = ${}", next()));
+        }
+        if filter.symbol.is_some() {
+            sql.push_str(&format!(" AND symbol = ${}", next()));
+        }
+        if filter.from_ms.is_some() {
+            sql.push_str(&format!(" AND completed_at_ms >= ${}", next()));
+        }
+        if filter.to_ms.is_some() {
+            sql.push_str(&format!(" AND completed_at_ms < ${}", next()));
+        }
+        sql.push_str(&format!(" ORDER BY completed_at_ms DESC LIMIT ${}", next()));
+
+        let mut query = sqlx::query(&sql);
+        if let Some(strategy_id) = &filter.strategy_id {
+            query = query.bind(strategy_id);
+        }
+        if let Some(symbol) = &filter.symbol {
+            query = query.bind(symbol);
+        }
+        if let Some(from_ms) = filter.from_ms {
+            query = query.bind(from_ms);
+        }
+        if let Some(to_ms) = filter.to_ms {
+            query = query.bind(to_ms);
+        }
+        let limit = filter.limit.unwrap_or(DEFAULT_HISTORY_LIMIT).clamp(1, MAX_HISTORY_LIMIT);
+        let rows = query.bind(limit).fetch_all(&self.pool).await?;
+
+        let mut entries = Vec::with_capacity(rows.len());
+        for row in rows {
+            let mut entry = entry_from_row(&row)?;
+            entry.orders = self.orders_for(&entry.execution_id).await?;
+            entries.push(entry);
+        }
+        Ok(entries)
+    }
+
+    pub async fn insert_funding_sample(&self, sample: &FundingSample) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            "INSERT INTO funding_rates (exchange, symbol, sampled_at_ms, funding_rate, predicted_rate)
+             VALUES ($1, $2, $3, $4, $5)",
+        )
+        .bind(&sample.exchange)
+        .bind(&sample.symbol)
+        .bind(sample.sampled_at_ms)
+        .bind(sample.funding_rate)
+        .bind(sample.predicted_rate)
+        .execute(&self.pool)
+        .await
+        .map(|_| ())
+    }
+
+    // 按時間順序讀取資金費率樣本，供回測回放
+    pub async fn funding_samples(
+        &self,
+        from_ms: Option<i64>,
+        to_ms: Option<i64>,
+    ) -> Result<Vec<FundingSample>, sqlx::Error> {
+        let rows = sqlx::query(
+            "SELECT exchange, symbol, sampled_at_ms, funding_rate, predicted_rate
+             FROM funding_rates WHERE sampled_at_ms >= $1 AND sampled_at_ms < $2
+             ORDER BY sampled_at_ms, exchange, symbol",
+        )
+        .bind(from_ms.unwrap_or(i64::MIN))
+        .bind(to_ms.unwrap_or(i64::MAX))
+        .fetch_all(&self.pool)
+        .await?;
+        rows.iter()
+            .map(|row| {
+                Ok(FundingSample {
+                    exchange: row.try_get("exchange")?,
+                    symbol: row.try_get("symbol")?,
+                    sampled_at_ms: row.try_get("sampled_at_ms")?,
+                    funding_rate: row.try_get("funding_rate")?,
+                    predicted_rate: row.try_get("predicted_rate")?,
+                })
+            })
+            .collect()
+    }
+
+    async fn orders_for(&self, execution_id: &str) -> Result<Vec<ChildOrder>, sqlx::Error> {
+        let rows = sqlx::query(
+            "SELECT leg, exchange, symbol, side, quantity, filled_quantity, fee, status
+             FROM child_orders WHERE execution_id = $1 ORDER BY leg",
+        )
+        .bind(execution_id)
+        .fetch_all(&self.pool)
+        .await?;
+        rows.iter()
+            .map(|row| {
+                Ok(ChildOrder {
+                    leg: row.try_get("leg")?,
+                    exchange: row.try_get("exchange")?,
+                    symbol: row.try_get("symbol")?,
+                    side: row.try_get("side")?,
+                    quantity: row.try_get("quantity")?,
+                    filled_quantity: row.try_get("filled_quantity")?,
+                    fee: row.try_get("fee")?,
+                    status: row.try_get("status")?,
+                })
+            })
+            .collect()
+    }
+}
+
+fn entry_from_row(row: &AnyRow) -> Result<HistoryEntry, sqlx::Error> {
+    Ok(HistoryEntry {
+        execution_id: row.try_get("execution_id")?,
+        strategy_id: row.try_get("strategy_id")?,
+        symbol: row.try_get("symbol")?,
+        primary_exchange: row.try_get("primary_exchange")?,
+        secondary_exchange: row.try_get("secondary_exchange")?,
+        amount: row.try_get("amount")?,
+        priority: row.try_get("priority")?,
+        requested_at: row.try_get("requested_at")?,
+        completed_at_ms: row.try_get("completed_at_ms")?,
+        status: row.try_get("status")?,
+        profit: row.try_get("profit")?,
+        fees: row.try_get("fees")?,
+        execution_time: row.try_get("execution_time")?,
+        error_message: row.try_get("error_message")?,
+        fast_path: row.try_get::<i64, _>("fast_path")? != 0,
+        orders: Vec::new(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bench::START_MS;
+    use crate::deterministic_sim::{engine, with_history};
+    use crate::{bookkeeping, config, EngineCommand, ExecutionEngine};
+    use std::sync::Arc;
+
+    // 執行記錄與子訂單寫入 SQLite 後重新連接仍在，get_history 按策略、交易對、時間範圍與條數過濾
+    #[tokio::test]
+    async fn execution_history_persists_and_filters_through_get_history() {
+        let (engine, path) = engine("history", 1, config::SimulatedExchangeConfig::default());
+        let (engine, history, db) = with_history(engine, "history").await;
+        let record = |n: i64, strategy_id: &str, symbol: &str| bookkeeping::ExecutionRecord {
+            execution_id: format!("h{}", n),
+            strategy_id: strategy_id.to_string(),
+            symbol: symbol.to_string(),
+            primary_exchange: "binance".to_string(),
+            secondary_exchange: "bybit".to_string(),
+            amount: 1_000.0,
+            priority: 5,
+            requested_at: START_MS.to_string(),
+            completed_at_ms: START_MS + n * 1_000,
+            status: "success".to_string(),
+            profit: Some(n as f64),
+            fees: 0.8,
+            execution_time: "1ms".to_string(),
+            error_message: None,
+            fast_path: false,
+            orders: ["long", "short"]
+                .into_iter()
+                .map(|leg| ChildOrder {
+                    leg: leg.to_string(),
+                    exchange: "binance".to_string(),
+                    symbol: symbol.to_string(),
+                    side: if leg == "long" { "buy" } else { "sell" }.to_string(),
+                    quantity: 1_000.0,
+                    filled_quantity: 1_000.0,
+                    fee: 0.4,
+                    status: "filled".to_string(),
+                })
+                .collect(),
+        };
+        for n in 0..6 {
+            let (strategy_id, symbol) = if n % 2 == 0 { ("alpha", "BTCUSDT") } else { ("beta", "ETHUSDT") };
+            engine.bookkeeper.record(record(n, strategy_id, symbol)).await;
+        }
+        history.close().await;
+
+        // 重新連接同一數據庫
+        let history = Arc::new(HistoryStore::connect(&format!("sqlite://{}?mode=rwc", db.display())).await.unwrap());
+        let engine = ExecutionEngine { history: Some(Arc::clone(&history)), ..engine };
+        let query = |filter: serde_json::Value| {
+            let engine = &engine;
+            async move {
+                let filter: HistoryFilter = serde_json::from_value(filter).unwrap();
+                let response = engine.handle_command(EngineCommand::GetHistory(filter)).await;
+                let entries = response.data.unwrap();
+                entries.as_array().unwrap().iter().map(|entry| entry["execution_id"].as_str().unwrap().to_string()).collect::<Vec<_>>()
+            }
+        };
+        assert_eq!(query(serde_json::json!({})).await, ["h5", "h4", "h3", "h2", "h1", "h0"]);
+        assert_eq!(query(serde_json::json!({"strategy_id": "alpha"})).await, ["h4", "h2", "h0"]);
+        assert_eq!(query(serde_json::json!({"symbol": "ETHUSDT", "limit": 2})).await, ["h5", "h3"]);
+        assert_eq!(query(serde_json::json!({"from_ms": START_MS + 1_000, "to_ms": START_MS + 3_000})).await, ["h2", "h1"]);
+        let entries = history.query(&HistoryFilter { strategy_id: Some("beta".to_string()), limit: Some(1), ..Default::default() }).await.unwrap();
+        assert_eq!((entries[0].profit, entries[0].orders.len()), (Some(5.0), 2));
+        assert_eq!(entries[0].orders[1].side, "sell");
+
+        let without = ExecutionEngine { history: None, ..engine };
+        let response = without.handle_command(EngineCommand::GetHistory(HistoryFilter::default())).await;
+        assert_eq!(response.error_message.as_deref(), Some("未啟用歷史存儲"));
+        history.close().await;
+        let _ = std::fs::remove_file(path);
+        let _ = std::fs::remove_file(db);
+    }
+}
@@ -0,0 +1,276 @@
+use super::execution_plan;
+use super::market_data::{self, OrderBook};
+use super::{ArbitrageRequest, ChildOrder, ExecutionEngine, ExecutionOutcome};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::sync::{Arc, RwLock};
+use tracing::{debug, info};
+
+pub use super::execution_plan::Action;
+pub use super::funding_history::FundingSample;
+pub use super::lending::LendingOp;
+
+/// 自定義策略：不修改引擎即可接入專有的機會邏輯。
+///
+/// 經 [`ExecutionEngine::register_strategy`] 註冊後，掃描器每輪調用 [`Strategy::evaluate`] 發布機會
+/// （`get_strategy_plugins` 指令查詢）；`strategy_type` 為 `plugin` 且 `plugin` 字段為註冊名的請求經同一執行管線
+/// （校驗、排隊、風控、資金分配、事件與記帳）執行，步驟由 [`Strategy::build_execution_plan`] 給出，
+/// 每完成一步回調 [`Strategy::on_fill`]。方法在引擎的執行與掃描任務上同步調用，不應阻塞。
+pub trait Strategy: Send + Sync {
+    // 註冊名，請求以 plugin 字段選擇
+    fn name(&self) -> &str;
+
+    fn evaluate(&self, market: &MarketView<'_>) -> Vec<StrategyOpportunity>;
+
+    // 為請求生成計劃；返回錯誤時放棄執行，不下任何單
+    fn build_execution_plan(&self, request: &ArbitrageRequest, market: &MarketView<'_>) -> Result<ExecutionPlan, String>;
+
+    fn on_fill(&self, _execution_id: &str, _order: &ChildOrder) {}
+}
+
+/// 策略可見的行情與費率。
+pub struct MarketView<'a> {
+    engine: &'a ExecutionEngine,
+}
+
+impl MarketView<'_> {
+    pub fn now_ms(&self) -> i64 {
+        self.engine.env.now_ms()
+    }
+
+    // 各交易所/交易對最近一次採樣的資金費率與預測費率
+    pub fn funding_samples(&self) -> Vec<FundingSample> {
+        self.engine.funding_history.latest()
+    }
+
+    // 本實例連接的交易所（主賬戶）
+    pub fn exchanges(&self) -> Vec<String> {
+        self.engine.venues().into_iter().cloned().collect()
+    }
+
+    pub fn taker_fee(&self, exchange: &str) -> f64 {
+        self.engine.taker_fee(exchange)
+    }
+
+    pub fn spot_book(&self, exchange: &str, symbol: &str) -> Result<OrderBook, String> {
+        let (base, quote) = market_data::split_symbol(symbol).ok_or_else(|| format!("無法解析交易對: {}", symbol))?;
+        market_data::simulated_spot_book(self.engine.env.rng.as_ref(), exchange, &base, &quote)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StrategyOpportunity {
+    pub symbol: String,
+    pub primary_exchange: String,
+    pub secondary_exchange: String,
+    // 每單位名義金額的預期淨收益，發布時按此降序
+    pub expected_edge: f64,
+    // 策略自定義的附加信息
+    #[serde(default)]
+    pub detail: Value,
+}
+
+/// 一次執行的計劃：沒有依賴的步驟並發執行，其餘在所依賴的步驟完成後執行；
+/// 任一步失敗時已完成的步驟按完成的逆序撤銷。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExecutionPlan {
+    pub steps: Vec<PlannedStep>,
+    // 全部成交時扣除手續費前的預期收益；部分成交時按各步最低成交比例折算，另加閃電貸結算的收益
+    pub expected_profit: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlannedStep {
+    pub leg: String,
+    #[serde(flatten)]
+    pub action: Action,
+    // 須先完成的步驟（leg 名）
+    #[serde(default)]
+    pub depends_on: Vec<String>,
+    // 撤銷動作；缺省時交易所單與劃轉取反向動作，兌換與閃電貸不撤銷
+    #[serde(default)]
+    pub unwind: Option<Action>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PluginView {
+    pub name: String,
+    pub opportunities: Vec<StrategyOpportunity>,
+}
+
+#[derive(Default)]
+pub struct Registry {
+    strategies: RwLock<BTreeMap<String, Arc<dyn Strategy>>>,
+    latest: RwLock<BTreeMap<String, Vec<StrategyOpportunity>>>,
+}
+
+impl Registry {
+    pub fn register(&self, strategy: Arc<dyn Strategy>) -> Result<(), String> {
+        let name = strategy.name().to_string();
+        if name.trim().is_empty() {
+            return Err("策略名不能為空".to_string());
+        }
+        let mut strategies = self.strategies.write().unwrap();
+        if strategies.contains_key(&name) {
+            return Err(format!("策略 {} 已註冊", name));
+        }
+        info!(strategy = %name, "已註冊自定義策略");
+        strategies.insert(name, strategy);
+        Ok(())
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.strategies.read().unwrap().contains_key(name)
+    }
+
+    fn get(&self, name: &str) -> Result<Arc<dyn Strategy>, String> {
+        self.strategies.read().unwrap().get(name).cloned().ok_or_else(|| format!("未註冊策略 {}", name))
+    }
+
+    // 掃描器每輪調用：各策略的機會按預期收益降序保存
+    pub(crate) fn evaluate(&self, engine: &ExecutionEngine) {
+        let market = MarketView { engine };
+        let strategies: Vec<Arc<dyn Strategy>> = self.strategies.read().unwrap().values().cloned().collect();
+        let mut latest = BTreeMap::new();
+        for strategy in strategies {
+            let mut opportunities = strategy.evaluate(&market);
+            opportunities.sort_by(|a, b| b.expected_edge.total_cmp(&a.expected_edge));
+            debug!(strategy = strategy.name(), count = opportunities.len(), "自定義策略評估完成");
+            latest.insert(strategy.name().to_string(), opportunities);
+        }
+        *self.latest.write().unwrap() = latest;
+    }
+
+    pub fn snapshot(&self) -> Vec<PluginView> {
+        let latest = self.latest.read().unwrap();
+        self.strategies
+            .read()
+            .unwrap()
+            .keys()
+            .map(|name| PluginView {
+                name: name.clone(),
+                opportunities: latest.get(name).cloned().unwrap_or_default(),
+            })
+            .collect()
+    }
+}
+
+// 取得請求選擇的策略並生成、檢查計劃
+pub(crate) fn plan(engine: &ExecutionEngine, request: &ArbitrageRequest) -> Result<(Arc<dyn Strategy>, execution_plan::ExecutionPlan, f64), String> {
+    let name = request.plugin.as_deref().ok_or("plugin 策略需要指定 plugin 字段")?;
+    let strategy = engine.strategies.get(name)?;
+    let plan = strategy.build_execution_plan(request, &MarketView { engine })?;
+    let mut steps = execution_plan::ExecutionPlan::default();
+    for step in &plan.steps {
+        match &step.action {
+            Action::Order { exchange, side, quantity, .. } => {
+                if !engine.exchanges.contains_key(exchange) {
+                    return Err(format!("策略 {} 的 {} 腿使用了未配置的交易所 {}", name, step.leg, exchange));
+                }
+                if !matches!(side.as_str(), "buy" | "sell") {
+                    return Err(format!("策略 {} 的 {} 腿方向應為 buy 或 sell", name, step.leg));
+                }
+                if !(quantity.is_finite() && *quantity > 0.0) {
+                    return Err(format!("策略 {} 的 {} 腿數量必須大於 0", name, step.leg));
+                }
+            }
+            Action::Transfer { amount, .. }
+            | Action::FlashLoan { amount, .. }
+            | Action::Swap { amount_in: amount, .. }
+            | Action::Lend { amount, .. } => {
+                if !(amount.is_finite() && *amount > 0.0) {
+                    return Err(format!("策略 {} 的 {} 步金額必須大於 0", name, step.leg));
+                }
+            }
+        }
+        let depends_on: Vec<&str> = step.depends_on.iter().map(String::as_str).collect();
+        steps = steps.step(&step.leg, step.action.clone(), &depends_on);
+        if let Some(unwind) = step.unwind.clone().or_else(|| step.action.reversed()) {
+            steps = steps.unwind(unwind);
+        }
+    }
+    steps.layers().map_err(|e| format!("策略 {} 的計劃無效: {}", name, e))?;
+    Ok((strategy, steps, plan.expected_profit))
+}
+
+// 按計劃執行，每完成一步回調策略的 on_fill
+pub(crate) async fn execute(engine: &ExecutionEngine, execution_id: &str, request: &ArbitrageRequest) -> Result<ExecutionOutcome, String> {
+    let (strategy, plan, expected_profit) = plan(engine, request)?;
+    // 鏈上步驟的 calldata 到執行時才生成，先按配置的 gas 單位以同一門檻篩除扣除 gas 後無利的計劃；
+    // 閃電貸步驟發送前再按實時估算檢查
+    let gas_usdt = plan.estimated_gas_usdt(engine, request)?;
+    if gas_usdt > 0.0 {
+        engine.check_profit_after_gas(engine.chain(request.chain.as_deref())?, &request.symbol, expected_profit, 0.0, gas_usdt)?;
+    }
+    let mut outcome = execution_plan::run(engine, execution_id, request, &plan, |order| strategy.on_fill(execution_id, order)).await?;
+    let ratio = outcome.fill_ratio();
+    let fees = outcome.fees();
+    let (gas_used, gas_cost_eth) = outcome.gas();
+    let expected_slippage_bps = engine.sizing.expected_slippage_bps(request.amount);
+    info!(strategy = strategy.name(), steps = outcome.fills.len(), fill_ratio = ratio, "自定義策略執行完成");
+    Ok(ExecutionOutcome {
+        profit: expected_profit * ratio + outcome.settled_profit() - fees,
+        fees,
+        orders: outcome.orders(),
+        // 市價單沒有逐檔成交可觀測，按模型預期值記錄
+        expected_slippage_bps,
+        realized_slippage_bps: expected_slippage_bps,
+        gas_used,
+        gas_cost_eth,
+        settlement_proof: outcome.settlement_proof(),
+        unwound: Vec::new(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::deterministic_sim::{engine, request, PairStrategy};
+    use crate::{config, validation, StrategyType};
+
+    #[tokio::test]
+    async fn registered_strategies_publish_opportunities_and_execute_plans() {
+        let simulation = config::SimulatedExchangeConfig {
+            reject_rate: 0.0,
+            partial_fill_rate: 0.0,
+            ..Default::default()
+        };
+        let (engine, path) = engine("plugin", 1, simulation);
+        let pair = Arc::new(PairStrategy {
+            fills: Default::default(),
+            restock: false,
+        });
+        engine.register_strategy(Arc::clone(&pair) as Arc<dyn Strategy>).unwrap();
+        let duplicate = PairStrategy {
+            fills: Default::default(),
+            restock: false,
+        };
+        assert!(engine.register_strategy(Arc::new(duplicate)).is_err());
+
+        engine.strategies.evaluate(&engine);
+        let plugins = engine.strategies.snapshot();
+        assert_eq!(plugins.len(), 1);
+        let edges: Vec<f64> = plugins[0].opportunities.iter().map(|opportunity| opportunity.expected_edge).collect();
+        assert_eq!(edges, [0.003, 0.001]);
+
+        let unknown = ArbitrageRequest {
+            strategy_type: StrategyType::Plugin,
+            plugin: Some("missing".to_string()),
+            ..request(1_000.0)
+        };
+        let fields: Vec<String> = validation::validate(&engine, &unknown).into_iter().map(|violation| violation.field).collect();
+        assert_eq!(fields, ["plugin"]);
+
+        let request = ArbitrageRequest {
+            plugin: Some("pair".to_string()),
+            ..unknown
+        };
+        assert!(validation::validate(&engine, &request).is_empty());
+        let outcome = execute(&engine, "plugin-1", &request).await.unwrap();
+        assert_eq!(*pair.fills.lock().unwrap(), ["long", "short"]);
+        assert!(outcome.orders.iter().all(|order| order.status == "filled"));
+        assert!((outcome.profit - (2.0 - outcome.fees)).abs() < 1e-9);
+        let _ = std::fs::remove_file(path);
+    }
+}
@@ -0,0 +1,152 @@
+use super::config::ExchangeConfig;
+use super::market_data;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+
+// 內部統一的交易對格式：大寫、無分隔符，如 BTCUSDT。
+// 接受 BTC/USDT、BTC-USDT、BTC_USDT、btcusdt 與 OKX 的 BTC-USDT-SWAP
+pub fn canonical(symbol: &str) -> Result<String, String> {
+    let upper = symbol.trim().to_uppercase();
+    let pair = upper.strip_suffix("-SWAP").unwrap_or(&upper);
+    let split = match pair.split_once(['/', '-', '_']) {
+        Some((base, quote)) => Some((base.to_string(), quote.to_string())),
+        None => market_data::split_symbol(pair),
+    };
+    match split {
+        Some((base, quote))
+            if !base.is_empty()
+                && !quote.is_empty()
+                && base.chars().chain(quote.chars()).all(|c| c.is_ascii_alphanumeric()) =>
+        {
+            Ok(format!("{}{}", base, quote))
+        }
+        _ => Err(format!("無法解析交易對: {}", symbol)),
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Format {
+    // BTCUSDT（Binance、Bybit）
+    Concatenated,
+    // BTC-USDT-SWAP（OKX 永續）
+    DashedSwap,
+}
+
+/// 單個交易所的交易對映射：按交易所的命名規則轉換，`symbols` 配置的覆蓋項優先（如 PEPEUSDT -> 1000PEPEUSDT）。
+#[derive(Debug, Clone)]
+pub struct VenueSymbols {
+    format: Format,
+    // 內部交易對 -> 交易所原生代碼，及其反向
+    to_native: HashMap<String, String>,
+    to_canonical: HashMap<String, String>,
+}
+
+impl VenueSymbols {
+    pub fn new(exchange: &str, overrides: &HashMap<String, String>) -> Result<Self, String> {
+        let format = match exchange {
+            "okx" => Format::DashedSwap,
+            _ => Format::Concatenated,
+        };
+        let mut to_native = HashMap::new();
+        let mut to_canonical = HashMap::new();
+        for (symbol, native) in overrides {
+            let symbol = canonical(symbol).map_err(|e| format!("exchanges.{}.symbols: {}", exchange, e))?;
+            if to_canonical.insert(native.clone(), symbol.clone()).is_some() {
+                return Err(format!("exchanges.{}.symbols 中 {} 對應多個交易對", exchange, native));
+            }
+            to_native.insert(symbol, native.clone());
+        }
+        Ok(Self {
+            format,
+            to_native,
+            to_canonical,
+        })
+    }
+
+    pub fn native(&self, symbol: &str) -> Result<String, String> {
+        let symbol = canonical(symbol)?;
+        if let Some(native) = self.to_native.get(&symbol) {
+            return Ok(native.clone());
+        }
+        match self.format {
+            Format::Concatenated => Ok(symbol),
+            Format::DashedSwap => {
+                let (base, quote) = market_data::split_symbol(&symbol).ok_or_else(|| format!("無法解析交易對: {}", symbol))?;
+                Ok(format!("{}-{}-SWAP", base, quote))
+            }
+        }
+    }
+}
+
+/// 全部交易所的交易對映射。請求中的交易對可以是任一交易所的原生代碼或常見寫法，
+/// 進入引擎時統一為內部格式，連接器下發請求時再轉換為各交易所的原生代碼。
+pub struct SymbolRegistry {
+    venues: BTreeMap<String, VenueSymbols>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SymbolMapping {
+    pub symbol: String,
+    pub venues: BTreeMap<String, String>,
+}
+
+impl SymbolRegistry {
+    pub fn new<'a>(exchanges: impl IntoIterator<Item = (&'a str, &'a ExchangeConfig)>) -> Result<Self, String> {
+        let venues = exchanges
+            .into_iter()
+            .map(|(name, settings)| Ok((name.to_string(), VenueSymbols::new(name, &settings.symbols)?)))
+            .collect::<Result<_, String>>()?;
+        Ok(Self { venues })
+    }
+
+    // 原生代碼覆蓋項優先，其次按常見寫法解析
+    pub fn canonical(&self, symbol: &str) -> Result<String, String> {
+        self.venues
+            .values()
+            .find_map(|venue| venue.to_canonical.get(symbol).cloned())
+            .map_or_else(|| canonical(symbol), Ok)
+    }
+
+    pub fn resolve(&self, symbol: &str) -> Result<SymbolMapping, String> {
+        let symbol = self.canonical(symbol)?;
+        let venues = self
+            .venues
+            .iter()
+            .map(|(name, venue)| Ok((name.clone(), venue.native(&symbol)?)))
+            .collect::<Result<_, String>>()?;
+        Ok(SymbolMapping { symbol, venues })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bench::START_MS;
+    use crate::deterministic_sim::{build, request};
+    use crate::{config, events, ArbitrageRequest, EngineCommand, Environment};
+
+    #[tokio::test]
+    async fn symbols_normalize_to_canonical_and_venue_native() {
+        let mut config = config::EngineConfig::default();
+        let binance = config.exchanges.entry("binance".to_string()).or_default();
+        binance.symbols.insert("PEPE/USDT".to_string(), "1000PEPEUSDT".to_string());
+        let (engine, path) = build("symbols", config, Environment::simulated(START_MS, 1));
+        let resolve = |symbol: &str| EngineCommand::GetSymbol { symbol: symbol.to_string() };
+        let mapping = engine.handle_command(resolve("1000PEPEUSDT")).await.data.unwrap();
+        assert_eq!(mapping["symbol"], "PEPEUSDT");
+        assert_eq!(mapping["venues"], serde_json::json!({"binance": "1000PEPEUSDT", "bybit": "PEPEUSDT", "okx": "PEPE-USDT-SWAP"}));
+        assert_eq!(engine.handle_command(resolve("btc-usdt-swap")).await.data.unwrap()["symbol"], "BTCUSDT");
+        assert_eq!(engine.handle_command(resolve("BTC/")).await.status, "error");
+
+        let request = ArbitrageRequest {
+            symbol: "eth/usdt".to_string(),
+            ..request(1_000.0)
+        };
+        engine.execute_funding_rate_arbitrage(request).await;
+        let received = engine.events.read(0, 100).into_iter().find_map(|envelope| match envelope.event {
+            events::EngineEvent::RequestReceived { symbol, .. } => Some(symbol),
+            _ => None,
+        });
+        assert_eq!(received.as_deref(), Some("ETHUSDT"));
+        let _ = std::fs::remove_file(path);
+    }
+}
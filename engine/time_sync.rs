@@ -0,0 +1,178 @@
+use super::environment::Clock;
+use super::{ExchangeConnector, ExecutionEngine};
+use serde::Serialize;
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use tokio::sync::Notify;
+use tracing::{debug, info, warn};
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ClockStatus {
+    // 交易所時間減本機時間，未同步前為 0
+    pub offset_ms: i64,
+    pub round_trip_ms: Option<i64>,
+    pub synced_at_ms: Option<i64>,
+    pub syncs: u64,
+    pub consecutive_failures: u32,
+    pub last_error: Option<String>,
+}
+
+pub struct ExchangeClock {
+    // 本機時鐘
+    local: Arc<dyn Clock>,
+    offset_ms: AtomicI64,
+    status: RwLock<ClockStatus>,
+    resync: Notify,
+}
+
+impl ExchangeClock {
+    pub fn new(local: Arc<dyn Clock>) -> Self {
+        Self {
+            local,
+            offset_ms: AtomicI64::new(0),
+            status: RwLock::new(ClockStatus::default()),
+            resync: Notify::new(),
+        }
+    }
+
+    // 校正後的交易所當前時間
+    pub fn now_ms(&self) -> i64 {
+        self.local.now_ms() + self.offset_ms.load(Ordering::Relaxed)
+    }
+
+    pub fn status(&self) -> ClockStatus {
+        self.status.read().unwrap().clone()
+    }
+
+    pub fn request_resync(&self) {
+        self.resync.notify_one();
+    }
+
+    fn apply(&self, offset_ms: i64, round_trip_ms: i64) {
+        self.offset_ms.store(offset_ms, Ordering::Relaxed);
+        let mut status = self.status.write().unwrap();
+        status.offset_ms = offset_ms;
+        status.round_trip_ms = Some(round_trip_ms);
+        status.synced_at_ms = Some(self.local.now_ms());
+        status.syncs += 1;
+        status.consecutive_failures = 0;
+        status.last_error = None;
+    }
+
+    // 同步失敗時保留上一次的偏差
+    fn record_failure(&self, error: String) {
+        let mut status = self.status.write().unwrap();
+        status.consecutive_failures += 1;
+        status.last_error = Some(error);
+    }
+}
+
+pub fn spawn(engine: Arc<ExecutionEngine>) {
+    if !engine.config.time_sync.enabled {
+        info!("時鐘同步未啟用");
+        return;
+    }
+    for exchange in engine.exchanges.keys() {
+        let engine = Arc::clone(&engine);
+        let exchange = exchange.clone();
+        tokio::spawn(async move { synchronize(&engine, &exchange).await });
+    }
+}
+
+async fn synchronize(engine: &ExecutionEngine, exchange: &str) {
+    let config = &engine.config.time_sync;
+    let Some(connector) = engine.exchanges.get(exchange) else { return };
+    let interval = Duration::from_secs(config.interval_secs);
+    while !engine.shutting_down.load(Ordering::Relaxed) {
+        match measure(connector, config.samples).await {
+            Ok((offset_ms, round_trip_ms)) => {
+                connector.clock.apply(offset_ms, round_trip_ms);
+                if offset_ms.abs() > config.warn_offset_ms as i64 {
+                    warn!(%exchange, offset_ms, round_trip_ms, "本機時鐘與交易所偏差過大，已按偏差校正");
+                } else {
+                    debug!(%exchange, offset_ms, round_trip_ms, "時鐘已同步");
+                }
+            }
+            Err(error) => {
+                warn!(%exchange, %error, "時鐘同步失敗，沿用上一次偏差");
+                connector.clock.record_failure(error);
+            }
+        }
+        tokio::select! {
+            _ = tokio::time::sleep(interval) => {}
+            _ = connector.clock.resync.notified() => {
+                info!(%exchange, "簽名請求時間戳被拒，立即重新同步時鐘");
+            }
+        }
+    }
+}
+
+// 假設上下行延遲對稱，服務器時間對應請求發出後半個往返；取往返最短的樣本誤差最小
+async fn measure(connector: &ExchangeConnector, samples: u32) -> Result<(i64, i64), String> {
+    let mut best: Option<(i64, i64)> = None;
+    for _ in 0..samples {
+        let sent_ms = connector.env.now_ms();
+        let server_ms = connector.fetch_server_time().await?;
+        let round_trip_ms = connector.env.now_ms() - sent_ms;
+        let offset_ms = server_ms - (sent_ms + round_trip_ms / 2);
+        if best.is_none_or(|(_, best_round_trip)| round_trip_ms < best_round_trip) {
+            best = Some((offset_ms, round_trip_ms));
+        }
+    }
+    best.ok_or_else(|| "未採集到服務器時間".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bench::START_MS;
+    use crate::deterministic_sim::{build, funded_config};
+    use crate::{funding_history, EngineCommand, Environment};
+
+    // 本機時鐘落後 okx 服務器 1.8 秒：同步前簽名請求超出 recvWindow 被拒並觸發重新同步，同步後簽名與資金費結算時間按偏差校正
+    #[tokio::test]
+    async fn time_sync_corrects_signed_timestamps_and_funding_times() {
+        let mut config = funded_config();
+        config.exchanges.get_mut("okx").unwrap().recv_window_ms = 1_000;
+        config.time_sync.interval_secs = 3_600;
+        // 本機時間距離下一次資金費結算還有 1 秒
+        let settlement = funding_history::next_funding_ms(START_MS, 8);
+        let (engine, path) = build("time-sync", config, Environment::simulated(settlement - 1_000, 1));
+        let engine = Arc::new(engine);
+        engine.funding_history.push(funding_history::FundingSample {
+            exchange: "okx".to_string(),
+            symbol: "BTCUSDT".to_string(),
+            sampled_at_ms: settlement - 60_000,
+            funding_rate: 0.0001,
+            predicted_rate: 0.0001,
+        });
+        async fn next_okx_funding(engine: &ExecutionEngine) -> i64 {
+            let calendar = engine.handle_command(EngineCommand::GetFundingCalendar).await.data.unwrap();
+            calendar["entries"].as_array().unwrap().iter().find(|entry| entry["exchange"] == "okx").unwrap()["next_funding_ms"].as_i64().unwrap()
+        }
+        let okx = &engine.exchanges["okx"];
+        assert!(okx.sign_request("/api/v5/trade/order", "instId=BTC-USDT-SWAP").unwrap_err().contains("超出 recvWindow"));
+        assert_eq!(next_okx_funding(&engine).await, settlement);
+
+        // 首輪同步後，被拒時留下的重新同步請求使其立即再同步一次，不等 interval_secs
+        spawn(Arc::clone(&engine));
+        for _ in 0..250 {
+            if okx.clock.status().syncs >= 2 {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        let status = okx.clock.status();
+        assert_eq!((status.syncs, status.consecutive_failures), (2, 0));
+        assert!((status.offset_ms - 1_800).abs() <= 5, "{}", status.offset_ms);
+        okx.sign_request("/api/v5/trade/order", "instId=BTC-USDT-SWAP").unwrap();
+        let clocks = engine.handle_command(EngineCommand::GetClockSync).await.data.unwrap();
+        assert_eq!(clocks["okx"]["offset_ms"], status.offset_ms);
+        assert!((clocks["bybit"]["offset_ms"].as_i64().unwrap() + 220).abs() <= 5);
+        // 按交易所時間 okx 已過本次結算，下一次在 8 小時後
+        assert_eq!(next_okx_funding(&engine).await, settlement + 8 * 3_600_000);
+        engine.shutting_down.store(true, std::sync::atomic::Ordering::Relaxed);
+        let _ = std::fs::remove_file(path);
+    }
+}
@@ -0,0 +1,162 @@
+use super::config::TlsConfig;
+use std::fs::File;
+use std::io::BufReader;
+use std::sync::Arc;
+use tokio_rustls::rustls::crypto::{ring, CryptoProvider};
+use tokio_rustls::rustls::pki_types::{CertificateDer, PrivateKeyDer, ServerName};
+use tokio_rustls::rustls::server::WebPkiClientVerifier;
+use tokio_rustls::rustls::{ClientConfig, RootCertStore, ServerConfig};
+use tokio_rustls::{TlsAcceptor, TlsConnector};
+
+fn provider() -> Arc<CryptoProvider> {
+    Arc::new(ring::default_provider())
+}
+
+fn open(path: &str) -> Result<BufReader<File>, String> {
+    File::open(path)
+        .map(BufReader::new)
+        .map_err(|e| format!("無法打開 {}: {}", path, e))
+}
+
+fn load_certs(path: &str) -> Result<Vec<CertificateDer<'static>>, String> {
+    let certs = rustls_pemfile::certs(&mut open(path)?)
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("{} 證書無效: {}", path, e))?;
+    if certs.is_empty() {
+        return Err(format!("{} 中沒有證書", path));
+    }
+    Ok(certs)
+}
+
+fn load_key(path: &str) -> Result<PrivateKeyDer<'static>, String> {
+    rustls_pemfile::private_key(&mut open(path)?)
+        .map_err(|e| format!("{} 私鑰無效: {}", path, e))?
+        .ok_or_else(|| format!("{} 中沒有私鑰", path))
+}
+
+fn load_roots(path: &str) -> Result<RootCertStore, String> {
+    let mut roots = RootCertStore::empty();
+    for cert in load_certs(path)? {
+        roots.add(cert).map_err(|e| format!("{} CA 證書無效: {}", path, e))?;
+    }
+    Ok(roots)
+}
+
+pub fn acceptor(config: &TlsConfig) -> Result<TlsAcceptor, String> {
+    let builder = ServerConfig::builder_with_provider(provider())
+        .with_safe_default_protocol_versions()
+        .map_err(|e| e.to_string())?;
+    let builder = match &config.client_ca_path {
+        Some(path) => {
+            let verifier = WebPkiClientVerifier::builder_with_provider(Arc::new(load_roots(path)?), provider());
+            let verifier = if config.require_client_cert {
+                verifier.build()
+            } else {
+                verifier.allow_unauthenticated().build()
+            };
+            builder.with_client_cert_verifier(verifier.map_err(|e| e.to_string())?)
+        }
+        None => builder.with_no_client_auth(),
+    };
+    let server = builder
+        .with_single_cert(load_certs(&config.cert_path)?, load_key(&config.key_path)?)
+        .map_err(|e| format!("TLS 證書與私鑰不匹配: {}", e))?;
+    Ok(TlsAcceptor::from(Arc::new(server)))
+}
+
+// 信任 ca_path 中的 CA；提供 identity 時向服務端出示客戶端證書
+pub fn client_config(ca_path: &str, identity: Option<(&str, &str)>) -> Result<Arc<ClientConfig>, String> {
+    let builder = ClientConfig::builder_with_provider(provider())
+        .with_safe_default_protocol_versions()
+        .map_err(|e| e.to_string())?
+        .with_root_certificates(load_roots(ca_path)?);
+    let client = match identity {
+        Some((cert_path, key_path)) => builder
+            .with_client_auth_cert(load_certs(cert_path)?, load_key(key_path)?)
+            .map_err(|e| format!("客戶端證書與私鑰不匹配: {}", e))?,
+        None => builder.with_no_client_auth(),
+    };
+    Ok(Arc::new(client))
+}
+
+// 區域實例間互連：以本實例證書作為客戶端證書，以客戶端 CA 校驗對端
+pub fn peer_connector(config: &TlsConfig) -> Result<Option<TlsConnector>, String> {
+    if !config.enabled {
+        return Ok(None);
+    }
+    let ca_path = config
+        .client_ca_path
+        .as_deref()
+        .ok_or("啟用 TLS 且配置了區域實例時需要 tls.client_ca_path")?;
+    let client = client_config(ca_path, Some((&config.cert_path, &config.key_path)))?;
+    Ok(Some(TlsConnector::from(client)))
+}
+
+// 從 host:port 中取出用於證書校驗的主機名或 IP
+pub fn server_name(address: &str) -> Result<ServerName<'static>, String> {
+    let host = address
+        .rsplit_once(':')
+        .map(|(host, _)| host)
+        .unwrap_or(address)
+        .trim_start_matches('[')
+        .trim_end_matches(']');
+    ServerName::try_from(host.to_string()).map_err(|e| format!("無效的主機名 {}: {}", host, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock_exchange::Scenario;
+    use crate::mock_exchange_e2e::engine;
+    use crate::{config, server};
+
+    // 客戶端監聽啟用 TLS 並要求客戶端證書（見 tests/fixtures/tls/README.md）：CA 簽發的客戶端完成握手後照常收發，
+    // 自簽證書或不出示證書的客戶端握手失敗
+    #[tokio::test]
+    async fn tls_listener_requires_client_certificates_signed_by_the_ca() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+        let fixture = |name: &str| format!("{}/tests/fixtures/tls/{}", env!("CARGO_MANIFEST_DIR"), name);
+        let acceptor = acceptor(&config::TlsConfig {
+            enabled: true,
+            cert_path: fixture("server.pem"),
+            key_path: fixture("server.key"),
+            client_ca_path: Some(fixture("ca.pem")),
+            require_client_cert: true,
+        })
+        .unwrap();
+        let (_mock, engine, path) = engine("tls", Scenario::default()).await;
+        let engine = Arc::new(engine);
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        tokio::spawn(async move {
+            while let Ok((socket, _)) = listener.accept().await {
+                let (acceptor, engine) = (acceptor.clone(), Arc::clone(&engine));
+                tokio::spawn(async move {
+                    if let Ok(stream) = acceptor.accept(socket).await {
+                        server::handle_connection(stream, &engine).await;
+                    }
+                });
+            }
+        });
+        let connect = |identity: Option<(&str, &str)>| {
+            let identity = identity.map(|(cert, key)| (fixture(cert), fixture(key)));
+            let client = client_config(&fixture("ca.pem"), identity.as_ref().map(|(cert, key)| (cert.as_str(), key.as_str()))).unwrap();
+            let address = address.clone();
+            async move {
+                let socket = tokio::net::TcpStream::connect(&address).await.unwrap();
+                let mut stream = tokio_rustls::TlsConnector::from(client).connect(server_name(&address).unwrap(), socket).await?;
+                // TLS 1.3 下服務端在首次讀寫時才拒絕客戶端證書
+                stream.write_all(br#"{"command":"get_latency"}"#).await?;
+                let mut buffer = vec![0; 64 * 1024];
+                let n = stream.read(&mut buffer).await?;
+                Ok::<_, std::io::Error>(serde_json::from_slice::<serde_json::Value>(&buffer[..n]).ok())
+            }
+        };
+        let response = connect(Some(("client.pem", "client.key"))).await.unwrap().unwrap();
+        assert_eq!(response["status"], "success");
+        for identity in [Some(("rogue.pem", "rogue.key")), None] {
+            assert!(!matches!(connect(identity).await, Ok(Some(_))), "{:?} 不應完成請求", identity);
+        }
+        let _ = std::fs::remove_file(path);
+    }
+}
@@ -0,0 +1,99 @@
+use super::config::{self, TransferCostConfig};
+use serde::Serialize;
+
+// 同一交易所賬戶之間的內部劃轉
+pub const INTERNAL_NETWORK: &str = "internal";
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TransferQuote {
+    pub asset: String,
+    pub network: String,
+    // 提幣手續費（以該資產計）
+    pub withdrawal_fee: f64,
+    pub minutes: f64,
+    // 以報價資產計的總成本：提幣手續費加轉賬期間的價格風險
+    pub cost: f64,
+}
+
+/// 跨交易所劃轉的成本模型，供掃描器評估資金費率機會的保證金回補成本、現貨套利與訂單路由評估庫存回補成本。
+pub struct TransferCostModel {
+    config: TransferCostConfig,
+}
+
+impl TransferCostModel {
+    pub fn new(config: TransferCostConfig) -> Self {
+        Self { config }
+    }
+
+    /// 以單價 `price`（報價資產計）把 `quantity` 個 `asset` 從 `from` 劃到 `to` 的最低成本報價。
+    /// 同一交易所的賬戶之間（如 binance 與 binance@sub1）不收費；沒有兩邊都支持且滿足最小提幣量的網絡時返回錯誤。
+    pub fn quote(&self, asset: &str, from: &str, to: &str, quantity: f64, price: f64) -> Result<TransferQuote, String> {
+        let (from, to) = (config::venue(from), config::venue(to));
+        if from == to {
+            return Ok(TransferQuote {
+                asset: asset.to_string(),
+                network: INTERNAL_NETWORK.to_string(),
+                withdrawal_fee: 0.0,
+                minutes: 0.0,
+                cost: 0.0,
+            });
+        }
+        let networks = self
+            .config
+            .networks
+            .get(asset)
+            .ok_or_else(|| format!("{} 沒有配置提幣網絡", asset))?;
+        let supports = |exchanges: &[String], venue: &str| exchanges.is_empty() || exchanges.iter().any(|e| e == venue);
+        let candidates: Vec<TransferQuote> = networks
+            .iter()
+            .filter(|(_, cost)| supports(&cost.exchanges, from) && supports(&cost.exchanges, to))
+            .filter(|(_, cost)| quantity >= cost.min_withdrawal)
+            .map(|(network, cost)| TransferQuote {
+                asset: asset.to_string(),
+                network: network.clone(),
+                withdrawal_fee: cost.withdrawal_fee,
+                minutes: cost.minutes,
+                cost: cost.withdrawal_fee * price + quantity * price * cost.minutes * self.config.price_risk_bps_per_minute / 10_000.0,
+            })
+            .collect();
+        candidates
+            .into_iter()
+            .min_by(|a, b| a.cost.total_cmp(&b.cost))
+            .ok_or_else(|| format!("{} 從 {} 到 {} 沒有可用網絡或數量 {} 低於最小提幣量", asset, from, to, quantity))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bench::START_MS;
+    use crate::deterministic_sim::{build, request};
+    use crate::{spot_arbitrage, ArbitrageRequest, Environment, StrategyType};
+
+    #[tokio::test]
+    async fn transfer_costs_pick_cheapest_supported_network() {
+        let mut config = config::EngineConfig::default();
+        let usdt = config.transfer_costs.networks.get_mut("USDT").unwrap();
+        usdt.get_mut("TRC20").unwrap().exchanges = vec!["binance".to_string(), "okx".to_string()];
+        config.transfer_costs.networks.remove("BTC");
+        for exchange in ["binance", "bybit"] {
+            config.spot_arbitrage.inventory.insert(exchange.to_string(), [("USDT".to_string(), 50_000.0), ("BTC".to_string(), 1.0)].into());
+        }
+        let (engine, path) = build("transfer_costs", config, Environment::simulated(START_MS, 1));
+        let model = &engine.transfer_costs;
+
+        // bybit 不支持 TRC20，只能走 ERC20；低於最小提幣量時沒有可用網絡
+        assert_eq!(model.quote("USDT", "binance", "okx", 1_000.0, 1.0).unwrap().network, "TRC20");
+        assert_eq!(model.quote("USDT", "binance", "bybit", 1_000.0, 1.0).unwrap().network, "ERC20");
+        assert!(model.quote("USDT", "binance", "bybit", 5.0, 1.0).is_err());
+        assert_eq!(model.quote("USDT", "binance", "binance@sub", 1.0, 1.0).unwrap().cost, 0.0);
+
+        let spot = ArbitrageRequest {
+            strategy_type: StrategyType::SpotArbitrage,
+            ..request(1_000.0)
+        };
+        let error = spot_arbitrage::execute(&engine, "spot", &spot).await.err();
+        assert_eq!(error.as_deref(), Some("BTC 沒有配置提幣網絡"));
+        let _ = std::fs::remove_file(path);
+    }
+}
@@ -0,0 +1,228 @@
+use super::market_data::{self, Fill, OrderBook};
+use super::{ArbitrageRequest, ChildOrder, ExecutionOutcome, ExecutionEngine};
+use std::collections::HashMap;
+use tracing::{debug, info};
+
+// 深度不足或收益為負時每次減半，最多嘗試的次數
+const MAX_SIZING_ATTEMPTS: usize = 8;
+
+#[derive(Debug, Clone)]
+struct Pair {
+    symbol: String,
+    base: String,
+    quote: String,
+}
+
+fn parse_pair(symbol: &str) -> Result<Pair, String> {
+    let (base, quote) = symbol
+        .split_once('/')
+        .ok_or_else(|| format!("交易對格式應為 BASE/QUOTE: {}", symbol))?;
+    Ok(Pair {
+        symbol: symbol.to_string(),
+        base: base.to_uppercase(),
+        quote: quote.to_uppercase(),
+    })
+}
+
+struct LegFill {
+    pair: Pair,
+    side: &'static str,
+    fill: Fill,
+}
+
+struct CyclePlan {
+    legs: Vec<LegFill>,
+    start_amount: f64,
+    end_amount: f64,
+}
+
+impl CyclePlan {
+    fn net_edge(&self) -> f64 {
+        (self.end_amount - self.start_amount) / self.start_amount
+    }
+}
+
+// 從 start_asset 出發依次經過三個交易對，逐檔成交並扣除手續費
+fn simulate_cycle(
+    start_asset: &str,
+    path: &[&Pair],
+    books: &HashMap<String, OrderBook>,
+    amount: f64,
+    fee_rate: f64,
+) -> Option<CyclePlan> {
+    let mut asset = start_asset.to_string();
+    let mut holding = amount;
+    let mut legs = Vec::with_capacity(path.len());
+    for pair in path {
+        let book = &books[&pair.symbol];
+        let (side, fill, next_asset, received) = if asset == pair.quote {
+            let fill = book.buy_with_quote(holding);
+            let received = fill.base_quantity;
+            ("buy", fill, pair.base.clone(), received)
+        } else if asset == pair.base {
+            let fill = book.sell_base(holding);
+            let received = fill.quote_quantity;
+            ("sell", fill, pair.quote.clone(), received)
+        } else {
+            return None;
+        };
+        if !fill.complete {
+            return None;
+        }
+        holding = received * (1.0 - fee_rate);
+        asset = next_asset;
+        legs.push(LegFill {
+            pair: (*pair).clone(),
+            side,
+            fill,
+        });
+    }
+    (asset == start_asset).then_some(CyclePlan {
+        legs,
+        start_amount: amount,
+        end_amount: holding,
+    })
+}
+
+// 在兩個方向中選擇收益更高的閉環；深度不足或收益為負時縮小規模重試
+fn plan(
+    start_asset: &str,
+    pairs: &[Pair],
+    books: &HashMap<String, OrderBook>,
+    amount: f64,
+    fee_rate: f64,
+) -> Option<CyclePlan> {
+    let (with_start, without_start): (Vec<&Pair>, Vec<&Pair>) = pairs
+        .iter()
+        .partition(|p| p.base == start_asset || p.quote == start_asset);
+    if with_start.len() != 2 || without_start.len() != 1 {
+        return None;
+    }
+    let forward = [with_start[0], without_start[0], with_start[1]];
+    let backward = [with_start[1], without_start[0], with_start[0]];
+
+    let mut size = amount;
+    for _ in 0..MAX_SIZING_ATTEMPTS {
+        let best = [&forward, &backward]
+            .into_iter()
+            .filter_map(|path| simulate_cycle(start_asset, path, books, size, fee_rate))
+            .max_by(|a, b| a.net_edge().total_cmp(&b.net_edge()));
+        match best {
+            Some(plan) if plan.net_edge() > 0.0 => return Some(plan),
+            _ => size /= 2.0,
+        }
+    }
+    None
+}
+
+pub async fn execute(engine: &ExecutionEngine, request: &ArbitrageRequest) -> Result<ExecutionOutcome, String> {
+    let exchange = &request.primary_exchange;
+    if !engine.exchanges.contains_key(exchange) {
+        return Err(format!("不支持的交易所: {}", exchange));
+    }
+    let symbols = request.triangle.as_ref().ok_or("三角套利需要提供 triangle 交易對")?;
+    if symbols.len() != 3 {
+        return Err("triangle 必須恰好包含三個交易對".to_string());
+    }
+    let pairs = symbols.iter().map(|s| parse_pair(s)).collect::<Result<Vec<_>, _>>()?;
+    // 以第一個交易對的報價資產作為起止資產
+    let start_asset = pairs[0].quote.clone();
+
+    let mut books = HashMap::new();
+    for pair in &pairs {
+        books.insert(
+            pair.symbol.clone(),
+            market_data::simulated_spot_book(engine.env.rng.as_ref(), exchange, &pair.base, &pair.quote)?,
+        );
+    }
+    let fee_rate = engine.taker_fee(exchange);
+    let plan = plan(&start_asset, &pairs, &books, request.amount, fee_rate)
+        .ok_or("沒有扣除手續費後收益為正的三角套利路徑")?;
+    debug!(net_edge = plan.net_edge(), size = plan.start_amount, "三角套利路徑");
+
+    engine.acquire_orders(&vec![exchange.as_str(); plan.legs.len()]).await?;
+    // 模擬執行延遲（微秒級）
+    tokio::time::sleep(tokio::time::Duration::from_micros(100)).await;
+
+    // 各腿成交價值大致相同，手續費按起始資產估算
+    let fees = plan.start_amount * fee_rate * plan.legs.len() as f64;
+    let orders = plan
+        .legs
+        .iter()
+        .enumerate()
+        .map(|(i, leg)| ChildOrder {
+            leg: format!("leg{}", i + 1),
+            exchange: exchange.clone(),
+            symbol: leg.pair.symbol.clone(),
+            side: leg.side.to_string(),
+            quantity: leg.fill.base_quantity,
+            filled_quantity: leg.fill.base_quantity,
+            fee: plan.start_amount * fee_rate,
+            status: "filled".to_string(),
+        })
+        .collect();
+    let realized_slippage_bps =
+        plan.legs.iter().map(|l| l.fill.slippage_bps()).sum::<f64>() / plan.legs.len() as f64;
+    info!(
+        start_asset = %start_asset,
+        size = plan.start_amount,
+        end_amount = plan.end_amount,
+        "三角套利完成"
+    );
+
+    Ok(ExecutionOutcome {
+        profit: plan.end_amount - plan.start_amount,
+        fees,
+        orders,
+        expected_slippage_bps: engine.sizing.expected_slippage_bps(plan.start_amount),
+        realized_slippage_bps,
+        gas_used: None,
+        gas_cost_eth: None,
+        settlement_proof: None,
+        unwound: Vec::new(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bench::START_MS;
+    use crate::deterministic_sim::{build, funded_config, request};
+    use crate::{Environment, StrategyType};
+
+    // 手續費為零時隨機偏移總有一個方向的閉環收益為正；手續費 1% 時三腿成本超過偏移，拒絕執行
+    #[tokio::test]
+    async fn triangular_cycle_closes_on_start_asset_and_refuses_after_fees() {
+        let mut config = funded_config();
+        config.exchanges.get_mut("binance").unwrap().taker_fee = 0.0;
+        let (engine, path) = build("triangular", config, Environment::simulated(START_MS, 3));
+        let triangle = ArbitrageRequest {
+            strategy_type: StrategyType::Triangular,
+            triangle: Some(vec!["BTC/USDT".to_string(), "ETH/BTC".to_string(), "ETH/USDT".to_string()]),
+            ..request(1_000.0)
+        };
+
+        let outcome = execute(&engine, &triangle).await.unwrap();
+        assert!(outcome.profit > 0.0 && outcome.fees == 0.0, "{}", outcome.profit);
+        let legs: Vec<_> = outcome.orders.iter().map(|o| (o.leg.as_str(), o.exchange.as_str(), o.status.as_str())).collect();
+        assert_eq!(legs, [("leg1", "binance", "filled"), ("leg2", "binance", "filled"), ("leg3", "binance", "filled")]);
+        // 起止資產為 USDT：首腿用 USDT 買入，末腿賣回 USDT，中間一腿為 ETH/BTC
+        let (first, last) = (&outcome.orders[0], &outcome.orders[2]);
+        assert_eq!((first.side.as_str(), last.side.as_str()), ("buy", "sell"));
+        assert!(first.symbol.ends_with("/USDT") && last.symbol.ends_with("/USDT") && first.symbol != last.symbol);
+        assert_eq!(outcome.orders[1].symbol, "ETH/BTC");
+
+        *engine.exchanges["binance"].taker_fee.write().unwrap() = 0.01;
+        let error = execute(&engine, &triangle).await.err().unwrap();
+        assert!(error.contains("沒有扣除手續費後收益為正"), "{}", error);
+
+        // 三個交易對不能構成閉環
+        let open = ArbitrageRequest {
+            triangle: Some(vec!["BTC/USDT".to_string(), "ETH/USDT".to_string(), "SOL/USDT".to_string()]),
+            ..triangle.clone()
+        };
+        *engine.exchanges["binance"].taker_fee.write().unwrap() = 0.0;
+        assert!(execute(&engine, &open).await.is_err());
+        let _ = std::fs::remove_file(path);
+    }
+}
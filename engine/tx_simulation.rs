@@ -0,0 +1,418 @@
+use super::config::{SimulationBackend, TxSimulationConfig};
+use super::environment::Vars;
+use serde_json::{json, Value};
+use std::time::Duration;
+use tracing::{info, warn};
+use web3::ethabi::{self, ParamType};
+use web3::transports::Http;
+use web3::types::{Address, BlockId, BlockNumber, CallRequest, TransactionId, TransactionParameters, TransactionReceipt};
+use web3::{Transport, Web3};
+
+// Error(string) 的選擇器
+const ERROR_SELECTOR: [u8; 4] = [0x08, 0xc3, 0x79, 0xa0];
+
+/// 回滾原因的分類，按回滾信息中的關鍵字判斷。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RevertKind {
+    // 成交量低於最少成交量
+    Slippage,
+    // 池子或借貸市場流動性不足、餘額不足
+    InsufficientLiquidity,
+    // 超過兌換截止時間
+    Deadline,
+    // 套利合約的最低收益檢查未通過
+    Unprofitable,
+    // 消耗完 gas 上限
+    OutOfGas,
+    Other,
+}
+
+impl RevertKind {
+    pub fn classify(reason: &str) -> Self {
+        let reason = reason.to_lowercase();
+        let has = |patterns: &[&str]| patterns.iter().any(|pattern| reason.contains(pattern));
+        if has(&["too little received", "too much requested", "slippage", "fewer coins", "insufficient_output", "insufficient output"]) {
+            RevertKind::Slippage
+        } else if has(&["too old", "deadline", "expired"]) {
+            RevertKind::Deadline
+        } else if has(&["liquidity", "exceeds balance", "insufficient balance", "not enough"]) {
+            RevertKind::InsufficientLiquidity
+        } else if has(&["profit"]) {
+            RevertKind::Unprofitable
+        } else if has(&["out of gas"]) {
+            RevertKind::OutOfGas
+        } else {
+            RevertKind::Other
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            RevertKind::Slippage => "滑點",
+            RevertKind::InsufficientLiquidity => "流動性不足",
+            RevertKind::Deadline => "超過截止時間",
+            RevertKind::Unprofitable => "無利可圖",
+            RevertKind::OutOfGas => "gas 耗盡",
+            RevertKind::Other => "其他",
+        }
+    }
+}
+
+// 已上鏈但回滾的交易，gas 照常扣除
+#[derive(Debug, Clone)]
+pub struct Revert {
+    pub kind: RevertKind,
+    pub reason: String,
+    pub tx_hash: String,
+    pub gas_used: u64,
+    // 以原生代幣計
+    pub gas_cost: f64,
+}
+
+/// 鏈上腿的錯誤；reverted 為 Some 時交易已上鏈回滾，調用方需把燒掉的 gas 記為虧損。
+#[derive(Debug, Clone)]
+pub struct OnchainError {
+    pub message: String,
+    pub reverted: Option<Revert>,
+}
+
+impl OnchainError {
+    pub fn reverted(what: &str, revert: Revert) -> Self {
+        Self {
+            message: format!("{}回滾（{}）: {}: {}", what, revert.kind.label(), revert.tx_hash, revert.reason),
+            reverted: Some(revert),
+        }
+    }
+}
+
+impl From<String> for OnchainError {
+    fn from(message: String) -> Self {
+        Self { message, reverted: None }
+    }
+}
+
+impl From<OnchainError> for String {
+    fn from(error: OnchainError) -> Self {
+        error.message
+    }
+}
+
+/// 解析回滾交易：在所在區塊的父狀態上按原參數重放取得回滾信息（同區塊內排在前面的交易不在其中，原因僅供參考），
+/// 並按收據計算燒掉的 gas；用盡 gas 上限時歸為 gas 耗盡。
+pub async fn inspect_revert<T: Transport>(web3: &Web3<T>, receipt: &TransactionReceipt, fallback_fee_per_gas: u64) -> Revert {
+    let tx_hash = format!("{:?}", receipt.transaction_hash);
+    let gas_used = receipt.gas_used.unwrap_or_default().as_u64();
+    let gas_price = receipt.effective_gas_price.map(|price| price.as_u64()).unwrap_or(fallback_fee_per_gas);
+    let (gas_limit, reason) = match replay(web3, receipt).await {
+        Ok((gas_limit, reason)) => (Some(gas_limit), reason),
+        Err(e) => {
+            warn!(%tx_hash, error = %e, "無法重放回滾交易");
+            (None, "未知原因".to_string())
+        }
+    };
+    let kind = if gas_limit.is_some_and(|limit| gas_used >= limit) {
+        RevertKind::OutOfGas
+    } else {
+        RevertKind::classify(&reason)
+    };
+    Revert {
+        kind,
+        reason,
+        tx_hash,
+        gas_used,
+        gas_cost: gas_used as f64 * gas_price as f64 / 1e18,
+    }
+}
+
+async fn replay<T: Transport>(web3: &Web3<T>, receipt: &TransactionReceipt) -> Result<(u64, String), String> {
+    let tx = web3
+        .eth()
+        .transaction(TransactionId::Hash(receipt.transaction_hash))
+        .await
+        .map_err(|e| e.to_string())?
+        .ok_or("交易不存在")?;
+    let block = receipt.block_number.ok_or("回執缺少區塊號")?.saturating_sub(1.into());
+    let call = CallRequest {
+        from: tx.from,
+        to: tx.to,
+        gas: Some(tx.gas),
+        value: Some(tx.value),
+        data: Some(tx.input),
+        ..CallRequest::default()
+    };
+    let reason = match web3.eth().call(call, Some(BlockId::Number(BlockNumber::Number(block)))).await {
+        // 父狀態上未回滾：回滾由同區塊中更早的交易改變狀態所致
+        Ok(_) => "同區塊中更早的交易改變了狀態".to_string(),
+        Err(web3::Error::Rpc(error)) => rpc_revert_reason(error.message, error.data.as_ref()),
+        Err(e) => return Err(e.to_string()),
+    };
+    Ok((tx.gas.as_u64(), reason))
+}
+
+pub struct Simulation {
+    // eth_call 後端不返回實際消耗
+    pub gas_used: Option<u64>,
+}
+
+pub struct TxSimulator {
+    config: TxSimulationConfig,
+    chain_id: u64,
+    fork: Option<Web3<Http>>,
+    client: reqwest::Client,
+    tenderly_key: Option<String>,
+}
+
+impl TxSimulator {
+    pub fn connect(config: &TxSimulationConfig, chain_id: u64, vars: &dyn Vars) -> Result<Option<Self>, String> {
+        if !config.enabled {
+            return Ok(None);
+        }
+        let fork = match (&config.backend, &config.fork_rpc_url) {
+            (SimulationBackend::Fork, Some(url)) => {
+                Some(Web3::new(Http::new(url).map_err(|e| format!("tx_simulation.fork_rpc_url 無效: {}", e))?))
+            }
+            _ => None,
+        };
+        let tenderly_key = match config.backend {
+            SimulationBackend::Tenderly => Some(
+                vars.var(&config.tenderly.access_key_env)
+                    .ok_or_else(|| format!("Tenderly 模擬未設置環境變量 {}", config.tenderly.access_key_env))?,
+            ),
+            _ => None,
+        };
+        info!(chain_id, backend = ?config.backend, "已啟用上鏈前交易模擬");
+        Ok(Some(Self {
+            config: config.clone(),
+            chain_id,
+            fork,
+            client: reqwest::Client::new(),
+            tenderly_key,
+        }))
+    }
+
+    /// 按 pending 狀態模擬即將發送的交易；回滾、超時或後端不可用時返回錯誤，調用方不應發送。
+    pub async fn simulate<T: Transport>(&self, web3: &Web3<T>, from: Address, tx: &TransactionParameters) -> Result<Simulation, String> {
+        let call = CallRequest {
+            from: Some(from),
+            to: tx.to,
+            gas: Some(tx.gas),
+            value: Some(tx.value),
+            data: Some(tx.data.clone()),
+            transaction_type: tx.transaction_type,
+            max_fee_per_gas: tx.max_fee_per_gas,
+            max_priority_fee_per_gas: tx.max_priority_fee_per_gas,
+            ..CallRequest::default()
+        };
+        let simulation = async {
+            match self.config.backend {
+                SimulationBackend::EthCall => eth_call(web3, call).await,
+                SimulationBackend::DebugTraceCall => trace_call(web3, &call).await,
+                SimulationBackend::Fork => match &self.fork {
+                    Some(fork) => trace_call(fork, &call).await,
+                    None => trace_call(web3, &call).await,
+                },
+                SimulationBackend::Tenderly => self.tenderly(&call).await,
+            }
+        };
+        tokio::time::timeout(Duration::from_millis(self.config.timeout_ms), simulation)
+            .await
+            .map_err(|_| format!("交易模擬超過 {}ms", self.config.timeout_ms))?
+    }
+
+    async fn tenderly(&self, call: &CallRequest) -> Result<Simulation, String> {
+        let tenderly = &self.config.tenderly;
+        let url = format!("{}/account/{}/project/{}/simulate", tenderly.api_url, tenderly.account, tenderly.project);
+        let body = json!({
+            "network_id": self.chain_id.to_string(),
+            "from": call.from,
+            "to": call.to,
+            "input": call.data,
+            "gas": call.gas.map(|gas| gas.as_u64()),
+            "value": call.value.unwrap_or_default().to_string(),
+            "simulation_type": "quick",
+            "save": false,
+            "save_if_fails": false,
+        });
+        let response: Value = self
+            .client
+            .post(&url)
+            .header("X-Access-Key", self.tenderly_key.as_deref().unwrap_or_default())
+            .json(&body)
+            .send()
+            .await
+            .map_err(|e| format!("連接 Tenderly 失敗: {}", e))?
+            .json()
+            .await
+            .map_err(|e| format!("Tenderly 響應無效: {}", e))?;
+        if let Some(error) = response.get("error") {
+            return Err(format!("Tenderly 返回錯誤: {}", error));
+        }
+        let transaction = &response["transaction"];
+        if transaction["status"].as_bool() != Some(true) {
+            return Err(simulated_revert(transaction["error_message"].as_str().unwrap_or("未知原因")));
+        }
+        Ok(Simulation {
+            gas_used: transaction["gas_used"].as_u64(),
+        })
+    }
+}
+
+async fn eth_call<T: Transport>(web3: &Web3<T>, call: CallRequest) -> Result<Simulation, String> {
+    match web3.eth().call(call, Some(BlockId::Number(BlockNumber::Pending))).await {
+        Ok(_) => Ok(Simulation { gas_used: None }),
+        Err(web3::Error::Rpc(error)) => Err(simulated_revert(&rpc_revert_reason(error.message, error.data.as_ref()))),
+        Err(e) => Err(format!("eth_call 模擬失敗: {}", e)),
+    }
+}
+
+// callTracer 的頂層調用帶有 gasUsed，回滾時帶有 error 與 revertReason
+async fn trace_call<T: Transport>(web3: &Web3<T>, call: &CallRequest) -> Result<Simulation, String> {
+    let params = vec![
+        serde_json::to_value(call).map_err(|e| e.to_string())?,
+        json!("pending"),
+        json!({"tracer": "callTracer"}),
+    ];
+    let trace = web3
+        .transport()
+        .execute("debug_traceCall", params)
+        .await
+        .map_err(|e| format!("debug_traceCall 模擬失敗: {}", e))?;
+    if let Some(error) = trace["error"].as_str() {
+        let reason = trace["revertReason"]
+            .as_str()
+            .map(str::to_string)
+            .or_else(|| trace["output"].as_str().and_then(decode_hex).as_deref().and_then(revert_reason))
+            .unwrap_or_else(|| error.to_string());
+        return Err(simulated_revert(&reason));
+    }
+    let gas_used = trace["gasUsed"]
+        .as_str()
+        .and_then(|gas| u64::from_str_radix(gas.trim_start_matches("0x"), 16).ok());
+    Ok(Simulation { gas_used })
+}
+
+fn simulated_revert(reason: &str) -> String {
+    format!("模擬回滾（{}）: {}", RevertKind::classify(reason).label(), reason)
+}
+
+// 節點在回滾時以錯誤的 data 字段返回回滾數據
+pub(crate) fn rpc_revert_reason(message: String, data: Option<&Value>) -> String {
+    let data = data.and_then(Value::as_str).and_then(decode_hex);
+    data.as_deref().and_then(revert_reason).unwrap_or(message)
+}
+
+fn decode_hex(value: &str) -> Option<Vec<u8>> {
+    hex::decode(value.trim_start_matches("0x")).ok()
+}
+
+// 解碼 require / revert 給出的 Error(string)；自定義錯誤返回選擇器
+pub(crate) fn revert_reason(data: &[u8]) -> Option<String> {
+    let (selector, payload) = (data.get(..4)?, &data[4..]);
+    if selector != ERROR_SELECTOR {
+        return Some(format!("自定義錯誤 0x{}", hex::encode(selector)));
+    }
+    ethabi::decode(&[ParamType::String], payload)
+        .ok()?
+        .into_iter()
+        .next()?
+        .into_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::environment::FixedVars;
+    use crate::mock_exchange_e2e::rpc_node;
+    use crate::{config, rpc, wallet};
+    use std::sync::Arc;
+
+    // 回滾原因按關鍵字分類；Error(string) 解碼為文本，自定義錯誤返回選擇器，節點錯誤不帶可解碼數據時沿用錯誤信息
+    #[test]
+    fn revert_reasons_decode_and_classify() {
+        use {revert_reason, rpc_revert_reason, RevertKind};
+        let cases = [
+            ("UniswapV2Router: INSUFFICIENT_OUTPUT_AMOUNT", RevertKind::Slippage),
+            ("Too little received", RevertKind::Slippage),
+            ("Transaction too old", RevertKind::Deadline),
+            ("ERC20: transfer amount exceeds balance", RevertKind::InsufficientLiquidity),
+            ("arb: profit below minimum", RevertKind::Unprofitable),
+            ("out of gas", RevertKind::OutOfGas),
+            ("Ownable: caller is not the owner", RevertKind::Other),
+        ];
+        for (reason, kind) in cases {
+            assert_eq!(RevertKind::classify(reason), kind, "{}", reason);
+        }
+
+        let payload = web3::ethabi::encode(&[web3::ethabi::Token::String("Too little received".to_string())]);
+        let error_string = [&[0x08, 0xc3, 0x79, 0xa0][..], &payload].concat();
+        assert_eq!(revert_reason(&error_string).as_deref(), Some("Too little received"));
+        assert_eq!(revert_reason(&[0xde, 0xad, 0xbe, 0xef, 0x01]).as_deref(), Some("自定義錯誤 0xdeadbeef"));
+        assert_eq!(revert_reason(&[0x08, 0xc3]), None);
+
+        let data = serde_json::json!(format!("0x{}", hex::encode(&error_string)));
+        assert_eq!(rpc_revert_reason("execution reverted".to_string(), Some(&data)), "Too little received");
+        assert_eq!(rpc_revert_reason("execution reverted".to_string(), Some(&serde_json::json!("0xzz"))), "execution reverted");
+        assert_eq!(rpc_revert_reason("execution reverted".to_string(), None), "execution reverted");
+    }
+
+    // eth_call 模擬回滾時拒絕發送；debug_traceCall 給出的實際 gas 消耗按淨收益門檻重新檢查，不足時同樣不發送
+    #[tokio::test]
+    async fn simulation_refuses_reverts_and_unprofitable_gas_use() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        let sent = Arc::new(AtomicUsize::new(0));
+        let node = Arc::clone(&sent);
+        let url = rpc_node(Arc::new(move |method, _| {
+            Ok(match method {
+                "eth_call" => return Err("execution reverted: Too little received".to_string()),
+                "debug_traceCall" => serde_json::json!({"type": "CALL", "gasUsed": "0x61a80"}),
+                "eth_getTransactionCount" => serde_json::json!("0x0"),
+                "eth_sendRawTransaction" => {
+                    node.fetch_add(1, Ordering::SeqCst);
+                    serde_json::json!(format!("{:?}", web3::types::H256::from_low_u64_be(9)))
+                }
+                other => return Err(format!("unexpected {}", other)),
+            })
+        }))
+        .await;
+        let rpc = rpc::RpcTransport::connect("e2e", &url, &config::RpcConfig::default()).unwrap();
+        let tx = web3::types::TransactionParameters {
+            to: Some(web3::types::Address::from_low_u64_be(0xf1)),
+            gas: 500_000.into(),
+            max_fee_per_gas: Some(20_000_000_000u64.into()),
+            max_priority_fee_per_gas: Some(1_500_000_000u64.into()),
+            transaction_type: Some(2.into()),
+            ..Default::default()
+        };
+
+        let simulator = TxSimulator::connect(&config::TxSimulationConfig::default(), 1, &FixedVars::default()).unwrap().unwrap();
+        let error = simulator
+            .simulate(&web3::Web3::new(rpc.clone()), web3::types::Address::zero(), &tx)
+            .await
+            .err()
+            .unwrap();
+        assert_eq!(error, "模擬回滾（滑點）: execution reverted: Too little received");
+
+        let vars = FixedVars::new([("E2E_SIMULATION_KEY", "4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318")]);
+        let flash_loan = config::FlashLoanConfig {
+            wallets: vec![config::WalletConfig {
+                name: "hot".to_string(),
+                private_key_env: Some("E2E_SIMULATION_KEY".to_string()),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        let simulation = config::TxSimulationConfig {
+            backend: config::SimulationBackend::DebugTraceCall,
+            ..Default::default()
+        };
+        let wallets = wallet::WalletManager::connect(&flash_loan, Some(&rpc), &config::BundleConfig::default(), &simulation, &config::SubscriptionConfig::default(), &vars)
+            .unwrap()
+            .unwrap();
+        let below_400k = |gas: u64| if gas < 400_000 { Ok(()) } else { Err("淨收益不足".to_string()) };
+        let error = wallets.submit_checked(wallets.primary(), tx.clone(), &below_400k).await.unwrap_err();
+        assert!(error.contains("按模擬消耗的 400000 gas 計算淨收益不足"), "{}", error);
+        assert_eq!(sent.load(Ordering::SeqCst), 0);
+        wallets.submit(wallets.primary(), tx).await.unwrap();
+        assert_eq!(sent.load(Ordering::SeqCst), 1);
+    }
+}
@@ -0,0 +1,292 @@
+use super::events::EngineEvent;
+use super::ExecutionEngine;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tracing::{debug, warn};
+
+// 每個交易所保留的最近訂單數
+const ORDER_HISTORY: usize = 500;
+// 對賬時忽略的數量差
+const RECONCILE_TOLERANCE: f64 = 1e-9;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum UserEvent {
+    Order {
+        order_id: String,
+        client_order_id: String,
+        symbol: String,
+        side: String,
+        status: String,
+        quantity: f64,
+        filled_quantity: f64,
+        last_fill_quantity: f64,
+        last_fill_price: f64,
+        fee: f64,
+        event_time_ms: i64,
+    },
+    Position {
+        symbol: String,
+        quantity: f64,
+        event_time_ms: i64,
+    },
+    Balance {
+        asset: String,
+        free: f64,
+        locked: f64,
+        event_time_ms: i64,
+    },
+}
+
+// REST 賬戶快照：持倉與餘額的完整狀態
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AccountSnapshot {
+    pub positions: BTreeMap<String, f64>,
+    pub balances: BTreeMap<String, Balance>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct Balance {
+    pub free: f64,
+    pub locked: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct OrderState {
+    pub order_id: String,
+    pub client_order_id: String,
+    pub symbol: String,
+    pub side: String,
+    pub status: String,
+    pub quantity: f64,
+    pub filled_quantity: f64,
+    pub fee: f64,
+    pub updated_ms: i64,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct AccountState {
+    pub positions: BTreeMap<String, f64>,
+    pub balances: BTreeMap<String, Balance>,
+    pub orders: VecDeque<OrderState>,
+    pub events_applied: u64,
+    pub last_event_ms: Option<i64>,
+    pub reconciled_at_ms: Option<i64>,
+    // 最近一次對賬修正的差異項數
+    pub last_reconcile_diffs: usize,
+}
+
+pub struct UserStreams {
+    accounts: Mutex<BTreeMap<String, AccountState>>,
+}
+
+fn now_ms() -> i64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_millis() as i64).unwrap_or_default()
+}
+
+impl UserStreams {
+    pub fn new() -> Self {
+        Self {
+            accounts: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    pub fn snapshot(&self) -> BTreeMap<String, AccountState> {
+        self.accounts.lock().unwrap().clone()
+    }
+
+    pub fn account(&self, exchange: &str) -> Option<AccountState> {
+        self.accounts.lock().unwrap().get(exchange).cloned()
+    }
+
+    fn apply(&self, exchange: &str, event: &UserEvent) {
+        let mut accounts = self.accounts.lock().unwrap();
+        let account = accounts.entry(exchange.to_string()).or_default();
+        account.events_applied += 1;
+        match event {
+            UserEvent::Order {
+                order_id,
+                client_order_id,
+                symbol,
+                side,
+                status,
+                quantity,
+                filled_quantity,
+                fee,
+                event_time_ms,
+                ..
+            } => {
+                account.last_event_ms = Some(*event_time_ms);
+                let state = OrderState {
+                    order_id: order_id.clone(),
+                    client_order_id: client_order_id.clone(),
+                    symbol: symbol.clone(),
+                    side: side.clone(),
+                    status: status.clone(),
+                    quantity: *quantity,
+                    filled_quantity: *filled_quantity,
+                    fee: *fee,
+                    updated_ms: *event_time_ms,
+                };
+                match account.orders.iter_mut().find(|order| order.order_id == *order_id) {
+                    Some(existing) => *existing = state,
+                    None => {
+                        account.orders.push_back(state);
+                        if account.orders.len() > ORDER_HISTORY {
+                            account.orders.pop_front();
+                        }
+                    }
+                }
+            }
+            UserEvent::Position { symbol, quantity, event_time_ms } => {
+                account.last_event_ms = Some(*event_time_ms);
+                account.positions.insert(symbol.clone(), *quantity);
+            }
+            UserEvent::Balance { asset, free, locked, event_time_ms } => {
+                account.last_event_ms = Some(*event_time_ms);
+                account.balances.insert(asset.clone(), Balance { free: *free, locked: *locked });
+            }
+        }
+    }
+
+    // 以 REST 快照覆蓋持倉與餘額，返回被修正的項數（斷線期間漏掉的推送）
+    pub fn reconcile(&self, exchange: &str, snapshot: AccountSnapshot) -> usize {
+        let mut accounts = self.accounts.lock().unwrap();
+        let account = accounts.entry(exchange.to_string()).or_default();
+        let position_diffs = diff_count(&account.positions, &snapshot.positions, |a, b| (a - b).abs() > RECONCILE_TOLERANCE);
+        let balance_diffs = diff_count(&account.balances, &snapshot.balances, |a, b| {
+            (a.free - b.free).abs() > RECONCILE_TOLERANCE || (a.locked - b.locked).abs() > RECONCILE_TOLERANCE
+        });
+        account.positions = snapshot.positions;
+        account.balances = snapshot.balances;
+        account.reconciled_at_ms = Some(now_ms());
+        account.last_reconcile_diffs = position_diffs + balance_diffs;
+        account.last_reconcile_diffs
+    }
+}
+
+fn diff_count<V: Copy + Default>(
+    local: &BTreeMap<String, V>,
+    remote: &BTreeMap<String, V>,
+    differs: impl Fn(V, V) -> bool,
+) -> usize {
+    let mut keys: Vec<&String> = local.keys().chain(remote.keys()).collect();
+    keys.sort();
+    keys.dedup();
+    keys.into_iter()
+        .filter(|key| {
+            differs(
+                local.get(*key).copied().unwrap_or_default(),
+                remote.get(*key).copied().unwrap_or_default(),
+            )
+        })
+        .count()
+}
+
+// 更新交易所側狀態；成交寫入事件日誌，client_order_id 是引擎下發的訂單時關聯到該次執行
+// （模擬成交回報的 client_order_id 為 "執行 ID/腿名"）
+pub fn ingest(engine: &ExecutionEngine, exchange: &str, event: UserEvent) {
+    engine.user_streams.apply(exchange, &event);
+    if let UserEvent::Order {
+        order_id,
+        client_order_id,
+        symbol,
+        side,
+        last_fill_quantity,
+        last_fill_price,
+        fee,
+        ..
+    } = event
+    {
+        if last_fill_quantity <= 0.0 {
+            return;
+        }
+        debug!(%exchange, %order_id, %client_order_id, last_fill_quantity, "用戶數據流成交");
+        let execution_id = engine
+            .order_ids
+            .lookup(&client_order_id)
+            .map(|tag| tag.execution_id)
+            .or_else(|| client_order_id.split_once('/').map(|(id, _)| id.to_string()));
+        engine.events.append(EngineEvent::ExchangeFill {
+            execution_id,
+            exchange: exchange.to_string(),
+            order_id,
+            symbol,
+            side,
+            quantity: last_fill_quantity,
+            price: last_fill_price,
+            fee,
+        });
+    }
+}
+
+// 連接建立或推送積壓丟失後拉取 REST 快照對賬
+pub async fn reconcile(engine: &ExecutionEngine, exchange: &str) -> Result<(), String> {
+    let connector = engine.exchanges.get(exchange).ok_or_else(|| format!("不支持的交易所: {}", exchange))?;
+    let snapshot = connector.fetch_account_snapshot().await?;
+    let diffs = engine.user_streams.reconcile(exchange, snapshot);
+    if diffs > 0 {
+        warn!(%exchange, diffs, "用戶數據流與 REST 快照不一致，已按快照修正");
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bench::START_MS;
+    use crate::deterministic_sim::{build, connect_sessions, funded_config};
+    use crate::{events, ChildOrder, Environment};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    // 用戶數據流推送的訂單、持倉與餘額更新交易所側賬戶，成交按 client_order_id 寫入執行的事件；
+    // 推送漏掉的變化在對賬時按 REST 快照修正並記錄差異項數
+    #[tokio::test]
+    async fn user_streams_track_fills_and_reconcile_missed_updates() {
+        let (engine, path) = build("user-streams", funded_config(), Environment::simulated(START_MS, 1));
+        let engine = Arc::new(engine);
+        connect_sessions(&engine).await;
+        let account = engine.user_streams.account("binance").unwrap();
+        assert!(account.reconciled_at_ms.is_some());
+        assert_eq!(account.balances["USDT"], Balance { free: 100_000.0, locked: 0.0 });
+
+        let fill = ChildOrder {
+            leg: "long".to_string(),
+            exchange: "binance".to_string(),
+            symbol: "BTCUSDT".to_string(),
+            side: "buy".to_string(),
+            quantity: 2.0,
+            filled_quantity: 2.0,
+            fee: 1.5,
+            status: "filled".to_string(),
+        };
+        engine.exchanges["binance"].simulate_fill("e1", &fill, None);
+        let mut account = engine.user_streams.account("binance").unwrap();
+        for _ in 0..250 {
+            if account.events_applied >= 3 {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            account = engine.user_streams.account("binance").unwrap();
+        }
+        assert_eq!(account.positions["BTCUSDT"], 2.0);
+        assert_eq!(account.balances["USDT"].free, 99_998.5);
+        let order = account.orders.back().unwrap();
+        assert_eq!((order.client_order_id.as_str(), order.filled_quantity), ("e1/long", 2.0));
+        let last = engine.events.read(engine.events.last_sequence(), 1).pop().unwrap();
+        assert!(matches!(last.event, events::EngineEvent::ExchangeFill { execution_id: Some(ref id), quantity, .. } if id == "e1" && quantity == 2.0));
+
+        // 交易所側變化沒有推送
+        engine.exchanges["binance"].account.lock().unwrap().positions.insert("ETHUSDT".to_string(), -1.0);
+        reconcile(&engine, "binance").await.unwrap();
+        let account = engine.user_streams.account("binance").unwrap();
+        assert_eq!(account.last_reconcile_diffs, 1);
+        assert_eq!(account.positions["ETHUSDT"], -1.0);
+        reconcile(&engine, "binance").await.unwrap();
+        assert_eq!(engine.user_streams.account("binance").unwrap().last_reconcile_diffs, 0);
+        let _ = std::fs::remove_file(path);
+    }
+}
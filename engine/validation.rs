@@ -0,0 +1,145 @@
+use super::{ArbitrageRequest, ExecutionEngine, FieldViolation, StrategyType};
+
+// 兩條腿必須在不同交易所的策略；期現套利的現貨與永續可在同一交易所，三角套利與自定義策略只校驗 primary_exchange
+fn cross_exchange(strategy_type: StrategyType) -> bool {
+    matches!(strategy_type, StrategyType::FundingRate | StrategyType::SpotArbitrage | StrategyType::LendingRate)
+}
+
+/// 按字段約束逐項檢查請求，返回全部違規項；在排隊與任何行情、賬戶訪問之前調用。
+pub(crate) fn validate(engine: &ExecutionEngine, request: &ArbitrageRequest) -> Vec<FieldViolation> {
+    let mut violations = Vec::new();
+    let mut violate = |field: &str, message: String| violations.push(FieldViolation { field: field.to_string(), message });
+    let strategy_type = request.strategy_type;
+
+    if request.strategy_id.trim().is_empty() {
+        violate("strategy_id", "不能為空".to_string());
+    }
+    if request.symbol.trim().is_empty() {
+        violate("symbol", "不能為空".to_string());
+    } else if let Err(e) = engine.symbols.canonical(&request.symbol) {
+        violate("symbol", e);
+    }
+    let legs: &[(&str, &str)] = match strategy_type {
+        StrategyType::Triangular | StrategyType::Plugin => &[("primary_exchange", &request.primary_exchange)],
+        _ => &[("primary_exchange", &request.primary_exchange), ("secondary_exchange", &request.secondary_exchange)],
+    };
+    // 借貸利差套利的 primary_exchange 為借貸市場，secondary_exchange 可為借貸市場或交易所
+    let lending = strategy_type == StrategyType::LendingRate;
+    for (field, exchange) in legs {
+        if exchange.trim().is_empty() {
+            violate(field, "不能為空".to_string());
+        } else if lending && *field == "primary_exchange" {
+            if !engine.lending.has_market(exchange) {
+                violate(field, format!("未配置借貸市場 {}", exchange));
+            }
+        } else if !(engine.exchanges.contains_key(*exchange) || engine.routing.is_remote(exchange) || lending && engine.lending.has_market(exchange)) {
+            violate(field, format!("未配置交易所 {}", exchange));
+        }
+    }
+    if cross_exchange(strategy_type) && !request.primary_exchange.is_empty() && request.primary_exchange == request.secondary_exchange {
+        violate("secondary_exchange", format!("{} 策略的兩條腿不能在同一交易所", strategy_type.name()));
+    }
+    if !(request.amount.is_finite() && request.amount > 0.0) {
+        violate("amount", format!("必須為大於 0 的有限數值，收到 {}", request.amount));
+    }
+    match (&request.triangle, strategy_type) {
+        (None, StrategyType::Triangular) => violate("triangle", "三角套利需要提供三個交易對".to_string()),
+        (Some(symbols), StrategyType::Triangular) if symbols.len() != 3 || symbols.iter().any(|symbol| symbol.trim().is_empty()) => {
+            violate("triangle", format!("必須恰好包含三個非空交易對，收到 {} 個", symbols.len()))
+        }
+        (Some(_), StrategyType::Triangular) | (None, _) => {}
+        (Some(_), _) => violate("triangle", "僅三角套利使用".to_string()),
+    }
+    match (&request.plugin, strategy_type) {
+        (None, StrategyType::Plugin) => violate("plugin", "自定義策略需要指定已註冊的策略名".to_string()),
+        (Some(name), StrategyType::Plugin) if !engine.strategies.contains(name) => violate("plugin", format!("未註冊策略 {}", name)),
+        (Some(_), StrategyType::Plugin) | (None, _) => {}
+        (Some(_), _) => violate("plugin", "僅自定義策略使用".to_string()),
+    }
+    if let Some(chain) = &request.chain {
+        if !engine.chains.contains_key(chain) {
+            violate("chain", format!("未配置鏈 {}", chain));
+        }
+    }
+    if request.account.as_ref().is_some_and(|account| account.trim().is_empty()) {
+        violate("account", "指定時不能為空".to_string());
+    }
+
+    // 槓桿、執行算法與掛單優先僅用於資金費率策略
+    let funding_only = |field: &str| format!("{} 僅支持資金費率策略", field);
+    if strategy_type != StrategyType::FundingRate {
+        for (field, present) in [
+            ("leverage", request.leverage.is_some()),
+            ("margin_mode", request.margin_mode.is_some()),
+            ("execution_algo", request.execution_algo.is_some()),
+            ("maker_first", request.maker_first.is_some()),
+        ] {
+            if present {
+                violate(field, funding_only(field));
+            }
+        }
+    } else {
+        if request.leverage.is_some_and(|leverage| !(leverage.is_finite() && leverage >= 1.0)) {
+            violate("leverage", "不能小於 1".to_string());
+        }
+        if let Some(Err(e)) = request.execution_algo.as_ref().map(|algo| algo.validate(request.amount, engine.algos.config())) {
+            violate("execution_algo", e);
+        }
+        if let Some(Err(e)) = request.maker_first.as_ref().map(|maker| maker.validate(engine.maker.config())) {
+            violate("maker_first", e);
+        }
+    }
+
+    let now_ms = engine.env.now_ms();
+    if request.deadline_ms.is_some_and(|deadline| deadline <= now_ms) {
+        violate("deadline_ms", "已過執行截止時間".to_string());
+    }
+    match (request.execute_at_ms, request.execute_before_funding_secs) {
+        (Some(_), Some(_)) => violate("execute_at_ms", "不能與 execute_before_funding_secs 同時指定".to_string()),
+        (Some(execute_at_ms), None) if execute_at_ms <= now_ms => violate("execute_at_ms", "已過".to_string()),
+        (None, Some(0)) => violate("execute_before_funding_secs", "必須大於 0".to_string()),
+        _ => {}
+    }
+    violations
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bench::START_MS;
+    use crate::deterministic_sim::{engine, request};
+    use crate::config;
+
+    #[tokio::test]
+    async fn malformed_requests_return_every_field_violation_before_execution() {
+        let (engine, path) = engine("validation", 1, config::SimulatedExchangeConfig::default());
+        let malformed = ArbitrageRequest {
+            strategy_id: " ".to_string(),
+            secondary_exchange: "binance".to_string(),
+            amount: -5.0,
+            leverage: Some(0.5),
+            execute_at_ms: Some(START_MS + 60_000),
+            execute_before_funding_secs: Some(30),
+            ..request(1_000.0)
+        };
+        let response = engine.submit(malformed).await;
+        assert_eq!(response.status, "error");
+        assert!(response.execution_id.is_none());
+        let fields: Vec<&str> = response.violations.iter().map(|violation| violation.field.as_str()).collect();
+        assert_eq!(fields, ["strategy_id", "secondary_exchange", "amount", "leverage", "execute_at_ms"]);
+        assert!(response.error_message.unwrap().contains("amount: 必須為大於 0"));
+
+        let triangular = ArbitrageRequest {
+            strategy_type: StrategyType::Triangular,
+            primary_exchange: "kraken".to_string(),
+            symbol: String::new(),
+            leverage: Some(2.0),
+            ..request(1_000.0)
+        };
+        let fields: Vec<String> = engine.submit(triangular).await.violations.into_iter().map(|violation| violation.field).collect();
+        assert_eq!(fields, ["symbol", "primary_exchange", "triangle", "leverage"]);
+        // 未校驗失敗的請求沒有進入執行，事件日誌為空
+        assert!(engine.events.last_sequence() == 0 && engine.open_executions.is_empty());
+        let _ = std::fs::remove_file(path);
+    }
+}
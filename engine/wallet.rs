@@ -0,0 +1,515 @@
+use super::block_watcher::BlockWatcher;
+use super::bundle_submitter::BundleSubmitter;
+use super::config::{BundleConfig, FlashLoanConfig, Severity, SubscriptionConfig, TxSimulationConfig, WalletConfig};
+use super::environment::Vars;
+use super::gas::ProfitGate;
+use super::tx_simulation::TxSimulator;
+use super::rpc::RpcTransport;
+use super::ExecutionEngine;
+use serde::Serialize;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex as StdMutex};
+use std::time::Duration;
+use tokio::sync::Mutex;
+use tracing::{debug, info, warn};
+use web3::signing::{Key, SecretKey, SecretKeyRef};
+use web3::types::{Address, BlockNumber, TransactionId, TransactionParameters, TransactionReceipt, H256, U256};
+use web3::Web3;
+
+const RECEIPT_POLL_INTERVAL: Duration = Duration::from_secs(1);
+// 已發送未確認的交易的對賬間隔
+const RECONCILE_INTERVAL: Duration = Duration::from_secs(30);
+
+pub struct HotWallet {
+    name: String,
+    key: SecretKey,
+    address: Address,
+    // 下一個可用 nonce；None 表示需要從鏈上恢復
+    nonce: Mutex<Option<U256>>,
+}
+
+impl HotWallet {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn address(&self) -> Address {
+        self.address
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct WalletStatus {
+    pub name: String,
+    pub address: String,
+    pub next_nonce: Option<String>,
+}
+
+// 等待回執超時或查詢失敗的交易：已發送，結果未知，由對賬任務跟進
+#[derive(Debug, Clone)]
+pub struct UnconfirmedTx {
+    pub tx_hash: H256,
+    // 交易用途，用於告警
+    pub what: String,
+    pub confirmations: usize,
+}
+
+#[derive(Debug)]
+pub enum LateOutcome {
+    // 達到確認數，成功或回滾見回執狀態
+    Confirmed(Box<TransactionReceipt>),
+    // 節點已沒有該交易，未上鏈
+    Dropped,
+}
+
+pub struct WalletManager {
+    web3: Web3<RpcTransport>,
+    // 簽名前的關鍵讀取按 rpc.quorum 交叉驗證
+    verified: Web3<RpcTransport>,
+    chain_id: u64,
+    wallets: Vec<HotWallet>,
+    next: AtomicUsize,
+    // 配置後所有交易先以私有 bundle 提交
+    bundles: Option<BundleSubmitter>,
+    // 啟用後所有交易簽名前先模擬
+    simulator: Option<TxSimulator>,
+    watcher: BlockWatcher,
+    receipt_timeout: Duration,
+    unconfirmed: StdMutex<Vec<UnconfirmedTx>>,
+}
+
+fn load_key(config: &WalletConfig, vars: &dyn Vars) -> Result<SecretKey, String> {
+    let env = |name: &str| vars.var(name).ok_or_else(|| format!("錢包 {} 未設置環境變量 {}", config.name, name));
+    let key = match (&config.keystore_path, &config.private_key_env) {
+        (Some(path), _) => {
+            let password_env = config
+                .password_env
+                .as_deref()
+                .ok_or_else(|| format!("錢包 {} 缺少 password_env", config.name))?;
+            let bytes = eth_keystore::decrypt_key(path, env(password_env)?)
+                .map_err(|e| format!("錢包 {} 解密 keystore {} 失敗: {}", config.name, path, e))?;
+            SecretKey::from_slice(&bytes)
+        }
+        (None, Some(private_key_env)) => SecretKey::from_str(env(private_key_env)?.trim().trim_start_matches("0x")),
+        (None, None) => return Err(format!("錢包 {} 沒有私鑰來源", config.name)),
+    };
+    key.map_err(|e| format!("錢包 {} 私鑰無效: {}", config.name, e))
+}
+
+impl WalletManager {
+    // 未配置 RPC 時不加載錢包，鏈上腿全部退回模擬
+    pub fn connect(
+        config: &FlashLoanConfig,
+        rpc: Option<&RpcTransport>,
+        bundle: &BundleConfig,
+        simulation: &TxSimulationConfig,
+        subscription: &SubscriptionConfig,
+        vars: &dyn Vars,
+    ) -> Result<Option<Arc<Self>>, String> {
+        let Some(rpc) = rpc else {
+            return Ok(None);
+        };
+        let mut wallets = Self::load(&config.wallets, rpc, config.chain_id, vars)?;
+        wallets.bundles = BundleSubmitter::connect(bundle, vars)?;
+        wallets.simulator = TxSimulator::connect(simulation, config.chain_id, vars)?;
+        wallets.watcher = BlockWatcher::new(subscription.clone());
+        wallets.receipt_timeout = Duration::from_secs(config.receipt_timeout_secs);
+        Ok(Some(Arc::new(wallets)))
+    }
+
+    pub fn load(configs: &[WalletConfig], rpc: &RpcTransport, chain_id: u64, vars: &dyn Vars) -> Result<Self, String> {
+        let mut wallets: Vec<HotWallet> = Vec::new();
+        for config in configs {
+            let key = load_key(config, vars)?;
+            let address = SecretKeyRef::new(&key).address();
+            if wallets.iter().any(|w| w.address == address) {
+                return Err(format!("錢包 {} 與已加載的錢包地址重複: {:?}", config.name, address));
+            }
+            info!(wallet = %config.name, address = ?address, "熱錢包已加載");
+            wallets.push(HotWallet {
+                name: config.name.clone(),
+                key,
+                address,
+                nonce: Mutex::new(None),
+            });
+        }
+        if wallets.is_empty() {
+            return Err("沒有可用的熱錢包".to_string());
+        }
+        Ok(Self {
+            web3: Web3::new(rpc.clone()),
+            verified: Web3::new(rpc.cross_checked()),
+            chain_id,
+            wallets,
+            next: AtomicUsize::new(0),
+            bundles: None,
+            simulator: None,
+            watcher: BlockWatcher::new(SubscriptionConfig::default()),
+            receipt_timeout: Duration::from_secs(FlashLoanConfig::default().receipt_timeout_secs),
+            unconfirmed: StdMutex::new(Vec::new()),
+        })
+    }
+
+    pub fn addresses(&self) -> Vec<Address> {
+        self.wallets.iter().map(|w| w.address).collect()
+    }
+
+    pub fn web3(&self) -> &Web3<RpcTransport> {
+        &self.web3
+    }
+
+    // 簽名前的餘額、gas 估算與 nonce 讀取使用；rpc.quorum 為 1 時與 web3 相同
+    pub fn verified_web3(&self) -> &Web3<RpcTransport> {
+        &self.verified
+    }
+
+    pub fn watcher(&self) -> &BlockWatcher {
+        &self.watcher
+    }
+
+    // 訂閱在線時隨新區塊查詢回執，否則輪詢，直到所在區塊獲得足夠確認數；確認後交給區塊訂閱核對重組。
+    // 超過 receipt_timeout_secs 仍未確認時返回錯誤
+    pub async fn wait_for_receipt(&self, hash: H256, confirmations: usize) -> Result<TransactionReceipt, String> {
+        let mut heads = self.watcher.subscribe();
+        let wait = async {
+            loop {
+                if let Some(receipt) = self.confirmed(hash, confirmations).await? {
+                    self.watcher.track(&receipt);
+                    return Ok(receipt);
+                }
+                self.watcher.wait(&mut heads, RECEIPT_POLL_INTERVAL).await;
+            }
+        };
+        tokio::time::timeout(self.receipt_timeout, wait)
+            .await
+            .map_err(|_| format!("{} 秒內未達到 {} 個確認", self.receipt_timeout.as_secs(), confirmations))?
+    }
+
+    // 等待確認；超時或查詢失敗時交易已發送但結果未知，登記後由對賬任務跟進，返回的錯誤註明已轉入對賬
+    pub async fn confirm(&self, hash: H256, confirmations: usize, what: &str) -> Result<TransactionReceipt, String> {
+        self.wait_for_receipt(hash, confirmations).await.map_err(|e| {
+            warn!(tx_hash = ?hash, what, error = %e, "交易已發送但未確認，轉入對賬");
+            self.unconfirmed.lock().unwrap().push(UnconfirmedTx {
+                tx_hash: hash,
+                what: what.to_string(),
+                confirmations,
+            });
+            format!("{} {:?} 已發送但未確認（{}），已轉入對賬", what, hash, e)
+        })
+    }
+
+    // 回執所在區塊已有足夠確認數時返回回執
+    async fn confirmed(&self, hash: H256, confirmations: usize) -> Result<Option<TransactionReceipt>, String> {
+        let eth = self.web3.eth();
+        let Some(receipt) = eth.transaction_receipt(hash).await.map_err(|e| e.to_string())? else {
+            return Ok(None);
+        };
+        let Some(block) = receipt.block_number else {
+            return Ok(None);
+        };
+        let head = match self.watcher.head() {
+            Some(head) => head,
+            None => eth.block_number().await.map_err(|e| e.to_string())?.as_u64(),
+        };
+        Ok((head + 1 >= block.as_u64() + confirmations as u64).then_some(receipt))
+    }
+
+    pub fn unconfirmed(&self) -> Vec<UnconfirmedTx> {
+        self.unconfirmed.lock().unwrap().clone()
+    }
+
+    // 逐筆查詢已發送未確認的交易，返回已有結果的交易並停止跟進；查詢失敗的下次重試
+    pub async fn reconcile_unconfirmed(&self) -> Vec<(UnconfirmedTx, LateOutcome)> {
+        let mut resolved = Vec::new();
+        for tx in self.unconfirmed() {
+            let outcome = match self.confirmed(tx.tx_hash, tx.confirmations).await {
+                Ok(Some(receipt)) => {
+                    self.watcher.track(&receipt);
+                    LateOutcome::Confirmed(Box::new(receipt))
+                }
+                Ok(None) => match self.web3.eth().transaction(TransactionId::Hash(tx.tx_hash)).await {
+                    Ok(None) => LateOutcome::Dropped,
+                    Ok(Some(_)) => continue,
+                    Err(e) => {
+                        warn!(tx_hash = ?tx.tx_hash, error = %e, "查詢未確認交易失敗");
+                        continue;
+                    }
+                },
+                Err(e) => {
+                    warn!(tx_hash = ?tx.tx_hash, error = %e, "查詢未確認交易的回執失敗");
+                    continue;
+                }
+            };
+            // 被丟棄的交易佔用的 nonce 未上鏈，下次發送前從鏈上恢復
+            if matches!(outcome, LateOutcome::Dropped) {
+                self.resync_nonces().await;
+            }
+            self.unconfirmed.lock().unwrap().retain(|pending| pending.tx_hash != tx.tx_hash);
+            resolved.push((tx, outcome));
+        }
+        resolved
+    }
+
+    pub fn wallet(&self, address: Address) -> Option<&HotWallet> {
+        self.wallets.iter().find(|wallet| wallet.address == address)
+    }
+
+    // 第一個熱錢包；借貸頭寸等需要固定歸屬地址的操作使用
+    pub fn primary(&self) -> &HotWallet {
+        &self.wallets[0]
+    }
+
+    // 輪流使用各熱錢包，讓多筆交易分散在不同的 nonce 隊列上
+    pub fn next(&self) -> &HotWallet {
+        let index = self.next.fetch_add(1, Ordering::Relaxed) % self.wallets.len();
+        &self.wallets[index]
+    }
+
+    // 模擬通過後以錢包的下一個 nonce 簽名並發送；持有 nonce 鎖直到節點接收交易，
+    // 私有提交時則一直持有到 bundle 被打包或退回公開發送
+    pub async fn submit(&self, wallet: &HotWallet, tx: TransactionParameters) -> Result<H256, String> {
+        self.submit_checked(wallet, tx, &|_| Ok(())).await
+    }
+
+    // 同 submit；模擬後端返回實際 gas 消耗時先按其重新檢查淨收益
+    pub async fn submit_checked(&self, wallet: &HotWallet, tx: TransactionParameters, profitable: &ProfitGate<'_>) -> Result<H256, String> {
+        let tx = match &self.bundles {
+            Some(bundles) => bundles.cap_priority_fee(tx),
+            None => tx,
+        };
+        if let Some(simulator) = &self.simulator {
+            let simulation = simulator
+                .simulate(&self.web3, wallet.address, &tx)
+                .await
+                .map_err(|e| format!("交易模擬未通過，未發送: {}", e))?;
+            debug!(wallet = %wallet.name, gas_used = ?simulation.gas_used, "交易模擬通過");
+            if let Some(gas_used) = simulation.gas_used {
+                profitable(gas_used).map_err(|e| format!("按模擬消耗的 {} gas 計算{}，未發送", gas_used, e))?;
+            }
+        }
+        let mut nonce = wallet.nonce.lock().await;
+        let current = match *nonce {
+            Some(current) => current,
+            None => self.recover_nonce(wallet).await?,
+        };
+        let signed = self
+            .web3
+            .accounts()
+            .sign_transaction(
+                TransactionParameters {
+                    nonce: Some(current),
+                    chain_id: Some(self.chain_id),
+                    ..tx
+                },
+                &wallet.key,
+            )
+            .await
+            .map_err(|e| format!("錢包 {} 簽名失敗: {}", wallet.name, e))?;
+        let sent = match &self.bundles {
+            Some(bundles) => bundles.submit(&self.web3, &signed).await,
+            None => self
+                .web3
+                .eth()
+                .send_raw_transaction(signed.raw_transaction)
+                .await
+                .map_err(|e| e.to_string()),
+        };
+        match sent {
+            Ok(hash) => {
+                *nonce = Some(current + 1);
+                Ok(hash)
+            }
+            Err(e) => {
+                // 無法確定節點是否已接收，下次提交前從鏈上重新同步
+                *nonce = None;
+                warn!(wallet = %wallet.name, nonce = %current, error = %e, "交易發送失敗，nonce 將從鏈上恢復");
+                Err(format!("錢包 {} 發送交易失敗: {}", wallet.name, e))
+            }
+        }
+    }
+
+    // 以 pending 區塊的交易數作為下一個 nonce，包含已進入內存池的交易
+    async fn recover_nonce(&self, wallet: &HotWallet) -> Result<U256, String> {
+        let nonce = self
+            .verified
+            .eth()
+            .transaction_count(wallet.address, Some(BlockNumber::Pending))
+            .await
+            .map_err(|e| format!("錢包 {} 恢復 nonce 失敗: {}", wallet.name, e))?;
+        info!(wallet = %wallet.name, nonce = %nonce, "已從鏈上恢復 nonce");
+        Ok(nonce)
+    }
+
+    // 已確認的交易被重組出鏈時，本地 nonce 可能超前於鏈上，下次發送前重新恢復
+    pub async fn resync_nonces(&self) {
+        for wallet in &self.wallets {
+            *wallet.nonce.lock().await = None;
+        }
+    }
+
+    pub async fn status(&self) -> Vec<WalletStatus> {
+        let mut status = Vec::with_capacity(self.wallets.len());
+        for wallet in &self.wallets {
+            status.push(WalletStatus {
+                name: wallet.name.clone(),
+                address: format!("{:?}", wallet.address),
+                next_nonce: wallet.nonce.lock().await.map(|nonce| nonce.to_string()),
+            });
+        }
+        status
+    }
+}
+
+// 每條加載了熱錢包的鏈定期對賬已發送未確認的交易；結果遲到時告警，由人工核對相關頭寸
+pub fn spawn(engine: Arc<ExecutionEngine>) {
+    for (name, chain) in &engine.chains {
+        let Some(wallets) = chain.wallets.clone() else { continue };
+        let engine = Arc::clone(&engine);
+        let name = name.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(RECONCILE_INTERVAL);
+            loop {
+                interval.tick().await;
+                for (tx, outcome) in wallets.reconcile_unconfirmed().await {
+                    let message = match outcome {
+                        LateOutcome::Confirmed(receipt) if receipt.status == Some(1.into()) => format!(
+                            "{} {:?} 在區塊 {} 確認成功，結果未計入執行，需核對相關頭寸",
+                            tx.what,
+                            tx.tx_hash,
+                            receipt.block_number.unwrap_or_default()
+                        ),
+                        LateOutcome::Confirmed(receipt) => {
+                            format!("{} {:?} 在區塊 {} 回滾", tx.what, tx.tx_hash, receipt.block_number.unwrap_or_default())
+                        }
+                        LateOutcome::Dropped => format!("{} {:?} 已被節點丟棄，未上鏈", tx.what, tx.tx_hash),
+                    };
+                    info!(chain = %name, %message, "未確認交易對賬完成");
+                    engine.alert(Severity::Warning, "未確認交易對賬", message);
+                }
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::environment::FixedVars;
+    use crate::mock_exchange_e2e::{hot_wallets, mined_receipt, rpc_node};
+    use crate::{config, rpc};
+    use serde_json::json;
+
+    // 超時未確認的交易登記對賬，之後按回執或節點是否仍持有交易得出結果
+    #[tokio::test]
+    async fn receipt_timeout_hands_transactions_to_reconciliation() {
+        let (mined, dropped) = (H256::from_low_u64_be(1), H256::from_low_u64_be(2));
+        let landed = Arc::new(AtomicUsize::new(0));
+        let node = Arc::clone(&landed);
+        let url = rpc_node(Arc::new(move |method, params| {
+            let landed = node.load(Ordering::SeqCst) > 0;
+            Ok(match method {
+                "eth_getTransactionReceipt" if landed && params[0] == json!(mined) => mined_receipt(&params[0]),
+                "eth_getTransactionReceipt" => json!(null),
+                "eth_getTransactionByHash" => json!(null),
+                "eth_blockNumber" => json!("0x10"),
+                other => return Err(format!("unexpected {}", other)),
+            })
+        }))
+        .await;
+        let mut wallets = hot_wallets(&url);
+        wallets.receipt_timeout = Duration::from_millis(200);
+
+        for hash in [mined, dropped] {
+            let error = wallets.confirm(hash, 1, "閃電貸交易").await.unwrap_err();
+            assert!(error.contains("已發送但未確認（0 秒內未達到 1 個確認），已轉入對賬"), "{}", error);
+        }
+        assert_eq!(wallets.unconfirmed().len(), 2);
+
+        landed.store(1, Ordering::SeqCst);
+        let resolved = wallets.reconcile_unconfirmed().await;
+        assert_eq!(resolved.len(), 2);
+        assert!(matches!(&resolved[0], (tx, LateOutcome::Confirmed(receipt)) if tx.tx_hash == mined && receipt.status == Some(1.into())));
+        assert!(matches!(&resolved[1], (tx, LateOutcome::Dropped) if tx.tx_hash == dropped && tx.what == "閃電貸交易"));
+        assert!(wallets.unconfirmed().is_empty());
+    }
+
+    // 熱錢包從 keystore 或環境變量加載並輪流使用；同一錢包的並發提交串行分配連續 nonce，只在首次與發送失敗後從鏈上恢復
+    #[tokio::test]
+    async fn wallets_load_keys_and_serialize_nonces_per_address() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        let counters = Arc::new((AtomicUsize::new(0), AtomicUsize::new(0)));
+        let node = Arc::clone(&counters);
+        let url = rpc_node(Arc::new(move |method, params| {
+            Ok(match method {
+                "eth_getTransactionCount" => {
+                    node.0.fetch_add(1, Ordering::SeqCst);
+                    serde_json::json!("0x5")
+                }
+                "eth_sendRawTransaction" => {
+                    if node.1.fetch_add(1, Ordering::SeqCst) == 10 {
+                        return Err("connection reset".to_string());
+                    }
+                    let raw = hex::decode(params[0].as_str().unwrap().trim_start_matches("0x")).unwrap();
+                    serde_json::json!(format!("0x{}", hex::encode(web3::signing::keccak256(&raw))))
+                }
+                other => return Err(format!("unexpected {}", other)),
+            })
+        }))
+        .await;
+        let rpc = rpc::RpcTransport::connect("e2e", &url, &config::RpcConfig::default()).unwrap();
+        let dir = std::env::temp_dir().join(format!("arb-wallets-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let cold_key = hex::decode("4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318").unwrap();
+        eth_keystore::encrypt_key(&dir, &mut rand::thread_rng(), &cold_key, "correct horse", Some("cold.json")).unwrap();
+        let vars = FixedVars::new([
+            ("E2E_KEYSTORE_PASSWORD", "correct horse".to_string()),
+            ("E2E_HOT_KEY", format!("0x{}", "11".repeat(32))),
+            ("E2E_DUPLICATE_KEY", hex::encode(&cold_key)),
+        ]);
+        let wallet = |name: &str, private_key_env: &str| config::WalletConfig {
+            name: name.to_string(),
+            private_key_env: Some(private_key_env.to_string()),
+            ..Default::default()
+        };
+        let cold = config::WalletConfig {
+            name: "cold".to_string(),
+            keystore_path: Some(dir.join("cold.json").display().to_string()),
+            password_env: Some("E2E_KEYSTORE_PASSWORD".to_string()),
+            private_key_env: None,
+        };
+
+        let error = WalletManager::load(&[wallet("missing", "E2E_UNSET_KEY")], &rpc, 1, &vars).err().unwrap();
+        assert!(error.contains("未設置環境變量 E2E_UNSET_KEY"), "{}", error);
+        let error = WalletManager::load(&[cold.clone(), wallet("copy", "E2E_DUPLICATE_KEY")], &rpc, 1, &vars).err().unwrap();
+        assert!(error.contains("地址重複"), "{}", error);
+
+        let wallets = WalletManager::load(&[cold, wallet("hot", "E2E_HOT_KEY")], &rpc, 1, &vars).unwrap();
+        assert_eq!(wallets.addresses().len(), 2);
+        let order: Vec<_> = (0..3).map(|_| wallets.next().name().to_string()).collect();
+        assert_eq!(order, ["cold", "hot", "cold"]);
+
+        let tx = web3::types::TransactionParameters {
+            to: Some(web3::types::Address::from_low_u64_be(0x99)),
+            gas: 21_000.into(),
+            transaction_type: Some(2.into()),
+            max_fee_per_gas: Some(1_000_000_000u64.into()),
+            max_priority_fee_per_gas: Some(1_000_000u64.into()),
+            ..Default::default()
+        };
+        let submits = (0..10).map(|_| wallets.submit(wallets.primary(), tx.clone()));
+        let hashes: std::collections::HashSet<_> = futures::future::join_all(submits).await.into_iter().map(Result::unwrap).collect();
+        assert_eq!(hashes.len(), 10);
+        let next_nonces = || async { wallets.status().await.into_iter().map(|status| status.next_nonce).collect::<Vec<_>>() };
+        assert_eq!(next_nonces().await, [Some("15".to_string()), None]);
+        assert_eq!(counters.0.load(Ordering::SeqCst), 1);
+
+        // 發送失敗後無法確定節點是否已接收，下一筆從鏈上重新恢復
+        assert!(wallets.submit(wallets.primary(), tx.clone()).await.is_err());
+        assert_eq!(next_nonces().await, [None, None]);
+        wallets.submit(wallets.primary(), tx).await.unwrap();
+        assert_eq!(next_nonces().await, [Some("6".to_string()), None]);
+        assert_eq!(counters.0.load(Ordering::SeqCst), 2);
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
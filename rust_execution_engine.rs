@@ -1,264 +1,115 @@
-use tokio::net::{TcpListener, TcpStream};
-use tokio::io::{AsyncReadExt, AsyncWriteExt};
-use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
-use std::time::{SystemTime, UNIX_EPOCH};
+use arbitrage_engine::{backtest, config::EngineConfig, load_test, mock_exchange, repl, server, LogHandle};
+use tracing::error;
+use tracing_subscriber::{fmt, prelude::*, reload, EnvFilter};
 
-#[derive(Debug, Serialize, Deserialize)]
-struct ArbitrageRequest {
-    strategy_id: String,
-    symbol: String,
-    primary_exchange: String,
-    secondary_exchange: String,
-    amount: f64,
-    priority: i32,
-    timestamp: String,
-}
-
-#[derive(Debug, Serialize, Deserialize)]
-struct ArbitrageResponse {
-    status: String,
-    profit: Option<f64>,
-    execution_time: String,
-    gas_used: Option<u64>,
-    error_message: Option<String>,
-}
-
-struct RustExecutionEngine {
-    exchanges: HashMap<String, ExchangeConnector>,
-    flash_loan_providers: Vec<String>,
-    gas_optimizer: GasOptimizer,
-}
-
-struct ExchangeConnector {
-    name: String,
-    base_url: String,
-    api_key: String,
-    secret_key: String,
-}
-
-struct GasOptimizer {
-    current_gas_price: u64,
-    max_gas_limit: u64,
+// 初始化 tracing：RUST_LOG 控制過濾，ARB_LOG_FORMAT=json 輸出 JSON 日誌
+fn init_tracing() -> LogHandle {
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"));
+    let (filter, handle) = reload::Layer::new(filter);
+    let json = std::env::var("ARB_LOG_FORMAT").map(|v| v == "json").unwrap_or(false);
+    
+    let registry = tracing_subscriber::registry().with(filter);
+    if json {
+        registry.with(fmt::layer().json().with_current_span(true)).init();
+    } else {
+        registry.with(fmt::layer()).init();
+    }
+    handle
 }
 
-impl RustExecutionEngine {
-    fn new() -> Self {
-        let mut exchanges = HashMap::new();
-        
-        // 初始化交易所連接器
-        exchanges.insert("binance".to_string(), ExchangeConnector {
-            name: "binance".to_string(),
-            base_url: "https://fapi.binance.com".to_string(),
-            api_key: "".to_string(),
-            secret_key: "".to_string(),
-        });
-        
-        exchanges.insert("bybit".to_string(), ExchangeConnector {
-            name: "bybit".to_string(),
-            base_url: "https://api.bybit.com".to_string(),
-            api_key: "".to_string(),
-            secret_key: "".to_string(),
-        });
-        
-        exchanges.insert("okx".to_string(), ExchangeConnector {
-            name: "okx".to_string(),
-            base_url: "https://www.okx.com".to_string(),
-            api_key: "".to_string(),
-            secret_key: "".to_string(),
-        });
-        
-        Self {
-            exchanges,
-            flash_loan_providers: vec![
-                "aave".to_string(),
-                "dydx".to_string(),
-                "compound".to_string(),
-            ],
-            gas_optimizer: GasOptimizer {
-                current_gas_price: 20_000_000_000, // 20 gwei
-                max_gas_limit: 5_000_000,
-            },
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    if args.get(1).map(String::as_str) == Some("repl") {
+        let address = args.get(2).cloned().unwrap_or_else(|| repl::DEFAULT_ADDRESS.to_string());
+        if let Err(e) = repl::run(&address) {
+            eprintln!("{}", e);
+            std::process::exit(1);
         }
+        return;
     }
     
-    async fn execute_funding_rate_arbitrage(&self, request: ArbitrageRequest) -> ArbitrageResponse {
-        let start_time = SystemTime::now();
-        
-        println!("🚀 Rust 引擎執行高頻套利: {}", request.strategy_id);
-        println!("   交易對: {}", request.symbol);
-        println!("   主要交易所: {}", request.primary_exchange);
-        println!("   次要交易所: {}", request.secondary_exchange);
-        println!("   金額: {} USDT", request.amount);
-        println!("   優先級: {}", request.priority);
-        
-        // 模擬高頻執行流程
-        match self.perform_high_frequency_arbitrage(&request).await {
-            Ok(profit) => {
-                let execution_time = SystemTime::now()
-                    .duration_since(start_time)
-                    .unwrap()
-                    .as_millis();
-                
-                println!("✅ 套利執行成功，利潤: {:.2f} USDT", profit);
-                println!("   執行時間: {} ms", execution_time);
-                
-                ArbitrageResponse {
-                    status: "success".to_string(),
-                    profit: Some(profit),
-                    execution_time: format!("{}ms", execution_time),
-                    gas_used: Some(self.gas_optimizer.current_gas_price),
-                    error_message: None,
-                }
-            }
-            Err(error) => {
-                println!("❌ 套利執行失敗: {}", error);
-                
-                ArbitrageResponse {
-                    status: "error".to_string(),
-                    profit: None,
-                    execution_time: "0ms".to_string(),
-                    gas_used: None,
-                    error_message: Some(error),
-                }
-            }
+    // 離線回測：`funding_rate_arbitrage_engine backtest <數據文件> [參數 JSON 文件]`，報告以 JSON 輸出到標準輸出
+    if args.get(1).map(String::as_str) == Some("backtest") {
+        if let Err(e) = run_backtest(&args[2..]) {
+            eprintln!("{}", e);
+            std::process::exit(1);
         }
+        return;
     }
     
-    async fn perform_high_frequency_arbitrage(&self, request: &ArbitrageRequest) -> Result<f64, String> {
-        // 1. 獲取當前資金費率
-        let primary_rate = self.get_funding_rate(&request.primary_exchange, &request.symbol).await?;
-        let secondary_rate = self.get_funding_rate(&request.secondary_exchange, &request.symbol).await?;
-        
-        println!("   主要交易所費率: {:.6f}", primary_rate);
-        println!("   次要交易所費率: {:.6f}", secondary_rate);
-        
-        // 2. 計算套利機會
-        let rate_diff = primary_rate - secondary_rate;
-        if rate_diff.abs() < 0.0001 {
-            return Err("資金費率差異太小".to_string());
+    // 回環壓測：`funding_rate_arbitrage_engine load-test [參數 JSON 文件]`，報告以 JSON 輸出到標準輸出
+    if args.get(1).map(String::as_str) == Some("load-test") {
+        let result = tokio::runtime::Runtime::new()
+            .map_err(|e| e.to_string())
+            .and_then(|runtime| runtime.block_on(run_load_test(&args[2..])));
+        if let Err(e) = result {
+            eprintln!("{}", e);
+            std::process::exit(1);
         }
-        
-        // 3. 執行閃電貸套利
-        let profit = self.execute_flash_loan_arbitrage(request, rate_diff).await?;
-        
-        Ok(profit)
+        return;
     }
     
-    async fn get_funding_rate(&self, exchange: &str, symbol: &str) -> Result<f64, String> {
-        // 模擬獲取資金費率
-        match exchange {
-            "binance" => Ok(0.0001 + (rand::random::<f64>() * 0.0002)),
-            "bybit" => Ok(0.0002 + (rand::random::<f64>() * 0.0002)),
-            "okx" => Ok(0.0003 + (rand::random::<f64>() * 0.0002)),
-            _ => Err(format!("不支持的交易所: {}", exchange)),
+    // 模擬交易所：`funding_rate_arbitrage_engine mock-exchange [地址] [場景 JSON 文件]`，Ctrl-C 退出
+    if args.get(1).map(String::as_str) == Some("mock-exchange") {
+        init_tracing();
+        let result = tokio::runtime::Runtime::new()
+            .map_err(|e| e.to_string())
+            .and_then(|runtime| runtime.block_on(run_mock_exchange(&args[2..])));
+        if let Err(e) = result {
+            error!(error = %e, "模擬交易所啟動失敗");
+            std::process::exit(1);
         }
+        return;
     }
     
-    async fn execute_flash_loan_arbitrage(&self, request: &ArbitrageRequest, rate_diff: f64) -> Result<f64, String> {
-        // 模擬閃電貸套利執行
-        println!("   🔄 執行閃電貸套利...");
-        
-        // 計算預期利潤
-        let expected_profit = request.amount * rate_diff.abs();
-        
-        // 模擬執行延遲（微秒級）
-        tokio::time::sleep(tokio::time::Duration::from_micros(100)).await;
-        
-        // 模擬成功率（90%）
-        if rand::random::<f64>() < 0.9 {
-            Ok(expected_profit * 0.95) // 95% 的預期利潤
-        } else {
-            Err("套利執行失敗".to_string())
-        }
+    // 引擎自行按配置建立運行時，以便將執行熱路徑隔離到綁定 CPU 核的線程
+    let log_handle = init_tracing();
+    if let Err(e) = server::serve(Some(log_handle)) {
+        error!(error = %e, "引擎啟動失敗");
+        std::process::exit(1);
     }
 }
 
-#[tokio::main]
-async fn main() {
-    println!("🚀 啟動 Rust 執行引擎...");
-    
-    let engine = RustExecutionEngine::new();
-    let listener = TcpListener::bind("127.0.0.1:8080").await.unwrap();
-    
-    println!("✅ Rust 引擎已啟動，監聽端口 8080");
-    
-    loop {
-        match listener.accept().await {
-            Ok((socket, addr)) => {
-                println!("📡 新連接: {}", addr);
-                let engine_clone = &engine;
-                tokio::spawn(async move {
-                    handle_connection(socket, engine_clone).await;
-                });
-            }
-            Err(e) => {
-                eprintln!("❌ 接受連接失敗: {}", e);
-            }
+fn run_backtest(args: &[String]) -> Result<(), String> {
+    let data_path = args.first().ok_or("用法: funding_rate_arbitrage_engine backtest <數據文件> [參數 JSON 文件]")?;
+    let mut params: backtest::BacktestParams = match args.get(1) {
+        Some(path) => {
+            let content = std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+            serde_json::from_str(&content).map_err(|e| format!("{}: {}", path, e))?
         }
-    }
+        None => backtest::BacktestParams::default(),
+    };
+    params.data_path = Some(data_path.clone());
+    let events = backtest::load_recording(data_path)?;
+    let report = backtest::run(&EngineConfig::load()?, &params, events);
+    println!("{}", serde_json::to_string_pretty(&report).map_err(|e| e.to_string())?);
+    Ok(())
 }
 
-async fn handle_connection(mut socket: TcpStream, engine: &RustExecutionEngine) {
-    let mut buffer = [0; 1024];
-    
-    loop {
-        match socket.read(&mut buffer).await {
-            Ok(n) if n == 0 => {
-                println!("📡 連接關閉");
-                break;
-            }
-            Ok(n) => {
-                let request_str = String::from_utf8_lossy(&buffer[0..n]);
-                
-                match serde_json::from_str::<ArbitrageRequest>(&request_str) {
-                    Ok(request) => {
-                        let response = engine.execute_funding_rate_arbitrage(request).await;
-                        let response_json = serde_json::to_string(&response).unwrap();
-                        
-                        if let Err(e) = socket.write_all(response_json.as_bytes()).await {
-                            eprintln!("❌ 發送響應失敗: {}", e);
-                            break;
-                        }
-                    }
-                    Err(e) => {
-                        eprintln!("❌ 解析請求失敗: {}", e);
-                        let error_response = ArbitrageResponse {
-                            status: "error".to_string(),
-                            profit: None,
-                            execution_time: "0ms".to_string(),
-                            gas_used: None,
-                            error_message: Some(format!("解析失敗: {}", e)),
-                        };
-                        
-                        let error_json = serde_json::to_string(&error_response).unwrap();
-                        if let Err(e) = socket.write_all(error_json.as_bytes()).await {
-                            eprintln!("❌ 發送錯誤響應失敗: {}", e);
-                            break;
-                        }
-                    }
-                }
-            }
-            Err(e) => {
-                eprintln!("❌ 讀取數據失敗: {}", e);
-                break;
-            }
+async fn run_load_test(args: &[String]) -> Result<(), String> {
+    let params: load_test::LoadTestParams = match args.first() {
+        Some(path) => {
+            let content = std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+            serde_json::from_str(&content).map_err(|e| format!("{}: {}", path, e))?
         }
-    }
+        None => load_test::LoadTestParams::default(),
+    };
+    let report = load_test::run(&params).await?;
+    println!("{}", serde_json::to_string_pretty(&report).map_err(|e| e.to_string())?);
+    Ok(())
 }
 
-// 添加 rand 依賴的模擬實現
-mod rand {
-    use std::collections::hash_map::DefaultHasher;
-    use std::hash::{Hash, Hasher};
-    use std::time::SystemTime;
-    
-    pub fn random<T>() -> T 
-    where
-        T: From<u64>,
-    {
-        let mut hasher = DefaultHasher::new();
-        SystemTime::now().hash(&mut hasher);
-        T::from(hasher.finish())
-    }
-} 
\ No newline at end of file
+async fn run_mock_exchange(args: &[String]) -> Result<(), String> {
+    let addr = args.first().map(String::as_str).unwrap_or("127.0.0.1:9100");
+    let scenario: mock_exchange::Scenario = match args.get(1) {
+        Some(path) => {
+            let content = std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+            serde_json::from_str(&content).map_err(|e| format!("{}: {}", path, e))?
+        }
+        None => mock_exchange::Scenario::default(),
+    };
+    let mock = mock_exchange::MockExchange::start(addr, scenario).await?;
+    println!("{}", serde_json::to_string(&mock.endpoint()).map_err(|e| e.to_string())?);
+    tokio::signal::ctrl_c().await.map_err(|e| e.to_string())?;
+    Ok(())
+}